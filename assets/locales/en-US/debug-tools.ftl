@@ -7,7 +7,6 @@ network-visualizer = Network Visualizer
 profiler = Profiler
 pathfinding-lines = Pathfinding Lines
 network-debug = Network Debug
-networking-disabled-message = Networking not enabled on this platform.
 
 profiler = Profiler
 
@@ -15,6 +14,9 @@ frame-diagnostics = Frame Diagnostics
 frames-per-second = Frames Per Second
 frame-time = Frame Time
 reset-min-max = Reset Min/Max
+measure-latency = Measure Latency
+input-latency = Input Latency
+present-latency = Present Latency
 pause = Pause
 resume = Resume
 frame = Frame
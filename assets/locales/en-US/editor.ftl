@@ -3,6 +3,10 @@ create-layer = Create a New Layer
 map-editor-preview-warning = ⚠ Warning: the map editor is a work-in-progress preview and may be broken or crash.
 
 map-info = Map Info
+author = Author
+description = Description
+recommended-players = Players
+version = Version
 map-export = Map Export
 tiles = Tiles
 tile = Tile
@@ -14,10 +18,28 @@ element-layer = Element Layer
 element-layer-icon = E
 add-element = Add Element
 delete-element = Delete Element
+flip-element-x = Flip Horizontally
+flip-element-y = Flip Vertically
+rotate-element = Rotate
+preview-element = Preview
+previewing-element = Previewing
+stop-preview = Stop Preview
+spawn-point-team = Team { $team }
+spawn-point-no-team = No Team
+spawn-point-priority = Priority
+spawn-point-face-left = Face Left
+wire-anchor = Wire End
+link-channel = Link Channel
 toggle-visibility = Toggle Visibility
 delete-layer = Delete Layer
+foreground-layer = Foreground
+foreground-parallax = Parallax
+foreground-fade = Fade When Behind
+foreground-fade-alpha = Opacity
 delete = Delete
 randomize = Randomize
+prefabs = Prefabs
+prefab-hint = Drag on the map to select tiles, click to stamp the selected prefab.
 
 create = Create
 layer-kind = Layer Kind
@@ -36,6 +58,7 @@ collisions = Collisions
 none = None
 
 jump-through = Jump Through
+rail = Rail
 empty = Empty
 solid = Solid
 
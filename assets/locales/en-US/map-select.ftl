@@ -4,4 +4,36 @@ waiting-for-map = Waiting for other player to select map...
 default-maps = Default Maps
 experimental-maps = Experimental Maps
 user-maps = User Maps
-builtin-maps = Builtin Maps
\ No newline at end of file
+builtin-maps = Builtin Maps
+item-settings = Item Settings
+map-list = Map List
+enabled = Enabled
+disabled = Disabled
+
+mutator-low-gravity = Low Gravity
+mutator-jetpacks = Jetpacks
+mutator-explosives-only = Explosives Only
+mutator-one-hit-swords = One-Hit Swords
+mutator-big-heads = Big Heads
+mutator-rising-lava = Rising Lava
+
+feature-flag-sticky-grenades = Experimental: Sticky Grenades
+feature-flag-swords-deflect-bullets = Experimental: Swords Deflect Bullets
+
+match-mode = Match Mode
+match-mode-free-for-all = Free for All
+match-mode-teams = Teams
+match-mode-capture-the-flag = Capture the Flag
+friendly-fire = Friendly Fire
+rounds-to-win = Rounds to Win
+rounds-to-win-count = First to { $rounds }
+rounds-to-win-endless = Endless
+time-limit = Time Limit
+time-limit-minutes = { $minutes } min
+time-limit-none = None
+arcade-mode = Arcade Mode
+pickup-assist = Pickup Assist
+ai-fill-disconnected = AI Replaces Disconnected Players
+afk-action = Idle Players
+afk-action-ai = Replaced by AI
+afk-action-drop = Removed
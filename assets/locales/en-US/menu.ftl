@@ -7,7 +7,11 @@ main-menu = Main Menu
 map-editor = Map Editor
 settings = Settings
 paused = Paused
+network-not-paused = The game keeps running in network games.
 credits = Credits
+daily-challenge = Daily Challenge
+tournament = Tournament
+stats = Stats
 
 # Actions
 close = Close
@@ -22,3 +26,88 @@ quit = Quit
 export = Export
 reload = Reload
 restart = Restart
+restart-round = Restart Round
+start = Start
+refresh = Refresh
+
+# Crash Report
+crash-report = Crash Report
+crash-report-description = Jumpy crashed the last time it was played. A crash report was saved to the folder below, which you can attach to a bug report to help us fix the problem.
+open-folder = Open Folder
+submit = Submit
+dismiss = Dismiss
+
+# Bug Report
+bug-report = Report a Bug
+bug-report-description = Describe what went wrong, and what you expected to happen:
+bug-report-attach-screenshot = Attach this screenshot of the game
+bug-report-saved = The bug report was saved to the file below. Please attach it to an issue.
+
+# Leaderboard
+leaderboard = Leaderboard
+leaderboard-disabled = Enable the online leaderboard in the networking settings to see the top scores.
+leaderboard-error = Could not reach the leaderboard server
+ranked = Ranked
+loading = Loading...
+
+# Daily Challenge
+opponents = Opponents
+score = Score
+previous-results = Previous Results
+
+# Tournament
+add-participant = Add Participant
+remove-participant = Remove
+tournament-map = Map
+tournament-next-match = Next match: { $first } vs { $second }
+tournament-champion = { $name } wins the tournament!
+finish-tournament = Finish
+quit-tournament = Quit Tournament
+
+# Scoreboard
+round-over = Round { $round }
+match-over = Match Over
+round-draw = Draw!
+player-wins = Player { $player } wins!
+team-wins = Team { $team } wins!
+player-round-wins = Player { $player }: { $wins }
+player-kills-deaths = Kills: { $kills } / Deaths: { $deaths }
+
+# Results
+match-results = Match Results
+player-name = Player { $player }
+round-wins = Wins
+kills = Kills
+deaths = Deaths
+damage-dealt = Hits
+items-used = Items Used
+rematch = Rematch
+awards = Awards
+award-mvp = MVP
+award-most-kills = Most Kills
+award-most-deaths = Most Deaths
+award-pacifist = Pacifist
+award-grenade-maniac = Grenade Maniac
+
+# Lifetime Stats
+no-stats = Finish a match to start collecting stats.
+matches-played = Matches
+win-rate = Win Rate
+favorite-item = Favorite Item
+
+# Kill Feed
+kill-feed-kill = Player { $killer } killed Player { $victim }
+kill-feed-self-kill = Player { $victim } killed themselves
+kill-feed-death = Player { $victim } died
+
+# Coin Counter
+coins = Coins
+player-coins = Player { $player }: { $coins }
+flag-captures = Flags Captured
+team-captures = Team { $team }: { $captures }
+
+# Match Timer
+sudden-death = Sudden Death!
+
+# Announcements
+announce-player-killed = Player { $player } was killed.
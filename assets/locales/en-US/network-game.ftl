@@ -5,6 +5,9 @@ join = Join
 servers = Servers
 players = Players
 no-servers = No Servers
+direct-connect = Direct Connect
+join-server-failed = Could not join the server.
+server-address = Address: { $address }
 server-name = Server Name
 start-server = Start Server
 stop-server = Stop Server
@@ -14,9 +17,89 @@ player-count = Player Count
 search = Search
 searching = Searching...
 search-for-match = Search for Match
+lobby-code = Lobby Code
+new-lobby = New Lobby
+invite-friends = Invite Friends
 connecting = Connecting...
 joining = Joining...
 connected-and-querying = Connected
 waiting-for-players = Waiting for Players: { $current } / { $total }
 match-ready = Match Ready!
 error = Error
+afk-warning-ai = No input detected! An AI will take over for you in { $seconds } seconds.
+afk-warning-drop = No input detected! You will be removed from the match in { $seconds } seconds.
+kick = Kick
+ban = Ban
+kicked = Kicked
+kicked-description = You have been kicked out of the game.
+votekick = Vote Kick
+votekick-description = Start a vote to kick a player out of the match.
+votekick-in-progress = Kick Player { $player } out of the match? { $votes } / { $voters } votes
+vote-yes = Yes
+vote-no = No
+player-left = Player Left
+player-left-description = Player { $player } left the match.
+end-match = End Match
+voice-chat = Voice Chat
+push-to-talk = Hold { $key } to talk.
+voice-chat-player = Player { $player }
+mute = Mute
+volume = Volume
+relay-bandwidth-limited = The relay bandwidth limit was reached: sending fewer updates, and voice chat is paused.
+search-for-ranked-match = Play Against Players of Your Level
+ranked-rating = Rating: { $rating } ({ $matches } matches played)
+join-queue = Join Queue
+leave-queue = Leave Queue
+ranked-rating-range = Searching for players rated { $min } to { $max }...
+ranked-reconnecting = Connection lost, reconnecting ({ $attempt } / { $max })...
+ranked-connection-failed = Could not connect to the matchmaking server.
+chat = Chat
+chat-hint = Say something...
+send = Send
+chat-you = You
+chat-player = Player { $player }
+chat-rate-limited = You are sending messages too fast, wait a moment.
+network-quality-good = Connection: Good
+network-quality-fair = Connection: Fair
+network-quality-poor = Connection: Poor
+network-peer-ping = Player { $player }: { $ping } ms
+network-peer-interrupted = Player { $player }: connection interrupted
+network-rollback-frames = Rollback: { $frames } frames/s
+network-predicted-frames = Prediction: { $frames } frames ahead
+desync = Out of Sync
+desync-description = The game went out of sync with the other players on frame { $frame }, so the match had to end.
+desync-report-saved = A desync report was saved to { $path }. Please attach it to a bug report.
+desync-report-failed = The desync report could not be saved.
+match-paused = Match Paused
+match-paused-countdown = Resuming in { $seconds } seconds. Press { $key } to resume now.
+pause-vote = Pause Match
+pause-vote-in-progress = Pause the match? { $votes } / { $voters } votes
+player-disconnected = Player Disconnected
+player-disconnected-description = Player { $player } lost their connection. Continue with an AI in their place?
+disconnect-vote-in-progress = { $votes } / { $voters } players want to continue. Continuing in { $seconds } seconds.
+connection-lost = Connection Lost
+connection-lost-reconnecting = Reconnecting to the match. Giving up in { $seconds } seconds.
+player-reconnecting = Waiting for Players
+player-reconnecting-description = Player { $player } lost their connection. Waiting { $seconds } seconds for them to reconnect.
+quick-match = Quick Match
+search-for-quick-match = Find a Match Near You
+quick-match-searching = Searching in { $region }: { $current } / { $total } players found
+quick-match-elapsed = Time in queue: { $time }
+quick-match-similar-rating = Looking for players of a similar level
+region = Region
+region-na = North America
+region-sa = South America
+region-eu = Europe
+region-af = Africa
+region-as = Asia
+region-oc = Oceania
+invite-online-player = Invite Online Player
+cancel-invite = Cancel Invite
+match-upgrade-inviting = Waiting for an online player to join with the lobby code { $code }
+match-upgrade-failed = The online player could not join the match.
+join-match-in-progress = Join Match in Progress
+match-upgrade-joining = Connecting to the match...
+match-upgrade-receiving = Loading the match...
+join-match-failed = Could not join the match.
+joining-lobby = Joining lobby { $code }...
+join-lobby-failed = Could not join the lobby.
@@ -14,3 +14,6 @@ press-button-to-remove = Press { $button } to Remove
 add-ai-player = Add AI Player
 remove-ai-player = Remove AI Player
 ai-player = AI Player
+ai-difficulty-easy = Easy
+ai-difficulty-normal = Normal
+ai-difficulty-hard = Hard
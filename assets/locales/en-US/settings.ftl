@@ -8,7 +8,31 @@ keyboard-2 = Keyboard 2
 gamepad = Gamepad
 
 action = Action
+gamepad-profile = Gamepad Controls
+all-players = All Players
+player-profile = Player { $player }
+shared-controls = Same as all players
+use-shared-controls = Use Shared Controls
+conflicting-inputs = Inputs marked with ! are bound to more than one action.
 
 # Networking settings
 networking = Networking
 matchmaking-server = Matchmaking Server
+relay-bandwidth-cap = Relay Bandwidth Limit
+relay-bandwidth-unlimited = Unlimited
+relay-bandwidth-kbps = { $kbps } KB/s
+network-hud = Show Network Quality
+leaderboard-server = Leaderboard Server
+leaderboard-opt-in = Share my scores on the online leaderboard
+
+# Accessibility settings
+accessibility = Accessibility
+text-to-speech = Read menus and match events aloud
+reduced-motion = Reduce motion
+high-contrast = High contrast
+damage-numbers = Damage numbers
+action-modes = Player { $player }
+grab = Grab
+crouch = Crouch
+hold = Hold
+toggle = Toggle
@@ -0,0 +1,109 @@
+//! Plays a match between AI players without a frontend, printing the match events and the final
+//! state of the players.
+//!
+//! Run with `cargo run -p jumpy_core --example headless`.
+
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use jumpy_core::{
+    metadata::{CoreMeta, ElementMeta, HatMeta, JumpyCoreAssetsPlugin, MapMeta, PlayerMeta},
+    observer::ObservedSession,
+    session::{CoreSessionInfo, GameSessionPlayerInfo},
+    MAX_PLAYERS,
+};
+
+const ASSET_FOLDER: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../assets");
+
+/// The number of frames to simulate.
+const FRAMES: usize = 60 * 30;
+
+/// The number of AI players in the match.
+const PLAYERS: usize = 2;
+
+fn main() {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(AssetPlugin {
+            asset_folder: ASSET_FOLDER.into(),
+            ..default()
+        })
+        .add_plugin(JumpyCoreAssetsPlugin);
+    let core_meta = load_core_meta(&mut app);
+
+    let map_meta = app
+        .world
+        .resource::<Assets<MapMeta>>()
+        .get(&core_meta.stable_maps[0].get_bevy_handle())
+        .unwrap()
+        .clone();
+    let player_info = std::array::from_fn(|i| {
+        (i < PLAYERS.min(MAX_PLAYERS)).then(|| GameSessionPlayerInfo {
+            player: core_meta.players[i % core_meta.players.len()].clone(),
+            hat: None,
+            is_ai: true,
+            ai_difficulty: default(),
+        })
+    });
+    let mut session = ObservedSession::new(CoreSessionInfo {
+        meta: core_meta,
+        map_meta,
+        player_info,
+        match_settings: default(),
+    });
+
+    session.subscribe(|event| println!("{event:?}"));
+    for _ in 0..FRAMES {
+        session.step(&mut app.world);
+    }
+
+    let snapshot = session.snapshot();
+    println!("After {} frames:", snapshot.frame);
+    for (i, player) in snapshot.players.iter().enumerate() {
+        if let Some(player) = player {
+            println!("  Player {i}: {player:?}");
+        }
+    }
+}
+
+/// Load the core metadata and all of the assets it refers to.
+fn load_core_meta(app: &mut App) -> Arc<CoreMeta> {
+    let handle: Handle<CoreMeta> = app
+        .world
+        .resource::<AssetServer>()
+        .load("default.core.yaml");
+
+    loop {
+        app.update();
+
+        let world = &app.world;
+        let Some(core) = world.resource::<Assets<CoreMeta>>().get(&handle) else {
+            continue;
+        };
+        let players = world.resource::<Assets<PlayerMeta>>();
+        let hats = world.resource::<Assets<HatMeta>>();
+        let elements = world.resource::<Assets<ElementMeta>>();
+        let maps = world.resource::<Assets<MapMeta>>();
+        let loaded = core
+            .players
+            .iter()
+            .all(|x| players.get(&x.get_bevy_handle()).is_some())
+            && core
+                .player_hats
+                .iter()
+                .all(|x| hats.get(&x.get_bevy_handle()).is_some())
+            && core
+                .map_elements
+                .iter()
+                .all(|x| elements.get(&x.get_bevy_handle()).is_some())
+            && core
+                .stable_maps
+                .iter()
+                .all(|x| maps.get(&x.get_bevy_handle()).is_some());
+
+        if loaded {
+            return Arc::new(core.clone());
+        }
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+}
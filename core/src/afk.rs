@@ -0,0 +1,109 @@
+//! Detection of players who have stopped playing.
+//!
+//! When [`MatchSettings::afk_timeout`] is set, players that don't make any input for that long are
+//! handled according to [`MatchSettings::afk_action`], so that the remaining players can keep
+//! playing. Because this only depends on the synchronized player inputs, every network peer makes
+//! the same decision on the same frame.
+
+use crate::prelude::*;
+
+/// Install this module.
+pub fn install(session: &mut CoreSession) {
+    session.world.init_resource::<AfkTimers>();
+    session
+        .stages
+        .add_system_to_stage(CoreStage::First, handle_afk_players);
+}
+
+/// The number of seconds before a player is considered AFK that they will be warned.
+pub const AFK_WARNING_TIME: f32 = 10.0;
+
+/// What to do with a player that is AFK.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum AfkAction {
+    /// Let an AI take control of the player.
+    #[default]
+    ConvertToAi,
+    /// Remove the player from the match.
+    Drop,
+}
+
+/// Resource tracking how long each player has gone without making any input.
+#[derive(Clone, Debug, Default, TypeUlid)]
+#[ulid = "01H1VQ7ZK3D8YB2M4TSG5XN6PA"]
+pub struct AfkTimers {
+    /// The number of seconds since each player last made an input.
+    pub idle_time: [f32; MAX_PLAYERS],
+    /// Whether or not each player has already been handled for being AFK.
+    pub is_afk: [bool; MAX_PLAYERS],
+}
+
+impl AfkTimers {
+    /// Get the number of seconds left before the given player is considered AFK, if they should
+    /// be warned about it.
+    pub fn warning(&self, player_idx: usize, match_settings: &MatchSettings) -> Option<f32> {
+        let timeout = match_settings.afk_timeout?;
+        let remaining = timeout - self.idle_time[player_idx];
+
+        (!self.is_afk[player_idx] && remaining <= AFK_WARNING_TIME).then_some(remaining.max(0.0))
+    }
+}
+
+/// Whether or not the control contains any input from the player.
+fn is_meaningful(control: &PlayerControl) -> bool {
+    control.jump_pressed
+        || control.shoot_pressed
+        || control.grab_pressed
+        || control.slide_pressed
+        || control.move_direction.length_squared() > f32::MIN_POSITIVE
+}
+
+/// Update the AFK timers, and handle the players that have become AFK.
+fn handle_afk_players(
+    entities: Res<Entities>,
+    time: Res<Time>,
+    match_settings: Res<MatchSettings>,
+    mut afk_timers: ResMut<AfkTimers>,
+    mut player_inputs: ResMut<PlayerInputs>,
+    player_indexes: Comp<PlayerIdx>,
+    mut ai_players: CompMut<AiPlayer>,
+) {
+    let Some(timeout) = match_settings.afk_timeout else {
+        return;
+    };
+
+    for (player_idx, input) in player_inputs.players.iter_mut().enumerate() {
+        if !input.active || input.is_ai || afk_timers.is_afk[player_idx] {
+            continue;
+        }
+
+        if is_meaningful(&input.control) {
+            afk_timers.idle_time[player_idx] = 0.0;
+            continue;
+        }
+
+        afk_timers.idle_time[player_idx] += time.delta().as_secs_f32();
+        if afk_timers.idle_time[player_idx] < timeout {
+            continue;
+        }
+
+        info!("Player {player_idx} is AFK");
+        afk_timers.is_afk[player_idx] = true;
+        input.control = default();
+
+        match match_settings.afk_action {
+            AfkAction::ConvertToAi => {
+                input.is_ai = true;
+                let player_ent = entities
+                    .iter_with(&player_indexes)
+                    .find(|(_, idx)| idx.0 == player_idx)
+                    .map(|(ent, _)| ent);
+                if let Some(player_ent) = player_ent {
+                    ai_players.insert(player_ent, default());
+                }
+            }
+            // The player is killed by `kill_inactive_players` once their input is inactive.
+            AfkAction::Drop => input.active = false,
+        }
+    }
+}
@@ -0,0 +1,558 @@
+//! AI players driven by behavior trees.
+//!
+//! Every frame, each [`AiPlayer`] ticks the tree of the [`AiBehavior`] resource with an
+//! [`AiContext`] describing its surroundings. The nodes of the tree decide what to do, and the
+//! actions fill in the [`PlayerControl`] that the AI plays with, in place of a controller.
+//!
+//! The [default tree][AiBehavior::default] attacks the nearest opponent when holding an item, and
+//! goes to pick up the nearest item otherwise, falling back to chasing the nearest opponent to
+//! stomp on them. The [`AiDifficulty`] of each player sets how fast and how often the AI reacts.
+
+use std::collections::VecDeque;
+
+use crate::{
+    prelude::*,
+    random::{GlobalRng, RngStream},
+};
+
+pub fn install(session: &mut CoreSession) {
+    session.world.init_resource::<AiBehavior>();
+    session
+        .stages
+        .add_system_to_stage(CoreStage::First, player_ai_system);
+}
+
+/// The colors of the pathfinding debug lines of each player.
+const PATHFINDING_DEBUG_COLORS: [Color; MAX_PLAYERS] = [
+    Color::RED,
+    Color::GREEN,
+    Color::BLUE,
+    Color::rgb(1.0, 0.0, 1.0),
+];
+
+/// How far above or below the AI, in pixels, an opponent may be to be attacked.
+const ATTACK_HEIGHT: f32 = 16.0;
+/// How close to an item, in pixels, the AI must be to grab it.
+const GRAB_DISTANCE: f32 = 12.0;
+/// The horizontal input used to turn toward an opponent while attacking them.
+const ATTACK_TURN_INPUT: f32 = 0.1;
+
+/// How well an AI player plays.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum AiDifficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl AiDifficulty {
+    /// All of the difficulties, from the easiest to the hardest.
+    pub const ALL: [Self; 3] = [Self::Easy, Self::Normal, Self::Hard];
+
+    /// The fraction of the walking speed that the AI moves at.
+    pub fn speed_multiplier(self) -> f32 {
+        match self {
+            Self::Easy => 0.5,
+            Self::Normal => 0.65,
+            Self::Hard => 0.9,
+        }
+    }
+
+    /// The chance, every [`AiPlayer`] tick, that the AI stops to take a pause.
+    pub fn pause_chance(self) -> f64 {
+        match self {
+            Self::Easy => 0.6,
+            Self::Normal => 0.4,
+            Self::Hard => 0.1,
+        }
+    }
+
+    /// How far in front of it, in pixels, the AI attacks opponents from.
+    pub fn attack_range(self) -> f32 {
+        match self {
+            Self::Easy => 16.0,
+            Self::Normal => 32.0,
+            Self::Hard => 64.0,
+        }
+    }
+}
+
+/// Component added to the players controlled by an AI.
+#[derive(Clone, Debug, TypeUlid)]
+#[ulid = "01GQWND0P969BCZF5JET9MY944"]
+pub struct AiPlayer {
+    /// Tick timer that is used for AI pausing logic.
+    tick: Timer,
+    /// Indicates the player is taking pause for the given number of ticks.
+    pausing: u32,
+    /// Buffers planned AI movements
+    movement_buffer: Option<VecDeque<PlayerControl>>,
+}
+
+impl Default for AiPlayer {
+    fn default() -> Self {
+        Self {
+            tick: Timer::from_seconds(0.5, TimerMode::Repeating),
+            pausing: 0,
+            movement_buffer: Default::default(),
+        }
+    }
+}
+
+/// The result of ticking a [`BehaviorNode`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BehaviorStatus {
+    /// The node did what it had to do.
+    Success,
+    /// The node couldn't do what it had to do.
+    Failure,
+    /// The node is still doing what it has to do.
+    Running,
+}
+
+/// A node of an AI behavior tree.
+///
+/// The tree is ticked from its root every frame, so the nodes don't keep any state of their own:
+/// anything that must last for more than a frame is kept in the [`AiContext`].
+#[derive(Clone, Debug)]
+pub enum BehaviorNode {
+    /// Tick the children in order until one of them doesn't fail, and return its status. Fails if
+    /// all of them fail.
+    Selector(Vec<BehaviorNode>),
+    /// Tick the children in order until one of them doesn't succeed, and return its status.
+    /// Succeeds if all of them succeed.
+    Sequence(Vec<BehaviorNode>),
+    /// Succeed if the condition holds, and fail otherwise.
+    Condition(fn(&AiContext) -> bool),
+    /// Run an action, which may change the controls of the AI.
+    Action(fn(&mut AiContext) -> BehaviorStatus),
+}
+
+impl BehaviorNode {
+    /// Tick the node, returning its status.
+    pub fn tick(&self, ctx: &mut AiContext) -> BehaviorStatus {
+        match self {
+            Self::Selector(children) => children
+                .iter()
+                .map(|child| child.tick(ctx))
+                .find(|status| *status != BehaviorStatus::Failure)
+                .unwrap_or(BehaviorStatus::Failure),
+            Self::Sequence(children) => children
+                .iter()
+                .map(|child| child.tick(ctx))
+                .find(|status| *status != BehaviorStatus::Success)
+                .unwrap_or(BehaviorStatus::Success),
+            Self::Condition(condition) => {
+                if condition(ctx) {
+                    BehaviorStatus::Success
+                } else {
+                    BehaviorStatus::Failure
+                }
+            }
+            Self::Action(action) => action(ctx),
+        }
+    }
+}
+
+/// Resource containing the behavior tree ticked by every AI player.
+///
+/// This may be replaced to change how the AI plays.
+#[derive(Clone, Debug, TypeUlid, Deref)]
+#[ulid = "01H2KA7C5WQ9RBX3NTM8YD0FJE"]
+pub struct AiBehavior(pub Arc<BehaviorNode>);
+
+impl Default for AiBehavior {
+    fn default() -> Self {
+        use BehaviorNode::*;
+        Self(Arc::new(Selector(vec![
+            Sequence(vec![
+                Condition(AiContext::has_item),
+                Condition(AiContext::is_opponent_in_attack_range),
+                Action(AiContext::attack_nearest_opponent),
+            ]),
+            Sequence(vec![
+                Condition(AiContext::has_item),
+                Action(AiContext::chase_nearest_opponent),
+            ]),
+            Sequence(vec![
+                Condition(|ctx| !ctx.has_item()),
+                Action(AiContext::fetch_nearest_item),
+            ]),
+            Action(AiContext::chase_nearest_opponent),
+        ])))
+    }
+}
+
+/// What an AI player knows about the match, and the controls it decides to make.
+pub struct AiContext {
+    /// The position of the AI player.
+    pub position: Vec2,
+    /// The velocity of the AI player.
+    pub velocity: Vec2,
+    /// The difficulty of the AI player.
+    pub difficulty: AiDifficulty,
+    /// The item that the AI player is holding, if any.
+    pub held_item: Option<Entity>,
+    /// The positions of the opponents that are alive, nearest first.
+    pub opponents: Vec<Vec2>,
+    /// The positions of the items that nobody is holding, nearest first.
+    pub items: Vec<Vec2>,
+    /// The map navigation graph.
+    pub nav_graph: Arc<NavGraphInner>,
+    /// The size of the map tiles.
+    pub tile_size: Vec2,
+    /// The controls that the AI player makes this frame.
+    pub control: PlayerControl,
+    /// The controls to make on the next frames, to finish moving to the next node of the path.
+    pub movement_buffer: Option<VecDeque<PlayerControl>>,
+    /// The path that the AI player is following, if any.
+    pub path: Option<Vec<NavNode>>,
+}
+
+impl AiContext {
+    /// Get the navigation node containing the given position.
+    fn nav_node(&self, pos: Vec2) -> NavNode {
+        NavNode::from_position(pos, self.tile_size)
+    }
+
+    /// Whether the AI player is holding an item.
+    pub fn has_item(&self) -> bool {
+        self.held_item.is_some()
+    }
+
+    /// Whether the nearest opponent is close enough in front of or behind the AI to attack them.
+    pub fn is_opponent_in_attack_range(&self) -> bool {
+        self.opponents.first().map_or(false, |opponent| {
+            let offset = *opponent - self.position;
+            offset.x.abs() <= self.difficulty.attack_range() && offset.y.abs() <= ATTACK_HEIGHT
+        })
+    }
+
+    /// Move toward the given position along the navigation graph, returning whether or not there
+    /// is a path to it.
+    pub fn navigate_to(&mut self, target: Vec2) -> bool {
+        let current_node = self.nav_node(self.position);
+        let target_node = self.nav_node(target);
+        let Some(path) = find_path(&self.nav_graph, current_node, target_node) else {
+            self.path = None;
+            return false;
+        };
+
+        if let Some(&next_node) = path.get(1) {
+            let edge = self.nav_graph.edge_weight(current_node, next_node).unwrap();
+            let mut movement_buffer = edge.inputs.clone();
+            let mut first_movement = movement_buffer.pop_front().unwrap();
+
+            // Slow down the AI movement according to its difficulty
+            first_movement.move_direction *= vec2(self.difficulty.speed_multiplier(), 1.0);
+
+            // This is a hack to prevent us from getting stuck when we think we should be falling
+            // straight down and we actually need to move off of the block we're half-standing on.
+            //
+            // If we aren't moving at all, just move in the direction of the path
+            if self.velocity == Vec2::ZERO && first_movement.move_direction == Vec2::ZERO {
+                let sign = (path.get(2).unwrap_or(&next_node).x as f32 * self.tile_size.x
+                    - self.position.x)
+                    .signum();
+                first_movement.move_direction.x = sign;
+            }
+
+            self.control = first_movement;
+            if !movement_buffer.is_empty() {
+                self.movement_buffer = Some(movement_buffer);
+            }
+        }
+        self.path = Some(path);
+
+        true
+    }
+
+    /// Turn toward the nearest opponent and use the held item.
+    pub fn attack_nearest_opponent(&mut self) -> BehaviorStatus {
+        let Some(&opponent) = self.opponents.first() else {
+            return BehaviorStatus::Failure;
+        };
+        self.control.move_direction.x = (opponent.x - self.position.x).signum() * ATTACK_TURN_INPUT;
+        self.control.shoot_pressed = true;
+        self.control.shoot_just_pressed = true;
+
+        BehaviorStatus::Success
+    }
+
+    /// Move toward the nearest opponent.
+    pub fn chase_nearest_opponent(&mut self) -> BehaviorStatus {
+        match self.opponents.first() {
+            Some(&opponent) if self.navigate_to(opponent) => BehaviorStatus::Running,
+            _ => BehaviorStatus::Failure,
+        }
+    }
+
+    /// Move toward the nearest item that nobody is holding, and grab it once it is close enough.
+    pub fn fetch_nearest_item(&mut self) -> BehaviorStatus {
+        let Some(&item) = self.items.first() else {
+            return BehaviorStatus::Failure;
+        };
+        if item.distance(self.position) <= GRAB_DISTANCE {
+            self.control.grab_pressed = true;
+            self.control.grab_just_pressed = true;
+            BehaviorStatus::Success
+        } else if self.navigate_to(item) {
+            BehaviorStatus::Running
+        } else {
+            BehaviorStatus::Failure
+        }
+    }
+}
+
+/// Resource containing the entities used to draw the path followed by each AI player, when
+/// [`DebugSettings::show_pathfinding_lines`] is enabled.
+#[derive(Debug, TypeUlid, Clone)]
+#[ulid = "01GRA68NKYG6X7C5D0WNA5W1VX"]
+pub struct PathfindingDebugLines {
+    pub entities: Vec<Entity>,
+}
+
+impl FromWorld for PathfindingDebugLines {
+    fn from_world(world: &mut World) -> Self {
+        let entities = world
+            .run_initialized_system(
+                |mut entities: ResMut<Entities>, mut transforms: CompMut<Transform>| {
+                    let ents = (0..MAX_PLAYERS)
+                        .map(|_| {
+                            let ent = entities.create();
+
+                            transforms.insert(
+                                ent,
+                                Transform::from_translation(Vec3::new(0.0, 0.0, -1.0)),
+                            );
+
+                            ent
+                        })
+                        .collect::<Vec<_>>();
+
+                    Ok(ents)
+                },
+            )
+            .unwrap();
+
+        Self { entities }
+    }
+}
+
+/// Tick the behavior tree of every AI player, and set their controls.
+fn player_ai_system(
+    entities: Res<Entities>,
+    nav_graph: Res<NavGraph>,
+    ai_behavior: Res<AiBehavior>,
+    match_settings: Res<MatchSettings>,
+    mut player_inputs: ResMut<PlayerInputs>,
+    mut ai_players: CompMut<AiPlayer>,
+    player_indexes: Comp<PlayerIdx>,
+    players_killed: Comp<PlayerKilled>,
+    items: Comp<Item>,
+    inventories: Comp<Inventory>,
+    map: Res<LoadedMap>,
+    transforms: Comp<Transform>,
+    pathfinding_debug_line: ResMut<PathfindingDebugLines>,
+    mut paths: CompMut<Path2d>,
+    bodies: Comp<KinematicBody>,
+    smoke_clouds: Comp<smoke_grenade::SmokeCloud>,
+    debug_settings: Res<DebugSettings>,
+    rng: Res<GlobalRng>,
+    time: Res<Time>,
+) {
+    let rng = rng.stream(RngStream::Ai);
+    let held_items = entities
+        .iter_with(&inventories)
+        .filter_map(|(_ent, inventory)| inventory.0)
+        .collect::<Vec<_>>();
+    let free_items = entities
+        .iter_with((&items, &transforms))
+        .filter(|(ent, _)| !held_items.contains(ent))
+        .map(|(_ent, (_item, transform))| transform.translation.truncate())
+        .collect::<Vec<_>>();
+    let alive_players = entities
+        .iter_with((&player_indexes, &transforms))
+        .filter(|(ent, _)| !players_killed.contains(*ent))
+        .map(|(ent, (player_idx, transform))| (ent, player_idx.0, transform.translation.truncate()))
+        .collect::<Vec<_>>();
+    let smoke_rects = entities
+        .iter_with((&smoke_clouds, &transforms))
+        .map(|(_ent, (cloud, transform))| cloud.rect(transform.translation.truncate()))
+        .collect::<Vec<_>>();
+
+    for (ai_ent, (player_idx, transform, ai_player)) in
+        entities.iter_with((&player_indexes, &transforms, &mut ai_players))
+    {
+        let difficulty = player_inputs.players[player_idx.0].ai_difficulty;
+
+        // Tick the AI timer
+        ai_player.tick.tick(time.delta());
+
+        // If a tick has elapsed
+        if ai_player.tick.just_finished() {
+            // If the player isn't pausing, there's a chance depending on the difficulty
+            if ai_player.pausing == 0 && rng.chance(difficulty.pause_chance()) {
+                // That we will pause for a random number of ticks between 0 and 2
+                ai_player.pausing = (rng.f32_normalized() * 2.0).round() as u32
+            }
+
+            // If the player is pausing
+            if ai_player.pausing > 0 {
+                // Subtract a tick from how long they should pause.
+                ai_player.pausing -= 1;
+            }
+        }
+
+        // If the player is pausing, don't have the AI move this frame.
+        if ai_player.pausing > 0 {
+            continue;
+        }
+
+        // Complete any previous movement instructions if we are in the middle of any
+        if let Some(movement_buffer) = &mut ai_player.movement_buffer {
+            if let Some(control) = movement_buffer.pop_front() {
+                player_inputs.players[player_idx.0].control = control;
+                if movement_buffer.is_empty() {
+                    ai_player.movement_buffer = None;
+                }
+                continue;
+            }
+        }
+
+        let position = transform.translation.truncate();
+        let by_distance = |a: &Vec2, b: &Vec2| {
+            a.distance_squared(position)
+                .total_cmp(&b.distance_squared(position))
+        };
+        let team = match_settings.team_of(player_idx.0);
+        // Opponents hidden behind or inside of smoke can't be seen
+        let mut opponents = alive_players
+            .iter()
+            .filter(|(ent, other_idx, _)| {
+                *ent != ai_ent && (team.is_none() || match_settings.team_of(*other_idx) != team)
+            })
+            .map(|(_, _, pos)| *pos)
+            .filter(|pos| !smoke_grenade::is_line_of_sight_blocked(&smoke_rects, position, *pos))
+            .collect::<Vec<_>>();
+        opponents.sort_by(by_distance);
+        let mut items = free_items.clone();
+        items.sort_by(by_distance);
+
+        let mut ctx = AiContext {
+            position,
+            velocity: bodies.get(ai_ent).map(|x| x.velocity).unwrap_or_default(),
+            difficulty,
+            held_item: inventories.get(ai_ent).and_then(|x| x.0),
+            opponents,
+            items,
+            nav_graph: nav_graph.0.clone(),
+            tile_size: map.tile_size,
+            control: default(),
+            movement_buffer: None,
+            path: None,
+        };
+        ai_behavior.tick(&mut ctx);
+
+        player_inputs.players[player_idx.0].control = ctx.control;
+        ai_player.movement_buffer = ctx.movement_buffer;
+
+        if !debug_settings.show_pathfinding_lines {
+            paths.remove(pathfinding_debug_line.entities[player_idx.0]);
+        } else if let Some(path) = ctx.path {
+            paths.insert(
+                pathfinding_debug_line.entities[player_idx.0],
+                Path2d {
+                    points: path.iter().map(|x| x.center(map.tile_size)).collect(),
+                    thickness: 2.0,
+                    color: PATHFINDING_DEBUG_COLORS[player_idx.0],
+                    ..default()
+                },
+            );
+        } else {
+            let pos = NavNode::from_position(position, map.tile_size).center(map.tile_size)
+                - vec2(0.0, 4.0);
+            paths.insert(
+                pathfinding_debug_line.entities[player_idx.0],
+                Path2d {
+                    points: vec![pos, pos + vec2(0.0, 4.0)],
+                    thickness: 8.0,
+                    color: Color::RED,
+                    ..default()
+                },
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn test_context() -> AiContext {
+        AiContext {
+            position: Vec2::ZERO,
+            velocity: Vec2::ZERO,
+            difficulty: default(),
+            held_item: None,
+            opponents: default(),
+            items: default(),
+            nav_graph: default(),
+            tile_size: Vec2::splat(16.0),
+            control: default(),
+            movement_buffer: None,
+            path: None,
+        }
+    }
+
+    #[test]
+    fn selectors_stop_at_the_first_child_that_does_not_fail() {
+        let mut ctx = test_context();
+        let tree = BehaviorNode::Selector(vec![
+            BehaviorNode::Condition(|_| false),
+            BehaviorNode::Action(|ctx| {
+                ctx.control.jump_pressed = true;
+                BehaviorStatus::Running
+            }),
+            BehaviorNode::Action(|ctx| {
+                ctx.control.slide_pressed = true;
+                BehaviorStatus::Success
+            }),
+        ]);
+
+        assert_eq!(tree.tick(&mut ctx), BehaviorStatus::Running);
+        assert!(ctx.control.jump_pressed);
+        assert!(!ctx.control.slide_pressed);
+    }
+
+    #[test]
+    fn sequences_stop_at_the_first_child_that_does_not_succeed() {
+        let mut ctx = test_context();
+        let tree = BehaviorNode::Sequence(vec![
+            BehaviorNode::Condition(|_| true),
+            BehaviorNode::Condition(AiContext::has_item),
+            BehaviorNode::Action(|ctx| {
+                ctx.control.jump_pressed = true;
+                BehaviorStatus::Success
+            }),
+        ]);
+
+        assert_eq!(tree.tick(&mut ctx), BehaviorStatus::Failure);
+        assert!(!ctx.control.jump_pressed);
+    }
+
+    #[test]
+    fn the_ai_attacks_opponents_in_range_when_holding_an_item() {
+        let mut ctx = test_context();
+        ctx.held_item = Some(Entities::default().create());
+        ctx.opponents = vec![Vec2::new(-10.0, 0.0)];
+
+        assert_eq!(
+            AiBehavior::default().tick(&mut ctx),
+            BehaviorStatus::Success
+        );
+        assert!(ctx.control.shoot_just_pressed);
+        assert!(ctx.control.move_direction.x < 0.0);
+    }
+}
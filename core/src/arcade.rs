@@ -0,0 +1,161 @@
+//! Arcade mode.
+//!
+//! When [`MatchSettings::arcade`] is enabled, the [coins][crate::elements::coin] placed on the map
+//! can be collected for score, and the [`ArcadeScore`] counts the coins collected by each player.
+//! Killed players drop a share of their coins, following the [coin rules][MapCoinsMeta] of the map,
+//! and the dropped coins fly out around them for anybody to collect. Like any other body, coins
+//! are scattered by explosions.
+
+use crate::{
+    elements::coin::{spawn_dropped_coins, Coin},
+    prelude::*,
+    random::{GlobalRng, RngStream},
+};
+
+/// Install this module.
+pub fn install(session: &mut CoreSession) {
+    session.world.init_resource::<ArcadeScore>();
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PostUpdate, drop_coins);
+}
+
+/// Resource containing the number of coins collected by each player in arcade mode.
+///
+/// It is kept when the map is reloaded for the next round.
+#[derive(Clone, Debug, Default, TypeUlid)]
+#[ulid = "01H32A6M9RZ2VW4TQ8KXJ0BN5C"]
+pub struct ArcadeScore {
+    /// The number of coins collected by each player.
+    pub coins: [u32; MAX_PLAYERS],
+    /// Whether or not each player was dead on the last frame.
+    was_killed: [bool; MAX_PLAYERS],
+}
+
+impl ArcadeScore {
+    /// Take the coins that the given player drops when they are killed, returning the number of
+    /// coins dropped, which is at most `limit`.
+    ///
+    /// The player loses the coins they drop, but the rules may have them drop more coins than they
+    /// collected.
+    pub fn take_dropped_coins(
+        &mut self,
+        player_idx: usize,
+        rules: &MapCoinsMeta,
+        limit: u32,
+    ) -> u32 {
+        let collected = &mut self.coins[player_idx];
+        let dropped = rules.drop_count(*collected).min(limit);
+        *collected = collected.saturating_sub(dropped);
+        dropped
+    }
+}
+
+/// Drop the coins of the players that are killed.
+fn drop_coins(
+    entities: Res<Entities>,
+    mut commands: Commands,
+    game_meta: Res<CoreMetaArc>,
+    match_settings: Res<MatchSettings>,
+    map: Res<LoadedMap>,
+    element_assets: BevyAssets<ElementMeta>,
+    player_indexes: Comp<PlayerIdx>,
+    players_killed: Comp<PlayerKilled>,
+    transforms: Comp<Transform>,
+    coins: Comp<Coin>,
+    rng: Res<GlobalRng>,
+    mut arcade_score: ResMut<ArcadeScore>,
+) {
+    let rng = rng.stream(RngStream::ItemSpawns);
+    if !match_settings.arcade {
+        return;
+    }
+
+    // The coins are dropped as the first coin element of the game
+    let Some((coin_handle, coin_meta)) = game_meta.map_elements.iter().find_map(|handle| {
+        let meta = element_assets.get(&handle.get_bevy_handle())?;
+        matches!(meta.builtin, BuiltinElementKind::Coin { .. }).then_some((handle, meta))
+    }) else {
+        return;
+    };
+    let BuiltinElementKind::Coin {
+        atlas,
+        body_diameter,
+        bounciness,
+        dropped_lifetime,
+        scatter_velocity,
+        ..
+    } = &coin_meta.builtin
+    else {
+        unreachable!();
+    };
+
+    let mut dropped_coins = entities
+        .iter_with(&coins)
+        .filter(|(_, coin)| coin.dropped)
+        .count() as u32;
+
+    for (player_ent, (player_idx, transform)) in entities.iter_with((&player_indexes, &transforms))
+    {
+        let killed = players_killed.contains(player_ent);
+        let was_killed = std::mem::replace(&mut arcade_score.was_killed[player_idx.0], killed);
+        if !killed || was_killed {
+            continue;
+        }
+
+        let limit = map.coins.max_dropped.saturating_sub(dropped_coins);
+        let dropped = arcade_score.take_dropped_coins(player_idx.0, &map.coins, limit);
+        if dropped == 0 {
+            continue;
+        }
+        dropped_coins += dropped;
+
+        // Throw the coins out in a fan above the player
+        let velocities = (0..dropped)
+            .map(|_| {
+                let spread = rng.f32_range(-1.0..1.0);
+                vec2(
+                    scatter_velocity.x * spread,
+                    scatter_velocity.y * rng.f32_range(0.5..1.0),
+                )
+            })
+            .collect();
+        commands.add(spawn_dropped_coins(
+            ElementHandle(coin_handle.clone()),
+            transform.translation,
+            velocities,
+            atlas.clone(),
+            *body_diameter,
+            *bounciness,
+            game_meta.physics.gravity,
+            *dropped_lifetime,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn killed_players_drop_a_share_of_their_coins() {
+        let rules = MapCoinsMeta {
+            drop_share: 0.5,
+            min_drop: 2,
+            max_dropped: 30,
+        };
+        let mut score = ArcadeScore {
+            coins: [9, 0, 3, 0],
+            ..default()
+        };
+
+        assert_eq!(score.take_dropped_coins(0, &rules, 30), 5);
+        assert_eq!(score.coins[0], 4);
+        // Players drop at least the minimum, even without coins
+        assert_eq!(score.take_dropped_coins(1, &rules, 30), 2);
+        assert_eq!(score.coins[1], 0);
+        // Players keep the coins that there is no room for on the map
+        assert_eq!(score.take_dropped_coins(2, &rules, 1), 1);
+        assert_eq!(score.coins[2], 2);
+    }
+}
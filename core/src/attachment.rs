@@ -57,7 +57,9 @@ pub fn update_attachments(
 
         *transform = attached_transform;
 
-        let mut offset = attachment.offset;
+        // Scale the offset along with the attached entity, so that attachments stay in place on
+        // entities that are grown or shrunk.
+        let mut offset = attachment.offset * attached_transform.scale.truncate().extend(1.0);
         if let Some((flip_x, flip_y)) = atlas_sprites
             .get(attachment.entity)
             .map(|x| (x.flip_x, x.flip_y))
@@ -19,12 +19,24 @@ pub fn install(session: &mut CoreSession) {
 #[derive(Clone, Debug, TypeUlid, Copy)]
 #[ulid = "01GQX3KM2A4WPV2NKJNG85TJ3P"]
 pub struct Bullet {
-    /// The direction that the bullet is moving on the X axis.
-    pub direction: f32,
+    /// The velocity of the bullet, in pixels per frame.
+    pub velocity: Vec2,
     /// The player entity that shot the bullet.
     pub owner: Entity,
 }
 
+/// Component for [`DamageRegion`]s that send the bullets they touch back the way they came.
+///
+/// Deflected bullets are then owned by the [`owner`][Self::owner] of the deflector, which they
+/// can't hurt. Swords' swings are deflectors when the
+/// [`SWORDS_DEFLECT_BULLETS`][crate::feature_flags::SWORDS_DEFLECT_BULLETS] feature flag is enabled.
+#[derive(Clone, Debug, TypeUlid, Copy)]
+#[ulid = "01HBQ8M3W6ZK4R2T9YV5XN7PDE"]
+pub struct BulletDeflector {
+    /// The player that deflects bullets.
+    pub owner: Entity,
+}
+
 /// Component containing the bullet's metadata handle.
 #[derive(Deref, DerefMut, TypeUlid, Clone)]
 #[ulid = "01GR1WH27X84VX22G0JY9J71PC"]
@@ -86,7 +98,7 @@ fn update(
     mut commands: Commands,
     bullet_handles: Comp<BulletHandle>,
     bullet_assets: BevyAssets<BulletMeta>,
-
+    match_settings: Res<MatchSettings>,
     player_indexes: Comp<PlayerIdx>,
     collision_world: CollisionWorld,
     mut transforms: CompMut<Transform>,
@@ -94,6 +106,9 @@ fn update(
     mut audio_events: ResMut<AudioEvents>,
     invincibles: CompMut<Invincibility>,
     mut emote_regions: CompMut<EmoteRegion>,
+    mut shield_walls: CompMut<shield::ShieldWall>,
+    damage_regions: Comp<DamageRegion>,
+    deflectors: Comp<BulletDeflector>,
 ) {
     for (entity, (bullet, bullet_handle)) in entities.iter_with((&mut bullets, &bullet_handles)) {
         let Some(bullet_meta) = bullet_assets.get(&bullet_handle.get_bevy_handle()) else {
@@ -101,7 +116,7 @@ fn update(
         };
 
         let BulletMeta {
-            velocity,
+            gravity,
             body_diameter,
             explosion_fps,
             explosion_volume,
@@ -115,7 +130,8 @@ fn update(
         // Move bullet
         let position = {
             let position = transforms.get_mut(entity).unwrap();
-            position.translation += bullet.direction * velocity.extend(0.0);
+            bullet.velocity.y -= gravity;
+            position.translation += bullet.velocity.extend(0.0);
 
             let emote_size = Vec2::new(*body_diameter * 6.0, *body_diameter * 3.5);
             emote_regions.insert(entity, EmoteRegion::basic(Emote::Alarm, emote_size, true));
@@ -123,6 +139,30 @@ fn update(
             *position
         };
 
+        // Send the bullet back if it was deflected by another player
+        let bullet_rect = Rect::new(
+            position.translation.x,
+            position.translation.y,
+            *body_diameter,
+            *body_diameter,
+        );
+        let deflected_by = entities
+            .iter_with((&deflectors, &damage_regions))
+            .find(|(deflector_ent, (deflector, damage_region))| {
+                deflector.owner != bullet.owner
+                    && transforms.get(*deflector_ent).map_or(false, |transform| {
+                        damage_region
+                            .collider_rect(transform.translation)
+                            .overlaps(&bullet_rect)
+                    })
+            })
+            .map(|(_, (deflector, _))| deflector.owner);
+        if let Some(owner) = deflected_by {
+            bullet.velocity = -bullet.velocity;
+            bullet.owner = owner;
+            continue;
+        }
+
         // Check actor collisions
         let mut hit_player = false;
         collision_world
@@ -130,22 +170,43 @@ fn update(
                 player_indexes.contains(e) && invincibles.get(e).is_none()
             })
             .into_iter()
-            .filter(|player| *player != bullet.owner)
+            .filter(|player| {
+                *player != bullet.owner
+                    && !is_protected_teammate(
+                        &match_settings,
+                        &player_indexes,
+                        bullet.owner,
+                        *player,
+                    )
+            })
             .for_each(|player| {
                 hit_player = true;
-                commands.add(PlayerCommand::kill(player, Some(position.translation.xy())));
+                commands.add(PlayerCommand::kill_by(
+                    player,
+                    Some(position.translation.xy()),
+                    DamageSource::Player(bullet.owner),
+                ));
             });
 
-        // check solid tile collisions
+        // Check shield wall collisions
+        let hit_walls =
+            collision_world.actor_collisions_filtered(entity, |e| shield_walls.contains(e));
+        for wall in &hit_walls {
+            shield_walls.get_mut(*wall).unwrap().hit(1);
+        }
+        let hit_wall = !hit_walls.is_empty();
+
+        // check solid tile and solid body collisions
         let hit_solid = collision_world.tile_collision(
             position,
             ColliderShape::Circle {
                 diameter: *body_diameter,
             },
-        ) == TileCollisionKind::Solid;
+        ) == TileCollisionKind::Solid
+            || !collision_world.overlapping_solids(entity).is_empty();
 
         // Bullet hit something
-        if hit_player || hit_solid {
+        if hit_player || hit_wall || hit_solid {
             audio_events.play(explosion_sound.clone(), *explosion_volume);
 
             let mut explosion_transform = *transforms.get(entity).unwrap();
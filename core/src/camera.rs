@@ -1,6 +1,10 @@
 //! Camera controller and parallax.
 
-use crate::prelude::*;
+use crate::{
+    damage::ChainReactions,
+    elements::{grenade::LitGrenade, kick_bomb::LitKickBomb},
+    prelude::*,
+};
 
 /// Install this module.
 pub fn install(session: &mut CoreSession) {
@@ -50,6 +54,11 @@ fn camera_controller(
     mut camera_states: CompMut<CameraState>,
     transforms: Comp<Transform>,
     player_indexes: Comp<PlayerIdx>,
+    players_killed: Comp<PlayerKilled>,
+    lit_grenades: Comp<LitGrenade>,
+    lit_kick_bombs: Comp<LitKickBomb>,
+    chain_reactions: Res<ChainReactions>,
+    afk_timers: Res<AfkTimers>,
     bodies: Comp<KinematicBody>,
     window: Res<Window>,
 ) {
@@ -114,34 +123,68 @@ fn camera_controller(
     let default_width = viewport_aspect * default_height;
     let map_size = map.grid_size.as_vec2() * map.tile_size;
 
+    // Collect the things to keep in frame, along with how much they pull the camera
+    let weights = &meta.weights;
+    let mut targets = Vec::new();
+    for (player_ent, player_idx) in entities.iter_with(&player_indexes) {
+        let weight = if afk_timers.is_afk[player_idx.0] {
+            weights.afk_player
+        } else if players_killed.contains(player_ent) {
+            weights.dead_player
+        } else {
+            weights.player
+        };
+        targets.push((camera_state.player_camera_rects[player_idx.0], weight));
+    }
+    let mut explosives = Vec::new();
+    for ent in entities
+        .iter_with(&lit_grenades)
+        .map(|(ent, _)| ent)
+        .chain(entities.iter_with(&lit_kick_bombs).map(|(ent, _)| ent))
+        .chain(chain_reactions.entities())
+    {
+        if !explosives.contains(&ent) {
+            explosives.push(ent);
+        }
+    }
+    for ent in explosives {
+        if let Some(transform) = transforms.get(ent) {
+            let pos = transform.translation.truncate();
+            targets.push((Rect { min: pos, max: pos }, weights.lit_explosive));
+        }
+    }
+    targets.retain(|(_, weight)| *weight > 0.0);
+
     let mut min = Vec2::new(f32::MAX, f32::MAX);
     let mut max = Vec2::new(f32::MIN, f32::MIN);
+    let mut weighted_center = Vec2::ZERO;
+    let mut total_weight = 0.0;
 
-    let players: Vec<usize> = entities
-        .iter_with(&player_indexes)
-        .map(|x| x.1 .0)
-        .collect();
-    let player_count = players.len();
-
-    for player_idx in players {
-        let rect = camera_state.player_camera_rects[player_idx];
-
+    for (rect, weight) in &targets {
         min = (rect.min - vec2(meta.border_left, meta.border_bottom))
             .min(min)
             .max(Vec2::ZERO);
         max = (rect.max + vec2(meta.border_right, meta.border_top)).max(max);
-        max.x = max.x.min(map_size.x)
+        max.x = max.x.min(map_size.x);
+
+        weighted_center += rect.center() * *weight;
+        total_weight += *weight;
     }
 
     let camera_pos = &mut camera_shake.center;
 
-    let mut middle_point = if player_count == 0 {
+    let mut middle_point = if targets.is_empty() {
         camera_pos.truncate()
     } else {
-        Rect { min, max }.center()
+        weighted_center / total_weight
     };
 
-    let size = max - min;
+    // Make the camera large enough to keep everything in frame around the weighted center
+    let size = if targets.is_empty() {
+        Vec2::ZERO
+    } else {
+        (max - middle_point).max(middle_point - min) * 2.0
+    };
     let size = size.max(meta.min_camera_size);
 
     let rh = size.y / default_height;
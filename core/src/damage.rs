@@ -1,14 +1,22 @@
 //! Damage / kill regions.
 //!
-//! Any player that intersects a damage region will be killed.
+//! Any player that intersects a damage region will be killed, and a [`DamageEvent`] is sent for
+//! any [`Damageable`] entity that intersects one. The [`DamageSource`] of the region is credited
+//! with the kill.
 
 use crate::prelude::*;
 
 /// Install this module.
 pub fn install(session: &mut CoreSession) {
+    session.world.init_resource::<ChainReactions>();
+    session.world.init_resource::<EventChannel<DamageEvent>>();
+    session.world.init_resource::<EventChannel<HitEvent>>();
     session
         .stages
-        .add_system_to_stage(CoreStage::PostUpdate, kill_players_in_damage_region);
+        .add_system_to_stage(CoreStage::First, update_chain_reactions)
+        .add_system_to_stage(CoreStage::PostUpdate, kill_players_in_damage_region)
+        .add_system_to_stage(CoreStage::PostUpdate, clear_events::<DamageEvent>)
+        .add_system_to_stage(CoreStage::PostUpdate, damage_damageables);
 }
 
 /// A rectangular damage region.
@@ -20,6 +28,8 @@ pub fn install(session: &mut CoreSession) {
 pub struct DamageRegion {
     /// The size of the damage region in pixels
     pub size: Vec2,
+    /// What the damage is dealt by.
+    pub source: DamageSource,
 }
 
 impl DamageRegion {
@@ -29,6 +39,26 @@ impl DamageRegion {
     }
 }
 
+/// What dealt damage or killed a player, used to credit players with their kills.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DamageSource {
+    /// Nobody in particular, such as falling off of the map or touching a hazard of the map.
+    #[default]
+    Environment,
+    /// A player, such as with a weapon they used or an explosive they threw.
+    Player(Entity),
+}
+
+impl DamageSource {
+    /// Get the player entity that dealt the damage, if it was dealt by a player.
+    pub fn player(self) -> Option<Entity> {
+        match self {
+            Self::Environment => None,
+            Self::Player(player) => Some(player),
+        }
+    }
+}
+
 /// A component that may be added to a damage region entity to indicate the triggering entity.
 ///
 /// If this entity is a player, it will not be harmed by the damage region.
@@ -40,6 +70,7 @@ pub struct DamageRegionOwner(pub Entity);
 fn kill_players_in_damage_region(
     entities: Res<Entities>,
     mut commands: Commands,
+    match_settings: Res<MatchSettings>,
     player_indexes: Comp<PlayerIdx>,
     transforms: Comp<Transform>,
     damage_regions: Comp<DamageRegion>,
@@ -62,18 +93,158 @@ fn kill_players_in_damage_region(
             let owner = damage_region_owners.get(ent);
             // Don't damage the player that owns this damage region
             if let Some(owner) = owner {
-                if owner.0 == player_ent {
+                if owner.0 == player_ent
+                    || is_protected_teammate(&match_settings, &player_indexes, owner.0, player_ent)
+                {
                     continue;
                 }
             }
 
             let damage_rect = damage_region.collider_rect(transform.translation);
             if player_rect.overlaps(&damage_rect) {
-                commands.add(PlayerCommand::kill(
+                commands.add(PlayerCommand::kill_by(
                     player_ent,
                     Some(transform.translation.xy()),
+                    damage_region.source,
                 ));
             }
         }
     }
 }
+
+/// A component for non-player entities that may be hit by damage regions, such as explosives.
+#[derive(Debug, Clone, Default, TypeUlid)]
+#[ulid = "01H1XB0K4TQ8P7N2GZ5YV3MREC"]
+pub struct Damageable {
+    /// The size of the entity's hit box, centered on its transform.
+    pub size: Vec2,
+}
+
+/// An event sent every frame that a [`Damageable`] entity is inside of a damage region.
+#[derive(Debug, Clone, TypeUlid)]
+#[ulid = "01H1XB0NQ2W5J6HDK8CTE4AZ9F"]
+pub struct DamageEvent {
+    /// The entity that was damaged.
+    pub entity: Entity,
+    /// The position of the damage region that hit the entity.
+    pub from: Vec2,
+    /// The owner of the damage region that hit the entity, if any.
+    pub owner: Option<Entity>,
+    /// What the damage region that hit the entity is dealt by.
+    pub source: DamageSource,
+}
+
+/// The damage dealt by a lethal hit.
+///
+/// Players have no health, so every lethal hit deals the whole of it.
+pub const LETHAL_HIT_DAMAGE: u32 = 100;
+
+/// An event sent when a player is hit, either killed or knocked back, to show the hit to the
+/// players.
+///
+/// The events are cleared by the [`presentation`][crate::presentation] module once it has shown
+/// them.
+#[derive(Debug, Clone, Copy, TypeUlid)]
+#[ulid = "01H345B8RZ2KXQ7MVN4TWJ9CDE"]
+pub struct HitEvent {
+    /// The player that was hit.
+    pub player: Entity,
+    /// The damage dealt by the hit.
+    pub damage: u32,
+    /// The velocity that the player was knocked back with.
+    pub knockback: Vec2,
+}
+
+impl EventChannel<DamageEvent> {
+    /// Get the first damage dealt to the given entity, if it was damaged.
+    pub fn damaged(&self, entity: Entity) -> Option<&DamageEvent> {
+        self.iter().find(|event| event.entity == entity)
+    }
+}
+
+/// Resource scheduling the reactions of damaged entities, such as explosives detonating.
+///
+/// Reactions are always delayed by at least one frame, and an entity that is already scheduled
+/// can't be scheduled again, so chain reactions between explosives spread out over several frames
+/// instead of looping within a single frame.
+#[derive(Debug, Clone, Default, TypeUlid)]
+#[ulid = "01H1XB0RSMD3BF7JX1A6KWQY2N"]
+pub struct ChainReactions {
+    /// The scheduled entities, with the number of frames left before they react.
+    scheduled: Vec<(Entity, usize)>,
+}
+
+impl ChainReactions {
+    /// Schedule an entity to react after the given number of frames.
+    pub fn schedule(&mut self, entity: Entity, delay_frames: usize) {
+        if !self.is_scheduled(entity) {
+            self.scheduled.push((entity, delay_frames.max(1)));
+        }
+    }
+
+    /// Whether the entity is waiting to react.
+    pub fn is_scheduled(&self, entity: Entity) -> bool {
+        self.scheduled.iter().any(|(ent, _)| *ent == entity)
+    }
+
+    /// Iterate over the entities waiting to react.
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.scheduled.iter().map(|(ent, _)| *ent)
+    }
+
+    /// Whether the entity should react this frame.
+    pub fn is_due(&self, entity: Entity) -> bool {
+        self.scheduled
+            .iter()
+            .any(|(ent, frames)| *ent == entity && *frames == 0)
+    }
+}
+
+/// Count down the scheduled chain reactions, dropping the ones that were due last frame.
+fn update_chain_reactions(entities: Res<Entities>, mut chain_reactions: ResMut<ChainReactions>) {
+    chain_reactions
+        .scheduled
+        .retain(|(ent, frames)| *frames > 0 && entities.is_alive(*ent));
+    for (_, frames) in &mut chain_reactions.scheduled {
+        *frames -= 1;
+    }
+}
+
+/// System that sends a [`DamageEvent`] for the [`Damageable`] entities intersecting with a damage
+/// region.
+fn damage_damageables(
+    entities: Res<Entities>,
+    transforms: Comp<Transform>,
+    damage_regions: Comp<DamageRegion>,
+    damage_region_owners: Comp<DamageRegionOwner>,
+    damageables: Comp<Damageable>,
+    mut damage_events: ResMut<EventChannel<DamageEvent>>,
+) {
+    let mut bitset = damageables.bitset().clone();
+    bitset.bit_and(transforms.bitset());
+
+    for entity in entities.iter_with_bitset(&bitset) {
+        let damageable = damageables.get(entity).unwrap();
+        let position = transforms.get(entity).unwrap().translation;
+        let rect = Rect::new(position.x, position.y, damageable.size.x, damageable.size.y);
+
+        for (region_ent, (damage_region, transform)) in
+            entities.iter_with((&damage_regions, &transforms))
+        {
+            let owner = damage_region_owners.get(region_ent).map(|x| x.0);
+            if owner == Some(entity) {
+                continue;
+            }
+
+            if rect.overlaps(&damage_region.collider_rect(transform.translation)) {
+                damage_events.send(DamageEvent {
+                    entity,
+                    from: transform.translation.xy(),
+                    owner,
+                    source: damage_region.source,
+                });
+                break;
+            }
+        }
+    }
+}
@@ -28,11 +28,16 @@ impl_system_param! {
         entities: ResMut<'a, Entities>,
         spawned_map_meta: ResMut<'a, SpawnedMapMeta>,
         element_handles: CompMut<'a, ElementHandle>,
+        element_orientations: CompMut<'a, ElementOrientation>,
+        spawn_points: CompMut<'a, SpawnPointMeta>,
+        wire_anchors: CompMut<'a, WireAnchorMeta>,
+        links: CompMut<'a, LinkMeta>,
         transforms: CompMut<'a, Transform>,
         spawned_map_layer_metas: CompMut<'a, SpawnedMapLayerMeta>,
         tile_layers: CompMut<'a, TileLayer>,
         tiles: CompMut<'a, Tile>,
         tile_collisions: CompMut<'a, TileCollisionKind>,
+        foreground_layers: CompMut<'a, ForegroundLayer>,
         map: Res<'a, LoadedMap>,
         element_kill_callbacks: Comp<'a, ElementKillCallback>,
         spawner_manager: SpawnerManager<'a>,
@@ -46,7 +51,7 @@ impl<'a> MapManager<'a> {
         element_meta_handle: &Handle<ElementMeta>,
         translation: &Vec2,
         layer_index: usize,
-    ) {
+    ) -> Entity {
         let entity = self.entities.create();
         // TODO remove element handles as the underlying elements are removed
         self.element_handles
@@ -62,6 +67,8 @@ impl<'a> MapManager<'a> {
                 layer_idx: layer_index,
             },
         );
+
+        entity
     }
     /// Create a new layer with the given name.
     pub fn create_layer(&mut self, name: String) {
@@ -147,6 +154,51 @@ impl<'a> MapManager<'a> {
         transform.translation.x = position.x;
         transform.translation.y = position.y;
     }
+    /// Mirror and rotate an element.
+    pub fn orient_element(&mut self, entity: Entity, orientation: ElementOrientation) {
+        let Some(transform) = self.transforms.get_mut(entity) else {
+            return;
+        };
+        let orientation = ElementOrientation {
+            rotation: orientation.rotation % 4,
+            ..orientation
+        };
+        transform.rotation = orientation.rotation_quat();
+        self.element_orientations.insert(entity, orientation);
+    }
+    /// Set the attributes of a player spawner, or remove them if they are [`None`].
+    pub fn set_spawn_point(&mut self, entity: Entity, spawn_point: Option<SpawnPointMeta>) {
+        match spawn_point {
+            Some(spawn_point) => {
+                self.spawn_points.insert(entity, spawn_point);
+            }
+            None => {
+                self.spawn_points.remove(entity);
+            }
+        }
+    }
+    /// Set the far anchor point of a trip wire, or remove it if it is [`None`].
+    pub fn set_wire_anchor(&mut self, entity: Entity, wire_anchor: Option<WireAnchorMeta>) {
+        match wire_anchor {
+            Some(wire_anchor) => {
+                self.wire_anchors.insert(entity, wire_anchor);
+            }
+            None => {
+                self.wire_anchors.remove(entity);
+            }
+        }
+    }
+    /// Set the link channel of an element, or remove it if it is [`None`].
+    pub fn set_link(&mut self, entity: Entity, link: Option<LinkMeta>) {
+        match link {
+            Some(link) => {
+                self.links.insert(entity, link);
+            }
+            None => {
+                self.links.remove(entity);
+            }
+        }
+    }
     /// Delete an element off of the map.
     pub fn delete_element(&mut self, entity: Entity) {
         if let Some(element_kill_callback) = self.element_kill_callbacks.get(entity) {
@@ -165,6 +217,48 @@ impl<'a> MapManager<'a> {
             self.entities.kill(entity);
         }
     }
+    /// Delete every element of the map except for the given one, so that it can be watched on its
+    /// own.
+    pub fn isolate_element(&mut self, entity: Entity) {
+        let others = self
+            .entities
+            .iter_with((&self.element_handles, &self.spawned_map_layer_metas))
+            .map(|(ent, _)| ent)
+            .filter(|ent| *ent != entity)
+            .collect::<Vec<_>>();
+        for other in others {
+            self.delete_element(other);
+        }
+    }
+    /// Make the given layer render in front of the players with the given settings, or behind
+    /// them again if they are [`None`].
+    pub fn set_layer_foreground(
+        &mut self,
+        layer_index: usize,
+        foreground: Option<MapForegroundMeta>,
+    ) {
+        let Some(layer_ent) = self
+            .entities
+            .iter_with((&self.tile_layers, &self.spawned_map_layer_metas))
+            .find(|x| x.1 .1.layer_idx == layer_index)
+            .map(|(ent, _)| ent) else { return; };
+
+        if let Some(meta) = foreground {
+            if let Some(foreground_layer) = self.foreground_layers.get_mut(layer_ent) {
+                foreground_layer.meta = meta;
+            } else {
+                self.foreground_layers
+                    .insert(layer_ent, ForegroundLayer::new(meta));
+            }
+        } else {
+            self.foreground_layers.remove(layer_ent);
+            let z_depth = z_depth_for_map_layer(layer_index);
+            self.transforms.insert(
+                layer_ent,
+                Transform::from_translation(Vec3::new(0.0, 0.0, z_depth)),
+            );
+        }
+    }
     /// Set the tilemap for the given layer.
     pub fn set_layer_tilemap(&mut self, layer_index: usize, tilemap: &Option<Handle<Atlas>>) {
         if let Some((_, (tile_layer, _))) = self
@@ -235,6 +329,55 @@ impl<'a> MapManager<'a> {
                 });
         };
     }
+    /// Copy the contents of a prefab onto the given layer, with the bottom-left corner of the
+    /// prefab at the given tile position.
+    ///
+    /// Tiles that would be outside of the map are skipped.
+    pub fn stamp_prefab(&mut self, prefab: &MapPrefabMeta, layer_index: usize, position: UVec2) {
+        // Use the prefab's tilemap if the layer doesn't have one yet.
+        let layer_has_tilemap = self
+            .entities
+            .iter_with((&self.tile_layers, &self.spawned_map_layer_metas))
+            .any(|(_, (tile_layer, layer_meta))| {
+                layer_meta.layer_idx == layer_index && tile_layer.atlas.path != AssetPath::default()
+            });
+        if !layer_has_tilemap && prefab.tilemap.is_some() {
+            self.set_layer_tilemap(layer_index, &prefab.tilemap);
+        }
+
+        let grid_size = self.spawned_map_meta.grid_size;
+        for tile in &prefab.tiles {
+            let tile_pos = position + tile.pos;
+            if tile_pos.x >= grid_size.x || tile_pos.y >= grid_size.y {
+                continue;
+            }
+            self.set_tile(
+                layer_index,
+                tile_pos,
+                &Some(tile.idx as usize),
+                tile.collision,
+            );
+        }
+
+        let offset = position.as_vec2() * self.spawned_map_meta.tile_size;
+        for element in &prefab.elements {
+            let entity =
+                self.create_element(&element.element, &(offset + element.pos), layer_index);
+            let orientation = ElementOrientation::from_spawn(element);
+            if orientation != ElementOrientation::default() {
+                self.orient_element(entity, orientation);
+            }
+            if element.spawn_point.is_some() {
+                self.set_spawn_point(entity, element.spawn_point);
+            }
+            if element.wire_anchor.is_some() {
+                self.set_wire_anchor(entity, element.wire_anchor);
+            }
+            if element.link.is_some() {
+                self.set_link(entity, element.link);
+            }
+        }
+    }
     /// Swap the position of two layers.
     pub fn swap_layer(&mut self, layer_index: usize, is_downward: bool) {
         let origin_layer_index = layer_index;
@@ -264,6 +407,19 @@ impl<'a> MapManager<'a> {
     pub fn rename_map(&mut self, name: String) {
         self.spawned_map_meta.name = name.into();
     }
+    /// Update the information about the map shown to players.
+    pub fn set_map_info(
+        &mut self,
+        author: &str,
+        description: &str,
+        version: u32,
+        recommended_players: Option<MapPlayerCountMeta>,
+    ) {
+        self.spawned_map_meta.author = author.into();
+        self.spawned_map_meta.description = description.into();
+        self.spawned_map_meta.version = version;
+        self.spawned_map_meta.recommended_players = recommended_players;
+    }
     /// Get the size of the map.
     pub fn get_size(&self) -> UVec2 {
         self.spawned_map_meta.grid_size
@@ -329,9 +485,42 @@ fn handle_editor_input(player_inputs: Res<PlayerInputs>, mut map_manager: MapMan
                 EditorInput::MoveEntity { entity, pos } => {
                     map_manager.move_element(*entity, pos);
                 }
+                EditorInput::OrientEntity {
+                    entity,
+                    flip_x,
+                    flip_y,
+                    rotation,
+                } => {
+                    map_manager.orient_element(
+                        *entity,
+                        ElementOrientation {
+                            flip_x: *flip_x,
+                            flip_y: *flip_y,
+                            rotation: *rotation,
+                        },
+                    );
+                }
+                EditorInput::SetSpawnPoint {
+                    entity,
+                    spawn_point,
+                } => {
+                    map_manager.set_spawn_point(*entity, *spawn_point);
+                }
+                EditorInput::SetWireAnchor {
+                    entity,
+                    wire_anchor,
+                } => {
+                    map_manager.set_wire_anchor(*entity, *wire_anchor);
+                }
+                EditorInput::SetLink { entity, link } => {
+                    map_manager.set_link(*entity, *link);
+                }
                 EditorInput::DeleteEntity { entity } => {
                     map_manager.delete_element(*entity);
                 }
+                EditorInput::SetLayerForeground { layer, foreground } => {
+                    map_manager.set_layer_foreground(*layer as usize, *foreground);
+                }
                 EditorInput::SetTilemap { layer, handle } => {
                     map_manager.set_layer_tilemap(*layer as usize, handle);
                 }
@@ -349,6 +538,14 @@ fn handle_editor_input(player_inputs: Res<PlayerInputs>, mut map_manager: MapMan
                 EditorInput::RenameMap { name } => {
                     map_manager.rename_map(name.clone());
                 }
+                EditorInput::SetMapInfo {
+                    author,
+                    description,
+                    version,
+                    recommended_players,
+                } => {
+                    map_manager.set_map_info(author, description, *version, *recommended_players);
+                }
                 EditorInput::RandomizeTiles {
                     tile_layers,
                     element_layers,
@@ -362,6 +559,9 @@ fn handle_editor_input(player_inputs: Res<PlayerInputs>, mut map_manager: MapMan
                     );
                     map_constructor.construct_map(&mut map_manager);
                 }
+                EditorInput::StampPrefab { prefab, layer, pos } => {
+                    map_manager.stamp_prefab(prefab, *layer as usize, *pos);
+                }
             }
         }
     }
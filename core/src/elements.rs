@@ -11,22 +11,39 @@ use ::bevy::{
 
 use crate::{impl_system_param, prelude::*};
 
+pub mod banana_peel;
+pub mod cloak;
+pub mod coin;
 pub mod crab;
 pub mod crate_item;
 pub mod decoration;
+pub mod decoy;
+pub mod elevator;
+pub mod explosive_barrel;
 pub mod fish_school;
+pub mod fishing_rod;
+pub mod flag_stand;
+pub mod freeze_ray;
 pub mod grenade;
+pub mod jetpack;
 pub mod kick_bomb;
 pub mod mine;
+pub mod minecart;
+pub mod missile_launcher;
 pub mod musket;
 pub mod player_spawner;
+pub mod pushable_block;
+pub mod shield;
+pub mod size_power_up;
 pub mod slippery;
 pub mod slippery_seaweed;
+pub mod smoke_grenade;
 pub mod snail;
 pub mod spike;
 pub mod sproinger;
 pub mod stomp_boots;
 pub mod sword;
+pub mod trip_wire;
 pub mod urchin;
 
 /// Marker component added to map elements that have been hydrated.
@@ -43,11 +60,65 @@ pub struct MapElementHydrated;
 #[ulid = "01GP9NY0Y50Y2A8M4A7E9NN8VE"]
 pub struct DehydrateOutOfBounds(pub Entity);
 
+/// Component tracking how long an item has been lying around since it was last held by a player.
+///
+/// It is added to items with a [`DehydrateOutOfBounds`] spawner the first time they are held, so
+/// that items that were never picked up stay at their spawn point.
+#[derive(Clone, TypeUlid, Default)]
+#[ulid = "01H1ZK8R5WQ3NV6J2TDB9XG4MC"]
+pub struct ItemIdleTime(pub f32);
+
+/// Component containing the number of items that a map element keeps on the map at once, when the
+/// [`MatchSettings`] spawn multiplier of the element makes it more than one.
+///
+/// The element spawns its first item like it normally would. Once all of its items have been
+/// picked up, it spawns another one at its spawn point, until it has this many items on the map.
+#[derive(Clone, Copy, TypeUlid, Deref, DerefMut)]
+#[ulid = "01H8SX78NZ2PP6M09MVVA8E40Q"]
+pub struct ItemSpawnCount(pub usize);
+
 /// Component containing an element's metadata handle.
 #[derive(Clone, TypeUlid, Deref, DerefMut, Default)]
 #[ulid = "01GP421CHN323T2614F19PA5E9"]
 pub struct ElementHandle(pub Handle<ElementMeta>);
 
+/// Component containing how a map element has been mirrored and rotated in the editor.
+///
+/// The rotation is applied to the element's [`Transform`] when it is spawned, and the mirroring
+/// is applied to its sprite once it has been hydrated.
+#[derive(Clone, Copy, TypeUlid, Default, Debug, PartialEq, Eq)]
+#[ulid = "01H2C4M7QK9V3XTB8E6RZJ1NWD"]
+pub struct ElementOrientation {
+    /// Whether the element is mirrored horizontally.
+    pub flip_x: bool,
+    /// Whether the element is mirrored vertically.
+    pub flip_y: bool,
+    /// The number of counter-clockwise quarter turns the element is rotated by.
+    pub rotation: u8,
+}
+
+impl ElementOrientation {
+    /// Get the orientation of an element spawned by the map.
+    pub fn from_spawn(spawn: &ElementSpawn) -> Self {
+        Self {
+            flip_x: spawn.flip_x,
+            flip_y: spawn.flip_y,
+            rotation: spawn.rotation % 4,
+        }
+    }
+
+    /// Get the rotation of the element's transform.
+    pub fn rotation_quat(&self) -> Quat {
+        Quat::from_rotation_z(self.rotation as f32 * std::f32::consts::FRAC_PI_2)
+    }
+}
+
+/// Component containing the [`ElementOrientation`] that has been applied to the sprite of a
+/// hydrated element.
+#[derive(Clone, Copy, TypeUlid, Default)]
+#[ulid = "01H2C4MF0SG6YB2W9PNAXH5KTE"]
+struct AppliedElementOrientation(ElementOrientation);
+
 #[derive(Clone, TypeUlid)]
 #[ulid = "01GP584Z9WN5P0RG2A82MV93P1"]
 pub struct ElementKillCallback {
@@ -230,7 +301,10 @@ impl<'a> SpawnerManager<'a> {
 pub fn install(session: &mut CoreSession) {
     session
         .stages
-        .add_system_to_stage(CoreStage::First, handle_out_of_bounds_items);
+        .add_system_to_stage(CoreStage::First, handle_out_of_bounds_items)
+        .add_system_to_stage(CoreStage::First, despawn_abandoned_items)
+        .add_system_to_stage(CoreStage::First, spawn_extra_items)
+        .add_system_to_stage(CoreStage::Update, apply_element_orientations);
 
     decoration::install(session);
     urchin::install(session);
@@ -238,17 +312,34 @@ pub fn install(session: &mut CoreSession) {
     sproinger::install(session);
     sword::install(session);
     grenade::install(session);
+    decoy::install(session);
     crab::install(session);
     snail::install(session);
     fish_school::install(session);
     kick_bomb::install(session);
     mine::install(session);
     musket::install(session);
+    missile_launcher::install(session);
+    fishing_rod::install(session);
     stomp_boots::install(session);
     crate_item::install(session);
     slippery_seaweed::install(session);
     slippery::install(session);
     spike::install(session);
+    explosive_barrel::install(session);
+    minecart::install(session);
+    size_power_up::install(session);
+    cloak::install(session);
+    banana_peel::install(session);
+    shield::install(session);
+    jetpack::install(session);
+    smoke_grenade::install(session);
+    trip_wire::install(session);
+    freeze_ray::install(session);
+    coin::install(session);
+    pushable_block::install(session);
+    elevator::install(session);
+    flag_stand::install(session);
 }
 
 fn handle_out_of_bounds_items(
@@ -268,3 +359,172 @@ fn handle_out_of_bounds_items(
         }
     }
 }
+
+/// De-hydrate the spawners that keep more than one item on the map, so that they spawn another
+/// item, once all of their items have been picked up.
+fn spawn_extra_items(
+    entities: Res<Entities>,
+    items: Comp<Item>,
+    spawners: Comp<DehydrateOutOfBounds>,
+    idle_times: Comp<ItemIdleTime>,
+    item_spawn_counts: Comp<ItemSpawnCount>,
+    mut hydrated: CompMut<MapElementHydrated>,
+) {
+    for (spawner_ent, spawn_count) in entities.iter_with(&item_spawn_counts) {
+        if !hydrated.contains(spawner_ent) {
+            continue;
+        }
+
+        let spawner_items = entities
+            .iter_with((&items, &spawners))
+            .filter(|(_, (_, spawner))| ***spawner == spawner_ent)
+            .map(|(item_ent, _)| item_ent)
+            .collect::<Vec<_>>();
+
+        // Items that were never picked up are still on the spawn point
+        let spawn_point_is_free = spawner_items
+            .iter()
+            .all(|item_ent| idle_times.contains(*item_ent));
+        if !spawner_items.is_empty() && spawner_items.len() < **spawn_count && spawn_point_is_free {
+            hydrated.remove(spawner_ent);
+        }
+    }
+}
+
+/// Mirror the sprites of hydrated elements according to their [`ElementOrientation`].
+///
+/// Elements insert fresh sprites when they are hydrated, so this runs after hydration, and again
+/// whenever the orientation is changed in the editor or the element is re-hydrated.
+fn apply_element_orientations(
+    entities: Res<Entities>,
+    hydrated: Comp<MapElementHydrated>,
+    orientations: Comp<ElementOrientation>,
+    mut applied_orientations: CompMut<AppliedElementOrientation>,
+    mut atlas_sprites: CompMut<AtlasSprite>,
+    mut sprites: CompMut<Sprite>,
+) {
+    for (entity, orientation) in entities.iter_with(&orientations) {
+        if !hydrated.contains(entity) {
+            // The sprite will be replaced when the element is hydrated again.
+            applied_orientations.remove(entity);
+            continue;
+        }
+
+        let applied = applied_orientations
+            .get(entity)
+            .map(|x| x.0)
+            .unwrap_or_default();
+        let flip_x = applied.flip_x != orientation.flip_x;
+        let flip_y = applied.flip_y != orientation.flip_y;
+        if let Some(sprite) = atlas_sprites.get_mut(entity) {
+            sprite.flip_x ^= flip_x;
+            sprite.flip_y ^= flip_y;
+        } else if let Some(sprite) = sprites.get_mut(entity) {
+            sprite.flip_x ^= flip_x;
+            sprite.flip_y ^= flip_y;
+        }
+        applied_orientations.insert(entity, AppliedElementOrientation(*orientation));
+    }
+}
+
+/// Despawn the items that have been abandoned for longer than
+/// [`CoreConfigMeta::item_despawn_time`], and the items of spawners that have more than
+/// [`CoreConfigMeta::max_items_per_spawner`] items on the map.
+///
+/// If the last item of a spawner is despawned, the spawner is de-hydrated so that it spawns a
+/// fresh item at its spawn point.
+fn despawn_abandoned_items(
+    mut commands: Commands,
+    entities: Res<Entities>,
+    time: Res<Time>,
+    game_meta: Res<CoreMetaArc>,
+    tag_index: Res<TagIndex>,
+    inventories: Comp<Inventory>,
+    spawners: Comp<DehydrateOutOfBounds>,
+    mut idle_times: CompMut<ItemIdleTime>,
+    mut hydrated: CompMut<MapElementHydrated>,
+) {
+    let config = &game_meta.config;
+    let held_items = entities
+        .iter_with(&inventories)
+        .filter_map(|(_ent, inventory)| inventory.0)
+        .collect::<Vec<_>>();
+
+    // The items that were spawned by a spawner, along with it
+    let spawned_items = tag_index
+        .iter(Tag::Item)
+        .filter_map(|item_ent| Some((item_ent, **spawners.get(item_ent)?)))
+        .collect::<Vec<_>>();
+
+    // Update the idle timers
+    for &(item_ent, _) in &spawned_items {
+        if held_items.contains(&item_ent) {
+            idle_times.insert(item_ent, ItemIdleTime::default());
+        } else if let Some(idle_time) = idle_times.get_mut(item_ent) {
+            idle_time.0 += time.delta().as_secs_f32();
+        }
+    }
+
+    let mut despawned = Vec::new();
+
+    // Despawn the items that have been abandoned for too long
+    if let Some(despawn_time) = config.item_despawn_time {
+        for (item_ent, (idle_time, _)) in entities.iter_with((&idle_times, &spawners)) {
+            if idle_time.0 >= despawn_time.as_secs_f32() {
+                despawned.push(item_ent);
+            }
+        }
+    }
+
+    // Despawn the longest abandoned items of the spawners that have too many items
+    if let Some(max_items) = config.max_items_per_spawner {
+        let live_items = spawned_items
+            .iter()
+            .filter(|(item_ent, _)| !despawned.contains(item_ent))
+            .copied()
+            .collect::<Vec<_>>();
+
+        for (i, (_, spawner)) in live_items.iter().enumerate() {
+            // Only handle each spawner once
+            if live_items[..i].iter().any(|(_, other)| other == spawner) {
+                continue;
+            }
+
+            let spawner_items = live_items.iter().filter(|(_, other)| other == spawner);
+            let item_count = spawner_items.clone().count();
+            if item_count <= max_items {
+                continue;
+            }
+
+            // Items that have never been picked up are despawned last
+            let mut candidates = spawner_items
+                .filter(|(item_ent, _)| !held_items.contains(item_ent))
+                .map(|(item_ent, _)| {
+                    let idle_time = idle_times.get(*item_ent).map(|x| x.0).unwrap_or(-1.0);
+                    (*item_ent, idle_time)
+                })
+                .collect::<Vec<_>>();
+            candidates.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+            despawned.extend(
+                candidates
+                    .into_iter()
+                    .take(item_count - max_items)
+                    .map(|(item_ent, _)| item_ent),
+            );
+        }
+    }
+
+    for &item_ent in &despawned {
+        let spawner = **spawners.get(item_ent).unwrap();
+        let is_last_item = !entities
+            .iter_with(&spawners)
+            .any(|(other, x)| **x == spawner && !despawned.contains(&other));
+        if is_last_item {
+            hydrated.remove(spawner);
+        }
+
+        commands.add(move |mut entities: ResMut<Entities>| {
+            entities.kill(item_ent);
+        });
+    }
+}
@@ -0,0 +1,188 @@
+//! Banana peel.
+//!
+//! The peel can be thrown around, and once it lies on the ground, the next player who runs over it
+//! slips: they slide along and lose control for a while, like when they are stomped on. The peel is
+//! used up, and comes back at its spawner.
+
+use crate::prelude::*;
+
+pub fn install(session: &mut CoreSession) {
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PreUpdate, hydrate)
+        .add_system_to_stage(PlayerStateStage, update);
+}
+
+#[derive(Clone, Debug, TypeUlid, Default)]
+#[ulid = "01H30A3B7Y2KQEN5MWZ8DHT6RF"]
+pub struct BananaPeel;
+
+fn hydrate(
+    game_meta: Res<CoreMetaArc>,
+    mut entities: ResMut<Entities>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    mut element_handles: CompMut<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut banana_peels: CompMut<BananaPeel>,
+    mut atlas_sprites: CompMut<AtlasSprite>,
+    mut bodies: CompMut<KinematicBody>,
+    mut transforms: CompMut<Transform>,
+    mut items: CompMut<Item>,
+    mut item_throws: CompMut<ItemThrow>,
+    mut item_grabs: CompMut<ItemGrab>,
+    mut respawn_points: CompMut<DehydrateOutOfBounds>,
+    mut spawner_manager: SpawnerManager,
+) {
+    let mut not_hydrated_bitset = hydrated.bitset().clone();
+    not_hydrated_bitset.bit_not();
+    not_hydrated_bitset.bit_and(element_handles.bitset());
+
+    let spawner_entities = entities
+        .iter_with_bitset(&not_hydrated_bitset)
+        .collect::<Vec<_>>();
+
+    for spawner_ent in spawner_entities {
+        let transform = *transforms.get(spawner_ent).unwrap();
+        let element_handle = element_handles.get(spawner_ent).unwrap();
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+
+        if let BuiltinElementKind::BananaPeel {
+            atlas,
+            body_size,
+            grab_offset,
+            fin_anim,
+            throw_velocity,
+            angular_velocity,
+            ..
+        } = &element_meta.builtin
+        {
+            hydrated.insert(spawner_ent, MapElementHydrated);
+
+            let entity = entities.create();
+            items.insert(entity, Item);
+            item_throws.insert(
+                entity,
+                ItemThrow::strength(*throw_velocity).with_spin(*angular_velocity),
+            );
+            item_grabs.insert(
+                entity,
+                ItemGrab {
+                    fin_anim: *fin_anim,
+                    sync_animation: false,
+                    grab_offset: *grab_offset,
+                },
+            );
+            banana_peels.insert(entity, BananaPeel);
+            atlas_sprites.insert(entity, AtlasSprite::new(atlas.clone()));
+            respawn_points.insert(entity, DehydrateOutOfBounds(spawner_ent));
+            transforms.insert(entity, transform);
+            element_handles.insert(entity, element_handle.clone());
+            hydrated.insert(entity, MapElementHydrated);
+            bodies.insert(
+                entity,
+                KinematicBody {
+                    shape: ColliderShape::Rectangle { size: *body_size },
+                    has_mass: true,
+                    has_friction: true,
+                    can_rotate: false,
+                    gravity: game_meta.physics.gravity,
+                    ..default()
+                },
+            );
+            spawner_manager.create_spawner(spawner_ent, vec![entity])
+        }
+    }
+}
+
+/// Make the players who run over the peels lying on the ground slip.
+fn update(
+    entities: Res<Entities>,
+    mut commands: Commands,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut banana_peels: CompMut<BananaPeel>,
+    collision_world: CollisionWorld,
+    player_inventories: PlayerInventories,
+    player_indexes: Comp<PlayerIdx>,
+    players_killed: Comp<PlayerKilled>,
+    mut player_states: CompMut<PlayerState>,
+    mut slipping: CompMut<Slipping>,
+    mut status_effects: CompMut<StatusEffects>,
+    mut bodies: CompMut<KinematicBody>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    spawners: Comp<DehydrateOutOfBounds>,
+    mut audio_events: ResMut<AudioEvents>,
+) {
+    let mut used_peels = Vec::new();
+    for (entity, (_banana_peel, element_handle, spawner)) in
+        entities.iter_with((&banana_peels, &element_handles, &spawners))
+    {
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+        let BuiltinElementKind::BananaPeel {
+            min_speed,
+            slip_velocity,
+            slip_slowdown,
+            slip_duration,
+            slip_sound,
+            slip_sound_volume,
+            ..
+        } = &element_meta.builtin
+        else {
+            unreachable!();
+        };
+
+        // Peels only trip players once they have landed
+        let is_held = player_inventories
+            .iter()
+            .any(|x| x.map_or(false, |x| x.inventory == entity));
+        if is_held || !bodies.get(entity).map_or(false, |x| x.is_on_ground) {
+            continue;
+        }
+
+        let Some(player) = collision_world
+            .actor_collisions(entity)
+            .into_iter()
+            .find(|player| {
+                player_indexes.contains(*player)
+                    && !players_killed.contains(*player)
+                    && player_states.get(*player).map_or(false, |x| {
+                        x.current != key!("core::incapacitated") && x.current != key!("core::riding")
+                    })
+                    && bodies
+                        .get(*player)
+                        .map_or(false, |x| x.is_on_ground && x.velocity.x.abs() >= *min_speed)
+            })
+        else {
+            continue;
+        };
+
+        // Send the player sliding in the direction they were running in
+        let body = bodies.get_mut(player).unwrap();
+        body.velocity = vec2(slip_velocity.x * body.velocity.x.signum(), slip_velocity.y);
+        Slipping::apply(
+            player,
+            *slip_slowdown,
+            *slip_duration,
+            &mut player_states,
+            &mut slipping,
+            &mut status_effects,
+        );
+        audio_events.play(slip_sound.clone(), *slip_sound_volume);
+
+        // Use up the peel, letting it respawn at its spawner
+        hydrated.remove(**spawner);
+        used_peels.push(entity);
+        commands.add(move |mut entities: ResMut<Entities>| {
+            entities.kill(entity);
+        });
+    }
+
+    // The state transitions may run again this frame, before the peels are despawned
+    for entity in used_peels {
+        banana_peels.remove(entity);
+    }
+}
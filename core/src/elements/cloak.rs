@@ -0,0 +1,166 @@
+//! Invisibility cloak.
+//!
+//! Putting on the cloak makes the player [invisible][StatusEffectKind::Invisible] for a while, until
+//! they attack by using the item they hold, which stays visible. Whether a player can see an
+//! invisible player only depends on which players are controlled on their computer, so the fading
+//! itself is done by the [presentation settings][PresentationSettings], outside of the game
+//! simulation.
+
+use crate::prelude::*;
+
+pub fn install(session: &mut CoreSession) {
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PreUpdate, hydrate)
+        // Run after the players have used their items, but before the items consume the
+        // `ItemUsed` markers.
+        .add_system_to_stage(CoreStage::Update, break_cloaks)
+        .add_system_to_stage(CoreStage::PostUpdate, update);
+}
+
+#[derive(Copy, Clone, Debug, TypeUlid)]
+#[ulid = "01H2Z3N7QK5W1XB8FRT0JDVC4M"]
+pub struct Cloak;
+
+fn hydrate(
+    game_meta: Res<CoreMetaArc>,
+    mut entities: ResMut<Entities>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    mut element_handles: CompMut<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut cloaks: CompMut<Cloak>,
+    mut atlas_sprites: CompMut<AtlasSprite>,
+    mut bodies: CompMut<KinematicBody>,
+    mut transforms: CompMut<Transform>,
+    mut items: CompMut<Item>,
+    mut item_throws: CompMut<ItemThrow>,
+    mut item_grabs: CompMut<ItemGrab>,
+    mut respawn_points: CompMut<DehydrateOutOfBounds>,
+    mut spawner_manager: SpawnerManager,
+) {
+    let mut not_hydrated_bitset = hydrated.bitset().clone();
+    not_hydrated_bitset.bit_not();
+    not_hydrated_bitset.bit_and(element_handles.bitset());
+
+    let spawner_entities = entities
+        .iter_with_bitset(&not_hydrated_bitset)
+        .collect::<Vec<_>>();
+
+    for spawner_ent in spawner_entities {
+        let transform = *transforms.get(spawner_ent).unwrap();
+        let element_handle = element_handles.get(spawner_ent).unwrap();
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+
+        if let BuiltinElementKind::Cloak {
+            atlas,
+            body_size,
+            grab_offset,
+            ..
+        } = &element_meta.builtin
+        {
+            hydrated.insert(spawner_ent, MapElementHydrated);
+
+            let entity = entities.create();
+            items.insert(entity, Item);
+            item_throws.insert(entity, ItemThrow::strength(0.0));
+            item_grabs.insert(
+                entity,
+                ItemGrab {
+                    fin_anim: key!("grab_2"),
+                    sync_animation: false,
+                    grab_offset: *grab_offset,
+                },
+            );
+            cloaks.insert(entity, Cloak);
+            atlas_sprites.insert(entity, AtlasSprite::new(atlas.clone()));
+            respawn_points.insert(entity, DehydrateOutOfBounds(spawner_ent));
+            transforms.insert(entity, transform);
+            element_handles.insert(entity, element_handle.clone());
+            hydrated.insert(entity, MapElementHydrated);
+            bodies.insert(
+                entity,
+                KinematicBody {
+                    shape: ColliderShape::Rectangle { size: *body_size },
+                    has_mass: true,
+                    has_friction: true,
+                    gravity: game_meta.physics.gravity,
+                    ..default()
+                },
+            );
+            spawner_manager.create_spawner(spawner_ent, vec![entity])
+        }
+    }
+}
+
+/// Put the cloaks on the players that use them.
+fn update(
+    entities: Res<Entities>,
+    mut commands: Commands,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    cloaks: Comp<Cloak>,
+    mut status_effects: CompMut<StatusEffects>,
+    mut items_used: CompMut<ItemUsed>,
+    player_inventories: PlayerInventories,
+    mut inventories: CompMut<Inventory>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    spawners: Comp<DehydrateOutOfBounds>,
+    mut audio_events: ResMut<AudioEvents>,
+) {
+    for (entity, (_cloak, element_handle, spawner)) in
+        entities.iter_with((&cloaks, &element_handles, &spawners))
+    {
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+        let BuiltinElementKind::Cloak {
+            duration,
+            sound,
+            sound_volume,
+            ..
+        } = &element_meta.builtin
+        else {
+            unreachable!();
+        };
+
+        // If the item is being held
+        let Some(Inv { player, .. }) = player_inventories
+            .iter()
+            .find_map(|x| x.filter(|x| x.inventory == entity))
+        else {
+            continue;
+        };
+
+        // If the item is being used
+        if items_used.remove(entity).is_none() {
+            continue;
+        }
+
+        // Put on the cloak, letting it respawn at its spawner
+        hydrated.remove(**spawner);
+        inventories.insert(player, Inventory(None));
+        audio_events.play(sound.clone(), *sound_volume);
+        if let Some(effects) = status_effects.get_mut(player) {
+            effects.add(StatusEffectKind::Invisible, *duration);
+        }
+        commands.add(move |mut entities: ResMut<Entities>| {
+            entities.kill(entity);
+        });
+    }
+}
+
+/// Make the invisible players that attack with the item they hold visible again.
+fn break_cloaks(
+    entities: Res<Entities>,
+    mut status_effects: CompMut<StatusEffects>,
+    inventories: Comp<Inventory>,
+    items_used: Comp<ItemUsed>,
+) {
+    for (_player, (effects, inventory)) in entities.iter_with((&mut status_effects, &inventories)) {
+        if inventory.0.map_or(false, |x| items_used.contains(x)) {
+            effects.remove(StatusEffectKind::Invisible);
+        }
+    }
+}
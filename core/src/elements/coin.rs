@@ -0,0 +1,251 @@
+//! Coins collected for score in arcade mode.
+//!
+//! Coins placed on the map come back a while after they are collected, while the coins dropped by
+//! killed players are gone once collected, or once they have been lying around for too long. Coins
+//! are only spawned in arcade mode, see the [`arcade`][crate::arcade] module.
+
+use std::time::Duration;
+
+use crate::{damage::DamageEvent, prelude::*};
+
+pub fn install(session: &mut CoreSession) {
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PreUpdate, hydrate)
+        .add_system_to_stage(CoreStage::PostUpdate, update);
+}
+
+#[derive(Clone, Debug, TypeUlid, Default)]
+#[ulid = "01H32A6T3KX8PQZ1RM5VNC9DWE"]
+pub struct Coin {
+    /// The time left until a coin placed on the map can be collected again.
+    pub respawn_timer: Timer,
+    /// Whether the coin was dropped by a killed player, in which case it is gone once collected.
+    pub dropped: bool,
+}
+
+/// Get the velocity of a coin scattered away from the given position.
+pub fn scatter_velocity(position: Vec2, from: Vec2, scatter_velocity: Vec2) -> Vec2 {
+    let direction = if position.x < from.x { -1.0 } else { 1.0 };
+    vec2(scatter_velocity.x * direction, scatter_velocity.y)
+}
+
+fn hydrate(
+    game_meta: Res<CoreMetaArc>,
+    match_settings: Res<MatchSettings>,
+    mut entities: ResMut<Entities>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    mut element_handles: CompMut<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut coins: CompMut<Coin>,
+    mut atlas_sprites: CompMut<AtlasSprite>,
+    mut bodies: CompMut<KinematicBody>,
+    mut transforms: CompMut<Transform>,
+    mut damageables: CompMut<Damageable>,
+    mut respawn_points: CompMut<DehydrateOutOfBounds>,
+    mut spawner_manager: SpawnerManager,
+) {
+    let mut not_hydrated_bitset = hydrated.bitset().clone();
+    not_hydrated_bitset.bit_not();
+    not_hydrated_bitset.bit_and(element_handles.bitset());
+
+    let spawner_entities = entities
+        .iter_with_bitset(&not_hydrated_bitset)
+        .collect::<Vec<_>>();
+
+    for spawner_ent in spawner_entities {
+        let transform = *transforms.get(spawner_ent).unwrap();
+        let element_handle = element_handles.get(spawner_ent).unwrap();
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+
+        if let BuiltinElementKind::Coin {
+            atlas,
+            body_diameter,
+            bounciness,
+            ..
+        } = &element_meta.builtin
+        {
+            hydrated.insert(spawner_ent, MapElementHydrated);
+
+            // Coins are only collected in arcade mode
+            if !match_settings.arcade {
+                continue;
+            }
+
+            let entity = entities.create();
+            coins.insert(entity, Coin::default());
+            atlas_sprites.insert(entity, AtlasSprite::new(atlas.clone()));
+            respawn_points.insert(entity, DehydrateOutOfBounds(spawner_ent));
+            transforms.insert(entity, transform);
+            element_handles.insert(entity, element_handle.clone());
+            hydrated.insert(entity, MapElementHydrated);
+            damageables.insert(
+                entity,
+                Damageable {
+                    size: Vec2::splat(*body_diameter),
+                },
+            );
+            bodies.insert(
+                entity,
+                KinematicBody {
+                    shape: ColliderShape::Circle {
+                        diameter: *body_diameter,
+                    },
+                    has_mass: true,
+                    has_friction: true,
+                    bounciness: *bounciness,
+                    gravity: game_meta.physics.gravity,
+                    ..default()
+                },
+            );
+            spawner_manager.create_spawner(spawner_ent, vec![entity])
+        }
+    }
+}
+
+/// Scatter the coins caught in explosions, and give the coins that the players touch to them.
+fn update(
+    entities: Res<Entities>,
+    mut commands: Commands,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    collision_world: CollisionWorld,
+    mut coins: CompMut<Coin>,
+    mut atlas_sprites: CompMut<AtlasSprite>,
+    mut bodies: CompMut<KinematicBody>,
+    mut transforms: CompMut<Transform>,
+    spawners: Comp<DehydrateOutOfBounds>,
+    player_indexes: Comp<PlayerIdx>,
+    players_killed: Comp<PlayerKilled>,
+    damage_events: Res<EventChannel<DamageEvent>>,
+    mut arcade_score: ResMut<ArcadeScore>,
+    mut audio_events: ResMut<AudioEvents>,
+    time: Res<Time>,
+) {
+    for (entity, (coin, element_handle)) in entities.iter_with((&mut coins, &element_handles)) {
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+        let BuiltinElementKind::Coin {
+            value,
+            respawn_delay,
+            scatter_velocity: scatter,
+            sound,
+            sound_volume,
+            ..
+        } = &element_meta.builtin
+        else {
+            unreachable!();
+        };
+
+        // Show the coin again once it has respawned
+        coin.respawn_timer.tick(time.delta());
+        let is_available = coin.respawn_timer.finished();
+        if let Some(sprite) = atlas_sprites.get_mut(entity) {
+            sprite.color.set_a(if is_available { 1.0 } else { 0.0 });
+        }
+        if !is_available {
+            continue;
+        }
+
+        // Scatter the coin away from explosions
+        if let Some(event) = damage_events.damaged(entity) {
+            let position = transforms.get(entity).unwrap().translation.truncate();
+            if let Some(body) = bodies.get_mut(entity) {
+                body.velocity = scatter_velocity(position, event.from, *scatter);
+            }
+        }
+
+        let Some(player_idx) = collision_world
+            .actor_collisions(entity)
+            .into_iter()
+            .filter(|ent| !players_killed.contains(*ent))
+            .find_map(|ent| player_indexes.get(ent).map(|idx| idx.0))
+        else {
+            continue;
+        };
+        arcade_score.coins[player_idx] += *value;
+        audio_events.play(sound.clone(), *sound_volume);
+
+        if coin.dropped {
+            commands.add(move |mut entities: ResMut<Entities>| {
+                entities.kill(entity);
+            });
+            continue;
+        }
+
+        // Put the coin back on its spawn point, hidden until it respawns
+        coin.respawn_timer = Timer::new(*respawn_delay, TimerMode::Once);
+        let Some(spawn_point) = spawners
+            .get(entity)
+            .and_then(|spawner| transforms.get(**spawner).copied())
+        else {
+            continue;
+        };
+        transforms.insert(entity, spawn_point);
+        if let Some(body) = bodies.get_mut(entity) {
+            body.velocity = Vec2::ZERO;
+        }
+    }
+}
+
+/// Spawn the coins dropped by a killed player, scattered around with the given velocities.
+pub fn spawn_dropped_coins(
+    element_handle: ElementHandle,
+    position: Vec3,
+    velocities: Vec<Vec2>,
+    atlas: Handle<Atlas>,
+    body_diameter: f32,
+    bounciness: f32,
+    gravity: f32,
+    lifetime: Duration,
+) -> System {
+    (move |mut entities: ResMut<Entities>,
+           mut element_handles: CompMut<ElementHandle>,
+           mut hydrated: CompMut<MapElementHydrated>,
+           mut coins: CompMut<Coin>,
+           mut atlas_sprites: CompMut<AtlasSprite>,
+           mut bodies: CompMut<KinematicBody>,
+           mut transforms: CompMut<Transform>,
+           mut damageables: CompMut<Damageable>,
+           mut lifetimes: CompMut<Lifetime>| {
+        for velocity in &velocities {
+            let entity = entities.create();
+            coins.insert(
+                entity,
+                Coin {
+                    dropped: true,
+                    ..default()
+                },
+            );
+            element_handles.insert(entity, element_handle.clone());
+            hydrated.insert(entity, MapElementHydrated);
+            atlas_sprites.insert(entity, AtlasSprite::new(atlas.clone()));
+            transforms.insert(entity, Transform::from_translation(position));
+            damageables.insert(
+                entity,
+                Damageable {
+                    size: Vec2::splat(body_diameter),
+                },
+            );
+            lifetimes.insert(entity, Lifetime::new(lifetime.as_secs_f32()));
+            bodies.insert(
+                entity,
+                KinematicBody {
+                    shape: ColliderShape::Circle {
+                        diameter: body_diameter,
+                    },
+                    velocity: *velocity,
+                    has_mass: true,
+                    has_friction: true,
+                    bounciness,
+                    gravity,
+                    ..default()
+                },
+            );
+        }
+    })
+    .system()
+}
@@ -1,4 +1,7 @@
-use crate::{prelude::*, random::GlobalRng};
+use crate::{
+    prelude::*,
+    random::{GlobalRng, RngStream},
+};
 
 pub fn install(session: &mut CoreSession) {
     session
@@ -138,6 +141,7 @@ fn update_crabs(
     element_handles: Comp<ElementHandle>,
     element_assets: BevyAssets<ElementMeta>,
 ) {
+    let rng = rng.stream(RngStream::Critters);
     for (entity, (crab, element_handle, spawner)) in
         entities.iter_with((&mut crabs, &element_handles, &spawners))
     {
@@ -249,9 +249,10 @@ fn update_thrown_crates(
             .collect::<Vec<_>>();
 
         for player_entity in &colliding_with_players {
-            commands.add(PlayerCommand::kill(
+            commands.add(PlayerCommand::kill_by(
                 *player_entity,
                 Some(transform.translation.xy()),
+                DamageSource::Player(thrown_crate.owner),
             ));
         }
         let kill_nearby_colliding: bool = kill_all_colliding_if_freshly_thrown(
@@ -330,15 +331,17 @@ fn kill_all_colliding_if_freshly_thrown(
     if !colliding_with_players.is_empty() {
         for player_entity in &colliding_with_players {
             if invincibles.get(*player_entity).is_none() {
-                commands.add(PlayerCommand::kill(
+                commands.add(PlayerCommand::kill_by(
                     *player_entity,
                     Some(transform.translation.xy()),
+                    DamageSource::Player(thrown_crate.owner),
                 ));
             }
         }
-        commands.add(PlayerCommand::kill(
+        commands.add(PlayerCommand::kill_by(
             thrown_crate.owner,
             Some(transform.translation.xy()),
+            DamageSource::Player(thrown_crate.owner),
         ));
         true
     } else {
@@ -0,0 +1,509 @@
+//! Decoys, a grenade that only pops into confetti, and an item that leaves a fake fish behind.
+
+use std::time::Duration;
+
+use crate::{damage::DamageEvent, prelude::*};
+
+pub fn install(session: &mut CoreSession) {
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PreUpdate, hydrate_decoy_grenades)
+        .add_system_to_stage(CoreStage::PreUpdate, hydrate_decoy_fish)
+        .add_system_to_stage(CoreStage::PostUpdate, update_idle_decoy_grenades)
+        .add_system_to_stage(CoreStage::PostUpdate, update_lit_decoy_grenades)
+        .add_system_to_stage(CoreStage::PostUpdate, update_decoy_fish)
+        .add_system_to_stage(CoreStage::PostUpdate, update_fake_fish);
+}
+
+#[derive(Clone, TypeUlid, Debug, Copy)]
+#[ulid = "01H2MC4A1Y8QJ5V3RZK7WDNB0E"]
+pub struct IdleDecoyGrenade;
+
+/// Component for lit decoy grenades, which pop when their fuse [`StepTimer`] finishes.
+#[derive(Clone, TypeUlid, Debug, Copy)]
+#[ulid = "01H2MC4F6KTB2X9HWQ0E3JRN7S"]
+pub struct LitDecoyGrenade;
+
+#[derive(Clone, TypeUlid, Debug, Copy)]
+#[ulid = "01H2MC4MZ3D7GNE1PC5AYV8QXT"]
+pub struct DecoyFish;
+
+/// A fake fish left standing by a [`DecoyFish`], which pops when it is hit or when its lifetime is
+/// over.
+#[derive(Clone, TypeUlid, Debug)]
+#[ulid = "01H2MC4TBQ0S6RJ2KX9FHW4VZM"]
+pub struct FakeFish {
+    /// The time left until the fake fish pops.
+    pub lifetime: Timer,
+    /// The confetti shown when the fake fish pops.
+    pub pop: DecoyPopMeta,
+}
+
+/// Commands for spawning dummy entities, which are only there to be seen and don't interact with
+/// the players.
+pub struct DummyCommand;
+
+impl DummyCommand {
+    /// Spawn an animation that plays once and then despawns.
+    pub fn spawn_effect(
+        transform: Transform,
+        atlas: Handle<Atlas>,
+        frames: usize,
+        fps: f32,
+        lifetime: f32,
+    ) -> System {
+        (move |mut entities: ResMut<Entities>,
+               mut transforms: CompMut<Transform>,
+               mut lifetimes: CompMut<Lifetime>,
+               mut sprites: CompMut<AtlasSprite>,
+               mut animated_sprites: CompMut<AnimatedSprite>| {
+            let ent = entities.create();
+            transforms.insert(ent, transform);
+            sprites.insert(
+                ent,
+                AtlasSprite {
+                    atlas: atlas.clone(),
+                    ..default()
+                },
+            );
+            animated_sprites.insert(
+                ent,
+                AnimatedSprite {
+                    frames: (0..frames).collect(),
+                    fps,
+                    repeat: false,
+                    ..default()
+                },
+            );
+            lifetimes.insert(ent, Lifetime::new(lifetime));
+        })
+        .system()
+    }
+
+    /// Spawn the confetti of a popped decoy.
+    pub fn pop(mut transform: Transform, pop: &DecoyPopMeta) -> System {
+        transform.translation.z = -10.0; // On top of almost everything
+        transform.rotation = Quat::IDENTITY;
+        Self::spawn_effect(
+            transform,
+            pop.atlas.clone(),
+            pop.frames,
+            pop.fps,
+            pop.lifetime,
+        )
+    }
+
+    /// Spawn a [`FakeFish`] that falls to the ground and stands there, playing the given
+    /// animation.
+    pub fn spawn_fake_fish(
+        transform: Transform,
+        sprite: AtlasSprite,
+        animation: AnimatedSprite,
+        body_size: Vec2,
+        fake_fish: FakeFish,
+    ) -> System {
+        (move |game_meta: Res<CoreMetaArc>,
+               mut entities: ResMut<Entities>,
+               mut transforms: CompMut<Transform>,
+               mut sprites: CompMut<AtlasSprite>,
+               mut animated_sprites: CompMut<AnimatedSprite>,
+               mut bodies: CompMut<KinematicBody>,
+               mut damageables: CompMut<Damageable>,
+               mut fake_fishes: CompMut<FakeFish>| {
+            let ent = entities.create();
+            transforms.insert(ent, transform);
+            sprites.insert(ent, sprite.clone());
+            animated_sprites.insert(ent, animation.clone());
+            damageables.insert(ent, Damageable { size: body_size });
+            bodies.insert(
+                ent,
+                KinematicBody {
+                    shape: ColliderShape::Rectangle { size: body_size },
+                    has_mass: true,
+                    has_friction: true,
+                    gravity: game_meta.physics.gravity,
+                    ..default()
+                },
+            );
+            fake_fishes.insert(ent, fake_fish.clone());
+        })
+        .system()
+    }
+}
+
+fn hydrate_decoy_grenades(
+    game_meta: Res<CoreMetaArc>,
+    mut entities: ResMut<Entities>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    mut element_handles: CompMut<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut idle_decoy_grenades: CompMut<IdleDecoyGrenade>,
+    mut atlas_sprites: CompMut<AtlasSprite>,
+    mut animated_sprites: CompMut<AnimatedSprite>,
+    mut bodies: CompMut<KinematicBody>,
+    mut transforms: CompMut<Transform>,
+    mut items: CompMut<Item>,
+    mut item_throws: CompMut<ItemThrow>,
+    mut item_grabs: CompMut<ItemGrab>,
+    mut respawn_points: CompMut<DehydrateOutOfBounds>,
+    mut spawner_manager: SpawnerManager,
+) {
+    let mut not_hydrated_bitset = hydrated.bitset().clone();
+    not_hydrated_bitset.bit_not();
+    not_hydrated_bitset.bit_and(element_handles.bitset());
+
+    let spawner_entities = entities
+        .iter_with_bitset(&not_hydrated_bitset)
+        .collect::<Vec<_>>();
+
+    for spawner_ent in spawner_entities {
+        let transform = *transforms.get(spawner_ent).unwrap();
+        let element_handle = element_handles.get(spawner_ent).unwrap();
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+
+        if let BuiltinElementKind::DecoyGrenade {
+            atlas,
+            fin_anim,
+            grab_offset,
+            body_diameter,
+            can_rotate,
+            bounciness,
+            throw_velocity,
+            angular_velocity,
+            ..
+        } = &element_meta.builtin
+        {
+            hydrated.insert(spawner_ent, MapElementHydrated);
+
+            let entity = entities.create();
+            items.insert(entity, Item);
+            idle_decoy_grenades.insert(entity, IdleDecoyGrenade);
+            item_throws.insert(
+                entity,
+                ItemThrow::strength(*throw_velocity).with_spin(*angular_velocity),
+            );
+            item_grabs.insert(
+                entity,
+                ItemGrab {
+                    fin_anim: *fin_anim,
+                    sync_animation: false,
+                    grab_offset: *grab_offset,
+                },
+            );
+            atlas_sprites.insert(entity, AtlasSprite::new(atlas.clone()));
+            respawn_points.insert(entity, DehydrateOutOfBounds(spawner_ent));
+            transforms.insert(entity, transform);
+            element_handles.insert(entity, element_handle.clone());
+            hydrated.insert(entity, MapElementHydrated);
+            animated_sprites.insert(entity, default());
+            bodies.insert(
+                entity,
+                KinematicBody {
+                    shape: ColliderShape::Circle {
+                        diameter: *body_diameter,
+                    },
+                    has_mass: true,
+                    has_friction: true,
+                    can_rotate: *can_rotate,
+                    bounciness: *bounciness,
+                    gravity: game_meta.physics.gravity,
+                    ..default()
+                },
+            );
+            spawner_manager.create_spawner(spawner_ent, vec![entity])
+        }
+    }
+}
+
+fn update_idle_decoy_grenades(
+    mut commands: Commands,
+    entities: Res<Entities>,
+    items_used: Comp<ItemUsed>,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut audio_events: ResMut<AudioEvents>,
+    idle_decoy_grenades: Comp<IdleDecoyGrenade>,
+    mut animated_sprites: CompMut<AnimatedSprite>,
+) {
+    for (entity, (_decoy, element_handle)) in
+        entities.iter_with((&idle_decoy_grenades, &element_handles))
+    {
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+
+        let BuiltinElementKind::DecoyGrenade {
+            fuse_sound,
+            fuse_sound_volume,
+            fuse_time,
+            ..
+        } = &element_meta.builtin else {
+            unreachable!();
+        };
+        let fuse_time = *fuse_time;
+
+        if items_used.get(entity).is_some() {
+            // Light it up just like a real grenade
+            let animated_sprite = animated_sprites.get_mut(entity).unwrap();
+            animated_sprite.frames = Arc::from([3, 4, 5]);
+            animated_sprite.repeat = true;
+            animated_sprite.fps = 8.0;
+
+            audio_events.play(fuse_sound.clone(), *fuse_sound_volume);
+
+            commands.add(
+                move |mut lit: CompMut<LitDecoyGrenade>,
+                      mut idle: CompMut<IdleDecoyGrenade>,
+                      mut items_used: CompMut<ItemUsed>,
+                      mut timers: CompMut<StepTimer>| {
+                    idle.remove(entity);
+                    lit.insert(entity, LitDecoyGrenade);
+                    timers.insert(entity, StepTimer::once(Duration::from_secs_f32(fuse_time)));
+                    items_used.remove(entity);
+                },
+            );
+        }
+    }
+}
+
+fn update_lit_decoy_grenades(
+    fuse_timers: Comp<StepTimer>,
+    mut commands: Commands,
+    mut entities: ResMut<Entities>,
+    transforms: Comp<Transform>,
+    element_handles: Comp<ElementHandle>,
+    spawners: Comp<DehydrateOutOfBounds>,
+    mut audio_events: ResMut<AudioEvents>,
+    lit_decoy_grenades: Comp<LitDecoyGrenade>,
+    player_inventories: PlayerInventories,
+    element_assets: BevyAssets<ElementMeta>,
+    mut player_layers: CompMut<PlayerLayers>,
+    mut hydrated: CompMut<MapElementHydrated>,
+) {
+    let mut popped = Vec::new();
+    for (entity, (_decoy, element_handle, spawner)) in
+        entities.iter_with((&lit_decoy_grenades, &element_handles, &spawners))
+    {
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+
+        let BuiltinElementKind::DecoyGrenade { fin_anim, pop, .. } = &element_meta.builtin else {
+            unreachable!();
+        };
+
+        // If the item is being held
+        if let Some(inventory) = player_inventories
+            .iter()
+            .find_map(|x| x.filter(|x| x.inventory == entity))
+        {
+            let layers = player_layers.get_mut(inventory.player).unwrap();
+            layers.fin_anim = *fin_anim;
+        }
+
+        // If it's time to pop
+        if fuse_timers.get(entity).map_or(false, |x| x.finished()) {
+            audio_events.play(pop.sound.clone(), pop.volume);
+
+            // Cause the item to respawn by un-hydrating it's spawner.
+            hydrated.remove(**spawner);
+            commands.add(DummyCommand::pop(*transforms.get(entity).unwrap(), pop));
+            popped.push(entity);
+        }
+    }
+
+    for entity in popped {
+        entities.kill(entity);
+    }
+}
+
+fn hydrate_decoy_fish(
+    game_meta: Res<CoreMetaArc>,
+    mut entities: ResMut<Entities>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    mut element_handles: CompMut<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut decoy_fishes: CompMut<DecoyFish>,
+    mut atlas_sprites: CompMut<AtlasSprite>,
+    mut bodies: CompMut<KinematicBody>,
+    mut transforms: CompMut<Transform>,
+    mut items: CompMut<Item>,
+    mut item_throws: CompMut<ItemThrow>,
+    mut item_grabs: CompMut<ItemGrab>,
+    mut respawn_points: CompMut<DehydrateOutOfBounds>,
+    mut spawner_manager: SpawnerManager,
+) {
+    let mut not_hydrated_bitset = hydrated.bitset().clone();
+    not_hydrated_bitset.bit_not();
+    not_hydrated_bitset.bit_and(element_handles.bitset());
+
+    let spawner_entities = entities
+        .iter_with_bitset(&not_hydrated_bitset)
+        .collect::<Vec<_>>();
+
+    for spawner_ent in spawner_entities {
+        let transform = *transforms.get(spawner_ent).unwrap();
+        let element_handle = element_handles.get(spawner_ent).unwrap();
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+
+        if let BuiltinElementKind::DecoyFish {
+            atlas,
+            fin_anim,
+            grab_offset,
+            body_size,
+            can_rotate,
+            bounciness,
+            throw_velocity,
+            angular_velocity,
+            ..
+        } = &element_meta.builtin
+        {
+            hydrated.insert(spawner_ent, MapElementHydrated);
+
+            let entity = entities.create();
+            items.insert(entity, Item);
+            decoy_fishes.insert(entity, DecoyFish);
+            item_throws.insert(
+                entity,
+                ItemThrow::strength(*throw_velocity).with_spin(*angular_velocity),
+            );
+            item_grabs.insert(
+                entity,
+                ItemGrab {
+                    fin_anim: *fin_anim,
+                    sync_animation: false,
+                    grab_offset: *grab_offset,
+                },
+            );
+            atlas_sprites.insert(entity, AtlasSprite::new(atlas.clone()));
+            respawn_points.insert(entity, DehydrateOutOfBounds(spawner_ent));
+            transforms.insert(entity, transform);
+            element_handles.insert(entity, element_handle.clone());
+            hydrated.insert(entity, MapElementHydrated);
+            bodies.insert(
+                entity,
+                KinematicBody {
+                    shape: ColliderShape::Rectangle { size: *body_size },
+                    has_mass: true,
+                    has_friction: true,
+                    can_rotate: *can_rotate,
+                    bounciness: *bounciness,
+                    gravity: game_meta.physics.gravity,
+                    ..default()
+                },
+            );
+            spawner_manager.create_spawner(spawner_ent, vec![entity])
+        }
+    }
+}
+
+/// Leave a fake fish where the decoy fish items are used, in place of the player using it.
+fn update_decoy_fish(
+    mut commands: Commands,
+    entities: Res<Entities>,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    decoy_fishes: Comp<DecoyFish>,
+    spawners: Comp<DehydrateOutOfBounds>,
+    transforms: Comp<Transform>,
+    sprites: Comp<AtlasSprite>,
+    mut audio_events: ResMut<AudioEvents>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    player_inventories: PlayerInventories,
+    mut items_used: CompMut<ItemUsed>,
+) {
+    for (entity, (_decoy, element_handle, spawner)) in
+        entities.iter_with((&decoy_fishes, &element_handles, &spawners))
+    {
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+
+        let BuiltinElementKind::DecoyFish {
+            fish_atlas,
+            fish_frames,
+            fish_fps,
+            fish_body_size,
+            fish_lifetime,
+            spawn_sound,
+            spawn_sound_volume,
+            pop,
+            ..
+        } = &element_meta.builtin else {
+            unreachable!();
+        };
+
+        // If the item is being held
+        let Some(inventory) = player_inventories
+            .iter()
+            .find_map(|x| x.filter(|x| x.inventory == entity))
+        else {
+            continue;
+        };
+        let player = inventory.player;
+
+        // If the item is being used
+        if items_used.remove(entity).is_none() {
+            continue;
+        }
+        audio_events.play(spawn_sound.clone(), *spawn_sound_volume);
+
+        commands.add(DummyCommand::spawn_fake_fish(
+            *transforms.get(player).unwrap(),
+            AtlasSprite {
+                atlas: fish_atlas.clone(),
+                flip_x: sprites.get(player).map_or(false, |x| x.flip_x),
+                ..default()
+            },
+            AnimatedSprite {
+                frames: (0..*fish_frames).collect(),
+                fps: *fish_fps,
+                repeat: true,
+                ..default()
+            },
+            *fish_body_size,
+            FakeFish {
+                lifetime: Timer::new(*fish_lifetime, TimerMode::Once),
+                pop: pop.clone(),
+            },
+        ));
+
+        // The item is used up, and respawns at its spawner
+        hydrated.remove(**spawner);
+        commands.add(PlayerCommand::set_inventory(player, None));
+        commands.add(move |mut entities: ResMut<Entities>| entities.kill(entity));
+    }
+}
+
+/// Pop the fake fish that are hit or that have been standing long enough.
+fn update_fake_fish(
+    mut commands: Commands,
+    mut entities: ResMut<Entities>,
+    mut fake_fishes: CompMut<FakeFish>,
+    transforms: Comp<Transform>,
+    damage_events: Res<EventChannel<DamageEvent>>,
+    mut audio_events: ResMut<AudioEvents>,
+    time: Res<Time>,
+) {
+    let mut popped = Vec::new();
+    for (entity, fake_fish) in entities.iter_with(&mut fake_fishes) {
+        fake_fish.lifetime.tick(time.delta());
+
+        if fake_fish.lifetime.finished() || damage_events.damaged(entity).is_some() {
+            audio_events.play(fake_fish.pop.sound.clone(), fake_fish.pop.volume);
+            commands.add(DummyCommand::pop(
+                *transforms.get(entity).unwrap(),
+                &fake_fish.pop,
+            ));
+            popped.push(entity);
+        }
+    }
+
+    for entity in popped {
+        entities.kill(entity);
+    }
+}
@@ -0,0 +1,326 @@
+//! Elevators and their call buttons.
+//!
+//! An elevator is a platform that travels straight up and down. A player touching a call button
+//! calls the elevators [linked][LinkMeta] to the button to the floor of the button, where the
+//! platform stops for a while before it travels to the next floor it was called to. Players and
+//! items standing on the platform ride along with it.
+
+use crate::prelude::*;
+
+pub fn install(session: &mut CoreSession) {
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PreUpdate, hydrate)
+        .add_system_to_stage(CoreStage::PostUpdate, press_call_buttons)
+        .add_system_to_stage(CoreStage::PostUpdate, move_elevators);
+}
+
+/// How far from the top of a platform, in pixels, the bottom of a body may be for the body to ride
+/// along with it.
+const RIDE_REACH: f32 = 1.0;
+
+#[derive(Clone, Debug, TypeUlid)]
+#[ulid = "01H33E4Q2WZ8MKT6XN5VB1RJCA"]
+pub struct Elevator {
+    /// The platform moved by the elevator.
+    pub platform: Entity,
+    /// The position of the elevator the platform was placed at, used to put the platform back when
+    /// the elevator is moved in the editor.
+    pub origin: Vec2,
+    /// The heights of the floors the elevator was called to, in the order they were called in.
+    pub calls: Vec<f32>,
+    /// The time left until the platform leaves the floor it stopped at.
+    pub stop: Timer,
+}
+
+impl Elevator {
+    /// Call the elevator to the floor at the given height, unless it was already called there.
+    pub fn call(&mut self, floor: f32) {
+        if !self.calls.contains(&floor) {
+            self.calls.push(floor);
+        }
+    }
+}
+
+#[derive(Clone, Debug, TypeUlid, Default)]
+#[ulid = "01H33E4WJ5RN9DQ2ZKX7MB3TVE"]
+pub struct ElevatorCallButton {
+    /// The time left until the button can be pressed again.
+    pub cooldown: Timer,
+}
+
+/// Whether a body is standing on top of a platform, given the bounding boxes of the body and the
+/// platform.
+pub fn is_riding(body: Rect, platform: Rect) -> bool {
+    let gap = body.min.y - platform.max.y;
+    (-RIDE_REACH..=RIDE_REACH).contains(&gap)
+        && body.max.x > platform.min.x
+        && body.min.x < platform.max.x
+}
+
+fn hydrate(
+    mut entities: ResMut<Entities>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut elevators: CompMut<Elevator>,
+    mut call_buttons: CompMut<ElevatorCallButton>,
+    mut atlas_sprites: CompMut<AtlasSprite>,
+    mut transforms: CompMut<Transform>,
+    mut colliders: CompMut<Collider>,
+    mut solids: CompMut<Solid>,
+    mut element_kill_callbacks: CompMut<ElementKillCallback>,
+) {
+    let mut not_hydrated_bitset = hydrated.bitset().clone();
+    not_hydrated_bitset.bit_not();
+    not_hydrated_bitset.bit_and(element_handles.bitset());
+
+    let element_entities = entities
+        .iter_with_bitset(&not_hydrated_bitset)
+        .collect::<Vec<_>>();
+
+    for entity in element_entities {
+        let element_handle = element_handles.get(entity).unwrap();
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+
+        match &element_meta.builtin {
+            BuiltinElementKind::Elevator {
+                atlas, body_size, ..
+            } => {
+                hydrated.insert(entity, MapElementHydrated);
+
+                // The platform is a separate entity, so that the elevator stays where it was placed
+                // in the editor.
+                let transform = *transforms.get(entity).unwrap();
+                let platform = entities.create();
+                transforms.insert(platform, transform);
+                atlas_sprites.insert(platform, AtlasSprite::new(atlas.clone()));
+                solids.insert(platform, Solid);
+                colliders.insert(
+                    platform,
+                    Collider {
+                        shape: ColliderShape::Rectangle { size: *body_size },
+                        ..default()
+                    },
+                );
+
+                elevators.insert(
+                    entity,
+                    Elevator {
+                        platform,
+                        origin: transform.translation.truncate(),
+                        calls: default(),
+                        stop: default(),
+                    },
+                );
+                element_kill_callbacks.insert(
+                    entity,
+                    ElementKillCallback::new(move |mut entities: ResMut<Entities>| {
+                        entities.kill(platform);
+                        entities.kill(entity);
+                    }),
+                );
+            }
+            BuiltinElementKind::ElevatorCallButton { atlas, .. } => {
+                hydrated.insert(entity, MapElementHydrated);
+                atlas_sprites.insert(entity, AtlasSprite::new(atlas.clone()));
+                call_buttons.insert(entity, default());
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Call the linked elevators to the floors of the buttons that players touch, and light up the
+/// buttons until they can be pressed again.
+fn press_call_buttons(
+    entities: Res<Entities>,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    links: Comp<LinkMeta>,
+    mut call_buttons: CompMut<ElevatorCallButton>,
+    mut elevators: CompMut<Elevator>,
+    player_indexes: Comp<PlayerIdx>,
+    players_killed: Comp<PlayerKilled>,
+    bodies: Comp<KinematicBody>,
+    transforms: Comp<Transform>,
+    mut atlas_sprites: CompMut<AtlasSprite>,
+    mut audio_events: ResMut<AudioEvents>,
+    time: Res<Time>,
+) {
+    let player_rects = entities
+        .iter_with((&player_indexes, &bodies, &transforms))
+        .filter(|(player, _)| !players_killed.contains(*player))
+        .map(|(_, (_, body, transform))| body.bounding_box(*transform))
+        .collect::<Vec<_>>();
+
+    for (entity, (call_button, element_handle, transform)) in
+        entities.iter_with((&mut call_buttons, &element_handles, &transforms))
+    {
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+        let BuiltinElementKind::ElevatorCallButton {
+            body_size,
+            floor_offset,
+            cooldown,
+            press_sound,
+            press_sound_volume,
+            ..
+        } = &element_meta.builtin
+        else {
+            unreachable!();
+        };
+
+        call_button.cooldown.tick(time.delta());
+        let is_ready = call_button.cooldown.finished();
+        if let Some(sprite) = atlas_sprites.get_mut(entity) {
+            sprite.index = if is_ready { 0 } else { 1 };
+        }
+        if !is_ready {
+            continue;
+        }
+
+        let position = transform.translation.truncate();
+        let rect = Rect::new(position.x, position.y, body_size.x, body_size.y);
+        if !player_rects.iter().any(|x| x.overlaps(&rect)) {
+            continue;
+        }
+        call_button.cooldown = Timer::new(*cooldown, TimerMode::Once);
+        audio_events.play(press_sound.clone(), *press_sound_volume);
+
+        let channel = links.get(entity).copied().unwrap_or_default();
+        let floor = position.y + floor_offset;
+        for (_, elevator) in entities
+            .iter_with(&mut elevators)
+            .filter(|(elevator_ent, _)| {
+                links.get(*elevator_ent).copied().unwrap_or_default() == channel
+            })
+        {
+            elevator.call(floor);
+        }
+    }
+}
+
+/// Move the platforms toward the floors they were called to, carrying the bodies standing on them.
+fn move_elevators(
+    entities: Res<Entities>,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut elevators: CompMut<Elevator>,
+    bodies: Comp<KinematicBody>,
+    mut transforms: CompMut<Transform>,
+    time: Res<Time>,
+) {
+    // Speeds are given in pixels per frame at 60 frames per second, like the velocities of bodies.
+    let time_factor = time.delta().as_secs_f32() * 60.0;
+
+    for (entity, (elevator, element_handle)) in
+        entities.iter_with((&mut elevators, &element_handles))
+    {
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+        let BuiltinElementKind::Elevator {
+            body_size,
+            speed,
+            stop_time,
+            ..
+        } = &element_meta.builtin
+        else {
+            unreachable!();
+        };
+
+        let elevator_transform = *transforms.get(entity).unwrap();
+        let Some(mut platform_transform) = transforms.get(elevator.platform).copied() else {
+            continue;
+        };
+
+        // Put the platform back when the elevator is moved in the editor
+        let origin = elevator_transform.translation.truncate();
+        if origin != elevator.origin {
+            elevator.origin = origin;
+            elevator.calls.clear();
+            transforms.insert(elevator.platform, elevator_transform);
+            continue;
+        }
+
+        elevator.stop.tick(time.delta());
+        if !elevator.stop.finished() {
+            continue;
+        }
+        let Some(floor) = elevator.calls.first().copied() else {
+            continue;
+        };
+
+        // The platform stops with its top level with the floor
+        let target = floor - body_size.y / 2.0;
+        let distance = target - platform_transform.translation.y;
+        let step = distance.clamp(-speed * time_factor, speed * time_factor);
+        if distance == step {
+            elevator.calls.remove(0);
+            elevator.stop = Timer::new(*stop_time, TimerMode::Once);
+        }
+        if step == 0.0 {
+            continue;
+        }
+
+        let platform_rect = Rect::new(
+            platform_transform.translation.x,
+            platform_transform.translation.y,
+            body_size.x,
+            body_size.y,
+        );
+        let riders = entities
+            .iter_with(&bodies)
+            .filter(|(rider, body)| {
+                let Some(transform) = transforms.get(*rider) else {
+                    return false;
+                };
+                // Bodies jumping off of the platform aren't carried
+                body.velocity.y <= 0.0 && is_riding(body.bounding_box(*transform), platform_rect)
+            })
+            .map(|(rider, _)| rider)
+            .collect::<Vec<_>>();
+        for rider in riders {
+            transforms.get_mut(rider).unwrap().translation.y += step;
+        }
+        platform_transform.translation.y += step;
+        transforms.insert(elevator.platform, platform_transform);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bodies_standing_on_platforms_ride_them() {
+        let platform = Rect::new(0.0, 0.0, 48.0, 8.0);
+        let body = |x: f32, y: f32| Rect::new(x, y, 20.0, 28.0);
+
+        assert!(is_riding(body(0.0, 18.1), platform));
+        assert!(is_riding(body(30.0, 18.0), platform));
+        // Off of the edge of the platform
+        assert!(!is_riding(body(40.0, 18.0), platform));
+        // Above or below the platform
+        assert!(!is_riding(body(0.0, 24.0), platform));
+        assert!(!is_riding(body(0.0, -18.0), platform));
+    }
+
+    #[test]
+    fn elevators_are_called_to_each_floor_once() {
+        let mut elevator = Elevator {
+            platform: Entity::new(0, 0),
+            origin: Vec2::ZERO,
+            calls: default(),
+            stop: default(),
+        };
+        elevator.call(64.0);
+        elevator.call(0.0);
+        elevator.call(64.0);
+        assert_eq!(elevator.calls, vec![64.0, 0.0]);
+    }
+}
@@ -0,0 +1,172 @@
+//! Explosive barrels.
+//!
+//! Barrels explode shortly after being hit by a damage region. Because the explosion is itself a
+//! damage region, barrels and grenades close to each other set each other off one after the other,
+//! scheduled by the [`ChainReactions`] resource.
+
+use crate::{
+    damage::{ChainReactions, DamageEvent, Damageable},
+    prelude::*,
+};
+
+pub fn install(session: &mut CoreSession) {
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PreUpdate, hydrate)
+        .add_system_to_stage(CoreStage::PostUpdate, update);
+}
+
+#[derive(Clone, Debug, TypeUlid, Default)]
+#[ulid = "01H1XB0W8ZC5YJ2RQ4NE7G6HVD"]
+pub struct ExplosiveBarrel {
+    /// What set the barrel off, credited with the kills of its explosion.
+    pub lit_by: DamageSource,
+}
+
+fn hydrate(
+    entities: Res<Entities>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut barrels: CompMut<ExplosiveBarrel>,
+    mut atlas_sprites: CompMut<AtlasSprite>,
+    mut bodies: CompMut<KinematicBody>,
+    mut damageables: CompMut<Damageable>,
+) {
+    let mut not_hydrated_bitset = hydrated.bitset().clone();
+    not_hydrated_bitset.bit_not();
+    not_hydrated_bitset.bit_and(element_handles.bitset());
+
+    for entity in entities.iter_with_bitset(&not_hydrated_bitset) {
+        let element_handle = element_handles.get(entity).unwrap();
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+
+        if let BuiltinElementKind::ExplosiveBarrel {
+            atlas, body_size, ..
+        } = &element_meta.builtin
+        {
+            hydrated.insert(entity, MapElementHydrated);
+            atlas_sprites.insert(entity, AtlasSprite::new(atlas.clone()));
+            bodies.insert(
+                entity,
+                KinematicBody {
+                    shape: ColliderShape::Rectangle { size: *body_size },
+                    has_mass: false,
+                    ..default()
+                },
+            );
+            damageables.insert(entity, Damageable { size: *body_size });
+            barrels.insert(entity, default());
+        }
+    }
+}
+
+fn update(
+    entities: Res<Entities>,
+    mut commands: Commands,
+    mut barrels: CompMut<ExplosiveBarrel>,
+    damage_events: Res<EventChannel<DamageEvent>>,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    transforms: Comp<Transform>,
+    mut chain_reactions: ResMut<ChainReactions>,
+    mut audio_events: ResMut<AudioEvents>,
+    mut trauma_events: ResMut<CameraTraumaEvents>,
+) {
+    for (entity, (barrel, element_handle)) in entities.iter_with((&mut barrels, &element_handles)) {
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+
+        let BuiltinElementKind::ExplosiveBarrel {
+            chain_delay_frames,
+            damage_region_size,
+            damage_region_lifetime,
+            explosion_sound,
+            explosion_volume,
+            explosion_lifetime,
+            explosion_atlas,
+            explosion_fps,
+            explosion_frames,
+            ..
+        } = &element_meta.builtin
+        else {
+            unreachable!();
+        };
+
+        // Light the barrel when it has been damaged
+        if let Some(event) = damage_events.damaged(entity) {
+            if !chain_reactions.is_scheduled(entity) {
+                barrel.lit_by = event.source;
+                chain_reactions.schedule(entity, *chain_delay_frames);
+            }
+        }
+
+        if !chain_reactions.is_due(entity) {
+            continue;
+        }
+
+        audio_events.play(explosion_sound.clone(), *explosion_volume);
+        trauma_events.send(5.0);
+
+        let mut explosion_transform = *transforms.get(entity).unwrap();
+        explosion_transform.translation.z = -10.0; // On top of almost everything
+
+        // Clone types for move into closure
+        let damage_region_size = *damage_region_size;
+        let damage_region_lifetime = *damage_region_lifetime;
+        let explosion_lifetime = *explosion_lifetime;
+        let explosion_atlas = explosion_atlas.clone();
+        let explosion_fps = *explosion_fps;
+        let explosion_frames = *explosion_frames;
+        let source = barrel.lit_by;
+        commands.add(
+            move |mut entities: ResMut<Entities>,
+                  mut transforms: CompMut<Transform>,
+                  mut damage_regions: CompMut<DamageRegion>,
+                  mut damage_region_owners: CompMut<DamageRegionOwner>,
+                  mut lifetimes: CompMut<Lifetime>,
+                  mut sprites: CompMut<AtlasSprite>,
+                  mut animated_sprites: CompMut<AnimatedSprite>| {
+                // Despawn the barrel
+                entities.kill(entity);
+
+                // Spawn the damage region
+                let ent = entities.create();
+                transforms.insert(ent, explosion_transform);
+                damage_regions.insert(
+                    ent,
+                    DamageRegion {
+                        size: damage_region_size,
+                        source,
+                    },
+                );
+                damage_region_owners.insert(ent, DamageRegionOwner(entity));
+                lifetimes.insert(ent, Lifetime::new(damage_region_lifetime));
+
+                // Spawn the explosion animation
+                let ent = entities.create();
+                transforms.insert(ent, explosion_transform);
+                sprites.insert(
+                    ent,
+                    AtlasSprite {
+                        atlas: explosion_atlas.clone(),
+                        ..default()
+                    },
+                );
+                animated_sprites.insert(
+                    ent,
+                    AnimatedSprite {
+                        frames: (0..explosion_frames).collect(),
+                        fps: explosion_fps,
+                        repeat: false,
+                        ..default()
+                    },
+                );
+                lifetimes.insert(ent, Lifetime::new(explosion_lifetime));
+            },
+        );
+    }
+}
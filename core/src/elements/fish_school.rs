@@ -1,6 +1,9 @@
 use std::time::Duration;
 
-use crate::{prelude::*, random::GlobalRng};
+use crate::{
+    prelude::*,
+    random::{GlobalRng, RngStream},
+};
 
 pub fn install(session: &mut CoreSession) {
     session
@@ -43,6 +46,7 @@ pub fn hydrate(
     mut animated_sprites: CompMut<AnimatedSprite>,
     mut spawner_manager: SpawnerManager,
 ) {
+    let rng = rng.stream(RngStream::Critters);
     let mut not_hydrated_bitset = hydrated.bitset().clone();
     not_hydrated_bitset.bit_not();
     not_hydrated_bitset.bit_and(element_handles.bitset());
@@ -100,13 +104,8 @@ pub fn hydrate(
                         rng.f32_normalized() * spawn_range,
                     );
 
-                let duration = {
-                    let min = 0.2;
-                    let max = 1.0;
-                    rng.f32() * (max - min) + min
-                };
-
-                let atlas = &kinds[rng.usize(0..kinds.len())];
+                let duration = rng.f32_range(0.2..1.0);
+                let Some(atlas) = rng.pick(kinds) else { break };
 
                 let fish_ent = entities.create();
                 fishes.insert(
@@ -170,6 +169,7 @@ pub fn update_fish_schools(
     collision_world: CollisionWorld,
     bodies: Comp<KinematicBody>,
 ) {
+    let rng = rng.stream(RngStream::Critters);
     for (school_ent, school) in entities.iter_with(&fish_schools) {
         let element_handle = element_handles.get(school_ent).unwrap();
         let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
@@ -223,11 +223,10 @@ pub fn update_fish_schools(
             let mut pos = transforms.get(*fish_ent).unwrap().translation.xy();
 
             let rand_bool = || rng.u8(0u8..2) == 0;
-            let rand_range = |min: f32, max: f32| rng.f32() * (max - min) + min;
 
             let pick_next_move = || {
                 if !is_grouped {
-                    let target_point = pos.lerp(center, rand_range(0.1, 0.4));
+                    let target_point = pos.lerp(center, rng.f32_range(0.1..0.4));
 
                     (
                         FishState::Moving {
@@ -235,14 +234,14 @@ pub fn update_fish_schools(
                             to: target_point,
                         },
                         Timer::new(
-                            Duration::from_secs_f32(rand_range(0.2, 0.7)),
+                            Duration::from_secs_f32(rng.f32_range(0.2..0.7)),
                             TimerMode::Repeating,
                         ),
                     )
                 } else if rand_bool() {
                     let target_point = vec2(
-                        pos.x + rand_range(-20.0, 20.0),
-                        pos.y + rand_range(-20.0, 20.0),
+                        pos.x + rng.f32_range(-20.0..20.0),
+                        pos.y + rng.f32_range(-20.0..20.0),
                     );
                     (
                         FishState::Moving {
@@ -250,19 +249,19 @@ pub fn update_fish_schools(
                             to: target_point,
                         },
                         Timer::new(
-                            Duration::from_secs_f32(rand_range(0.5, 1.5)),
+                            Duration::from_secs_f32(rng.f32_range(0.5..1.5)),
                             TimerMode::Repeating,
                         ),
                     )
                 } else {
-                    let target_point = pos.lerp(spawn_pos, rand_range(0.10, 0.25));
+                    let target_point = pos.lerp(spawn_pos, rng.f32_range(0.10..0.25));
                     (
                         FishState::Moving {
                             from: pos,
                             to: target_point,
                         },
                         Timer::new(
-                            Duration::from_secs_f32(rand_range(0.5, 1.5)),
+                            Duration::from_secs_f32(rng.f32_range(0.5..1.5)),
                             TimerMode::Repeating,
                         ),
                     )
@@ -275,10 +274,10 @@ pub fn update_fish_schools(
                 let diff = pos - transforms.get(scary_thing).unwrap().translation.xy();
                 fish.state = FishState::Moving {
                     from: pos,
-                    to: pos + diff.normalize() * rand_range(30.0, 60.0),
+                    to: pos + diff.normalize() * rng.f32_range(30.0..60.0),
                 };
                 fish.state_timer = Timer::new(
-                    Duration::from_secs_f32(rand_range(0.2, 0.6)),
+                    Duration::from_secs_f32(rng.f32_range(0.2..0.6)),
                     TimerMode::Repeating,
                 );
                 // We tick the timer an extra time here to make sure that the fish gets moving
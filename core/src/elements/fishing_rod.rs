@@ -0,0 +1,315 @@
+use std::time::Duration;
+
+use crate::prelude::*;
+
+pub fn install(session: &mut CoreSession) {
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PreUpdate, hydrate)
+        .add_system_to_stage(CoreStage::PostUpdate, update)
+        .add_system_to_stage(CoreStage::PostUpdate, pull_hooked_players);
+}
+
+/// The color of the fishing line.
+const LINE_COLOR: Color = Color::rgba(0.9, 0.9, 0.9, 0.8);
+/// The thickness of the fishing line.
+const LINE_THICKNESS: f32 = 1.0;
+/// The depth of the fishing line relative to the player that is pulling.
+const LINE_Z_OFFSET: f32 = 0.5;
+
+#[derive(Clone, Debug, TypeUlid, Default)]
+#[ulid = "01H2KF3Q8VJ6ZC0WXN4TBM7RDA"]
+pub struct FishingRod {
+    pub cooldown: Timer,
+}
+
+/// Component added to a player that has been snagged by a [`FishingRod`], while they are pulled
+/// toward the player holding it.
+#[derive(Clone, Debug, TypeUlid)]
+#[ulid = "01H2KF3X2MRB9ES5GQ7HYD1KVN"]
+pub struct Hooked {
+    /// The player pulling on the line.
+    pub wielder: Entity,
+    /// The fishing rod the player was snagged by.
+    pub rod: Entity,
+    /// The entity drawing the fishing line.
+    pub line: Entity,
+    /// The time left until the player is released, if they aren't pulled in before.
+    pub timer: Timer,
+}
+
+fn hydrate(
+    game_meta: Res<CoreMetaArc>,
+    mut entities: ResMut<Entities>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    mut element_handles: CompMut<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut fishing_rods: CompMut<FishingRod>,
+    mut atlas_sprites: CompMut<AtlasSprite>,
+    mut bodies: CompMut<KinematicBody>,
+    mut transforms: CompMut<Transform>,
+    mut items: CompMut<Item>,
+    mut item_throws: CompMut<ItemThrow>,
+    mut item_grabs: CompMut<ItemGrab>,
+    mut respawn_points: CompMut<DehydrateOutOfBounds>,
+    mut spawner_manager: SpawnerManager,
+) {
+    let mut not_hydrated_bitset = hydrated.bitset().clone();
+    not_hydrated_bitset.bit_not();
+    not_hydrated_bitset.bit_and(element_handles.bitset());
+
+    let spawner_entities = entities
+        .iter_with_bitset(&not_hydrated_bitset)
+        .collect::<Vec<_>>();
+
+    for spawner_ent in spawner_entities {
+        let transform = *transforms.get(spawner_ent).unwrap();
+        let element_handle = element_handles.get(spawner_ent).unwrap();
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+
+        if let BuiltinElementKind::FishingRod {
+            atlas,
+            fin_anim,
+            grab_offset,
+            body_size,
+            can_rotate,
+            bounciness,
+            throw_velocity,
+            angular_velocity,
+            ..
+        } = &element_meta.builtin
+        {
+            hydrated.insert(spawner_ent, MapElementHydrated);
+
+            let entity = entities.create();
+            items.insert(entity, Item);
+            item_throws.insert(
+                entity,
+                ItemThrow::strength(*throw_velocity).with_spin(*angular_velocity),
+            );
+            item_grabs.insert(
+                entity,
+                ItemGrab {
+                    fin_anim: *fin_anim,
+                    sync_animation: false,
+                    grab_offset: *grab_offset,
+                },
+            );
+            fishing_rods.insert(
+                entity,
+                FishingRod {
+                    cooldown: Timer::new(Duration::ZERO, TimerMode::Once),
+                },
+            );
+            atlas_sprites.insert(entity, AtlasSprite::new(atlas.clone()));
+            respawn_points.insert(entity, DehydrateOutOfBounds(spawner_ent));
+            transforms.insert(entity, transform);
+            element_handles.insert(entity, element_handle.clone());
+            hydrated.insert(entity, MapElementHydrated);
+            bodies.insert(
+                entity,
+                KinematicBody {
+                    shape: ColliderShape::Rectangle { size: *body_size },
+                    has_mass: true,
+                    has_friction: true,
+                    can_rotate: *can_rotate,
+                    bounciness: *bounciness,
+                    gravity: game_meta.physics.gravity,
+                    ..default()
+                },
+            );
+            spawner_manager.create_spawner(spawner_ent, vec![entity])
+        }
+    }
+}
+
+/// Cast the hook of the fishing rods that are used, snagging the nearest opponent in the cone in
+/// front of the player.
+fn update(
+    mut entities: ResMut<Entities>,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    match_settings: Res<MatchSettings>,
+    mut fishing_rods: CompMut<FishingRod>,
+    mut hooked_players: CompMut<Hooked>,
+    mut transforms: CompMut<Transform>,
+    mut paths: CompMut<Path2d>,
+    sprites: Comp<AtlasSprite>,
+    player_indexes: Comp<PlayerIdx>,
+    players_killed: Comp<PlayerKilled>,
+    invincibles: Comp<Invincibility>,
+    mut audio_events: ResMut<AudioEvents>,
+    player_inventories: PlayerInventories,
+    mut items_used: CompMut<ItemUsed>,
+    time: Res<Time>,
+) {
+    let rods = entities
+        .iter_with((&mut fishing_rods, &element_handles))
+        .map(|(ent, (rod, handle))| {
+            rod.cooldown.tick(time.delta());
+            (ent, rod.cooldown.finished(), handle.clone())
+        })
+        .collect::<Vec<_>>();
+
+    for (entity, is_ready, element_handle) in rods {
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+        let BuiltinElementKind::FishingRod {
+            range,
+            cone_angle,
+            max_pull_duration,
+            cooldown,
+            cast_sound,
+            cast_sound_volume,
+            ..
+        } = &element_meta.builtin else {
+            unreachable!();
+        };
+
+        // If the item is being held
+        let Some(inventory) = player_inventories
+            .iter()
+            .find_map(|x| x.filter(|x| x.inventory == entity))
+        else {
+            continue;
+        };
+        let player = inventory.player;
+
+        // If the item is being used
+        if items_used.remove(entity).is_none() || !is_ready {
+            continue;
+        }
+        fishing_rods.get_mut(entity).unwrap().cooldown = Timer::new(*cooldown, TimerMode::Once);
+        audio_events.play(cast_sound.clone(), *cast_sound_volume);
+
+        // Find the nearest opponent in the cone in front of the player
+        let player_transform = *transforms.get(player).unwrap();
+        let player_pos = player_transform.translation.truncate();
+        let facing = if sprites.get(player).map_or(false, |x| x.flip_x) {
+            Vec2::NEG_X
+        } else {
+            Vec2::X
+        };
+        let max_angle = cone_angle.to_radians() / 2.0;
+        let target = entities
+            .iter_with((&player_indexes, &transforms))
+            .filter(|(ent, _)| {
+                *ent != player
+                    && !players_killed.contains(*ent)
+                    && !invincibles.contains(*ent)
+                    && !hooked_players.contains(*ent)
+                    && !is_protected_teammate(&match_settings, &player_indexes, player, *ent)
+            })
+            .map(|(ent, (_, transform))| (ent, transform.translation.truncate() - player_pos))
+            .filter(|(_, offset)| {
+                offset.length() <= *range && facing.angle_between(*offset).abs() <= max_angle
+            })
+            .min_by(|(_, a), (_, b)| a.length().total_cmp(&b.length()))
+            .map(|(ent, _)| ent);
+        let Some(target) = target else { continue };
+
+        // Tie the line between the two players
+        let line = entities.create();
+        transforms.insert(
+            line,
+            Transform::from_translation(Vec3::new(
+                0.0,
+                0.0,
+                player_transform.translation.z + LINE_Z_OFFSET,
+            )),
+        );
+        paths.insert(line, default());
+        hooked_players.insert(
+            target,
+            Hooked {
+                wielder: player,
+                rod: entity,
+                line,
+                timer: Timer::new(*max_pull_duration, TimerMode::Once),
+            },
+        );
+    }
+}
+
+/// Pull the hooked players toward the players holding the line, and stun them once they have been
+/// pulled in.
+fn pull_hooked_players(
+    mut entities: ResMut<Entities>,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut hooked_players: CompMut<Hooked>,
+    transforms: Comp<Transform>,
+    mut paths: CompMut<Path2d>,
+    mut bodies: CompMut<KinematicBody>,
+    mut player_states: CompMut<PlayerState>,
+    players_killed: Comp<PlayerKilled>,
+    player_inventories: PlayerInventories,
+    time: Res<Time>,
+) {
+    let mut released = Vec::new();
+    for (player, hooked) in entities.iter_with(&mut hooked_players) {
+        hooked.timer.tick(time.delta());
+
+        // The line snaps if either player dies, or if the rod is dropped
+        let is_rod_held = player_inventories
+            .iter()
+            .flatten()
+            .any(|x| x.player == hooked.wielder && x.inventory == hooked.rod);
+        let (Some(player_transform), Some(wielder_transform)) =
+            (transforms.get(player), transforms.get(hooked.wielder))
+        else {
+            released.push((player, false));
+            continue;
+        };
+        if !is_rod_held
+            || players_killed.contains(player)
+            || players_killed.contains(hooked.wielder)
+        {
+            released.push((player, false));
+            continue;
+        }
+
+        let Some(BuiltinElementKind::FishingRod {
+            pull_speed,
+            release_distance,
+            ..
+        }) = element_handles
+            .get(hooked.rod)
+            .and_then(|handle| element_assets.get(&handle.get_bevy_handle()))
+            .map(|meta| &meta.builtin)
+        else {
+            released.push((player, false));
+            continue;
+        };
+
+        let player_pos = player_transform.translation.truncate();
+        let wielder_pos = wielder_transform.translation.truncate();
+        let offset = wielder_pos - player_pos;
+        if offset.length() <= *release_distance || hooked.timer.finished() {
+            released.push((player, true));
+            continue;
+        }
+
+        if let Some(body) = bodies.get_mut(player) {
+            body.velocity = offset.normalize_or_zero() * *pull_speed;
+        }
+        if let Some(path) = paths.get_mut(hooked.line) {
+            path.points = vec![wielder_pos, player_pos];
+            path.color = LINE_COLOR;
+            path.thickness = LINE_THICKNESS;
+        }
+    }
+
+    for (player, stun) in released {
+        let hooked = hooked_players.remove(player).unwrap();
+        entities.kill(hooked.line);
+        if stun {
+            if let Some(state) = player_states.get_mut(player) {
+                state.current = key!("core::incapacitated");
+            }
+        }
+    }
+}
@@ -0,0 +1,263 @@
+//! Flag stands, for capture the flag matches.
+//!
+//! Every stand belongs to a team. In [capture the flag][crate::gamemode::ctf] matches, the flag of
+//! the team is spawned on its stand, and in other matches the stands stay empty. A player touching
+//! the flag of the other team picks it up and carries it on their back, until they are killed and
+//! drop it where they died. A dropped flag is returned to its stand when a player of its team
+//! touches it, or once it has been lying around for a while.
+
+use crate::prelude::*;
+
+pub fn install(session: &mut CoreSession) {
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PreUpdate, hydrate)
+        .add_system_to_stage(CoreStage::PostUpdate, update_flags);
+}
+
+/// The depth of a carried flag relative to the player, behind their body.
+const CARRIED_FLAG_Z_OFFSET: f32 = -0.1;
+
+#[derive(Clone, Debug, TypeUlid)]
+#[ulid = "01H34B6N2XQ8RZK5TW9VMJ3CDA"]
+pub struct FlagStand {
+    /// The team that the stand belongs to.
+    pub team: u8,
+    /// The flag of the team, if the match is a capture the flag match.
+    pub flag: Option<Entity>,
+}
+
+#[derive(Clone, Debug, TypeUlid)]
+#[ulid = "01H34B6VKM4WQ2NZ7XR8TJ5CDE"]
+pub struct Flag {
+    /// The team that the flag belongs to.
+    pub team: u8,
+    /// The stand of the flag.
+    pub stand: Entity,
+    /// Where the flag is.
+    pub state: FlagState,
+}
+
+/// Where a [`Flag`] is.
+#[derive(Clone, Debug)]
+pub enum FlagState {
+    /// The flag is at its stand.
+    AtStand,
+    /// The flag is carried by a player.
+    Carried(Entity),
+    /// The flag was dropped, and is returned to its stand when the timer finishes.
+    Dropped(Timer),
+}
+
+/// Whether a player of the given team may pick up a flag of the given team.
+///
+/// Players pick up the flags of the other teams, and return the dropped flags of their own team.
+pub fn can_pick_up_flag(player_team: u8, flag_team: u8) -> bool {
+    player_team != flag_team
+}
+
+/// Put a flag back on its stand, taking it from its carrier if it is carried.
+pub fn return_flag(
+    flag_ent: Entity,
+    flag: &mut Flag,
+    flag_offset: Vec2,
+    transforms: &mut CompMut<Transform>,
+    player_body_attachments: &mut CompMut<PlayerBodyAttachment>,
+) {
+    flag.state = FlagState::AtStand;
+    player_body_attachments.remove(flag_ent);
+    if let Some(stand_transform) = transforms.get(flag.stand).copied() {
+        transforms.get_mut(flag_ent).unwrap().translation =
+            stand_transform.translation + flag_offset.extend(0.0);
+    }
+}
+
+fn hydrate(
+    match_settings: Res<MatchSettings>,
+    mut entities: ResMut<Entities>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut flag_stands: CompMut<FlagStand>,
+    mut flags: CompMut<Flag>,
+    mut atlas_sprites: CompMut<AtlasSprite>,
+    mut transforms: CompMut<Transform>,
+    mut element_kill_callbacks: CompMut<ElementKillCallback>,
+) {
+    let mut not_hydrated_bitset = hydrated.bitset().clone();
+    not_hydrated_bitset.bit_not();
+    not_hydrated_bitset.bit_and(element_handles.bitset());
+
+    let element_entities = entities
+        .iter_with_bitset(&not_hydrated_bitset)
+        .collect::<Vec<_>>();
+
+    for entity in element_entities {
+        let element_handle = element_handles.get(entity).unwrap();
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+
+        let BuiltinElementKind::FlagStand {
+            team,
+            atlas,
+            flag_atlas,
+            flag_offset,
+            ..
+        } = &element_meta.builtin
+        else {
+            continue;
+        };
+
+        hydrated.insert(entity, MapElementHydrated);
+        atlas_sprites.insert(
+            entity,
+            AtlasSprite {
+                atlas: atlas.clone(),
+                index: *team as usize,
+                ..default()
+            },
+        );
+
+        let flag = (match_settings.mode == MatchMode::CaptureTheFlag).then(|| {
+            let flag = entities.create();
+            let mut transform = *transforms.get(entity).unwrap();
+            transform.translation += flag_offset.extend(0.0);
+            transforms.insert(flag, transform);
+            atlas_sprites.insert(
+                flag,
+                AtlasSprite {
+                    atlas: flag_atlas.clone(),
+                    index: *team as usize,
+                    ..default()
+                },
+            );
+            flags.insert(
+                flag,
+                Flag {
+                    team: *team,
+                    stand: entity,
+                    state: FlagState::AtStand,
+                },
+            );
+            flag
+        });
+        flag_stands.insert(entity, FlagStand { team: *team, flag });
+        element_kill_callbacks.insert(
+            entity,
+            ElementKillCallback::new(move |mut entities: ResMut<Entities>| {
+                if let Some(flag) = flag {
+                    entities.kill(flag);
+                }
+                entities.kill(entity);
+            }),
+        );
+    }
+}
+
+/// Pick up, drop, and return the flags.
+fn update_flags(
+    entities: Res<Entities>,
+    match_settings: Res<MatchSettings>,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut flags: CompMut<Flag>,
+    player_indexes: Comp<PlayerIdx>,
+    players_killed: Comp<PlayerKilled>,
+    bodies: Comp<KinematicBody>,
+    mut transforms: CompMut<Transform>,
+    mut player_body_attachments: CompMut<PlayerBodyAttachment>,
+    mut audio_events: ResMut<AudioEvents>,
+    time: Res<Time>,
+) {
+    let players = entities
+        .iter_with((&player_indexes, &bodies, &transforms))
+        .filter(|(player, _)| !players_killed.contains(*player))
+        .filter_map(|(player, (idx, body, transform))| {
+            let team = match_settings.team_of(idx.0)?;
+            Some((player, team, body.bounding_box(*transform)))
+        })
+        .collect::<Vec<_>>();
+
+    for (flag_ent, flag) in entities.iter_with(&mut flags) {
+        let Some(element_meta) = element_handles
+            .get(flag.stand)
+            .and_then(|x| element_assets.get(&x.get_bevy_handle()))
+        else {
+            continue;
+        };
+        let BuiltinElementKind::FlagStand {
+            flag_size,
+            flag_offset,
+            carry_offset,
+            return_time,
+            pickup_sound,
+            pickup_sound_volume,
+            return_sound,
+            return_sound_volume,
+            ..
+        } = &element_meta.builtin
+        else {
+            unreachable!();
+        };
+
+        let mut return_to_stand = false;
+        match &mut flag.state {
+            FlagState::AtStand => (),
+            FlagState::Carried(carrier) => {
+                // Drop the flag where its carrier was killed
+                let carrier = *carrier;
+                if entities.is_alive(carrier) && !players_killed.contains(carrier) {
+                    continue;
+                }
+                player_body_attachments.remove(flag_ent);
+                if let Some(position) = transforms.get(carrier).map(|x| x.translation) {
+                    transforms.get_mut(flag_ent).unwrap().translation = position;
+                }
+                flag.state = FlagState::Dropped(Timer::new(*return_time, TimerMode::Once));
+                continue;
+            }
+            FlagState::Dropped(timer) => {
+                timer.tick(time.delta());
+                return_to_stand = timer.finished();
+            }
+        }
+
+        let Some(position) = transforms.get(flag_ent).map(|x| x.translation.truncate()) else {
+            continue;
+        };
+        let rect = Rect::new(position.x, position.y, flag_size.x, flag_size.y);
+        let toucher = players
+            .iter()
+            .find(|(_, _, player_rect)| player_rect.overlaps(&rect));
+        if let Some((player, team, _)) = toucher {
+            if can_pick_up_flag(*team, flag.team) {
+                audio_events.play(pickup_sound.clone(), *pickup_sound_volume);
+                flag.state = FlagState::Carried(*player);
+                player_body_attachments.insert(
+                    flag_ent,
+                    PlayerBodyAttachment {
+                        player: *player,
+                        offset: carry_offset.extend(CARRIED_FLAG_Z_OFFSET),
+                        head: false,
+                        sync_animation: false,
+                        sync_color: false,
+                    },
+                );
+                continue;
+            }
+            return_to_stand |= matches!(flag.state, FlagState::Dropped(_));
+        }
+
+        if return_to_stand {
+            audio_events.play(return_sound.clone(), *return_sound_volume);
+            return_flag(
+                flag_ent,
+                flag,
+                *flag_offset,
+                &mut transforms,
+                &mut player_body_attachments,
+            );
+        }
+    }
+}
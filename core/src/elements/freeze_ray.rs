@@ -0,0 +1,257 @@
+//! Freeze ray.
+//!
+//! The freeze ray shoots a beam that freezes the first player in its way. Hitting a player again
+//! shortly after they were frozen shatters them.
+
+use crate::prelude::*;
+
+pub fn install(session: &mut CoreSession) {
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PreUpdate, hydrate)
+        .add_system_to_stage(CoreStage::PostUpdate, update);
+}
+
+#[derive(Clone, Debug, TypeUlid, Default)]
+#[ulid = "01H31ZFB2MV8TQ5KXN0RJ7YCWA"]
+pub struct FreezeRay {
+    pub ammo: usize,
+    pub cooldown: Timer,
+}
+
+fn hydrate(
+    game_meta: Res<CoreMetaArc>,
+    mut entities: ResMut<Entities>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    mut element_handles: CompMut<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut freeze_rays: CompMut<FreezeRay>,
+    mut atlas_sprites: CompMut<AtlasSprite>,
+    mut bodies: CompMut<KinematicBody>,
+    mut transforms: CompMut<Transform>,
+    mut items: CompMut<Item>,
+    mut item_throws: CompMut<ItemThrow>,
+    mut item_grabs: CompMut<ItemGrab>,
+    mut respawn_points: CompMut<DehydrateOutOfBounds>,
+    mut spawner_manager: SpawnerManager,
+) {
+    let mut not_hydrated_bitset = hydrated.bitset().clone();
+    not_hydrated_bitset.bit_not();
+    not_hydrated_bitset.bit_and(element_handles.bitset());
+
+    let spawner_entities = entities
+        .iter_with_bitset(&not_hydrated_bitset)
+        .collect::<Vec<_>>();
+
+    for spawner_ent in spawner_entities {
+        let transform = *transforms.get(spawner_ent).unwrap();
+        let element_handle = element_handles.get(spawner_ent).unwrap();
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+
+        if let BuiltinElementKind::FreezeRay {
+            atlas,
+            body_size,
+            grab_offset,
+            fin_anim,
+            bounciness,
+            can_rotate,
+            throw_velocity,
+            angular_velocity,
+            max_ammo,
+            ..
+        } = &element_meta.builtin
+        {
+            hydrated.insert(spawner_ent, MapElementHydrated);
+
+            let entity = entities.create();
+            items.insert(entity, Item);
+            freeze_rays.insert(
+                entity,
+                FreezeRay {
+                    ammo: *max_ammo,
+                    cooldown: default(),
+                },
+            );
+            item_throws.insert(
+                entity,
+                ItemThrow::strength(*throw_velocity).with_spin(*angular_velocity),
+            );
+            item_grabs.insert(
+                entity,
+                ItemGrab {
+                    fin_anim: *fin_anim,
+                    sync_animation: false,
+                    grab_offset: *grab_offset,
+                },
+            );
+            atlas_sprites.insert(entity, AtlasSprite::new(atlas.clone()));
+            respawn_points.insert(entity, DehydrateOutOfBounds(spawner_ent));
+            transforms.insert(entity, transform);
+            element_handles.insert(entity, element_handle.clone());
+            hydrated.insert(entity, MapElementHydrated);
+            bodies.insert(
+                entity,
+                KinematicBody {
+                    shape: ColliderShape::Rectangle { size: *body_size },
+                    has_mass: true,
+                    has_friction: true,
+                    can_rotate: *can_rotate,
+                    bounciness: *bounciness,
+                    gravity: game_meta.physics.gravity,
+                    ..default()
+                },
+            );
+            spawner_manager.create_spawner(spawner_ent, vec![entity])
+        }
+    }
+}
+
+/// Shoot the freeze rays that are used, freezing or shattering the first player in the way of the
+/// beam, and reload the freeze rays that are dropped.
+fn update(
+    entities: Res<Entities>,
+    mut commands: Commands,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    match_settings: Res<MatchSettings>,
+    mut freeze_rays: CompMut<FreezeRay>,
+    player_inventories: PlayerInventories,
+    mut items_used: CompMut<ItemUsed>,
+    items_dropped: Comp<ItemDropped>,
+    player_indexes: Comp<PlayerIdx>,
+    players_killed: Comp<PlayerKilled>,
+    invincibles: Comp<Invincibility>,
+    mut status_effects: CompMut<StatusEffects>,
+    transforms: Comp<Transform>,
+    bodies: Comp<KinematicBody>,
+    sprites: Comp<AtlasSprite>,
+    mut audio_events: ResMut<AudioEvents>,
+    time: Res<Time>,
+) {
+    for (entity, (freeze_ray, element_handle)) in
+        entities.iter_with((&mut freeze_rays, &element_handles))
+    {
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+        let BuiltinElementKind::FreezeRay {
+            max_ammo,
+            cooldown,
+            range,
+            muzzle_offset,
+            beam_color,
+            beam_thickness,
+            beam_lifetime,
+            freeze_duration,
+            shatter_window,
+            shoot_sound,
+            shoot_sound_volume,
+            empty_shoot_sound,
+            empty_shoot_sound_volume,
+            shatter_sound,
+            shatter_sound_volume,
+            ..
+        } = &element_meta.builtin
+        else {
+            unreachable!();
+        };
+
+        freeze_ray.cooldown.tick(time.delta());
+
+        // Reload once dropped
+        if items_dropped.contains(entity) {
+            freeze_ray.ammo = *max_ammo;
+        }
+
+        // If the item is being held
+        let Some(Inv { player, .. }) = player_inventories
+            .iter()
+            .find_map(|x| x.filter(|x| x.inventory == entity))
+        else {
+            continue;
+        };
+
+        // If the item is being used
+        if items_used.remove(entity).is_none() || !freeze_ray.cooldown.finished() {
+            continue;
+        }
+        if freeze_ray.ammo == 0 {
+            audio_events.play(empty_shoot_sound.clone(), *empty_shoot_sound_volume);
+            continue;
+        }
+        freeze_ray.cooldown = Timer::new(*cooldown, TimerMode::Once);
+        freeze_ray.ammo -= 1;
+        audio_events.play(shoot_sound.clone(), *shoot_sound_volume);
+
+        let flip_factor = if sprites.get(player).map_or(false, |x| x.flip_x) {
+            -1.0
+        } else {
+            1.0
+        };
+        let gun_transform = *transforms.get(entity).unwrap();
+        let start = gun_transform.translation.truncate()
+            + vec2(muzzle_offset.x * flip_factor, muzzle_offset.y);
+        let mut end = start + vec2(*range * flip_factor, 0.0);
+
+        // Hit the nearest player in the way of the beam
+        let target = entities
+            .iter_with((&player_indexes, &transforms, &bodies))
+            .filter(|(ent, _)| {
+                *ent != player
+                    && !players_killed.contains(*ent)
+                    && !invincibles.contains(*ent)
+                    && !is_protected_teammate(&match_settings, &player_indexes, player, *ent)
+            })
+            .filter(|(_, (_, transform, body))| {
+                body.bounding_box(**transform)
+                    .intersects_segment(start, end)
+            })
+            .map(|(ent, (_, transform, _))| (ent, transform.translation.truncate()))
+            .min_by(|(_, a), (_, b)| {
+                a.distance_squared(start)
+                    .total_cmp(&b.distance_squared(start))
+            });
+        if let Some((target, target_pos)) = target {
+            end.x = target_pos.x;
+            // Players hit again shortly after they were frozen shatter
+            let can_shatter = status_effects
+                .get(target)
+                .and_then(|x| x.get(StatusEffectKind::Frozen))
+                .map_or(false, |x| x.timer.elapsed() < *shatter_window);
+            if can_shatter {
+                audio_events.play(shatter_sound.clone(), *shatter_sound_volume);
+                commands.add(PlayerCommand::kill_by(
+                    target,
+                    Some(start),
+                    DamageSource::Player(player),
+                ));
+            } else if let Some(effects) = status_effects.get_mut(target) {
+                effects.add(StatusEffectKind::Frozen, *freeze_duration);
+            }
+        }
+
+        // Draw the beam
+        let beam_transform =
+            Transform::from_translation(Vec3::new(0.0, 0.0, gun_transform.translation.z + 0.1));
+        let beam_path = Path2d {
+            points: vec![start, end],
+            color: beam_color.0,
+            thickness: *beam_thickness,
+            ..default()
+        };
+        let beam_lifetime = *beam_lifetime;
+        commands.add(
+            move |mut entities: ResMut<Entities>,
+                  mut transforms: CompMut<Transform>,
+                  mut paths: CompMut<Path2d>,
+                  mut lifetimes: CompMut<Lifetime>| {
+                let ent = entities.create();
+                transforms.insert(ent, beam_transform);
+                paths.insert(ent, beam_path.clone());
+                lifetimes.insert(ent, Lifetime::new(beam_lifetime));
+            },
+        );
+    }
+}
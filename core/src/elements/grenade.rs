@@ -1,4 +1,8 @@
-use crate::prelude::*;
+use crate::{
+    damage::{ChainReactions, DamageEvent, Damageable},
+    feature_flags,
+    prelude::*,
+};
 use std::time::Duration;
 
 pub fn install(session: &mut CoreSession) {
@@ -13,14 +17,17 @@ pub fn install(session: &mut CoreSession) {
 #[ulid = "01GPRSBWQ3X0QJC37BDDQXDN84"]
 pub struct IdleGrenade;
 
-// #[derive(Clone, TypeUlid, Debug, Copy)]
-#[derive(Clone, TypeUlid, Debug)]
+/// The number of frames between a grenade being hit by an explosion and it exploding.
+pub const CHAIN_REACTION_DELAY_FRAMES: usize = 8;
+
+/// Component for lit grenades, which explode when their fuse [`StepTimer`] finishes.
+#[derive(Clone, TypeUlid, Debug, Copy)]
 #[ulid = "01GPY9N9CBR6EFJX0RS2H2K58J"]
 pub struct LitGrenade {
     /// The owner of the grenade.
     pub owner: Entity,
-    /// The amount of time left until the grenade explodes.
-    pub fuse_time: Timer,
+    /// What lit the grenade, credited with the kills of its explosion.
+    pub lit_by: DamageSource,
 }
 
 fn hydrate(
@@ -39,6 +46,7 @@ fn hydrate(
     mut item_grabs: CompMut<ItemGrab>,
     mut respawn_points: CompMut<DehydrateOutOfBounds>,
     mut spawner_manager: SpawnerManager,
+    mut damageables: CompMut<Damageable>,
 ) {
     let mut not_hydrated_bitset = hydrated.bitset().clone();
     not_hydrated_bitset.bit_not();
@@ -90,6 +98,12 @@ fn hydrate(
             element_handles.insert(entity, element_handle.clone());
             hydrated.insert(entity, MapElementHydrated);
             animated_sprites.insert(entity, default());
+            damageables.insert(
+                entity,
+                Damageable {
+                    size: Vec2::splat(*body_diameter),
+                },
+            );
             bodies.insert(
                 entity,
                 KinematicBody {
@@ -113,6 +127,8 @@ fn update_idle_grenades(
     mut commands: Commands,
     entities: Res<Entities>,
     items_used: Comp<ItemUsed>,
+    damage_events: Res<EventChannel<DamageEvent>>,
+    mut chain_reactions: ResMut<ChainReactions>,
     element_handles: Comp<ElementHandle>,
     element_assets: BevyAssets<ElementMeta>,
     mut audio_events: ResMut<AudioEvents>,
@@ -136,7 +152,18 @@ fn update_idle_grenades(
         };
         let fuse_time = *fuse_time;
 
-        if items_used.get(entity).is_some() {
+        // Grenades hit by an explosion are lit by whoever caused the explosion
+        let damaged_by = damage_events
+            .damaged(entity)
+            .map(|damaged| damaged.owner.unwrap_or(entity));
+        let damage_source = damage_events.damaged(entity).map(|damaged| damaged.source);
+
+        // And explode shortly after
+        if damaged_by.is_some() && !chain_reactions.is_scheduled(entity) {
+            chain_reactions.schedule(entity, CHAIN_REACTION_DELAY_FRAMES);
+        }
+
+        if items_used.get(entity).is_some() || damaged_by.is_some() {
             // Animate Grenade
             let animated_sprite = animated_sprites.get_mut(entity).unwrap();
             animated_sprite.frames = Arc::from([3, 4, 5]);
@@ -149,19 +176,35 @@ fn update_idle_grenades(
             commands.add(
                 move |mut lit: CompMut<LitGrenade>,
                       mut idle: CompMut<IdleGrenade>,
-                      mut items_used: CompMut<ItemUsed>| {
+                      mut items_used: CompMut<ItemUsed>,
+                      mut timers: CompMut<StepTimer>,
+                      player_indexes: Comp<PlayerIdx>,
+                      mut match_stats: ResMut<MatchStats>| {
                     idle.remove(entity);
 
+                    if let Some(idx) = items_used
+                        .get(entity)
+                        .and_then(|x| player_indexes.get(x.owner))
+                    {
+                        match_stats.players[idx.0].grenades_lit += 1;
+                    }
+
                     lit.insert(
                         entity,
                         LitGrenade {
-                            owner: items_used.get(entity).unwrap().owner,
-                            fuse_time: Timer::new(
-                                Duration::from_secs_f32(fuse_time),
-                                TimerMode::Once,
-                            ),
+                            owner: items_used
+                                .get(entity)
+                                .map(|x| x.owner)
+                                .or(damaged_by)
+                                .unwrap(),
+                            lit_by: items_used
+                                .get(entity)
+                                .map(|x| DamageSource::Player(x.owner))
+                                .or(damage_source)
+                                .unwrap_or_default(),
                         },
                     );
+                    timers.insert(entity, StepTimer::once(Duration::from_secs_f32(fuse_time)));
 
                     items_used.remove(entity);
                 },
@@ -171,7 +214,7 @@ fn update_idle_grenades(
 }
 
 fn update_lit_grenades(
-    time: Res<Time>,
+    fuse_timers: Comp<StepTimer>,
     mut commands: Commands,
     entities: Res<Entities>,
     transforms: CompMut<Transform>,
@@ -185,7 +228,14 @@ fn update_lit_grenades(
     mut player_layers: CompMut<PlayerLayers>,
     mut hydrated: CompMut<MapElementHydrated>,
     mut trauma_events: ResMut<CameraTraumaEvents>,
+    damage_events: Res<EventChannel<DamageEvent>>,
+    mut chain_reactions: ResMut<ChainReactions>,
+    match_settings: Res<MatchSettings>,
+    collision_world: CollisionWorld,
+    mut bodies: CompMut<KinematicBody>,
 ) {
+    let sticky = match_settings.has_feature_flag(feature_flags::STICKY_GRENADES);
+
     for (entity, (grenade, element_handle, spawner)) in
         entities.iter_with((&mut lit_grenades, &element_handles, &spawners))
     {
@@ -208,7 +258,10 @@ fn update_lit_grenades(
             unreachable!();
         };
 
-        grenade.fuse_time.tick(time.delta());
+        // Explode shortly after being hit by another explosion
+        if damage_events.damaged(entity).is_some() && !chain_reactions.is_scheduled(entity) {
+            chain_reactions.schedule(entity, CHAIN_REACTION_DELAY_FRAMES);
+        }
 
         if !emote_regions.contains(entity) {
             emote_regions.insert(
@@ -236,13 +289,37 @@ fn update_lit_grenades(
 
             emote_region.active = false;
 
+            // Let sticky grenades fall again once they are thrown
+            if sticky {
+                bodies.get_mut(entity).unwrap().has_mass = true;
+            }
+
         // If the item is not being held
         } else {
             emote_region.active = true;
+
+            // Stick the grenade to any wall that it touches
+            if sticky {
+                let body = bodies.get_mut(entity).unwrap();
+                let transform = *transforms.get(entity).unwrap();
+                let rect = body.bounding_box(transform);
+                // Look just past the sides of the body, because `solid_at` doesn't count points on
+                // the edge of a tile.
+                let border = 1.0;
+                let y = transform.translation.y;
+                if collision_world.solid_at(vec2(rect.min.x - border, y))
+                    || collision_world.solid_at(vec2(rect.max.x + border, y))
+                {
+                    body.velocity = Vec2::ZERO;
+                    body.angular_velocity = 0.0;
+                    body.has_mass = false;
+                }
+            }
         }
 
         // If it's time to explode
-        if grenade.fuse_time.finished() {
+        let fuse_finished = fuse_timers.get(entity).map_or(false, |x| x.finished());
+        if fuse_finished || chain_reactions.is_due(entity) {
             audio_events.play(explosion_sound.clone(), *explosion_volume);
 
             trauma_events.send(5.0);
@@ -253,56 +330,74 @@ fn update_lit_grenades(
             explosion_transform.translation.z = -10.0; // On top of almost everything
             explosion_transform.rotation = Quat::IDENTITY;
 
-            // Clone types for move into closure
-            let damage_region_size = *damage_region_size;
-            let damage_region_lifetime = *damage_region_lifetime;
-            let explosion_lifetime = *explosion_lifetime;
-            let explosion_atlas = explosion_atlas.clone();
-            let explosion_fps = *explosion_fps;
-            let explosion_frames = *explosion_frames;
-            commands.add(
-                move |mut entities: ResMut<Entities>,
-                      mut transforms: CompMut<Transform>,
-                      mut damage_regions: CompMut<DamageRegion>,
-                      mut lifetimes: CompMut<Lifetime>,
-                      mut sprites: CompMut<AtlasSprite>,
-                      mut animated_sprites: CompMut<AnimatedSprite>| {
-                    // Despawn the grenade
-                    entities.kill(entity);
-
-                    // Spawn the damage region
-                    let ent = entities.create();
-                    transforms.insert(ent, explosion_transform);
-                    damage_regions.insert(
-                        ent,
-                        DamageRegion {
-                            size: damage_region_size,
-                        },
-                    );
-                    lifetimes.insert(ent, Lifetime::new(damage_region_lifetime));
-
-                    // Spawn the explosion animation
-                    let ent = entities.create();
-                    transforms.insert(ent, explosion_transform);
-                    sprites.insert(
-                        ent,
-                        AtlasSprite {
-                            atlas: explosion_atlas.clone(),
-                            ..default()
-                        },
-                    );
-                    animated_sprites.insert(
-                        ent,
-                        AnimatedSprite {
-                            frames: (0..explosion_frames).collect(),
-                            fps: explosion_fps,
-                            repeat: false,
-                            ..default()
-                        },
-                    );
-                    lifetimes.insert(ent, Lifetime::new(explosion_lifetime));
-                },
-            );
+            commands.add(move |mut entities: ResMut<Entities>| {
+                // Despawn the grenade
+                entities.kill(entity);
+            });
+            commands.add(spawn_explosion(
+                explosion_transform,
+                grenade.lit_by,
+                *damage_region_size,
+                *damage_region_lifetime,
+                explosion_atlas.clone(),
+                *explosion_frames,
+                *explosion_fps,
+                *explosion_lifetime,
+            ));
         }
     }
 }
+
+/// Command that spawns the damage region and the animation of an explosion, such as the one of a
+/// grenade.
+pub fn spawn_explosion(
+    explosion_transform: Transform,
+    source: DamageSource,
+    damage_region_size: Vec2,
+    damage_region_lifetime: f32,
+    explosion_atlas: Handle<Atlas>,
+    explosion_frames: usize,
+    explosion_fps: f32,
+    explosion_lifetime: f32,
+) -> System {
+    (move |mut entities: ResMut<Entities>,
+           mut transforms: CompMut<Transform>,
+           mut damage_regions: CompMut<DamageRegion>,
+           mut lifetimes: CompMut<Lifetime>,
+           mut sprites: CompMut<AtlasSprite>,
+           mut animated_sprites: CompMut<AnimatedSprite>| {
+        // Spawn the damage region
+        let ent = entities.create();
+        transforms.insert(ent, explosion_transform);
+        damage_regions.insert(
+            ent,
+            DamageRegion {
+                size: damage_region_size,
+                source,
+            },
+        );
+        lifetimes.insert(ent, Lifetime::new(damage_region_lifetime));
+
+        // Spawn the explosion animation
+        let ent = entities.create();
+        transforms.insert(ent, explosion_transform);
+        sprites.insert(
+            ent,
+            AtlasSprite {
+                atlas: explosion_atlas.clone(),
+                ..default()
+            },
+        );
+        animated_sprites.insert(
+            ent,
+            AnimatedSprite {
+                frames: (0..explosion_frames).collect(),
+                fps: explosion_fps,
+                repeat: false,
+                ..default()
+            },
+        );
+        lifetimes.insert(ent, Lifetime::new(explosion_lifetime));
+    })
+    .system()
+}
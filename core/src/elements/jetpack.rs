@@ -0,0 +1,275 @@
+//! Jetpack.
+//!
+//! While the player holding the jetpack keeps the jump button pressed in the air, the jetpack
+//! thrusts them upward and burns fuel. Once it is out of fuel, the jetpack falls off of the player.
+//! Jetpacks are full of fuel, so they explode when they are caught in an explosion, empty or not.
+
+use crate::{
+    damage::{ChainReactions, DamageEvent, Damageable},
+    elements::grenade::{spawn_explosion, CHAIN_REACTION_DELAY_FRAMES},
+    prelude::*,
+};
+
+pub fn install(session: &mut CoreSession) {
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PreUpdate, hydrate)
+        .add_system_to_stage(CoreStage::PostUpdate, update);
+}
+
+#[derive(Clone, Debug, TypeUlid, Default)]
+#[ulid = "01H31C2W5HM9QZ4TXR7KNAV0BE"]
+pub struct Jetpack {
+    /// The thrust time left until the jetpack is out of fuel.
+    pub fuel: Timer,
+    /// The number of frames the jetpack has been thrusting for.
+    pub thrust_frames: usize,
+}
+
+fn hydrate(
+    game_meta: Res<CoreMetaArc>,
+    mut entities: ResMut<Entities>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    mut element_handles: CompMut<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut jetpacks: CompMut<Jetpack>,
+    mut atlas_sprites: CompMut<AtlasSprite>,
+    mut bodies: CompMut<KinematicBody>,
+    mut transforms: CompMut<Transform>,
+    mut items: CompMut<Item>,
+    mut item_throws: CompMut<ItemThrow>,
+    mut item_grabs: CompMut<ItemGrab>,
+    mut damageables: CompMut<Damageable>,
+    mut respawn_points: CompMut<DehydrateOutOfBounds>,
+    mut spawner_manager: SpawnerManager,
+) {
+    let mut not_hydrated_bitset = hydrated.bitset().clone();
+    not_hydrated_bitset.bit_not();
+    not_hydrated_bitset.bit_and(element_handles.bitset());
+
+    let spawner_entities = entities
+        .iter_with_bitset(&not_hydrated_bitset)
+        .collect::<Vec<_>>();
+
+    for spawner_ent in spawner_entities {
+        let transform = *transforms.get(spawner_ent).unwrap();
+        let element_handle = element_handles.get(spawner_ent).unwrap();
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+
+        if let BuiltinElementKind::Jetpack {
+            atlas,
+            body_size,
+            grab_offset,
+            fin_anim,
+            throw_velocity,
+            angular_velocity,
+            fuel,
+            ..
+        } = &element_meta.builtin
+        {
+            hydrated.insert(spawner_ent, MapElementHydrated);
+
+            let entity = entities.create();
+            items.insert(entity, Item);
+            item_throws.insert(
+                entity,
+                ItemThrow::strength(*throw_velocity).with_spin(*angular_velocity),
+            );
+            item_grabs.insert(
+                entity,
+                ItemGrab {
+                    fin_anim: *fin_anim,
+                    sync_animation: false,
+                    grab_offset: *grab_offset,
+                },
+            );
+            jetpacks.insert(
+                entity,
+                Jetpack {
+                    fuel: Timer::new(*fuel, TimerMode::Once),
+                    thrust_frames: 0,
+                },
+            );
+            atlas_sprites.insert(entity, AtlasSprite::new(atlas.clone()));
+            respawn_points.insert(entity, DehydrateOutOfBounds(spawner_ent));
+            transforms.insert(entity, transform);
+            element_handles.insert(entity, element_handle.clone());
+            hydrated.insert(entity, MapElementHydrated);
+            damageables.insert(entity, Damageable { size: *body_size });
+            bodies.insert(
+                entity,
+                KinematicBody {
+                    shape: ColliderShape::Rectangle { size: *body_size },
+                    has_mass: true,
+                    has_friction: true,
+                    can_rotate: true,
+                    gravity: game_meta.physics.gravity,
+                    ..default()
+                },
+            );
+            spawner_manager.create_spawner(spawner_ent, vec![entity])
+        }
+    }
+}
+
+/// Thrust the players holding jetpacks upward, drop the jetpacks that are out of fuel, and blow up
+/// the jetpacks that are caught in explosions.
+fn update(
+    entities: Res<Entities>,
+    mut commands: Commands,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut jetpacks: CompMut<Jetpack>,
+    player_inputs: Res<PlayerInputs>,
+    player_indexes: Comp<PlayerIdx>,
+    player_inventories: PlayerInventories,
+    mut bodies: CompMut<KinematicBody>,
+    transforms: Comp<Transform>,
+    sprites: Comp<AtlasSprite>,
+    spawners: Comp<DehydrateOutOfBounds>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    damage_events: Res<EventChannel<DamageEvent>>,
+    mut chain_reactions: ResMut<ChainReactions>,
+    mut audio_events: ResMut<AudioEvents>,
+    mut trauma_events: ResMut<CameraTraumaEvents>,
+    time: Res<Time>,
+) {
+    for (entity, (jetpack, element_handle, spawner)) in
+        entities.iter_with((&mut jetpacks, &element_handles, &spawners))
+    {
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+        let BuiltinElementKind::Jetpack {
+            thrust,
+            max_rise_speed,
+            flame_atlas,
+            flame_frames,
+            flame_fps,
+            flame_lifetime,
+            flame_offset,
+            flame_interval,
+            empty_sound,
+            empty_sound_volume,
+            damage_region_size,
+            damage_region_lifetime,
+            explosion_sound,
+            explosion_volume,
+            explosion_lifetime,
+            explosion_atlas,
+            explosion_fps,
+            explosion_frames,
+            ..
+        } = &element_meta.builtin
+        else {
+            unreachable!();
+        };
+
+        // Explode shortly after being hit by an explosion
+        if damage_events.damaged(entity).is_some() && !chain_reactions.is_scheduled(entity) {
+            chain_reactions.schedule(entity, CHAIN_REACTION_DELAY_FRAMES);
+        }
+        if chain_reactions.is_due(entity) {
+            audio_events.play(explosion_sound.clone(), *explosion_volume);
+            trauma_events.send(5.0);
+
+            // Cause the item to respawn by un-hydrating it's spawner.
+            hydrated.remove(**spawner);
+            let mut explosion_transform = *transforms.get(entity).unwrap();
+            explosion_transform.translation.z = -10.0; // On top of almost everything
+            explosion_transform.rotation = Quat::IDENTITY;
+
+            commands.add(move |mut entities: ResMut<Entities>| {
+                // Despawn the jetpack
+                entities.kill(entity);
+            });
+            commands.add(spawn_explosion(
+                explosion_transform,
+                DamageSource::Environment,
+                *damage_region_size,
+                *damage_region_lifetime,
+                explosion_atlas.clone(),
+                *explosion_frames,
+                *explosion_fps,
+                *explosion_lifetime,
+            ));
+            continue;
+        }
+
+        // If the item is being held
+        let Some(Inv { player, .. }) = player_inventories
+            .iter()
+            .find_map(|x| x.filter(|x| x.inventory == entity))
+        else {
+            continue;
+        };
+        let Some(player_idx) = player_indexes.get(player) else {
+            continue;
+        };
+        let control = &player_inputs.players[player_idx.0].control;
+        let Some(body) = bodies.get_mut(player) else {
+            continue;
+        };
+
+        // Thrust while the jump button is held in the air
+        if !control.jump_pressed || body.is_on_ground || jetpack.fuel.finished() {
+            continue;
+        }
+        body.velocity.y = (body.velocity.y + *thrust).min(*max_rise_speed);
+        jetpack.fuel.tick(time.delta());
+        jetpack.thrust_frames += 1;
+
+        // Blow flames out of the bottom of the jetpack
+        if *flame_interval > 0 && jetpack.thrust_frames % *flame_interval == 1 {
+            let flip_factor = if sprites.get(player).map_or(false, |x| x.flip_x) {
+                -1.0
+            } else {
+                1.0
+            };
+            let mut flame_transform = *transforms.get(entity).unwrap();
+            flame_transform.translation.x += flame_offset.x * flip_factor;
+            flame_transform.translation.y += flame_offset.y;
+            flame_transform.translation.z -= 0.1;
+            flame_transform.rotation = Quat::IDENTITY;
+            let atlas = flame_atlas.clone();
+            let frames = *flame_frames;
+            let fps = *flame_fps;
+            let lifetime = *flame_lifetime;
+            commands.add(
+                move |mut entities: ResMut<Entities>,
+                      mut transforms: CompMut<Transform>,
+                      mut lifetimes: CompMut<Lifetime>,
+                      mut sprites: CompMut<AtlasSprite>,
+                      mut animated_sprites: CompMut<AnimatedSprite>| {
+                    let ent = entities.create();
+                    transforms.insert(ent, flame_transform);
+                    sprites.insert(
+                        ent,
+                        AtlasSprite {
+                            atlas: atlas.clone(),
+                            ..default()
+                        },
+                    );
+                    animated_sprites.insert(
+                        ent,
+                        AnimatedSprite {
+                            frames: (0..frames).collect(),
+                            fps,
+                            repeat: false,
+                            ..default()
+                        },
+                    );
+                    lifetimes.insert(ent, Lifetime::new(lifetime));
+                },
+            );
+        }
+
+        // Fall off of the player once out of fuel
+        if jetpack.fuel.finished() {
+            audio_events.play(empty_sound.clone(), *empty_sound_volume);
+            commands.add(PlayerCommand::set_inventory(player, None));
+        }
+    }
+}
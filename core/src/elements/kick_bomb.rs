@@ -12,11 +12,15 @@ pub fn install(session: &mut CoreSession) {
 #[ulid = "01GQ0ZWBNA8HZRXYKZXCT05CXT"]
 pub struct IdleKickBomb;
 
+/// Component for lit kick bombs.
+///
+/// Lit kick bombs explode when their fuse [`StepTimer`] finishes, and may only be kicked once
+/// their arming [`Cooldown`] is ready.
 #[derive(Clone, TypeUlid, Debug)]
 #[ulid = "01GQ0ZWFYZSHJPESPY9QPSTARR"]
 pub struct LitKickBomb {
-    arm_delay: Timer,
-    fuse_time: Timer,
+    /// The player that lit the bomb or kicked it last, credited with the kills of its explosion.
+    pub lit_by: DamageSource,
 }
 
 fn hydrate(
@@ -135,23 +139,22 @@ fn update_idle_kick_bombs(
         let arm_delay = *arm_delay;
         let fuse_time = *fuse_time;
 
-        if items_used.get(entity).is_some() {
+        if let Some(item_used) = items_used.remove(entity) {
             audio_events.play(fuse_sound.clone(), *fuse_sound_volume);
-            items_used.remove(entity);
+            let lit_by = DamageSource::Player(item_used.owner);
             let animated_sprite = animated_sprites.get_mut(entity).unwrap();
             animated_sprite.frames = Arc::from([3, 4, 5]);
             animated_sprite.repeat = true;
             animated_sprite.fps = 8.0;
             commands.add(
-                move |mut idle: CompMut<IdleKickBomb>, mut lit: CompMut<LitKickBomb>| {
+                move |mut idle: CompMut<IdleKickBomb>,
+                      mut lit: CompMut<LitKickBomb>,
+                      mut timers: CompMut<StepTimer>,
+                      mut cooldowns: CompMut<Cooldown>| {
                     idle.remove(entity);
-                    lit.insert(
-                        entity,
-                        LitKickBomb {
-                            arm_delay: Timer::new(arm_delay, TimerMode::Once),
-                            fuse_time: Timer::new(fuse_time, TimerMode::Once),
-                        },
-                    );
+                    lit.insert(entity, LitKickBomb { lit_by });
+                    timers.insert(entity, StepTimer::once(fuse_time));
+                    cooldowns.insert(entity, Cooldown::new(arm_delay));
                 },
             );
         }
@@ -176,7 +179,8 @@ fn update_lit_kick_bombs(
     player_inventories: PlayerInventories,
     mut transforms: CompMut<Transform>,
     mut commands: Commands,
-    time: Res<Time>,
+    fuse_timers: Comp<StepTimer>,
+    arm_delays: Comp<Cooldown>,
     spawners: Comp<DehydrateOutOfBounds>,
     invincibles: CompMut<Invincibility>,
 ) {
@@ -204,9 +208,6 @@ fn update_lit_kick_bombs(
             unreachable!();
         };
 
-        kick_bomb.fuse_time.tick(time.delta());
-        kick_bomb.arm_delay.tick(time.delta());
-
         let mut should_explode = false;
         // If the item is being held
         if let Some(inventory) = player_inventories
@@ -245,6 +246,7 @@ fn update_lit_kick_bombs(
             let player_translation = transforms.get(player_entity).unwrap().translation;
 
             let player_standing_left = player_translation.x <= translation.x;
+            kick_bomb.lit_by = DamageSource::Player(player_entity);
 
             if body.velocity.x == 0.0 {
                 body.velocity = *kick_velocity;
@@ -257,13 +259,14 @@ fn update_lit_kick_bombs(
             } else if !player_standing_left && player_sprite.flip_x {
                 body.velocity.x = -kick_velocity.x;
                 body.velocity.y = kick_velocity.y;
-            } else if kick_bomb.arm_delay.finished() {
+            } else if arm_delays.get(entity).map_or(true, |x| x.is_ready()) {
                 should_explode = true;
             }
         }
 
         // If it's time to explode
-        if kick_bomb.fuse_time.finished() || should_explode {
+        let fuse_finished = fuse_timers.get(entity).map_or(false, |x| x.finished());
+        if fuse_finished || should_explode {
             audio_events.play(explosion_sound.clone(), *explosion_volume);
 
             trauma_events.send(7.5);
@@ -281,6 +284,7 @@ fn update_lit_kick_bombs(
             let explosion_atlas = explosion_atlas.clone();
             let explosion_fps = *explosion_fps;
             let explosion_frames = *explosion_frames;
+            let source = kick_bomb.lit_by;
             commands.add(
                 move |mut entities: ResMut<Entities>,
                       mut transforms: CompMut<Transform>,
@@ -298,6 +302,7 @@ fn update_lit_kick_bombs(
                         ent,
                         DamageRegion {
                             size: damage_region_size,
+                            source,
                         },
                     );
                     lifetimes.insert(ent, Lifetime::new(damage_region_lifetime));
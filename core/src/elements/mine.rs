@@ -1,4 +1,4 @@
-use crate::prelude::*;
+use crate::{elements::grenade::spawn_explosion, prelude::*};
 use std::time::Duration;
 
 pub fn install(session: &mut CoreSession) {
@@ -18,6 +18,8 @@ pub struct IdleMine;
 pub struct ThrownMine {
     // The mine won't explode until this timer finishes.
     arm_delay: Timer,
+    /// The player who threw the mine, credited with the kills of its explosion.
+    owner: Entity,
 }
 
 fn hydrate(
@@ -137,6 +139,7 @@ fn update_idle_mines(
                                     Duration::from_secs_f32(arm_delay),
                                     TimerMode::Once,
                                 ),
+                                owner: player,
                             },
                         );
                     },
@@ -146,6 +149,8 @@ fn update_idle_mines(
     }
 }
 
+/// Arm the thrown mines once their delay is over, and blow up the armed mines when a player steps
+/// into their trigger region, including the player who threw them.
 fn update_thrown_mines(
     entities: Res<Entities>,
     element_handles: Comp<ElementHandle>,
@@ -156,16 +161,18 @@ fn update_thrown_mines(
     mut animated_sprites: CompMut<AnimatedSprite>,
     mut hydrated: CompMut<MapElementHydrated>,
     player_indexes: Comp<PlayerIdx>,
+    players_killed: Comp<PlayerKilled>,
+    bodies: Comp<KinematicBody>,
     mut commands: Commands,
-    collision_world: CollisionWorld,
     transforms: Comp<Transform>,
     time: Res<Time>,
     spawners: Comp<DehydrateOutOfBounds>,
-    invincibles: CompMut<Invincibility>,
+    invincibles: Comp<Invincibility>,
 ) {
-    let players = entities
-        .iter_with(&player_indexes)
-        .map(|x| x.0)
+    let player_rects = entities
+        .iter_with((&player_indexes, &transforms, &bodies))
+        .filter(|(ent, _)| !players_killed.contains(*ent) && !invincibles.contains(*ent))
+        .map(|(_ent, (_idx, transform, body))| body.bounding_box(*transform))
         .collect::<Vec<_>>();
     for (entity, (thrown_mine, element_handle, sprite, spawner)) in entities.iter_with((
         &mut thrown_mines,
@@ -186,12 +193,21 @@ fn update_thrown_mines(
             armed_frames,
             armed_fps,
             damage_region_size,
-            damage_region_lifetime, explosion_volume, arm_sound_volume, explosion_lifetime, .. } = &element_meta.builtin else {
+            damage_region_lifetime,
+            explosion_volume,
+            arm_sound_volume,
+            explosion_lifetime,
+            body_size,
+            trigger_size,
+            ..
+        } = &element_meta.builtin
+        else {
             unreachable!();
         };
 
         thrown_mine.arm_delay.tick(time.delta());
 
+        // Blink once armed
         if thrown_mine.arm_delay.just_finished() {
             audio_events.play(arm_sound.clone(), *arm_sound_volume);
 
@@ -200,83 +216,44 @@ fn update_thrown_mines(
             sprite.repeat = true;
         }
 
-        let colliding_with_players = collision_world
-            .actor_collisions_filtered(entity, |e| {
-                players.contains(&e) && invincibles.get(e).is_none()
-            })
-            .into_iter()
-            .collect::<Vec<_>>();
-
-        if !colliding_with_players.is_empty() && thrown_mine.arm_delay.finished() {
-            let mine_transform = *transforms.get(entity).unwrap();
-
-            trauma_events.send(6.0);
-
-            for player in &colliding_with_players {
-                commands.add(PlayerCommand::kill(
-                    *player,
-                    Some(mine_transform.translation.xy()),
-                ));
-            }
-
-            audio_events.play(explosion_sound.clone(), *explosion_volume);
-
-            hydrated.remove(**spawner);
+        if !thrown_mine.arm_delay.finished() {
+            continue;
+        }
 
-            // Clone types for move into closure
-            let damage_region_size = *damage_region_size;
-            let damage_region_lifetime = *damage_region_lifetime;
-            let explosion_lifetime = *explosion_lifetime;
-            let explosion_atlas = explosion_atlas.clone();
-            let explosion_fps = *explosion_fps;
-            let explosion_frames = *explosion_frames;
-            commands.add(
-                move |mut entities: ResMut<Entities>,
-                      mut transforms: CompMut<Transform>,
-                      mut damage_regions: CompMut<DamageRegion>,
-                      mut lifetimes: CompMut<Lifetime>,
-                      mut sprites: CompMut<AtlasSprite>,
-                      mut animated_sprites: CompMut<AnimatedSprite>| {
-                    let mut explosion_transform = mine_transform;
-                    explosion_transform.translation.z = -10.0; // On top of almost everything
-                    explosion_transform.rotation = Quat::IDENTITY;
+        let mine_transform = *transforms.get(entity).unwrap();
+        let trigger_size = trigger_size.unwrap_or(*body_size);
+        let trigger_rect = Rect::new(
+            mine_transform.translation.x,
+            mine_transform.translation.y,
+            trigger_size.x,
+            trigger_size.y,
+        );
+        if !player_rects.iter().any(|x| x.overlaps(&trigger_rect)) {
+            continue;
+        }
 
-                    // Despawn the grenade
-                    entities.kill(entity);
+        trauma_events.send(6.0);
+        audio_events.play(explosion_sound.clone(), *explosion_volume);
 
-                    // Spawn the damage region
-                    let damage_ent = entities.create();
-                    transforms.insert(damage_ent, explosion_transform);
-                    damage_regions.insert(
-                        damage_ent,
-                        DamageRegion {
-                            size: damage_region_size,
-                        },
-                    );
-                    lifetimes.insert(damage_ent, Lifetime::new(damage_region_lifetime));
+        // Cause the item to respawn by un-hydrating it's spawner.
+        hydrated.remove(**spawner);
+        let mut explosion_transform = mine_transform;
+        explosion_transform.translation.z = -10.0; // On top of almost everything
+        explosion_transform.rotation = Quat::IDENTITY;
 
-                    // Spawn the explosion animation
-                    let ent = entities.create();
-                    transforms.insert(ent, explosion_transform);
-                    sprites.insert(
-                        ent,
-                        AtlasSprite {
-                            atlas: explosion_atlas.clone(),
-                            ..default()
-                        },
-                    );
-                    animated_sprites.insert(
-                        ent,
-                        AnimatedSprite {
-                            frames: (0..explosion_frames).collect(),
-                            fps: explosion_fps,
-                            repeat: false,
-                            ..default()
-                        },
-                    );
-                    lifetimes.insert(ent, Lifetime::new(explosion_lifetime));
-                },
-            );
-        }
+        commands.add(move |mut entities: ResMut<Entities>| {
+            // Despawn the mine
+            entities.kill(entity);
+        });
+        commands.add(spawn_explosion(
+            explosion_transform,
+            DamageSource::Player(thrown_mine.owner),
+            *damage_region_size,
+            *damage_region_lifetime,
+            explosion_atlas.clone(),
+            *explosion_frames,
+            *explosion_fps,
+            *explosion_lifetime,
+        ));
     }
 }
@@ -0,0 +1,270 @@
+//! Rideable minecarts.
+//!
+//! Players hop into a minecart by landing in it. The cart then rolls along rail tiles, keeping its
+//! momentum while the rider pushes it left and right, and runs over the opponents in its way when
+//! it is going fast enough. Explosions knock the cart off of the rails, throwing the rider out, and
+//! it can't be ridden again until it lands back on a rail.
+
+use crate::{
+    damage::{DamageEvent, Damageable},
+    prelude::*,
+};
+
+pub fn install(session: &mut CoreSession) {
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PreUpdate, hydrate)
+        .add_system_to_stage(CoreStage::PostUpdate, update)
+        .add_system_to_stage(CoreStage::Last, carry_riders);
+}
+
+#[derive(Clone, Debug, TypeUlid, Default)]
+#[ulid = "01H2RB7A5QDM1W9EKX3JHNVZ6T"]
+pub struct Minecart {
+    /// The player riding the cart.
+    pub rider: Option<Entity>,
+    /// Whether the cart has been knocked off of the rails.
+    pub derailed: bool,
+}
+
+fn hydrate(
+    game_meta: Res<CoreMetaArc>,
+    mut entities: ResMut<Entities>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    mut element_handles: CompMut<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut minecarts: CompMut<Minecart>,
+    mut atlas_sprites: CompMut<AtlasSprite>,
+    mut bodies: CompMut<KinematicBody>,
+    mut transforms: CompMut<Transform>,
+    mut damageables: CompMut<Damageable>,
+    mut respawn_points: CompMut<DehydrateOutOfBounds>,
+    mut spawner_manager: SpawnerManager,
+) {
+    let mut not_hydrated_bitset = hydrated.bitset().clone();
+    not_hydrated_bitset.bit_not();
+    not_hydrated_bitset.bit_and(element_handles.bitset());
+
+    let spawner_entities = entities
+        .iter_with_bitset(&not_hydrated_bitset)
+        .collect::<Vec<_>>();
+
+    for spawner_ent in spawner_entities {
+        let transform = *transforms.get(spawner_ent).unwrap();
+        let element_handle = element_handles.get(spawner_ent).unwrap();
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+
+        if let BuiltinElementKind::Minecart {
+            atlas, body_size, ..
+        } = &element_meta.builtin
+        {
+            hydrated.insert(spawner_ent, MapElementHydrated);
+
+            let entity = entities.create();
+            minecarts.insert(entity, Minecart::default());
+            atlas_sprites.insert(entity, AtlasSprite::new(atlas.clone()));
+            respawn_points.insert(entity, DehydrateOutOfBounds(spawner_ent));
+            transforms.insert(entity, transform);
+            element_handles.insert(entity, element_handle.clone());
+            hydrated.insert(entity, MapElementHydrated);
+            damageables.insert(entity, Damageable { size: *body_size });
+            bodies.insert(
+                entity,
+                KinematicBody {
+                    shape: ColliderShape::Rectangle { size: *body_size },
+                    has_mass: true,
+                    gravity: game_meta.physics.gravity,
+                    ..default()
+                },
+            );
+            spawner_manager.create_spawner(spawner_ent, vec![entity])
+        }
+    }
+}
+
+/// Let players into the carts, roll the carts along the rails, and knock them off of the rails when
+/// they are caught in explosions.
+fn update(
+    entities: Res<Entities>,
+    mut commands: Commands,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    match_settings: Res<MatchSettings>,
+    player_inputs: Res<PlayerInputs>,
+    damage_events: Res<EventChannel<DamageEvent>>,
+    collision_world: CollisionWorld,
+    mut minecarts: CompMut<Minecart>,
+    mut riding: CompMut<Riding>,
+    mut bodies: CompMut<KinematicBody>,
+    mut sprites: CompMut<AtlasSprite>,
+    transforms: Comp<Transform>,
+    player_indexes: Comp<PlayerIdx>,
+    players_killed: Comp<PlayerKilled>,
+    invincibles: Comp<Invincibility>,
+    mut audio_events: ResMut<AudioEvents>,
+) {
+    for (entity, (minecart, element_handle, transform)) in
+        entities.iter_with((&mut minecarts, &element_handles, &transforms))
+    {
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+        let BuiltinElementKind::Minecart {
+            body_size,
+            acceleration,
+            max_speed,
+            rolling_friction,
+            run_over_speed,
+            derail_velocity,
+            derail_sound,
+            derail_volume,
+            ..
+        } = &element_meta.builtin
+        else {
+            unreachable!();
+        };
+        let pos = transform.translation.truncate();
+
+        // Forget about riders that have jumped out or died
+        if let Some(rider) = minecart.rider {
+            let is_riding = riding.get(rider).map(|x| x.vehicle) == Some(entity);
+            if !is_riding || players_killed.contains(rider) {
+                riding.remove(rider);
+                minecart.rider = None;
+            }
+        }
+
+        // Knock the cart off of the rails when it is caught in an explosion
+        if let Some(event) = damage_events.damaged(entity) {
+            if !minecart.derailed {
+                minecart.derailed = true;
+                audio_events.play(derail_sound.clone(), *derail_volume);
+
+                let direction = if pos.x < event.from.x { -1.0 } else { 1.0 };
+                let velocity = vec2(derail_velocity.x * direction, derail_velocity.y);
+                bodies.get_mut(entity).unwrap().velocity = velocity;
+                if let Some(rider) = minecart.rider.take() {
+                    riding.remove(rider);
+                    if let Some(body) = bodies.get_mut(rider) {
+                        body.velocity = velocity;
+                    }
+                }
+            }
+        }
+
+        let collisions = collision_world.actor_collisions(entity);
+        let body = bodies.get(entity).unwrap();
+        let rail_pos = pos - vec2(0.0, body_size.y / 2.0 + 1.0);
+        let is_on_rail = body.is_on_ground
+            && collision_world.tile_collision_point(rail_pos) == TileCollisionKind::Rail;
+
+        // Put the cart back on the rails once it lands on them
+        if minecart.derailed && is_on_rail && body.velocity.y <= 0.0 {
+            minecart.derailed = false;
+        }
+
+        // Let in the first player that lands in the cart
+        if minecart.rider.is_none() && !minecart.derailed {
+            let rider = collisions.iter().copied().find(|ent| {
+                player_indexes.contains(*ent)
+                    && !players_killed.contains(*ent)
+                    && !riding.contains(*ent)
+                    && transforms
+                        .get(*ent)
+                        .map_or(false, |x| x.translation.y > pos.y)
+                    && bodies.get(*ent).map_or(false, |x| x.velocity.y < 0.0)
+            });
+            if let Some(rider) = rider {
+                minecart.rider = Some(rider);
+                riding.insert(rider, Riding { vehicle: entity });
+            }
+        }
+
+        let body = bodies.get_mut(entity).unwrap();
+        if is_on_rail && !minecart.derailed {
+            // Push the cart in the direction the rider is holding
+            if let Some(player_idx) = minecart.rider.and_then(|x| player_indexes.get(x)) {
+                let control = &player_inputs.players[player_idx.0].control;
+                body.velocity.x = (body.velocity.x + control.move_direction.x * *acceleration)
+                    .clamp(-*max_speed, *max_speed);
+            }
+
+            // Slowly roll to a stop
+            if body.velocity.x.is_sign_positive() {
+                body.velocity.x = (body.velocity.x - *rolling_friction).max(0.0);
+            } else {
+                body.velocity.x = (body.velocity.x + *rolling_friction).min(0.0);
+            }
+        } else if body.is_on_ground {
+            // Carts don't roll off of the rails
+            body.velocity.x = 0.0;
+        }
+
+        let velocity = body.velocity;
+        if velocity.x != 0.0 {
+            if let Some(sprite) = sprites.get_mut(entity) {
+                sprite.flip_x = velocity.x < 0.0;
+            }
+        }
+
+        // Run over the players in the way of the cart
+        if velocity.x.abs() < *run_over_speed {
+            continue;
+        }
+        for player in collisions {
+            if !player_indexes.contains(player)
+                || Some(player) == minecart.rider
+                || players_killed.contains(player)
+                || invincibles.contains(player)
+                || riding.contains(player)
+                || minecart.rider.map_or(false, |rider| {
+                    is_protected_teammate(&match_settings, &player_indexes, rider, player)
+                })
+            {
+                continue;
+            }
+            let source = minecart
+                .rider
+                .map_or(DamageSource::Environment, DamageSource::Player);
+            commands.add(PlayerCommand::kill_by(player, Some(pos), source));
+        }
+    }
+}
+
+/// Move the riders along with their carts, once the physics have moved the carts.
+fn carry_riders(
+    entities: Res<Entities>,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    minecarts: Comp<Minecart>,
+    riding: Comp<Riding>,
+    mut transforms: CompMut<Transform>,
+    mut sprites: CompMut<AtlasSprite>,
+) {
+    for (entity, (minecart, element_handle)) in entities.iter_with((&minecarts, &element_handles)) {
+        let Some(rider) = minecart.rider else {
+            continue;
+        };
+        if riding.get(rider).map(|x| x.vehicle) != Some(entity) {
+            continue;
+        }
+        let Some(BuiltinElementKind::Minecart { rider_offset, .. }) = element_assets
+            .get(&element_handle.get_bevy_handle())
+            .map(|x| &x.builtin)
+        else {
+            continue;
+        };
+
+        let cart_transform = *transforms.get(entity).unwrap();
+        let flip_x = sprites.get(entity).map_or(false, |x| x.flip_x);
+        if let Some(transform) = transforms.get_mut(rider) {
+            transform.translation.x = cart_transform.translation.x + rider_offset.x;
+            transform.translation.y = cart_transform.translation.y + rider_offset.y;
+        }
+        if let Some(sprite) = sprites.get_mut(rider) {
+            sprite.flip_x = flip_x;
+        }
+    }
+}
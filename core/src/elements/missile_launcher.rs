@@ -0,0 +1,513 @@
+//! Missile launcher item.
+//!
+//! The launcher shoots missiles that turn toward the nearest opponent, a limited angle every frame,
+//! leaving a trail of smoke behind them. Launchers with a [lock-on][MissileLockOnMeta] instead lock
+//! their missiles onto the nearest opponent in front of them when they shoot, and the missiles keep
+//! beeping to warn their target. Missiles explode like grenades when they hit a player or a wall,
+//! or when they run out of fuel.
+
+use std::time::Duration;
+
+use crate::{
+    elements::grenade::spawn_explosion,
+    physics::collisions::{Actor, Collider},
+    prelude::*,
+};
+
+pub fn install(session: &mut CoreSession) {
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PreUpdate, hydrate)
+        .add_system_to_stage(CoreStage::PostUpdate, update)
+        .add_system_to_stage(CoreStage::PostUpdate, update_missiles);
+}
+
+/// The distance in front of the launcher that missiles are shot from.
+const MISSILE_SPAWN_OFFSET: f32 = 15.0;
+
+#[derive(Clone, Debug, TypeUlid, Default)]
+#[ulid = "01H2XM4C6B8TNQ2JVZ0KD5RWEH"]
+pub struct MissileLauncher {
+    pub ammo: usize,
+    pub cooldown: Timer,
+}
+
+/// A missile in flight.
+#[derive(Clone, Debug, TypeUlid)]
+#[ulid = "01H2XM4K0W3SEYB7HQ9PFA6NZD"]
+pub struct Missile {
+    /// The player that shot the missile, which it doesn't seek.
+    pub owner: Entity,
+    /// The velocity of the missile, in pixels per frame.
+    pub velocity: Vec2,
+    /// The time left until the missile runs out of fuel.
+    pub fuel: Timer,
+    /// The number of frames the missile has been flying for.
+    pub age: usize,
+    /// The player that the missile is locked onto, for launchers with a lock-on.
+    pub target: Option<Entity>,
+    /// The metadata of the launcher that shot the missile.
+    pub launcher_meta: Handle<ElementMeta>,
+}
+
+/// Whether a missile shot by the `owner` may seek the `player`.
+fn is_target(
+    owner: Entity,
+    player: Entity,
+    match_settings: &MatchSettings,
+    player_indexes: &Comp<PlayerIdx>,
+    players_killed: &Comp<PlayerKilled>,
+    invincibles: &Comp<Invincibility>,
+) -> bool {
+    player != owner
+        && player_indexes.contains(player)
+        && !players_killed.contains(player)
+        && !invincibles.contains(player)
+        && !is_protected_teammate(match_settings, player_indexes, owner, player)
+}
+
+/// Whether `to_target` is inside of the cone of the given angle, in radians, around `direction`.
+fn is_in_cone(direction: Vec2, to_target: Vec2, cone: f32) -> bool {
+    if to_target == Vec2::ZERO {
+        return true;
+    }
+    direction.angle_between(to_target).abs() <= cone / 2.0
+}
+
+/// Turn the `direction` toward `to_target`, by an angle of at most `max_turn` radians.
+fn steer(direction: Vec2, to_target: Vec2, max_turn: f32) -> Vec2 {
+    if direction == Vec2::ZERO || to_target == Vec2::ZERO {
+        return direction;
+    }
+    let angle = direction
+        .angle_between(to_target)
+        .clamp(-max_turn, max_turn);
+    Vec2::from_angle(angle).rotate(direction)
+}
+
+fn hydrate(
+    game_meta: Res<CoreMetaArc>,
+    mut entities: ResMut<Entities>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    mut element_handles: CompMut<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut launchers: CompMut<MissileLauncher>,
+    mut atlas_sprites: CompMut<AtlasSprite>,
+    mut bodies: CompMut<KinematicBody>,
+    mut transforms: CompMut<Transform>,
+    mut items: CompMut<Item>,
+    mut item_throws: CompMut<ItemThrow>,
+    mut item_grabs: CompMut<ItemGrab>,
+    mut respawn_points: CompMut<DehydrateOutOfBounds>,
+    mut spawner_manager: SpawnerManager,
+) {
+    let mut not_hydrated_bitset = hydrated.bitset().clone();
+    not_hydrated_bitset.bit_not();
+    not_hydrated_bitset.bit_and(element_handles.bitset());
+
+    let spawner_entities = entities
+        .iter_with_bitset(&not_hydrated_bitset)
+        .collect::<Vec<_>>();
+
+    for spawner_ent in spawner_entities {
+        let transform = *transforms.get(spawner_ent).unwrap();
+        let element_handle = element_handles.get(spawner_ent).unwrap();
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+
+        if let BuiltinElementKind::MissileLauncher {
+            atlas,
+            fin_anim,
+            grab_offset,
+            max_ammo,
+            body_size,
+            can_rotate,
+            bounciness,
+            throw_velocity,
+            angular_velocity,
+            ..
+        } = &element_meta.builtin
+        {
+            hydrated.insert(spawner_ent, MapElementHydrated);
+
+            let entity = entities.create();
+            items.insert(entity, Item);
+            item_throws.insert(
+                entity,
+                ItemThrow::strength(*throw_velocity)
+                    .with_spin(*angular_velocity)
+                    .with_system(missile_launcher_drop(entity, *max_ammo)),
+            );
+            item_grabs.insert(
+                entity,
+                ItemGrab {
+                    fin_anim: *fin_anim,
+                    sync_animation: false,
+                    grab_offset: *grab_offset,
+                },
+            );
+            launchers.insert(
+                entity,
+                MissileLauncher {
+                    ammo: *max_ammo,
+                    cooldown: Timer::new(Duration::ZERO, TimerMode::Once),
+                },
+            );
+            atlas_sprites.insert(entity, AtlasSprite::new(atlas.clone()));
+            respawn_points.insert(entity, DehydrateOutOfBounds(spawner_ent));
+            transforms.insert(entity, transform);
+            element_handles.insert(entity, element_handle.clone());
+            hydrated.insert(entity, MapElementHydrated);
+            bodies.insert(
+                entity,
+                KinematicBody {
+                    shape: ColliderShape::Rectangle { size: *body_size },
+                    has_mass: true,
+                    has_friction: true,
+                    can_rotate: *can_rotate,
+                    bounciness: *bounciness,
+                    gravity: game_meta.physics.gravity,
+                    ..default()
+                },
+            );
+            spawner_manager.create_spawner(spawner_ent, vec![entity])
+        }
+    }
+}
+
+/// Shoot missiles from the launchers that are used.
+fn update(
+    entities: Res<Entities>,
+    mut commands: Commands,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut launchers: CompMut<MissileLauncher>,
+    transforms: Comp<Transform>,
+    sprites: Comp<AtlasSprite>,
+    mut audio_events: ResMut<AudioEvents>,
+    match_settings: Res<MatchSettings>,
+    player_indexes: Comp<PlayerIdx>,
+    players_killed: Comp<PlayerKilled>,
+    invincibles: Comp<Invincibility>,
+    player_inventories: PlayerInventories,
+    mut items_used: CompMut<ItemUsed>,
+    time: Res<Time>,
+) {
+    for (entity, (launcher, element_handle)) in
+        entities.iter_with((&mut launchers, &element_handles))
+    {
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+        let BuiltinElementKind::MissileLauncher {
+            cooldown,
+            shoot_sound,
+            shoot_sound_volume,
+            empty_shoot_sound,
+            empty_shoot_sound_volume,
+            missile,
+            ..
+        } = &element_meta.builtin
+        else {
+            unreachable!();
+        };
+
+        launcher.cooldown.tick(time.delta());
+
+        // If the item is being held
+        let Some(inventory) = player_inventories
+            .iter()
+            .find_map(|x| x.filter(|x| x.inventory == entity))
+        else {
+            continue;
+        };
+        let player = inventory.player;
+
+        // If the item is being used
+        if items_used.remove(entity).is_none() || !launcher.cooldown.finished() {
+            continue;
+        }
+        if launcher.ammo == 0 {
+            audio_events.play(empty_shoot_sound.clone(), *empty_shoot_sound_volume);
+            continue;
+        }
+        launcher.cooldown = Timer::new(*cooldown, TimerMode::Once);
+        launcher.ammo -= 1;
+        audio_events.play(shoot_sound.clone(), *shoot_sound_volume);
+
+        let flip_factor = if sprites.get(player).map_or(false, |x| x.flip_x) {
+            -1.0
+        } else {
+            1.0
+        };
+        let mut missile_transform = *transforms.get(entity).unwrap();
+        missile_transform.translation.x += MISSILE_SPAWN_OFFSET * flip_factor;
+        missile_transform.translation.z += 1.0;
+        missile_transform.rotation = Quat::IDENTITY;
+
+        // Lock onto the nearest target in front of the launcher
+        let target = missile.lock_on.as_ref().and_then(|lock_on| {
+            let pos = missile_transform.translation.truncate();
+            let cone = lock_on.cone.to_radians();
+            entities
+                .iter_with((&player_indexes, &transforms))
+                .filter(|(ent, _)| {
+                    is_target(
+                        player,
+                        *ent,
+                        &match_settings,
+                        &player_indexes,
+                        &players_killed,
+                        &invincibles,
+                    )
+                })
+                .map(|(ent, (_, transform))| (ent, transform.translation.truncate() - pos))
+                .filter(|(_, offset)| is_in_cone(vec2(flip_factor, 0.0), *offset, cone))
+                .min_by(|(_, a), (_, b)| a.length_squared().total_cmp(&b.length_squared()))
+                .map(|(ent, _)| ent)
+        });
+
+        let missile_meta = missile.clone();
+        let launcher_meta = element_handle.0.clone();
+        commands.add(
+            move |mut entities: ResMut<Entities>,
+                  mut transforms: CompMut<Transform>,
+                  mut sprites: CompMut<AtlasSprite>,
+                  mut actors: CompMut<Actor>,
+                  mut colliders: CompMut<Collider>,
+                  mut missiles: CompMut<Missile>| {
+                let ent = entities.create();
+                transforms.insert(ent, missile_transform);
+                sprites.insert(
+                    ent,
+                    AtlasSprite {
+                        atlas: missile_meta.atlas.clone(),
+                        flip_x: flip_factor < 0.0,
+                        ..default()
+                    },
+                );
+                actors.insert(ent, Actor);
+                colliders.insert(
+                    ent,
+                    Collider {
+                        shape: ColliderShape::Circle {
+                            diameter: missile_meta.body_diameter,
+                        },
+                        ..default()
+                    },
+                );
+                missiles.insert(
+                    ent,
+                    Missile {
+                        owner: player,
+                        velocity: vec2(missile_meta.speed * flip_factor, 0.0),
+                        fuel: Timer::new(missile_meta.fuel_time, TimerMode::Once),
+                        age: 0,
+                        target,
+                        launcher_meta: launcher_meta.clone(),
+                    },
+                );
+            },
+        );
+    }
+}
+
+/// Steer the missiles toward their targets, and blow them up when they hit something or run out of
+/// fuel.
+fn update_missiles(
+    entities: Res<Entities>,
+    mut commands: Commands,
+    element_assets: BevyAssets<ElementMeta>,
+    match_settings: Res<MatchSettings>,
+    collision_world: CollisionWorld,
+    player_indexes: Comp<PlayerIdx>,
+    players_killed: Comp<PlayerKilled>,
+    invincibles: Comp<Invincibility>,
+    shield_walls: Comp<shield::ShieldWall>,
+    mut missiles: CompMut<Missile>,
+    mut transforms: CompMut<Transform>,
+    mut sprites: CompMut<AtlasSprite>,
+    mut audio_events: ResMut<AudioEvents>,
+    mut trauma_events: ResMut<CameraTraumaEvents>,
+    time: Res<Time>,
+) {
+    for (entity, missile) in entities.iter_with(&mut missiles) {
+        let Some(BuiltinElementKind::MissileLauncher { missile: meta, .. }) = element_assets
+            .get(&missile.launcher_meta.get_bevy_handle())
+            .map(|x| &x.builtin)
+        else {
+            continue;
+        };
+        missile.fuel.tick(time.delta());
+        missile.age += 1;
+
+        let owner = missile.owner;
+        let is_valid_target = |ent: Entity| {
+            is_target(
+                owner,
+                ent,
+                &match_settings,
+                &player_indexes,
+                &players_killed,
+                &invincibles,
+            )
+        };
+
+        // Turn toward the locked on target, or the nearest target without a lock-on
+        let pos = transforms.get(entity).unwrap().translation.truncate();
+        let target = if let Some(lock_on) = &meta.lock_on {
+            // Lose the lock once the target is gone
+            missile.target = missile.target.filter(|x| is_valid_target(*x));
+            if missile.target.is_some()
+                && lock_on.warning_interval > 0
+                && missile.age % lock_on.warning_interval == 1
+            {
+                audio_events.play(lock_on.warning_sound.clone(), lock_on.warning_volume);
+            }
+            missile
+                .target
+                .and_then(|x| transforms.get(x))
+                .map(|x| x.translation.truncate())
+        } else {
+            entities
+                .iter_with((&player_indexes, &transforms))
+                .filter(|(ent, _)| is_valid_target(*ent))
+                .map(|(_, (_, transform))| transform.translation.truncate())
+                .min_by(|a, b| a.distance_squared(pos).total_cmp(&b.distance_squared(pos)))
+        };
+        let mut direction = missile.velocity.normalize_or_zero();
+        if let Some(target) = target {
+            direction = steer(direction, target - pos, meta.turn_rate.to_radians());
+        }
+        missile.velocity = direction * meta.speed;
+
+        // Move the missile, pointing it where it is going
+        let transform = transforms.get_mut(entity).unwrap();
+        transform.translation += missile.velocity.extend(0.0);
+        let angle = if direction.x < 0.0 {
+            Vec2::NEG_X.angle_between(direction)
+        } else {
+            Vec2::X.angle_between(direction)
+        };
+        transform.rotation = Quat::from_rotation_z(angle);
+        if let Some(sprite) = sprites.get_mut(entity) {
+            sprite.flip_x = direction.x < 0.0;
+        }
+        let transform = *transform;
+
+        // Leave a trail of smoke
+        if meta.smoke_interval > 0 && missile.age % meta.smoke_interval == 0 {
+            let mut smoke_transform = transform;
+            smoke_transform.translation.z -= 0.1;
+            smoke_transform.rotation = Quat::IDENTITY;
+            let atlas = meta.smoke_atlas.clone();
+            let frames = meta.smoke_frames;
+            let fps = meta.smoke_fps;
+            let lifetime = meta.smoke_lifetime;
+            commands.add(
+                move |mut entities: ResMut<Entities>,
+                      mut transforms: CompMut<Transform>,
+                      mut lifetimes: CompMut<Lifetime>,
+                      mut sprites: CompMut<AtlasSprite>,
+                      mut animated_sprites: CompMut<AnimatedSprite>| {
+                    let ent = entities.create();
+                    transforms.insert(ent, smoke_transform);
+                    sprites.insert(
+                        ent,
+                        AtlasSprite {
+                            atlas: atlas.clone(),
+                            ..default()
+                        },
+                    );
+                    animated_sprites.insert(
+                        ent,
+                        AnimatedSprite {
+                            frames: (0..frames).collect(),
+                            fps,
+                            repeat: false,
+                            ..default()
+                        },
+                    );
+                    lifetimes.insert(ent, Lifetime::new(lifetime));
+                },
+            );
+        }
+
+        // Explode when hitting a player, a shield wall, or a solid tile, or when out of fuel
+        let hit_actor = !collision_world
+            .actor_collisions_filtered(entity, |e| is_valid_target(e) || shield_walls.contains(e))
+            .is_empty();
+        let hit_solid = collision_world.tile_collision(
+            transform,
+            ColliderShape::Circle {
+                diameter: meta.body_diameter,
+            },
+        ) == TileCollisionKind::Solid;
+        if !(hit_actor || hit_solid || missile.fuel.finished()) {
+            continue;
+        }
+
+        audio_events.play(meta.explosion_sound.clone(), meta.explosion_volume);
+        trauma_events.send(5.0);
+
+        let mut explosion_transform = transform;
+        explosion_transform.translation.z = -10.0; // On top of almost everything
+        explosion_transform.rotation = Quat::IDENTITY;
+        commands.add(move |mut entities: ResMut<Entities>| {
+            // Despawn the missile
+            entities.kill(entity);
+        });
+        commands.add(spawn_explosion(
+            explosion_transform,
+            DamageSource::Player(missile.owner),
+            meta.blast_size,
+            meta.blast_lifetime,
+            meta.explosion_atlas.clone(),
+            meta.explosion_frames,
+            meta.explosion_fps,
+            meta.explosion_lifetime,
+        ));
+    }
+}
+
+fn missile_launcher_drop(entity: Entity, max_ammo: usize) -> System {
+    (move |mut launchers: CompMut<MissileLauncher>| {
+        // Reload the launcher
+        launchers.get_mut(entity).unwrap().ammo = max_ammo;
+    })
+    .system()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn steering_is_limited_by_the_turn_rate() {
+        let max_turn = 10f32.to_radians();
+        let direction = steer(Vec2::X, Vec2::Y, max_turn);
+        assert!((Vec2::X.angle_between(direction) - max_turn).abs() < 1e-5);
+        assert!((direction.length() - 1.0).abs() < 1e-5);
+
+        let direction = steer(Vec2::X, Vec2::NEG_Y, max_turn);
+        assert!((Vec2::X.angle_between(direction) + max_turn).abs() < 1e-5);
+    }
+
+    #[test]
+    fn lock_on_only_targets_players_in_the_cone() {
+        let cone = 60f32.to_radians();
+        assert!(is_in_cone(Vec2::X, vec2(10.0, 5.0), cone));
+        assert!(!is_in_cone(Vec2::X, vec2(10.0, 10.0), cone));
+        assert!(!is_in_cone(Vec2::X, vec2(-10.0, 0.0), cone));
+        assert!(is_in_cone(Vec2::NEG_X, vec2(-10.0, -1.0), cone));
+    }
+
+    #[test]
+    fn steering_reaches_close_targets() {
+        let to_target = Vec2::from_angle(5f32.to_radians());
+        let direction = steer(Vec2::X, to_target, 10f32.to_radians());
+        assert!(direction.abs_diff_eq(to_target, 1e-5));
+        assert_eq!(steer(Vec2::X, Vec2::ZERO, 1.0), Vec2::X);
+    }
+}
@@ -1,6 +1,9 @@
 use std::time::Duration;
 
-use crate::prelude::*;
+use crate::{
+    prelude::*,
+    random::{GlobalRng, RngStream},
+};
 
 pub fn install(session: &mut CoreSession) {
     session
@@ -122,9 +125,12 @@ fn update(
     mut items_used: CompMut<ItemUsed>,
     items_dropped: CompMut<ItemDropped>,
     time: Res<Time>,
+    rng: Res<GlobalRng>,
+    bullet_assets: BevyAssets<BulletMeta>,
 
     mut bodies: CompMut<KinematicBody>,
 ) {
+    let rng = rng.stream(RngStream::Combat);
     for (entity, (musket, element_handle)) in entities.iter_with((&mut muskets, &element_handles)) {
         let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
             continue;
@@ -143,6 +149,7 @@ fn update(
             shoot_sound_volume,
             empty_shoot_sound_volume,
             kickback,
+            spread,
             ..
         } = &element_meta.builtin else {
             unreachable!();
@@ -178,8 +185,19 @@ fn update(
                 let player_flip_x = player_sprite.flip_x;
                 let player_body = bodies.get_mut(player).unwrap();
 
+                // Pick a random direction in the spread cone
+                let flip_factor = if player_flip_x { -1.0 } else { 1.0 };
+                let spread_rotation =
+                    Vec2::from_angle(rng.f32_normalized() * spread.to_radians() / 2.0);
+                let direction = spread_rotation.rotate(vec2(flip_factor, 0.0));
+                let bullet_velocity = bullet_assets
+                    .get(&bullet_meta.get_bevy_handle())
+                    .map(|meta| spread_rotation.rotate(meta.velocity * vec2(flip_factor, 1.0)))
+                    .unwrap_or_default();
+
                 //Set kickback
-                player_body.velocity.x = if player_flip_x { 1.0 } else { -1.0 } * kickback;
+                player_body.velocity.x = -direction.x * kickback;
+                player_body.velocity.y -= direction.y * kickback;
 
                 let mut shoot_animation_transform = *transforms.get(entity).unwrap();
                 shoot_animation_transform.translation.z += 1.0;
@@ -233,7 +251,7 @@ fn update(
                                 ent,
                                 Bullet {
                                     owner: player,
-                                    direction: if player_flip_x { -1.0 } else { 1.0 },
+                                    velocity: bullet_velocity,
                                 },
                             );
                             transforms.insert(ent, shoot_animation_transform);
@@ -47,9 +47,13 @@ fn hydrate(
 fn update(
     mut entities: ResMut<Entities>,
     mut current_spawner: ResMut<CurrentSpawner>,
+    match_settings: Res<MatchSettings>,
+    match_score: Res<MatchScore>,
     player_spawners: Comp<PlayerSpawner>,
+    spawn_point_metas: Comp<SpawnPointMeta>,
     mut player_indexes: CompMut<PlayerIdx>,
     mut transforms: CompMut<Transform>,
+    mut atlas_sprites: CompMut<AtlasSprite>,
     player_inputs: Res<PlayerInputs>,
     mut spawner_manager: SpawnerManager,
 ) {
@@ -59,20 +63,30 @@ fn update(
         .collect::<Vec<_>>();
     let spawn_points = entities
         .iter_with((&player_spawners, &transforms))
-        .map(|(_ent, (_spawner, transform))| transform.translation)
+        .map(|(ent, (_spawner, transform))| {
+            let meta = spawn_point_metas.get(ent).copied().unwrap_or_default();
+            (transform.translation, meta)
+        })
+        .collect::<Vec<_>>();
+    // The positions of the players, including the ones spawned this frame
+    let mut player_positions = entities
+        .iter_with((&player_indexes, &transforms))
+        .map(|(_ent, (_pidx, transform))| transform.translation.truncate())
         .collect::<Vec<_>>();
 
     // For every player
     for i in 0..MAX_PLAYERS {
         let player = &player_inputs.players[i];
 
-        // If the player is active, but not alive
-        if player.active && !alive_players.contains(&i) {
-            // Increment the spawner index
-            current_spawner.0 += 1;
-            current_spawner.0 %= spawn_points.len().max(1);
-
-            let Some(mut spawn_point) = spawn_points.get(current_spawner.0).copied() else { return };
+        // If the player is active, but not alive, and may respawn this round
+        if player.active && !alive_players.contains(&i) && !match_score.is_eliminated(i) {
+            let team = match_settings.team_of(i);
+            let Some(spawner_idx) =
+                choose_spawn_point(&spawn_points, &player_positions, current_spawner.0, team)
+                else { continue };
+            current_spawner.0 = spawner_idx;
+            let (mut spawn_point, meta) = spawn_points[spawner_idx];
+            player_positions.push(spawn_point.truncate());
 
             // Make sure each player spawns at a different z level ( give enough room for 10 players
             // to fit between map layers )
@@ -81,6 +95,16 @@ fn update(
             let player_ent = entities.create();
             player_indexes.insert(player_ent, PlayerIdx(i));
             transforms.insert(player_ent, Transform::from_translation(spawn_point));
+            if meta.face_left {
+                // The player is hydrated facing the same way as this sprite
+                atlas_sprites.insert(
+                    player_ent,
+                    AtlasSprite {
+                        flip_x: true,
+                        ..default()
+                    },
+                );
+            }
 
             spawner_manager.insert_spawned_entity_into_grouped_spawner(
                 player_ent,
@@ -90,3 +114,91 @@ fn update(
         }
     }
 }
+
+/// Choose the spawn point to spawn a player at, returning its index.
+///
+/// The spawn points of the player's `team`, or without a team, with the highest priority are
+/// considered, and the one furthest from the other players is chosen. Ties are broken by cycling
+/// through the spawn points, starting after the last one used. Players without a team can use
+/// every spawn point.
+fn choose_spawn_point(
+    spawn_points: &[(Vec3, SpawnPointMeta)],
+    player_positions: &[Vec2],
+    last_spawner: usize,
+    team: Option<u8>,
+) -> Option<usize> {
+    let count = spawn_points.len();
+    let distance_to_players = |pos: Vec3| {
+        player_positions
+            .iter()
+            .map(|x| x.distance(pos.truncate()))
+            .fold(f32::INFINITY, f32::min)
+    };
+
+    let mut best: Option<(usize, i32, f32)> = None;
+    for offset in 1..=count {
+        let idx = (last_spawner + offset) % count;
+        let (pos, meta) = spawn_points[idx];
+        if team.is_some() && meta.team.is_some() && meta.team != team {
+            continue;
+        }
+        let distance = distance_to_players(pos);
+        let is_better = match best {
+            None => true,
+            Some((_, priority, best_distance)) => {
+                meta.priority > priority || (meta.priority == priority && distance > best_distance)
+            }
+        };
+        if is_better {
+            best = Some((idx, meta.priority, distance));
+        }
+    }
+
+    best.map(|(idx, ..)| idx)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn spawn_point(x: f32, priority: i32) -> (Vec3, SpawnPointMeta) {
+        (
+            Vec3::new(x, 0.0, 0.0),
+            SpawnPointMeta {
+                priority,
+                ..default()
+            },
+        )
+    }
+
+    #[test]
+    fn spawn_points_are_chosen_by_priority_then_distance() {
+        let spawn_points = [
+            spawn_point(0.0, 0),
+            spawn_point(100.0, 0),
+            spawn_point(50.0, 1),
+        ];
+        assert_eq!(choose_spawn_point(&spawn_points, &[], 0, None), Some(2));
+        assert_eq!(
+            choose_spawn_point(&spawn_points[..2], &[Vec2::ZERO], 1, None),
+            Some(1)
+        );
+        assert_eq!(choose_spawn_point(&[], &[], 0, None), None);
+    }
+
+    #[test]
+    fn spawn_points_are_cycled_through_without_players() {
+        let spawn_points = [spawn_point(0.0, 0), spawn_point(100.0, 0)];
+        assert_eq!(choose_spawn_point(&spawn_points, &[], 0, None), Some(1));
+        assert_eq!(choose_spawn_point(&spawn_points, &[], 1, None), Some(0));
+    }
+
+    #[test]
+    fn spawn_points_of_other_teams_are_skipped() {
+        let mut spawn_points = [spawn_point(0.0, 1), spawn_point(100.0, 0)];
+        spawn_points[0].1.team = Some(1);
+        assert_eq!(choose_spawn_point(&spawn_points, &[], 1, Some(0)), Some(1));
+        assert_eq!(choose_spawn_point(&spawn_points, &[], 1, Some(1)), Some(0));
+        assert_eq!(choose_spawn_point(&spawn_points, &[], 1, None), Some(0));
+    }
+}
@@ -0,0 +1,181 @@
+//! Heavy pushable blocks.
+//!
+//! Blocks are [solids][Solid], so players, items and other blocks collide with them and can rest
+//! on top of them, and bullets stop against them. A player walking into the side of a block shoves
+//! it along, slowed down by the weight of the block.
+
+use crate::prelude::*;
+
+pub fn install(session: &mut CoreSession) {
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PreUpdate, hydrate)
+        .add_system_to_stage(CoreStage::PostUpdate, push_blocks);
+}
+
+/// How far from the side of a block, in pixels, a player is still pushing it.
+///
+/// Players are stopped a little short of the blocks, and a block moves away from the player
+/// pushing it before the player catches up with it, so there is some room between them.
+const PUSH_REACH: f32 = 4.0;
+/// How much a player must overlap the side of a block vertically, in pixels, to push it, so that
+/// players standing on top of a block or bumping into its corner don't push it.
+const PUSH_MIN_OVERLAP: f32 = 2.0;
+
+#[derive(Clone, Debug, TypeUlid, Default)]
+#[ulid = "01H32KQ7V4NB2XZ8MRT5WC0EYD"]
+pub struct PushableBlock {
+    /// The mass of the block, relative to the mass of a player.
+    pub mass: f32,
+}
+
+/// Whether a body moving horizontally at the given velocity is pushing against the side of a
+/// block, given the bounding boxes of the body and the block.
+pub fn is_pushing(pusher: Rect, velocity_x: f32, block: Rect) -> bool {
+    let vertical_overlap = pusher.max.y.min(block.max.y) - pusher.min.y.max(block.min.y);
+    if vertical_overlap < PUSH_MIN_OVERLAP {
+        return false;
+    }
+
+    let gap = if velocity_x > 0.0 {
+        block.min.x - pusher.max.x
+    } else if velocity_x < 0.0 {
+        pusher.min.x - block.max.x
+    } else {
+        return false;
+    };
+    (-PUSH_REACH..=PUSH_REACH).contains(&gap)
+}
+
+fn hydrate(
+    game_meta: Res<CoreMetaArc>,
+    mut entities: ResMut<Entities>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    mut element_handles: CompMut<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut pushable_blocks: CompMut<PushableBlock>,
+    mut atlas_sprites: CompMut<AtlasSprite>,
+    mut bodies: CompMut<KinematicBody>,
+    mut solids: CompMut<Solid>,
+    mut transforms: CompMut<Transform>,
+    mut respawn_points: CompMut<DehydrateOutOfBounds>,
+    mut spawner_manager: SpawnerManager,
+) {
+    let mut not_hydrated_bitset = hydrated.bitset().clone();
+    not_hydrated_bitset.bit_not();
+    not_hydrated_bitset.bit_and(element_handles.bitset());
+
+    let spawner_entities = entities
+        .iter_with_bitset(&not_hydrated_bitset)
+        .collect::<Vec<_>>();
+
+    for spawner_ent in spawner_entities {
+        let transform = *transforms.get(spawner_ent).unwrap();
+        let element_handle = element_handles.get(spawner_ent).unwrap();
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+
+        if let BuiltinElementKind::PushableBlock {
+            atlas,
+            body_size,
+            mass,
+        } = &element_meta.builtin
+        {
+            hydrated.insert(spawner_ent, MapElementHydrated);
+
+            let entity = entities.create();
+            pushable_blocks.insert(entity, PushableBlock { mass: *mass });
+            atlas_sprites.insert(entity, AtlasSprite::new(atlas.clone()));
+            respawn_points.insert(entity, DehydrateOutOfBounds(spawner_ent));
+            transforms.insert(entity, transform);
+            element_handles.insert(entity, element_handle.clone());
+            hydrated.insert(entity, MapElementHydrated);
+            solids.insert(entity, Solid);
+            bodies.insert(
+                entity,
+                KinematicBody {
+                    shape: ColliderShape::Rectangle { size: *body_size },
+                    has_mass: true,
+                    has_friction: true,
+                    gravity: game_meta.physics.gravity,
+                    ..default()
+                },
+            );
+            spawner_manager.create_spawner(spawner_ent, vec![entity])
+        }
+    }
+}
+
+/// Shove the blocks that players are walking into.
+///
+/// The momentum of the player is passed on to the block, and the player moves along with it at the
+/// speed of the block.
+fn push_blocks(
+    entities: Res<Entities>,
+    pushable_blocks: Comp<PushableBlock>,
+    player_indexes: Comp<PlayerIdx>,
+    players_killed: Comp<PlayerKilled>,
+    transforms: Comp<Transform>,
+    mut bodies: CompMut<KinematicBody>,
+) {
+    let players = entities
+        .iter_with((&player_indexes, &transforms))
+        .filter(|(player, _)| !players_killed.contains(*player))
+        .map(|(player, (_, transform))| (player, *transform))
+        .collect::<Vec<_>>();
+
+    for (block, (pushable_block, block_transform)) in
+        entities.iter_with((&pushable_blocks, &transforms))
+    {
+        let Some(block_rect) = bodies.get(block).map(|x| x.bounding_box(*block_transform)) else {
+            continue;
+        };
+
+        for (player, player_transform) in &players {
+            let Some(player_body) = bodies.get_mut(*player) else {
+                continue;
+            };
+            let velocity_x = player_body.velocity.x;
+            if !is_pushing(
+                player_body.bounding_box(*player_transform),
+                velocity_x,
+                block_rect,
+            ) {
+                continue;
+            }
+
+            let push_velocity = velocity_x / pushable_block.mass.max(1.0);
+            player_body.velocity.x = push_velocity;
+            let block_body = bodies.get_mut(block).unwrap();
+            // Blocks pushed from both sides at once don't move
+            block_body.velocity.x = if block_body.velocity.x * push_velocity < 0.0 {
+                0.0
+            } else {
+                push_velocity
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn players_push_blocks_they_walk_into() {
+        let block = Rect::new(0.0, 0.0, 32.0, 32.0);
+        let player = |x: f32, y: f32| Rect::new(x, y, 20.0, 28.0);
+
+        // Walking into either side
+        assert!(is_pushing(player(-26.1, -2.0), 2.0, block));
+        assert!(is_pushing(player(26.1, -2.0), -2.0, block));
+        // Walking away, or standing still
+        assert!(!is_pushing(player(-26.1, -2.0), -2.0, block));
+        assert!(!is_pushing(player(-26.1, -2.0), 0.0, block));
+        // Too far away
+        assert!(!is_pushing(player(-40.0, -2.0), 2.0, block));
+        // Standing on top of the block
+        assert!(!is_pushing(player(-20.0, 30.1), 2.0, block));
+    }
+}
@@ -0,0 +1,276 @@
+//! Deployable shield.
+//!
+//! Using the shield puts it up as a wall in front of the player. The wall stops bullets and
+//! missiles, and cracks a little more every time it is hit, until it breaks.
+
+use crate::{
+    damage::{DamageEvent, Damageable},
+    prelude::*,
+};
+
+pub fn install(session: &mut CoreSession) {
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PreUpdate, hydrate)
+        .add_system_to_stage(CoreStage::PostUpdate, update)
+        .add_system_to_stage(CoreStage::PostUpdate, update_walls);
+}
+
+#[derive(Clone, Debug, TypeUlid, Default)]
+#[ulid = "01H30Q6M1VZ8KTA3WXHN5E2BDR"]
+pub struct Shield;
+
+/// A wall put up by a [`Shield`].
+#[derive(Clone, Debug, TypeUlid)]
+#[ulid = "01H30Q6T9CJ4RB7EYN0MKXF3WS"]
+pub struct ShieldWall {
+    /// The number of bullets the wall can still take before it breaks.
+    pub health: u32,
+    /// Whether the wall was caught in an explosion on the last frame.
+    pub was_damaged: bool,
+    /// The metadata of the shield that put up the wall.
+    pub shield_meta: Handle<ElementMeta>,
+}
+
+impl ShieldWall {
+    /// Take the given damage off of the wall's health.
+    pub fn hit(&mut self, damage: u32) {
+        self.health = self.health.saturating_sub(damage);
+    }
+}
+
+/// Get the frame of the wall's atlas to show, given how much health it has left.
+fn crack_frame(health: u32, max_health: u32, crack_frames: usize) -> usize {
+    if max_health == 0 || crack_frames == 0 {
+        return 0;
+    }
+    let damage = max_health.saturating_sub(health) as usize;
+    (damage * crack_frames / max_health as usize).min(crack_frames - 1)
+}
+
+fn hydrate(
+    game_meta: Res<CoreMetaArc>,
+    mut entities: ResMut<Entities>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    mut element_handles: CompMut<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut shields: CompMut<Shield>,
+    mut atlas_sprites: CompMut<AtlasSprite>,
+    mut bodies: CompMut<KinematicBody>,
+    mut transforms: CompMut<Transform>,
+    mut items: CompMut<Item>,
+    mut item_throws: CompMut<ItemThrow>,
+    mut item_grabs: CompMut<ItemGrab>,
+    mut respawn_points: CompMut<DehydrateOutOfBounds>,
+    mut spawner_manager: SpawnerManager,
+) {
+    let mut not_hydrated_bitset = hydrated.bitset().clone();
+    not_hydrated_bitset.bit_not();
+    not_hydrated_bitset.bit_and(element_handles.bitset());
+
+    let spawner_entities = entities
+        .iter_with_bitset(&not_hydrated_bitset)
+        .collect::<Vec<_>>();
+
+    for spawner_ent in spawner_entities {
+        let transform = *transforms.get(spawner_ent).unwrap();
+        let element_handle = element_handles.get(spawner_ent).unwrap();
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+
+        if let BuiltinElementKind::Shield {
+            atlas,
+            body_size,
+            grab_offset,
+            fin_anim,
+            throw_velocity,
+            angular_velocity,
+            ..
+        } = &element_meta.builtin
+        {
+            hydrated.insert(spawner_ent, MapElementHydrated);
+
+            let entity = entities.create();
+            items.insert(entity, Item);
+            item_throws.insert(
+                entity,
+                ItemThrow::strength(*throw_velocity).with_spin(*angular_velocity),
+            );
+            item_grabs.insert(
+                entity,
+                ItemGrab {
+                    fin_anim: *fin_anim,
+                    sync_animation: false,
+                    grab_offset: *grab_offset,
+                },
+            );
+            shields.insert(entity, Shield);
+            atlas_sprites.insert(entity, AtlasSprite::new(atlas.clone()));
+            respawn_points.insert(entity, DehydrateOutOfBounds(spawner_ent));
+            transforms.insert(entity, transform);
+            element_handles.insert(entity, element_handle.clone());
+            hydrated.insert(entity, MapElementHydrated);
+            bodies.insert(
+                entity,
+                KinematicBody {
+                    shape: ColliderShape::Rectangle { size: *body_size },
+                    has_mass: true,
+                    has_friction: true,
+                    can_rotate: true,
+                    gravity: game_meta.physics.gravity,
+                    ..default()
+                },
+            );
+            spawner_manager.create_spawner(spawner_ent, vec![entity])
+        }
+    }
+}
+
+/// Put up walls in front of the players that use their shields.
+fn update(
+    entities: Res<Entities>,
+    mut commands: Commands,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    shields: Comp<Shield>,
+    mut items_used: CompMut<ItemUsed>,
+    player_inventories: PlayerInventories,
+    mut inventories: CompMut<Inventory>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    spawners: Comp<DehydrateOutOfBounds>,
+    transforms: Comp<Transform>,
+    sprites: Comp<AtlasSprite>,
+    mut audio_events: ResMut<AudioEvents>,
+) {
+    for (entity, (_shield, element_handle, spawner)) in
+        entities.iter_with((&shields, &element_handles, &spawners))
+    {
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+        let BuiltinElementKind::Shield { wall, .. } = &element_meta.builtin else {
+            unreachable!();
+        };
+
+        // If the item is being held
+        let Some(Inv { player, .. }) = player_inventories
+            .iter()
+            .find_map(|x| x.filter(|x| x.inventory == entity))
+        else {
+            continue;
+        };
+
+        // If the item is being used
+        if items_used.remove(entity).is_none() {
+            continue;
+        }
+
+        // Use up the shield, letting it respawn at its spawner
+        hydrated.remove(**spawner);
+        inventories.insert(player, Inventory(None));
+        audio_events.play(wall.place_sound.clone(), wall.place_volume);
+
+        let flip_factor = if sprites.get(player).map_or(false, |x| x.flip_x) {
+            -1.0
+        } else {
+            1.0
+        };
+        let mut wall_transform = *transforms.get(player).unwrap();
+        wall_transform.translation.x += wall.offset.x * flip_factor;
+        wall_transform.translation.y += wall.offset.y;
+        wall_transform.rotation = Quat::IDENTITY;
+        wall_transform.scale = Vec3::ONE;
+
+        let wall = wall.clone();
+        let shield_meta = element_handle.0.clone();
+        commands.add(
+            move |mut entities: ResMut<Entities>,
+                  mut transforms: CompMut<Transform>,
+                  mut sprites: CompMut<AtlasSprite>,
+                  mut bodies: CompMut<KinematicBody>,
+                  mut damageables: CompMut<Damageable>,
+                  mut lifetimes: CompMut<Lifetime>,
+                  mut shield_walls: CompMut<ShieldWall>| {
+                // Despawn the shield item
+                entities.kill(entity);
+
+                let ent = entities.create();
+                transforms.insert(ent, wall_transform);
+                sprites.insert(ent, AtlasSprite::new(wall.atlas.clone()));
+                bodies.insert(
+                    ent,
+                    KinematicBody {
+                        shape: ColliderShape::Rectangle { size: wall.size },
+                        has_mass: false,
+                        ..default()
+                    },
+                );
+                damageables.insert(ent, Damageable { size: wall.size });
+                lifetimes.insert(ent, Lifetime::new(wall.lifetime));
+                shield_walls.insert(
+                    ent,
+                    ShieldWall {
+                        health: wall.health,
+                        was_damaged: false,
+                        shield_meta: shield_meta.clone(),
+                    },
+                );
+            },
+        );
+    }
+}
+
+/// Damage the walls caught in explosions, crack them according to the health they have left, and
+/// break them once they run out of health.
+fn update_walls(
+    entities: Res<Entities>,
+    mut commands: Commands,
+    element_assets: BevyAssets<ElementMeta>,
+    damage_events: Res<EventChannel<DamageEvent>>,
+    mut shield_walls: CompMut<ShieldWall>,
+    mut sprites: CompMut<AtlasSprite>,
+    mut audio_events: ResMut<AudioEvents>,
+) {
+    for (entity, shield_wall) in entities.iter_with(&mut shield_walls) {
+        let Some(BuiltinElementKind::Shield { wall, .. }) = element_assets
+            .get(&shield_wall.shield_meta.get_bevy_handle())
+            .map(|x| &x.builtin)
+        else {
+            continue;
+        };
+
+        // Explosions last for several frames, so only the first frame of one does damage
+        let is_damaged = damage_events.damaged(entity).is_some();
+        if is_damaged && !shield_wall.was_damaged {
+            shield_wall.hit(wall.explosion_damage);
+        }
+        shield_wall.was_damaged = is_damaged;
+
+        if let Some(sprite) = sprites.get_mut(entity) {
+            sprite.index = crack_frame(shield_wall.health, wall.health, wall.crack_frames);
+        }
+
+        if shield_wall.health == 0 {
+            audio_events.play(wall.break_sound.clone(), wall.break_volume);
+            commands.add(move |mut entities: ResMut<Entities>| {
+                entities.kill(entity);
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn walls_crack_as_they_lose_health() {
+        assert_eq!(crack_frame(4, 4, 4), 0);
+        assert_eq!(crack_frame(3, 4, 4), 1);
+        assert_eq!(crack_frame(1, 4, 4), 3);
+        assert_eq!(crack_frame(0, 4, 4), 3);
+        assert_eq!(crack_frame(5, 10, 3), 1);
+        assert_eq!(crack_frame(0, 0, 3), 0);
+    }
+}
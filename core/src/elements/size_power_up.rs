@@ -0,0 +1,247 @@
+//! Power-ups that shrink or grow the player that collects them.
+//!
+//! The player's sprite and collider are scaled for a while: tiny players are harder to hit but throw
+//! items weaker, while giant players knock the players they stomp on away but make a bigger target.
+
+use std::time::Duration;
+
+use crate::prelude::*;
+
+pub fn install(session: &mut CoreSession) {
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PreUpdate, hydrate)
+        .add_system_to_stage(CoreStage::PostUpdate, update)
+        .add_system_to_stage(CoreStage::PostUpdate, update_resized_players);
+}
+
+#[derive(Clone, Debug, TypeUlid, Default)]
+#[ulid = "01H2VD1K8PZ4QW6NRT3XJ5MHCE"]
+pub struct SizePowerUp {
+    /// The time left until the power-up can be collected again.
+    pub respawn_timer: Timer,
+}
+
+/// Component added to players that have been shrunk or grown by a [`SizePowerUp`].
+#[derive(Clone, Debug, TypeUlid)]
+#[ulid = "01H2VD1RZ3M8XK5TB0WQEH7N6A"]
+pub struct Resized {
+    /// The scale of the player's sprite and collider.
+    pub scale: f32,
+    /// The multiplier applied to the velocity of the items the player throws.
+    pub throw_strength: f32,
+    /// The velocity the players stomped on by the player are knocked away with.
+    pub knockback: Vec2,
+    /// The shape of the player's collider before it was resized.
+    pub base_shape: ColliderShape,
+    /// The time left until the player is back to their normal size.
+    pub timer: Timer,
+}
+
+/// Scale a collider shape by the given factor.
+fn scale_shape(shape: ColliderShape, scale: f32) -> ColliderShape {
+    match shape {
+        ColliderShape::Circle { diameter } => ColliderShape::Circle {
+            diameter: diameter * scale,
+        },
+        ColliderShape::Rectangle { size } => ColliderShape::Rectangle { size: size * scale },
+    }
+}
+
+fn hydrate(
+    game_meta: Res<CoreMetaArc>,
+    mut entities: ResMut<Entities>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    mut element_handles: CompMut<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut power_ups: CompMut<SizePowerUp>,
+    mut atlas_sprites: CompMut<AtlasSprite>,
+    mut bodies: CompMut<KinematicBody>,
+    mut transforms: CompMut<Transform>,
+    mut respawn_points: CompMut<DehydrateOutOfBounds>,
+    mut spawner_manager: SpawnerManager,
+) {
+    let mut not_hydrated_bitset = hydrated.bitset().clone();
+    not_hydrated_bitset.bit_not();
+    not_hydrated_bitset.bit_and(element_handles.bitset());
+
+    let spawner_entities = entities
+        .iter_with_bitset(&not_hydrated_bitset)
+        .collect::<Vec<_>>();
+
+    for spawner_ent in spawner_entities {
+        let transform = *transforms.get(spawner_ent).unwrap();
+        let element_handle = element_handles.get(spawner_ent).unwrap();
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+
+        if let BuiltinElementKind::SizePowerUp {
+            atlas, body_size, ..
+        } = &element_meta.builtin
+        {
+            hydrated.insert(spawner_ent, MapElementHydrated);
+
+            let entity = entities.create();
+            power_ups.insert(
+                entity,
+                SizePowerUp {
+                    respawn_timer: Timer::new(Duration::ZERO, TimerMode::Once),
+                },
+            );
+            atlas_sprites.insert(entity, AtlasSprite::new(atlas.clone()));
+            respawn_points.insert(entity, DehydrateOutOfBounds(spawner_ent));
+            transforms.insert(entity, transform);
+            element_handles.insert(entity, element_handle.clone());
+            hydrated.insert(entity, MapElementHydrated);
+            bodies.insert(
+                entity,
+                KinematicBody {
+                    shape: ColliderShape::Rectangle { size: *body_size },
+                    has_mass: true,
+                    has_friction: true,
+                    gravity: game_meta.physics.gravity,
+                    ..default()
+                },
+            );
+            spawner_manager.create_spawner(spawner_ent, vec![entity])
+        }
+    }
+}
+
+/// Resize the players that touch the power-ups, and hide the power-ups until they respawn.
+fn update(
+    entities: Res<Entities>,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    collision_world: CollisionWorld,
+    mut power_ups: CompMut<SizePowerUp>,
+    mut resized_players: CompMut<Resized>,
+    mut atlas_sprites: CompMut<AtlasSprite>,
+    mut bodies: CompMut<KinematicBody>,
+    mut transforms: CompMut<Transform>,
+    player_indexes: Comp<PlayerIdx>,
+    players_killed: Comp<PlayerKilled>,
+    mut audio_events: ResMut<AudioEvents>,
+    time: Res<Time>,
+) {
+    for (entity, (power_up, element_handle)) in
+        entities.iter_with((&mut power_ups, &element_handles))
+    {
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+        let BuiltinElementKind::SizePowerUp {
+            scale,
+            duration,
+            throw_strength,
+            knockback,
+            respawn_delay,
+            sound,
+            sound_volume,
+            ..
+        } = &element_meta.builtin
+        else {
+            unreachable!();
+        };
+
+        // Show the power-up again once it has respawned
+        power_up.respawn_timer.tick(time.delta());
+        let is_available = power_up.respawn_timer.finished();
+        if let Some(sprite) = atlas_sprites.get_mut(entity) {
+            sprite.color.set_a(if is_available { 1.0 } else { 0.0 });
+        }
+        if !is_available {
+            continue;
+        }
+
+        let Some(player) = collision_world
+            .actor_collisions(entity)
+            .into_iter()
+            .find(|ent| player_indexes.contains(*ent) && !players_killed.contains(*ent))
+        else {
+            continue;
+        };
+        power_up.respawn_timer = Timer::new(*respawn_delay, TimerMode::Once);
+        audio_events.play(sound.clone(), *sound_volume);
+
+        // Resize the player, starting from their normal size if they were already resized
+        let body = bodies.get_mut(player).unwrap();
+        let base_shape = resized_players
+            .get(player)
+            .map_or(body.shape, |x| x.base_shape);
+        let old_height = body.bounding_box(default()).height();
+        body.shape = scale_shape(base_shape, *scale);
+        let new_height = body.bounding_box(default()).height();
+
+        // Keep the player's feet in place
+        let transform = transforms.get_mut(player).unwrap();
+        transform.scale = Vec3::new(*scale, *scale, 1.0);
+        transform.translation.y += (new_height - old_height) / 2.0;
+
+        resized_players.insert(
+            player,
+            Resized {
+                scale: *scale,
+                throw_strength: *throw_strength,
+                knockback: *knockback,
+                base_shape,
+                timer: Timer::new(*duration, TimerMode::Once),
+            },
+        );
+    }
+}
+
+/// Bring resized players back to their normal size once the power-up wears off.
+fn update_resized_players(
+    entities: Res<Entities>,
+    mut resized_players: CompMut<Resized>,
+    mut bodies: CompMut<KinematicBody>,
+    mut transforms: CompMut<Transform>,
+    time: Res<Time>,
+) {
+    let mut expired = Vec::new();
+    for (player, resized) in entities.iter_with(&mut resized_players) {
+        resized.timer.tick(time.delta());
+        if resized.timer.finished() {
+            expired.push(player);
+        }
+    }
+
+    for player in expired {
+        let resized = resized_players.remove(player).unwrap();
+        let Some(body) = bodies.get_mut(player) else {
+            continue;
+        };
+        let old_height = body.bounding_box(default()).height();
+        body.shape = resized.base_shape;
+        let new_height = body.bounding_box(default()).height();
+
+        if let Some(transform) = transforms.get_mut(player) {
+            transform.scale = Vec3::ONE;
+            transform.translation.y += (new_height - old_height) / 2.0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn shapes_are_scaled() {
+        let rect = ColliderShape::Rectangle {
+            size: vec2(10.0, 20.0),
+        };
+        assert_eq!(
+            scale_shape(rect, 0.5),
+            ColliderShape::Rectangle {
+                size: vec2(5.0, 10.0)
+            }
+        );
+        assert_eq!(
+            scale_shape(ColliderShape::Circle { diameter: 4.0 }, 2.0),
+            ColliderShape::Circle { diameter: 8.0 }
+        );
+    }
+}
@@ -0,0 +1,283 @@
+//! Smoke grenade.
+//!
+//! Once lit, the grenade blooms into a cloud of smoke that grows to its full size and lingers for a
+//! while. The cloud itself is part of the game simulation: AI players can't see through it. Hiding
+//! the players inside of it is only a matter of how they are drawn, so it is done by the
+//! [presentation settings][PresentationSettings].
+
+use crate::prelude::*;
+
+pub fn install(session: &mut CoreSession) {
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PreUpdate, hydrate)
+        .add_system_to_stage(CoreStage::PostUpdate, update)
+        .add_system_to_stage(CoreStage::PostUpdate, update_clouds);
+}
+
+#[derive(Clone, Debug, TypeUlid, Default)]
+#[ulid = "01H31K8R2XVF6MZQ0T4NCJ7BWA"]
+pub struct SmokeGrenade {
+    /// The time left until the grenade blooms, once it has been lit.
+    pub fuse: Option<Timer>,
+}
+
+/// A cloud of smoke, that players can't be seen through.
+#[derive(Clone, Debug, TypeUlid)]
+#[ulid = "01H31K8ZB5TE9QHWY3RA0MXN6D"]
+pub struct SmokeCloud {
+    /// The size of the cloud once it has fully bloomed.
+    pub full_size: Vec2,
+    /// The current size of the cloud.
+    pub size: Vec2,
+    /// The time left until the cloud has fully bloomed.
+    pub bloom: Timer,
+    /// The alpha that the players inside of the cloud are rendered with.
+    pub player_alpha: f32,
+}
+
+impl SmokeCloud {
+    /// Get the rectangle covered by the cloud, given its position.
+    pub fn rect(&self, position: Vec2) -> Rect {
+        Rect::new(position.x, position.y, self.size.x, self.size.y)
+    }
+}
+
+/// Whether the line of sight between two positions is blocked by a cloud of smoke.
+pub fn is_line_of_sight_blocked(clouds: &[Rect], from: Vec2, to: Vec2) -> bool {
+    clouds
+        .iter()
+        .any(|cloud| cloud.intersects_segment(from, to))
+}
+
+fn hydrate(
+    game_meta: Res<CoreMetaArc>,
+    mut entities: ResMut<Entities>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    mut element_handles: CompMut<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut smoke_grenades: CompMut<SmokeGrenade>,
+    mut atlas_sprites: CompMut<AtlasSprite>,
+    mut bodies: CompMut<KinematicBody>,
+    mut transforms: CompMut<Transform>,
+    mut items: CompMut<Item>,
+    mut item_throws: CompMut<ItemThrow>,
+    mut item_grabs: CompMut<ItemGrab>,
+    mut respawn_points: CompMut<DehydrateOutOfBounds>,
+    mut spawner_manager: SpawnerManager,
+) {
+    let mut not_hydrated_bitset = hydrated.bitset().clone();
+    not_hydrated_bitset.bit_not();
+    not_hydrated_bitset.bit_and(element_handles.bitset());
+
+    let spawner_entities = entities
+        .iter_with_bitset(&not_hydrated_bitset)
+        .collect::<Vec<_>>();
+
+    for spawner_ent in spawner_entities {
+        let transform = *transforms.get(spawner_ent).unwrap();
+        let element_handle = element_handles.get(spawner_ent).unwrap();
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+
+        if let BuiltinElementKind::SmokeGrenade {
+            atlas,
+            body_diameter,
+            grab_offset,
+            fin_anim,
+            throw_velocity,
+            angular_velocity,
+            can_rotate,
+            bounciness,
+            ..
+        } = &element_meta.builtin
+        {
+            hydrated.insert(spawner_ent, MapElementHydrated);
+
+            let entity = entities.create();
+            items.insert(entity, Item);
+            smoke_grenades.insert(entity, SmokeGrenade::default());
+            item_throws.insert(
+                entity,
+                ItemThrow::strength(*throw_velocity).with_spin(*angular_velocity),
+            );
+            item_grabs.insert(
+                entity,
+                ItemGrab {
+                    fin_anim: *fin_anim,
+                    sync_animation: false,
+                    grab_offset: *grab_offset,
+                },
+            );
+            atlas_sprites.insert(entity, AtlasSprite::new(atlas.clone()));
+            respawn_points.insert(entity, DehydrateOutOfBounds(spawner_ent));
+            transforms.insert(entity, transform);
+            element_handles.insert(entity, element_handle.clone());
+            hydrated.insert(entity, MapElementHydrated);
+            bodies.insert(
+                entity,
+                KinematicBody {
+                    shape: ColliderShape::Circle {
+                        diameter: *body_diameter,
+                    },
+                    has_mass: true,
+                    has_friction: true,
+                    can_rotate: *can_rotate,
+                    bounciness: *bounciness,
+                    gravity: game_meta.physics.gravity,
+                    ..default()
+                },
+            );
+            spawner_manager.create_spawner(spawner_ent, vec![entity])
+        }
+    }
+}
+
+/// Light the grenades that are used, and bloom them into clouds once their fuse runs out.
+fn update(
+    entities: Res<Entities>,
+    mut commands: Commands,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut smoke_grenades: CompMut<SmokeGrenade>,
+    mut items_used: CompMut<ItemUsed>,
+    transforms: Comp<Transform>,
+    spawners: Comp<DehydrateOutOfBounds>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    mut audio_events: ResMut<AudioEvents>,
+    time: Res<Time>,
+) {
+    for (entity, (smoke_grenade, element_handle, spawner)) in
+        entities.iter_with((&mut smoke_grenades, &element_handles, &spawners))
+    {
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+        let BuiltinElementKind::SmokeGrenade {
+            fuse_time,
+            fuse_sound,
+            fuse_sound_volume,
+            cloud,
+            ..
+        } = &element_meta.builtin
+        else {
+            unreachable!();
+        };
+
+        // Light the grenade when it is used
+        if items_used.remove(entity).is_some() && smoke_grenade.fuse.is_none() {
+            audio_events.play(fuse_sound.clone(), *fuse_sound_volume);
+            smoke_grenade.fuse = Some(Timer::new(*fuse_time, TimerMode::Once));
+        }
+
+        let Some(fuse) = &mut smoke_grenade.fuse else {
+            continue;
+        };
+        fuse.tick(time.delta());
+        if !fuse.finished() {
+            continue;
+        }
+
+        // Bloom into a cloud, letting the grenade respawn at its spawner
+        audio_events.play(cloud.sound.clone(), cloud.sound_volume);
+        hydrated.remove(**spawner);
+
+        let mut cloud_transform = *transforms.get(entity).unwrap();
+        cloud_transform.translation.z = -10.0; // On top of almost everything
+        cloud_transform.rotation = Quat::IDENTITY;
+        cloud_transform.scale = Vec3::ZERO;
+
+        let cloud = cloud.clone();
+        commands.add(
+            move |mut entities: ResMut<Entities>,
+                  mut transforms: CompMut<Transform>,
+                  mut sprites: CompMut<AtlasSprite>,
+                  mut animated_sprites: CompMut<AnimatedSprite>,
+                  mut lifetimes: CompMut<Lifetime>,
+                  mut smoke_clouds: CompMut<SmokeCloud>| {
+                // Despawn the grenade
+                entities.kill(entity);
+
+                let ent = entities.create();
+                transforms.insert(ent, cloud_transform);
+                sprites.insert(ent, AtlasSprite::new(cloud.atlas.clone()));
+                animated_sprites.insert(
+                    ent,
+                    AnimatedSprite {
+                        frames: (0..cloud.frames).collect(),
+                        fps: cloud.fps,
+                        repeat: true,
+                        ..default()
+                    },
+                );
+                lifetimes.insert(ent, Lifetime::new(cloud.duration.as_secs_f32()));
+                smoke_clouds.insert(
+                    ent,
+                    SmokeCloud {
+                        full_size: cloud.size,
+                        size: Vec2::ZERO,
+                        bloom: Timer::new(cloud.bloom_time, TimerMode::Once),
+                        player_alpha: cloud.player_alpha,
+                    },
+                );
+            },
+        );
+    }
+}
+
+/// Grow the clouds until they have fully bloomed.
+fn update_clouds(
+    entities: Res<Entities>,
+    mut smoke_clouds: CompMut<SmokeCloud>,
+    mut transforms: CompMut<Transform>,
+    time: Res<Time>,
+) {
+    for (entity, smoke_cloud) in entities.iter_with(&mut smoke_clouds) {
+        smoke_cloud.bloom.tick(time.delta());
+        let scale = if smoke_cloud.bloom.duration().is_zero() {
+            1.0
+        } else {
+            smoke_cloud.bloom.percent()
+        };
+        smoke_cloud.size = smoke_cloud.full_size * scale;
+        if let Some(transform) = transforms.get_mut(entity) {
+            transform.scale = Vec3::new(scale, scale, 1.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn clouds_block_the_line_of_sight_through_them() {
+        let clouds = [Rect::new(0.0, 0.0, 20.0, 20.0)];
+        assert!(is_line_of_sight_blocked(
+            &clouds,
+            vec2(-50.0, 0.0),
+            vec2(50.0, 5.0)
+        ));
+        assert!(is_line_of_sight_blocked(
+            &clouds,
+            vec2(0.0, 0.0),
+            vec2(50.0, 50.0)
+        ));
+        assert!(!is_line_of_sight_blocked(
+            &clouds,
+            vec2(-50.0, 20.0),
+            vec2(50.0, 20.0)
+        ));
+        assert!(!is_line_of_sight_blocked(
+            &clouds,
+            vec2(15.0, -50.0),
+            vec2(15.0, 50.0)
+        ));
+        assert!(!is_line_of_sight_blocked(
+            &clouds,
+            vec2(-50.0, 0.0),
+            vec2(-20.0, 0.0)
+        ));
+    }
+}
@@ -4,12 +4,13 @@ pub fn install(session: &mut CoreSession) {
     session
         .stages
         .add_system_to_stage(CoreStage::PreUpdate, hydrate)
-        .add_system_to_stage(CoreStage::PostUpdate, update)
-        .add_system_to_stage(CoreStage::PostUpdate, update_wearer);
+        .add_system_to_stage(CoreStage::PostUpdate, update);
 }
 
 /// Marker component added to things ( presumably players, but not necessarily! ) that are wearing
-/// stomp boots
+/// stomp boots.
+///
+/// Players wearing stomp boots kill the players they stomp on instead of incapacitating them.
 #[derive(Debug, Clone, Copy, Default, TypeUlid)]
 #[ulid = "01GR0P6HDCXJA6P2VNN8E1TH6Q"]
 pub struct WearingStompBoots;
@@ -153,44 +154,3 @@ fn update(
         }
     }
 }
-
-fn update_wearer(
-    entities: Res<Entities>,
-    mut commands: Commands,
-    wearing_stomp_boots: Comp<WearingStompBoots>,
-    player_indexes: Comp<PlayerIdx>,
-    collision_world: CollisionWorld,
-    kinematic_bodies: Comp<KinematicBody>,
-    transforms: Comp<Transform>,
-) {
-    for (entity, _) in entities.iter_with(&wearing_stomp_boots) {
-        let kinematic_body = kinematic_bodies.get(entity).unwrap();
-        if kinematic_body.velocity.y > 0.
-            || kinematic_body.is_on_ground
-            || kinematic_body.is_on_platform
-        {
-            continue;
-        }
-        collision_world
-            .actor_collisions_filtered(entity, |e| player_indexes.contains(e))
-            .into_iter()
-            .for_each(|player| {
-                let wearer_transform = transforms
-                    .get(entity)
-                    .expect("stomp boots wearer should have Transform component");
-                let player_transform = transforms.get(player).unwrap();
-                let player_kinematic_body = kinematic_bodies.get(player).unwrap();
-                if kinematic_body.bounding_box(*wearer_transform).bottom()
-                    > player_kinematic_body
-                        .bounding_box(*player_transform)
-                        .center()
-                        .y
-                {
-                    commands.add(PlayerCommand::kill(
-                        player,
-                        Some(player_transform.translation.xy()),
-                    ))
-                }
-            });
-    }
-}
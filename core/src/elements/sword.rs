@@ -1,6 +1,6 @@
 use std::time::Duration;
 
-use crate::{damage::DamageRegion, prelude::*};
+use crate::{damage::DamageRegion, feature_flags, prelude::*};
 
 pub fn install(session: &mut CoreSession) {
     session
@@ -14,20 +14,39 @@ pub fn install(session: &mut CoreSession) {
 pub struct Sword {
     pub state: SwordState,
     pub dropped_time: f32,
+    /// The index of the swing of the combo chain that will be done if the sword is used before
+    /// the combo window is over.
+    pub combo_step: usize,
+    /// The number of frames shoot has been held for since it was pressed.
+    pub held_frames: usize,
 }
 
 #[derive(Default, Clone, Copy, Debug)]
 pub enum SwordState {
     #[default]
     Idle,
+    /// Shoot is still held after a swing, charging a heavy swing.
+    Charging {
+        frame: usize,
+    },
     Swinging {
         frame: usize,
+        swing: SwordSwing,
     },
     Cooldown {
         frame: usize,
     },
 }
 
+/// Which of the sword's swings is being done.
+#[derive(Clone, Copy, Debug)]
+pub enum SwordSwing {
+    /// The swing with the given index in the combo chain.
+    Combo(usize),
+    /// The charged swing.
+    Heavy,
+}
+
 fn hydrate(
     game_meta: Res<CoreMetaArc>,
     mut entities: ResMut<Entities>,
@@ -124,7 +143,7 @@ fn update(
     mut audio_events: ResMut<AudioEvents>,
     mut swords: CompMut<Sword>,
     mut sprites: CompMut<AtlasSprite>,
-    bodies: CompMut<KinematicBody>,
+    mut bodies: CompMut<KinematicBody>,
     mut items_used: CompMut<ItemUsed>,
     player_indexes: Comp<PlayerIdx>,
     player_inventories: PlayerInventories,
@@ -132,7 +151,25 @@ fn update(
     mut player_layers: CompMut<PlayerLayers>,
     transforms: CompMut<Transform>,
     invincibles: CompMut<Invincibility>,
+    match_settings: Res<MatchSettings>,
+    player_inputs: Res<PlayerInputs>,
+    spawners: Comp<DehydrateOutOfBounds>,
+    mut inventories: CompMut<Inventory>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    mut hit_events: ResMut<EventChannel<HitEvent>>,
 ) {
+    let deflects_bullets = match_settings.has_feature_flag(feature_flags::SWORDS_DEFLECT_BULLETS);
+
+    let parried = parried_swords(
+        &entities,
+        &swords,
+        &element_handles,
+        &element_assets,
+        &player_inventories,
+        &transforms,
+        &sprites,
+    );
+
     for (entity, (sword, element_handle)) in entities.iter_with((&mut swords, &element_handles)) {
         let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
             continue;
@@ -146,6 +183,7 @@ fn update(
                       mut emote_regions: CompMut<EmoteRegion>,
                       mut damage_regions: CompMut<DamageRegion>,
                       mut damage_region_owners: CompMut<DamageRegionOwner>,
+                      mut deflectors: CompMut<BulletDeflector>,
                       mut lifetimes: CompMut<Lifetime>| {
                     let entity = entities.create();
 
@@ -162,9 +200,18 @@ fn update(
                     );
 
                     lifetimes.insert(entity, Lifetime::new(2.0 / 60.0));
-                    damage_regions.insert(entity, DamageRegion { size });
+                    damage_regions.insert(
+                        entity,
+                        DamageRegion {
+                            size,
+                            source: DamageSource::Player(owner),
+                        },
+                    );
                     transforms.insert(entity, Transform::from_translation(pos));
                     damage_region_owners.insert(entity, DamageRegionOwner(owner));
+                    if deflects_bullets {
+                        deflectors.insert(entity, BulletDeflector { owner });
+                    }
                 },
             );
         };
@@ -174,10 +221,18 @@ fn update(
             sound,
             sound_volume,
             killing_speed,
+            combo,
+            combo_window_frames,
+            heavy_swing,
+            parry,
             ..
         } = &element_meta.builtin else {
             unreachable!();
         };
+        let swing_meta = |swing: SwordSwing| match swing {
+            SwordSwing::Combo(i) => combo.get(i),
+            SwordSwing::Heavy => heavy_swing.as_ref().map(|x| &x.swing),
+        };
 
         // If the item is being held
         if let Some(inventory) = player_inventories
@@ -189,6 +244,9 @@ fn update(
             let player_translation = transforms.get(player).unwrap().translation;
             let flip = sprite.flip_x;
             let flip_factor = if flip { -1.0 } else { 1.0 };
+            let player_idx = player_indexes.get(player).unwrap().0;
+            let shoot_held = player_inputs.players[player_idx].control.shoot_pressed;
+            sword.held_frames = if shoot_held { sword.held_frames + 1 } else { 0 };
 
             let player_layer = player_layers.get_mut(player).unwrap();
 
@@ -200,66 +258,80 @@ fn update(
             let mut next_state = None;
             match &mut sword.state {
                 SwordState::Idle => (),
-                SwordState::Swinging { frame } => {
-                    // If we're at the end of the swinging animation
-                    if sprite.index >= 11 {
-                        player_layer.fin_offset = Vec2::ZERO;
-                        // Go to cooldown frames
-                        next_state = Some(SwordState::Cooldown { frame: 0 });
+                SwordState::Charging { frame } => {
+                    let charged = heavy_swing
+                        .as_ref()
+                        .filter(|heavy| *frame >= heavy.charge_frames);
+                    if let Some(heavy) = charged {
+                        sprite.index = heavy.charge_sprite_index;
+                    }
 
-                    // If we're still swinging
+                    // Do the heavy swing when shoot is released, if it has been charged
+                    if shoot_held {
+                        *frame += 1;
+                    } else if charged.is_some() {
+                        next_state = Some(SwordState::Swinging {
+                            frame: 0,
+                            swing: SwordSwing::Heavy,
+                        });
+                        audio_events.play(sound.clone(), *sound_volume);
                     } else {
-                        // Set the current attack frame to the animation index
-                        sprite.index = 8 + *frame / 3;
+                        next_state = Some(SwordState::Idle);
                     }
+                }
+                SwordState::Swinging { frame, swing } => {
+                    // Find the stage of the swing that we are in
+                    let stage = swing_meta(*swing).and_then(|swing| swing.stage_at(*frame));
 
-                    // TODO: Move all these constants to the builtin item config
-                    match *frame / 3 {
-                        0 => {
-                            spawn_damage_region(
-                                Vec3::new(
-                                    player_translation.x + 20.0 * flip_factor,
-                                    player_translation.y + 20.0,
-                                    player_translation.z,
-                                ),
-                                Vec2::new(30.0, 70.0),
-                                player,
-                            );
-
-                            player_layer.fin_offset = vec2(-1.0, 2.0);
-                        }
-                        1 => {
-                            spawn_damage_region(
-                                Vec3::new(
-                                    player_translation.x + 25.0 * flip_factor,
-                                    player_translation.y + 20.0,
-                                    player_translation.z,
-                                ),
-                                Vec2::new(40.0, 50.0),
+                    // If our swing clashed with another sword, both swings are cancelled
+                    if let Some(parry) = parry.as_ref().filter(|_| parried.contains(&entity)) {
+                        audio_events.play(parry.sound.clone(), parry.sound_volume);
+                        if let Some(body) = bodies.get_mut(player) {
+                            body.velocity.x = -flip_factor * parry.knockback;
+                            hit_events.send(HitEvent {
                                 player,
-                            );
-                            player_layer.fin_offset = vec2(0.0, -1.0);
+                                damage: 0,
+                                knockback: vec2(body.velocity.x, 0.0),
+                            });
                         }
-                        2 => {
+                        player_layer.fin_offset = Vec2::ZERO;
+                        sword.combo_step = 0;
+                        next_state = Some(SwordState::Cooldown { frame: 0 });
+                    } else if let Some(stage) = stage {
+                        sprite.index = stage.sprite_index;
+                        player_layer.fin_offset = stage.fin_offset;
+                        if let Some(damage) = &stage.damage {
                             spawn_damage_region(
                                 Vec3::new(
-                                    player_translation.x + 20.0 * flip_factor,
-                                    player_translation.y,
+                                    player_translation.x + damage.offset.x * flip_factor,
+                                    player_translation.y + damage.offset.y,
                                     player_translation.z,
                                 ),
-                                Vec2::new(40.0, 40.0),
+                                damage.size,
                                 player,
                             );
-                            player_layer.fin_offset = vec2(0.0, -2.0);
                         }
-                        _ => (),
-                    }
 
-                    *frame += 1;
+                        *frame += 1;
+
+                    // If we're at the end of the swinging animation
+                    } else {
+                        player_layer.fin_offset = Vec2::ZERO;
+
+                        // Continue the combo chain, unless this was its last swing
+                        sword.combo_step = match *swing {
+                            SwordSwing::Combo(i) if i + 1 < combo.len() => i + 1,
+                            _ => 0,
+                        };
+
+                        // Go to cooldown frames
+                        next_state = Some(SwordState::Cooldown { frame: 0 });
+                    }
                 }
                 SwordState::Cooldown { frame } => {
                     if *frame >= *cooldown_frames {
                         next_state = Some(SwordState::Idle);
+                        sword.combo_step = 0;
                     } else {
                         *frame += 1;
                     }
@@ -267,16 +339,56 @@ fn update(
             }
 
             if let Some(next) = next_state {
+                // With the one-hit swords mutator, the sword breaks after its first swing.
+                if matches!(next, SwordState::Cooldown { .. })
+                    && match_settings.has_mutator(Mutator::OneHitSwords)
+                {
+                    if let Some(spawner) = spawners.get(entity) {
+                        hydrated.remove(**spawner);
+                    }
+                    inventories.insert(player, Inventory(None));
+                    commands.add(move |mut entities: ResMut<Entities>| {
+                        entities.kill(entity);
+                    });
+                    continue;
+                }
+
                 sword.state = next;
             }
 
+            // Start charging a heavy swing when shoot is held long enough after a normal swing
+            if let Some(heavy) = heavy_swing {
+                if sword.held_frames >= heavy.hold_frames
+                    && matches!(sword.state, SwordState::Idle | SwordState::Cooldown { .. })
+                {
+                    sword.combo_step = 0;
+                    sword.state = SwordState::Charging { frame: 0 };
+                }
+            }
+
             // If the item is being used
             let item_used = items_used.get(entity).is_some();
             if item_used {
                 items_used.remove(entity);
-                if matches!(sword.state, SwordState::Idle) {
-                    sprite.index = 8;
-                    sword.state = SwordState::Swinging { frame: 0 };
+                let continues_combo = sword.combo_step > 0
+                    && matches!(
+                        sword.state,
+                        SwordState::Cooldown { frame } if frame < *combo_window_frames
+                    );
+
+                let swing = if continues_combo {
+                    Some(SwordSwing::Combo(sword.combo_step))
+                } else if matches!(sword.state, SwordState::Idle) {
+                    Some(SwordSwing::Combo(0))
+                } else {
+                    None
+                };
+
+                if let Some(swing) = swing {
+                    if let Some(stage) = swing_meta(swing).and_then(|x| x.stages.first()) {
+                        sprite.index = stage.sprite_index;
+                    }
+                    sword.state = SwordState::Swinging { frame: 0, swing };
                     audio_events.play(sound.clone(), *sound_volume);
                 }
             }
@@ -308,6 +420,69 @@ fn update(
     }
 }
 
+/// Find the swords that are swinging into the swing of another player's sword this frame.
+fn parried_swords(
+    entities: &Entities,
+    swords: &CompMut<Sword>,
+    element_handles: &Comp<ElementHandle>,
+    element_assets: &BevyAssets<ElementMeta>,
+    player_inventories: &PlayerInventories,
+    transforms: &CompMut<Transform>,
+    sprites: &CompMut<AtlasSprite>,
+) -> Vec<Entity> {
+    let swings = entities
+        .iter_with((swords, element_handles))
+        .filter_map(|(entity, (sword, element_handle))| {
+            let SwordState::Swinging { frame, swing } = sword.state else {
+                return None;
+            };
+            let element_meta = element_assets.get(&element_handle.get_bevy_handle())?;
+            let BuiltinElementKind::Sword {
+                combo,
+                heavy_swing,
+                parry: Some(_),
+                ..
+            } = &element_meta.builtin
+            else {
+                return None;
+            };
+            let swing_meta = match swing {
+                SwordSwing::Combo(i) => combo.get(i),
+                SwordSwing::Heavy => heavy_swing.as_ref().map(|x| &x.swing),
+            };
+            let damage = swing_meta?.stage_at(frame)?.damage.as_ref()?;
+
+            let player = player_inventories
+                .iter()
+                .find_map(|x| x.filter(|x| x.inventory == entity))?
+                .player;
+            let player_translation = transforms.get(player)?.translation;
+            let flip_factor = if sprites.get(entity)?.flip_x {
+                -1.0
+            } else {
+                1.0
+            };
+            let region = Rect::new(
+                player_translation.x + damage.offset.x * flip_factor,
+                player_translation.y + damage.offset.y,
+                damage.size.x,
+                damage.size.y,
+            );
+            Some((entity, player, region))
+        })
+        .collect::<Vec<_>>();
+
+    swings
+        .iter()
+        .filter(|(_, player, region)| {
+            swings
+                .iter()
+                .any(|(_, other_player, other)| other_player != player && region.overlaps(other))
+        })
+        .map(|(entity, ..)| *entity)
+        .collect()
+}
+
 fn sword_drop(entity: Entity) -> System {
     (move |mut swords: CompMut<Sword>, mut sprites: CompMut<AtlasSprite>| {
         // Put sword in rest position
@@ -0,0 +1,262 @@
+//! Shock trap trip wire.
+//!
+//! The wire is stretched from the element to an anchor point set in the editor. The first player
+//! to cross it gets shocked: they are knocked back, drop what they are holding, and are stunned for
+//! a while. The wire then needs to recharge before it can shock anybody again.
+
+use crate::prelude::*;
+
+pub fn install(session: &mut CoreSession) {
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PreUpdate, hydrate)
+        .add_system_to_stage(CoreStage::PostUpdate, update_wires)
+        // Stunning the players changes their state, so it has to happen with the state transitions.
+        .add_system_to_stage(PlayerStateStage, shock_players);
+}
+
+/// The z offset of the wire from the posts, so that it is drawn in front of them.
+const WIRE_Z_OFFSET: f32 = 0.1;
+/// The horizontal speed, in pixels per frame, that the shocked players lose every frame.
+const SHOCK_SLOWDOWN: f32 = 0.3;
+
+#[derive(Clone, Debug, TypeUlid)]
+#[ulid = "01H31TWB6QZ9XN2KC4RM8YJD5F"]
+pub struct TripWire {
+    /// The entity drawing the wire.
+    pub line: Entity,
+    /// The post at the far end of the wire.
+    pub end_post: Entity,
+    /// The time left until the wire can shock a player again, if it is recharging.
+    pub recharge: Option<Timer>,
+}
+
+impl TripWire {
+    /// Whether the wire is ready to shock a player.
+    pub fn is_charged(&self) -> bool {
+        self.recharge.is_none()
+    }
+}
+
+/// Get the two ends of a wire, given the position of the element and its far anchor point.
+fn wire_ends(position: Vec2, anchor: Vec2) -> (Vec2, Vec2) {
+    (position, position + anchor)
+}
+
+fn hydrate(
+    mut entities: ResMut<Entities>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut trip_wires: CompMut<TripWire>,
+    mut atlas_sprites: CompMut<AtlasSprite>,
+    mut transforms: CompMut<Transform>,
+    mut paths: CompMut<Path2d>,
+    mut element_kill_callbacks: CompMut<ElementKillCallback>,
+) {
+    let mut not_hydrated_bitset = hydrated.bitset().clone();
+    not_hydrated_bitset.bit_not();
+    not_hydrated_bitset.bit_and(element_handles.bitset());
+
+    let wire_entities = entities
+        .iter_with_bitset(&not_hydrated_bitset)
+        .collect::<Vec<_>>();
+
+    for entity in wire_entities {
+        let element_handle = element_handles.get(entity).unwrap();
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+
+        if let BuiltinElementKind::TripWire { atlas, .. } = &element_meta.builtin {
+            hydrated.insert(entity, MapElementHydrated);
+            atlas_sprites.insert(entity, AtlasSprite::new(atlas.clone()));
+
+            // The wire and the far post are positioned by `update_wires`
+            let transform = *transforms.get(entity).unwrap();
+            let line = entities.create();
+            transforms.insert(
+                line,
+                Transform::from_translation(Vec3::new(
+                    0.0,
+                    0.0,
+                    transform.translation.z + WIRE_Z_OFFSET,
+                )),
+            );
+            paths.insert(line, default());
+            let end_post = entities.create();
+            transforms.insert(end_post, transform);
+            atlas_sprites.insert(end_post, AtlasSprite::new(atlas.clone()));
+
+            trip_wires.insert(
+                entity,
+                TripWire {
+                    line,
+                    end_post,
+                    recharge: None,
+                },
+            );
+            element_kill_callbacks.insert(
+                entity,
+                ElementKillCallback::new(move |mut entities: ResMut<Entities>| {
+                    entities.kill(line);
+                    entities.kill(end_post);
+                    entities.kill(entity);
+                }),
+            );
+        }
+    }
+}
+
+/// Recharge the wires, and stretch them to their anchor points, which may be moved in the editor.
+fn update_wires(
+    entities: Res<Entities>,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    wire_anchors: Comp<WireAnchorMeta>,
+    mut trip_wires: CompMut<TripWire>,
+    mut transforms: CompMut<Transform>,
+    mut paths: CompMut<Path2d>,
+    time: Res<Time>,
+) {
+    for (entity, (trip_wire, element_handle)) in
+        entities.iter_with((&mut trip_wires, &element_handles))
+    {
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+        let BuiltinElementKind::TripWire {
+            default_anchor,
+            wire_color,
+            recharging_color,
+            wire_thickness,
+            ..
+        } = &element_meta.builtin
+        else {
+            unreachable!();
+        };
+
+        if let Some(recharge) = &mut trip_wire.recharge {
+            recharge.tick(time.delta());
+            if recharge.finished() {
+                trip_wire.recharge = None;
+            }
+        }
+
+        let transform = *transforms.get(entity).unwrap();
+        let anchor = wire_anchors
+            .get(entity)
+            .map_or(*default_anchor, |x| x.offset);
+        let (start, end) = wire_ends(transform.translation.truncate(), anchor);
+
+        if let Some(end_post) = transforms.get_mut(trip_wire.end_post) {
+            end_post.translation = end.extend(transform.translation.z);
+        }
+        if let Some(path) = paths.get_mut(trip_wire.line) {
+            path.points = vec![start, end];
+            path.color = if trip_wire.is_charged() {
+                wire_color.0
+            } else {
+                recharging_color.0
+            };
+            path.thickness = *wire_thickness;
+        }
+    }
+}
+
+/// Shock the first player to cross each charged wire.
+fn shock_players(
+    entities: Res<Entities>,
+    mut commands: Commands,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    wire_anchors: Comp<WireAnchorMeta>,
+    mut trip_wires: CompMut<TripWire>,
+    transforms: Comp<Transform>,
+    player_indexes: Comp<PlayerIdx>,
+    players_killed: Comp<PlayerKilled>,
+    invincibles: Comp<Invincibility>,
+    mut player_states: CompMut<PlayerState>,
+    mut slipping: CompMut<Slipping>,
+    mut status_effects: CompMut<StatusEffects>,
+    mut bodies: CompMut<KinematicBody>,
+    mut audio_events: ResMut<AudioEvents>,
+) {
+    for (entity, (trip_wire, element_handle, transform)) in
+        entities.iter_with((&mut trip_wires, &element_handles, &transforms))
+    {
+        if !trip_wire.is_charged() {
+            continue;
+        }
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+        let BuiltinElementKind::TripWire {
+            default_anchor,
+            shock_velocity,
+            stun_duration,
+            recharge_time,
+            shock_sound,
+            shock_volume,
+            ..
+        } = &element_meta.builtin
+        else {
+            unreachable!();
+        };
+
+        let anchor = wire_anchors
+            .get(entity)
+            .map_or(*default_anchor, |x| x.offset);
+        let (start, end) = wire_ends(transform.translation.truncate(), anchor);
+
+        let Some(player) = entities
+            .iter_with((&player_indexes, &transforms))
+            .map(|(player, _)| player)
+            .find(|player| {
+                !players_killed.contains(*player)
+                    && !invincibles.contains(*player)
+                    && player_states
+                        .get(*player)
+                        .map_or(false, |x| x.current != key!("core::incapacitated"))
+                    && bodies.get(*player).map_or(false, |body| {
+                        body.bounding_box(*transforms.get(*player).unwrap())
+                            .intersects_segment(start, end)
+                    })
+            })
+        else {
+            continue;
+        };
+
+        // Knock the player back the way they came, and stun them
+        let body = bodies.get_mut(player).unwrap();
+        let direction = if body.velocity.x > 0.0 { -1.0 } else { 1.0 };
+        body.velocity = vec2(shock_velocity.x * direction, shock_velocity.y);
+        Slipping::apply(
+            player,
+            SHOCK_SLOWDOWN,
+            *stun_duration,
+            &mut player_states,
+            &mut slipping,
+            &mut status_effects,
+        );
+        commands.add(PlayerCommand::set_inventory(player, None));
+        audio_events.play(shock_sound.clone(), *shock_volume);
+
+        trip_wire.recharge = Some(Timer::new(*recharge_time, TimerMode::Once));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn players_crossing_the_wire_are_caught() {
+        let (start, end) = wire_ends(vec2(0.0, 10.0), vec2(64.0, 0.0));
+        let player = |x: f32, y: f32| Rect::new(x, y, 16.0, 24.0);
+        assert!(player(32.0, 20.0).intersects_segment(start, end));
+        assert!(player(-6.0, 0.0).intersects_segment(start, end));
+        assert!(!player(-10.0, 20.0).intersects_segment(start, end));
+        assert!(!player(32.0, 40.0).intersects_segment(start, end));
+    }
+}
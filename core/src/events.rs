@@ -0,0 +1,71 @@
+//! Event channels.
+//!
+//! An [`EventChannel`] is a resource containing a queue of events of a single type, that systems
+//! can use to notify each other without mutating each other's components or queuing
+//! [`Commands`]. Because the channel is a resource of the world, it is snapshotted and rolled back
+//! along with everything else.
+//!
+//! Each channel is cleared once per frame by a [`clear_events()`] system, added to a stage right
+//! before the systems that send the events:
+//!
+//! ```ignore
+//! session.world.init_resource::<EventChannel<MyEvent>>();
+//! session
+//!     .stages
+//!     .add_system_to_stage(CoreStage::PostUpdate, clear_events::<MyEvent>)
+//!     .add_system_to_stage(CoreStage::PostUpdate, send_my_events);
+//! ```
+//!
+//! That way events stay in the channel for a whole frame, and every system sees every event
+//! exactly once: systems that run after the senders see them on the same frame, and the others
+//! see them on the next frame.
+
+use crate::prelude::*;
+
+/// XOR-ed with the ULID of the event type to get the ULID of its [`EventChannel`].
+const EVENT_CHANNEL_ULID_MASK: u128 = 0x0188_E2B1_5C4F_7A93_D60E_2F1B_84C7_A5E9;
+
+/// A resource containing the events of type `T` sent during the last frame.
+#[derive(Clone, Debug)]
+pub struct EventChannel<T> {
+    events: Vec<T>,
+}
+
+impl<T: TypeUlid> TypeUlid for EventChannel<T> {
+    const ULID: Ulid = Ulid(T::ULID.0 ^ EVENT_CHANNEL_ULID_MASK);
+}
+
+impl<T> Default for EventChannel<T> {
+    fn default() -> Self {
+        Self { events: default() }
+    }
+}
+
+impl<T> EventChannel<T> {
+    /// Send an event.
+    pub fn send(&mut self, event: T) {
+        self.events.push(event);
+    }
+
+    /// Iterate over the events, in the order they were sent.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.events.iter()
+    }
+
+    /// Whether or not no events have been sent.
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Remove all of the events.
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+}
+
+/// System that clears the [`EventChannel`] of events of type `T`.
+pub fn clear_events<T: TypeUlid + Clone + Send + Sync + 'static>(
+    mut channel: ResMut<EventChannel<T>>,
+) {
+    channel.clear();
+}
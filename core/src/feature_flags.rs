@@ -0,0 +1,45 @@
+//! Experimental element behaviors that may be switched on for a single match.
+//!
+//! Feature flags are selected by name in [`MatchSettings::feature_flags`], and element systems
+//! check them with [`MatchSettings::has_feature_flag()`]. This lets playtests compare a gameplay
+//! change against the current behavior in the same build, by playing one match with the flag and
+//! one without it.
+//!
+//! Unlike [mutators][crate::mutator], flags are plain strings, so settings naming a flag that this
+//! build doesn't know about still load, and the flag is simply ignored.
+
+/// Lit grenades that touch a wall stick to it until they are picked up again.
+pub const STICKY_GRENADES: &str = "sticky-grenades";
+
+/// Swinging a sword sends the bullets it hits back the way they came, now shot by the swinging
+/// player.
+pub const SWORDS_DEFLECT_BULLETS: &str = "swords-deflect-bullets";
+
+/// All of the feature flags read by this build.
+pub const ALL: [&str; 2] = [STICKY_GRENADES, SWORDS_DEFLECT_BULLETS];
+
+/// The localization key for the name of the given feature flag.
+pub fn name_key(flag: &str) -> String {
+    format!("feature-flag-{flag}")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn toggle_feature_flags() {
+        let mut settings = MatchSettings::default();
+        assert!(!settings.has_feature_flag(STICKY_GRENADES));
+
+        settings.set_feature_flag_enabled(STICKY_GRENADES, true);
+        settings.set_feature_flag_enabled(STICKY_GRENADES, true);
+        assert!(settings.has_feature_flag(STICKY_GRENADES));
+        assert!(!settings.has_feature_flag(SWORDS_DEFLECT_BULLETS));
+        assert_eq!(settings.feature_flags.len(), 1);
+
+        settings.set_feature_flag_enabled(STICKY_GRENADES, false);
+        assert!(!settings.has_feature_flag(STICKY_GRENADES));
+    }
+}
@@ -0,0 +1,85 @@
+//! Foreground map layers.
+//!
+//! Map layers with [`MapLayerMeta::foreground`] set are rendered in front of the players, and may
+//! move with a slight parallax to look closer to the camera than the rest of the map, unless
+//! [`PresentationSettings::reduced_motion`] is enabled.
+//!
+//! Foreground layers with a [`fade_alpha`][MapForegroundMeta::fade_alpha] become transparent while
+//! a player is behind one of their tiles. Tile layers can't be made transparent, so the tile layers
+//! of those layers are hidden, and the game draws their tiles itself. The fading is purely visual,
+//! so it is left out of the simulation.
+
+use crate::prelude::*;
+
+/// Install this module.
+pub fn install(session: &mut CoreSession) {
+    session
+        .stages
+        .add_system_to_stage(CoreStage::Last, update_foreground_layers);
+}
+
+/// The depth of the first foreground layer, in front of the players and all of the other layers.
+pub const MAP_FOREGROUND_MIN_DEPTH: f32 = -100.0;
+
+/// Get the Z depth that a foreground layer with the given index should be rendered at.
+pub fn z_depth_for_foreground_layer(layer_idx: usize) -> f32 {
+    MAP_FOREGROUND_MIN_DEPTH + layer_idx as f32 * MAP_LAYERS_GAP_DEPTH / 10.0
+}
+
+/// Component added to the tile layer entities of foreground layers.
+#[derive(Clone, TypeUlid, Debug)]
+#[ulid = "01H2D1V6B3QWJ8YNK4XE0RTZ5M"]
+pub struct ForegroundLayer {
+    /// The foreground settings of the layer.
+    pub meta: MapForegroundMeta,
+}
+
+impl ForegroundLayer {
+    pub fn new(meta: MapForegroundMeta) -> Self {
+        Self { meta }
+    }
+}
+
+/// Move the foreground layers according to the camera position, and hide the tile layers of the
+/// foreground layers that may fade.
+fn update_foreground_layers(
+    entities: Res<Entities>,
+    map: Res<LoadedMap>,
+    presentation: Res<PresentationSettings>,
+    cameras: Comp<Camera>,
+    spawned_map_layer_metas: Comp<SpawnedMapLayerMeta>,
+    foreground_layers: Comp<ForegroundLayer>,
+    mut transforms: CompMut<Transform>,
+) {
+    let map_size = map.grid_size.as_vec2() * map.tile_size;
+    let Some(camera_pos) = entities
+        .iter_with((&transforms, &cameras))
+        .next()
+        .map(|(_, (transform, _))| transform.translation.truncate())
+    else {
+        return;
+    };
+    let camera_offset = map_size / 2.0 - camera_pos;
+
+    for (layer_ent, foreground) in entities.iter_with(&foreground_layers) {
+        let layer_idx = spawned_map_layer_metas
+            .get(layer_ent)
+            .map(|x| x.layer_idx)
+            .unwrap_or_default();
+        let parallax = if presentation.reduced_motion {
+            0.0
+        } else {
+            foreground.meta.parallax
+        };
+        let Some(transform) = transforms.get_mut(layer_ent) else {
+            continue;
+        };
+        transform.translation =
+            (camera_offset * parallax).extend(z_depth_for_foreground_layer(layer_idx));
+        transform.scale = if foreground.meta.fade_alpha.is_some() {
+            Vec3::ZERO
+        } else {
+            Vec3::ONE
+        };
+    }
+}
@@ -0,0 +1,10 @@
+//! Match modes with their own rules for scoring and winning rounds.
+
+use crate::prelude::*;
+
+pub mod ctf;
+
+/// Install this module.
+pub fn install(session: &mut CoreSession) {
+    ctf::install(session);
+}
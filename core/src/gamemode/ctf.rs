@@ -0,0 +1,167 @@
+//! Capture the flag matches.
+//!
+//! When [`MatchSettings::mode`] is [`MatchMode::CaptureTheFlag`], the players are split into teams
+//! like in a team match, and the [flag stands][crate::elements::flag_stand] of the map hold the flag
+//! of their team. A team captures a flag by bringing the flag of the other team to its own stand,
+//! while its own flag is at the stand, and the [`CtfScore`] counts the captures of each team.
+//!
+//! Killed players respawn right away. When [`MatchSettings::rounds_to_win`] is set, the round is
+//! won by the first team to make [`CAPTURES_PER_ROUND`] captures.
+
+use crate::{
+    elements::flag_stand::{return_flag, Flag, FlagStand, FlagState},
+    prelude::*,
+};
+
+/// Install this module.
+pub fn install(session: &mut CoreSession) {
+    session.world.init_resource::<CtfScore>();
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PostUpdate, capture_flags);
+}
+
+/// The number of captures that win a round, when the match is played over multiple rounds.
+pub const CAPTURES_PER_ROUND: u32 = 3;
+
+/// Resource containing the number of flags captured by each team in the current round.
+#[derive(Clone, Debug, Default, TypeUlid)]
+#[ulid = "01H34B7C5TNQ2WX8ZRK4MV9JDE"]
+pub struct CtfScore {
+    /// The number of flags captured by each team.
+    pub captures: [u32; TEAM_COUNT],
+}
+
+impl CtfScore {
+    /// Count a capture for the given team, returning whether the team has won the round.
+    pub fn capture(&mut self, team: u8) -> bool {
+        let captures = &mut self.captures[team as usize];
+        *captures += 1;
+        *captures >= CAPTURES_PER_ROUND
+    }
+}
+
+/// Score the flags that are carried to the stand of their carrier's team, and end the round when a
+/// team has captured enough of them.
+fn capture_flags(
+    entities: Res<Entities>,
+    match_settings: Res<MatchSettings>,
+    player_inputs: Res<PlayerInputs>,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    flag_stands: Comp<FlagStand>,
+    mut flags: CompMut<Flag>,
+    player_indexes: Comp<PlayerIdx>,
+    bodies: Comp<KinematicBody>,
+    mut transforms: CompMut<Transform>,
+    mut player_body_attachments: CompMut<PlayerBodyAttachment>,
+    mut audio_events: ResMut<AudioEvents>,
+    mut ctf_score: ResMut<CtfScore>,
+    mut match_score: ResMut<MatchScore>,
+) {
+    if match_settings.mode != MatchMode::CaptureTheFlag || match_score.state != RoundState::Playing
+    {
+        return;
+    }
+
+    let stand_meta = |stand_ent| {
+        let element_meta = element_handles
+            .get(stand_ent)
+            .and_then(|x| element_assets.get(&x.get_bevy_handle()))?;
+        match &element_meta.builtin {
+            BuiltinElementKind::FlagStand {
+                body_size,
+                flag_offset,
+                capture_sound,
+                capture_sound_volume,
+                ..
+            } => Some((
+                *body_size,
+                *flag_offset,
+                capture_sound.clone(),
+                *capture_sound_volume,
+            )),
+            _ => None,
+        }
+    };
+
+    // The stands that a team may score at, because the flag of the team is on them
+    let home_stands = entities
+        .iter_with((&flag_stands, &transforms))
+        .filter(|(_, (stand, _))| {
+            stand
+                .flag
+                .and_then(|flag| flags.get(flag))
+                .map_or(false, |flag| matches!(flag.state, FlagState::AtStand))
+        })
+        .filter_map(|(stand_ent, (stand, transform))| {
+            let (body_size, _, capture_sound, capture_sound_volume) = stand_meta(stand_ent)?;
+            let position = transform.translation;
+            let rect = Rect::new(position.x, position.y, body_size.x, body_size.y);
+            Some((stand.team, rect, capture_sound, capture_sound_volume))
+        })
+        .collect::<Vec<_>>();
+
+    for (flag_ent, flag) in entities.iter_with(&mut flags) {
+        let FlagState::Carried(carrier) = flag.state else {
+            continue;
+        };
+        let (Some(team), Some(body), Some(carrier_transform)) = (
+            player_indexes
+                .get(carrier)
+                .and_then(|idx| match_settings.team_of(idx.0)),
+            bodies.get(carrier),
+            transforms.get(carrier),
+        ) else {
+            continue;
+        };
+        let carrier_rect = body.bounding_box(*carrier_transform);
+        let Some((_, _, capture_sound, capture_sound_volume)) = home_stands
+            .iter()
+            .find(|(stand_team, rect, ..)| *stand_team == team && rect.overlaps(&carrier_rect))
+        else {
+            continue;
+        };
+        let Some((_, flag_offset, ..)) = stand_meta(flag.stand) else {
+            continue;
+        };
+
+        audio_events.play(capture_sound.clone(), *capture_sound_volume);
+        return_flag(
+            flag_ent,
+            flag,
+            flag_offset,
+            &mut transforms,
+            &mut player_body_attachments,
+        );
+
+        let has_won = ctf_score.capture(team);
+        debug!("Flag of team {} captured by team {team}", flag.team);
+        let Some(rounds_to_win) = match_settings.rounds_to_win else { continue };
+        if has_won {
+            let mut winners = [false; MAX_PLAYERS];
+            for (player, won) in winners.iter_mut().enumerate() {
+                *won = player_inputs.players[player].active
+                    && match_settings.team_of(player) == Some(team);
+            }
+            match_score.end_round(winners, rounds_to_win);
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rounds_are_won_by_enough_captures() {
+        let mut score = CtfScore::default();
+        for _ in 1..CAPTURES_PER_ROUND {
+            assert!(!score.capture(1));
+        }
+        assert!(!score.capture(0));
+        assert!(score.capture(1));
+        assert_eq!(score.captures, [1, CAPTURES_PER_ROUND]);
+    }
+}
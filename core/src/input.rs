@@ -39,10 +39,12 @@ pub struct PlayerInput {
     pub editor_input: Option<EditorInput>,
     /// Whether or not this is an AI player.
     pub is_ai: bool,
+    /// How well the AI plays, if this is an AI player.
+    pub ai_difficulty: AiDifficulty,
 }
 
 /// Player control input state
-#[derive(Default, Clone, Debug)]
+#[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[repr(C)]
 pub struct PlayerControl {
     pub move_direction: Vec2,
@@ -92,6 +94,38 @@ pub enum EditorInput {
         /// The amount to move the entity.
         pos: Vec2,
     },
+    /// Mirror and rotate an element.
+    OrientEntity {
+        /// The entity to orient.
+        entity: Entity,
+        /// Whether the element is mirrored horizontally.
+        flip_x: bool,
+        /// Whether the element is mirrored vertically.
+        flip_y: bool,
+        /// The number of counter-clockwise quarter turns to rotate the element by.
+        rotation: u8,
+    },
+    /// Set the attributes of a player spawner.
+    SetSpawnPoint {
+        /// The player spawner entity.
+        entity: Entity,
+        /// The new attributes, or [`None`] to use the defaults.
+        spawn_point: Option<SpawnPointMeta>,
+    },
+    /// Set the far anchor point of a trip wire.
+    SetWireAnchor {
+        /// The trip wire entity.
+        entity: Entity,
+        /// The new anchor point, or [`None`] to use the default.
+        wire_anchor: Option<WireAnchorMeta>,
+    },
+    /// Set the link channel of an element.
+    SetLink {
+        /// The element entity.
+        entity: Entity,
+        /// The new link channel, or [`None`] to use the default.
+        link: Option<LinkMeta>,
+    },
     DeleteEntity {
         /// The entity to delete.
         entity: Entity,
@@ -118,6 +152,13 @@ pub enum EditorInput {
         /// Whether or not to move the layer down. If false, move the layer up.
         down: bool,
     },
+    /// Make a layer render in front of the players, or behind them again.
+    SetLayerForeground {
+        /// The layer index of the layer to update.
+        layer: u8,
+        /// The foreground settings of the layer, or [`None`] to render it behind the players.
+        foreground: Option<MapForegroundMeta>,
+    },
     /// Update the tilemap of a layer.
     SetTilemap {
         /// The layer index of the layer to update.
@@ -138,9 +179,25 @@ pub enum EditorInput {
     RenameMap {
         name: String,
     },
+    /// Update the information about the map shown to players.
+    SetMapInfo {
+        author: String,
+        description: String,
+        version: u32,
+        recommended_players: Option<MapPlayerCountMeta>,
+    },
     RandomizeTiles {
         tile_layers: Vec<TileLayer>,
         element_layers: Vec<ElementLayer>,
         tile_size: Vec2,
     },
+    /// Copy the contents of a prefab onto a map layer.
+    StampPrefab {
+        /// The prefab to stamp.
+        prefab: MapPrefabMeta,
+        /// The layer index of the layer to stamp the prefab on.
+        layer: u8,
+        /// The tile position of the bottom-left corner of the prefab.
+        pos: UVec2,
+    },
 }
@@ -2,11 +2,14 @@
 //!
 //! An item is anything in the game that can be picked up by the player.
 
-use crate::prelude::{player_spawner::PlayerSpawner, *};
+use crate::prelude::{player_spawner::PlayerSpawner, size_power_up::Resized, *};
 
 pub fn install(session: &mut CoreSession) {
+    session.world.init_resource::<EventChannel<ItemGrabbed>>();
+
     session
         .stages
+        .add_system_to_stage(CoreStage::First, clear_events::<ItemGrabbed>)
         .add_system_to_stage(CoreStage::Last, grab_items)
         .add_system_to_stage(CoreStage::Last, throw_dropped_items);
 }
@@ -78,14 +81,25 @@ pub struct ItemDropped {
     pub player: Entity,
 }
 
-/// Marker component added to items when they are grabbed.
-#[derive(Clone, Copy, TypeUlid)]
+/// Event sent when a player grabs an item.
+#[derive(Clone, Copy, Debug, TypeUlid)]
 #[ulid = "01GP4DJ2RPYTDPKSKEK8JKK9VT"]
 pub struct ItemGrabbed {
+    /// The item that was grabbed
+    pub item: Entity,
     /// The player that grabbed the item
     pub player: Entity,
 }
 
+/// Marker component added to players that started trying to grab an item with an empty inventory,
+/// while they keep the grab button pressed.
+///
+/// When [`MatchSettings::pickup_assist_radius`] is set, nearby items are pulled toward these
+/// players, and grabbed as soon as they touch them.
+#[derive(Clone, Copy, TypeUlid)]
+#[ulid = "01H1ZD3M6Q8XKT2V7BN4JC9RWA"]
+pub struct PickupAssist;
+
 /// Marker component added to items when they are used.
 #[derive(Clone, Copy, TypeUlid)]
 #[ulid = "01GP4DJ84TFB8Z7H9VY7Y0R47H"]
@@ -96,8 +110,8 @@ pub struct ItemUsed {
 
 /// Component defining the grab settings when an item is grabbed.
 ///
-/// Mainly handled by the [`grab_items`] system which consumes the
-/// [`ItemGrabbed`] components for entities which have this component.
+/// Mainly handled by the [`grab_items`] system which handles the
+/// [`ItemGrabbed`] events for entities which have this component.
 /// [`Item`] is required for the system to take affect.
 #[derive(Clone, Copy, TypeUlid)]
 #[ulid = "01GTJHWG4C2AW6KCY0P11MZ1KW"]
@@ -111,7 +125,7 @@ pub fn grab_items(
     entities: Res<Entities>,
     item_grab: Comp<ItemGrab>,
     items: Comp<Item>,
-    mut items_grabbed: CompMut<ItemGrabbed>,
+    item_grabbed_events: Res<EventChannel<ItemGrabbed>>,
     mut bodies: CompMut<KinematicBody>,
     mut attachments: CompMut<PlayerBodyAttachment>,
     mut player_layers: CompMut<PlayerLayers>,
@@ -123,9 +137,7 @@ pub fn grab_items(
             sync_animation,
         } = *item_grab;
 
-        if let Some(ItemGrabbed { player }) = items_grabbed.remove(entity) {
-            items_grabbed.remove(entity);
-
+        for &ItemGrabbed { player, .. } in item_grabbed_events.iter().filter(|x| x.item == entity) {
             player_layers.get_mut(player).unwrap().fin_anim = fin_anim;
 
             if let Some(body) = bodies.get_mut(entity) {
@@ -249,6 +261,7 @@ pub fn throw_dropped_items(
     item_spawners: Comp<DehydrateOutOfBounds>,
     map_layers: Comp<SpawnedMapLayerMeta>,
     player_spawnwers: Comp<PlayerSpawner>,
+    resized_players: Comp<Resized>,
     mut commands: Commands,
 ) {
     for (entity, (_items, item_throw, body, transform)) in
@@ -269,13 +282,19 @@ pub fn throw_dropped_items(
                 Vec2::ONE
             };
 
+            let throw_strength = resized_players
+                .get(player)
+                .map_or(1.0, |x| x.throw_strength);
             let throw_velocity = item_throw.velocity_from_control(
                 &player_inputs
                     .players
                     .get(player_indexes.get(player).unwrap().0)
                     .unwrap()
                     .control,
-            );
+            ) * throw_strength;
+
+            // Items held by resized players are scaled along with them
+            transform.scale = Vec3::ONE;
 
             // Use the item's spawner depth as the drop depth
             if let Some(item_spawner) = item_spawners.get(entity) {
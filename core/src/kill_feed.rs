@@ -0,0 +1,100 @@
+//! Kill attribution.
+//!
+//! Killing a player with [`PlayerCommand::kill_by()`] credits the kill to the [`DamageSource`] that
+//! dealt it, and sends a [`KillEvent`]. The events are collected in the [`KillFeed`], which keeps
+//! the recent kills for the frontend to show. The kills and deaths of every player over the whole
+//! match are counted in the [`MatchStats`].
+
+use std::time::Duration;
+
+use crate::prelude::*;
+
+/// Install this module.
+pub fn install(session: &mut CoreSession) {
+    session.world.init_resource::<EventChannel<KillEvent>>();
+    session.world.init_resource::<KillFeed>();
+    session
+        .stages
+        // The kills are sent by commands throughout the frame, so they are collected at the start
+        // of the next one.
+        .add_system_to_stage(CoreStage::First, update_kill_feed)
+        .add_system_to_stage(CoreStage::First, clear_events::<KillEvent>);
+}
+
+/// How long a kill stays in the [`KillFeed`].
+pub const KILL_FEED_DURATION: Duration = Duration::from_secs(5);
+/// The maximum number of kills in the [`KillFeed`] at once.
+pub const KILL_FEED_LENGTH: usize = 5;
+
+/// An event sent when a player is killed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, TypeUlid)]
+#[ulid = "01H33K6V1QXN8ZD4MB7TRW2JEA"]
+pub struct KillEvent {
+    /// The index of the player that was killed.
+    pub victim: usize,
+    /// The index of the player credited with the kill, if a player killed them.
+    ///
+    /// This may be the victim itself, such as when a player is caught in their own explosion.
+    pub killer: Option<usize>,
+}
+
+/// Resource containing the recent kills.
+///
+/// It is kept when the map is reloaded for the next round.
+#[derive(Clone, Debug, Default, TypeUlid)]
+#[ulid = "01H33K73CZ5RW9QJ2XB8NT6MVE"]
+pub struct KillFeed {
+    /// The recent kills, from the oldest to the newest, with the time left until they are removed
+    /// from the feed.
+    pub entries: Vec<(KillEvent, Timer)>,
+}
+
+impl KillFeed {
+    /// Add a kill to the feed, dropping the oldest kill if the feed is full.
+    pub fn record(&mut self, event: KillEvent) {
+        if self.entries.len() >= KILL_FEED_LENGTH {
+            self.entries.remove(0);
+        }
+        self.entries
+            .push((event, Timer::new(KILL_FEED_DURATION, TimerMode::Once)));
+    }
+}
+
+/// Collect the kills of the last frame, and remove the old kills from the feed.
+fn update_kill_feed(
+    kill_events: Res<EventChannel<KillEvent>>,
+    mut kill_feed: ResMut<KillFeed>,
+    time: Res<Time>,
+) {
+    for (_, timer) in &mut kill_feed.entries {
+        timer.tick(time.delta());
+    }
+    kill_feed.entries.retain(|(_, timer)| !timer.finished());
+
+    for event in kill_events.iter() {
+        kill_feed.record(*event);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn old_kills_are_dropped_from_full_feeds() {
+        let mut feed = KillFeed::default();
+        feed.record(KillEvent {
+            victim: 1,
+            killer: Some(0),
+        });
+        for _ in 0..KILL_FEED_LENGTH {
+            feed.record(KillEvent {
+                victim: 2,
+                killer: Some(3),
+            });
+        }
+
+        assert_eq!(feed.entries.len(), KILL_FEED_LENGTH);
+        assert!(feed.entries.iter().all(|(event, _)| event.victim == 2));
+    }
+}
@@ -0,0 +1,184 @@
+//! Rising lava.
+//!
+//! When the [`Mutator::RisingLava`] mutator is enabled, or the map [enables it][MapLavaMeta] on its
+//! own, lava rises from the bottom of the map over the round, following the rise curve of the map,
+//! and kills the players that fall into it. A shimmer at the bottom of the map warns of the lava
+//! shortly before it starts rising, and keeps pulsing over its surface while it rises, so long
+//! rounds push the players further and further up the map.
+
+use crate::prelude::*;
+
+/// Install this module.
+pub fn install(session: &mut CoreSession) {
+    session.world.init_resource::<RisingLava>();
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PostUpdate, update_lava)
+        .add_system_to_stage(CoreStage::PostUpdate, kill_players_in_lava);
+}
+
+/// The Z depth of the lava, in front of the map and the players.
+const LAVA_Z: f32 = 500.0;
+/// How far below the bottom of the map the lava reaches, so that the bottom of the lava is never
+/// in view.
+const LAVA_DEPTH: f32 = 500.0;
+
+/// Resource containing the height of the surface of the lava.
+#[derive(Clone, Debug, Default, TypeUlid)]
+#[ulid = "01H33Z4K7QW2NB8RTX5VM9JCDA"]
+pub struct RisingLava {
+    /// The height of the surface of the lava in pixels, if it has started rising.
+    pub height: Option<f32>,
+    /// The lava and shimmer entities, once they are spawned.
+    sprites: Option<(Entity, Entity)>,
+}
+
+/// Whether the lava rises in this match.
+pub fn is_lava_enabled(match_settings: &MatchSettings, map: &MapMeta) -> bool {
+    match_settings.has_mutator(Mutator::RisingLava) || map.lava.enabled
+}
+
+/// Raise the lava, and stretch the lava and shimmer sprites over it.
+fn update_lava(
+    game_meta: Res<CoreMetaArc>,
+    match_settings: Res<MatchSettings>,
+    map: Res<LoadedMap>,
+    time: Res<Time>,
+    mut entities: ResMut<Entities>,
+    mut rising_lava: ResMut<RisingLava>,
+    mut sprites: CompMut<Sprite>,
+    mut transforms: CompMut<Transform>,
+) {
+    if !is_lava_enabled(&match_settings, &map) {
+        return;
+    }
+    let Some(start_time) = map.lava.start_time() else {
+        return;
+    };
+    let meta = &game_meta.lava;
+    let elapsed = time.elapsed().as_secs_f32();
+    let warning_time = meta.warning_time.as_secs_f32();
+    if elapsed < start_time - warning_time {
+        return;
+    }
+
+    let map_size = map.grid_size.as_vec2() * map.tile_size;
+    rising_lava.height = map.lava.height_at(elapsed).map(|x| x * map_size.y);
+
+    let (lava_ent, shimmer_ent) = *rising_lava.sprites.get_or_insert_with(|| {
+        let lava_ent = entities.create();
+        sprites.insert(
+            lava_ent,
+            Sprite {
+                image: meta.image.clone(),
+                ..default()
+            },
+        );
+        let shimmer_ent = entities.create();
+        sprites.insert(
+            shimmer_ent,
+            Sprite {
+                image: meta.shimmer_image.clone(),
+                ..default()
+            },
+        );
+        (lava_ent, shimmer_ent)
+    });
+
+    // The lava spans the width of the map, from below the bottom of the map up to its surface.
+    let surface = rising_lava.height.unwrap_or(0.0);
+    let width = map_size.x + 2.0 * LAVA_DEPTH;
+    let lava_height = surface + LAVA_DEPTH;
+    transforms.insert(
+        lava_ent,
+        Transform {
+            translation: Vec3::new(map_size.x / 2.0, surface - lava_height / 2.0, LAVA_Z),
+            scale: Vec3::new(width, lava_height, 1.0) / meta.image_size.extend(1.0),
+            ..default()
+        },
+    );
+    transforms.insert(
+        shimmer_ent,
+        Transform {
+            translation: Vec3::new(
+                map_size.x / 2.0,
+                surface + meta.shimmer_height / 2.0,
+                LAVA_Z,
+            ),
+            scale: Vec3::new(width, meta.shimmer_height, 1.0) / meta.image_size.extend(1.0),
+            ..default()
+        },
+    );
+
+    let phase = elapsed / meta.shimmer_period.max(f32::EPSILON) * std::f32::consts::TAU;
+    if let Some(sprite) = sprites.get_mut(shimmer_ent) {
+        sprite.color.set_a(0.5 + 0.5 * phase.sin());
+    }
+}
+
+/// Kill the players whose feet are below the surface of the lava.
+fn kill_players_in_lava(
+    entities: Res<Entities>,
+    rising_lava: Res<RisingLava>,
+    player_indexes: Comp<PlayerIdx>,
+    players_killed: Comp<PlayerKilled>,
+    invincibles: Comp<Invincibility>,
+    bodies: Comp<KinematicBody>,
+    transforms: Comp<Transform>,
+    mut commands: Commands,
+) {
+    let Some(height) = rising_lava.height else {
+        return;
+    };
+
+    for (player_ent, (_, body, transform)) in
+        entities.iter_with((&player_indexes, &bodies, &transforms))
+    {
+        if players_killed.contains(player_ent) || invincibles.contains(player_ent) {
+            continue;
+        }
+        if body.bounding_box(*transform).min.y < height {
+            let position = transform.translation.truncate();
+            commands.add(PlayerCommand::kill(player_ent, Some(position)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn lava_follows_the_rise_curve() {
+        let mut lava = MapLavaMeta {
+            enabled: true,
+            rise: vec![
+                LavaPointMeta {
+                    time: 10.0,
+                    height: 0.0,
+                },
+                LavaPointMeta {
+                    time: 20.0,
+                    height: 0.5,
+                },
+                LavaPointMeta {
+                    time: 30.0,
+                    height: 0.5,
+                },
+                LavaPointMeta {
+                    time: 40.0,
+                    height: 0.7,
+                },
+            ],
+        };
+
+        assert_eq!(lava.height_at(5.0), None);
+        assert_eq!(lava.height_at(10.0), Some(0.0));
+        assert_eq!(lava.height_at(15.0), Some(0.25));
+        assert_eq!(lava.height_at(25.0), Some(0.5));
+        assert_eq!(lava.height_at(60.0), Some(0.7));
+
+        lava.rise.clear();
+        assert_eq!(lava.height_at(60.0), None);
+    }
+}
@@ -13,9 +13,13 @@ mod prelude;
 pub mod bevy_prelude {
     pub use {
         crate::{
+            ai::AiDifficulty,
             input::EditorInput,
+            match_settings::MatchSettings,
             metadata::*,
+            mutator::Mutator,
             session::{CoreSession, CoreSessionInfo, GameSessionPlayerInfo},
+            teams::MatchMode,
             MAX_PLAYERS,
         },
         bones_lib::prelude as bones,
@@ -34,6 +38,9 @@ pub mod external {
     pub use bones_lib;
 }
 
+pub mod afk;
+pub mod ai;
+pub mod arcade;
 pub mod attachment;
 pub mod bullet;
 pub mod camera;
@@ -41,17 +48,35 @@ pub mod damage;
 pub mod debug;
 pub mod editor;
 pub mod elements;
+pub mod events;
+pub mod feature_flags;
+pub mod foreground;
+pub mod gamemode;
 pub mod globals;
 pub mod input;
 pub mod item;
+pub mod kill_feed;
+pub mod lava;
 pub mod lifetime;
 pub mod map;
 pub mod map_constructor;
+pub mod match_settings;
+pub mod match_stats;
 pub mod metadata;
+pub mod mutator;
+pub mod nav;
+pub mod observer;
 pub mod physics;
 pub mod player;
+pub mod presentation;
 pub mod random;
+pub mod replay;
+pub mod rounds;
 pub mod session;
+pub mod sudden_death;
+pub mod tags;
+pub mod teams;
+pub mod timer;
 pub mod utils;
 
 /// The target fixed frames-per-second that the game sumulation runs at.
@@ -94,14 +119,31 @@ pub fn install_modules(session: &mut session::CoreSession) {
     input::install(session);
     map::install(session);
     player::install(session);
+    ai::install(session);
+    // Tag the entities before the elements clean up the items they spawned.
+    tags::install(session);
     elements::install(session);
     damage::install(session);
     camera::install(session);
+    foreground::install(session);
     lifetime::install(session);
+    timer::install(session);
     random::install(session);
     debug::install(session);
     item::install(session);
     attachment::install(session);
     bullet::install(session);
     editor::install(session);
+    mutator::install(session);
+    lava::install(session);
+    afk::install(session);
+    observer::install(session);
+    presentation::install(session);
+    teams::install(session);
+    rounds::install(session);
+    gamemode::install(session);
+    sudden_death::install(session);
+    arcade::install(session);
+    match_stats::install(session);
+    kill_feed::install(session);
 }
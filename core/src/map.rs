@@ -1,18 +1,17 @@
-//! Map and navigation mesh implementation.
+//! Map implementation.
 
-use std::{
-    cmp::{max, min},
-    collections::VecDeque,
+use crate::{
+    nav::create_nav_graph,
+    prelude::{collisions::TileCollisionKind, *},
+    random::{GlobalRng, RngStream},
 };
 
-use ::bevy::utils::HashSet;
-
-use crate::prelude::{collisions::TileCollisionKind, *};
-
 pub fn install(session: &mut CoreSession) {
+    session.world.init_resource::<MapStages>();
     session
         .stages
         .add_system_to_stage(CoreStage::First, spawn_map)
+        .add_system_to_stage(CoreStage::First, transition_map_stages)
         .add_system_to_stage(CoreStage::First, handle_out_of_bounds_players);
 }
 
@@ -26,6 +25,16 @@ pub struct LoadedMap(pub Arc<MapMeta>);
 #[ulid = "01GP3Z38HKE37JB6GRHHPPTY38"]
 pub struct MapSpawned(pub bool);
 
+/// Resource tracking the [stages][MapStageMeta] of the loaded map.
+#[derive(Clone, TypeUlid, Default)]
+#[ulid = "01H1ZQ4B7NX2KD9CR6VT3MJW8E"]
+pub struct MapStages {
+    /// The index of the next stage that the map will transition to.
+    pub next_stage: usize,
+    /// Whether the tiles and elements of each map layer are currently spawned.
+    pub visible_layers: Vec<bool>,
+}
+
 /// The Z depth of the deepest map layer.
 pub const MAP_LAYERS_MIN_DEPTH: f32 = -900.0;
 /// The Z depth in between each map layer.
@@ -44,22 +53,32 @@ pub fn z_depth_for_map_layer(layer_idx: usize) -> f32 {
 #[ulid = "01GSR8V683B3EH5QAB2PMGN9J7"]
 pub struct SpawnedMapMeta {
     pub name: Arc<str>,
+    pub author: Arc<str>,
+    pub description: Arc<str>,
+    pub version: u32,
+    pub recommended_players: Option<MapPlayerCountMeta>,
     pub background: Arc<BackgroundMeta>,
     pub background_color: ColorMeta,
     pub grid_size: UVec2,
     pub tile_size: Vec2,
     pub layer_names: Arc<[String]>,
+    pub physics: MapPhysicsMeta,
 }
 
 impl Default for SpawnedMapMeta {
     fn default() -> Self {
         Self {
             name: "".into(),
+            author: "".into(),
+            description: "".into(),
+            version: default(),
+            recommended_players: default(),
             background: default(),
             background_color: default(),
             grid_size: default(),
             tile_size: default(),
             layer_names: Arc::new([]),
+            physics: default(),
         }
     }
 }
@@ -75,99 +94,24 @@ pub struct SpawnedMapLayerMeta {
     pub layer_idx: usize,
 }
 
-/// The map navigation graph resource.
-#[derive(Clone, Debug, Deref, DerefMut, TypeUlid, Default)]
-#[ulid = "01GQWP4QG11NBVX3M289TXAK6W"]
-pub struct NavGraph(pub Arc<NavGraphInner>);
-
-/// The inner graph type of [`NavGraph`].
-pub type NavGraphInner = petgraph::graphmap::DiGraphMap<NavNode, NavGraphEdge>;
-
-/// The type of nodes in the map navigation graph.
-///
-/// This is merely a wrapper around [`UVec2`] to add an [`Ord`] implementation.
-#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash, Deref, DerefMut)]
-pub struct NavNode(pub IVec2);
-
-impl NavNode {
-    /// Calculates the Pythagorean distance between two nodes.
-    pub fn distance(&self, other: &Self) -> f32 {
-        let dx = (max(self.x, other.x) - min(self.x, other.x)) as f32;
-        let dy = (max(self.y, other.y) - min(self.y, other.y)) as f32;
-        (dx * dx) + (dy * dy)
-    }
-
-    pub fn right(&self) -> NavNode {
-        NavNode(self.0 + ivec2(1, 0))
-    }
-
-    pub fn above(&self) -> NavNode {
-        NavNode(self.0 + ivec2(0, 1))
-    }
-
-    pub fn left(&self) -> NavNode {
-        NavNode(self.0 - ivec2(1, 0))
-    }
-
-    pub fn below(&self) -> NavNode {
-        NavNode(self.0 - ivec2(0, 1))
-    }
-}
-
-impl From<IVec2> for NavNode {
-    fn from(v: IVec2) -> Self {
-        Self(v)
-    }
-}
-impl From<NavNode> for IVec2 {
-    fn from(v: NavNode) -> Self {
-        v.0
-    }
-}
-impl std::cmp::Ord for NavNode {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.partial_cmp(other).unwrap()
-    }
-}
-impl std::cmp::PartialOrd for NavNode {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        let xcmp = self.0.x.cmp(&other.0.x);
-        Some(if xcmp == std::cmp::Ordering::Equal {
-            self.0.y.cmp(&other.0.y)
-        } else {
-            xcmp
-        })
-    }
-}
-
-/// Represents the way to get from one tile to another tile in the navigation graph.
-#[derive(Debug, Clone)]
-pub struct NavGraphEdge {
-    /// The sequence of inputs for each frame, required to get to the connected tile.
-    pub inputs: VecDeque<PlayerControl>,
-    /// The distance to the connected tile. This is used as the heuristic for pathfinding.
-    pub distance: f32,
-}
-
 fn spawn_map(
     mut commands: Commands,
     mut entities: ResMut<Entities>,
     mut clear_color: ResMut<ClearColor>,
     map: Res<LoadedMap>,
     mut map_spawned: ResMut<MapSpawned>,
-    mut tiles: CompMut<Tile>,
     mut tile_layers: CompMut<TileLayer>,
     mut transforms: CompMut<Transform>,
-    mut element_handles: CompMut<ElementHandle>,
-    mut tile_collisions: CompMut<TileCollisionKind>,
     mut parallax_bg_sprites: CompMut<ParallaxBackgroundSprite>,
     mut sprites: CompMut<Sprite>,
     mut nav_graph: ResMut<NavGraph>,
+    mut map_stages: ResMut<MapStages>,
     mut cameras: CompMut<Camera>,
     mut camera_shakes: CompMut<CameraShake>,
     mut camera_states: CompMut<CameraState>,
     mut spawned_map_layer_metas: CompMut<SpawnedMapLayerMeta>,
     mut spawned_map_meta: ResMut<SpawnedMapMeta>,
+    mut foreground_layers: CompMut<ForegroundLayer>,
 ) {
     if map_spawned.0 {
         return;
@@ -176,11 +120,16 @@ fn spawn_map(
     // Fill in the spawned map metadata
     *spawned_map_meta = SpawnedMapMeta {
         name: map.name.clone().into(),
+        author: map.author.clone().into(),
+        description: map.description.clone().into(),
+        version: map.version,
+        recommended_players: map.recommended_players,
         background: Arc::new(map.background.clone()),
         background_color: map.background_color,
         grid_size: map.grid_size,
         tile_size: map.tile_size,
         layer_names: map.layers.iter().map(|x| x.id.to_string()).collect(),
+        physics: map.physics.clone(),
     };
 
     // Spawn the camera
@@ -201,9 +150,6 @@ fn spawn_map(
     map_spawned.0 = true;
     **clear_color = map.background_color.0;
 
-    // Load the navigation graph
-    nav_graph.0 = create_nav_graph(&map);
-
     // Spawn parallax backgrounds
     for layer in &map.background.layers {
         for i in -1..=1 {
@@ -226,15 +172,72 @@ fn spawn_map(
         }
     }
 
-    // Load tiles
+    // Create the map layers, and spawn the contents of the visible ones
+    map_stages.next_stage = 0;
+    map_stages.visible_layers = Vec::with_capacity(map.layers.len());
     for (layer_idx, layer) in map.layers.iter().enumerate() {
-        let layer_z = z_depth_for_map_layer(layer_idx);
-        let mut tile_layer = TileLayer::new(
-            map.grid_size,
-            map.tile_size,
-            // Just use a dummy atlas if one is not specified
-            layer.tilemap.clone().unwrap_or_default(),
+        let layer_ent = entities.create();
+        spawned_map_layer_metas.insert(layer_ent, SpawnedMapLayerMeta { layer_idx });
+        tile_layers.insert(
+            layer_ent,
+            TileLayer::new(
+                map.grid_size,
+                map.tile_size,
+                // Just use a dummy atlas if one is not specified
+                layer.tilemap.clone().unwrap_or_default(),
+            ),
         );
+        transforms.insert(
+            layer_ent,
+            Transform::from_translation(Vec3::new(0.0, 0.0, z_depth_for_map_layer(layer_idx))),
+        );
+        if let Some(foreground) = layer.foreground {
+            foreground_layers.insert(layer_ent, ForegroundLayer::new(foreground));
+        }
+
+        let visible = map.is_layer_initially_visible(&layer.id);
+        map_stages.visible_layers.push(visible);
+        if visible {
+            commands.add(spawn_layer_contents(layer_idx));
+        }
+    }
+
+    // Load the navigation graph
+    nav_graph.0 = create_nav_graph(&map, &map_stages.visible_layers);
+
+    // Update collision world with map tiles
+    commands.add(|mut collision_world: CollisionWorld| {
+        collision_world.update_tiles();
+    });
+}
+
+/// Spawn the tiles and elements of the map layer with the given index.
+fn spawn_layer_contents(layer_idx: usize) -> System {
+    (move |mut entities: ResMut<Entities>,
+           map: Res<LoadedMap>,
+           match_settings: Res<MatchSettings>,
+           element_assets: BevyAssets<ElementMeta>,
+           rng: Res<GlobalRng>,
+           mut tiles: CompMut<Tile>,
+           mut tile_layers: CompMut<TileLayer>,
+           mut tile_collisions: CompMut<TileCollisionKind>,
+           mut transforms: CompMut<Transform>,
+           mut element_handles: CompMut<ElementHandle>,
+           mut element_orientations: CompMut<ElementOrientation>,
+           mut spawn_points: CompMut<SpawnPointMeta>,
+           mut wire_anchors: CompMut<WireAnchorMeta>,
+           mut links: CompMut<LinkMeta>,
+           mut item_spawn_counts: CompMut<ItemSpawnCount>,
+           mut spawned_map_layer_metas: CompMut<SpawnedMapLayerMeta>| {
+        let rng = rng.stream(RngStream::ItemSpawns);
+        let layer = &map.layers[layer_idx];
+        let layer_z = z_depth_for_map_layer(layer_idx);
+
+        let Some(layer_ent) = entities
+            .iter_with((&tile_layers, &spawned_map_layer_metas))
+            .find(|(_, (_, meta))| meta.layer_idx == layer_idx)
+            .map(|(ent, _)| ent) else { return; };
+        let tile_layer = tile_layers.get_mut(layer_ent).unwrap();
 
         for tile_meta in &layer.tiles {
             let tile_ent = entities.create();
@@ -257,30 +260,145 @@ fn spawn_map(
                 tile_collisions.insert(tile_ent, tile_meta.collision);
             }
         }
-        let layer_ent = entities.create();
-        spawned_map_layer_metas.insert(layer_ent, SpawnedMapLayerMeta { layer_idx });
-        tile_layers.insert(layer_ent, tile_layer);
-        transforms.insert(
-            layer_ent,
-            Transform::from_translation(Vec3::new(0.0, 0.0, layer_z)),
-        );
 
         for element_meta in &layer.elements {
-            let element_ent = entities.create();
+            // Apply the match settings to decide how many items the element keeps on the map
+            let spawn_count = element_assets
+                .get(&element_meta.element.get_bevy_handle())
+                .map(|meta| match_settings.spawn_count(meta, rng.f32()))
+                .unwrap_or(1);
+
+            if spawn_count == 0 {
+                continue;
+            }
 
+            let element_ent = entities.create();
+            let orientation = ElementOrientation::from_spawn(element_meta);
             spawned_map_layer_metas.insert(element_ent, SpawnedMapLayerMeta { layer_idx });
             transforms.insert(
                 element_ent,
-                Transform::from_translation(element_meta.pos.extend(layer_z)),
+                Transform {
+                    translation: element_meta.pos.extend(layer_z),
+                    rotation: orientation.rotation_quat(),
+                    ..default()
+                },
             );
             element_handles.insert(element_ent, ElementHandle(element_meta.element.clone()));
+            if orientation != ElementOrientation::default() {
+                element_orientations.insert(element_ent, orientation);
+            }
+            if let Some(spawn_point) = element_meta.spawn_point {
+                spawn_points.insert(element_ent, spawn_point);
+            }
+            if let Some(wire_anchor) = element_meta.wire_anchor {
+                wire_anchors.insert(element_ent, wire_anchor);
+            }
+            if let Some(link) = element_meta.link {
+                links.insert(element_ent, link);
+            }
+            if spawn_count > 1 {
+                item_spawn_counts.insert(element_ent, ItemSpawnCount(spawn_count));
+            }
         }
+    })
+    .system()
+}
+
+/// Transition the map to its next [stage][MapStageMeta] when it is time to, showing and hiding
+/// the map layers.
+fn transition_map_stages(
+    mut commands: Commands,
+    mut entities: ResMut<Entities>,
+    time: Res<Time>,
+    map: Res<LoadedMap>,
+    map_spawned: Res<MapSpawned>,
+    mut map_stages: ResMut<MapStages>,
+    mut tile_layers: CompMut<TileLayer>,
+    spawned_map_layer_metas: Comp<SpawnedMapLayerMeta>,
+    element_handles: Comp<ElementHandle>,
+    element_kill_callbacks: Comp<ElementKillCallback>,
+    mut spawner_manager: SpawnerManager,
+) {
+    if !map_spawned.0 {
+        return;
     }
 
-    // Update collision world with map tiles
-    commands.add(|mut collision_world: CollisionWorld| {
-        collision_world.update_tiles();
-    });
+    let mut transitioned = false;
+    while let Some(stage) = map.stages.get(map_stages.next_stage) {
+        if time.elapsed().as_secs_f32() < stage.start_time {
+            break;
+        }
+        debug!("Transitioning to map stage {}", map_stages.next_stage);
+        map_stages.next_stage += 1;
+        transitioned = true;
+
+        for (layer_idx, layer) in map.layers.iter().enumerate() {
+            let visible = map_stages.visible_layers[layer_idx];
+
+            if !visible && stage.show_layers.contains(&layer.id) {
+                map_stages.visible_layers[layer_idx] = true;
+                commands.add(spawn_layer_contents(layer_idx));
+            } else if visible && stage.hide_layers.contains(&layer.id) {
+                map_stages.visible_layers[layer_idx] = false;
+
+                // Despawn the layer tiles
+                let mut to_kill = Vec::new();
+                for (_, (tile_layer, _)) in entities
+                    .iter_with((&mut tile_layers, &spawned_map_layer_metas))
+                    .filter(|(_, (_, meta))| meta.layer_idx == layer_idx)
+                {
+                    for x in 0..map.grid_size.x {
+                        for y in 0..map.grid_size.y {
+                            let pos = UVec2::new(x, y);
+                            if let Some(tile_ent) = tile_layer.get(pos) {
+                                to_kill.push(tile_ent);
+                                tile_layer.set(pos, None);
+                            }
+                        }
+                    }
+                }
+
+                // Despawn the layer elements
+                let elements = entities
+                    .iter_with((&element_handles, &spawned_map_layer_metas))
+                    .filter(|(_, (_, meta))| meta.layer_idx == layer_idx)
+                    .map(|(ent, _)| ent)
+                    .collect::<Vec<_>>();
+                for element_ent in elements {
+                    if let Some(element_kill_callback) = element_kill_callbacks.get(element_ent) {
+                        let system = element_kill_callback.system.clone();
+                        commands
+                            .add(move |world: &World| (system.lock().unwrap().run)(world).unwrap());
+                    } else if spawner_manager.is_entity_a_spawner(element_ent) {
+                        spawner_manager.kill_spawner_entity(
+                            element_ent,
+                            &mut entities,
+                            &element_kill_callbacks,
+                            &mut commands,
+                        );
+                    } else {
+                        to_kill.push(element_ent);
+                    }
+                }
+
+                for ent in to_kill {
+                    entities.kill(ent);
+                }
+            }
+        }
+    }
+
+    if transitioned {
+        commands.add(
+            |map: Res<LoadedMap>,
+             map_stages: Res<MapStages>,
+             mut nav_graph: ResMut<NavGraph>,
+             mut collision_world: CollisionWorld| {
+                nav_graph.0 = create_nav_graph(&map, &map_stages.visible_layers);
+                collision_world.update_tiles();
+            },
+        );
+    }
 }
 
 fn handle_out_of_bounds_players(
@@ -297,434 +415,3 @@ fn handle_out_of_bounds_players(
         }
     }
 }
-
-/// Helper method to create a navigation graph from the map metadata.
-fn create_nav_graph(meta: &MapMeta) -> Arc<NavGraphInner> {
-    // Load the navigation graph
-    let mut graph = NavGraphInner::default();
-
-    // Initialize set of traversable tiles, assuming all tiles are traversable
-    for x in 0..meta.grid_size.x as i32 {
-        for y in 0..meta.grid_size.y as i32 {
-            graph.add_node(NavNode(ivec2(x, y)));
-        }
-    }
-
-    // Find all solid tiles and remove them from the traversable tiles list, while also recording
-    // the jump-through tiles.
-    let mut semi_solids = HashSet::default();
-    for layer in &meta.layers {
-        for tile in &layer.tiles {
-            if tile.collision == TileCollisionKind::JumpThrough {
-                semi_solids.insert(NavNode(tile.pos.as_ivec2()));
-            } else if tile.collision != TileCollisionKind::Empty {
-                graph.remove_node(NavNode(tile.pos.as_ivec2()));
-            }
-        }
-    }
-
-    // Calculate possible movements from every node
-    macro_rules! is_solid {
-        ($node:expr) => {
-            !graph.contains_node($node) || semi_solids.contains(&$node)
-        };
-    }
-
-    for node in graph.nodes().collect::<Vec<_>>() {
-        // walk left or right along the ground
-        let has_ground = is_solid!(node.below());
-        let maybe_has_ground =
-            has_ground || is_solid!(node.below().left()) || is_solid!(node.below().right());
-
-        /////////////////
-        // Grounded
-        /////////////////
-
-        if maybe_has_ground {
-            // Moving Right
-            let right = node.right();
-            if graph.contains_node(right) {
-                graph.add_edge(
-                    node,
-                    right,
-                    NavGraphEdge {
-                        inputs: [PlayerControl {
-                            moving: true,
-                            move_direction: vec2(1.0, 0.0),
-                            ..default()
-                        }]
-                        .into(),
-                        distance: node.distance(&right),
-                    },
-                );
-            }
-
-            // Moving Left
-            let left = node.left();
-            if graph.contains_node(left) {
-                graph.add_edge(
-                    node,
-                    left,
-                    NavGraphEdge {
-                        inputs: [PlayerControl {
-                            moving: true,
-                            move_direction: vec2(-1.0, 0.0),
-                            ..default()
-                        }]
-                        .into(),
-                        distance: node.distance(&left),
-                    },
-                );
-            }
-        }
-
-        if has_ground {
-            /////////////////
-            // JUMPING
-            /////////////////
-            let above1 = node.above();
-            let above2 = above1.above();
-            let above3 = above2.above();
-            let contains_above1 = graph.contains_node(above1);
-            let contains_above2 = graph.contains_node(above2);
-            let contains_above3 = graph.contains_node(above3);
-
-            if contains_above1 {
-                // Jump staight up
-                graph.add_edge(
-                    node,
-                    above1,
-                    NavGraphEdge {
-                        inputs: [PlayerControl {
-                            jump_just_pressed: true,
-                            jump_pressed: true,
-                            ..default()
-                        }]
-                        .into(),
-                        distance: node.distance(&above1),
-                    },
-                );
-            }
-            if contains_above2 && contains_above1 {
-                // Jump staight up
-                graph.add_edge(
-                    node,
-                    above2,
-                    NavGraphEdge {
-                        inputs: [PlayerControl {
-                            jump_just_pressed: true,
-                            jump_pressed: true,
-                            ..default()
-                        }]
-                        .into(),
-                        distance: node.distance(&above2),
-                    },
-                );
-            }
-            if contains_above3 && contains_above2 && contains_above1 {
-                // Jump staight up
-                graph.add_edge(
-                    node,
-                    above2,
-                    NavGraphEdge {
-                        inputs: [PlayerControl {
-                            jump_just_pressed: true,
-                            jump_pressed: true,
-                            ..default()
-                        }]
-                        .into(),
-                        distance: node.distance(&above3),
-                    },
-                );
-            }
-
-            // Jump up and left
-            let above3l2 = above3.left().left();
-            let above2l = above2.left();
-            let contains_above2l = graph.contains_node(above2l);
-            let contains_above3l2 = graph.contains_node(above3l2);
-            if contains_above3l2 && contains_above2 && contains_above3 && contains_above2l {
-                graph.add_edge(
-                    node,
-                    above3l2,
-                    NavGraphEdge {
-                        inputs: std::iter::repeat(PlayerControl {
-                            move_direction: vec2(-1.0, 0.0),
-                            jump_just_pressed: true,
-                            jump_pressed: true,
-                            ..default()
-                        })
-                        .take(20)
-                        .collect(),
-                        distance: node.distance(&above3l2),
-                    },
-                );
-            }
-            let above3l3 = above3.left().left().left();
-            if graph.contains_node(above3l3)
-                && graph.contains_node(above3.left())
-                && contains_above3l2
-                && contains_above2
-                && contains_above3
-                && contains_above2l
-            {
-                graph.add_edge(
-                    node,
-                    above3l3,
-                    NavGraphEdge {
-                        inputs: std::iter::repeat(PlayerControl {
-                            move_direction: vec2(-1.0, 0.0),
-                            jump_just_pressed: true,
-                            jump_pressed: true,
-                            ..default()
-                        })
-                        .take(20)
-                        .collect(),
-                        distance: node.distance(&above3l3),
-                    },
-                );
-            }
-
-            // Jump up and right
-            let above3r2 = above3.right().right();
-            let above2r = above2.right();
-            let contains_above2r = graph.contains_node(above2r);
-            let contains_above3r2 = graph.contains_node(above3r2);
-            if contains_above3r2 && contains_above2 && contains_above3 && contains_above2r {
-                graph.add_edge(
-                    node,
-                    above3r2,
-                    NavGraphEdge {
-                        inputs: std::iter::repeat(PlayerControl {
-                            move_direction: vec2(1.0, 0.0),
-                            jump_just_pressed: true,
-                            jump_pressed: true,
-                            ..default()
-                        })
-                        .take(20)
-                        .collect(),
-                        distance: node.distance(&above3r2),
-                    },
-                );
-            }
-            let above3r3 = above3.right().right().right();
-            if graph.contains_node(above3r3)
-                && graph.contains_node(above3.right())
-                && contains_above3r2
-                && contains_above2
-                && contains_above3
-                && contains_above2r
-            {
-                graph.add_edge(
-                    node,
-                    above3r3,
-                    NavGraphEdge {
-                        inputs: std::iter::repeat(PlayerControl {
-                            move_direction: vec2(1.0, 0.0),
-                            jump_just_pressed: true,
-                            jump_pressed: true,
-                            ..default()
-                        })
-                        .take(20)
-                        .collect(),
-                        distance: node.distance(&above3r3),
-                    },
-                );
-            }
-        }
-
-        /////////////////
-        // Falling Down
-        /////////////////
-
-        // Fall straight down
-        let below = node.below();
-        if graph.contains_node(below) {
-            if semi_solids.contains(&below) {
-                graph.add_edge(
-                    node,
-                    below,
-                    NavGraphEdge {
-                        inputs: [
-                            PlayerControl {
-                                move_direction: vec2(0.0, -1.0),
-                                jump_just_pressed: true,
-                                jump_pressed: true,
-                                ..default()
-                            },
-                            default(),
-                            default(),
-                            default(),
-                            default(),
-                        ]
-                        .into(),
-                        distance: node.distance(&below),
-                    },
-                );
-            } else {
-                graph.add_edge(
-                    node,
-                    below,
-                    NavGraphEdge {
-                        inputs: [PlayerControl::default()].into(),
-                        distance: node.distance(&below),
-                    },
-                );
-            }
-        }
-
-        // Fall diagonally down right
-        let below_right = node.below().right();
-        if graph.contains_node(below_right) {
-            if semi_solids.contains(&below_right) {
-                graph.add_edge(
-                    node,
-                    below_right,
-                    NavGraphEdge {
-                        inputs: [
-                            PlayerControl {
-                                move_direction: vec2(1.0, -1.0),
-                                jump_just_pressed: true,
-                                jump_pressed: true,
-                                ..default()
-                            },
-                            PlayerControl {
-                                move_direction: vec2(1.0, -1.0),
-                                jump_pressed: true,
-                                ..default()
-                            },
-                            PlayerControl {
-                                move_direction: vec2(1.0, 0.0),
-                                ..default()
-                            },
-                            PlayerControl {
-                                move_direction: vec2(1.0, 0.0),
-                                ..default()
-                            },
-                        ]
-                        .into(),
-                        distance: node.distance(&below_right),
-                    },
-                );
-            } else {
-                graph.add_edge(
-                    node,
-                    below_right,
-                    NavGraphEdge {
-                        inputs: [PlayerControl {
-                            move_direction: vec2(1.0, 0.0),
-                            ..default()
-                        }]
-                        .into(),
-                        distance: node.distance(&below_right),
-                    },
-                );
-            }
-        }
-        // Fall diagonally down left
-        let below_left = node.below().left();
-        if graph.contains_node(below_left) {
-            if semi_solids.contains(&below_left) {
-                graph.add_edge(
-                    node,
-                    below_left,
-                    NavGraphEdge {
-                        inputs: [
-                            PlayerControl {
-                                move_direction: vec2(-1.0, -1.0),
-                                jump_just_pressed: true,
-                                jump_pressed: true,
-                                ..default()
-                            },
-                            PlayerControl {
-                                move_direction: vec2(-1.0, -1.0),
-                                jump_pressed: true,
-                                ..default()
-                            },
-                            PlayerControl {
-                                move_direction: vec2(-1.0, 0.0),
-                                ..default()
-                            },
-                            PlayerControl {
-                                move_direction: vec2(-1.0, 0.0),
-                                ..default()
-                            },
-                        ]
-                        .into(),
-                        distance: node.distance(&below_left),
-                    },
-                );
-            } else {
-                graph.add_edge(
-                    node,
-                    below_left,
-                    NavGraphEdge {
-                        inputs: [PlayerControl {
-                            move_direction: vec2(-1.0, 0.0),
-                            ..default()
-                        }]
-                        .into(),
-                        distance: node.distance(&below_left),
-                    },
-                );
-            }
-        }
-
-        // Slow fall right
-        let far_right_below = node.right().right().right().right().below();
-        let path = [
-            node.right(),
-            node.right().right(),
-            node.right().right().right(),
-            node.right().right().right().right(),
-            far_right_below,
-        ];
-        if path.iter().all(|x| graph.contains_node(*x)) {
-            graph.add_edge(
-                node,
-                far_right_below,
-                NavGraphEdge {
-                    inputs: std::iter::repeat(PlayerControl {
-                        move_direction: vec2(1.0, 0.0),
-                        jump_pressed: true,
-                        ..default()
-                    })
-                    .take(20)
-                    .collect(),
-                    // Bias against using this move because it doesn't always work, by adding an
-                    // extra distance.
-                    distance: node.distance(&far_right_below) + 1.0,
-                },
-            );
-        }
-        // Slow fall left
-        let far_left_below = node.left().left().left().left().below();
-        let path = [
-            node.left(),
-            node.left().left(),
-            node.left().left().left(),
-            node.left().left().left().left(),
-            far_left_below,
-        ];
-        if path.iter().all(|x| graph.contains_node(*x)) {
-            graph.add_edge(
-                node,
-                far_left_below,
-                NavGraphEdge {
-                    inputs: std::iter::repeat(PlayerControl {
-                        move_direction: vec2(-1.0, 0.0),
-                        jump_pressed: true,
-                        ..default()
-                    })
-                    .take(20)
-                    .collect(),
-                    // Bias against using this move because it doesn't always work, by adding an
-                    // extra distance.
-                    distance: node.distance(&far_left_below) + 1.0,
-                },
-            );
-        }
-    }
-
-    Arc::new(graph)
-}
@@ -0,0 +1,163 @@
+//! Per-match gameplay configuration chosen before the match starts.
+
+use std::collections::HashMap;
+
+use crate::prelude::*;
+
+/// Resource containing the settings the host picked for the current match.
+///
+/// This is provided through [`CoreSessionInfo::match_settings`] and is inserted into the world when
+/// the session is created. Because it is part of the session info it is sent to network peers
+/// along with the map selection, so every client simulates the match with the same settings.
+#[derive(Clone, Debug, Default, TypeUlid, Serialize, Deserialize, PartialEq)]
+#[ulid = "01H1QE4V3Z7RCX8NT4JYK6WB9M"]
+#[serde(default)]
+pub struct MatchSettings {
+    /// The names of the elements that will not be spawned on the map.
+    pub disabled_elements: Vec<String>,
+    /// Spawn multipliers for elements, by element name.
+    ///
+    /// Elements not in this map have a multiplier of `1.0`. See
+    /// [`spawn_count()`][Self::spawn_count] for how the multiplier is applied.
+    pub spawn_multipliers: HashMap<String, f32>,
+    /// The mutators enabled for the match.
+    pub mutators: Vec<Mutator>,
+    /// The names of the experimental [feature flags][crate::feature_flags] enabled for the match.
+    pub feature_flags: Vec<String>,
+    /// The seed for the match's [`GlobalRng`][crate::random::GlobalRng].
+    ///
+    /// If this is `None` the [default seed][crate::random::DEFAULT_SEED] will be used.
+    pub seed: Option<u64>,
+    /// The number of seconds a player may go without making any input before they are considered
+    /// AFK.
+    ///
+    /// If this is `None`, players are never considered AFK.
+    pub afk_timeout: Option<f32>,
+    /// What to do with players that are AFK.
+    pub afk_action: AfkAction,
+    /// The radius in pixels around a player, that idle items are pulled in from while the player
+    /// is trying to grab something.
+    ///
+    /// If this is `None`, items must be touched to be grabbed.
+    pub pickup_assist_radius: Option<f32>,
+    /// Whether network players that disconnect during the match are taken over by an AI, instead
+    /// of being removed from the match.
+    pub ai_fill_disconnected: bool,
+    /// Whether the players play for themselves or in teams.
+    pub mode: MatchMode,
+    /// Whether players can hurt their teammates in a team match.
+    pub friendly_fire: bool,
+    /// The number of rounds a player must win to win the match.
+    ///
+    /// If this is `None`, killed players respawn right away instead of waiting for the next round,
+    /// and the match goes on until the players leave. See the [`rounds`][crate::rounds] module.
+    pub rounds_to_win: Option<u32>,
+    /// The number of seconds each round lasts before sudden death starts, when the match is played
+    /// over multiple rounds.
+    ///
+    /// If this is `None`, rounds have no time limit. See the
+    /// [`sudden_death`][crate::sudden_death] module.
+    pub time_limit: Option<f32>,
+    /// Whether the match is played in arcade mode, where players collect coins for score.
+    ///
+    /// See the [`arcade`][crate::arcade] module.
+    pub arcade: bool,
+}
+
+impl MatchSettings {
+    /// The largest spawn multiplier that may be set for an element.
+    pub const MAX_SPAWN_MULTIPLIER: f32 = 3.0;
+
+    /// The pickup assist radius used when pickup assist is enabled from the menu.
+    pub const DEFAULT_PICKUP_ASSIST_RADIUS: f32 = 50.0;
+
+    /// Whether or not the element with the given name is enabled for this match.
+    pub fn is_element_enabled(&self, element_name: &str) -> bool {
+        !self.disabled_elements.iter().any(|x| x == element_name)
+    }
+
+    /// Enable or disable the element with the given name.
+    pub fn set_element_enabled(&mut self, element_name: &str, enabled: bool) {
+        self.disabled_elements.retain(|x| x != element_name);
+        if !enabled {
+            self.disabled_elements.push(element_name.to_string());
+        }
+    }
+
+    /// Get the spawn multiplier for the element with the given name.
+    pub fn spawn_multiplier(&self, element_name: &str) -> f32 {
+        self.spawn_multipliers
+            .get(element_name)
+            .copied()
+            .unwrap_or(1.0)
+    }
+
+    /// Set the spawn multiplier for the element with the given name.
+    ///
+    /// The multiplier is clamped between `0.0` and
+    /// [`MAX_SPAWN_MULTIPLIER`][Self::MAX_SPAWN_MULTIPLIER].
+    pub fn set_spawn_multiplier(&mut self, element_name: &str, multiplier: f32) {
+        let multiplier = multiplier.clamp(0.0, Self::MAX_SPAWN_MULTIPLIER);
+        if multiplier == 1.0 {
+            self.spawn_multipliers.remove(element_name);
+        } else {
+            self.spawn_multipliers
+                .insert(element_name.to_string(), multiplier);
+        }
+    }
+
+    /// Whether or not the given mutator is enabled.
+    pub fn has_mutator(&self, mutator: Mutator) -> bool {
+        self.mutators.contains(&mutator)
+    }
+
+    /// Enable or disable the given mutator.
+    pub fn set_mutator_enabled(&mut self, mutator: Mutator, enabled: bool) {
+        self.mutators.retain(|x| *x != mutator);
+        if enabled {
+            self.mutators.push(mutator);
+        }
+    }
+
+    /// Whether or not the [feature flag][crate::feature_flags] with the given name is enabled.
+    pub fn has_feature_flag(&self, flag: &str) -> bool {
+        self.feature_flags.iter().any(|x| x == flag)
+    }
+
+    /// Enable or disable the [feature flag][crate::feature_flags] with the given name.
+    pub fn set_feature_flag_enabled(&mut self, flag: &str, enabled: bool) {
+        self.feature_flags.retain(|x| x != flag);
+        if enabled {
+            self.feature_flags.push(flag.to_string());
+        }
+    }
+
+    /// Get the team of the given player, or [`None`] if the match isn't played in teams.
+    ///
+    /// Players are split between the teams in the order they joined the match.
+    pub fn team_of(&self, player_idx: usize) -> Option<u8> {
+        match self.mode {
+            MatchMode::FreeForAll => None,
+            MatchMode::Teams | MatchMode::CaptureTheFlag => Some((player_idx % TEAM_COUNT) as u8),
+        }
+    }
+
+    /// Get the number of items that a single spawn point of an element in the map keeps on the map
+    /// at once, see [`ItemSpawnCount`].
+    ///
+    /// An element with a count of `0` isn't spawned at all. The whole part of the multiplier is
+    /// always used, and the fractional part is used as the chance to keep one more item. `roll`
+    /// should be a random number between `0.0` and `1.0`.
+    pub fn spawn_count(&self, element_meta: &ElementMeta, roll: f32) -> usize {
+        let element_name = &element_meta.name;
+        if !self.is_element_enabled(element_name)
+            || !self.mutators.iter().all(|x| x.allows_element(element_meta))
+        {
+            return 0;
+        }
+        let multiplier = self.spawn_multiplier(element_name);
+        let extra = if roll < multiplier.fract() { 1 } else { 0 };
+
+        multiplier.trunc() as usize + extra
+    }
+}
@@ -0,0 +1,263 @@
+//! Match statistics.
+//!
+//! The [`MatchStats`] count what every player did over the whole match, for the results screen
+//! shown once the match is over, along with the [`Award`]s given for them.
+
+use std::{cmp::Reverse, collections::BTreeMap};
+
+use crate::prelude::*;
+
+/// Install this module.
+///
+/// This must be installed before the [`kill_feed`][crate::kill_feed], which clears the
+/// [`KillEvent`]s once it has collected them.
+pub fn install(session: &mut CoreSession) {
+    session.world.init_resource::<MatchStats>();
+    session
+        .stages
+        .add_system_to_stage(CoreStage::First, count_kills)
+        .add_system_to_stage(CoreStage::Last, update_match_stats);
+}
+
+/// The statistics of a single player over the match.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PlayerStats {
+    /// The number of opponents killed by the player, not counting themselves.
+    pub kills: u32,
+    /// The number of times the player was killed.
+    pub deaths: u32,
+    /// The number of times the player used an item.
+    pub items_used: u32,
+    /// The number of grenades lit by the player.
+    pub grenades_lit: u32,
+    /// The number of hits the player landed on other players and on damageable objects, such as
+    /// explosives.
+    pub damage_dealt: u32,
+    /// The number of rounds won by the player.
+    pub round_wins: u32,
+}
+
+/// The number of grenades that a player must light to earn the [`Award::GrenadeManiac`].
+pub const GRENADE_MANIAC_MINIMUM: u32 = 5;
+
+/// A fun award given to players at the end of the match for their [`PlayerStats`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Award {
+    /// The player that won the most rounds, or got the most kills if tied.
+    Mvp,
+    /// The players that killed the most opponents.
+    MostKills,
+    /// The players that were killed the most times.
+    MostDeaths,
+    /// The players that never hurt anybody.
+    Pacifist,
+    /// The players that lit the most grenades.
+    GrenadeManiac,
+}
+
+impl Award {
+    /// All of the awards, in the order they are shown in.
+    pub const ALL: [Award; 5] = [
+        Award::Mvp,
+        Award::MostKills,
+        Award::MostDeaths,
+        Award::Pacifist,
+        Award::GrenadeManiac,
+    ];
+}
+
+/// Resource containing the [`PlayerStats`] of every player.
+///
+/// It is kept when the map is reloaded for the next round.
+#[derive(Clone, Debug, Default, TypeUlid)]
+#[ulid = "01H33T2R8MXK5QZ9VB4NJW7CDE"]
+pub struct MatchStats {
+    /// The statistics of each player.
+    pub players: [PlayerStats; MAX_PLAYERS],
+    /// The number of times each player used each kind of item, by the name of the item.
+    pub item_uses: [BTreeMap<String, u32>; MAX_PLAYERS],
+    /// The entities that were damaged on the last frame, which aren't hit again until they leave
+    /// the damage region.
+    damaged: Vec<Entity>,
+}
+
+impl MatchStats {
+    /// Count a kill for the killer and a death for the victim.
+    pub fn record_kill(&mut self, event: KillEvent) {
+        self.players[event.victim].deaths += 1;
+        if let Some(killer) = event.killer.filter(|x| *x != event.victim) {
+            self.players[killer].kills += 1;
+            self.players[killer].damage_dealt += 1;
+        }
+    }
+
+    /// Count a use of the item with the given name by the given player.
+    pub fn record_item_use(&mut self, player_idx: usize, item_name: &str) {
+        self.players[player_idx].items_used += 1;
+        *self.item_uses[player_idx]
+            .entry(item_name.to_owned())
+            .or_default() += 1;
+    }
+
+    /// Get the awards earned by the given players over the match, in the order of [`Award::ALL`].
+    ///
+    /// An award is shared by every player tied for it, except for the [`Award::Mvp`], which is only
+    /// given to a player that did better than everybody else.
+    pub fn awards(&self, players: &[usize]) -> Vec<(Award, usize)> {
+        Award::ALL
+            .into_iter()
+            .flat_map(|award| {
+                self.award_winners(award, players)
+                    .into_iter()
+                    .map(move |player| (award, player))
+            })
+            .collect()
+    }
+
+    /// Get the players that earned the given award, out of the given players.
+    fn award_winners(&self, award: Award, players: &[usize]) -> Vec<usize> {
+        let leaders = |stat: fn(&PlayerStats) -> u32, minimum: u32| {
+            let best = players
+                .iter()
+                .map(|&i| stat(&self.players[i]))
+                .max()
+                .unwrap_or_default();
+            players
+                .iter()
+                .copied()
+                .filter(|&i| best >= minimum && stat(&self.players[i]) == best)
+                .collect::<Vec<_>>()
+        };
+
+        match award {
+            Award::Mvp => {
+                let key = |i: usize| {
+                    let stats = &self.players[i];
+                    (stats.round_wins, stats.kills, Reverse(stats.deaths))
+                };
+                let best = players.iter().map(|&i| key(i)).max();
+                let mvps = players
+                    .iter()
+                    .copied()
+                    .filter(|&i| Some(key(i)) == best)
+                    .collect::<Vec<_>>();
+                match mvps[..] {
+                    [mvp] if players.len() > 1 => vec![mvp],
+                    _ => Vec::new(),
+                }
+            }
+            Award::MostKills => leaders(|x| x.kills, 1),
+            Award::MostDeaths => leaders(|x| x.deaths, 1),
+            Award::Pacifist => players
+                .iter()
+                .copied()
+                .filter(|&i| self.players[i].damage_dealt == 0)
+                .collect(),
+            Award::GrenadeManiac => leaders(|x| x.grenades_lit, GRENADE_MANIAC_MINIMUM),
+        }
+    }
+}
+
+/// Count the kills of the last frame.
+fn count_kills(kill_events: Res<EventChannel<KillEvent>>, mut match_stats: ResMut<MatchStats>) {
+    for event in kill_events.iter() {
+        match_stats.record_kill(*event);
+    }
+}
+
+/// Count the damageable objects hit by players, and the rounds won.
+fn update_match_stats(
+    damage_events: Res<EventChannel<DamageEvent>>,
+    match_score: Res<MatchScore>,
+    player_indexes: Comp<PlayerIdx>,
+    mut match_stats: ResMut<MatchStats>,
+) {
+    // Damageable objects are damaged every frame that they overlap a damage region, so only the
+    // first frame counts as a hit.
+    let damaged = damage_events
+        .iter()
+        .map(|event| event.entity)
+        .collect::<Vec<_>>();
+    for event in damage_events.iter() {
+        if match_stats.damaged.contains(&event.entity) {
+            continue;
+        }
+        let player = event.source.player().and_then(|x| player_indexes.get(x));
+        if let Some(player) = player {
+            match_stats.players[player.0].damage_dealt += 1;
+        }
+    }
+    match_stats.damaged = damaged;
+
+    for (stats, wins) in match_stats.players.iter_mut().zip(match_score.wins) {
+        stats.round_wins = wins;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn players_are_only_credited_with_the_kills_of_others() {
+        let mut stats = MatchStats::default();
+        stats.record_kill(KillEvent {
+            victim: 1,
+            killer: Some(0),
+        });
+        stats.record_kill(KillEvent {
+            victim: 0,
+            killer: Some(0),
+        });
+        stats.record_kill(KillEvent {
+            victim: 1,
+            killer: None,
+        });
+
+        let kills = stats.players.map(|x| x.kills);
+        let deaths = stats.players.map(|x| x.deaths);
+        assert_eq!(kills, [1, 0, 0, 0]);
+        assert_eq!(deaths, [1, 2, 0, 0]);
+        assert_eq!(stats.players[0].damage_dealt, 1);
+    }
+
+    #[test]
+    fn awards_go_to_the_leaders() {
+        let mut stats = MatchStats::default();
+        stats.players[0] = PlayerStats {
+            kills: 2,
+            damage_dealt: 3,
+            round_wins: 1,
+            ..default()
+        };
+        stats.players[1] = PlayerStats {
+            kills: 2,
+            deaths: 3,
+            damage_dealt: 2,
+            grenades_lit: GRENADE_MANIAC_MINIMUM,
+            ..default()
+        };
+        stats.players[2] = PlayerStats {
+            deaths: 3,
+            ..default()
+        };
+
+        assert_eq!(
+            stats.awards(&[0, 1, 2]),
+            vec![
+                (Award::Mvp, 0),
+                (Award::MostKills, 0),
+                (Award::MostKills, 1),
+                (Award::MostDeaths, 1),
+                (Award::MostDeaths, 2),
+                (Award::Pacifist, 2),
+                (Award::GrenadeManiac, 1),
+            ]
+        );
+        // Nobody stands out in a match where nothing happened
+        assert_eq!(
+            MatchStats::default().awards(&[0, 1]),
+            vec![(Award::Pacifist, 0), (Award::Pacifist, 1)]
+        );
+    }
+}
@@ -44,6 +44,10 @@ pub struct CoreMeta {
     pub camera: CameraMeta,
     pub physics: PhysicsMeta,
     pub config: CoreConfigMeta,
+    pub status_effects: StatusEffectsMeta,
+    pub lava: LavaMeta,
+    pub sudden_death: SuddenDeathMeta,
+    pub hit_popups: HitPopupsMeta,
     pub map_tilesets: Vec<Handle<Atlas>>,
     pub players: Vec<Handle<PlayerMeta>>,
     pub player_hats: Vec<Handle<HatMeta>>,
@@ -66,6 +70,8 @@ pub struct CameraMeta {
     pub zoom_out_lerp_factor: f32,
     pub min_camera_size: Vec2,
     pub player_camera_box_size: Vec2,
+    /// How much the different things on the map pull the camera toward them.
+    pub weights: CameraWeightsMeta,
 }
 
 impl Default for CameraMeta {
@@ -81,23 +87,203 @@ impl Default for CameraMeta {
             zoom_out_lerp_factor: 1.0,
             min_camera_size: Vec2::ZERO,
             player_camera_box_size: Vec2::ZERO,
+            weights: default(),
         }
     }
 }
 
-#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug, Default)]
+/// The weights used by the camera to decide what to focus on.
+///
+/// The camera keeps everything with a weight above zero in frame, and centers itself on the
+/// weighted average of their positions.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct CameraWeightsMeta {
+    /// The weight of living players.
+    pub player: f32,
+    /// The weight of dead players.
+    pub dead_player: f32,
+    /// The weight of AFK players, who are often left idle at the edge of the map.
+    pub afk_player: f32,
+    /// The weight of explosives that are about to explode.
+    pub lit_explosive: f32,
+}
+
+impl Default for CameraWeightsMeta {
+    fn default() -> Self {
+        Self {
+            player: 1.0,
+            dead_player: 0.3,
+            afk_player: 0.0,
+            lit_explosive: 0.5,
+        }
+    }
+}
+
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
 #[serde(deny_unknown_fields)]
 pub struct PhysicsMeta {
     pub gravity: f32,
     pub terminal_velocity: f32,
     pub friction_lerp: f32,
     pub stop_threshold: f32,
+    /// Multiplier applied to the gravity of every kinematic body.
+    #[serde(default = "default_gravity_scale")]
+    pub gravity_scale: f32,
 }
 
-#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug, Default)]
+impl Default for PhysicsMeta {
+    fn default() -> Self {
+        Self {
+            gravity: default(),
+            terminal_velocity: default(),
+            friction_lerp: default(),
+            stop_threshold: default(),
+            gravity_scale: default_gravity_scale(),
+        }
+    }
+}
+
+fn default_gravity_scale() -> f32 {
+    1.0
+}
+
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
 #[serde(deny_unknown_fields)]
 pub struct CoreConfigMeta {
     #[serde(default)]
     #[serde(with = "humantime_serde")]
     pub respawn_invincibility_time: Duration,
+    /// The upward velocity a player bounces back with after landing on another player's head.
+    #[serde(default = "default_stomp_bounce_velocity")]
+    pub stomp_bounce_velocity: f32,
+    /// How long an item that has been picked up may lie around without being held before it is
+    /// despawned. Items that have never been picked up are never despawned.
+    #[serde(default)]
+    #[serde(with = "humantime_serde")]
+    pub item_despawn_time: Option<Duration>,
+    /// The maximum number of items from the same spawner that may be on the map at the same time.
+    ///
+    /// When a spawner has more items than this, the items that have been abandoned for the
+    /// longest are despawned.
+    #[serde(default)]
+    pub max_items_per_spawner: Option<usize>,
+}
+
+impl Default for CoreConfigMeta {
+    fn default() -> Self {
+        Self {
+            respawn_invincibility_time: default(),
+            stomp_bounce_velocity: default_stomp_bounce_velocity(),
+            item_despawn_time: None,
+            max_items_per_spawner: None,
+        }
+    }
+}
+
+fn default_stomp_bounce_velocity() -> f32 {
+    10.0
+}
+
+/// How the [status effects][StatusEffects] of the players are displayed.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct StatusEffectsMeta {
+    /// The atlas containing the icons shown above the players for their effects.
+    pub icon_atlas: Handle<Atlas>,
+    /// The offset of the row of icons from the center of the player.
+    pub icon_offset: Vec2,
+    /// The horizontal distance between the centers of the icons.
+    pub icon_spacing: f32,
+    /// The alpha that invisible players are rendered with for their opponents.
+    pub invisible_opponent_alpha: f32,
+    /// The alpha that invisible players are rendered with for the players on the same computer.
+    pub invisible_own_alpha: f32,
+    pub frozen: StatusEffectMeta,
+    pub burning: StatusEffectMeta,
+    pub poisoned: StatusEffectMeta,
+    pub slowed: StatusEffectMeta,
+    pub shielded: StatusEffectMeta,
+    pub invisible: StatusEffectMeta,
+    pub slipping: StatusEffectMeta,
+}
+
+impl StatusEffectsMeta {
+    /// Get the metadata of a kind of status effect.
+    pub fn get(&self, kind: StatusEffectKind) -> &StatusEffectMeta {
+        match kind {
+            StatusEffectKind::Frozen => &self.frozen,
+            StatusEffectKind::Burning => &self.burning,
+            StatusEffectKind::Poisoned => &self.poisoned,
+            StatusEffectKind::Slowed => &self.slowed,
+            StatusEffectKind::Shielded => &self.shielded,
+            StatusEffectKind::Invisible => &self.invisible,
+            StatusEffectKind::Slipping => &self.slipping,
+        }
+    }
+}
+
+/// How the [hit popups][crate::presentation::HitPopup] are displayed.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct HitPopupsMeta {
+    /// The atlas containing the digits from `0` to `9`.
+    pub digit_atlas: Handle<Atlas>,
+    /// The horizontal distance between the centers of the digits.
+    pub digit_spacing: f32,
+    /// The offset of the popups from the center of the player that was hit.
+    pub offset: Vec2,
+    /// How far the popups rise every frame, in pixels.
+    pub rise_speed: f32,
+    /// The number of seconds that the popups take to fade out.
+    pub lifetime: f32,
+    /// The color of the damage numbers.
+    pub damage_color: ColorMeta,
+    /// The color of the knock-back numbers.
+    pub knockback_color: ColorMeta,
+}
+
+/// How the rising [lava][crate::lava] is displayed.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct LavaMeta {
+    /// The image that is stretched over the lava.
+    pub image: Handle<Image>,
+    /// The image that is stretched over the shimmer above the surface of the lava.
+    pub shimmer_image: Handle<Image>,
+    /// The size of the images in pixels.
+    pub image_size: Vec2,
+    /// The height of the shimmer above the surface of the lava.
+    pub shimmer_height: f32,
+    /// The number of seconds that the shimmer takes to pulse once.
+    pub shimmer_period: f32,
+    /// How long before the lava starts rising that the shimmer warns of it.
+    #[serde(with = "humantime_serde")]
+    pub warning_time: Duration,
+}
+
+/// How the walls closing in during [sudden death][crate::sudden_death] behave and are displayed.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct SuddenDeathMeta {
+    /// The image that is stretched over the walls.
+    pub image: Handle<Image>,
+    /// The size of the image in pixels.
+    pub image_size: Vec2,
+    /// How fast the walls close in, in pixels per second.
+    pub close_speed: f32,
+    /// How much of the width of the map each wall covers once it stops closing in, between `0.0`
+    /// and `0.5`.
+    pub max_coverage: f32,
+}
+
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct StatusEffectMeta {
+    /// The index of the icon of the effect in the icon atlas.
+    pub icon: usize,
+    /// The color the players with the effect are tinted with. Players aren't tinted if it is white.
+    #[serde(default)]
+    pub tint: ColorMeta,
 }
@@ -14,6 +14,11 @@ pub struct ElementMeta {
 
     #[serde(default)]
     pub editor: ElementEditorMeta,
+
+    /// The [`Tag`]s added to the element's entities.
+    #[serde(default)]
+    #[asset(deserialize_only)]
+    pub tags: Vec<Tag>,
 }
 
 #[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
@@ -43,7 +48,11 @@ impl Default for ElementEditorMeta {
 #[asset_id = "bullet"]
 #[serde(deny_unknown_fields)]
 pub struct BulletMeta {
+    /// The velocity of the bullet when shot facing right, in pixels per frame.
     pub velocity: Vec2,
+    /// The downward acceleration of the bullet, in pixels per frame per frame.
+    #[serde(default)]
+    pub gravity: f32,
     pub body_diameter: f32,
     pub atlas: Handle<Atlas>,
 
@@ -92,6 +101,51 @@ pub enum BuiltinElementKind {
         #[serde(default)]
         angular_velocity: f32,
     },
+    /// A grenade that looks just like a real one, but only pops harmlessly into confetti.
+    DecoyGrenade {
+        body_diameter: f32,
+        fin_anim: Key,
+        grab_offset: Vec2,
+        throw_velocity: f32,
+        /// The time in seconds before the decoy pops
+        fuse_time: f32,
+        fuse_sound: Handle<AudioSource>,
+        fuse_sound_volume: f64,
+        #[serde(default)]
+        can_rotate: bool,
+        atlas: Handle<Atlas>,
+        #[serde(default)]
+        bounciness: f32,
+        #[serde(default)]
+        angular_velocity: f32,
+        /// The confetti shown when the decoy pops.
+        pop: DecoyPopMeta,
+    },
+    /// An item that leaves a fake, standing fish where it is used.
+    DecoyFish {
+        atlas: Handle<Atlas>,
+        fin_anim: Key,
+        #[serde(default)]
+        grab_offset: Vec2,
+        body_size: Vec2,
+        bounciness: f32,
+        can_rotate: bool,
+        throw_velocity: f32,
+        angular_velocity: f32,
+
+        /// The atlas of the fake fish, which is animated from its first frame.
+        fish_atlas: Handle<Atlas>,
+        fish_frames: usize,
+        fish_fps: f32,
+        fish_body_size: Vec2,
+        /// How long the fake fish stands before popping.
+        #[serde(with = "humantime_serde")]
+        fish_lifetime: Duration,
+        spawn_sound: Handle<AudioSource>,
+        spawn_sound_volume: f64,
+        /// The confetti shown when the decoy pops.
+        pop: DecoyPopMeta,
+    },
     /// An animated decoration such as seaweed or anemones
     AnimatedDecoration {
         start_frame: usize,
@@ -180,6 +234,19 @@ pub enum BuiltinElementKind {
         bounciness: f32,
         throw_velocity: f32,
         cooldown_frames: usize,
+        /// The swings of the combo chain, in order. Pressing shoot again within
+        /// `combo_window_frames` after a swing does the next swing of the chain.
+        #[serde(default = "default_sword_combo")]
+        combo: Vec<SwordSwingMeta>,
+        #[serde(default)]
+        combo_window_frames: usize,
+        /// The swing done after holding shoot, if the sword can do a charged swing.
+        #[serde(default)]
+        heavy_swing: Option<SwordHeavySwingMeta>,
+        /// What happens when the swing of the sword clashes with the swing of another player's
+        /// sword, if swords can parry each other.
+        #[serde(default)]
+        parry: Option<SwordParryMeta>,
     },
     /// The throwable crate item
     Crate {
@@ -225,6 +292,11 @@ pub enum BuiltinElementKind {
         armed_fps: f32,
         arm_sound_volume: f64,
         arm_sound: Handle<AudioSource>,
+        /// The size of the region around the armed mine that blows it up when a player enters it.
+        ///
+        /// Defaults to the size of the mine's body.
+        #[serde(default)]
+        trigger_size: Option<Vec2>,
 
         throw_velocity: f32,
         body_size: Vec2,
@@ -286,7 +358,11 @@ pub enum BuiltinElementKind {
         #[serde(with = "humantime_serde")]
         cooldown: Duration,
         bullet_meta: Handle<BulletMeta>,
+        /// The velocity given to the shooter opposite to the direction of the shot.
         kickback: f32,
+        /// The angle, in degrees, of the cone that bullets are randomly shot in.
+        #[serde(default)]
+        spread: f32,
 
         shoot_fps: f32,
         shoot_lifetime: f32,
@@ -297,6 +373,59 @@ pub enum BuiltinElementKind {
         shoot_sound: Handle<AudioSource>,
         empty_shoot_sound: Handle<AudioSource>,
     },
+    /// A launcher that shoots missiles that seek the nearest opponent.
+    MissileLauncher {
+        #[serde(default)]
+        grab_offset: Vec2,
+        fin_anim: Key,
+
+        body_size: Vec2,
+        bounciness: f32,
+        can_rotate: bool,
+        throw_velocity: f32,
+        angular_velocity: f32,
+        atlas: Handle<Atlas>,
+
+        max_ammo: usize,
+        #[serde(with = "humantime_serde")]
+        cooldown: Duration,
+        shoot_sound: Handle<AudioSource>,
+        shoot_sound_volume: f64,
+        empty_shoot_sound: Handle<AudioSource>,
+        empty_shoot_sound_volume: f64,
+
+        missile: MissileMeta,
+    },
+    /// A fishing rod, that casts a hook to snag the nearest opponent in front of the player and
+    /// pull them in.
+    FishingRod {
+        atlas: Handle<Atlas>,
+        fin_anim: Key,
+        #[serde(default)]
+        grab_offset: Vec2,
+        body_size: Vec2,
+        bounciness: f32,
+        can_rotate: bool,
+        throw_velocity: f32,
+        angular_velocity: f32,
+
+        /// How far the hook reaches, in pixels.
+        range: f32,
+        /// The angle, in degrees, of the cone in front of the player that opponents are snagged
+        /// in.
+        cone_angle: f32,
+        /// The speed, in pixels per frame, at which snagged players are pulled in.
+        pull_speed: f32,
+        /// How close to the wielder, in pixels, snagged players are released.
+        release_distance: f32,
+        /// How long a snagged player may be pulled in for, before being released.
+        #[serde(with = "humantime_serde")]
+        max_pull_duration: Duration,
+        #[serde(with = "humantime_serde")]
+        cooldown: Duration,
+        cast_sound: Handle<AudioSource>,
+        cast_sound_volume: f64,
+    },
     SlipperySeaweed {
         atlas: Handle<Atlas>,
         start_frame: usize,
@@ -310,6 +439,100 @@ pub enum BuiltinElementKind {
         player_slide: f32,
         body_friction: f32,
     },
+    /// A banana peel that makes the next player who runs over it slip.
+    BananaPeel {
+        atlas: Handle<Atlas>,
+        body_size: Vec2,
+        grab_offset: Vec2,
+        fin_anim: Key,
+        throw_velocity: f32,
+        angular_velocity: f32,
+
+        /// The minimum horizontal speed, in pixels per frame, that players must run over the peel
+        /// with to slip on it.
+        min_speed: f32,
+        /// The velocity that players slip with, in the direction they were running in.
+        slip_velocity: Vec2,
+        /// The horizontal speed, in pixels per frame, that slipping players lose every frame.
+        slip_slowdown: f32,
+        /// How long players lose control for after they slip.
+        #[serde(with = "humantime_serde")]
+        slip_duration: Duration,
+        slip_sound: Handle<AudioSource>,
+        slip_sound_volume: f64,
+    },
+    /// A shield that is put up as a wall in front of the player, blocking projectiles until it
+    /// breaks.
+    Shield {
+        atlas: Handle<Atlas>,
+        body_size: Vec2,
+        grab_offset: Vec2,
+        fin_anim: Key,
+        throw_velocity: f32,
+        angular_velocity: f32,
+
+        wall: ShieldWallMeta,
+    },
+    /// A grenade that blooms into a cloud of smoke, hiding the players inside of it.
+    SmokeGrenade {
+        atlas: Handle<Atlas>,
+        body_diameter: f32,
+        grab_offset: Vec2,
+        fin_anim: Key,
+        throw_velocity: f32,
+        angular_velocity: f32,
+        #[serde(default)]
+        can_rotate: bool,
+        #[serde(default)]
+        bounciness: f32,
+
+        /// The time between the grenade being used and it blooming into a cloud.
+        #[serde(with = "humantime_serde")]
+        fuse_time: Duration,
+        fuse_sound: Handle<AudioSource>,
+        fuse_sound_volume: f64,
+
+        cloud: SmokeCloudMeta,
+    },
+    /// A jetpack that lifts the player holding it while they keep the jump button pressed in the
+    /// air, until it runs out of fuel.
+    Jetpack {
+        atlas: Handle<Atlas>,
+        body_size: Vec2,
+        grab_offset: Vec2,
+        fin_anim: Key,
+        throw_velocity: f32,
+        angular_velocity: f32,
+
+        /// The upward speed, in pixels per frame, added to the player every frame of thrust.
+        thrust: f32,
+        /// The maximum upward speed, in pixels per frame, that the jetpack lifts the player at.
+        max_rise_speed: f32,
+        /// How long the jetpack can thrust for before it is out of fuel.
+        #[serde(with = "humantime_serde")]
+        fuel: Duration,
+
+        /// The flames coming out of the jetpack while it thrusts.
+        flame_atlas: Handle<Atlas>,
+        flame_frames: usize,
+        flame_fps: f32,
+        flame_lifetime: f32,
+        /// The offset of the flames from the jetpack, when facing right.
+        flame_offset: Vec2,
+        /// The number of frames between two flames.
+        flame_interval: usize,
+        empty_sound: Handle<AudioSource>,
+        empty_sound_volume: f64,
+
+        damage_region_size: Vec2,
+        damage_region_lifetime: f32,
+        explosion_sound: Handle<AudioSource>,
+        explosion_volume: f64,
+        explosion_lifetime: f32,
+        explosion_atlas: Handle<Atlas>,
+        explosion_fps: f32,
+        explosion_frames: usize,
+    },
     Spike {
         atlas: Handle<Atlas>,
         body_size: Vec2,
@@ -317,4 +540,456 @@ pub enum BuiltinElementKind {
         end_frame: usize,
         fps: f32,
     },
+    /// A barrel that explodes when it is damaged.
+    ExplosiveBarrel {
+        atlas: Handle<Atlas>,
+        body_size: Vec2,
+        /// The number of frames between the barrel being damaged and it exploding.
+        chain_delay_frames: usize,
+        damage_region_size: Vec2,
+        damage_region_lifetime: f32,
+        explosion_sound: Handle<AudioSource>,
+        explosion_volume: f64,
+        explosion_lifetime: f32,
+        explosion_atlas: Handle<Atlas>,
+        explosion_fps: f32,
+        explosion_frames: usize,
+    },
+    /// A minecart that players can ride along rail tiles.
+    Minecart {
+        atlas: Handle<Atlas>,
+        body_size: Vec2,
+        /// The offset of the rider from the center of the cart.
+        rider_offset: Vec2,
+        /// The speed gained every frame that the rider pushes in a direction.
+        acceleration: f32,
+        max_speed: f32,
+        /// The speed lost every frame while rolling along the rails.
+        rolling_friction: f32,
+        /// The minimum speed at which the cart runs over the players in its way.
+        run_over_speed: f32,
+        /// The velocity the cart is knocked off of the rails with when it is caught in an
+        /// explosion, pointing away from the explosion horizontally.
+        derail_velocity: Vec2,
+        derail_sound: Handle<AudioSource>,
+        derail_volume: f64,
+    },
+    /// A gun shooting a beam that freezes the first player in its way, and shatters them if they
+    /// are hit again shortly after.
+    FreezeRay {
+        atlas: Handle<Atlas>,
+        body_size: Vec2,
+        grab_offset: Vec2,
+        fin_anim: Key,
+        bounciness: f32,
+        can_rotate: bool,
+        throw_velocity: f32,
+        angular_velocity: f32,
+
+        max_ammo: usize,
+        #[serde(with = "humantime_serde")]
+        cooldown: Duration,
+        /// The length of the beam.
+        range: f32,
+        /// The offset of the start of the beam from the center of the gun.
+        muzzle_offset: Vec2,
+        beam_color: ColorMeta,
+        beam_thickness: f32,
+        beam_lifetime: f32,
+
+        /// How long the players hit by the beam stay frozen.
+        #[serde(with = "humantime_serde")]
+        freeze_duration: Duration,
+        /// How long after being frozen the players shatter if they are hit again.
+        #[serde(with = "humantime_serde")]
+        shatter_window: Duration,
+
+        shoot_sound: Handle<AudioSource>,
+        shoot_sound_volume: f64,
+        empty_shoot_sound: Handle<AudioSource>,
+        empty_shoot_sound_volume: f64,
+        shatter_sound: Handle<AudioSource>,
+        shatter_sound_volume: f64,
+    },
+    /// A wire stretched between two anchor points, that shocks the first player to cross it.
+    TripWire {
+        /// The atlas of the posts at both ends of the wire.
+        atlas: Handle<Atlas>,
+        /// The far anchor point, relative to the element, when it isn't set in the editor.
+        default_anchor: Vec2,
+        wire_color: ColorMeta,
+        /// The color of the wire while it recharges.
+        recharging_color: ColorMeta,
+        wire_thickness: f32,
+        /// The velocity the shocked player is knocked back with, pointing away from the direction
+        /// they were moving in horizontally.
+        shock_velocity: Vec2,
+        /// How long the shocked player is stunned for.
+        #[serde(with = "humantime_serde")]
+        stun_duration: Duration,
+        /// How long the wire takes to recharge after shocking a player.
+        #[serde(with = "humantime_serde")]
+        recharge_time: Duration,
+        shock_sound: Handle<AudioSource>,
+        shock_volume: f64,
+    },
+    /// A heavy block that players can push along the ground, stack, and stand on.
+    PushableBlock {
+        atlas: Handle<Atlas>,
+        body_size: Vec2,
+        /// The mass of the block, relative to the mass of a player.
+        ///
+        /// A player pushing the block moves it at their speed divided by its mass.
+        mass: f32,
+    },
+    /// A power-up that shrinks or grows the player that collects it.
+    SizePowerUp {
+        atlas: Handle<Atlas>,
+        body_size: Vec2,
+        /// The scale of the player's sprite and collider.
+        scale: f32,
+        /// How long the player stays resized.
+        #[serde(with = "humantime_serde")]
+        duration: Duration,
+        /// The multiplier applied to the velocity of the items the player throws.
+        #[serde(default = "default_throw_strength")]
+        throw_strength: f32,
+        /// The velocity the players stomped on by the player are knocked away with, pointing away
+        /// from the player horizontally.
+        #[serde(default)]
+        knockback: Vec2,
+        /// How long it takes for the power-up to come back after it has been collected.
+        #[serde(with = "humantime_serde")]
+        respawn_delay: Duration,
+        sound: Handle<AudioSource>,
+        sound_volume: f64,
+    },
+    /// A cloak that makes the player that puts it on nearly invisible to their opponents for a
+    /// while, until they attack.
+    Cloak {
+        atlas: Handle<Atlas>,
+        body_size: Vec2,
+        grab_offset: Vec2,
+        /// How long the player stays [invisible][StatusEffectKind::Invisible], if they don't
+        /// attack.
+        #[serde(with = "humantime_serde")]
+        duration: Duration,
+        sound: Handle<AudioSource>,
+        sound_volume: f64,
+    },
+    /// A coin that can be collected for score in arcade mode.
+    ///
+    /// The same coin is dropped by the players killed in arcade mode.
+    Coin {
+        atlas: Handle<Atlas>,
+        body_diameter: f32,
+        bounciness: f32,
+        /// The score the coin is worth.
+        value: u32,
+        /// How long it takes for a coin placed on the map to come back after it has been collected.
+        #[serde(with = "humantime_serde")]
+        respawn_delay: Duration,
+        /// How long the coins dropped by killed players stay on the map if nobody collects them.
+        #[serde(with = "humantime_serde")]
+        dropped_lifetime: Duration,
+        /// The velocity the coins are scattered with when they are dropped or caught in an
+        /// explosion, pointing away from where they come from horizontally.
+        scatter_velocity: Vec2,
+        sound: Handle<AudioSource>,
+        sound_volume: f64,
+    },
+    /// A platform that travels up and down to the floors of the call buttons linked to it,
+    /// carrying everything that stands on it.
+    Elevator {
+        atlas: Handle<Atlas>,
+        body_size: Vec2,
+        /// The speed of the platform, in pixels per frame.
+        speed: f32,
+        /// How long the platform stops at a floor before it travels to the next floor it was called
+        /// to.
+        #[serde(with = "humantime_serde")]
+        stop_time: Duration,
+    },
+    /// A button that calls the elevators linked to it to its floor when a player touches it.
+    ElevatorCallButton {
+        atlas: Handle<Atlas>,
+        body_size: Vec2,
+        /// The height of the floor of the button, relative to the button. Elevators called to the
+        /// floor stop with the top of their platform level with it.
+        floor_offset: f32,
+        /// How long the button stays lit after it is pressed, before it can be pressed again.
+        #[serde(with = "humantime_serde")]
+        cooldown: Duration,
+        press_sound: Handle<AudioSource>,
+        press_sound_volume: f64,
+    },
+    /// The stand of the flag of a team, in capture the flag matches.
+    FlagStand {
+        /// The team that the stand and its flag belong to.
+        team: u8,
+        atlas: Handle<Atlas>,
+        body_size: Vec2,
+        flag_atlas: Handle<Atlas>,
+        flag_size: Vec2,
+        /// The offset of the flag from the stand, while the flag is at its stand.
+        flag_offset: Vec2,
+        /// The offset of the flag from the body of the player carrying it.
+        carry_offset: Vec2,
+        /// How long a dropped flag lies around before it is returned to its stand.
+        #[serde(with = "humantime_serde")]
+        return_time: Duration,
+        pickup_sound: Handle<AudioSource>,
+        pickup_sound_volume: f64,
+        return_sound: Handle<AudioSource>,
+        return_sound_volume: f64,
+        capture_sound: Handle<AudioSource>,
+        capture_sound_volume: f64,
+    },
+}
+
+impl BuiltinElementKind {
+    /// Whether or not the element is an item that players pick up, or a pickup that its spawner
+    /// spawns again once it has been collected.
+    ///
+    /// These are the elements that the match settings may disable or spawn more of.
+    pub fn is_item(&self) -> bool {
+        matches!(
+            self,
+            Self::Grenade { .. }
+                | Self::DecoyGrenade { .. }
+                | Self::DecoyFish { .. }
+                | Self::Sword { .. }
+                | Self::Crate { .. }
+                | Self::Mine { .. }
+                | Self::StompBoots { .. }
+                | Self::KickBomb { .. }
+                | Self::Musket { .. }
+                | Self::MissileLauncher { .. }
+                | Self::FishingRod { .. }
+                | Self::BananaPeel { .. }
+                | Self::Shield { .. }
+                | Self::SmokeGrenade { .. }
+                | Self::Jetpack { .. }
+                | Self::FreezeRay { .. }
+                | Self::SizePowerUp { .. }
+                | Self::Cloak { .. }
+                | Self::Coin { .. }
+        )
+    }
+}
+
+/// A single swing of the sword.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct SwordSwingMeta {
+    /// The stages of the swing animation, in order.
+    pub stages: Vec<SwordSwingStageMeta>,
+}
+
+impl SwordSwingMeta {
+    /// Get the stage of the swing at the given frame, or `None` if the swing is over by then.
+    pub fn stage_at(&self, frame: usize) -> Option<&SwordSwingStageMeta> {
+        let mut stage_end = 0;
+        self.stages.iter().find(|stage| {
+            stage_end += stage.frames;
+            frame < stage_end
+        })
+    }
+}
+
+/// A stage of a sword swing.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SwordSwingStageMeta {
+    /// The sword sprite index to show during this stage.
+    pub sprite_index: usize,
+    /// The number of frames the stage lasts.
+    #[serde(default = "default_sword_stage_frames")]
+    pub frames: usize,
+    /// The offset of the player's fin during this stage.
+    #[serde(default)]
+    pub fin_offset: Vec2,
+    /// The damage region spawned during this stage, if any.
+    #[serde(default)]
+    pub damage: Option<SwordDamageMeta>,
+}
+
+/// A damage region spawned by a sword swing.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct SwordDamageMeta {
+    /// The offset of the region from the player, when facing right.
+    pub offset: Vec2,
+    pub size: Vec2,
+}
+
+/// The charged swing of a sword.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct SwordHeavySwingMeta {
+    /// The number of frames shoot must be held after being pressed before the sword starts
+    /// charging, so that pressing shoot still does a normal swing right away.
+    pub hold_frames: usize,
+    /// The number of frames the sword must be charging before the swing is charged.
+    pub charge_frames: usize,
+    /// The sword sprite index to show while charging.
+    pub charge_sprite_index: usize,
+    pub swing: SwordSwingMeta,
+}
+
+/// The cloud of smoke of a [`BuiltinElementKind::SmokeGrenade`].
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct SmokeCloudMeta {
+    pub atlas: Handle<Atlas>,
+    pub frames: usize,
+    pub fps: f32,
+    /// The size of the cloud once it has fully bloomed.
+    pub size: Vec2,
+    /// How long the cloud takes to grow to its full size.
+    #[serde(with = "humantime_serde")]
+    pub bloom_time: Duration,
+    /// How long the cloud lingers for.
+    #[serde(with = "humantime_serde")]
+    pub duration: Duration,
+    /// The alpha that the players inside of the cloud are rendered with.
+    pub player_alpha: f32,
+    pub sound: Handle<AudioSource>,
+    pub sound_volume: f64,
+}
+
+/// The wall put up by a [`BuiltinElementKind::Shield`].
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ShieldWallMeta {
+    /// The atlas of the wall, with one frame for every stage of cracking, from intact to nearly
+    /// broken.
+    pub atlas: Handle<Atlas>,
+    pub crack_frames: usize,
+    pub size: Vec2,
+    /// The offset of the wall from the player, when facing right.
+    pub offset: Vec2,
+    /// The number of bullets the wall can take before it breaks.
+    pub health: u32,
+    /// The health the wall loses when it is caught in an explosion.
+    pub explosion_damage: u32,
+    /// The time in seconds before the wall goes away by itself.
+    pub lifetime: f32,
+    pub place_sound: Handle<AudioSource>,
+    pub place_volume: f64,
+    pub break_sound: Handle<AudioSource>,
+    pub break_volume: f64,
+}
+
+/// A missile shot by a [`BuiltinElementKind::MissileLauncher`].
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct MissileMeta {
+    pub atlas: Handle<Atlas>,
+    pub body_diameter: f32,
+    /// The speed of the missile, in pixels per frame.
+    pub speed: f32,
+    /// The maximum angle, in degrees, that the missile turns toward its target every frame.
+    pub turn_rate: f32,
+    /// How long the missile flies before it runs out of fuel and explodes.
+    #[serde(with = "humantime_serde")]
+    pub fuel_time: Duration,
+
+    /// The size of the damage region of the missile's explosion.
+    pub blast_size: Vec2,
+    pub blast_lifetime: f32,
+    pub explosion_atlas: Handle<Atlas>,
+    pub explosion_frames: usize,
+    pub explosion_fps: f32,
+    pub explosion_lifetime: f32,
+    pub explosion_sound: Handle<AudioSource>,
+    pub explosion_volume: f64,
+
+    /// The smoke puffs left behind the missile.
+    pub smoke_atlas: Handle<Atlas>,
+    pub smoke_frames: usize,
+    pub smoke_fps: f32,
+    pub smoke_lifetime: f32,
+    /// The number of frames between two smoke puffs.
+    pub smoke_interval: usize,
+
+    /// Lock the missile onto a single target when it is shot, instead of seeking the nearest
+    /// opponent.
+    #[serde(default)]
+    pub lock_on: Option<MissileLockOnMeta>,
+}
+
+/// The lock-on of a [`MissileMeta`].
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct MissileLockOnMeta {
+    /// The angle in degrees of the cone in front of the launcher that targets are locked onto in.
+    pub cone: f32,
+    /// The sound warning the target that a missile is locked onto them.
+    pub warning_sound: Handle<AudioSource>,
+    pub warning_volume: f64,
+    /// The number of frames between two warning sounds.
+    pub warning_interval: usize,
+}
+
+/// The clank of two swords whose swings clash, which cancels both swings.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct SwordParryMeta {
+    pub sound: Handle<AudioSource>,
+    pub sound_volume: f64,
+    /// The horizontal speed the players are pushed back from each other with.
+    pub knockback: f32,
+}
+
+/// The harmless confetti pop of a decoy.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct DecoyPopMeta {
+    pub atlas: Handle<Atlas>,
+    pub frames: usize,
+    pub fps: f32,
+    /// How long the confetti is shown for, in seconds.
+    pub lifetime: f32,
+    pub sound: Handle<AudioSource>,
+    pub volume: f64,
+}
+
+fn default_throw_strength() -> f32 {
+    1.0
+}
+
+fn default_sword_stage_frames() -> usize {
+    3
+}
+
+/// The single swing that swords without a combo chain do.
+fn default_sword_combo() -> Vec<SwordSwingMeta> {
+    let stage = |sprite_index, fin_offset, damage: Option<(Vec2, Vec2)>| SwordSwingStageMeta {
+        sprite_index,
+        frames: default_sword_stage_frames(),
+        fin_offset,
+        damage: damage.map(|(offset, size)| SwordDamageMeta { offset, size }),
+    };
+
+    vec![SwordSwingMeta {
+        stages: vec![
+            stage(
+                8,
+                vec2(-1.0, 2.0),
+                Some((vec2(20.0, 20.0), vec2(30.0, 70.0))),
+            ),
+            stage(
+                9,
+                vec2(0.0, -1.0),
+                Some((vec2(25.0, 20.0), vec2(40.0, 50.0))),
+            ),
+            stage(
+                10,
+                vec2(0.0, -2.0),
+                Some((vec2(20.0, 0.0), vec2(40.0, 40.0))),
+            ),
+            stage(11, vec2(0.0, -2.0), None),
+        ],
+    }]
 }
@@ -6,6 +6,21 @@ use super::*;
 #[serde(deny_unknown_fields)]
 pub struct MapMeta {
     pub name: String,
+    /// The name of the person who made the map.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub author: String,
+    /// A short description of the map.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    /// The version of the map, increased every time that it is saved in the editor.
+    ///
+    /// Copies of a map with the same name and version are the same, so this can be used to tell
+    /// whether a cached copy of a custom map is still up to date.
+    #[serde(default)]
+    pub version: u32,
+    /// The number of players that the map was designed for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recommended_players: Option<MapPlayerCountMeta>,
     /// The parallax background layers
     #[serde(default)]
     pub background: BackgroundMeta,
@@ -17,6 +32,215 @@ pub struct MapMeta {
     pub tile_size: Vec2,
     /// The layers of the map
     pub layers: Vec<MapLayerMeta>,
+    /// Overrides of the global physics settings for this map
+    #[serde(default, skip_serializing_if = "MapPhysicsMeta::is_empty")]
+    pub physics: MapPhysicsMeta,
+    /// The stages that the map transitions through during the match, in chronological order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stages: Vec<MapStageMeta>,
+    /// The rules for the coins dropped by killed players in arcade mode.
+    #[serde(default, skip_serializing_if = "MapCoinsMeta::is_default")]
+    pub coins: MapCoinsMeta,
+    /// The rules for the rising lava.
+    #[serde(default, skip_serializing_if = "MapLavaMeta::is_default")]
+    pub lava: MapLavaMeta,
+}
+
+/// A range of player counts.
+#[derive(BonesBevyAssetLoad, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct MapPlayerCountMeta {
+    pub min: u32,
+    pub max: u32,
+}
+
+impl Default for MapPlayerCountMeta {
+    fn default() -> Self {
+        Self {
+            min: 2,
+            max: MAX_PLAYERS as u32,
+        }
+    }
+}
+
+/// A stage of a multi-stage map.
+///
+/// When the map transitions to a stage, the tiles and elements of the layers in
+/// [`show_layers`][Self::show_layers] are spawned, and the ones in
+/// [`hide_layers`][Self::hide_layers] are despawned. Layers that are shown by any stage are hidden
+/// when the match starts.
+#[derive(BonesBevyAssetLoad, Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct MapStageMeta {
+    /// The number of seconds after the start of the match that the map transitions to this stage.
+    pub start_time: f32,
+    /// The IDs of the layers that appear when transitioning to this stage.
+    #[serde(default)]
+    pub show_layers: Vec<String>,
+    /// The IDs of the layers that disappear when transitioning to this stage.
+    #[serde(default)]
+    pub hide_layers: Vec<String>,
+}
+
+/// Overrides of the global [`PhysicsMeta`] for a single map.
+///
+/// Any setting left unset uses the value from the core metadata.
+#[derive(BonesBevyAssetLoad, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct MapPhysicsMeta {
+    /// Multiplier applied to the gravity of every kinematic body, on top of the global gravity
+    /// scale.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gravity_scale: Option<f32>,
+    /// The maximum falling speed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub terminal_velocity: Option<f32>,
+    /// The factor that velocity is multiplied by every frame to slow bodies down.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub friction_lerp: Option<f32>,
+}
+
+impl MapPhysicsMeta {
+    /// Whether or not this map doesn't override any physics setting.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Apply the overrides to the core metadata.
+    pub fn apply_to_meta(&self, meta: Arc<CoreMeta>) -> Arc<CoreMeta> {
+        if self.is_empty() {
+            return meta;
+        }
+
+        let mut meta = (*meta).clone();
+        if let Some(gravity_scale) = self.gravity_scale {
+            meta.physics.gravity_scale *= gravity_scale;
+        }
+        if let Some(terminal_velocity) = self.terminal_velocity {
+            meta.physics.terminal_velocity = terminal_velocity;
+        }
+        if let Some(friction_lerp) = self.friction_lerp {
+            meta.physics.friction_lerp = friction_lerp;
+        }
+
+        Arc::new(meta)
+    }
+}
+
+/// The rules for the coins dropped by killed players in arcade mode.
+///
+/// See the [`arcade`][crate::arcade] module.
+#[derive(BonesBevyAssetLoad, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct MapCoinsMeta {
+    /// The share of the coins they collected that killed players drop, from `0.0` to `1.0`.
+    pub drop_share: f32,
+    /// The number of coins that killed players drop, even if they haven't collected as many.
+    pub min_drop: u32,
+    /// The most dropped coins that may be lying on the map at once.
+    pub max_dropped: u32,
+}
+
+impl Default for MapCoinsMeta {
+    fn default() -> Self {
+        Self {
+            drop_share: 0.5,
+            min_drop: 1,
+            max_dropped: 30,
+        }
+    }
+}
+
+impl MapCoinsMeta {
+    /// Whether or not this map uses the default rules.
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Get the number of coins a killed player drops, given the number of coins they collected.
+    pub fn drop_count(&self, collected: u32) -> u32 {
+        let share = (collected as f32 * self.drop_share.clamp(0.0, 1.0)).ceil() as u32;
+        share.max(self.min_drop)
+    }
+}
+
+/// The rules for the rising lava.
+///
+/// See the [`lava`][crate::lava] module.
+#[derive(BonesBevyAssetLoad, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct MapLavaMeta {
+    /// Whether the lava rises on this map even when the rising lava mutator isn't enabled.
+    pub enabled: bool,
+    /// The rise curve of the lava, in chronological order.
+    ///
+    /// The lava rises in a straight line from one point to the next, and stays at the height of
+    /// the last point once it is reached.
+    pub rise: Vec<LavaPointMeta>,
+}
+
+impl Default for MapLavaMeta {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            rise: vec![
+                LavaPointMeta {
+                    time: 30.0,
+                    height: 0.0,
+                },
+                LavaPointMeta {
+                    time: 150.0,
+                    height: 0.75,
+                },
+            ],
+        }
+    }
+}
+
+impl MapLavaMeta {
+    /// Whether or not this map uses the default rules.
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The number of seconds after the start of the round that the lava starts rising.
+    pub fn start_time(&self) -> Option<f32> {
+        self.rise.first().map(|x| x.time)
+    }
+
+    /// Get the height of the surface of the lava, as a share of the map height, the given number of
+    /// seconds after the start of the round, if it has started rising.
+    pub fn height_at(&self, time: f32) -> Option<f32> {
+        let first = self.rise.first()?;
+        if time < first.time {
+            return None;
+        }
+
+        let next = self.rise.iter().position(|x| x.time > time);
+        let height = match next {
+            None => self.rise.last().unwrap().height,
+            Some(next) => {
+                let (a, b) = (&self.rise[next - 1], &self.rise[next]);
+                let t = (time - a.time) / (b.time - a.time);
+                a.height + (b.height - a.height) * t
+            }
+        };
+        Some(height)
+    }
+}
+
+/// A point of the rise curve of the lava.
+#[derive(BonesBevyAssetLoad, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct LavaPointMeta {
+    /// The number of seconds after the start of the round.
+    pub time: f32,
+    /// The height of the surface of the lava, as a share of the map height, from `0.0` at the
+    /// bottom of the map to `1.0` at the top.
+    pub height: f32,
 }
 
 #[derive(BonesBevyAssetLoad, Serialize, Deserialize, Clone, Debug, Default)]
@@ -48,6 +272,26 @@ pub struct MapLayerMeta {
     pub tiles: Vec<MapTileMeta>,
     #[serde(default)]
     pub elements: Vec<ElementSpawn>,
+    /// Makes the layer render in front of the players, if set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub foreground: Option<MapForegroundMeta>,
+}
+
+/// The settings of a map layer that renders in front of the players.
+///
+/// Foreground layers are meant for decoration such as foliage or pillars, so their tiles usually
+/// don't have collisions.
+#[derive(BonesBevyAssetLoad, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct MapForegroundMeta {
+    /// How much faster than the map the layer moves when the camera moves, giving the impression
+    /// that it is closer to the camera. `0.0` disables the parallax.
+    #[serde(default)]
+    pub parallax: f32,
+    /// The opacity of the layer while a player is behind one of its tiles, or [`None`] to keep
+    /// the layer opaque.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fade_alpha: Option<f32>,
 }
 
 #[derive(BonesBevyAssetLoad, Serialize, Deserialize, Clone, Debug, Default)]
@@ -55,6 +299,83 @@ pub struct MapLayerMeta {
 pub struct ElementSpawn {
     pub pos: Vec2,
     pub element: Handle<ElementMeta>,
+    /// Whether the element is mirrored horizontally.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub flip_x: bool,
+    /// Whether the element is mirrored vertically.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub flip_y: bool,
+    /// The number of counter-clockwise quarter turns the element is rotated by.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub rotation: u8,
+    /// The attributes of a player spawner, set in the editor.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spawn_point: Option<SpawnPointMeta>,
+    /// The far end of a trip wire, set in the editor.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wire_anchor: Option<WireAnchorMeta>,
+    /// The link channel of an element that works with other elements, set in the editor.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link: Option<LinkMeta>,
+}
+
+/// The attributes of a player spawner, used to choose where players spawn.
+///
+/// When a player spawns, the spawners of the player's team, or without a team, with the highest
+/// priority are considered, and the one furthest from the other players is used.
+#[derive(
+    BonesBevyAssetLoad, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, TypeUlid,
+)]
+#[serde(deny_unknown_fields)]
+#[ulid = "01H2GT4E7MZQ3KXN8B5VJ1RCWD"]
+pub struct SpawnPointMeta {
+    /// The index of the team that spawns here, or [`None`] if any player can spawn here.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub team: Option<u8>,
+    /// Spawners with a higher priority are used before the others.
+    #[serde(default, skip_serializing_if = "is_zero_i32")]
+    pub priority: i32,
+    /// Whether players spawned here face left.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub face_left: bool,
+}
+
+/// The far anchor point of a [trip wire][crate::elements::trip_wire], which the wire is stretched to
+/// from the element's position.
+#[derive(
+    BonesBevyAssetLoad, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, TypeUlid,
+)]
+#[serde(deny_unknown_fields)]
+#[ulid = "01H31TW4J8KD2QRX5MNB7EVA3C"]
+pub struct WireAnchorMeta {
+    /// The position of the anchor point, relative to the element.
+    pub offset: Vec2,
+}
+
+/// The link channel of an element that works together with other elements, such as an
+/// [elevator][crate::elements::elevator] and its call buttons.
+///
+/// Elements are linked to the other elements on the same channel. Elements without a channel set
+/// are on the channel `0`.
+#[derive(
+    BonesBevyAssetLoad, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, TypeUlid,
+)]
+#[serde(deny_unknown_fields)]
+#[ulid = "01H33E2N7XKQ4BV9TZ5MRJ8WDC"]
+pub struct LinkMeta {
+    pub channel: u8,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+fn is_zero(value: &u8) -> bool {
+    *value == 0
+}
+
+fn is_zero_i32(value: &i32) -> bool {
+    *value == 0
 }
 
 #[derive(BonesBevyAssetLoad, Serialize, Deserialize, Clone, Debug)]
@@ -66,7 +387,73 @@ pub struct MapTileMeta {
     pub collision: TileCollisionKind,
 }
 
+/// A reusable group of tiles and elements that can be stamped into maps from the editor.
+///
+/// Stamping a prefab copies its contents into the map layer, so maps never refer to prefabs.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct MapPrefabMeta {
+    pub name: String,
+    /// The tilemap that the tile indexes refer to.
+    #[serde(default)]
+    pub tilemap: Option<Handle<Atlas>>,
+    /// The tiles, positioned relative to the bottom-left corner of the prefab.
+    #[serde(default)]
+    pub tiles: Vec<MapTileMeta>,
+    /// The elements, positioned relative to the bottom-left corner of the prefab.
+    #[serde(default)]
+    pub elements: Vec<ElementSpawn>,
+}
+
+impl MapPrefabMeta {
+    /// Create a prefab from the tiles and elements of a map layer that are inside of the given
+    /// rectangle of tiles, `min` being inclusive and `max` exclusive.
+    pub fn from_layer(
+        name: String,
+        layer: &MapLayerMeta,
+        tile_size: Vec2,
+        min: UVec2,
+        max: UVec2,
+    ) -> Self {
+        let min_pos = min.as_vec2() * tile_size;
+        let max_pos = max.as_vec2() * tile_size;
+
+        Self {
+            name,
+            tilemap: layer.tilemap.clone(),
+            tiles: layer
+                .tiles
+                .iter()
+                .filter(|tile| tile.pos.cmpge(min).all() && tile.pos.cmplt(max).all())
+                .map(|tile| MapTileMeta {
+                    pos: tile.pos - min,
+                    ..tile.clone()
+                })
+                .collect(),
+            elements: layer
+                .elements
+                .iter()
+                .filter(|element| {
+                    element.pos.cmpge(min_pos).all() && element.pos.cmplt(max_pos).all()
+                })
+                .map(|element| ElementSpawn {
+                    pos: element.pos - min_pos,
+                    ..element.clone()
+                })
+                .collect(),
+        }
+    }
+}
+
 impl MapMeta {
+    /// Whether or not the layer with the given ID is visible when the match starts.
+    pub fn is_layer_initially_visible(&self, layer_id: &str) -> bool {
+        !self
+            .stages
+            .iter()
+            .any(|stage| stage.show_layers.iter().any(|x| x == layer_id))
+    }
+
     /// Checks if the given position is out of the bounds of the map.
     pub fn is_out_of_bounds(&self, pos: &Vec3) -> bool {
         const KILL_ZONE_BORDER: f32 = 500.0;
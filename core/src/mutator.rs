@@ -0,0 +1,156 @@
+//! Match mutators: silly modifiers that change the rules of a match.
+//!
+//! Mutators are selected in the [`MatchSettings`] and are applied in two ways:
+//!
+//! - Metadata overrides, which are applied to the [`CoreMeta`] once, when the session is created,
+//!   by [`apply_meta_overrides()`].
+//! - Systems, which are installed for every session, but only do anything when their mutator is
+//!   enabled.
+//!
+//! Mutators are independent of each other, so any combination of them may be enabled at once.
+
+use crate::prelude::*;
+
+/// Install this module.
+pub fn install(session: &mut CoreSession) {
+    session
+        .stages
+        .add_system_to_stage(CoreStage::Update, jetpacks)
+        .add_system_to_stage(CoreStage::Last, big_heads);
+}
+
+/// A modifier that may be enabled for a match in the [`MatchSettings`].
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum Mutator {
+    /// Everything falls much more slowly.
+    LowGravity,
+    /// Every player can fly by holding the jump button in the air.
+    Jetpacks,
+    /// Only explosive items will spawn on the map.
+    ExplosivesOnly,
+    /// Swords break after a single swing.
+    OneHitSwords,
+    /// Every player has a giant head.
+    BigHeads,
+    /// Lava rises from the bottom of the map over the round.
+    ///
+    /// See the [`lava`][crate::lava] module.
+    RisingLava,
+}
+
+impl Mutator {
+    /// All of the available mutators.
+    pub const ALL: [Mutator; 6] = [
+        Mutator::LowGravity,
+        Mutator::Jetpacks,
+        Mutator::ExplosivesOnly,
+        Mutator::OneHitSwords,
+        Mutator::BigHeads,
+        Mutator::RisingLava,
+    ];
+
+    /// The localization key for the name of the mutator.
+    pub fn name_key(&self) -> &'static str {
+        match self {
+            Mutator::LowGravity => "mutator-low-gravity",
+            Mutator::Jetpacks => "mutator-jetpacks",
+            Mutator::ExplosivesOnly => "mutator-explosives-only",
+            Mutator::OneHitSwords => "mutator-one-hit-swords",
+            Mutator::BigHeads => "mutator-big-heads",
+            Mutator::RisingLava => "mutator-rising-lava",
+        }
+    }
+
+    /// Whether or not this mutator allows the given element to be spawned on the map.
+    pub fn allows_element(&self, element_meta: &ElementMeta) -> bool {
+        match self {
+            Mutator::ExplosivesOnly => {
+                element_meta.category != "Weapons"
+                    || matches!(
+                        element_meta.builtin,
+                        BuiltinElementKind::Grenade { .. }
+                            | BuiltinElementKind::KickBomb { .. }
+                            | BuiltinElementKind::Mine { .. }
+                    )
+            }
+            _ => true,
+        }
+    }
+}
+
+/// The gravity scale used by the [`Mutator::LowGravity`] mutator.
+const LOW_GRAVITY_SCALE: f32 = 0.4;
+/// The upward acceleration applied every frame by the [`Mutator::Jetpacks`] mutator.
+const JETPACK_THRUST: f32 = 1.2;
+/// The maximum upward speed that a jetpack can reach.
+const JETPACK_MAX_SPEED: f32 = 6.0;
+/// The scale of player heads in the [`Mutator::BigHeads`] mutator.
+const BIG_HEAD_SCALE: f32 = 1.8;
+
+/// Apply the metadata overrides for the mutators enabled in the match settings.
+pub fn apply_meta_overrides(meta: Arc<CoreMeta>, match_settings: &MatchSettings) -> Arc<CoreMeta> {
+    if match_settings.mutators.is_empty() {
+        return meta;
+    }
+
+    let mut meta = (*meta).clone();
+    for mutator in &match_settings.mutators {
+        if let Mutator::LowGravity = mutator {
+            meta.physics.gravity_scale *= LOW_GRAVITY_SCALE;
+        }
+    }
+
+    Arc::new(meta)
+}
+
+/// Let players fly while they hold the jump button in the air.
+fn jetpacks(
+    entities: Res<Entities>,
+    match_settings: Res<MatchSettings>,
+    player_inputs: Res<PlayerInputs>,
+    player_indexes: Comp<PlayerIdx>,
+    mut bodies: CompMut<KinematicBody>,
+) {
+    if !match_settings.has_mutator(Mutator::Jetpacks) {
+        return;
+    }
+
+    for (_ent, (player_idx, body)) in entities.iter_with((&player_indexes, &mut bodies)) {
+        let control = &player_inputs.players[player_idx.0].control;
+
+        if control.jump_pressed && !control.jump_just_pressed && !body.is_on_ground {
+            body.velocity.y = (body.velocity.y + JETPACK_THRUST).min(JETPACK_MAX_SPEED);
+        }
+    }
+}
+
+/// Scale up the player faces and hats.
+///
+/// This runs after the attachment systems, which reset the transforms of the face and hat every
+/// frame, so the scale does not accumulate.
+fn big_heads(
+    entities: Res<Entities>,
+    match_settings: Res<MatchSettings>,
+    player_layers: Comp<PlayerLayers>,
+    attachments: Comp<Attachment>,
+    mut transforms: CompMut<Transform>,
+) {
+    if !match_settings.has_mutator(Mutator::BigHeads) {
+        return;
+    }
+
+    for (_ent, layers) in entities.iter_with(&player_layers) {
+        for head_ent in [Some(layers.face_ent), layers.hat_ent]
+            .into_iter()
+            .flatten()
+        {
+            if !attachments.contains(head_ent) {
+                continue;
+            }
+            if let Some(transform) = transforms.get_mut(head_ent) {
+                transform.scale.x *= BIG_HEAD_SCALE;
+                transform.scale.y *= BIG_HEAD_SCALE;
+            }
+        }
+    }
+}
@@ -0,0 +1,618 @@
+//! Navigation graph used by the AI to move around the map.
+//!
+//! The [`NavGraph`] resource is re-computed from the tiles of the visible map layers whenever the
+//! map is spawned or transitions to another stage. Each node of the graph is a tile that a player
+//! can be in, and each edge is a movement between two tiles, such as walking, jumping, or falling,
+//! along with the inputs required to perform it. This lets the AI look for paths that it can
+//! actually follow, instead of walking off ledges.
+
+use std::{
+    cmp::{max, min},
+    collections::VecDeque,
+};
+
+use ::bevy::utils::HashSet;
+
+use crate::prelude::{collisions::TileCollisionKind, *};
+
+/// The map navigation graph resource.
+#[derive(Clone, Debug, Deref, DerefMut, TypeUlid, Default)]
+#[ulid = "01GQWP4QG11NBVX3M289TXAK6W"]
+pub struct NavGraph(pub Arc<NavGraphInner>);
+
+impl NavGraph {
+    /// Find the shortest path between two nodes of the graph, see [`find_path`].
+    pub fn find_path(&self, start: NavNode, goal: NavNode) -> Option<Vec<NavNode>> {
+        find_path(&self.0, start, goal)
+    }
+}
+
+/// The inner graph type of [`NavGraph`].
+pub type NavGraphInner = petgraph::graphmap::DiGraphMap<NavNode, NavGraphEdge>;
+
+/// The type of nodes in the map navigation graph.
+///
+/// This is merely a wrapper around [`UVec2`] to add an [`Ord`] implementation.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash, Deref, DerefMut)]
+pub struct NavNode(pub IVec2);
+
+impl NavNode {
+    /// Get the node of the tile containing the given position, in world space.
+    pub fn from_position(position: Vec2, tile_size: Vec2) -> Self {
+        NavNode((position / tile_size).floor().as_ivec2())
+    }
+
+    /// Get the position of the center of the node's tile, in world space.
+    pub fn center(&self, tile_size: Vec2) -> Vec2 {
+        self.0.as_vec2() * tile_size + tile_size / 2.0
+    }
+
+    /// Calculates the Pythagorean distance between two nodes.
+    pub fn distance(&self, other: &Self) -> f32 {
+        let dx = (max(self.x, other.x) - min(self.x, other.x)) as f32;
+        let dy = (max(self.y, other.y) - min(self.y, other.y)) as f32;
+        (dx * dx) + (dy * dy)
+    }
+
+    pub fn right(&self) -> NavNode {
+        NavNode(self.0 + ivec2(1, 0))
+    }
+
+    pub fn above(&self) -> NavNode {
+        NavNode(self.0 + ivec2(0, 1))
+    }
+
+    pub fn left(&self) -> NavNode {
+        NavNode(self.0 - ivec2(1, 0))
+    }
+
+    pub fn below(&self) -> NavNode {
+        NavNode(self.0 - ivec2(0, 1))
+    }
+}
+
+impl From<IVec2> for NavNode {
+    fn from(v: IVec2) -> Self {
+        Self(v)
+    }
+}
+impl From<NavNode> for IVec2 {
+    fn from(v: NavNode) -> Self {
+        v.0
+    }
+}
+impl std::cmp::Ord for NavNode {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.partial_cmp(other).unwrap()
+    }
+}
+impl std::cmp::PartialOrd for NavNode {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        let xcmp = self.0.x.cmp(&other.0.x);
+        Some(if xcmp == std::cmp::Ordering::Equal {
+            self.0.y.cmp(&other.0.y)
+        } else {
+            xcmp
+        })
+    }
+}
+
+/// Represents the way to get from one tile to another tile in the navigation graph.
+#[derive(Debug, Clone)]
+pub struct NavGraphEdge {
+    /// The sequence of inputs for each frame, required to get to the connected tile.
+    pub inputs: VecDeque<PlayerControl>,
+    /// The distance to the connected tile. This is used as the heuristic for pathfinding.
+    pub distance: f32,
+}
+
+/// Helper method to create a navigation graph from the map metadata, taking only the tiles of the
+/// visible layers into account.
+pub(crate) fn create_nav_graph(meta: &MapMeta, visible_layers: &[bool]) -> Arc<NavGraphInner> {
+    // Load the navigation graph
+    let mut graph = NavGraphInner::default();
+
+    // Initialize set of traversable tiles, assuming all tiles are traversable
+    for x in 0..meta.grid_size.x as i32 {
+        for y in 0..meta.grid_size.y as i32 {
+            graph.add_node(NavNode(ivec2(x, y)));
+        }
+    }
+
+    // Find all solid tiles and remove them from the traversable tiles list, while also recording
+    // the jump-through tiles.
+    let mut semi_solids = HashSet::default();
+    for (layer, _) in meta
+        .layers
+        .iter()
+        .zip(visible_layers)
+        .filter(|(_, visible)| **visible)
+    {
+        for tile in &layer.tiles {
+            if tile.collision.is_jump_through() {
+                semi_solids.insert(NavNode(tile.pos.as_ivec2()));
+            } else if tile.collision != TileCollisionKind::Empty {
+                graph.remove_node(NavNode(tile.pos.as_ivec2()));
+            }
+        }
+    }
+
+    // Calculate possible movements from every node
+    macro_rules! is_solid {
+        ($node:expr) => {
+            !graph.contains_node($node) || semi_solids.contains(&$node)
+        };
+    }
+
+    for node in graph.nodes().collect::<Vec<_>>() {
+        // walk left or right along the ground
+        let has_ground = is_solid!(node.below());
+        let maybe_has_ground =
+            has_ground || is_solid!(node.below().left()) || is_solid!(node.below().right());
+
+        /////////////////
+        // Grounded
+        /////////////////
+
+        if maybe_has_ground {
+            // Moving Right
+            let right = node.right();
+            if graph.contains_node(right) {
+                graph.add_edge(
+                    node,
+                    right,
+                    NavGraphEdge {
+                        inputs: [PlayerControl {
+                            moving: true,
+                            move_direction: vec2(1.0, 0.0),
+                            ..default()
+                        }]
+                        .into(),
+                        distance: node.distance(&right),
+                    },
+                );
+            }
+
+            // Moving Left
+            let left = node.left();
+            if graph.contains_node(left) {
+                graph.add_edge(
+                    node,
+                    left,
+                    NavGraphEdge {
+                        inputs: [PlayerControl {
+                            moving: true,
+                            move_direction: vec2(-1.0, 0.0),
+                            ..default()
+                        }]
+                        .into(),
+                        distance: node.distance(&left),
+                    },
+                );
+            }
+        }
+
+        if has_ground {
+            /////////////////
+            // JUMPING
+            /////////////////
+            let above1 = node.above();
+            let above2 = above1.above();
+            let above3 = above2.above();
+            let contains_above1 = graph.contains_node(above1);
+            let contains_above2 = graph.contains_node(above2);
+            let contains_above3 = graph.contains_node(above3);
+
+            if contains_above1 {
+                // Jump staight up
+                graph.add_edge(
+                    node,
+                    above1,
+                    NavGraphEdge {
+                        inputs: [PlayerControl {
+                            jump_just_pressed: true,
+                            jump_pressed: true,
+                            ..default()
+                        }]
+                        .into(),
+                        distance: node.distance(&above1),
+                    },
+                );
+            }
+            if contains_above2 && contains_above1 {
+                // Jump staight up
+                graph.add_edge(
+                    node,
+                    above2,
+                    NavGraphEdge {
+                        inputs: [PlayerControl {
+                            jump_just_pressed: true,
+                            jump_pressed: true,
+                            ..default()
+                        }]
+                        .into(),
+                        distance: node.distance(&above2),
+                    },
+                );
+            }
+            if contains_above3 && contains_above2 && contains_above1 {
+                // Jump staight up
+                graph.add_edge(
+                    node,
+                    above2,
+                    NavGraphEdge {
+                        inputs: [PlayerControl {
+                            jump_just_pressed: true,
+                            jump_pressed: true,
+                            ..default()
+                        }]
+                        .into(),
+                        distance: node.distance(&above3),
+                    },
+                );
+            }
+
+            // Jump up and left
+            let above3l2 = above3.left().left();
+            let above2l = above2.left();
+            let contains_above2l = graph.contains_node(above2l);
+            let contains_above3l2 = graph.contains_node(above3l2);
+            if contains_above3l2 && contains_above2 && contains_above3 && contains_above2l {
+                graph.add_edge(
+                    node,
+                    above3l2,
+                    NavGraphEdge {
+                        inputs: std::iter::repeat(PlayerControl {
+                            move_direction: vec2(-1.0, 0.0),
+                            jump_just_pressed: true,
+                            jump_pressed: true,
+                            ..default()
+                        })
+                        .take(20)
+                        .collect(),
+                        distance: node.distance(&above3l2),
+                    },
+                );
+            }
+            let above3l3 = above3.left().left().left();
+            if graph.contains_node(above3l3)
+                && graph.contains_node(above3.left())
+                && contains_above3l2
+                && contains_above2
+                && contains_above3
+                && contains_above2l
+            {
+                graph.add_edge(
+                    node,
+                    above3l3,
+                    NavGraphEdge {
+                        inputs: std::iter::repeat(PlayerControl {
+                            move_direction: vec2(-1.0, 0.0),
+                            jump_just_pressed: true,
+                            jump_pressed: true,
+                            ..default()
+                        })
+                        .take(20)
+                        .collect(),
+                        distance: node.distance(&above3l3),
+                    },
+                );
+            }
+
+            // Jump up and right
+            let above3r2 = above3.right().right();
+            let above2r = above2.right();
+            let contains_above2r = graph.contains_node(above2r);
+            let contains_above3r2 = graph.contains_node(above3r2);
+            if contains_above3r2 && contains_above2 && contains_above3 && contains_above2r {
+                graph.add_edge(
+                    node,
+                    above3r2,
+                    NavGraphEdge {
+                        inputs: std::iter::repeat(PlayerControl {
+                            move_direction: vec2(1.0, 0.0),
+                            jump_just_pressed: true,
+                            jump_pressed: true,
+                            ..default()
+                        })
+                        .take(20)
+                        .collect(),
+                        distance: node.distance(&above3r2),
+                    },
+                );
+            }
+            let above3r3 = above3.right().right().right();
+            if graph.contains_node(above3r3)
+                && graph.contains_node(above3.right())
+                && contains_above3r2
+                && contains_above2
+                && contains_above3
+                && contains_above2r
+            {
+                graph.add_edge(
+                    node,
+                    above3r3,
+                    NavGraphEdge {
+                        inputs: std::iter::repeat(PlayerControl {
+                            move_direction: vec2(1.0, 0.0),
+                            jump_just_pressed: true,
+                            jump_pressed: true,
+                            ..default()
+                        })
+                        .take(20)
+                        .collect(),
+                        distance: node.distance(&above3r3),
+                    },
+                );
+            }
+        }
+
+        /////////////////
+        // Falling Down
+        /////////////////
+
+        // Fall straight down
+        let below = node.below();
+        if graph.contains_node(below) {
+            if semi_solids.contains(&below) {
+                graph.add_edge(
+                    node,
+                    below,
+                    NavGraphEdge {
+                        inputs: [
+                            PlayerControl {
+                                move_direction: vec2(0.0, -1.0),
+                                jump_just_pressed: true,
+                                jump_pressed: true,
+                                ..default()
+                            },
+                            default(),
+                            default(),
+                            default(),
+                            default(),
+                        ]
+                        .into(),
+                        distance: node.distance(&below),
+                    },
+                );
+            } else {
+                graph.add_edge(
+                    node,
+                    below,
+                    NavGraphEdge {
+                        inputs: [PlayerControl::default()].into(),
+                        distance: node.distance(&below),
+                    },
+                );
+            }
+        }
+
+        // Fall diagonally down right
+        let below_right = node.below().right();
+        if graph.contains_node(below_right) {
+            if semi_solids.contains(&below_right) {
+                graph.add_edge(
+                    node,
+                    below_right,
+                    NavGraphEdge {
+                        inputs: [
+                            PlayerControl {
+                                move_direction: vec2(1.0, -1.0),
+                                jump_just_pressed: true,
+                                jump_pressed: true,
+                                ..default()
+                            },
+                            PlayerControl {
+                                move_direction: vec2(1.0, -1.0),
+                                jump_pressed: true,
+                                ..default()
+                            },
+                            PlayerControl {
+                                move_direction: vec2(1.0, 0.0),
+                                ..default()
+                            },
+                            PlayerControl {
+                                move_direction: vec2(1.0, 0.0),
+                                ..default()
+                            },
+                        ]
+                        .into(),
+                        distance: node.distance(&below_right),
+                    },
+                );
+            } else {
+                graph.add_edge(
+                    node,
+                    below_right,
+                    NavGraphEdge {
+                        inputs: [PlayerControl {
+                            move_direction: vec2(1.0, 0.0),
+                            ..default()
+                        }]
+                        .into(),
+                        distance: node.distance(&below_right),
+                    },
+                );
+            }
+        }
+        // Fall diagonally down left
+        let below_left = node.below().left();
+        if graph.contains_node(below_left) {
+            if semi_solids.contains(&below_left) {
+                graph.add_edge(
+                    node,
+                    below_left,
+                    NavGraphEdge {
+                        inputs: [
+                            PlayerControl {
+                                move_direction: vec2(-1.0, -1.0),
+                                jump_just_pressed: true,
+                                jump_pressed: true,
+                                ..default()
+                            },
+                            PlayerControl {
+                                move_direction: vec2(-1.0, -1.0),
+                                jump_pressed: true,
+                                ..default()
+                            },
+                            PlayerControl {
+                                move_direction: vec2(-1.0, 0.0),
+                                ..default()
+                            },
+                            PlayerControl {
+                                move_direction: vec2(-1.0, 0.0),
+                                ..default()
+                            },
+                        ]
+                        .into(),
+                        distance: node.distance(&below_left),
+                    },
+                );
+            } else {
+                graph.add_edge(
+                    node,
+                    below_left,
+                    NavGraphEdge {
+                        inputs: [PlayerControl {
+                            move_direction: vec2(-1.0, 0.0),
+                            ..default()
+                        }]
+                        .into(),
+                        distance: node.distance(&below_left),
+                    },
+                );
+            }
+        }
+
+        // Slow fall right
+        let far_right_below = node.right().right().right().right().below();
+        let path = [
+            node.right(),
+            node.right().right(),
+            node.right().right().right(),
+            node.right().right().right().right(),
+            far_right_below,
+        ];
+        if path.iter().all(|x| graph.contains_node(*x)) {
+            graph.add_edge(
+                node,
+                far_right_below,
+                NavGraphEdge {
+                    inputs: std::iter::repeat(PlayerControl {
+                        move_direction: vec2(1.0, 0.0),
+                        jump_pressed: true,
+                        ..default()
+                    })
+                    .take(20)
+                    .collect(),
+                    // Bias against using this move because it doesn't always work, by adding an
+                    // extra distance.
+                    distance: node.distance(&far_right_below) + 1.0,
+                },
+            );
+        }
+        // Slow fall left
+        let far_left_below = node.left().left().left().left().below();
+        let path = [
+            node.left(),
+            node.left().left(),
+            node.left().left().left(),
+            node.left().left().left().left(),
+            far_left_below,
+        ];
+        if path.iter().all(|x| graph.contains_node(*x)) {
+            graph.add_edge(
+                node,
+                far_left_below,
+                NavGraphEdge {
+                    inputs: std::iter::repeat(PlayerControl {
+                        move_direction: vec2(-1.0, 0.0),
+                        jump_pressed: true,
+                        ..default()
+                    })
+                    .take(20)
+                    .collect(),
+                    // Bias against using this move because it doesn't always work, by adding an
+                    // extra distance.
+                    distance: node.distance(&far_left_below) + 1.0,
+                },
+            );
+        }
+    }
+
+    Arc::new(graph)
+}
+
+/// Find the shortest path from the `start` node to the `goal` node of the navigation graph.
+///
+/// The path includes both the start and the goal nodes. Returns [`None`] if the goal can't be
+/// reached from the start.
+pub fn find_path(graph: &NavGraphInner, start: NavNode, goal: NavNode) -> Option<Vec<NavNode>> {
+    petgraph::algo::astar(
+        graph,
+        start,
+        |x| x == goal,
+        |(_, _, edge)| edge.distance,
+        |_| 0.0,
+    )
+    .map(|(_cost, path)| path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Create a map with a floor along the bottom and a wall too high to jump over in the middle.
+    fn walled_map(wall_height: u32) -> MapMeta {
+        let grid_size = uvec2(9, 8);
+        let mut tiles = (0..grid_size.x)
+            .map(|x| MapTileMeta {
+                pos: uvec2(x, 0),
+                idx: 0,
+                collision: TileCollisionKind::Solid,
+            })
+            .collect::<Vec<_>>();
+        tiles.extend((1..=wall_height).map(|y| MapTileMeta {
+            pos: uvec2(4, y),
+            idx: 0,
+            collision: TileCollisionKind::Solid,
+        }));
+
+        MapMeta {
+            grid_size,
+            tile_size: Vec2::splat(16.0),
+            layers: vec![MapLayerMeta {
+                id: "tiles".into(),
+                tilemap: None,
+                tiles,
+                elements: default(),
+                foreground: None,
+            }],
+            ..default()
+        }
+    }
+
+    #[test]
+    fn paths_go_over_low_walls() {
+        let graph = create_nav_graph(&walled_map(1), &[true]);
+        let path = find_path(&graph, NavNode(ivec2(1, 1)), NavNode(ivec2(7, 1))).unwrap();
+        assert_eq!(path.first(), Some(&NavNode(ivec2(1, 1))));
+        assert_eq!(path.last(), Some(&NavNode(ivec2(7, 1))));
+        assert!(path.iter().all(|node| graph.contains_node(*node)));
+    }
+
+    #[test]
+    fn high_walls_are_unreachable() {
+        let graph = create_nav_graph(&walled_map(7), &[true]);
+        assert_eq!(
+            find_path(&graph, NavNode(ivec2(1, 1)), NavNode(ivec2(7, 1))),
+            None
+        );
+    }
+
+    #[test]
+    fn hidden_layers_are_ignored() {
+        let graph = create_nav_graph(&walled_map(7), &[false]);
+        assert!(graph.contains_node(NavNode(ivec2(4, 3))));
+    }
+}
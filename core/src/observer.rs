@@ -0,0 +1,204 @@
+//! API for embedding the simulation.
+//!
+//! [`ObservedSession`] wraps a [`CoreSession`] with a small API that is meant to stay stable as
+//! the game changes, for bots, analysis tools, or alternative frontends: feed it player controls,
+//! step it, and read a [`MatchSnapshot`] of the match or the [`MatchEvent`]s of the last frame.
+//!
+//! See the `headless` example for a match played without a frontend.
+
+use crate::prelude::*;
+
+pub fn install(session: &mut CoreSession) {
+    session.world.init_resource::<EventChannel<MatchEvent>>();
+    session.world.init_resource::<ObservedPlayers>();
+    session
+        .stages
+        .add_system_to_stage(CoreStage::Last, clear_events::<MatchEvent>)
+        .add_system_to_stage(CoreStage::Last, send_match_events);
+}
+
+/// Something that happened during a match.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, TypeUlid, Serialize, Deserialize)]
+#[ulid = "01H2G62F77VM87GJEGVPVZKRVH"]
+pub enum MatchEvent {
+    /// A player spawned into the map.
+    PlayerSpawned { player: usize },
+    /// A player was killed.
+    PlayerKilled { player: usize },
+    /// A dead player was removed from the map.
+    PlayerDespawned { player: usize },
+}
+
+/// The state of a player in a [`MatchSnapshot`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlayerSnapshot {
+    /// The position of the player.
+    pub position: Vec2,
+    /// The velocity of the player.
+    pub velocity: Vec2,
+    /// Whether or not the player is alive.
+    pub alive: bool,
+    /// Whether or not the player is holding an item.
+    pub holding_item: bool,
+}
+
+/// The state of a match at the end of a frame.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MatchSnapshot {
+    /// The number of frames simulated so far.
+    pub frame: u64,
+    /// The players that are currently in the map.
+    pub players: [Option<PlayerSnapshot>; MAX_PLAYERS],
+}
+
+/// A [`CoreSession`] with an API for embedding it.
+pub struct ObservedSession {
+    session: CoreSession,
+    frame: u64,
+    subscribers: Vec<Box<dyn FnMut(&MatchEvent)>>,
+}
+
+impl ObservedSession {
+    /// Create a session for a new match.
+    pub fn new(info: CoreSessionInfo) -> Self {
+        Self {
+            session: CoreSession::new(info),
+            frame: 0,
+            subscribers: default(),
+        }
+    }
+
+    /// Set the control of a player for the next frames.
+    ///
+    /// The `*_just_pressed` and `just_moved` fields must be set by the caller, on the frame the
+    /// button is pressed. The controls of AI players are ignored, because they are decided by the
+    /// simulation.
+    pub fn set_control(&mut self, player: usize, control: PlayerControl) {
+        self.session.update_input(|inputs| {
+            let input = &mut inputs.players[player];
+            if !input.is_ai {
+                input.control = control;
+            }
+        });
+    }
+
+    /// Simulate a frame, and notify the subscribers of the events that happened during it.
+    ///
+    /// `bevy_world` must contain the loaded assets referred to by the [`CoreMeta`].
+    pub fn step(&mut self, bevy_world: &mut ::bevy::prelude::World) {
+        self.session.advance(bevy_world);
+        self.frame += 1;
+
+        let events = self.events();
+        for subscriber in &mut self.subscribers {
+            for event in &events {
+                subscriber(event);
+            }
+        }
+    }
+
+    /// Call `subscriber` with every [`MatchEvent`] from now on.
+    pub fn subscribe(&mut self, subscriber: impl FnMut(&MatchEvent) + 'static) {
+        self.subscribers.push(Box::new(subscriber));
+    }
+
+    /// Get the events that happened during the last frame.
+    pub fn events(&self) -> Vec<MatchEvent> {
+        let channel = self.session.world.resource::<EventChannel<MatchEvent>>();
+        let channel = channel.borrow();
+        channel.iter().copied().collect()
+    }
+
+    /// Get the number of frames simulated so far.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Get a snapshot of the match.
+    pub fn snapshot(&self) -> MatchSnapshot {
+        let snapshot_system = |entities: Res<Entities>,
+                               player_indexes: Comp<PlayerIdx>,
+                               transforms: Comp<Transform>,
+                               bodies: Comp<KinematicBody>,
+                               players_killed: Comp<PlayerKilled>,
+                               inventories: Comp<Inventory>| {
+            let mut players: [Option<PlayerSnapshot>; MAX_PLAYERS] = default();
+            for (ent, (player_idx, transform)) in entities.iter_with((&player_indexes, &transforms))
+            {
+                players[player_idx.0] = Some(PlayerSnapshot {
+                    position: transform.translation.truncate(),
+                    velocity: bodies.get(ent).map(|x| x.velocity).unwrap_or_default(),
+                    alive: !players_killed.contains(ent),
+                    holding_item: inventories.get(ent).map_or(false, |x| x.is_some()),
+                });
+            }
+
+            Ok(players)
+        };
+
+        MatchSnapshot {
+            frame: self.frame,
+            players: self
+                .session
+                .world
+                .run_initialized_system(snapshot_system)
+                .unwrap(),
+        }
+    }
+
+    /// Get the wrapped session.
+    ///
+    /// Unlike the rest of this API, the session may change with the game.
+    pub fn session(&self) -> &CoreSession {
+        &self.session
+    }
+
+    /// Get the wrapped session mutably.
+    ///
+    /// Unlike the rest of this API, the session may change with the game.
+    pub fn session_mut(&mut self) -> &mut CoreSession {
+        &mut self.session
+    }
+}
+
+/// Resource tracking the players that the [`MatchEvent`]s have been sent for.
+#[derive(Clone, TypeUlid, Default)]
+#[ulid = "01H2GAY3ZQ4HX1M7RW0DBJNE5T"]
+struct ObservedPlayers {
+    /// For each player, its entity and whether it has been killed.
+    players: [Option<(Entity, bool)>; MAX_PLAYERS],
+}
+
+/// Send the [`MatchEvent`]s for the changes to the players.
+fn send_match_events(
+    entities: Res<Entities>,
+    player_indexes: Comp<PlayerIdx>,
+    player_states: Comp<PlayerState>,
+    players_killed: Comp<PlayerKilled>,
+    mut observed: ResMut<ObservedPlayers>,
+    mut match_events: ResMut<EventChannel<MatchEvent>>,
+) {
+    let mut present = [false; MAX_PLAYERS];
+    for (ent, (player_idx, _)) in entities.iter_with((&player_indexes, &player_states)) {
+        let player = player_idx.0;
+        present[player] = true;
+
+        let observed = &mut observed.players[player];
+        if observed.map(|(observed_ent, _)| observed_ent) != Some(ent) {
+            *observed = Some((ent, false));
+            match_events.send(MatchEvent::PlayerSpawned { player });
+        }
+        if let Some((_, killed)) = observed {
+            if !*killed && players_killed.contains(ent) {
+                *killed = true;
+                match_events.send(MatchEvent::PlayerKilled { player });
+            }
+        }
+    }
+
+    for (player, observed) in observed.players.iter_mut().enumerate() {
+        if !present[player] && observed.take().is_some() {
+            match_events.send(MatchEvent::PlayerDespawned { player });
+        }
+    }
+}
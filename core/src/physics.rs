@@ -3,7 +3,7 @@
 use crate::prelude::*;
 
 pub use collisions::{
-    Actor, Collider, ColliderShape, CollisionWorld, RapierContext, RapierUserData,
+    Actor, Collider, ColliderShape, CollisionWorld, RapierContext, RapierUserData, Solid,
     TileCollisionKind,
 };
 
@@ -53,6 +53,9 @@ pub fn install(session: &mut CoreSession) {
 #[repr(C)]
 pub struct KinematicBody {
     pub velocity: Vec2,
+    /// The shape of the body's collider.
+    ///
+    /// It may be changed at any time to resize the body, and the collider will be updated to match.
     pub shape: ColliderShape,
     /// Angular velocity in degrees per second
     pub angular_velocity: f32,
@@ -263,13 +266,15 @@ fn update_kinematic_bodies(
                 puffin::profile_scope!("fall through check");
                 // Don't get stuck floating in fall-through platforms
                 if body.velocity == Vec2::ZERO
-                    && collision_world.tile_collision_filtered(transform, body.shape, |ent| {
-                        collision_world
-                            .tile_collision_kinds
-                            .get(ent)
-                            .map(|x| *x == TileCollisionKind::JumpThrough)
-                            .unwrap_or(false)
-                    }) == TileCollisionKind::JumpThrough
+                    && collision_world
+                        .tile_collision_filtered(transform, body.shape, |ent| {
+                            collision_world
+                                .tile_collision_kinds
+                                .get(ent)
+                                .map(|x| x.is_jump_through())
+                                .unwrap_or(false)
+                        })
+                        .is_jump_through()
                 {
                     body.fall_through = true;
                 }
@@ -287,17 +292,20 @@ fn update_kinematic_bodies(
                     collision_world
                         .tile_collision_kinds
                         .get(ent)
-                        .map(|x| *x != TileCollisionKind::JumpThrough)
+                        .map(|x| !x.is_jump_through())
                         .unwrap_or(false)
                 } else {
                     true
                 }
             });
 
-            let on_jump_through_tile = tile == TileCollisionKind::JumpThrough;
-            body.is_on_ground =
+            let on_jump_through_tile = tile.is_jump_through();
+            let on_tile =
                 tile != TileCollisionKind::Empty && !(on_jump_through_tile && body.fall_through);
-            body.is_on_platform = body.is_on_ground && on_jump_through_tile;
+            // Bodies can also rest on top of solids, such as pushable blocks
+            let on_solid = collision_world.solid_collision(entity, transform, body.shape);
+            body.is_on_ground = on_tile || on_solid;
+            body.is_on_platform = on_tile && on_jump_through_tile;
         }
 
         if body.is_on_ground {
@@ -322,7 +330,7 @@ fn update_kinematic_bodies(
         }
 
         if !body.is_on_ground && body.has_mass {
-            body.velocity.y -= body.gravity * time_factor;
+            body.velocity.y -= body.gravity * game.physics.gravity_scale * time_factor;
 
             if body.velocity.y < -game.physics.terminal_velocity {
                 body.velocity.y = -game.physics.terminal_velocity;
@@ -163,8 +163,10 @@ impl_system_param! {
         /// Solids are things like walls and platforms, that aren't tiles, that have solid
         /// collisions.
         ///
-        /// > **⚠️ Warning:** Solids have not been fully implemented yet and may not work. They were
-        /// > from the old physics system and haven't been fully ported.
+        /// Actors moved by [`move_vertical()`][Self::move_vertical] and
+        /// [`move_horizontal()`][Self::move_horizontal] are stopped by solids, except for the ones
+        /// that they already overlap, which they may move out of. An actor may be a solid itself,
+        /// such as a pushable block, in which case it can rest on top of other solids.
         solids: CompMut<'a, Solid>,
         /// A collider is anything that can detect collisions in the world other than tiles, and
         /// must either be an [`Actor`] or `Solid`] to participate in collision detection.
@@ -215,6 +217,9 @@ pub struct Collider {
     /// The handle to the Rapier rigid body associated to this collider, if one has been spawned as
     /// of yet.
     pub rapier_handle: Option<rapier::RigidBodyHandle>,
+    /// The shape that the Rapier collider was last given, used to resize the Rapier collider when
+    /// the shape of the collider changes.
+    pub rapier_shape: Option<ColliderShape>,
 }
 
 /// Component added to tiles that have been given corresponding rapier colliders.
@@ -253,6 +258,15 @@ pub enum TileCollisionKind {
     Empty,
     Solid,
     JumpThrough,
+    /// A jump-through tile that vehicles, such as minecarts, can ride along.
+    Rail,
+}
+
+impl TileCollisionKind {
+    /// Whether or not bodies can jump up through the tile, and drop down through it.
+    pub fn is_jump_through(&self) -> bool {
+        matches!(self, Self::JumpThrough | Self::Rail)
+    }
 }
 
 impl BonesBevyAssetLoad for TileCollisionKind {}
@@ -391,6 +405,12 @@ impl<'a> CollisionWorld<'a> {
             );
             let rapier_collider = collider_set.get_mut(rapier_body.colliders()[0]).unwrap();
             rapier_collider.set_enabled(!collider.disabled);
+
+            // Resize the collider if its shape has changed, such as when a player grows or shrinks.
+            if collider.rapier_shape != Some(collider.shape) {
+                rapier_collider.set_shape(shared_shape.clone());
+                collider.rapier_shape = Some(collider.shape);
+            }
             rapier_collider.set_position_wrt_parent(rapier::Isometry::new(default(), 0.0));
         }
     }
@@ -481,13 +501,11 @@ impl<'a> CollisionWorld<'a> {
     // TODO: I believe we can make this method unnecessary by correctly detecting when a body is
     // stuck in a wood platform, with no ground below it.
     pub fn handle_teleport(&mut self, entity: Entity) {
-        if self
-            .ctx
-            .collision_cache
-            .get(entity)
-            .iter()
-            .any(|x| self.tile_collision_kinds.get(*x) == Some(&TileCollisionKind::JumpThrough))
-        {
+        if self.ctx.collision_cache.get(entity).iter().any(|x| {
+            self.tile_collision_kinds
+                .get(*x)
+                .map_or(false, |x| x.is_jump_through())
+        }) {
             let collider = self.colliders.get_mut(entity).unwrap();
             collider.descent = true;
             collider.seen_wood = true;
@@ -534,6 +552,48 @@ impl<'a> CollisionWorld<'a> {
             .collect()
     }
 
+    /// Get the solids that the entity is overlapping, other than itself.
+    ///
+    /// Actors aren't stopped by the solids that they overlap, such as when a pushable block lands on
+    /// top of a player, so that they may move out of them.
+    pub fn overlapping_solids(&self, entity: Entity) -> Vec<Entity> {
+        self.ctx
+            .collision_cache
+            .get(entity)
+            .iter()
+            .filter(|x| **x != entity && self.solids.contains(**x))
+            .copied()
+            .collect()
+    }
+
+    /// Whether the `shape` at the given `transform` collides with any solid, other than the
+    /// `entity` itself and the solids that it overlaps.
+    pub fn solid_collision(
+        &self,
+        entity: Entity,
+        transform: Transform,
+        shape: ColliderShape,
+    ) -> bool {
+        let overlapping_solids = self.overlapping_solids(entity);
+        self.ctx
+            .query_pipeline
+            .intersection_with_shape(
+                &self.ctx.rigid_body_set,
+                &self.ctx.collider_set,
+                &(
+                    transform.translation.truncate(),
+                    transform.rotation.to_euler(EulerRot::XYZ).2,
+                )
+                    .into(),
+                &*shape.shared_shape(),
+                rapier::QueryFilter::new().predicate(&|_handle, collider| {
+                    let ent = RapierUserData::entity(collider.user_data);
+                    ent != entity && self.solids.contains(ent) && !overlapping_solids.contains(&ent)
+                }),
+            )
+            .is_some()
+    }
+
     /// Put the entity's collider into descent mode so that it will fall through jump-through
     /// platforms.
     pub fn descent(&mut self, entity: Entity) {
@@ -553,6 +613,11 @@ impl<'a> CollisionWorld<'a> {
     ) -> bool {
         puffin::profile_function!();
 
+        assert!(self.actors.contains(entity));
+        if dy == 0.0 {
+            return false;
+        }
+        let overlapping_solids = self.overlapping_solids(entity);
         let RapierContext {
             query_pipeline,
             collider_set,
@@ -560,10 +625,6 @@ impl<'a> CollisionWorld<'a> {
             collider_shape_cache,
             ..
         } = &mut *self.ctx;
-        assert!(self.actors.contains(entity));
-        if dy == 0.0 {
-            return false;
-        }
 
         // Get the shape and position info for the given entity
         let collider = self.colliders.get_mut(entity).unwrap();
@@ -589,13 +650,16 @@ impl<'a> CollisionWorld<'a> {
                 rapier::QueryFilter::new().predicate(&|_handle, rapier_collider| {
                     let ent = RapierUserData::entity(rapier_collider.user_data);
 
+                    if self.solids.contains(ent) {
+                        return ent != entity && !overlapping_solids.contains(&ent);
+                    }
                     let Some(tile_kind) = self.tile_collision_kinds.get(ent) else {
-                        // Ignore non-tile collisions
+                        // Ignore other non-tile collisions
                         return false;
                     };
 
                     // Ignore jump-through tiles if we have already seen wood
-                    !(collider.seen_wood && *tile_kind == TileCollisionKind::JumpThrough)
+                    !(collider.seen_wood && tile_kind.is_jump_through())
                 }),
             );
 
@@ -610,20 +674,25 @@ impl<'a> CollisionWorld<'a> {
                 // Subtract from the remaining attempted movement
                 dy -= diff;
 
-                let tile_kind = *self.tile_collision_kinds.get(ent).unwrap();
+                // Solids block the movement like solid tiles
+                let tile_kind = self
+                    .tile_collision_kinds
+                    .get(ent)
+                    .copied()
+                    .unwrap_or(TileCollisionKind::Solid);
 
                 // collider wants to go down and collided with jumpthrough tile
-                if tile_kind == TileCollisionKind::JumpThrough && collider.descent {
+                if tile_kind.is_jump_through() && collider.descent {
                     collider.seen_wood = true;
                 }
                 // collider wants to go up and encoutered jumpthrough obstace
-                if tile_kind == TileCollisionKind::JumpThrough && dy > 0.0 {
+                if tile_kind.is_jump_through() && dy > 0.0 {
                     collider.seen_wood = true;
                     collider.descent = true;
                 }
 
                 // If we hit a solid block, or a jumpthrough tile that we aren't falling through
-                if !(tile_kind == TileCollisionKind::JumpThrough
+                if !(tile_kind.is_jump_through()
                     && (collider.descent || dy > 0.0 || collider.seen_wood))
                 {
                     // Indicate we ran into something and stop processing
@@ -657,7 +726,9 @@ impl<'a> CollisionWorld<'a> {
                     &**shape,
                     rapier::QueryFilter::new().predicate(&|_handle, collider| {
                         let ent = RapierUserData::entity(collider.user_data);
-                        self.tile_collision_kinds.get(ent) == Some(&TileCollisionKind::JumpThrough)
+                        self.tile_collision_kinds
+                            .get(ent)
+                            .map_or(false, |x| x.is_jump_through())
                     }),
                 )
                 .is_some();
@@ -681,6 +752,11 @@ impl<'a> CollisionWorld<'a> {
     ) -> bool {
         puffin::profile_function!();
 
+        assert!(self.actors.contains(entity));
+        if dx == 0.0 {
+            return false;
+        }
+        let overlapping_solids = self.overlapping_solids(entity);
         let RapierContext {
             query_pipeline,
             collider_set,
@@ -688,10 +764,6 @@ impl<'a> CollisionWorld<'a> {
             collider_shape_cache,
             ..
         } = &mut *self.ctx;
-        assert!(self.actors.contains(entity));
-        if dx == 0.0 {
-            return false;
-        }
 
         // Get the shape and position info for the given entity
         let collider = self.colliders.get_mut(entity).unwrap();
@@ -720,13 +792,16 @@ impl<'a> CollisionWorld<'a> {
                     rapier::QueryFilter::new().predicate(&|_handle, rapier_collider| {
                         let ent = RapierUserData::entity(rapier_collider.user_data);
 
+                        if self.solids.contains(ent) {
+                            return ent != entity && !overlapping_solids.contains(&ent);
+                        }
                         let Some(tile_kind) = self.tile_collision_kinds.get(ent) else {
-                        // Ignore non-tile collisions
+                        // Ignore other non-tile collisions
                         return false;
                     };
 
                         // Ignore jump-through tiles if we have already seen wood.
-                        !(collider.seen_wood && *tile_kind == TileCollisionKind::JumpThrough)
+                        !(collider.seen_wood && tile_kind.is_jump_through())
                     }),
                 )
             };
@@ -742,10 +817,15 @@ impl<'a> CollisionWorld<'a> {
                 // Subtract from the remaining attempted movement
                 dx -= diff;
 
-                let tile_kind = *self.tile_collision_kinds.get(ent).unwrap();
+                // Solids block the movement like solid tiles
+                let tile_kind = self
+                    .tile_collision_kinds
+                    .get(ent)
+                    .copied()
+                    .unwrap_or(TileCollisionKind::Solid);
 
                 // If we ran into a jump-through tile, go through it and continue casting
-                if tile_kind == TileCollisionKind::JumpThrough {
+                if tile_kind.is_jump_through() {
                     collider.seen_wood = true;
                     collider.descent = true;
 
@@ -782,7 +862,9 @@ impl<'a> CollisionWorld<'a> {
                     &**shape,
                     rapier::QueryFilter::new().predicate(&|_handle, collider| {
                         let ent = RapierUserData::entity(collider.user_data);
-                        self.tile_collision_kinds.get(ent) == Some(&TileCollisionKind::JumpThrough)
+                        self.tile_collision_kinds
+                            .get(ent)
+                            .map_or(false, |x| x.is_jump_through())
                     }),
                 )
                 .is_some();
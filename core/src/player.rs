@@ -1,38 +1,33 @@
 //! Player controller, states, and animation implementation.
 
-use std::collections::VecDeque;
-
 use crate::{
     item::ItemGrabbed,
     physics::KinematicBody,
-    prelude::{player_spawner::PlayerSpawner, *},
-    random::GlobalRng,
+    prelude::{
+        player_spawner::PlayerSpawner, size_power_up::Resized, stomp_boots::WearingStompBoots, *,
+    },
 };
 
 mod state;
+mod status;
 use bones_lib::animation::AnimationBankSprite;
 pub use state::*;
-use turborand::GenCore;
-
-const PLAYER_COLORS: [Color; 4] = [
-    Color::RED,
-    Color::GREEN,
-    Color::BLUE,
-    Color::rgb(1.0, 0.0, 1.0),
-];
+pub use status::*;
 
 pub fn install(session: &mut CoreSession) {
     state::install(session);
+    status::install(session);
 
     // Add other player systems
     session
         .stages
         .add_system_to_stage(CoreStage::First, hydrate_players)
-        .add_system_to_stage(CoreStage::First, player_ai_system)
+        .add_system_to_stage(CoreStage::First, take_over_ai_players)
+        .add_system_to_stage(CoreStage::First, kill_inactive_players)
+        .add_system_to_stage(CoreStage::PostUpdate, stomp_players)
         .add_system_to_stage(CoreStage::PostUpdate, play_itemless_fin_animations)
         .add_system_to_stage(CoreStage::PostUpdate, player_facial_animations)
         .add_system_to_stage(CoreStage::PostUpdate, equip_hats)
-        .add_system_to_stage(CoreStage::Last, delete_dead_ai_swords)
         .add_system_to_stage(CoreStage::Last, update_player_layers);
 }
 
@@ -139,6 +134,8 @@ impl Emote {
 #[ulid = "01GP49AK25A8S9G2GYNAVE4PTN"]
 pub struct PlayerKilled {
     pub hit_from: Option<Vec2>,
+    /// What killed the player.
+    pub source: DamageSource,
 }
 
 /// Events that can be used to trigger player actions, such as killing, setting inventory, etc.
@@ -150,11 +147,19 @@ impl PlayerCommand {
     ///
     /// > **Note:** This doesn't despawn the player, it just puts the player into it's death animation.
     pub fn kill(player: Entity, hit_from: Option<Vec2>) -> System {
+        Self::kill_by(player, hit_from, DamageSource::Environment)
+    }
+    /// Kill a player, crediting the kill to the given source.
+    ///
+    /// A [`KillEvent`] is sent for the kill.
+    pub fn kill_by(player: Entity, hit_from: Option<Vec2>, source: DamageSource) -> System {
         (move |entities: Res<Entities>,
                mut players_killed: CompMut<PlayerKilled>,
                mut items_dropped: CompMut<ItemDropped>,
                mut inventories: CompMut<Inventory>,
-               player_indexes: Comp<PlayerIdx>| {
+               player_indexes: Comp<PlayerIdx>,
+               mut kill_events: ResMut<EventChannel<KillEvent>>,
+               mut hit_events: ResMut<EventChannel<HitEvent>>| {
             if players_killed.contains(player) {
                 // No need to kill him again
                 return;
@@ -179,7 +184,19 @@ impl PlayerCommand {
             // Update the inventory
             inventories.insert(player, Inventory(None));
 
-            players_killed.insert(player, PlayerKilled { hit_from });
+            players_killed.insert(player, PlayerKilled { hit_from, source });
+            kill_events.send(KillEvent {
+                victim: idx.0,
+                killer: source
+                    .player()
+                    .and_then(|killer| player_indexes.get(killer))
+                    .map(|x| x.0),
+            });
+            hit_events.send(HitEvent {
+                player,
+                damage: LETHAL_HIT_DAMAGE,
+                knockback: Vec2::ZERO,
+            });
         })
         .system()
     }
@@ -226,7 +243,7 @@ impl PlayerCommand {
     }
     /// Set the player's inventory
     pub fn set_inventory(player: Entity, item: Option<Entity>) -> System {
-        (move |mut items_grabbed: CompMut<ItemGrabbed>,
+        (move |mut item_grabbed_events: ResMut<EventChannel<ItemGrabbed>>,
                mut items_dropped: CompMut<ItemDropped>,
                mut inventories: CompMut<Inventory>| {
             let inventory = inventories.get(player).cloned().unwrap_or_default();
@@ -238,7 +255,7 @@ impl PlayerCommand {
 
             // If there is a new item, grab it
             if let Some(item) = item {
-                items_grabbed.insert(item, ItemGrabbed { player });
+                item_grabbed_events.send(ItemGrabbed { item, player });
             }
 
             // Update the inventory
@@ -248,232 +265,30 @@ impl PlayerCommand {
     }
     /// Have the player use the item they are carrying, if any.
     pub fn use_item(player: Entity) -> System {
-        (move |mut items_used: CompMut<ItemUsed>, inventories: CompMut<Inventory>| {
+        (move |mut items_used: CompMut<ItemUsed>,
+               inventories: CompMut<Inventory>,
+               player_indexes: Comp<PlayerIdx>,
+               element_handles: Comp<ElementHandle>,
+               element_assets: BevyAssets<ElementMeta>,
+               mut match_stats: ResMut<MatchStats>| {
             // If the player has an item
             if let Some(item) = inventories.get(player).and_then(|x| x.0) {
                 // Use it
                 items_used.insert(item, ItemUsed { owner: player });
+                if let Some(idx) = player_indexes.get(player) {
+                    let item_name = element_handles
+                        .get(item)
+                        .and_then(|x| element_assets.get(&x.get_bevy_handle()))
+                        .map(|x| x.name.as_str())
+                        .unwrap_or_default();
+                    match_stats.record_item_use(idx.0, item_name);
+                }
             }
         })
         .system()
     }
 }
 
-#[derive(Clone, Debug, TypeUlid)]
-#[ulid = "01GQWND0P969BCZF5JET9MY944"]
-pub struct AiPlayer {
-    /// Tick timer that is used for AI pausing logic.
-    tick: Timer,
-    /// Indicates the player is taking pause for the given number of ticks.
-    pausing: u32,
-    /// Buffers planned AI movements
-    movement_buffer: Option<VecDeque<PlayerControl>>,
-    /// The player that the AI is targeting.
-    target_player: Option<Entity>,
-}
-
-impl Default for AiPlayer {
-    fn default() -> Self {
-        Self {
-            tick: Timer::from_seconds(0.5, TimerMode::Repeating),
-            pausing: 0,
-            movement_buffer: Default::default(),
-            target_player: Default::default(),
-        }
-    }
-}
-
-#[derive(Debug, TypeUlid, Clone)]
-#[ulid = "01GRA68NKYG6X7C5D0WNA5W1VX"]
-pub struct PathfindingDebugLines {
-    pub entities: Vec<Entity>,
-}
-
-impl FromWorld for PathfindingDebugLines {
-    fn from_world(world: &mut World) -> Self {
-        let entities = world
-            .run_initialized_system(
-                |mut entities: ResMut<Entities>, mut transforms: CompMut<Transform>| {
-                    let ents = (0..MAX_PLAYERS)
-                        .map(|_| {
-                            let ent = entities.create();
-
-                            transforms.insert(
-                                ent,
-                                Transform::from_translation(Vec3::new(0.0, 0.0, -1.0)),
-                            );
-
-                            ent
-                        })
-                        .collect::<Vec<_>>();
-
-                    Ok(ents)
-                },
-            )
-            .unwrap();
-
-        Self { entities }
-    }
-}
-
-fn player_ai_system(
-    entities: Res<Entities>,
-    nav_graph: ResMut<NavGraph>,
-    mut player_inputs: ResMut<PlayerInputs>,
-    mut ai_players: CompMut<AiPlayer>,
-    player_indexes: Comp<PlayerIdx>,
-    map: Res<LoadedMap>,
-    transforms: Comp<Transform>,
-    pathfinding_debug_line: ResMut<PathfindingDebugLines>,
-    mut paths: CompMut<Path2d>,
-    bodies: Comp<KinematicBody>,
-    debug_settings: Res<DebugSettings>,
-    rng: Res<GlobalRng>,
-    time: Res<Time>,
-) {
-    const SWORD_SWING_DIST: f32 = 10.0;
-    const AI_SPEED_MULTIPLIER: f32 = 0.65;
-
-    for (ai_ent, (player_idx, transform, ai_player)) in
-        entities.iter_with((&player_indexes, &transforms, &mut ai_players))
-    {
-        // Tick the AI timer
-        ai_player.tick.tick(time.delta());
-
-        // If a tick has elapsed
-        if ai_player.tick.just_finished() {
-            // If the player isn't pausing, then there's a 40% chance
-            if ai_player.pausing == 0 && rng.chance(0.4) {
-                // That we will pause for a random number of ticks between 0 and 2
-                ai_player.pausing = (rng.f32_normalized() * 2.0).round() as u32
-            }
-
-            // If the player is pausing
-            if ai_player.pausing > 0 {
-                // Subtract a tick from how long they should pause.
-                ai_player.pausing -= 1;
-            }
-        }
-
-        // If the player is pausing, don't have the AI move this frame.
-        if ai_player.pausing > 0 {
-            continue;
-        }
-
-        let target_transform = match ai_player.target_player {
-            Some(target_player) if transforms.contains(target_player) => {
-                transforms.get(target_player).unwrap()
-            }
-            _ => {
-                let players = entities
-                    .iter_with((&player_indexes, &transforms))
-                    .filter(|(ent, _)| *ent != ai_ent)
-                    .collect::<Vec<_>>();
-                if players.is_empty() {
-                    continue;
-                }
-
-                let (target_player, (_, transform)) = players[rng.gen_usize() % players.len()];
-
-                ai_player.target_player = Some(target_player);
-                transform
-            }
-        };
-        let target_pos = target_transform.translation.truncate();
-        let tile = (target_pos / map.tile_size).floor().as_ivec2();
-        let target_node = NavNode(tile);
-        let ai_pos = transform.translation.truncate();
-        let tile = (ai_pos / map.tile_size).floor().as_ivec2();
-        let current_node = NavNode(tile);
-
-        // Complete any previous movement instructions if we are in the middle of any
-        if let Some(movement_buffer) = &mut ai_player.movement_buffer {
-            if let Some(control) = movement_buffer.pop_front() {
-                player_inputs.players[player_idx.0].control = control;
-                if movement_buffer.is_empty() {
-                    ai_player.movement_buffer = None;
-                }
-                continue;
-            }
-        }
-
-        let path = petgraph::algo::astar(
-            nav_graph.as_ref(),
-            current_node,
-            |x| x == target_node,
-            |(_, _, edge)| edge.distance,
-            |_| 0.0,
-        );
-
-        if let Some((_cost, path)) = path {
-            if debug_settings.show_pathfinding_lines {
-                paths.insert(
-                    pathfinding_debug_line.entities[player_idx.0],
-                    Path2d {
-                        points: path
-                            .iter()
-                            .map(|x| x.0.as_vec2() * map.tile_size + map.tile_size / 2.0)
-                            .collect(),
-                        thickness: 2.0,
-                        color: PLAYER_COLORS[player_idx.0],
-                        ..default()
-                    },
-                );
-            }
-
-            if let Some(&next_node) = path.get(1) {
-                let edge = nav_graph.edge_weight(current_node, next_node).unwrap();
-                let mut movement_buffer = edge.inputs.clone();
-                let mut first_movement = movement_buffer.pop_front().unwrap();
-
-                // Slow down the AI movement according to the fixed multiplier
-                first_movement.move_direction *= vec2(AI_SPEED_MULTIPLIER, 1.0);
-
-                // This is a hack to prevent us from getting stuck when we think we should be falling
-                // straight down and we actually need to move off of the block we're half-standing on.
-                //
-                // If we aren't moving at all, just move in the direction of player 1
-                if bodies.get(ai_ent).unwrap().velocity == Vec2::ZERO
-                    && first_movement.move_direction == Vec2::ZERO
-                {
-                    let sign = (path.get(2).unwrap_or(&next_node).x as f32 * map.tile_size.x
-                        - transform.translation.x)
-                        .signum();
-                    first_movement.move_direction.x = sign;
-                }
-
-                player_inputs.players[player_idx.0].control = first_movement;
-                if !movement_buffer.is_empty() {
-                    ai_player.movement_buffer = Some(movement_buffer)
-                }
-            }
-
-            if (target_pos - ai_pos).length() < SWORD_SWING_DIST {
-                player_inputs.players[player_idx.0]
-                    .control
-                    .shoot_just_pressed = true;
-                player_inputs.players[player_idx.0].control.shoot_pressed = true;
-            }
-        } else if debug_settings.show_pathfinding_lines {
-            let pos =
-                current_node.0.as_vec2() * map.tile_size + map.tile_size / 2.0 - vec2(0.0, 4.0);
-            paths.insert(
-                pathfinding_debug_line.entities[player_idx.0],
-                Path2d {
-                    points: vec![pos, pos + vec2(0.0, 4.0)],
-                    thickness: 8.0,
-                    color: Color::RED,
-                    ..default()
-                },
-            );
-        }
-
-        if !debug_settings.show_pathfinding_lines {
-            paths.remove(pathfinding_debug_line.entities[player_idx.0]);
-        }
-    }
-}
-
 /// Resource that tracks which players have already been spawned before.
 ///
 /// This lets us handle re-spawns differently, like not spawning you with a hat on a re-spawn.
@@ -493,7 +308,6 @@ struct PlayersHaveSpawned {
 struct Hat(Handle<HatMeta>);
 
 fn hydrate_players(
-    mut commands: Commands,
     mut entities: ResMut<Entities>,
     game_meta: Res<CoreMetaArc>,
     player_inputs: Res<PlayerInputs>,
@@ -510,6 +324,7 @@ fn hydrate_players(
     mut transforms: CompMut<Transform>,
     mut emote_states: CompMut<EmoteState>,
     mut ai_players: CompMut<AiPlayer>,
+    mut status_effects: CompMut<StatusEffects>,
     mut invincibles: CompMut<Invincibility>,
     mut element_kill_callbacks: CompMut<ElementKillCallback>,
     mut players_have_spawned: ResMut<PlayersHaveSpawned>,
@@ -552,19 +367,29 @@ fn hydrate_players(
         emote_states.insert(player_entity, default());
         animation_bank_sprites.insert(player_entity, animation_bank_sprite);
         inventories.insert(player_entity, default());
-        invincibles.insert(
-            player_entity,
-            Invincibility::new(game_meta.config.respawn_invincibility_time),
+        // Protect the player while they get their bearings
+        let mut effects = StatusEffects::default();
+        effects.add(
+            StatusEffectKind::Shielded,
+            game_meta.config.respawn_invincibility_time,
         );
+        status_effects.insert(player_entity, effects);
+        invincibles.insert(player_entity, Invincibility);
         element_kill_callbacks.insert(
             player_entity,
             ElementKillCallback::new(player_kill_callback(player_entity)),
         );
 
+        // Keep the facing chosen by the player spawner
+        let flip_x = atlas_sprites
+            .get(player_entity)
+            .map(|sprite| sprite.flip_x)
+            .unwrap_or_default();
         atlas_sprites.insert(
             player_entity,
             AtlasSprite {
                 atlas: meta.layers.body.atlas.clone(),
+                flip_x,
                 ..default()
             },
         );
@@ -713,81 +538,6 @@ fn hydrate_players(
         // Handle AI players
         if is_ai {
             ai_players.insert(player_entity, default());
-
-            // Give the player a sword NOTE: It's not good that we're duplicating the sword hydrate
-            // functionality here, and this is pretty hacky, but the AI as it stands is temporary
-            // anyway, so it's fine for now.
-            commands.add(
-                move |mut entities: ResMut<Entities>,
-                      mut swords: CompMut<sword::Sword>,
-                      mut element_handles: CompMut<ElementHandle>,
-                      element_assets: BevyAssets<ElementMeta>,
-                      mut hydrated: CompMut<MapElementHydrated>,
-                      mut bodies: CompMut<KinematicBody>,
-                      mut atlas_sprites: CompMut<AtlasSprite>,
-                      mut items: CompMut<Item>,
-                      mut transforms: CompMut<Transform>,
-                      game_meta: Res<CoreMetaArc>,
-                      mut attachments: CompMut<PlayerBodyAttachment>,
-                      mut inventories: CompMut<Inventory>| {
-                    let element_handle = game_meta
-                        .map_elements
-                        .iter()
-                        .find(|handle| {
-                            matches!(
-                                element_assets
-                                    .get(&handle.get_bevy_handle())
-                                    .unwrap()
-                                    .builtin,
-                                BuiltinElementKind::Sword { .. }
-                            )
-                        })
-                        .unwrap();
-                    let element_meta = element_assets
-                        .get(&element_handle.get_bevy_handle())
-                        .unwrap();
-                    if let BuiltinElementKind::Sword {
-                        atlas,
-                        body_size,
-                        can_rotate,
-                        bounciness,
-                        grab_offset,
-                        ..
-                    } = &element_meta.builtin
-                    {
-                        let sword_ent = entities.create();
-                        inventories.insert(player_entity, Inventory(Some(sword_ent)));
-                        items.insert(sword_ent, Item);
-                        swords.insert(sword_ent, sword::Sword::default());
-                        atlas_sprites.insert(sword_ent, AtlasSprite::new(atlas.clone()));
-                        transforms.insert(sword_ent, default());
-                        element_handles.insert(sword_ent, ElementHandle(element_handle.clone()));
-                        hydrated.insert(sword_ent, MapElementHydrated);
-                        bodies.insert(
-                            sword_ent,
-                            KinematicBody {
-                                shape: ColliderShape::Rectangle { size: *body_size },
-                                has_mass: true,
-                                has_friction: true,
-                                can_rotate: *can_rotate,
-                                bounciness: *bounciness,
-                                gravity: game_meta.physics.gravity,
-                                ..default()
-                            },
-                        );
-                        attachments.insert(
-                            sword_ent,
-                            PlayerBodyAttachment {
-                                sync_color: false,
-                                sync_animation: false,
-                                player: player_entity,
-                                head: false,
-                                offset: grab_offset.extend(1.0),
-                            },
-                        );
-                    }
-                },
-            );
         }
     }
 
@@ -817,21 +567,120 @@ fn player_kill_callback(player_entity: Entity) -> System {
     .system()
 }
 
-/// System that makes sure the swords held by AI are despawned when they are killed.
-fn delete_dead_ai_swords(
-    mut entities: ResMut<Entities>,
-    ai_players: Comp<AiPlayer>,
-    items: Comp<Item>,
-    dropped: Comp<ItemDropped>,
+/// System that lets an AI take control of the players whose input has been switched to AI during
+/// the match, such as network players that disconnected.
+fn take_over_ai_players(
+    entities: Res<Entities>,
+    player_inputs: Res<PlayerInputs>,
+    player_indexes: Comp<PlayerIdx>,
+    mut ai_players: CompMut<AiPlayer>,
 ) {
-    let mut to_kill = Vec::new();
-    for (ent, (_item, dropped)) in entities.iter_with((&items, &dropped)) {
-        if ai_players.contains(dropped.player) {
-            to_kill.push(ent);
+    for (player_ent, player_idx) in entities.iter_with(&player_indexes) {
+        let input = &player_inputs.players[player_idx.0];
+        if input.active && input.is_ai && !ai_players.contains(player_ent) {
+            ai_players.insert(player_ent, default());
         }
     }
-    for entity in to_kill {
-        entities.kill(entity);
+}
+
+/// System that kills the players whose input is no longer active, such as players that have left
+/// or have been kicked out of a network match.
+fn kill_inactive_players(
+    mut commands: Commands,
+    entities: Res<Entities>,
+    player_inputs: Res<PlayerInputs>,
+    player_indexes: Comp<PlayerIdx>,
+    players_killed: Comp<PlayerKilled>,
+) {
+    for (player_ent, player_idx) in entities.iter_with(&player_indexes) {
+        if !player_inputs.players[player_idx.0].active && !players_killed.contains(player_ent) {
+            commands.add(PlayerCommand::kill(player_ent, None));
+        }
+    }
+}
+
+/// System that handles players landing on each other's heads.
+///
+/// The stomping player bounces back up, and the stomped player is incapacitated, or killed if the
+/// stomping player is wearing stomp boots.
+fn stomp_players(
+    entities: Res<Entities>,
+    mut commands: Commands,
+    game_meta: Res<CoreMetaArc>,
+    match_settings: Res<MatchSettings>,
+    collision_world: CollisionWorld,
+    player_indexes: Comp<PlayerIdx>,
+    players_killed: Comp<PlayerKilled>,
+    invincibles: Comp<Invincibility>,
+    wearing_stomp_boots: Comp<WearingStompBoots>,
+    resized_players: Comp<Resized>,
+    transforms: Comp<Transform>,
+    mut bodies: CompMut<KinematicBody>,
+    mut player_states: CompMut<PlayerState>,
+    mut hit_events: ResMut<EventChannel<HitEvent>>,
+) {
+    // Players don't have a body until they are hydrated
+    let mut bitset = player_indexes.bitset().clone();
+    bitset.bit_and(bodies.bitset());
+    bitset.bit_andnot(players_killed.bitset());
+
+    for player_ent in entities.iter_with_bitset(&bitset) {
+        let body = bodies.get(player_ent).unwrap();
+        if body.velocity.y > 0. || body.is_on_ground || body.is_on_platform {
+            continue;
+        }
+        let player_bottom = body
+            .bounding_box(*transforms.get(player_ent).unwrap())
+            .bottom();
+
+        let stomped = collision_world
+            .actor_collisions_filtered(player_ent, |e| bitset.contains(e))
+            .into_iter()
+            .filter(|other| {
+                let other_body = bodies.get(*other).unwrap();
+                let other_transform = transforms.get(*other).unwrap();
+                player_bottom > other_body.bounding_box(*other_transform).center().y
+            })
+            .collect::<Vec<_>>();
+        if stomped.is_empty() {
+            continue;
+        }
+
+        bodies.get_mut(player_ent).unwrap().velocity.y = game_meta.config.stomp_bounce_velocity;
+
+        for other in stomped {
+            if invincibles.contains(other)
+                || is_protected_teammate(&match_settings, &player_indexes, player_ent, other)
+            {
+                continue;
+            }
+
+            if wearing_stomp_boots.contains(player_ent) {
+                let other_transform = transforms.get(other).unwrap();
+                commands.add(PlayerCommand::kill_by(
+                    other,
+                    Some(other_transform.translation.xy()),
+                    DamageSource::Player(player_ent),
+                ));
+            } else if let Some(state) = player_states.get_mut(other) {
+                state.current = key!("core::incapacitated");
+
+                // Giant players knock the players they stomp on away
+                let knockback = resized_players.get(player_ent).map(|x| x.knockback);
+                if let Some(knockback) = knockback.filter(|x| *x != Vec2::ZERO) {
+                    let direction = (transforms.get(other).unwrap().translation.x
+                        - transforms.get(player_ent).unwrap().translation.x)
+                        .signum();
+                    let knockback = vec2(knockback.x * direction, knockback.y);
+                    bodies.get_mut(other).unwrap().velocity = knockback;
+                    hit_events.send(HitEvent {
+                        player: other,
+                        damage: 0,
+                        knockback,
+                    });
+                }
+            }
+        }
     }
 }
 
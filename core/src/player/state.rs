@@ -4,6 +4,7 @@ pub use stage::*;
 mod stage;
 
 use states::*;
+pub use states::{incapacitated::Slipping, riding::Riding};
 mod states;
 
 /// The state of the player controller.
@@ -54,6 +55,8 @@ pub fn install(session: &mut CoreSession) {
     walk::install(session);
     dead::install(session);
     incapacitated::install(session);
+    riding::install(session);
+    frozen::install(session);
 }
 
 fn update_player_state_age(entities: Res<Entities>, mut player_states: CompMut<PlayerState>) {
@@ -62,6 +65,9 @@ fn update_player_state_age(entities: Res<Entities>, mut player_states: CompMut<P
     }
 }
 
+/// The speed, in pixels per frame, at which the pickup assist pulls items toward players.
+const PICKUP_ASSIST_SPEED: f32 = 3.0;
+
 fn use_drop_or_grab_items_system(id: Key) -> System {
     (move |entities: Res<Entities>,
            player_inputs: Res<PlayerInputs>,
@@ -70,6 +76,10 @@ fn use_drop_or_grab_items_system(id: Key) -> System {
            player_assets: BevyAssets<PlayerMeta>,
            items: Comp<Item>,
            collision_world: CollisionWorld,
+           match_settings: Res<MatchSettings>,
+           transforms: Comp<Transform>,
+           mut bodies: CompMut<KinematicBody>,
+           mut pickup_assists: CompMut<PickupAssist>,
            mut inventories: CompMut<Inventory>,
            mut audio_events: ResMut<AudioEvents>,
            mut commands: Commands| {
@@ -91,8 +101,20 @@ fn use_drop_or_grab_items_system(id: Key) -> System {
             let Some(meta) = player_assets.get(&meta_handle) else { continue; };
 
             let control = &player_inputs.players[player_idx.0].control;
+
+            // Keep track of whether the player is trying to grab an item, for the pickup assist
+            if control.grab_just_pressed && inventory.is_none() {
+                pickup_assists.insert(player_ent, PickupAssist);
+            } else if !control.grab_pressed || inventory.is_some() {
+                pickup_assists.remove(player_ent);
+            }
+            let pickup_assist_radius = match_settings
+                .pickup_assist_radius
+                .filter(|_| pickup_assists.contains(player_ent));
+
             // If we are grabbing
-            if control.grab_just_pressed {
+            if control.grab_just_pressed || (pickup_assist_radius.is_some() && inventory.is_none())
+            {
                 if inventory.is_none() {
                     // If we don't have an item
                     let colliders = collision_world
@@ -101,7 +123,6 @@ fn use_drop_or_grab_items_system(id: Key) -> System {
                         .into_iter()
                         // Filter out anything not an item
                         .filter(|ent| items.contains(*ent))
-                        // TODO: Use the ItemGrabbed tag for this detection after fixing the ItemGrabbed handling
                         // Filter out any items held by other players
                         .filter(|ent| !held_items.contains(ent))
                         .collect::<Vec<_>>();
@@ -113,6 +134,24 @@ fn use_drop_or_grab_items_system(id: Key) -> System {
 
                         // Play grab sound
                         audio_events.play(meta.sounds.grab.clone(), meta.sounds.grab_volume);
+
+                    // Pull the closest item in range toward us, if the pickup assist is enabled
+                    } else if let Some(radius) = pickup_assist_radius {
+                        let player_pos = transforms.get(player_ent).unwrap().translation.xy();
+                        let closest_item = entities
+                            .iter_with((&items, &transforms))
+                            .filter(|(ent, _)| !held_items.contains(ent))
+                            .map(|(ent, (_, transform))| {
+                                (ent, transform.translation.xy() - player_pos)
+                            })
+                            .filter(|(_, offset)| offset.length() <= radius)
+                            .min_by(|(_, a), (_, b)| a.length().total_cmp(&b.length()));
+
+                        if let Some((item, offset)) = closest_item {
+                            if let Some(body) = bodies.get_mut(item) {
+                                body.velocity = -offset.normalize_or_zero() * PICKUP_ASSIST_SPEED;
+                            }
+                        }
                     }
 
                 // If we are already carrying an item
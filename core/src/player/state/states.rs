@@ -3,7 +3,9 @@ use super::*;
 pub mod crouch;
 pub mod dead;
 pub mod default;
+pub mod frozen;
 pub mod idle;
 pub mod incapacitated;
 pub mod midair;
+pub mod riding;
 pub mod walk;
@@ -0,0 +1,54 @@
+use super::*;
+
+pub const ID: Key = key!("core::frozen");
+
+pub fn install(session: &mut CoreSession) {
+    PlayerState::add_player_state_transition_system(session, player_state_transition);
+    PlayerState::add_player_state_update_system(session, handle_player_state);
+}
+
+pub fn player_state_transition(
+    entities: Res<Entities>,
+    status_effects: Comp<StatusEffects>,
+    killed_players: Comp<PlayerKilled>,
+    mut player_states: CompMut<PlayerState>,
+) {
+    for (player_ent, player_state) in entities.iter_with(&mut player_states) {
+        if killed_players.contains(player_ent) {
+            continue;
+        }
+
+        if status_effects
+            .get(player_ent)
+            .map_or(false, |x| x.has(StatusEffectKind::Frozen))
+        {
+            player_state.current = ID;
+        } else if player_state.current == ID {
+            player_state.current = idle::ID;
+        }
+    }
+}
+
+/// Hold the frozen players still. They don't handle their controls, so they can't move or use
+/// their items until they thaw.
+pub fn handle_player_state(
+    entities: Res<Entities>,
+    player_states: Comp<PlayerState>,
+    mut animations: CompMut<AnimationBankSprite>,
+    mut bodies: CompMut<KinematicBody>,
+) {
+    for (_player_ent, (player_state, animation, body)) in
+        entities.iter_with((&player_states, &mut animations, &mut bodies))
+    {
+        if player_state.current != ID {
+            continue;
+        }
+
+        if player_state.age == 0 {
+            animation.current = key!("idle");
+        }
+
+        // Frozen players still fall, but don't slide along
+        body.velocity.x = 0.0;
+    }
+}
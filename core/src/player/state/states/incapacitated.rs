@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use super::*;
 
 pub const ID: Key = key!("core::incapacitated");
@@ -7,6 +9,39 @@ pub fn install(session: &mut CoreSession) {
 }
 
 const SLOWING_SPEED: f32 = 0.3;
+const INCAPACITATED_FRAMES: u64 = 80;
+
+/// Component added to a player that slipped, such as on a banana peel, to change how they slide
+/// while they are incapacitated.
+///
+/// The player keeps the velocity they slipped with, instead of being pushed forward, and stays
+/// incapacitated until their [`Slipping`][StatusEffectKind::Slipping] status effect wears off.
+#[derive(Clone, Debug, TypeUlid)]
+#[ulid = "01H30A2QF8VNM4XK6TRB1JZE7C"]
+pub struct Slipping {
+    /// The horizontal speed, in pixels per frame, that the player loses every frame.
+    pub slowdown: f32,
+}
+
+impl Slipping {
+    /// Make a player slip for the given duration, incapacitating them.
+    pub fn apply(
+        player: Entity,
+        slowdown: f32,
+        duration: Duration,
+        player_states: &mut CompMut<PlayerState>,
+        slipping: &mut CompMut<Slipping>,
+        status_effects: &mut CompMut<StatusEffects>,
+    ) {
+        if let Some(state) = player_states.get_mut(player) {
+            state.current = ID;
+        }
+        slipping.insert(player, Slipping { slowdown });
+        if let Some(effects) = status_effects.get_mut(player) {
+            effects.add(StatusEffectKind::Slipping, duration);
+        }
+    }
+}
 
 pub fn handle_player_state(
     entities: Res<Entities>,
@@ -15,6 +50,8 @@ pub fn handle_player_state(
     player_assets: BevyAssets<PlayerMeta>,
     player_inputs: Res<PlayerInputs>,
     atlas_sprites: Comp<AtlasSprite>,
+    mut slipping: CompMut<Slipping>,
+    mut status_effects: CompMut<StatusEffects>,
     mut animations: CompMut<AnimationBankSprite>,
     mut bodies: CompMut<KinematicBody>,
 ) {
@@ -26,6 +63,12 @@ pub fn handle_player_state(
         &atlas_sprites,
     )) {
         if state.current != ID {
+            // The player may have been knocked out of the slip, such as by being frozen
+            if slipping.remove(player_ent).is_some() {
+                if let Some(effects) = status_effects.get_mut(player_ent) {
+                    effects.remove(StatusEffectKind::Slipping);
+                }
+            }
             continue;
         };
 
@@ -36,28 +79,39 @@ pub fn handle_player_state(
             continue;
         };
 
+        let slowing_speed = slipping
+            .get(player_ent)
+            .map_or(SLOWING_SPEED, |x| x.slowdown);
+        let incapacitated = if slipping.contains(player_ent) {
+            status_effects
+                .get(player_ent)
+                .map_or(false, |x| x.has(StatusEffectKind::Slipping))
+        } else {
+            state.age < INCAPACITATED_FRAMES
+        };
+
         match state.age {
             0 => {
                 // TODO find right animation
                 animation.current = key!("rise");
                 PlayerCommand::set_inventory(player_ent, None);
 
-                if body.velocity.x.abs() < meta.stats.walk_speed {
+                if body.velocity.x.abs() < meta.stats.walk_speed && !slipping.contains(player_ent) {
                     body.velocity.x = 5. * if atlas_sprite.flip_x { -1.0f32 } else { 1.0 };
                 }
             }
-            n if n < 80 => {
-                if body.velocity.x.abs() < SLOWING_SPEED {
+            _ if incapacitated => {
+                if body.velocity.x.abs() < slowing_speed {
                     body.velocity.x = 0.;
                 } else {
-                    body.velocity.x -= body.velocity.x.signum() * SLOWING_SPEED
+                    body.velocity.x -= body.velocity.x.signum() * slowing_speed
                 }
             }
-            n if n >= 80 => {
+            _ => {
+                slipping.remove(player_ent);
                 state.current = idle::ID;
                 animation.current = key!("idle");
             }
-            _ => (),
         }
     }
 }
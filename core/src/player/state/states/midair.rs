@@ -48,6 +48,7 @@ pub fn handle_player_state(
     mut sprites: CompMut<AtlasSprite>,
     mut animations: CompMut<AnimationBankSprite>,
     mut bodies: CompMut<KinematicBody>,
+    status_effects: Comp<StatusEffects>,
 ) {
     let players = entities.iter_with((
         &player_states,
@@ -56,7 +57,7 @@ pub fn handle_player_state(
         &mut sprites,
         &mut bodies,
     ));
-    for (_player_ent, (player_state, player_idx, animation, sprite, body)) in players {
+    for (player_ent, (player_state, player_idx, animation, sprite, body)) in players {
         if player_state.current != ID {
             continue;
         }
@@ -67,6 +68,9 @@ pub fn handle_player_state(
             continue;
         };
         let control = &player_inputs.players[player_idx.0].control;
+        let speed_factor = status_effects
+            .get(player_ent)
+            .map_or(1.0, |x| x.speed_factor());
 
         if body.velocity.y > 0.0 {
             animation.current = key!("rise");
@@ -82,9 +86,9 @@ pub fn handle_player_state(
         // Walk in movement direction
         body.velocity.x += meta.stats.accel_air_speed * control.move_direction.x;
         if control.move_direction.x.is_sign_positive() {
-            body.velocity.x = body.velocity.x.min(meta.stats.air_speed);
+            body.velocity.x = body.velocity.x.min(meta.stats.air_speed * speed_factor);
         } else {
-            body.velocity.x = body.velocity.x.max(-meta.stats.air_speed);
+            body.velocity.x = body.velocity.x.max(-meta.stats.air_speed * speed_factor);
         }
 
         if control.move_direction.x == 0.0 {
@@ -0,0 +1,84 @@
+use super::*;
+
+pub const ID: Key = key!("core::riding");
+
+/// Component added to a player that is riding a vehicle, such as a minecart.
+///
+/// While riding, the vehicle moves the player along with it and steers with the player's controls.
+/// The player gets out by jumping, or is thrown out when the vehicle removes this component.
+#[derive(Clone, Debug, TypeUlid)]
+#[ulid = "01H2RB6F3TKX8N0QJZ5WCVYM4E"]
+pub struct Riding {
+    /// The vehicle the player is riding.
+    pub vehicle: Entity,
+}
+
+pub fn install(session: &mut CoreSession) {
+    PlayerState::add_player_state_transition_system(session, player_state_transition);
+    PlayerState::add_player_state_update_system(session, handle_player_state);
+    PlayerState::add_player_state_update_system(session, use_drop_or_grab_items_system(ID));
+}
+
+pub fn player_state_transition(
+    entities: Res<Entities>,
+    riding: Comp<Riding>,
+    killed_players: Comp<PlayerKilled>,
+    mut player_states: CompMut<PlayerState>,
+) {
+    for (player_ent, player_state) in entities.iter_with(&mut player_states) {
+        if killed_players.contains(player_ent) {
+            continue;
+        }
+
+        if riding.contains(player_ent) {
+            player_state.current = ID;
+        } else if player_state.current == ID {
+            player_state.current = midair::ID;
+        }
+    }
+}
+
+pub fn handle_player_state(
+    entities: Res<Entities>,
+    player_inputs: Res<PlayerInputs>,
+    player_indexes: Comp<PlayerIdx>,
+    player_states: Comp<PlayerState>,
+    player_assets: BevyAssets<PlayerMeta>,
+    mut riding: CompMut<Riding>,
+    mut animations: CompMut<AnimationBankSprite>,
+    mut bodies: CompMut<KinematicBody>,
+    mut audio_events: ResMut<AudioEvents>,
+) {
+    let players = entities.iter_with((
+        &player_states,
+        &player_indexes,
+        &mut animations,
+        &mut bodies,
+    ));
+    for (player_ent, (player_state, player_idx, animation, body)) in players {
+        if player_state.current != ID {
+            continue;
+        }
+        let meta_handle = player_inputs.players[player_idx.0]
+            .selected_player
+            .get_bevy_handle();
+        let Some(meta) = player_assets.get(&meta_handle) else {
+            continue;
+        };
+
+        if player_state.age == 0 {
+            animation.current = key!("idle");
+        }
+
+        // The vehicle carries the player
+        body.velocity = Vec2::ZERO;
+
+        // Jump out of the vehicle
+        let control = &player_inputs.players[player_idx.0].control;
+        if control.jump_just_pressed {
+            riding.remove(player_ent);
+            audio_events.play(meta.sounds.jump.clone(), meta.sounds.jump_volume);
+            body.velocity.y = meta.stats.jump_speed;
+        }
+    }
+}
@@ -43,6 +43,7 @@ pub fn handle_player_state(
     mut sprites: CompMut<AtlasSprite>,
     mut animations: CompMut<AnimationBankSprite>,
     mut bodies: CompMut<KinematicBody>,
+    status_effects: Comp<StatusEffects>,
     mut audio_events: ResMut<AudioEvents>,
 ) {
     let players = entities.iter_with((
@@ -52,7 +53,7 @@ pub fn handle_player_state(
         &mut sprites,
         &mut bodies,
     ));
-    for (_player_ent, (player_state, player_idx, animation, sprite, body)) in players {
+    for (player_ent, (player_state, player_idx, animation, sprite, body)) in players {
         if player_state.current != ID {
             continue;
         }
@@ -63,6 +64,9 @@ pub fn handle_player_state(
             continue;
         };
         let control = &player_inputs.players[player_idx.0].control;
+        let speed_factor = status_effects
+            .get(player_ent)
+            .map_or(1.0, |x| x.speed_factor());
 
         // If this is the first frame of this state
         if player_state.age == 0 {
@@ -84,12 +88,12 @@ pub fn handle_player_state(
             body.velocity.x = body
                 .velocity
                 .x
-                .min(meta.stats.walk_speed * control.move_direction.x);
+                .min(meta.stats.walk_speed * speed_factor * control.move_direction.x);
         } else {
             body.velocity.x = body
                 .velocity
                 .x
-                .max(meta.stats.walk_speed * control.move_direction.x);
+                .max(meta.stats.walk_speed * speed_factor * control.move_direction.x);
         }
 
         // Point in movement direction
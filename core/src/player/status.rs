@@ -0,0 +1,409 @@
+//! Player status effects.
+//!
+//! A status effect changes how a player behaves or looks for a limited time, such as being
+//! [frozen][StatusEffectKind::Frozen] or [slowed][StatusEffectKind::Slowed]. The effects of a player
+//! are kept in their [`StatusEffects`] component, which items and hazards add effects to:
+//!
+//! ```ignore
+//! status_effects
+//!     .get_mut(player)
+//!     .unwrap()
+//!     .add(StatusEffectKind::Slowed, Duration::from_secs(3));
+//! ```
+//!
+//! This module counts the effects down, removes them once they wear off, and shows an icon above
+//! the player for each of their effects. Applying an effect that the player already has follows the
+//! [stacking rule][Stacking] of the effect.
+//!
+//! The effects are the only way that items and hazards change the players over time: the
+//! [cloak][crate::elements::cloak] makes its wearer [invisible][StatusEffectKind::Invisible], the
+//! respawn protection [shields][StatusEffectKind::Shielded] the players, and the
+//! [banana peel][crate::elements::banana_peel] makes them [slip][StatusEffectKind::Slipping].
+
+use std::time::Duration;
+
+use crate::prelude::*;
+
+pub fn install(session: &mut CoreSession) {
+    session
+        .stages
+        .add_system_to_stage(CoreStage::First, update_status_effects)
+        .add_system_to_stage(CoreStage::PostUpdate, shield_players)
+        // Run before the attachments are updated, so that they take the tint of the player.
+        .add_system_to_stage(CoreStage::Last, tint_players)
+        .add_system_to_stage(CoreStage::Last, update_status_effect_icons);
+}
+
+/// How much slower each stack of [`Slowed`][StatusEffectKind::Slowed] makes the player.
+pub const SLOW_PER_STACK: f32 = 0.25;
+/// The number of stacks of [`Poisoned`][StatusEffectKind::Poisoned] that kill the player.
+pub const LETHAL_POISON_STACKS: u32 = 3;
+/// The depth of the status effect icons relative to the player.
+const ICON_Z_OFFSET: f32 = 0.5;
+
+/// The kinds of status effects.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StatusEffectKind {
+    /// The player can't move or use their items, and shatters if they are frozen again shortly
+    /// after they were frozen.
+    Frozen,
+    /// The player burns to death once the effect wears off.
+    Burning,
+    /// The player dies once they have [`LETHAL_POISON_STACKS`] stacks of poison.
+    Poisoned,
+    /// The player moves slower for every stack of the effect.
+    Slowed,
+    /// The player can't be killed.
+    Shielded,
+    /// The player can barely be seen by their opponents.
+    Invisible,
+    /// The player slides along and can't control their movement, such as after slipping on a
+    /// banana peel.
+    Slipping,
+}
+
+/// How applying an effect to a player that already has it is handled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stacking {
+    /// The effect restarts with the new duration.
+    Refresh,
+    /// The effect gains a stack, up to `max` stacks, and restarts with the new duration.
+    Stack { max: u32 },
+    /// Nothing happens until the effect has worn off.
+    Ignore,
+}
+
+impl StatusEffectKind {
+    /// All the kinds of status effects, in the order that their icons are shown in.
+    pub const ALL: [Self; 7] = [
+        Self::Frozen,
+        Self::Burning,
+        Self::Poisoned,
+        Self::Slowed,
+        Self::Shielded,
+        Self::Invisible,
+        Self::Slipping,
+    ];
+
+    /// Get how applying the effect again is handled.
+    pub fn stacking(self) -> Stacking {
+        match self {
+            Self::Frozen | Self::Shielded | Self::Invisible => Stacking::Refresh,
+            Self::Burning | Self::Slipping => Stacking::Ignore,
+            Self::Poisoned => Stacking::Stack {
+                max: LETHAL_POISON_STACKS,
+            },
+            Self::Slowed => Stacking::Stack { max: 3 },
+        }
+    }
+}
+
+/// A status effect applied to a player.
+#[derive(Clone, Debug)]
+pub struct StatusEffect {
+    pub kind: StatusEffectKind,
+    /// The time left until the effect wears off.
+    pub timer: Timer,
+    /// The number of times the effect has been stacked, starting from `1`.
+    pub stacks: u32,
+}
+
+/// Component containing the status effects of a player.
+#[derive(Clone, Debug, Default, TypeUlid)]
+#[ulid = "01H32R5D8WQK3TZ6XN0MBV4JCA"]
+pub struct StatusEffects {
+    effects: Vec<StatusEffect>,
+}
+
+impl StatusEffects {
+    /// Apply an effect for the given duration, following the [stacking rule][Stacking] of the effect
+    /// if the player already has it.
+    pub fn add(&mut self, kind: StatusEffectKind, duration: Duration) {
+        let timer = Timer::new(duration, TimerMode::Once);
+        let Some(effect) = self.effects.iter_mut().find(|x| x.kind == kind) else {
+            self.effects.push(StatusEffect {
+                kind,
+                timer,
+                stacks: 1,
+            });
+            return;
+        };
+
+        match kind.stacking() {
+            Stacking::Refresh => effect.timer = timer,
+            Stacking::Stack { max } => {
+                effect.timer = timer;
+                effect.stacks = (effect.stacks + 1).min(max);
+            }
+            Stacking::Ignore => (),
+        }
+    }
+
+    /// Remove an effect, if the player has it.
+    pub fn remove(&mut self, kind: StatusEffectKind) {
+        self.effects.retain(|x| x.kind != kind);
+    }
+
+    /// Get an effect, if the player has it.
+    pub fn get(&self, kind: StatusEffectKind) -> Option<&StatusEffect> {
+        self.effects.iter().find(|x| x.kind == kind)
+    }
+
+    /// Whether the player has an effect.
+    pub fn has(&self, kind: StatusEffectKind) -> bool {
+        self.get(kind).is_some()
+    }
+
+    /// Iterate over the effects of the player.
+    pub fn iter(&self) -> impl Iterator<Item = &StatusEffect> {
+        self.effects.iter()
+    }
+
+    /// The factor that the movement speed of the player is multiplied by.
+    pub fn speed_factor(&self) -> f32 {
+        let stacks = self.get(StatusEffectKind::Slowed).map_or(0, |x| x.stacks);
+        (1.0 - stacks as f32 * SLOW_PER_STACK).max(0.0)
+    }
+
+    /// Count the effects down, and remove the ones that wore off, returning their kinds.
+    pub fn tick(&mut self, delta: Duration) -> Vec<StatusEffectKind> {
+        let mut finished = Vec::new();
+        self.effects.retain_mut(|effect| {
+            effect.timer.tick(delta);
+            if effect.timer.finished() {
+                finished.push(effect.kind);
+            }
+            !effect.timer.finished()
+        });
+        finished
+    }
+}
+
+/// Component added to the icons shown above the players for their status effects.
+#[derive(Clone, Debug, TypeUlid)]
+#[ulid = "01H32R5KQ1ZP7MX9AT3VWE6N2B"]
+pub struct StatusEffectIcon {
+    /// The player the icon is shown above.
+    pub player: Entity,
+    pub kind: StatusEffectKind,
+}
+
+/// Count the status effects down, apply the effects that kill players, and clear the effects of
+/// the dead players.
+fn update_status_effects(
+    entities: Res<Entities>,
+    mut commands: Commands,
+    player_indexes: Comp<PlayerIdx>,
+    players_killed: Comp<PlayerKilled>,
+    mut status_effects: CompMut<StatusEffects>,
+    time: Res<Time>,
+) {
+    // Every player can have status effects
+    let new_players = entities
+        .iter_with(&player_indexes)
+        .map(|(player, _)| player)
+        .filter(|player| !status_effects.contains(*player))
+        .collect::<Vec<_>>();
+    for player in new_players {
+        status_effects.insert(player, default());
+    }
+
+    for (player, effects) in entities.iter_with(&mut status_effects) {
+        if players_killed.contains(player) {
+            *effects = default();
+            continue;
+        }
+
+        let finished = effects.tick(time.delta());
+        let burned = finished.contains(&StatusEffectKind::Burning);
+        let poisoned = effects
+            .get(StatusEffectKind::Poisoned)
+            .map_or(false, |x| x.stacks >= LETHAL_POISON_STACKS);
+        if (burned || poisoned) && !effects.has(StatusEffectKind::Shielded) {
+            commands.add(PlayerCommand::kill(player, None));
+        }
+    }
+}
+
+/// Marker component for the players that can't be hurt, kept in sync with their
+/// [`Shielded`][StatusEffectKind::Shielded] status effect.
+///
+/// The hazards check this component rather than the status effects, so that they can filter the
+/// players with its bitset.
+#[derive(Clone, Default, TypeUlid, Debug)]
+#[ulid = "01GV3P99HFCZSC2MMXHA9394EJ"]
+pub struct Invincibility;
+
+/// Keep the shielded players invincible until their shield wears off.
+fn shield_players(
+    entities: Res<Entities>,
+    status_effects: Comp<StatusEffects>,
+    mut invincibles: CompMut<Invincibility>,
+) {
+    for (player, effects) in entities.iter_with(&status_effects) {
+        if effects.has(StatusEffectKind::Shielded) {
+            invincibles.insert(player, Invincibility);
+        } else {
+            invincibles.remove(player);
+        }
+    }
+}
+
+/// Tint the players with the color of their status effects, and the attachments that take their
+/// color.
+///
+/// The tint of the first effect in [`StatusEffectKind::ALL`] that the player has is used.
+fn tint_players(
+    entities: Res<Entities>,
+    game_meta: Res<CoreMetaArc>,
+    status_effects: Comp<StatusEffects>,
+    player_indexes: Comp<PlayerIdx>,
+    attachments: Comp<Attachment>,
+    mut atlas_sprites: CompMut<AtlasSprite>,
+) {
+    let meta = &game_meta.status_effects;
+    let tint = |player: Entity| {
+        let Some(effects) = status_effects.get(player) else {
+            return Color::WHITE;
+        };
+        StatusEffectKind::ALL
+            .into_iter()
+            .filter(|kind| effects.has(*kind))
+            .map(|kind| meta.get(kind).tint.0)
+            .find(|tint| *tint != Color::WHITE)
+            .unwrap_or(Color::WHITE)
+    };
+    let apply_tint = |sprite: &mut AtlasSprite, tint: Color| {
+        let alpha = sprite.color.a();
+        sprite.color = tint;
+        sprite.color.set_a(alpha);
+    };
+
+    for (player, _) in entities.iter_with(&player_indexes) {
+        if let Some(sprite) = atlas_sprites.get_mut(player) {
+            apply_tint(sprite, tint(player));
+        }
+    }
+    for (ent, attachment) in entities.iter_with(&attachments) {
+        if !attachment.sync_color || !player_indexes.contains(attachment.entity) {
+            continue;
+        }
+        if let Some(sprite) = atlas_sprites.get_mut(ent) {
+            apply_tint(sprite, tint(attachment.entity));
+        }
+    }
+}
+
+/// Show a row of icons above the players for their status effects.
+fn update_status_effect_icons(
+    mut entities: ResMut<Entities>,
+    game_meta: Res<CoreMetaArc>,
+    status_effects: Comp<StatusEffects>,
+    mut icons: CompMut<StatusEffectIcon>,
+    mut attachments: CompMut<Attachment>,
+    mut atlas_sprites: CompMut<AtlasSprite>,
+    mut transforms: CompMut<Transform>,
+) {
+    let meta = &game_meta.status_effects;
+
+    // Remove the icons of the effects that wore off
+    let mut shown = Vec::new();
+    let mut worn_off = Vec::new();
+    for (icon_ent, icon) in entities.iter_with(&icons) {
+        if status_effects
+            .get(icon.player)
+            .map_or(false, |x| x.has(icon.kind))
+        {
+            shown.push((icon.player, icon.kind, icon_ent));
+        } else {
+            worn_off.push(icon_ent);
+        }
+    }
+    for icon_ent in worn_off {
+        entities.kill(icon_ent);
+    }
+
+    let players = entities
+        .iter_with(&status_effects)
+        .map(|(player, effects)| {
+            let kinds = StatusEffectKind::ALL
+                .into_iter()
+                .filter(|kind| effects.has(*kind))
+                .collect::<Vec<_>>();
+            (player, kinds)
+        })
+        .collect::<Vec<_>>();
+    for (player, kinds) in players {
+        // Center the row of icons above the player
+        let row_width = kinds.len().saturating_sub(1) as f32 * meta.icon_spacing;
+        for (i, kind) in kinds.into_iter().enumerate() {
+            let offset = Vec3::new(
+                meta.icon_offset.x - row_width / 2.0 + i as f32 * meta.icon_spacing,
+                meta.icon_offset.y,
+                ICON_Z_OFFSET,
+            );
+            let icon_ent = shown
+                .iter()
+                .find(|(p, k, _)| *p == player && *k == kind)
+                .map(|(_, _, ent)| *ent);
+            let icon_ent = icon_ent.unwrap_or_else(|| {
+                let ent = entities.create();
+                icons.insert(ent, StatusEffectIcon { player, kind });
+                transforms.insert(ent, default());
+                atlas_sprites.insert(
+                    ent,
+                    AtlasSprite {
+                        atlas: meta.icon_atlas.clone(),
+                        index: meta.get(kind).icon,
+                        ..default()
+                    },
+                );
+                ent
+            });
+            attachments.insert(
+                icon_ent,
+                Attachment {
+                    entity: player,
+                    offset,
+                    sync_animation: false,
+                    sync_color: false,
+                },
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn effects_follow_their_stacking_rules() {
+        let mut effects = StatusEffects::default();
+        let second = Duration::from_secs(1);
+
+        // Refreshed effects restart
+        effects.add(StatusEffectKind::Frozen, second);
+        effects.tick(second / 2);
+        effects.add(StatusEffectKind::Frozen, second);
+        assert_eq!(effects.tick(second / 2), vec![]);
+        assert_eq!(effects.get(StatusEffectKind::Frozen).unwrap().stacks, 1);
+
+        // Ignored effects don't restart
+        effects.add(StatusEffectKind::Burning, second);
+        effects.tick(second / 2);
+        effects.add(StatusEffectKind::Burning, second);
+        assert_eq!(effects.tick(second / 2), vec![StatusEffectKind::Burning]);
+        assert!(!effects.has(StatusEffectKind::Frozen));
+
+        // Stacked effects add up, to a maximum
+        for _ in 0..5 {
+            effects.add(StatusEffectKind::Slowed, second);
+        }
+        assert_eq!(effects.get(StatusEffectKind::Slowed).unwrap().stacks, 3);
+        assert_eq!(effects.speed_factor(), 0.25);
+        effects.tick(second);
+        assert!(!effects.has(StatusEffectKind::Slowed));
+        assert_eq!(effects.speed_factor(), 1.0);
+    }
+}
@@ -2,9 +2,11 @@
 
 pub use {
     crate::{
-        attachment::*, bullet::*, camera::*, damage::*, debug::*, debug::*, elements::*,
-        globals::*, input::*, item::*, item::*, lifetime::*, map::*, metadata::*, physics::*,
-        player::*, session::*, utils::*, MAX_PLAYERS,
+        afk::*, ai::*, arcade::*, attachment::*, bullet::*, camera::*, damage::*, debug::*,
+        elements::*, events::*, foreground::*, globals::*, input::*, item::*, kill_feed::*,
+        lava::*, lifetime::*, map::*, match_settings::*, match_stats::*, metadata::*, mutator::*,
+        nav::*, observer::*, physics::*, player::*, presentation::*, rounds::*, session::*,
+        sudden_death::*, tags::*, teams::*, timer::*, utils::*, MAX_PLAYERS,
     },
     bones_bevy_asset::{BevyAssets, BonesBevyAsset, BonesBevyAssetLoad},
     bones_lib::prelude::*,
@@ -0,0 +1,301 @@
+//! Presentation settings, for players who are sensitive to motion or need more contrast.
+//!
+//! The [`PresentationSettings`] resource is set by the frontend from the player's settings. It only
+//! changes how the match looks: camera shake, parallax, visual-only entities, and the fading of
+//! [invisible][StatusEffectKind::Invisible] players and of players hidden in
+//! [smoke][smoke_grenade::SmokeCloud], and whether the hit popups are shown, so it doesn't have to
+//! be the same for every player of a network match.
+//!
+//! Camera shake and parallax move the camera, the parallax background, and the foreground layers,
+//! so those entities are [left out][PresentationOnly] of the [checksum][CoreSession::checksum] that
+//! network matches compare to detect desyncs. The other settings only change the colors of the
+//! sprites, which aren't part of the checksum.
+//!
+//! Hit popups float up from the players that are hit, showing the damage dealt by the hit, or how
+//! hard the player was knocked back. They are spawned from the [`HitEvent`]s whether or not they
+//! are shown, so that every player of a network match has the same entities.
+
+use crate::prelude::*;
+
+pub fn install(session: &mut CoreSession) {
+    session.world.init_resource::<PresentationSettings>();
+    session
+        .stages
+        // Run after the elements have caused camera shake, which is applied in the last stage.
+        .add_system_to_stage(CoreStage::PostUpdate, reduce_camera_shake)
+        .add_system_to_stage(CoreStage::Last, apply_presentation_settings)
+        // Run after the attachments have synchronized the players' colors.
+        .add_system_to_stage(CoreStage::Last, fade_hidden_players)
+        // The hits are sent by commands throughout the frame, so they are shown at the start of
+        // the next one.
+        .add_system_to_stage(CoreStage::First, spawn_hit_popups)
+        .add_system_to_stage(CoreStage::First, clear_events::<HitEvent>)
+        .add_system_to_stage(CoreStage::Last, animate_hit_popups);
+}
+
+/// Resource containing the presentation settings chosen by the player.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, TypeUlid)]
+#[ulid = "01H2GQ3W8J1TPZKXB6N9R4CVEA"]
+pub struct PresentationSettings {
+    /// Disable camera shake and parallax, and hide the parallax background and the
+    /// [decorations][Tag::Decoration].
+    pub reduced_motion: bool,
+    /// Hide the parallax background and the [decorations][Tag::Decoration], and render the map on
+    /// a black background, so that the players and items stand out.
+    pub high_contrast: bool,
+    /// For every player index, whether the player is controlled on this computer.
+    ///
+    /// Invisible players are only faded out for the players that aren't controlling them.
+    pub local_players: [bool; MAX_PLAYERS],
+    /// Show popups with the damage dealt to the players that are hit.
+    pub hit_popups: bool,
+}
+
+impl PresentationSettings {
+    /// Whether or not the purely visual parts of the map are hidden.
+    pub fn hide_background(&self) -> bool {
+        self.reduced_motion || self.high_contrast
+    }
+}
+
+/// Cancel all camera shake when motion is reduced.
+fn reduce_camera_shake(
+    settings: Res<PresentationSettings>,
+    mut trauma_events: ResMut<CameraTraumaEvents>,
+    mut camera_shakes: CompMut<CameraShake>,
+) {
+    if !settings.reduced_motion {
+        return;
+    }
+
+    trauma_events.queue.clear();
+    for camera_shake in camera_shakes.iter_mut() {
+        camera_shake.trauma = 0.0;
+    }
+}
+
+/// Show or hide the background and the decorations, and set the background color.
+fn apply_presentation_settings(
+    settings: Res<PresentationSettings>,
+    map: Res<LoadedMap>,
+    tag_index: Res<TagIndex>,
+    entities: Res<Entities>,
+    parallax_bg_sprites: Comp<ParallaxBackgroundSprite>,
+    mut sprites: CompMut<Sprite>,
+    mut atlas_sprites: CompMut<AtlasSprite>,
+    mut clear_color: ResMut<ClearColor>,
+) {
+    let alpha = if settings.hide_background() { 0.0 } else { 1.0 };
+
+    for (_ent, (_, sprite)) in entities.iter_with((&parallax_bg_sprites, &mut sprites)) {
+        sprite.color.set_a(alpha);
+    }
+    for ent in tag_index.iter(Tag::Decoration) {
+        if let Some(sprite) = atlas_sprites.get_mut(ent) {
+            sprite.color.set_a(alpha);
+        }
+        if let Some(sprite) = sprites.get_mut(ent) {
+            sprite.color.set_a(alpha);
+        }
+    }
+
+    **clear_color = if settings.high_contrast {
+        Color::BLACK
+    } else {
+        map.background_color.0
+    };
+}
+
+/// Fade out the invisible players, more so for the players that aren't controlling them, and the
+/// players inside of smoke clouds.
+fn fade_hidden_players(
+    settings: Res<PresentationSettings>,
+    game_meta: Res<CoreMetaArc>,
+    entities: Res<Entities>,
+    status_effects: Comp<StatusEffects>,
+    smoke_clouds: Comp<smoke_grenade::SmokeCloud>,
+    player_indexes: Comp<PlayerIdx>,
+    transforms: Comp<Transform>,
+    attachments: Comp<Attachment>,
+    mut atlas_sprites: CompMut<AtlasSprite>,
+) {
+    let smoke = entities
+        .iter_with((&smoke_clouds, &transforms))
+        .map(|(_ent, (cloud, transform))| {
+            (
+                cloud.rect(transform.translation.truncate()),
+                cloud.player_alpha,
+            )
+        })
+        .collect::<Vec<_>>();
+    let status_effects_meta = &game_meta.status_effects;
+    let alpha = |player: Entity| {
+        let is_local = player_indexes
+            .get(player)
+            .map_or(false, |x| settings.local_players[x.0]);
+        let is_invisible = status_effects
+            .get(player)
+            .map_or(false, |x| x.has(StatusEffectKind::Invisible));
+        let invisible_alpha = is_invisible.then_some(if is_local {
+            status_effects_meta.invisible_own_alpha
+        } else {
+            status_effects_meta.invisible_opponent_alpha
+        });
+        let smoke_alpha = transforms.get(player).and_then(|transform| {
+            let position = transform.translation.truncate();
+            smoke
+                .iter()
+                .filter(|(rect, _)| rect.contains(position))
+                .map(|(_, alpha)| *alpha)
+                .reduce(f32::min)
+        });
+        [invisible_alpha, smoke_alpha]
+            .into_iter()
+            .flatten()
+            .reduce(f32::min)
+    };
+
+    for (player, _) in entities.iter_with(&player_indexes) {
+        if let (Some(alpha), Some(sprite)) = (alpha(player), atlas_sprites.get_mut(player)) {
+            sprite.color.set_a(alpha);
+        }
+    }
+    // Fade the fins, hats, and other attachments that take the color of the player
+    for (ent, attachment) in entities.iter_with(&attachments) {
+        if !attachment.sync_color {
+            continue;
+        }
+        let Some(alpha) = alpha(attachment.entity) else {
+            continue;
+        };
+        if let Some(sprite) = atlas_sprites.get_mut(ent) {
+            sprite.color.set_a(alpha);
+        }
+    }
+}
+
+/// The Z depth of the hit popups, in front of almost everything.
+const HIT_POPUP_Z: f32 = 800.0;
+
+/// A digit of a hit popup.
+#[derive(Clone, Debug, TypeUlid)]
+#[ulid = "01H345C2XW8NQ5RTB7KM3VZJDA"]
+pub struct HitPopup {
+    /// The color of the digit, before it fades out.
+    pub color: Color,
+}
+
+/// Get the number shown by the popup of a hit, and whether it is a damage number rather than a
+/// knock-back number.
+pub fn hit_popup_number(hit: &HitEvent) -> Option<(u32, bool)> {
+    if hit.damage > 0 {
+        Some((hit.damage, true))
+    } else {
+        let knockback = hit.knockback.length().round() as u32;
+        (knockback > 0).then_some((knockback, false))
+    }
+}
+
+/// Spawn a popup above every player hit during the last frame.
+fn spawn_hit_popups(
+    game_meta: Res<CoreMetaArc>,
+    hit_events: Res<EventChannel<HitEvent>>,
+    mut entities: ResMut<Entities>,
+    mut hit_popups: CompMut<HitPopup>,
+    mut atlas_sprites: CompMut<AtlasSprite>,
+    mut transforms: CompMut<Transform>,
+    mut lifetimes: CompMut<Lifetime>,
+) {
+    let meta = &game_meta.hit_popups;
+
+    for hit in hit_events.iter() {
+        let Some((number, is_damage)) = hit_popup_number(hit) else {
+            continue;
+        };
+        let Some(position) = transforms.get(hit.player).map(|x| x.translation) else {
+            continue;
+        };
+        let color = if is_damage {
+            meta.damage_color.0
+        } else {
+            meta.knockback_color.0
+        };
+
+        // Lay the digits out centered above the player
+        let digits = number.to_string();
+        let width = digits.len() as f32 * meta.digit_spacing;
+        for (i, digit) in digits.chars().enumerate() {
+            let offset =
+                vec2((i as f32 + 0.5) * meta.digit_spacing - width / 2.0, 0.0) + meta.offset;
+            let ent = entities.create();
+            hit_popups.insert(ent, HitPopup { color });
+            atlas_sprites.insert(
+                ent,
+                AtlasSprite {
+                    atlas: meta.digit_atlas.clone(),
+                    index: digit.to_digit(10).unwrap() as usize,
+                    color,
+                    ..default()
+                },
+            );
+            transforms.insert(
+                ent,
+                Transform::from_translation((position.truncate() + offset).extend(HIT_POPUP_Z)),
+            );
+            lifetimes.insert(ent, Lifetime::new(meta.lifetime));
+        }
+    }
+}
+
+/// Float the hit popups up while they fade out, and hide them if they are turned off.
+fn animate_hit_popups(
+    settings: Res<PresentationSettings>,
+    game_meta: Res<CoreMetaArc>,
+    entities: Res<Entities>,
+    hit_popups: Comp<HitPopup>,
+    lifetimes: Comp<Lifetime>,
+    mut atlas_sprites: CompMut<AtlasSprite>,
+    mut transforms: CompMut<Transform>,
+) {
+    for (ent, (hit_popup, lifetime, transform)) in
+        entities.iter_with((&hit_popups, &lifetimes, &mut transforms))
+    {
+        transform.translation.y += game_meta.hit_popups.rise_speed;
+
+        let Some(sprite) = atlas_sprites.get_mut(ent) else {
+            continue;
+        };
+        let progress = (lifetime.age / lifetime.lifetime).clamp(0.0, 1.0);
+        let alpha = if settings.hit_popups {
+            hit_popup.color.a() * (1.0 - progress)
+        } else {
+            0.0
+        };
+        sprite.color = hit_popup.color;
+        sprite.color.set_a(alpha);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn hits_show_their_damage_or_knockback() {
+        let hit = |damage, knockback| HitEvent {
+            player: Entity::new(0, 0),
+            damage,
+            knockback,
+        };
+
+        assert_eq!(
+            hit_popup_number(&hit(LETHAL_HIT_DAMAGE, Vec2::ZERO)),
+            Some((LETHAL_HIT_DAMAGE, true))
+        );
+        assert_eq!(
+            hit_popup_number(&hit(0, vec2(-6.0, 8.0))),
+            Some((10, false))
+        );
+        assert_eq!(hit_popup_number(&hit(0, Vec2::ZERO)), None);
+    }
+}
@@ -1,4 +1,9 @@
-//! Global, deterministic random resource.
+//! Global, deterministic random resource, with independent streams of random numbers.
+
+use std::{
+    ops::Range,
+    sync::atomic::{AtomicU64, Ordering},
+};
 
 use crate::prelude::*;
 pub use turborand::prelude::*;
@@ -7,15 +12,236 @@ pub fn install(session: &mut CoreSession) {
     session.world.init_resource::<GlobalRng>();
 }
 
+/// The seed of the [`GlobalRng`] when the [`MatchSettings`] don't have one.
+pub const DEFAULT_SEED: u64 = 7;
+
+/// The independent streams of random numbers of a match.
+///
+/// Every stream is seeded from the seed of the match, but produces its own sequence, so that the
+/// numbers drawn by one system don't depend on how many numbers were drawn by the others. For
+/// example, changing how often the critters move around doesn't change which items spawn.
+///
+/// Add a new stream for randomness that shouldn't affect the existing streams.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RngStream {
+    /// Which items are spawned on the map, and where the items dropped by players go.
+    ItemSpawns,
+    /// The decisions of the AI players.
+    Ai,
+    /// Weapon spread and other random outcomes of attacks.
+    Combat,
+    /// The movement of the critters living on the map, like crabs and fish.
+    Critters,
+}
+
+impl RngStream {
+    /// All of the streams.
+    pub const ALL: [Self; 4] = [Self::ItemSpawns, Self::Ai, Self::Combat, Self::Critters];
+}
+
 /// Resource that can produce deterministic, pseudo-random numbers.
 ///
-/// Access in a system with [`Res<GlobalRng>`].
-#[derive(Clone, TypeUlid, Deref, DerefMut)]
+/// Access in a system with [`Res<GlobalRng>`], and draw the numbers from the [`RngStream`] the
+/// system belongs to with [`stream()`][Self::stream]. Peers and replays using the same seed get
+/// the same numbers from every stream.
+///
+/// The generator state is copied when the resource is cloned, so that snapshots of the world, such
+/// as the ones taken for network rollback, produce the same numbers as the original world when they
+/// are restored.
+#[derive(TypeUlid, Clone)]
 #[ulid = "01GQ0K6DDA9KKQTM3WDK1R91TE"]
-pub struct GlobalRng(AtomicRng);
+pub struct GlobalRng {
+    streams: [RandomStream; RngStream::ALL.len()],
+}
 
 impl Default for GlobalRng {
     fn default() -> Self {
-        Self(AtomicRng::with_seed(7))
+        Self::with_seed(DEFAULT_SEED)
+    }
+}
+
+impl GlobalRng {
+    /// Create a new [`GlobalRng`] with the given seed.
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            streams: RngStream::ALL.map(|stream| {
+                // Scramble the seed of every stream, so that they don't follow each other
+                RandomStream::with_seed(splitmix64(seed ^ (stream as u64 + 1)))
+            }),
+        }
+    }
+
+    /// Get the stream of random numbers with the given name.
+    pub fn stream(&self, stream: RngStream) -> &RandomStream {
+        &self.streams[stream as usize]
+    }
+}
+
+/// Mix the bits of a seed, using the SplitMix64 algorithm.
+fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// A deterministic sequence of pseudo-random numbers, one of the streams of the [`GlobalRng`].
+pub struct RandomStream {
+    state: AtomicU64,
+}
+
+impl Clone for RandomStream {
+    fn clone(&self) -> Self {
+        Self {
+            state: AtomicU64::new(self.state.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl RandomStream {
+    /// Create a new [`RandomStream`] with the given seed.
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            state: AtomicU64::new(seed),
+        }
+    }
+
+    /// Generate a random [`u64`], using the WyRand algorithm.
+    pub fn gen_u64(&self) -> u64 {
+        const INCREMENT: u64 = 0xa076_1d64_78bd_642f;
+        let state = self
+            .state
+            .fetch_add(INCREMENT, Ordering::Relaxed)
+            .wrapping_add(INCREMENT);
+        let t = u128::from(state) * u128::from(state ^ 0xe703_7ed1_a0b4_28db);
+        ((t >> 64) as u64) ^ (t as u64)
+    }
+
+    /// Generate a random [`u64`] in the given range.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is empty.
+    pub fn u64(&self, range: Range<u64>) -> u64 {
+        assert!(
+            !range.is_empty(),
+            "Can't generate a number in an empty range"
+        );
+        let span = range.end - range.start;
+        range.start + ((u128::from(self.gen_u64()) * u128::from(span)) >> 64) as u64
+    }
+
+    /// Generate a random [`u8`] in the given range.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is empty.
+    pub fn u8(&self, range: Range<u8>) -> u8 {
+        self.u64(range.start.into()..range.end.into()) as u8
+    }
+
+    /// Generate a random [`usize`] in the given range.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is empty.
+    pub fn usize(&self, range: Range<usize>) -> usize {
+        self.u64(range.start as u64..range.end as u64) as usize
+    }
+
+    /// Generate a random [`f32`] between `0.0` and `1.0`.
+    pub fn f32(&self) -> f32 {
+        (self.gen_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Generate a random [`f64`] between `0.0` and `1.0`.
+    pub fn f64(&self) -> f64 {
+        (self.gen_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Generate a random [`f32`] between `-1.0` and `1.0`.
+    pub fn f32_normalized(&self) -> f32 {
+        self.f32() * 2.0 - 1.0
+    }
+
+    /// Generate a random [`f32`] in the given range.
+    pub fn f32_range(&self, range: Range<f32>) -> f32 {
+        range.start + self.f32() * (range.end - range.start)
+    }
+
+    /// Returns `true` with the given probability, between `0.0` and `1.0`.
+    pub fn chance(&self, probability: f64) -> bool {
+        self.f64() < probability
+    }
+
+    /// Pick a random item from the slice, or `None` if it is empty.
+    pub fn pick<'a, T>(&self, items: &'a [T]) -> Option<&'a T> {
+        (!items.is_empty()).then(|| &items[self.usize(0..items.len())])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn clones_continue_the_same_sequence() {
+        let rng = GlobalRng::with_seed(42);
+        let stream = rng.stream(RngStream::Ai);
+        stream.gen_u64();
+
+        let snapshot = rng.clone();
+        let expected = (0..10).map(|_| stream.gen_u64()).collect::<Vec<_>>();
+        let restored = (0..10)
+            .map(|_| snapshot.stream(RngStream::Ai).gen_u64())
+            .collect::<Vec<_>>();
+        assert_eq!(expected, restored);
+    }
+
+    #[test]
+    fn streams_are_independent() {
+        let draw = |rng: &GlobalRng, stream| {
+            (0..10)
+                .map(|_| rng.stream(stream).gen_u64())
+                .collect::<Vec<_>>()
+        };
+
+        let rng = GlobalRng::with_seed(42);
+        let items = draw(&rng, RngStream::ItemSpawns);
+        // Drawing from another stream doesn't change the sequence of a stream
+        let other = GlobalRng::with_seed(42);
+        let critters = draw(&other, RngStream::Critters);
+        assert_eq!(draw(&other, RngStream::ItemSpawns), items);
+        assert_ne!(critters, items);
+
+        // The same seed always produces the same sequences
+        assert_eq!(
+            draw(&GlobalRng::with_seed(42), RngStream::Critters),
+            critters
+        );
+        assert_ne!(
+            draw(&GlobalRng::with_seed(43), RngStream::ItemSpawns),
+            items
+        );
+    }
+
+    #[test]
+    fn numbers_are_in_range() {
+        let rng = RandomStream::with_seed(DEFAULT_SEED);
+        for _ in 0..1000 {
+            let x = rng.f32_range(-3.0..5.0);
+            assert!((-3.0..5.0).contains(&x));
+            assert!((2..7).contains(&rng.u8(2..7)));
+            assert!((-1.0..1.0).contains(&rng.f32_normalized()));
+        }
+    }
+
+    #[test]
+    fn pick_from_slice() {
+        let rng = RandomStream::with_seed(DEFAULT_SEED);
+        assert_eq!(rng.pick::<u8>(&[]), None);
+        assert_eq!(rng.pick(&[3]), Some(&3));
+        let items = [1, 2, 3];
+        assert!(items.contains(rng.pick(&items).unwrap()));
     }
 }
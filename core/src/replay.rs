@@ -0,0 +1,102 @@
+//! Match replays.
+//!
+//! A [`Replay`] contains everything needed to re-simulate a match: the map, the match settings,
+//! the player selections, and the controls of every player for each frame. Because the simulation
+//! is deterministic, playing a replay back results in exactly the same match, which is verified
+//! by comparing the [`checksum`][CoreSession::checksum] of the world at the end of the replay.
+//!
+//! The replays in `core/tests/replays` are re-simulated by the test suite. When they stop
+//! matching, the change that broke them affects gameplay: bump [`REPLAY_VERSION`] and re-record
+//! them.
+
+use crate::prelude::*;
+
+/// The version of the game simulation.
+///
+/// This must be bumped whenever a change affects gameplay, because replays recorded with an older
+/// version can't be played back, and players of a network match must run the same simulation.
+pub const REPLAY_VERSION: u32 = 10;
+
+/// A recording of a match.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Replay {
+    /// The [`REPLAY_VERSION`] the replay was recorded with.
+    pub version: u32,
+    /// The map the match was played on.
+    pub map: MapMeta,
+    /// The settings of the match.
+    pub match_settings: MatchSettings,
+    /// The player selections.
+    pub players: [Option<GameSessionPlayerInfo>; MAX_PLAYERS],
+    /// The duration of a simulation frame, in seconds.
+    pub time_step: f32,
+    /// The controls of each player for every frame, or `None` if the player's input was inactive.
+    pub frames: Vec<[Option<PlayerControl>; MAX_PLAYERS]>,
+    /// The checksum of the world after the last frame.
+    pub checksum: u64,
+}
+
+impl Replay {
+    /// Start recording a replay of the given session.
+    ///
+    /// This must be called before the first frame has been simulated.
+    pub fn new(session: &CoreSession) -> Self {
+        Self {
+            version: REPLAY_VERSION,
+            map: session.info.map_meta.clone(),
+            match_settings: session.info.match_settings.clone(),
+            players: session.info.player_info.clone(),
+            time_step: session.time_step,
+            frames: default(),
+            checksum: 0,
+        }
+    }
+
+    /// Record the player inputs of the frame that is about to be simulated.
+    pub fn record_frame(&mut self, inputs: &PlayerInputs) {
+        self.frames.push(std::array::from_fn(|i| {
+            let input = &inputs.players[i];
+            input.active.then(|| input.control.clone())
+        }));
+    }
+
+    /// Record the state of the world once the last frame has been simulated.
+    pub fn finish(&mut self, session: &CoreSession) {
+        self.checksum = session.checksum();
+    }
+
+    /// Get the info needed to start a session playing back the replay.
+    pub fn session_info(&self, meta: Arc<CoreMeta>) -> CoreSessionInfo {
+        CoreSessionInfo {
+            meta,
+            map_meta: self.map.clone(),
+            player_info: self.players.clone(),
+            match_settings: self.match_settings.clone(),
+        }
+    }
+
+    /// Set the player inputs for the given frame.
+    ///
+    /// The controls of AI players are left alone, because they are decided by the simulation.
+    pub fn apply_frame(&self, frame: usize, inputs: &mut PlayerInputs) {
+        for (input, control) in inputs.players.iter_mut().zip(&self.frames[frame]) {
+            match control {
+                Some(control) if !input.is_ai => input.control = control.clone(),
+                Some(_) => (),
+                None => input.active = false,
+            }
+        }
+    }
+
+    /// Simulate all of the frames of the replay in a session created from
+    /// [`session_info()`][Self::session_info], returning the checksum of the world at the end.
+    pub fn play(&self, session: &mut CoreSession, bevy_world: &mut ::bevy::prelude::World) -> u64 {
+        session.time_step = self.time_step;
+        for frame in 0..self.frames.len() {
+            session.update_input(|inputs| self.apply_frame(frame, inputs));
+            session.advance(bevy_world);
+        }
+
+        session.checksum()
+    }
+}
@@ -0,0 +1,204 @@
+//! Matches played over multiple rounds.
+//!
+//! When [`MatchSettings::rounds_to_win`] is set, players that are killed stay dead until the end
+//! of the round. The round ends once the players left alive are all on the same side, either a
+//! single player, or the players of a single team in a team match, and they win it. After an
+//! intermission showing the [`MatchScore`], the map is reloaded for the next round, until a player
+//! has won enough rounds to win the match.
+//!
+//! In [capture the flag][crate::gamemode::ctf] matches, players respawn as usual, and the round is
+//! won by the team that captures enough flags instead.
+//!
+//! Without the setting, dead players respawn right away and the match never ends.
+
+use std::time::Duration;
+
+use crate::prelude::*;
+
+/// Install this module.
+pub fn install(session: &mut CoreSession) {
+    session.world.init_resource::<MatchScore>();
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PostUpdate, update_rounds);
+}
+
+/// How long the score is shown between two rounds.
+pub const INTERMISSION_DURATION: Duration = Duration::from_secs(4);
+
+/// The state of the current round.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RoundState {
+    /// The round is being played.
+    #[default]
+    Playing,
+    /// The round is over, and the next one starts when the intermission is over.
+    Intermission {
+        /// The time left until the next round.
+        remaining: Duration,
+    },
+    /// A player has won enough rounds to win the match.
+    MatchOver,
+}
+
+/// Resource containing the number of rounds won by each player.
+///
+/// It is kept when the map is reloaded for the next round.
+#[derive(Clone, Debug, Default, TypeUlid)]
+#[ulid = "01H2JR4X8ZQ3MT6KD0VNB5EYCW"]
+pub struct MatchScore {
+    /// The number of rounds won by each player.
+    pub wins: [u32; MAX_PLAYERS],
+    /// The number of the current round, starting from `0`.
+    pub round: u32,
+    /// The state of the current round.
+    pub state: RoundState,
+    /// Whether each player won the last round that ended.
+    ///
+    /// This is empty after a draw, when the last players were killed at the same time.
+    pub round_winners: [bool; MAX_PLAYERS],
+    /// Whether each player was killed during the current round.
+    eliminated: [bool; MAX_PLAYERS],
+}
+
+impl MatchScore {
+    /// Whether the given player was killed during the current round, and may not respawn until
+    /// the next one.
+    pub fn is_eliminated(&self, player_idx: usize) -> bool {
+        self.eliminated[player_idx]
+    }
+
+    /// Whether the intermission is over, and the map should be reloaded for the next round.
+    pub fn is_next_round_due(&self) -> bool {
+        self.state
+            == RoundState::Intermission {
+                remaining: Duration::ZERO,
+            }
+    }
+
+    /// Reset the score for the next round.
+    pub fn start_next_round(&mut self) {
+        self.round += 1;
+        self.restart_round();
+    }
+
+    /// End the current round, won by the given players, and start the intermission, or end the
+    /// match if a player has won `rounds_to_win` rounds.
+    pub fn end_round(&mut self, winners: [bool; MAX_PLAYERS], rounds_to_win: u32) {
+        self.round_winners = winners;
+        for (wins, won) in self.wins.iter_mut().zip(winners) {
+            if won {
+                *wins += 1;
+            }
+        }
+
+        self.state = if self.wins.iter().any(|&wins| wins >= rounds_to_win) {
+            RoundState::MatchOver
+        } else {
+            RoundState::Intermission {
+                remaining: INTERMISSION_DURATION,
+            }
+        };
+    }
+
+    /// Reset the score to play the current round again.
+    pub fn restart_round(&mut self) {
+        self.state = RoundState::Playing;
+        self.eliminated = default();
+    }
+}
+
+/// Whether or not the two players play on the same side, either because they are the same player
+/// or because they are teammates.
+pub fn is_same_side(match_settings: &MatchSettings, a: usize, b: usize) -> bool {
+    let team = match_settings.team_of(a);
+    a == b || (team.is_some() && team == match_settings.team_of(b))
+}
+
+/// Eliminate killed players, and end the round when a single side is left alive.
+fn update_rounds(
+    entities: Res<Entities>,
+    time: Res<Time>,
+    match_settings: Res<MatchSettings>,
+    player_inputs: Res<PlayerInputs>,
+    player_indexes: Comp<PlayerIdx>,
+    players_killed: Comp<PlayerKilled>,
+    mut match_score: ResMut<MatchScore>,
+) {
+    let Some(rounds_to_win) = match_settings.rounds_to_win else { return };
+
+    match &mut match_score.state {
+        RoundState::Playing => (),
+        RoundState::Intermission { remaining } => {
+            *remaining = remaining.saturating_sub(time.delta());
+            return;
+        }
+        RoundState::MatchOver => return,
+    }
+
+    // Killed players respawn in capture the flag matches, where the rounds are won by capturing
+    // flags instead.
+    if match_settings.mode == MatchMode::CaptureTheFlag {
+        return;
+    }
+
+    for (ent, player_idx) in entities.iter_with(&player_indexes) {
+        if players_killed.contains(ent) {
+            match_score.eliminated[player_idx.0] = true;
+        }
+    }
+
+    let active = (0..MAX_PLAYERS)
+        .filter(|&i| player_inputs.players[i].active)
+        .collect::<Vec<_>>();
+    let alive = active
+        .iter()
+        .copied()
+        .filter(|&i| !match_score.eliminated[i])
+        .collect::<Vec<_>>();
+
+    // A round can't be won without opponents
+    let Some(&first) = active.first() else { return };
+    if active
+        .iter()
+        .all(|&i| is_same_side(&match_settings, first, i))
+    {
+        return;
+    }
+    let winner = match alive.first() {
+        Some(&winner)
+            if alive
+                .iter()
+                .all(|&i| is_same_side(&match_settings, winner, i)) =>
+        {
+            Some(winner)
+        }
+        Some(_) => return,
+        None => None,
+    };
+
+    let mut winners = [false; MAX_PLAYERS];
+    if let Some(winner) = winner {
+        for player in active {
+            winners[player] = is_same_side(&match_settings, winner, player);
+        }
+    }
+    debug!("Round {} won by {winner:?}", match_score.round);
+    match_score.end_round(winners, rounds_to_win);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn teammates_are_on_the_same_side() {
+        let mut match_settings = MatchSettings::default();
+        assert!(is_same_side(&match_settings, 1, 1));
+        assert!(!is_same_side(&match_settings, 0, 2));
+
+        match_settings.mode = MatchMode::Teams;
+        assert!(is_same_side(&match_settings, 0, 2));
+        assert!(!is_same_side(&match_settings, 0, 1));
+    }
+}
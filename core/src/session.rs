@@ -1,6 +1,6 @@
 //! [`CoreSession`] implementation: the entrypoint for using `jumpy_core`.
 
-use crate::prelude::*;
+use crate::{impl_system_param, prelude::*};
 
 /// Implementation of the Jumpy match session.
 ///
@@ -70,10 +70,12 @@ pub struct CoreSessionInfo {
     pub map_meta: MapMeta,
     /// The player selections.
     pub player_info: [Option<GameSessionPlayerInfo>; MAX_PLAYERS],
+    /// The settings for the match, such as which items are enabled.
+    pub match_settings: MatchSettings,
 }
 
 /// Info for a player in the [`CoreSessionInfo`] struct.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GameSessionPlayerInfo {
     /// The asset handle for the player skin.
     pub player: Handle<PlayerMeta>,
@@ -81,6 +83,9 @@ pub struct GameSessionPlayerInfo {
     pub hat: Option<Handle<HatMeta>>,
     /// Whether or not the player is an AI player.
     pub is_ai: bool,
+    /// How well the AI plays, if the player is an AI player.
+    #[serde(default)]
+    pub ai_difficulty: AiDifficulty,
 }
 
 impl CoreSession {
@@ -108,9 +113,19 @@ impl CoreSession {
         // Initialize bevy world resource with an empty bevy world
         session.world.init_resource::<BevyWorld>();
         // Set the map
+        let map_physics = info.map_meta.physics.clone();
         session
             .world
             .insert_resource(LoadedMap(Arc::new(info.map_meta)));
+        // Set the match settings
+        let seed = info
+            .match_settings
+            .seed
+            .unwrap_or(crate::random::DEFAULT_SEED);
+        session
+            .world
+            .insert_resource(crate::random::GlobalRng::with_seed(seed));
+        session.world.insert_resource(info.match_settings.clone());
 
         // Set player initial character selections
         let player_inputs = session.world.resource::<PlayerInputs>();
@@ -121,10 +136,13 @@ impl CoreSession {
                 player_inputs.players[i].selected_player = info.player;
                 player_inputs.players[i].selected_hat = info.hat;
                 player_inputs.players[i].is_ai = info.is_ai;
+                player_inputs.players[i].ai_difficulty = info.ai_difficulty;
             }
         }
 
-        session.set_metadata(info.meta);
+        let meta = crate::mutator::apply_meta_overrides(info.meta, &info.match_settings);
+        let meta = map_physics.apply_to_meta(meta);
+        session.set_metadata(meta);
 
         session
     }
@@ -200,6 +218,56 @@ impl CoreSession {
                 input.editor_input = None;
             }
         }
+
+        // Reload the map once the intermission between two rounds is over
+        if self
+            .world
+            .resource::<MatchScore>()
+            .borrow()
+            .is_next_round_due()
+        {
+            self.start_next_round();
+        }
+    }
+
+    /// Reload the map to play the current round of the match again, keeping the score of the
+    /// previous rounds.
+    pub fn restart_round(&mut self) {
+        self.reload_round(MatchScore::restart_round);
+    }
+
+    /// Reload the map for the next round of the match.
+    fn start_next_round(&mut self) {
+        self.reload_round(MatchScore::start_next_round);
+    }
+
+    /// Reload the map, keeping the score, the kill feed, the match statistics, the player inputs,
+    /// and the random number generator.
+    fn reload_round(&mut self, update_score: fn(&mut MatchScore)) {
+        let mut match_score = self.world.resource::<MatchScore>().borrow().clone();
+        let team_score = self.world.resource::<TeamScore>().borrow().clone();
+        let arcade_score = self.world.resource::<ArcadeScore>().borrow().clone();
+        let kill_feed = self.world.resource::<KillFeed>().borrow().clone();
+        let match_stats = self.world.resource::<MatchStats>().borrow().clone();
+        let player_inputs = self.world.resource::<PlayerInputs>().borrow().clone();
+        let rng = self
+            .world
+            .resource::<crate::random::GlobalRng>()
+            .borrow()
+            .clone();
+        let time_step = self.time_step;
+
+        *self = Self::new(self.info.clone());
+
+        update_score(&mut match_score);
+        self.world.insert_resource(match_score);
+        self.world.insert_resource(team_score);
+        self.world.insert_resource(arcade_score);
+        self.world.insert_resource(kill_feed);
+        self.world.insert_resource(match_stats);
+        self.world.insert_resource(player_inputs);
+        self.world.insert_resource(rng);
+        self.time_step = time_step;
     }
 
     /// Export the current map metadata by scanning the world entities. This means that the export
@@ -207,13 +275,20 @@ impl CoreSession {
     pub fn export_map(&self) -> MapMeta {
         let export_system =
             move |map_meta: Res<SpawnedMapMeta>,
+                  loaded_map: Res<LoadedMap>,
+                  map_stages: Res<MapStages>,
                   entities: Res<Entities>,
                   tile_layers: Comp<TileLayer>,
                   spawned_map_layer_metas: Comp<SpawnedMapLayerMeta>,
                   tile_collisions: Comp<TileCollisionKind>,
                   tiles: Comp<Tile>,
                   transforms: Comp<Transform>,
-                  element_handles: Comp<ElementHandle>| {
+                  element_handles: Comp<ElementHandle>,
+                  element_orientations: Comp<ElementOrientation>,
+                  spawn_points: Comp<SpawnPointMeta>,
+                  wire_anchors: Comp<WireAnchorMeta>,
+                  links: Comp<LinkMeta>,
+                  foreground_layers: Comp<ForegroundLayer>| {
                 let mut layers = map_meta
                     .layer_names
                     .iter()
@@ -222,9 +297,17 @@ impl CoreSession {
                         tilemap: default(),
                         tiles: default(),
                         elements: default(),
+                        foreground: default(),
                     })
                     .collect::<Vec<_>>();
 
+                // Export the foreground settings of the layers
+                for (_ent, (foreground, layer_meta)) in
+                    entities.iter_with((&foreground_layers, &spawned_map_layer_metas))
+                {
+                    layers[layer_meta.layer_idx].foreground = Some(foreground.meta);
+                }
+
                 // Export the tile layers
                 for (_ent, (tile_layer, layer_meta)) in
                     entities.iter_with((&tile_layers, &spawned_map_layer_metas))
@@ -259,26 +342,54 @@ impl CoreSession {
                 }
 
                 // Export the entity layers
-                for (_ent, (element_handle, transform, layer_meta)) in
+                for (ent, (element_handle, transform, layer_meta)) in
                     entities.iter_with((&element_handles, &transforms, &spawned_map_layer_metas))
                 {
                     let layer_idx = layer_meta.layer_idx;
                     let layer = &mut layers[layer_idx];
+                    let orientation = element_orientations.get(ent).copied().unwrap_or_default();
 
                     layer.elements.push(ElementSpawn {
                         pos: transform.translation.truncate(),
                         element: element_handle.0.clone(),
+                        flip_x: orientation.flip_x,
+                        flip_y: orientation.flip_y,
+                        rotation: orientation.rotation,
+                        spawn_point: spawn_points.get(ent).copied(),
+                        wire_anchor: wire_anchors.get(ent).copied(),
+                        link: links.get(ent).copied(),
                     });
                 }
 
+                // The hidden layers of multi-stage maps aren't spawned, so export them from the
+                // loaded map instead.
+                for layer in &mut layers {
+                    let Some(loaded_idx) = loaded_map.layers.iter().position(|x| x.id == layer.id)
+                        else { continue; };
+                    if map_stages.visible_layers.get(loaded_idx) == Some(&false) {
+                        let loaded_layer = &loaded_map.layers[loaded_idx];
+                        layer.tilemap = loaded_layer.tilemap.clone();
+                        layer.tiles = loaded_layer.tiles.clone();
+                        layer.elements = loaded_layer.elements.clone();
+                    }
+                }
+
                 // Return complete map metadata
                 Ok(MapMeta {
                     name: map_meta.name.to_string(),
+                    author: map_meta.author.to_string(),
+                    description: map_meta.description.to_string(),
+                    version: map_meta.version,
+                    recommended_players: map_meta.recommended_players,
                     background: (*map_meta.background).clone(),
                     background_color: map_meta.background_color,
                     grid_size: map_meta.grid_size,
                     tile_size: map_meta.tile_size,
                     layers,
+                    physics: map_meta.physics.clone(),
+                    stages: loaded_map.stages.clone(),
+                    coins: loaded_map.coins.clone(),
+                    lava: loaded_map.lava.clone(),
                 })
             };
 
@@ -296,4 +407,119 @@ impl CoreSession {
     pub fn restore(&mut self, world: &mut World) {
         std::mem::swap(&mut self.world, world)
     }
+
+    /// Compute a checksum of the world state.
+    ///
+    /// Only the positions and velocities of the entities are hashed, which is enough to tell when
+    /// two simulations have diverged. The [presentation-only][PresentationOnly] entities are left
+    /// out, because they depend on the [`PresentationSettings`] of each player.
+    pub fn checksum(&self) -> u64 {
+        let checksum_system = |entities: Res<Entities>,
+                               transforms: Comp<Transform>,
+                               bodies: Comp<KinematicBody>,
+                               presentation_only: PresentationOnly| {
+            let mut hash = FNV_OFFSET_BASIS;
+            for (entity, transform) in entities.iter_with(&transforms) {
+                if presentation_only.contains(entity) {
+                    continue;
+                }
+                hash_entity_state(&mut hash, entity, transform, bodies.get(entity));
+            }
+
+            Ok(hash)
+        };
+
+        self.world.run_initialized_system(checksum_system).unwrap()
+    }
+
+    /// Get a text dump of the state hashed by the [`checksum`][Self::checksum], with one line per
+    /// entity including the hash of its state.
+    ///
+    /// The dumps of two simulations that have diverged can be compared with a diff tool to find the
+    /// entities that are out of sync.
+    pub fn state_dump(&self) -> String {
+        let dump_system = |entities: Res<Entities>,
+                           transforms: Comp<Transform>,
+                           bodies: Comp<KinematicBody>,
+                           presentation_only: PresentationOnly| {
+            let mut dump = String::new();
+            for (entity, transform) in entities.iter_with(&transforms) {
+                if presentation_only.contains(entity) {
+                    continue;
+                }
+                let body = bodies.get(entity);
+                let mut hash = FNV_OFFSET_BASIS;
+                hash_entity_state(&mut hash, entity, transform, body);
+
+                dump.push_str(&format!(
+                    "{}: {hash:016x} translation {:?} rotation {:?}",
+                    entity.index(),
+                    transform.translation.to_array(),
+                    transform.rotation.to_array(),
+                ));
+                if let Some(body) = body {
+                    dump.push_str(&format!(" velocity {:?}", body.velocity.to_array()));
+                }
+                dump.push('\n');
+            }
+
+            Ok(dump)
+        };
+
+        self.world.run_initialized_system(dump_system).unwrap()
+    }
+}
+
+impl_system_param! {
+    /// A system parameter telling which entities are only there for the presentation of the match.
+    ///
+    /// The camera, the parallax background, and the foreground layers are moved differently
+    /// depending on the [`PresentationSettings`] of each player, and nothing in the simulation
+    /// depends on them, so they are left out of the [checksum][CoreSession::checksum].
+    pub struct PresentationOnly<'a> {
+        cameras: Comp<'a, Camera>,
+        parallax_bg_sprites: Comp<'a, ParallaxBackgroundSprite>,
+        foreground_layers: Comp<'a, ForegroundLayer>,
+    }
+}
+
+impl<'a> PresentationOnly<'a> {
+    /// Whether the entity is only there for the presentation of the match.
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.cameras.get(entity).is_some()
+            || self.parallax_bg_sprites.get(entity).is_some()
+            || self.foreground_layers.get(entity).is_some()
+    }
+}
+
+/// The initial value of the FNV-1a hash used for the world checksum.
+///
+/// FNV-1a is used because unlike the std hasher, it is stable across Rust versions.
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// Add the state of an entity that is part of the world checksum to a FNV-1a hash.
+fn hash_entity_state(
+    hash: &mut u64,
+    entity: Entity,
+    transform: &Transform,
+    body: Option<&KinematicBody>,
+) {
+    let mut write = |value: u32| {
+        for byte in value.to_le_bytes() {
+            *hash ^= byte as u64;
+            *hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    };
+
+    write(entity.index());
+    for value in transform.translation.to_array() {
+        write(value.to_bits());
+    }
+    for value in transform.rotation.to_array() {
+        write(value.to_bits());
+    }
+    if let Some(body) = body {
+        write(body.velocity.x.to_bits());
+        write(body.velocity.y.to_bits());
+    }
 }
@@ -0,0 +1,217 @@
+//! Sudden death.
+//!
+//! When [`MatchSettings::time_limit`] is set in a match played over multiple rounds, each round
+//! lasts that long before sudden death starts. Walls of [damage regions][DamageRegion] then close
+//! in from the left and right edges of the map, and the first death ends the round: the players
+//! left alive that aren't on the side of a killed player win it, and the round is a draw if there
+//! are none.
+
+use std::time::Duration;
+
+use crate::prelude::*;
+
+/// Install this module.
+///
+/// This must be installed after the [`rounds`][crate::rounds], so that the players killed on this
+/// frame are already eliminated.
+pub fn install(session: &mut CoreSession) {
+    session.world.init_resource::<SuddenDeath>();
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PostUpdate, update_sudden_death);
+}
+
+/// The Z depth of the walls, in front of the map and the players.
+const WALL_Z: f32 = 500.0;
+/// How far past the edges of the map the walls reach, so that the outer edges of the walls are
+/// never in view.
+const WALL_DEPTH: f32 = 500.0;
+
+/// Resource containing the state of sudden death in the current round.
+#[derive(Clone, Debug, Default, TypeUlid)]
+#[ulid = "01H35D2K8QX4MZ7RVN3TWB9JCE"]
+pub struct SuddenDeath {
+    /// The time left until sudden death starts, if the round has a time limit.
+    ///
+    /// This is [`Duration::ZERO`] once sudden death has started.
+    pub remaining: Option<Duration>,
+    /// Whether each player was alive when sudden death started.
+    contenders: [bool; MAX_PLAYERS],
+    /// The left and right wall entities, once sudden death has started.
+    walls: Option<(Entity, Entity)>,
+}
+
+impl SuddenDeath {
+    /// Whether sudden death has started.
+    pub fn is_active(&self) -> bool {
+        self.remaining == Some(Duration::ZERO)
+    }
+}
+
+/// Get how far into the map, in pixels, each wall reaches after sudden death has been going on for
+/// `elapsed` seconds.
+pub fn wall_coverage(meta: &SuddenDeathMeta, map_width: f32, elapsed: f32) -> f32 {
+    (elapsed * meta.close_speed).min(map_width * meta.max_coverage)
+}
+
+/// Get the winners of a round in sudden death, once any of the contenders was eliminated.
+///
+/// Returns `None` while none of the contenders were eliminated.
+pub fn sudden_death_winners(
+    match_settings: &MatchSettings,
+    contenders: &[bool; MAX_PLAYERS],
+    eliminated: &[bool; MAX_PLAYERS],
+) -> Option<[bool; MAX_PLAYERS]> {
+    let losers = (0..MAX_PLAYERS)
+        .filter(|&i| contenders[i] && eliminated[i])
+        .collect::<Vec<_>>();
+    if losers.is_empty() {
+        return None;
+    }
+
+    let mut winners = [false; MAX_PLAYERS];
+    for (i, won) in winners.iter_mut().enumerate() {
+        *won = contenders[i]
+            && !eliminated[i]
+            && !losers
+                .iter()
+                .any(|&loser| is_same_side(match_settings, i, loser));
+    }
+    Some(winners)
+}
+
+/// Count down to sudden death, close the walls in, and end the round on the first death.
+fn update_sudden_death(
+    game_meta: Res<CoreMetaArc>,
+    match_settings: Res<MatchSettings>,
+    map: Res<LoadedMap>,
+    time: Res<Time>,
+    player_inputs: Res<PlayerInputs>,
+    mut entities: ResMut<Entities>,
+    mut sudden_death: ResMut<SuddenDeath>,
+    mut match_score: ResMut<MatchScore>,
+    mut sprites: CompMut<Sprite>,
+    mut transforms: CompMut<Transform>,
+    mut damage_regions: CompMut<DamageRegion>,
+) {
+    let (Some(rounds_to_win), Some(time_limit)) =
+        (match_settings.rounds_to_win, match_settings.time_limit)
+    else {
+        return;
+    };
+    if match_score.state != RoundState::Playing {
+        return;
+    }
+
+    let time_limit = Duration::from_secs_f32(time_limit);
+    let elapsed = time.elapsed();
+    let was_active = sudden_death.is_active();
+    sudden_death.remaining = Some(time_limit.saturating_sub(elapsed));
+    if !sudden_death.is_active() {
+        return;
+    }
+
+    if !was_active {
+        debug!("Sudden death in round {}", match_score.round);
+        for (i, contender) in sudden_death.contenders.iter_mut().enumerate() {
+            *contender = player_inputs.players[i].active && !match_score.is_eliminated(i);
+        }
+    }
+
+    // Close the walls in from the edges of the map
+    let meta = &game_meta.sudden_death;
+    let map_size = map.grid_size.as_vec2() * map.tile_size;
+    let coverage = wall_coverage(meta, map_size.x, (elapsed - time_limit).as_secs_f32());
+    let (left_wall, right_wall) = *sudden_death.walls.get_or_insert_with(|| {
+        let mut spawn_wall = || {
+            let wall = entities.create();
+            sprites.insert(
+                wall,
+                Sprite {
+                    image: meta.image.clone(),
+                    ..default()
+                },
+            );
+            damage_regions.insert(wall, default());
+            wall
+        };
+        (spawn_wall(), spawn_wall())
+    });
+    let wall_size = Vec2::new(coverage + WALL_DEPTH, map_size.y + 2.0 * WALL_DEPTH);
+    for (wall, x) in [
+        (left_wall, coverage - wall_size.x / 2.0),
+        (right_wall, map_size.x - coverage + wall_size.x / 2.0),
+    ] {
+        transforms.insert(
+            wall,
+            Transform {
+                translation: Vec3::new(x, map_size.y / 2.0, WALL_Z),
+                scale: wall_size.extend(1.0) / meta.image_size.extend(1.0),
+                ..default()
+            },
+        );
+        if let Some(damage_region) = damage_regions.get_mut(wall) {
+            damage_region.size = wall_size;
+        }
+    }
+
+    // The first death ends the round
+    let mut eliminated = [false; MAX_PLAYERS];
+    for (i, eliminated) in eliminated.iter_mut().enumerate() {
+        *eliminated = match_score.is_eliminated(i);
+    }
+    if let Some(winners) =
+        sudden_death_winners(&match_settings, &sudden_death.contenders, &eliminated)
+    {
+        debug!("Round {} ended by sudden death", match_score.round);
+        match_score.end_round(winners, rounds_to_win);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn the_first_death_ends_the_round() {
+        let mut match_settings = MatchSettings::default();
+        let contenders = [true, true, true, false];
+
+        assert_eq!(
+            sudden_death_winners(&match_settings, &contenders, &[false; MAX_PLAYERS]),
+            None
+        );
+        // Players that were eliminated before sudden death don't count
+        assert_eq!(
+            sudden_death_winners(&match_settings, &contenders, &[false, false, false, true]),
+            None
+        );
+        assert_eq!(
+            sudden_death_winners(&match_settings, &contenders, &[false, true, false, false]),
+            Some([true, false, true, false])
+        );
+
+        // The teammates of the killed player lose with them
+        match_settings.mode = MatchMode::Teams;
+        assert_eq!(
+            sudden_death_winners(&match_settings, &contenders, &[false, true, false, false]),
+            Some([true, false, true, false])
+        );
+        assert_eq!(
+            sudden_death_winners(&match_settings, &contenders, &[true, false, false, false]),
+            Some([false, true, false, false])
+        );
+    }
+
+    #[test]
+    fn walls_stop_closing_in() {
+        let meta = SuddenDeathMeta {
+            close_speed: 10.0,
+            max_coverage: 0.4,
+            ..default()
+        };
+        assert_eq!(wall_coverage(&meta, 1000.0, 0.0), 0.0);
+        assert_eq!(wall_coverage(&meta, 1000.0, 5.0), 50.0);
+        assert_eq!(wall_coverage(&meta, 1000.0, 100.0), 400.0);
+    }
+}
@@ -0,0 +1,190 @@
+//! Entity tags.
+//!
+//! [`Tags`] put entities into broad classes, such as hazards or projectiles, so that systems like
+//! the AI, the camera, or cleanup policies can find every entity of a class without knowing each
+//! of the components that make it one.
+//!
+//! Tags are added automatically at the start of every frame to entities that don't have any yet:
+//! players, items, bullets, and damage regions get their builtin tags, and map elements get the
+//! [`tags`][ElementMeta::tags] from their metadata. They can also be inserted by hand.
+//!
+//! The [`TagIndex`] resource lists the entities with each tag, and is rebuilt once those tags have
+//! been added, before the elements are updated. It doesn't include entities spawned later in the
+//! same frame.
+
+use crate::prelude::*;
+
+pub fn install(session: &mut CoreSession) {
+    session.world.init_resource::<TagIndex>();
+    session
+        .stages
+        .add_system_to_stage(CoreStage::First, tag_entities)
+        .add_system_to_stage(CoreStage::First, update_tag_index);
+}
+
+/// A class of entities.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[repr(u8)]
+pub enum Tag {
+    /// A player.
+    Player,
+    /// An item that can be picked up.
+    Item,
+    /// A projectile, such as a bullet.
+    Projectile,
+    /// Something that hurts players.
+    Hazard,
+    /// Something that explodes.
+    Explosive,
+    /// A critter roaming the map.
+    Critter,
+    /// A purely visual element.
+    Decoration,
+}
+
+impl Tag {
+    /// The number of different tags.
+    pub const COUNT: usize = 7;
+
+    /// All of the tags, in order.
+    pub const ALL: [Tag; Tag::COUNT] = [
+        Tag::Player,
+        Tag::Item,
+        Tag::Projectile,
+        Tag::Hazard,
+        Tag::Explosive,
+        Tag::Critter,
+        Tag::Decoration,
+    ];
+
+    fn bit(self) -> u32 {
+        1 << self as u8
+    }
+}
+
+/// The set of [`Tag`]s of an entity.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, TypeUlid)]
+#[ulid = "01H2GD58QWSR4D6SMSAYJVA216"]
+pub struct Tags(u32);
+
+impl Tags {
+    /// Create a set containing the given tags.
+    pub fn new(tags: impl IntoIterator<Item = Tag>) -> Self {
+        let mut set = Self::default();
+        for tag in tags {
+            set.insert(tag);
+        }
+        set
+    }
+
+    /// Add a tag to the set.
+    pub fn insert(&mut self, tag: Tag) {
+        self.0 |= tag.bit();
+    }
+
+    /// Remove a tag from the set.
+    pub fn remove(&mut self, tag: Tag) {
+        self.0 &= !tag.bit();
+    }
+
+    /// Whether or not the set contains the given tag.
+    pub fn contains(&self, tag: Tag) -> bool {
+        self.0 & tag.bit() != 0
+    }
+
+    /// Whether or not the set contains no tags.
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Iterate over the tags in the set.
+    pub fn iter(&self) -> impl Iterator<Item = Tag> + '_ {
+        Tag::ALL.into_iter().filter(|tag| self.contains(*tag))
+    }
+}
+
+/// Resource listing the entities with each [`Tag`].
+#[derive(Clone, Debug, Default, TypeUlid)]
+#[ulid = "01H2G3GC8P64KWVE680V2ATS75"]
+pub struct TagIndex {
+    entities: [Vec<Entity>; Tag::COUNT],
+}
+
+impl TagIndex {
+    /// Iterate over the entities with the given tag.
+    ///
+    /// Entities may have been killed since the index was built, so systems that kill entities
+    /// should check that they are still alive.
+    pub fn iter(&self, tag: Tag) -> impl Iterator<Item = Entity> + '_ {
+        self.entities[tag as usize].iter().copied()
+    }
+
+    /// Get the number of entities with the given tag.
+    pub fn count(&self, tag: Tag) -> usize {
+        self.entities[tag as usize].len()
+    }
+}
+
+/// Add the builtin and metadata tags to entities that don't have any tags yet.
+fn tag_entities(
+    entities: Res<Entities>,
+    player_indexes: Comp<PlayerIdx>,
+    items: Comp<Item>,
+    bullets: Comp<Bullet>,
+    damage_regions: Comp<DamageRegion>,
+    spawners: Comp<Spawner>,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut tags: CompMut<Tags>,
+) {
+    let mut bitset = player_indexes.bitset().clone();
+    bitset.bit_or(items.bitset());
+    bitset.bit_or(bullets.bitset());
+    bitset.bit_or(damage_regions.bitset());
+    bitset.bit_or(element_handles.bitset());
+    bitset.bit_andnot(tags.bitset());
+
+    for entity in entities.iter_with_bitset(&bitset) {
+        let mut entity_tags = Tags::default();
+        if player_indexes.contains(entity) {
+            entity_tags.insert(Tag::Player);
+        }
+        if items.contains(entity) {
+            entity_tags.insert(Tag::Item);
+        }
+        if bullets.contains(entity) {
+            entity_tags.insert(Tag::Projectile);
+        }
+        if damage_regions.contains(entity) {
+            entity_tags.insert(Tag::Hazard);
+        }
+        // Spawners aren't the elements themselves, they only keep track of them
+        if !spawners.contains(entity) {
+            if let Some(element_meta) = element_handles
+                .get(entity)
+                .and_then(|handle| element_assets.get(&handle.get_bevy_handle()))
+            {
+                for tag in &element_meta.tags {
+                    entity_tags.insert(*tag);
+                }
+            }
+        }
+
+        if !entity_tags.is_empty() {
+            tags.insert(entity, entity_tags);
+        }
+    }
+}
+
+/// Rebuild the [`TagIndex`].
+fn update_tag_index(entities: Res<Entities>, tags: Comp<Tags>, mut tag_index: ResMut<TagIndex>) {
+    for list in &mut tag_index.entities {
+        list.clear();
+    }
+    for (entity, entity_tags) in entities.iter_with(&tags) {
+        for tag in entity_tags.iter() {
+            tag_index.entities[tag as usize].push(entity);
+        }
+    }
+}
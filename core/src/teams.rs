@@ -0,0 +1,174 @@
+//! Team matches.
+//!
+//! When [`MatchSettings::mode`] is [`MatchMode::Teams`] or [`MatchMode::CaptureTheFlag`], the
+//! players are split into [`TEAM_COUNT`] teams by [`MatchSettings::team_of()`]. Each player gets an
+//! outline of their team's color, players can't hurt their teammates unless
+//! [`MatchSettings::friendly_fire`] is enabled, and the [`TeamScore`] counts the kills of each
+//! team.
+
+use crate::prelude::*;
+
+/// Install this module.
+pub fn install(session: &mut CoreSession) {
+    session.world.init_resource::<TeamScore>();
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PostUpdate, update_team_score)
+        .add_system_to_stage(CoreStage::Last, update_team_outlines);
+}
+
+/// The number of teams in a team match.
+pub const TEAM_COUNT: usize = 2;
+
+/// The colors of the teams.
+pub const TEAM_COLORS: [Color; TEAM_COUNT] = [
+    Color::rgba(0.9, 0.2, 0.2, 0.8),
+    Color::rgba(0.2, 0.4, 0.9, 0.8),
+];
+
+/// How much bigger than the player their team outline is.
+const TEAM_OUTLINE_SCALE: f32 = 1.15;
+/// The depth of the team outline relative to the player.
+const TEAM_OUTLINE_Z_OFFSET: f32 = -0.01;
+
+/// How a match is played.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum MatchMode {
+    /// Every player plays for themselves.
+    #[default]
+    FreeForAll,
+    /// The players are split into teams.
+    Teams,
+    /// The players are split into teams, that score by capturing the flag of the other team.
+    ///
+    /// See the [`ctf`][crate::gamemode::ctf] module.
+    CaptureTheFlag,
+}
+
+/// Resource containing the score of each team in a team match.
+///
+/// Every time a player is killed, each of the other teams scores a point.
+#[derive(Clone, Debug, Default, TypeUlid)]
+#[ulid = "01H2GW8D3PKT5Q6ZR1XMEN7BVA"]
+pub struct TeamScore {
+    /// The score of each team.
+    pub scores: [u32; TEAM_COUNT],
+    /// Whether or not each player was dead on the last frame.
+    was_killed: [bool; MAX_PLAYERS],
+}
+
+/// Component containing the entity of a player's team outline.
+#[derive(Clone, Copy, Debug, TypeUlid)]
+#[ulid = "01H2GW8KJ9A4XN2FC6TZBY0RSE"]
+pub struct TeamOutline(pub Entity);
+
+/// Whether the `attacker` may not hurt the `victim`, because they are teammates and friendly fire
+/// is disabled.
+pub fn is_protected_teammate(
+    match_settings: &MatchSettings,
+    player_indexes: &Comp<PlayerIdx>,
+    attacker: Entity,
+    victim: Entity,
+) -> bool {
+    if match_settings.friendly_fire || attacker == victim {
+        return false;
+    }
+    let team = |ent| {
+        player_indexes
+            .get(ent)
+            .and_then(|idx| match_settings.team_of(idx.0))
+    };
+
+    matches!((team(attacker), team(victim)), (Some(a), Some(b)) if a == b)
+}
+
+/// Give points to the other teams when a player is killed.
+fn update_team_score(
+    entities: Res<Entities>,
+    match_settings: Res<MatchSettings>,
+    player_inputs: Res<PlayerInputs>,
+    player_indexes: Comp<PlayerIdx>,
+    players_killed: Comp<PlayerKilled>,
+    mut team_score: ResMut<TeamScore>,
+) {
+    let mut killed = [false; MAX_PLAYERS];
+    for (ent, player_idx) in entities.iter_with(&player_indexes) {
+        killed[player_idx.0] = players_killed.contains(ent);
+    }
+
+    for (player, &killed) in killed.iter().enumerate() {
+        let was_killed = std::mem::replace(&mut team_score.was_killed[player], killed);
+        if !killed || was_killed {
+            continue;
+        }
+        let Some(team) = match_settings.team_of(player) else { continue };
+
+        // Only teams with players in the match score
+        let mut scoring_teams = [false; TEAM_COUNT];
+        for (other, input) in player_inputs.players.iter().enumerate() {
+            if let Some(other_team) = match_settings.team_of(other) {
+                scoring_teams[other_team as usize] |= input.active && other_team != team;
+            }
+        }
+        for (score, scores) in team_score.scores.iter_mut().zip(scoring_teams) {
+            if scores {
+                *score += 1;
+            }
+        }
+    }
+}
+
+/// Give the players of a team match an outline of their team's color.
+///
+/// The outline is a bigger copy of the player's body behind them, attached to the player, so it is
+/// despawned along with them.
+fn update_team_outlines(
+    mut entities: ResMut<Entities>,
+    match_settings: Res<MatchSettings>,
+    player_indexes: Comp<PlayerIdx>,
+    player_states: Comp<PlayerState>,
+    mut team_outlines: CompMut<TeamOutline>,
+    mut attachments: CompMut<Attachment>,
+    mut atlas_sprites: CompMut<AtlasSprite>,
+    mut transforms: CompMut<Transform>,
+) {
+    // Only hydrated players have a body sprite
+    let mut new_players = Vec::new();
+    for (ent, (player_idx, _state)) in entities.iter_with((&player_indexes, &player_states)) {
+        if let Some(team) = match_settings.team_of(player_idx.0) {
+            if !team_outlines.contains(ent) {
+                new_players.push((ent, team));
+            }
+        }
+    }
+
+    for (player_ent, team) in new_players {
+        let Some(body_sprite) = atlas_sprites.get(player_ent) else { continue };
+        let outline_sprite = AtlasSprite {
+            atlas: body_sprite.atlas.clone(),
+            color: TEAM_COLORS[team as usize],
+            ..default()
+        };
+
+        let outline_ent = entities.create();
+        atlas_sprites.insert(outline_ent, outline_sprite);
+        transforms.insert(outline_ent, default());
+        attachments.insert(
+            outline_ent,
+            Attachment {
+                entity: player_ent,
+                offset: Vec3::new(0.0, 0.0, TEAM_OUTLINE_Z_OFFSET),
+                sync_animation: true,
+                sync_color: false,
+            },
+        );
+        team_outlines.insert(player_ent, TeamOutline(outline_ent));
+    }
+
+    for (player_ent, outline) in entities.iter_with(&team_outlines) {
+        let Some(player_scale) = transforms.get(player_ent).map(|x| x.scale) else { continue };
+        if let Some(transform) = transforms.get_mut(outline.0) {
+            transform.scale = player_scale * TEAM_OUTLINE_SCALE;
+        }
+    }
+}
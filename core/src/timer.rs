@@ -0,0 +1,91 @@
+//! Timers and cooldowns ticked by the simulation.
+//!
+//! Instead of ticking a [`Timer`] of their own, systems can add a [`StepTimer`] or a [`Cooldown`]
+//! component to an entity. They are all ticked by the session's step time at the start of every
+//! frame, so they stay in sync with each other, and are rolled back with the rest of the world.
+
+use std::time::Duration;
+
+use crate::prelude::*;
+
+pub fn install(session: &mut CoreSession) {
+    session
+        .stages
+        .add_system_to_stage(CoreStage::First, tick_timers);
+}
+
+/// A timer component, ticked every frame.
+///
+/// This is named differently from [`Timer`], which it wraps, so that it can be used from the
+/// prelude without ambiguity.
+#[derive(Clone, TypeUlid, Debug, Default, Deref, DerefMut)]
+#[ulid = "01H2F7K3XMB5RWQ9ZTN6E8JD4C"]
+pub struct StepTimer(pub Timer);
+
+impl StepTimer {
+    /// Create a timer that finishes once after `duration`.
+    pub fn once(duration: Duration) -> Self {
+        Self(Timer::new(duration, TimerMode::Once))
+    }
+
+    /// Create a timer that finishes every `duration`.
+    pub fn repeating(duration: Duration) -> Self {
+        Self(Timer::new(duration, TimerMode::Repeating))
+    }
+}
+
+/// A cooldown component, preventing an action from being repeated until it has elapsed.
+#[derive(Clone, Copy, TypeUlid, Debug, Default)]
+#[ulid = "01H2F7KB4VN8QC1SGYH0DW6PXR"]
+pub struct Cooldown {
+    remaining: Duration,
+}
+
+impl Cooldown {
+    /// Create a cooldown that becomes ready after `duration`.
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            remaining: duration,
+        }
+    }
+
+    /// Whether or not the cooldown has elapsed.
+    pub fn is_ready(&self) -> bool {
+        self.remaining.is_zero()
+    }
+
+    /// The time left until the cooldown is ready.
+    pub fn remaining(&self) -> Duration {
+        self.remaining
+    }
+
+    /// Restart the cooldown, so that it becomes ready after `duration`.
+    pub fn start(&mut self, duration: Duration) {
+        self.remaining = duration;
+    }
+
+    /// If the cooldown is ready, restart it with `duration` and return `true`.
+    pub fn try_start(&mut self, duration: Duration) -> bool {
+        let ready = self.is_ready();
+        if ready {
+            self.start(duration);
+        }
+        ready
+    }
+}
+
+/// Tick all of the [`StepTimer`]s and [`Cooldown`]s.
+fn tick_timers(
+    entities: Res<Entities>,
+    time: Res<Time>,
+    mut timers: CompMut<StepTimer>,
+    mut cooldowns: CompMut<Cooldown>,
+) {
+    let delta = time.delta();
+    for (_ent, timer) in entities.iter_with(&mut timers) {
+        timer.tick(delta);
+    }
+    for (_ent, cooldown) in entities.iter_with(&mut cooldowns) {
+        cooldown.remaining = cooldown.remaining.saturating_sub(delta);
+    }
+}
@@ -84,6 +84,32 @@ impl Rect {
             && point.y <= self.top()
     }
 
+    /// Whether the line segment from `start` to `end` crosses or touches the rectangle.
+    pub fn intersects_segment(&self, start: Vec2, end: Vec2) -> bool {
+        let delta = end - start;
+        let mut t_min = 0.0f32;
+        let mut t_max = 1.0f32;
+        for (start, delta, min, max) in [
+            (start.x, delta.x, self.min.x, self.max.x),
+            (start.y, delta.y, self.min.y, self.max.y),
+        ] {
+            if delta == 0.0 {
+                if start < min || start > max {
+                    return false;
+                }
+                continue;
+            }
+            let t1 = (min - start) / delta;
+            let t2 = (max - start) / delta;
+            t_min = t_min.max(t1.min(t2));
+            t_max = t_max.min(t1.max(t2));
+            if t_min > t_max {
+                return false;
+            }
+        }
+        true
+    }
+
     #[inline]
     pub fn center(&self) -> Vec2 {
         let half_size = self.size() / 2.0;
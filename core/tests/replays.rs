@@ -0,0 +1,182 @@
+//! Re-simulates the replays in `tests/replays`, and checks that they still end in the same state.
+//!
+//! A failure means that gameplay has changed. If that is intended, bump
+//! [`REPLAY_VERSION`][jumpy_core::replay::REPLAY_VERSION] and re-record the replays by running
+//! this test with the `JUMPY_RECORD_REPLAYS` environment variable set.
+
+use std::{path::PathBuf, sync::Arc};
+
+use bevy::prelude::*;
+use jumpy_core::{
+    metadata::{CoreMeta, ElementMeta, HatMeta, JumpyCoreAssetsPlugin, PlayerMeta},
+    presentation::PresentationSettings,
+    replay::{Replay, REPLAY_VERSION},
+    session::CoreSession,
+    MAX_PLAYERS,
+};
+
+const ASSET_FOLDER: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../assets");
+const REPLAY_FOLDER: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/replays");
+
+/// When this environment variable is set, the replays are re-recorded with the current simulation
+/// instead of being checked: their version and checksum are updated in place.
+const RECORD_VAR: &str = "JUMPY_RECORD_REPLAYS";
+
+/// The number of app updates to wait for the core assets to load.
+const MAX_LOAD_UPDATES: usize = 10_000;
+
+/// Get the paths of the replays to test.
+fn replay_paths() -> Vec<PathBuf> {
+    let mut paths = std::fs::read_dir(REPLAY_FOLDER)
+        .map(|dir| {
+            dir.flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.to_string_lossy().ends_with(".replay.yaml"))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    paths.sort();
+    paths
+}
+
+/// Load the core metadata and all of the assets it refers to, without rendering anything.
+fn load_core_meta(app: &mut App) -> Arc<CoreMeta> {
+    let handle: Handle<CoreMeta> = app
+        .world
+        .resource::<AssetServer>()
+        .load("default.core.yaml");
+
+    for _ in 0..MAX_LOAD_UPDATES {
+        app.update();
+
+        let world = &app.world;
+        let Some(core) = world.resource::<Assets<CoreMeta>>().get(&handle) else {
+            continue;
+        };
+        let players = world.resource::<Assets<PlayerMeta>>();
+        let hats = world.resource::<Assets<HatMeta>>();
+        let elements = world.resource::<Assets<ElementMeta>>();
+        let loaded = core
+            .players
+            .iter()
+            .all(|x| players.get(&x.get_bevy_handle()).is_some())
+            && core
+                .player_hats
+                .iter()
+                .all(|x| hats.get(&x.get_bevy_handle()).is_some())
+            && core
+                .map_elements
+                .iter()
+                .all(|x| elements.get(&x.get_bevy_handle()).is_some());
+
+        if loaded {
+            return Arc::new(core.clone());
+        }
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+
+    panic!("Timed out loading the core assets");
+}
+
+/// Create an app able to run the core sessions, and load the core metadata into it.
+fn load_app() -> (App, Arc<CoreMeta>) {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(AssetPlugin {
+            asset_folder: ASSET_FOLDER.into(),
+            ..default()
+        })
+        .add_plugin(JumpyCoreAssetsPlugin);
+    let core_meta = load_core_meta(&mut app);
+
+    (app, core_meta)
+}
+
+#[test]
+fn replays_resimulate_identically() {
+    let paths = replay_paths();
+    assert!(
+        !paths.is_empty(),
+        "No replays found in {REPLAY_FOLDER}, so the simulation isn't tested"
+    );
+
+    let (mut app, core_meta) = load_app();
+
+    let record = std::env::var_os(RECORD_VAR).is_some();
+    let mut failures = Vec::new();
+    for path in paths {
+        let mut replay: Replay = serde_yaml::from_str(&std::fs::read_to_string(&path).unwrap())
+            .unwrap_or_else(|e| panic!("Could not parse {}: {e}", path.display()));
+        if replay.version != REPLAY_VERSION && !record {
+            failures.push(format!(
+                "{}: recorded with version {}, re-record it",
+                path.display(),
+                replay.version
+            ));
+            continue;
+        }
+
+        let mut session = CoreSession::new(replay.session_info(core_meta.clone()));
+        let checksum = replay.play(&mut session, &mut app.world);
+        if record {
+            replay.version = REPLAY_VERSION;
+            replay.checksum = checksum;
+            std::fs::write(&path, serde_yaml::to_string(&replay).unwrap())
+                .unwrap_or_else(|e| panic!("Could not write {}: {e}", path.display()));
+            continue;
+        }
+        if checksum != replay.checksum {
+            failures.push(format!(
+                "{}: expected checksum {:x}, got {checksum:x}",
+                path.display(),
+                replay.checksum
+            ));
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "Replays don't re-simulate to the same state anymore, so gameplay has changed. If that is \
+        intended, bump REPLAY_VERSION and re-record them by running this test with {RECORD_VAR} \
+        set.\n{}",
+        failures.join("\n")
+    );
+}
+
+/// The presentation settings only change how the match looks, so they must not change the
+/// checksum, or network matches between players with different settings would desync.
+#[test]
+fn presentation_settings_dont_change_checksum() {
+    let path = replay_paths()
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| panic!("No replays found in {REPLAY_FOLDER}"));
+    let replay: Replay = serde_yaml::from_str(&std::fs::read_to_string(&path).unwrap())
+        .unwrap_or_else(|e| panic!("Could not parse {}: {e}", path.display()));
+    let (mut app, core_meta) = load_app();
+
+    let checksums = [
+        PresentationSettings::default(),
+        PresentationSettings {
+            reduced_motion: true,
+            high_contrast: true,
+            local_players: [true; MAX_PLAYERS],
+            hit_popups: true,
+        },
+    ]
+    .map(|settings| {
+        let mut session = CoreSession::new(replay.session_info(core_meta.clone()));
+        *session
+            .world
+            .resource::<PresentationSettings>()
+            .borrow_mut() = settings;
+        replay.play(&mut session, &mut app.world)
+    });
+
+    assert_eq!(
+        checksums[0],
+        checksums[1],
+        "The presentation settings changed the checksum of {}",
+        path.display()
+    );
+}
@@ -105,15 +105,18 @@ fn load(
                 player: meta.players[0].clone(),
                 hat: Some(meta.player_hats[0].clone()),
                 is_ai: false,
+                ai_difficulty: default(),
             }),
             Some(GameSessionPlayerInfo {
                 player: meta.players[0].clone(),
                 hat: Some(meta.player_hats[1].clone()),
                 is_ai: true,
+                ai_difficulty: default(),
             }),
             None,
             None,
         ],
+        match_settings: default(),
         meta: Arc::new(meta),
     });
 
@@ -0,0 +1,153 @@
+//! Text-to-speech announcements, for low-vision players.
+//!
+//! When [`Settings::text_to_speech`] is enabled, the [`Announcer`] reads aloud the menu items as
+//! they are focused, countdowns, and the players killed during a match. Anything else can be read
+//! by pushing it to the [`Announcer`] queue.
+//!
+//! Speech needs the `text-to-speech` cargo feature, which uses the platform's speech synthesis.
+//! Without it, announcements are only logged.
+
+use std::collections::VecDeque;
+
+use bevy_egui::EguiContexts;
+use bevy_fluent::Localization;
+use jumpy_core::player::{PlayerIdx, PlayerKilled};
+
+use crate::prelude::*;
+
+/// Announcer plugin.
+pub struct JumpyAnnouncerPlugin;
+
+impl Plugin for JumpyAnnouncerPlugin {
+    fn build(&self, app: &mut App) {
+        #[cfg(feature = "text-to-speech")]
+        app.insert_non_send_resource(TtsBackend(
+            tts::Tts::default()
+                .map_err(|e| warn!("Could not start text-to-speech: {e}"))
+                .ok(),
+        ));
+
+        app.init_resource::<Announcer>()
+            .add_system(update_announcer_settings.in_base_set(CoreSet::PreUpdate))
+            .add_system(
+                announce_kills
+                    .run_if(in_state(EngineState::InGame))
+                    .run_if(resource_exists::<Session>()),
+            )
+            .add_systems(
+                (announce_menu_focus, speak_announcements)
+                    .chain()
+                    .in_base_set(CoreSet::PostUpdate)
+                    .before(bevy_egui::EguiSet::ProcessOutput),
+            );
+    }
+}
+
+/// Resource containing the queue of text to read aloud.
+#[derive(Resource, Default)]
+pub struct Announcer {
+    /// Whether or not announcements are enabled in the settings.
+    pub enabled: bool,
+    queue: VecDeque<String>,
+}
+
+impl Announcer {
+    /// Queue text to be read aloud, if announcements are enabled.
+    pub fn announce(&mut self, text: impl Into<String>) {
+        if self.enabled {
+            self.queue.push_back(text.into());
+        }
+    }
+}
+
+/// Non-send resource containing the speech synthesizer, or `None` if it couldn't be started.
+#[cfg(feature = "text-to-speech")]
+struct TtsBackend(Option<tts::Tts>);
+
+/// Enable or disable the [`Announcer`] when the settings change.
+fn update_announcer_settings(
+    game: Option<Res<GameMeta>>,
+    mut storage: ResMut<Storage>,
+    mut announcer: ResMut<Announcer>,
+) {
+    let Some(game) = game else { return };
+    if !storage.is_changed() && !game.is_changed() {
+        return;
+    }
+
+    // Reading the settings needs mutable access, but doesn't change the storage.
+    let storage = storage.bypass_change_detection();
+    announcer.enabled = Settings::get_stored_or_default(&game, storage).text_to_speech;
+    if !announcer.enabled {
+        announcer.queue.clear();
+    }
+}
+
+/// Announce the menu items when they gain focus.
+fn announce_menu_focus(mut contexts: EguiContexts, mut announcer: ResMut<Announcer>) {
+    if !announcer.enabled {
+        return;
+    }
+
+    let events = contexts.ctx_mut().output(|o| o.events.clone());
+    for event in events {
+        if let egui::output::OutputEvent::FocusGained(info) = event {
+            if let Some(label) = info.label {
+                announcer.announce(label);
+            }
+        }
+    }
+}
+
+/// Announce the players that are killed during a match.
+fn announce_kills(
+    mut session: ResMut<Session>,
+    localization: Res<Localization>,
+    mut announcer: ResMut<Announcer>,
+    mut was_killed: Local<[bool; MAX_PLAYERS]>,
+) {
+    if !announcer.enabled {
+        return;
+    }
+
+    let killed = session
+        .world()
+        .run_initialized_system(
+            |entities: bones::Res<bones::Entities>,
+             player_indexes: bones::Comp<PlayerIdx>,
+             players_killed: bones::Comp<PlayerKilled>| {
+                let mut killed = [false; MAX_PLAYERS];
+                for (ent, player_idx) in entities.iter_with(&player_indexes) {
+                    killed[player_idx.0] = players_killed.contains(ent);
+                }
+                Ok(killed)
+            },
+        )
+        .unwrap();
+
+    for (player, (killed, was_killed)) in killed.iter().zip(was_killed.iter_mut()).enumerate() {
+        if *killed && !*was_killed {
+            announcer.announce(
+                localization.get(&format!("announce-player-killed?player={}", player + 1)),
+            );
+        }
+        *was_killed = *killed;
+    }
+}
+
+/// Read the queued announcements aloud.
+fn speak_announcements(
+    mut announcer: ResMut<Announcer>,
+    #[cfg(feature = "text-to-speech")] mut backend: NonSendMut<TtsBackend>,
+) {
+    for text in announcer.queue.drain(..) {
+        debug!("Announcing: {text}");
+
+        #[cfg(feature = "text-to-speech")]
+        if let Some(tts) = &mut backend.0 {
+            if let Err(e) = tts.speak(text, false) {
+                warn!("Could not read announcement aloud: {e}");
+            }
+        }
+    }
+}
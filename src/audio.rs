@@ -4,7 +4,8 @@
 //! and installs our [`MusicChannel`] and [`EffectsChannel`] for playing music and sound effects.
 //!
 //! Also in this module is the [`music_system`] which handles playing the right music in different
-//! game states.
+//! game states. During matches, the calm and intense stems of layered songs are crossfaded
+//! according to the [`MusicIntensity`], which is updated from the state of the match.
 //!
 //! Game sounds are _not_ handled here. Game sounds events are created in
 //! [`jumpy_core`][::jumpy_core] and then processed and sent to the effects channel by the
@@ -14,12 +15,18 @@
 
 use std::time::Duration;
 
+use jumpy_core::player::{PlayerIdx, PlayerKilled};
+
 use bevy_kira_audio::{
     AudioApp, AudioChannel, AudioControl, AudioInstance, AudioSource, PlaybackState,
 };
 use rand::{seq::SliceRandom, thread_rng};
 
-use crate::{main_menu::MenuPage, metadata::GameMeta, prelude::*};
+use crate::{
+    main_menu::MenuPage,
+    metadata::{FightMusicMeta, GameMeta},
+    prelude::*,
+};
 
 /// Audio & Music plugin.
 pub struct JumpyAudioPlugin;
@@ -29,9 +36,17 @@ impl Plugin for JumpyAudioPlugin {
         app.add_plugin(bevy_kira_audio::AudioPlugin)
             .init_resource::<MusicState>()
             .init_resource::<ShuffledPlaylist>()
+            .init_resource::<MusicIntensity>()
             .add_audio_channel::<MusicChannel>()
             .add_audio_channel::<EffectsChannel>()
             .add_startup_system(setup_audio_defaults)
+            .add_system(
+                update_music_intensity
+                    .run_if(in_state(EngineState::InGame))
+                    .run_if(resource_exists::<Session>())
+                    .before(music_system),
+            )
+            .add_system(reset_music_intensity.in_schedule(OnExit(EngineState::InGame)))
             .add_system(music_system.run_if(resource_exists::<GameMeta>()));
     }
 }
@@ -60,6 +75,8 @@ pub enum MusicState {
     Fight {
         /// The handle to the audio instance.
         instance: Handle<AudioInstance>,
+        /// The handle to the audio instance of the intense stem, if the song is layered.
+        intense: Option<Handle<AudioInstance>>,
         /// The index of the song in the shuffled playlist.
         idx: usize,
     },
@@ -76,11 +93,46 @@ impl MusicState {
             MusicState::Fight { instance, .. } => Some(instance),
         }
     }
+
+    /// Fade out the music that is playing.
+    fn stop(&self, audio_instances: &mut Assets<AudioInstance>) {
+        let intense = match self {
+            MusicState::Fight { intense, .. } => intense.as_ref(),
+            _ => None,
+        };
+        for instance in self.current_instance().into_iter().chain(intense) {
+            if let Some(instance) = audio_instances.get_mut(instance) {
+                instance.stop(AudioTween::linear(MUSIC_FADE_DURATION));
+            }
+        }
+    }
+}
+
+/// How intense the current match is, used to crossfade the stems of layered fight songs.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct MusicIntensity {
+    /// The intensity, from `0.0` for calm to `1.0` for intense.
+    pub intensity: f32,
+    /// The intensity added by recent kills, which decays over time.
+    kill_heat: f32,
+    /// Which players were killed on the last update.
+    was_killed: [bool; MAX_PLAYERS],
+}
+
+impl MusicIntensity {
+    /// Get the volumes of the calm and the intense stems of a layered song.
+    ///
+    /// The stems are crossfaded with an equal-power curve, so that the music doesn't get quieter
+    /// in the middle of the crossfade.
+    pub fn stem_volumes(&self) -> (f64, f64) {
+        let angle = self.intensity as f64 * std::f64::consts::FRAC_PI_2;
+        (angle.cos(), angle.sin())
+    }
 }
 
 /// Bevy resource containing the in-game music playlist shuffled.
 #[derive(Resource, Deref, DerefMut, Clone, Debug, Default)]
-pub struct ShuffledPlaylist(pub Vec<AssetHandle<AudioSource>>);
+pub struct ShuffledPlaylist(pub Vec<FightMusicMeta>);
 
 /// Sets the default music and effects volume.
 ///
@@ -96,6 +148,100 @@ fn setup_audio_defaults(
 /// The amount of time to spend fading the music in and out.
 const MUSIC_FADE_DURATION: Duration = Duration::from_millis(500);
 
+/// The intensity added by every kill.
+const KILL_HEAT: f32 = 0.5;
+
+/// How much of the intensity added by kills goes away every second.
+const KILL_HEAT_DECAY: f32 = 0.1;
+
+/// How quickly the music intensity moves toward the intensity of the match, per second.
+const INTENSITY_CHANGE_SPEED: f32 = 0.5;
+
+/// Update the [`MusicIntensity`] from the number of players left alive and the recent kills.
+fn update_music_intensity(
+    time: Res<Time>,
+    mut session: ResMut<Session>,
+    mut intensity: ResMut<MusicIntensity>,
+) {
+    let killed = session
+        .world()
+        .run_initialized_system(
+            |entities: bones::Res<bones::Entities>,
+             player_indexes: bones::Comp<PlayerIdx>,
+             players_killed: bones::Comp<PlayerKilled>| {
+                let mut killed = [None; MAX_PLAYERS];
+                for (ent, player_idx) in entities.iter_with(&player_indexes) {
+                    killed[player_idx.0] = Some(players_killed.contains(ent));
+                }
+                Ok(killed)
+            },
+        )
+        .unwrap();
+
+    let delta = time.delta_seconds();
+    let mut kill_heat = (intensity.kill_heat - KILL_HEAT_DECAY * delta).max(0.0);
+    for (killed, was_killed) in killed.iter().zip(&mut intensity.was_killed) {
+        let killed = killed.unwrap_or(false);
+        if killed && !*was_killed {
+            kill_heat += KILL_HEAT;
+        }
+        *was_killed = killed;
+    }
+    intensity.kill_heat = kill_heat.min(1.0);
+
+    // The match gets more intense as fewer players are left alive
+    let players = killed.iter().flatten().count();
+    let alive = killed.iter().flatten().filter(|killed| !**killed).count();
+    let last_players = if players > 1 {
+        1.0 - alive.saturating_sub(1) as f32 / (players - 1) as f32
+    } else {
+        0.0
+    };
+
+    let target = last_players.max(intensity.kill_heat);
+    let change = (target - intensity.intensity).clamp(
+        -INTENSITY_CHANGE_SPEED * delta,
+        INTENSITY_CHANGE_SPEED * delta,
+    );
+    intensity.intensity += change;
+}
+
+/// Reset the [`MusicIntensity`] when leaving the match.
+fn reset_music_intensity(mut intensity: ResMut<MusicIntensity>) {
+    *intensity = default();
+}
+
+/// Play a fight song, returning the audio instances of its calm and intense stems.
+fn play_fight_song(
+    music: &AudioChannel<MusicChannel>,
+    song: &FightMusicMeta,
+    looped: bool,
+    intensity: &MusicIntensity,
+) -> (Handle<AudioInstance>, Option<Handle<AudioInstance>>) {
+    let Some(intense) = song.intense() else {
+        let mut command = music.play(song.calm().inner.clone_weak());
+        command.linear_fade_in(MUSIC_FADE_DURATION);
+        if looped {
+            command.looped();
+        }
+        return (command.handle(), None);
+    };
+
+    let (calm_volume, intense_volume) = intensity.stem_volumes();
+    let play = |stem: &AssetHandle<AudioSource>, volume: f64| {
+        let mut command = music.play(stem.inner.clone_weak());
+        command.with_volume(volume);
+        if looped {
+            command.looped();
+        }
+        command.handle()
+    };
+    (
+        play(song.calm(), calm_volume),
+        Some(play(intense, intense_volume)),
+    )
+}
+
 /// System that plays music according to the game mode.
 fn music_system(
     game: Res<GameMeta>,
@@ -105,6 +251,7 @@ fn music_system(
     music: Res<AudioChannel<MusicChannel>>,
     engine_state: Res<State<EngineState>>,
     menu_page: Res<MenuPage>,
+    intensity: Res<MusicIntensity>,
 ) {
     if shuffled_fight_music.is_empty() || engine_state.is_changed() {
         let mut songs = game.music.fight.clone();
@@ -115,30 +262,43 @@ fn music_system(
     match engine_state.0 {
         EngineState::LoadingPlatformStorage | EngineState::LoadingGameData => (),
         EngineState::InGame => {
-            if let MusicState::Fight { instance, idx } = &mut *music_state {
+            if let MusicState::Fight {
+                instance,
+                intense,
+                idx,
+            } = &mut *music_state
+            {
                 let inst = audio_instances.get(instance).unwrap();
                 if let PlaybackState::Stopped = inst.state() {
+                    if let Some(intense) = intense.take() {
+                        if let Some(intense) = audio_instances.get_mut(&intense) {
+                            intense.stop(default());
+                        }
+                    }
+
                     *idx += 1;
                     *idx %= shuffled_fight_music.len();
 
-                    *instance = music
-                        .play(shuffled_fight_music[*idx].inner.clone_weak())
-                        .linear_fade_in(MUSIC_FADE_DURATION)
-                        .handle();
+                    (*instance, *intense) =
+                        play_fight_song(&music, &shuffled_fight_music[*idx], false, &intensity);
+                } else if let Some(intense) = intense {
+                    // Crossfade the stems of layered songs
+                    let (calm_volume, intense_volume) = intensity.stem_volumes();
+                    if let Some(calm) = audio_instances.get_mut(instance) {
+                        calm.set_volume(calm_volume, default());
+                    }
+                    if let Some(intense) = audio_instances.get_mut(intense) {
+                        intense.set_volume(intense_volume, default());
+                    }
                 }
             } else {
-                if let Some(instance) = music_state.current_instance() {
-                    let instance = audio_instances.get_mut(instance).unwrap();
-                    instance.stop(AudioTween::linear(MUSIC_FADE_DURATION));
-                }
+                music_state.stop(&mut audio_instances);
 
                 if let Some(song) = shuffled_fight_music.get(0) {
+                    let (instance, intense) = play_fight_song(&music, song, true, &intensity);
                     *music_state = MusicState::Fight {
-                        instance: music
-                            .play(song.inner.clone_weak())
-                            .linear_fade_in(MUSIC_FADE_DURATION)
-                            .looped()
-                            .handle(),
+                        instance,
+                        intense,
                         idx: 0,
                     };
                 }
@@ -147,10 +307,7 @@ fn music_system(
         EngineState::MainMenu => match &*menu_page {
             MenuPage::PlayerSelect | MenuPage::MapSelect { .. } | MenuPage::NetworkGame => {
                 if !matches!(*music_state, MusicState::CharacterSelect(..)) {
-                    if let Some(instance) = music_state.current_instance() {
-                        let instance = audio_instances.get_mut(instance).unwrap();
-                        instance.stop(AudioTween::linear(MUSIC_FADE_DURATION));
-                    }
+                    music_state.stop(&mut audio_instances);
                     *music_state = MusicState::CharacterSelect(
                         music
                             .play(game.music.character_screen.inner.clone_weak())
@@ -160,12 +317,12 @@ fn music_system(
                     );
                 }
             }
-            MenuPage::Home | MenuPage::Settings => {
+            MenuPage::Home
+            | MenuPage::Settings
+            | MenuPage::DailyChallenge
+            | MenuPage::Leaderboard => {
                 if !matches!(*music_state, MusicState::MainMenu(..)) {
-                    if let Some(instance) = music_state.current_instance() {
-                        let instance = audio_instances.get_mut(instance).unwrap();
-                        instance.stop(AudioTween::linear(MUSIC_FADE_DURATION));
-                    }
+                    music_state.stop(&mut audio_instances);
                     *music_state = MusicState::MainMenu(
                         music
                             .play(game.music.title_screen.inner.clone_weak())
@@ -177,10 +334,7 @@ fn music_system(
             }
             MenuPage::Credits => {
                 if !matches!(*music_state, MusicState::Credits(..)) {
-                    if let Some(instance) = music_state.current_instance() {
-                        let instance = audio_instances.get_mut(instance).unwrap();
-                        instance.stop(AudioTween::linear(MUSIC_FADE_DURATION));
-                    }
+                    music_state.stop(&mut audio_instances);
                     *music_state = MusicState::Credits(
                         music
                             .play(game.music.credits.inner.clone_weak())
@@ -2,8 +2,8 @@
 //!
 //! We have three different Bevy state types. The [`EngineState`] is the "parent" or "main" state.
 //! We take advantage of the ability to have multiple different kinds of states in Bevy and have
-//! [`InGameState`] and [`GameEditorState`] represent the game pause state and editor visibility
-//! when the [`EngineState`] is set to [`EngineState::InGame`].
+//! [`InGameState`] and [`GameEditorState`] represent the game pause or results state and editor
+//! visibility when the [`EngineState`] is set to [`EngineState::InGame`].
 
 use crate::prelude::*;
 
@@ -35,6 +35,8 @@ pub enum InGameState {
     Playing,
     /// The game is paused.
     Paused,
+    /// The match is over, and its results are shown.
+    Results,
 }
 
 /// Bevy [`States`] tracking the editor visibility, relevant only while the [`EngineState::InGame`]
@@ -0,0 +1,315 @@
+//! In-game bug report tool.
+//!
+//! Pressing [`BUG_REPORT_KEY`] during a match pauses the game, captures a snapshot of the game
+//! world and the most recent player inputs, and opens a form to describe the bug. Saving the report
+//! bundles everything into a zip file in the `bug_reports` folder of the user's data directory,
+//! which can then be attached to an issue.
+//!
+//! With the `bug-report-screenshots` feature, a screenshot of the game is captured too. Bevy 0.10
+//! has no API to read back the rendered frame, so the screenshot is taken by capturing the area of
+//! the screen covered by the client area of the primary window. It shows anything covering the game
+//! at that moment, such as other windows, so the form shows a preview of it and the screenshot is
+//! only attached if the player leaves it checked.
+
+use std::{io::Write, path::PathBuf};
+
+#[cfg(feature = "bug-report-screenshots")]
+use bevy::{
+    render::texture::{CompressedImageFormats, ImageType},
+    window::PrimaryWindow,
+    winit::WinitWindows,
+};
+use bevy_egui::EguiContexts;
+use bevy_fluent::Localization;
+use jumpy_core::{map::LoadedMap, player::PlayerIdx};
+
+use crate::{
+    crash_report::{RECENT_LOG_LINES, REPLAY_BUFFER},
+    prelude::*,
+    ui::pause_menu::PauseMenuPage,
+};
+
+/// Bug report plugin.
+pub struct JumpyBugReportPlugin;
+
+impl Plugin for JumpyBugReportPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BugReport>()
+            .add_system(
+                start_bug_report
+                    .run_if(in_state(EngineState::InGame))
+                    .run_if(in_state(InGameState::Playing))
+                    .run_if(resource_exists::<Session>()),
+            )
+            .add_system(
+                bug_report_form
+                    .run_if(in_state(EngineState::InGame))
+                    .run_if(in_state(InGameState::Paused))
+                    .run_if(resource_equals(PauseMenuPage::BugReport)),
+            );
+    }
+}
+
+/// The key used to open the bug report form.
+pub const BUG_REPORT_KEY: KeyCode = KeyCode::F8;
+
+/// The largest width of the screenshot preview shown in the bug report form.
+const SCREENSHOT_PREVIEW_WIDTH: f32 = 320.0;
+
+/// Resource containing the bug report being written.
+#[derive(Resource, Default)]
+pub struct BugReport {
+    /// The player's description of the bug.
+    pub description: String,
+    /// A text dump of the game world at the moment the report was started.
+    pub world_snapshot: String,
+    /// The most recent player inputs at the moment the report was started.
+    pub recent_inputs: String,
+    /// The most recent log lines at the moment the report was started.
+    pub recent_logs: String,
+    /// A PNG screenshot of the game window at the moment the report was started, if it could be
+    /// captured.
+    pub screenshot: Option<Vec<u8>>,
+    /// The preview of the screenshot shown in the form.
+    pub screenshot_preview: Option<Handle<Image>>,
+    /// Whether or not the player chose to attach the screenshot to the report.
+    pub attach_screenshot: bool,
+    /// The path the report was saved to, once it has been saved.
+    pub saved_to: Option<PathBuf>,
+}
+
+impl BugReport {
+    /// Write the report to a zip file in the user's data directory, returning the file path.
+    fn save(&self) -> anyhow::Result<PathBuf> {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs();
+        let dir = directories::ProjectDirs::from("org", "FishFolk", "Jumpy")
+            .context("Couldn't identify the user data directory")?
+            .data_dir()
+            .join("bug_reports");
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("bug-{timestamp}.zip"));
+
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&path)?);
+        let options = zip::write::FileOptions::default();
+        for (name, contents) in [
+            ("description.txt", &self.description),
+            ("world.txt", &self.world_snapshot),
+            ("inputs.txt", &self.recent_inputs),
+            ("log.txt", &self.recent_logs),
+        ] {
+            zip.start_file(name, options)?;
+            zip.write_all(contents.as_bytes())?;
+        }
+        if let Some(screenshot) = self.screenshot.as_ref().filter(|_| self.attach_screenshot) {
+            zip.start_file("screenshot.png", options)?;
+            zip.write_all(screenshot)?;
+        }
+        zip.finish()?;
+
+        Ok(path)
+    }
+}
+
+/// Capture the area of the screen covered by the client area of a window as a PNG image.
+///
+/// The position and size of the client area are given in physical pixels, along with the scale
+/// factor of the window.
+#[cfg(feature = "bug-report-screenshots")]
+fn capture_window(position: IVec2, size: UVec2, window_scale: f64) -> anyhow::Result<Vec<u8>> {
+    // Screens are looked up and captured in logical pixels, using the scale factor of the screen
+    // rather than the one of the window.
+    let logical = |x: i32, scale: f64| (f64::from(x) / scale).round() as i32;
+    let screen = screenshots::Screen::from_point(
+        logical(position.x, window_scale),
+        logical(position.y, window_scale),
+    )?;
+    let scale = f64::from(screen.display_info.scale_factor);
+    let image = screen.capture_area(
+        logical(position.x, scale) - screen.display_info.x,
+        logical(position.y, scale) - screen.display_info.y,
+        logical(size.x as i32, scale) as u32,
+        logical(size.y as i32, scale) as u32,
+    )?;
+
+    Ok(image.buffer().clone())
+}
+
+/// Capture a screenshot of the primary window, and load it as an image for its preview.
+#[cfg(feature = "bug-report-screenshots")]
+fn capture_screenshot(
+    winit_windows: &WinitWindows,
+    windows: &Query<Entity, With<PrimaryWindow>>,
+    images: &mut Assets<Image>,
+) -> anyhow::Result<(Vec<u8>, Handle<Image>)> {
+    let window = windows
+        .get_single()
+        .ok()
+        .and_then(|entity| winit_windows.get_window(entity))
+        .context("The primary window was not found")?;
+
+    // The inner position and size only cover the client area, leaving out the title bar and the
+    // borders of the window.
+    let position = window
+        .inner_position()
+        .map_err(|e| anyhow::format_err!("The window position is not known: {e}"))?;
+    let size = window.inner_size();
+    let png = capture_window(
+        IVec2::new(position.x, position.y),
+        UVec2::new(size.width, size.height),
+        window.scale_factor(),
+    )?;
+
+    let preview = Image::from_buffer(
+        &png,
+        ImageType::Extension("png"),
+        CompressedImageFormats::NONE,
+        true,
+    )?;
+
+    Ok((png, images.add(preview)))
+}
+
+/// Pause the game and capture the game state when the bug report key is pressed.
+fn start_bug_report(
+    mut commands: Commands,
+    keyboard: Res<Input<KeyCode>>,
+    mut session: ResMut<Session>,
+    mut bug_report: ResMut<BugReport>,
+    mut pause_page: ResMut<PauseMenuPage>,
+    #[cfg(feature = "bug-report-screenshots")] winit_windows: NonSend<WinitWindows>,
+    #[cfg(feature = "bug-report-screenshots")] windows: Query<Entity, With<PrimaryWindow>>,
+    #[cfg(feature = "bug-report-screenshots")] mut images: ResMut<Assets<Image>>,
+) {
+    if !keyboard.just_pressed(BUG_REPORT_KEY) {
+        return;
+    }
+
+    // Capture the screen first, before the bug report form is drawn over the game.
+    #[cfg(feature = "bug-report-screenshots")]
+    let (screenshot, screenshot_preview) =
+        match capture_screenshot(&winit_windows, &windows, &mut images) {
+            Ok((png, preview)) => (Some(png), Some(preview)),
+            Err(e) => {
+                warn!("Could not capture a screenshot for the bug report: {e}");
+                (None, None)
+            }
+        };
+    #[cfg(not(feature = "bug-report-screenshots"))]
+    let (screenshot, screenshot_preview) = (None, None);
+
+    let world_snapshot = session
+        .world()
+        .run_initialized_system(
+            |entities: bones::Res<bones::Entities>,
+             map: bones::Res<LoadedMap>,
+             match_settings: bones::Res<MatchSettings>,
+             time: bones::Res<bones::Time>,
+             transforms: bones::Comp<bones::Transform>,
+             player_indexes: bones::Comp<PlayerIdx>| {
+                let mut snapshot = format!(
+                    "map: {}\nelapsed: {:.3}\nmatch settings: {:?}\n\n",
+                    map.name,
+                    time.elapsed().as_secs_f32(),
+                    *match_settings
+                );
+                for (entity, transform) in entities.iter_with(&transforms) {
+                    snapshot.push_str(&format!("{entity:?}: {:?}", transform.translation));
+                    if let Some(player_idx) = player_indexes.get(entity) {
+                        snapshot.push_str(&format!(" player {}", player_idx.0));
+                    }
+                    snapshot.push('\n');
+                }
+                Ok(snapshot)
+            },
+        )
+        .unwrap();
+
+    *bug_report = BugReport {
+        world_snapshot,
+        recent_inputs: REPLAY_BUFFER
+            .lock()
+            .map(|x| x.to_text())
+            .unwrap_or_default(),
+        recent_logs: RECENT_LOG_LINES
+            .lock()
+            .map(|x| x.iter().map(String::as_str).collect())
+            .unwrap_or_default(),
+        attach_screenshot: screenshot.is_some(),
+        screenshot,
+        screenshot_preview,
+        ..default()
+    };
+    *pause_page = PauseMenuPage::BugReport;
+    commands.insert_resource(NextState(Some(InGameState::Paused)));
+}
+
+/// Show the bug report form.
+fn bug_report_form(
+    mut commands: Commands,
+    mut contexts: EguiContexts,
+    mut bug_report: ResMut<BugReport>,
+    mut pause_page: ResMut<PauseMenuPage>,
+    localization: Res<Localization>,
+    images: Res<Assets<Image>>,
+) {
+    let mut close = false;
+
+    let preview = bug_report.screenshot_preview.as_ref().and_then(|handle| {
+        let size = images.get(handle)?.size();
+        Some((contexts.add_image(handle.clone_weak()), size))
+    });
+
+    egui::Window::new(localization.get("bug-report"))
+        .id(egui::Id::new("bug-report"))
+        .collapsible(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(contexts.ctx_mut(), |ui| {
+            if let Some(path) = &bug_report.saved_to {
+                ui.label(localization.get("bug-report-saved"));
+                ui.monospace(path.display().to_string());
+                if ui.button(localization.get("close")).clicked() {
+                    close = true;
+                }
+                return;
+            }
+
+            ui.label(localization.get("bug-report-description"));
+            ui.add(
+                egui::TextEdit::multiline(&mut bug_report.description)
+                    .desired_rows(6)
+                    .desired_width(f32::INFINITY),
+            );
+
+            if let Some((texture, size)) = preview {
+                ui.checkbox(
+                    &mut bug_report.attach_screenshot,
+                    localization.get("bug-report-attach-screenshot"),
+                );
+                let width = size.x.min(SCREENSHOT_PREVIEW_WIDTH);
+                ui.image(texture, egui::vec2(width, width * size.y / size.x));
+            }
+
+            ui.horizontal(|ui| {
+                if ui.button(localization.get("save")).clicked() {
+                    match bug_report.save() {
+                        Ok(path) => {
+                            info!("Bug report saved to {}", path.display());
+                            bug_report.saved_to = Some(path);
+                        }
+                        Err(e) => error!("Could not save bug report: {e}"),
+                    }
+                }
+                if ui.button(localization.get("cancel")).clicked() {
+                    close = true;
+                }
+            });
+        });
+
+    if close {
+        *bug_report = default();
+        *pause_page = default();
+        commands.insert_resource(NextState(Some(InGameState::Playing)));
+    }
+}
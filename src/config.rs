@@ -6,11 +6,16 @@
 //! [`EngineConfig`] is parsed from commandline arguments or environment variables on native
 //! platforms, and from the query string on web.
 
+use std::net::{IpAddr, Ipv4Addr};
+
 use once_cell::sync::Lazy;
 
 /// The name of the environment variable used to set the asset dir.
 pub const ASSET_DIR_ENV_VAR: &str = "JUMPY_ASSET_DIR";
 
+/// The name of the environment variable used to set the password of the dedicated host admin API.
+pub const ADMIN_PASSWORD_ENV_VAR: &str = "JUMPY_ADMIN_PASSWORD";
+
 /// The default log level string.
 const DEFAULT_LOG_LEVEL: &str = "info,wgpu=error,bevy_fluent=warn,symphonia_core=warn,symphonia_format_ogg=warn,symphonia_bundle_mp3=warn";
 
@@ -55,6 +60,61 @@ pub struct EngineConfig {
     /// `module=level` items.
     #[arg(short = 'l', long, default_value = DEFAULT_LOG_LEVEL)]
     pub log_level: String,
+
+    /// Join the online lobby with the given code at startup
+    #[arg(long)]
+    pub lobby_code: Option<String>,
+
+    /// The signaling server that browser builds join online lobbies through
+    #[arg(skip)]
+    pub signaling_server: Option<String>,
+
+    /// The number of players of the online lobby joined by browser builds
+    #[arg(skip)]
+    pub lobby_player_count: Option<usize>,
+
+    /// The STUN server that browser builds find their public address with
+    #[arg(skip)]
+    pub stun_server: Option<String>,
+
+    /// Play back a replay in director mode, as described by the given script
+    #[arg(long)]
+    pub director_script: Option<String>,
+
+    /// Record local matches, saving the replays in the user data directory
+    #[arg(long)]
+    pub record_replays: bool,
+
+    /// Play back the given replay
+    #[arg(long)]
+    pub replay: Option<String>,
+
+    /// Show a ghost repeating the run of the first human player of the given replay, in local
+    /// matches played on the map of the replay
+    #[arg(long)]
+    pub ghost: Option<String>,
+
+    /// Run as a dedicated host, serving LAN matches on the rotation of maps described by the given
+    /// file
+    #[arg(long)]
+    pub dedicated_host: Option<String>,
+
+    /// Accept admin connections to the dedicated host on the given port
+    #[arg(long)]
+    pub admin_port: Option<u16>,
+
+    /// The address to accept admin connections on. The admin API isn't encrypted, so it should
+    /// only be reachable from other machines through a tunnel
+    #[arg(long, default_value_t = IpAddr::V4(Ipv4Addr::LOCALHOST))]
+    pub admin_address: IpAddr,
+
+    /// The password of the admin connections to the dedicated host
+    #[arg(long, env = ADMIN_PASSWORD_ENV_VAR, hide_env_values = true)]
+    pub admin_password: Option<String>,
+
+    /// Serve the metrics of the dedicated host over HTTP on the given port
+    #[arg(long)]
+    pub metrics_port: Option<u16>,
 }
 
 impl EngineConfig {
@@ -75,6 +135,22 @@ impl EngineConfig {
                 config.log_level = log_level.into();
             }
 
+            if let Some(lobby_code) = parse_url_query_string(&query, "lobby_code") {
+                config.lobby_code = Some(lobby_code.into());
+            }
+
+            if let Some(server) = parse_url_query_string(&query, "signaling_server") {
+                config.signaling_server = Some(server.into());
+            }
+
+            if let Some(player_count) = parse_url_query_string(&query, "player_count") {
+                config.lobby_player_count = player_count.parse().ok();
+            }
+
+            if let Some(stun_server) = parse_url_query_string(&query, "stun_server") {
+                config.stun_server = Some(stun_server.into());
+            }
+
             config
         } else {
             Self::web_default()
@@ -91,6 +167,19 @@ impl EngineConfig {
             game_asset: "default.game.yaml".into(),
             log_level: DEFAULT_LOG_LEVEL.into(),
             sync_test_check_distance: 0,
+            lobby_code: None,
+            signaling_server: None,
+            lobby_player_count: None,
+            stun_server: None,
+            director_script: None,
+            record_replays: false,
+            replay: None,
+            ghost: None,
+            dedicated_host: None,
+            admin_port: None,
+            admin_address: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            admin_password: None,
+            metrics_port: None,
         }
     }
 }
@@ -0,0 +1,328 @@
+//! Crash reporting.
+//!
+//! When the game panics, a crash bundle is written to the `crash_reports` folder in the user's
+//! data directory. The bundle contains:
+//!
+//! - `crash.txt`: the panic message, location, and a backtrace.
+//! - `log.txt`: the most recent log lines.
+//! - `replay.txt`: the map and the most recent player inputs of the session that was running.
+//!
+//! The next time the game is started, a dialog offers to open the folder containing the bundle, or
+//! to submit it to the crash report server. Nothing is ever uploaded unless the player chooses to
+//! submit the report, and uploading is only available with the `crash-upload` cargo feature.
+
+use std::{
+    collections::VecDeque,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use bevy_egui::EguiContexts;
+use bevy_fluent::Localization;
+use jumpy_core::{
+    input::{PlayerControl, PlayerInputs},
+    map::LoadedMap,
+};
+
+use crate::prelude::*;
+
+/// Crash report plugin.
+pub struct JumpyCrashReportPlugin;
+
+impl Plugin for JumpyCrashReportPlugin {
+    fn build(&self, app: &mut App) {
+        install_panic_hook();
+
+        app.insert_resource(UnreportedCrash(find_unreported_crash()))
+            .add_system(
+                record_replay_buffer
+                    .run_if(in_state(EngineState::InGame))
+                    .run_if(resource_exists::<Session>()),
+            )
+            .add_system(clear_replay_buffer.in_schedule(OnExit(EngineState::InGame)))
+            .add_system(crash_report_dialog.run_if(in_state(EngineState::MainMenu)));
+    }
+}
+
+/// The number of log lines to keep for the crash bundle.
+const LOG_BUFFER_LEN: usize = 200;
+
+/// The number of frames of player input to keep for the crash bundle.
+const REPLAY_BUFFER_LEN: usize = 600;
+
+/// The name of the marker file placed in crash bundles that haven't been handled by the player yet.
+const UNREPORTED_MARKER: &str = "unreported";
+
+/// The most recent log lines, used to build the crash bundle.
+pub static RECENT_LOG_LINES: Lazy<Mutex<VecDeque<String>>> =
+    Lazy::new(|| Mutex::new(VecDeque::with_capacity(LOG_BUFFER_LEN)));
+
+/// The most recent inputs of the running session, used to build the crash bundle.
+pub static REPLAY_BUFFER: Lazy<Mutex<ReplayBuffer>> = Lazy::new(default);
+
+/// A rolling buffer of the most recent inputs of a game session.
+#[derive(Default)]
+pub struct ReplayBuffer {
+    /// The name of the map being played.
+    pub map_name: Option<String>,
+    /// The inputs of the active players for each recorded frame.
+    pub frames: VecDeque<ReplayFrame>,
+}
+
+/// The player inputs at a single point in a game session.
+pub struct ReplayFrame {
+    /// The time since the start of the session.
+    pub elapsed: f32,
+    /// The controls of each active player, by player index.
+    pub controls: Vec<(usize, PlayerControl)>,
+}
+
+impl ReplayBuffer {
+    /// Format the buffer as text, with one line per player per frame.
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "map: {}\n",
+            self.map_name.as_deref().unwrap_or("<no session running>")
+        );
+        for frame in &self.frames {
+            for (player_idx, control) in &frame.controls {
+                text.push_str(&format!(
+                    "{:.3} player {player_idx}: {control:?}\n",
+                    frame.elapsed
+                ));
+            }
+        }
+        text
+    }
+}
+
+/// [`std::io::Write`] implementation used by the logging layer to fill [`RECENT_LOG_LINES`].
+#[derive(Default)]
+pub struct RecentLogWriter;
+
+impl Write for RecentLogWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if let Ok(mut lines) = RECENT_LOG_LINES.lock() {
+            if lines.len() >= LOG_BUFFER_LEN {
+                lines.pop_front();
+            }
+            lines.push_back(String::from_utf8_lossy(buf).into_owned());
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Resource containing the path of a crash bundle from a previous run that the player hasn't
+/// handled yet.
+#[derive(Resource, Default)]
+pub struct UnreportedCrash(pub Option<PathBuf>);
+
+/// Get the directory crash bundles are written to.
+pub fn crash_report_dir() -> Option<PathBuf> {
+    directories::ProjectDirs::from("org", "FishFolk", "Jumpy")
+        .map(|dirs| dirs.data_dir().join("crash_reports"))
+}
+
+/// Install the panic hook that writes the crash bundle.
+///
+/// The previously installed hook is still called afterward, so the panic is printed as usual.
+fn install_panic_hook() {
+    let previous_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        match write_crash_bundle(info) {
+            Ok(path) => eprintln!("Crash report written to {}", path.display()),
+            Err(e) => eprintln!("Could not write crash report: {e}"),
+        }
+        previous_hook(info);
+    }));
+}
+
+/// Write the crash bundle for a panic, returning the bundle directory.
+fn write_crash_bundle(info: &std::panic::PanicInfo) -> std::io::Result<PathBuf> {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or_default();
+    let dir = crash_report_dir()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "No data directory"))?
+        .join(format!("crash-{timestamp}"));
+    std::fs::create_dir_all(&dir)?;
+
+    let backtrace = std::backtrace::Backtrace::force_capture();
+    std::fs::write(
+        dir.join("crash.txt"),
+        format!(
+            "Jumpy {}\n\n{info}\n\n{backtrace}\n",
+            env!("CARGO_PKG_VERSION")
+        ),
+    )?;
+
+    // Don't wait on the locks: the panic may have happened while they were held.
+    let log = match RECENT_LOG_LINES.try_lock() {
+        Ok(lines) => lines.iter().map(String::as_str).collect::<String>(),
+        Err(_) => "<log unavailable>".into(),
+    };
+    std::fs::write(dir.join("log.txt"), log)?;
+
+    let replay = match REPLAY_BUFFER.try_lock() {
+        Ok(buffer) => buffer.to_text(),
+        Err(_) => "<replay unavailable>".into(),
+    };
+    std::fs::write(dir.join("replay.txt"), replay)?;
+
+    std::fs::write(dir.join(UNREPORTED_MARKER), "")?;
+
+    Ok(dir)
+}
+
+/// Find the most recent crash bundle that hasn't been handled by the player yet.
+fn find_unreported_crash() -> Option<PathBuf> {
+    let dir = crash_report_dir()?;
+    let mut bundles = std::fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.join(UNREPORTED_MARKER).exists())
+        .collect::<Vec<_>>();
+    bundles.sort();
+    bundles.pop()
+}
+
+/// Mark a crash bundle as handled, so that we don't ask about it again.
+fn mark_reported(bundle: &Path) {
+    std::fs::remove_file(bundle.join(UNREPORTED_MARKER)).ok();
+}
+
+/// Open a folder in the system file browser.
+fn open_folder(path: &Path) {
+    #[cfg(target_os = "windows")]
+    let program = "explorer";
+    #[cfg(target_os = "macos")]
+    let program = "open";
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let program = "xdg-open";
+
+    if let Err(e) = std::process::Command::new(program).arg(path).spawn() {
+        warn!("Could not open crash report folder: {e}");
+    }
+}
+
+/// Upload a crash bundle to the crash report server in the background.
+#[cfg(feature = "crash-upload")]
+fn submit_crash_bundle(server: String, bundle: PathBuf) {
+    #[derive(Serialize)]
+    struct CrashReport {
+        crash: String,
+        log: String,
+        replay: String,
+    }
+
+    std::thread::spawn(move || {
+        let read = |name: &str| std::fs::read_to_string(bundle.join(name)).unwrap_or_default();
+        let report = CrashReport {
+            crash: read("crash.txt"),
+            log: read("log.txt"),
+            replay: read("replay.txt"),
+        };
+
+        match ureq::post(&format!("{server}/crashes")).send_json(&report) {
+            Ok(_) => info!("Crash report submitted"),
+            Err(e) => warn!("Could not submit crash report: {e}"),
+        }
+    });
+}
+
+/// Record the inputs of the running session.
+fn record_replay_buffer(mut session: ResMut<Session>) {
+    let Ok(mut buffer) = REPLAY_BUFFER.lock() else {
+        return;
+    };
+
+    let (map_name, frame) = session
+        .world()
+        .run_initialized_system(
+            |time: bones::Res<bones::Time>,
+             map: bones::Res<LoadedMap>,
+             inputs: bones::Res<PlayerInputs>| {
+                let frame = ReplayFrame {
+                    elapsed: time.elapsed().as_secs_f32(),
+                    controls: inputs
+                        .players
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, x)| x.active)
+                        .map(|(i, x)| (i, x.control.clone()))
+                        .collect(),
+                };
+                Ok((map.name.clone(), frame))
+            },
+        )
+        .unwrap();
+
+    buffer.map_name = Some(map_name);
+    if buffer.frames.len() >= REPLAY_BUFFER_LEN {
+        buffer.frames.pop_front();
+    }
+    buffer.frames.push_back(frame);
+}
+
+/// Clear the replay buffer when leaving the match.
+fn clear_replay_buffer() {
+    if let Ok(mut buffer) = REPLAY_BUFFER.lock() {
+        *buffer = default();
+    }
+}
+
+/// Show a dialog about the crash bundle of a previous run.
+fn crash_report_dialog(
+    mut contexts: EguiContexts,
+    mut unreported: ResMut<UnreportedCrash>,
+    localization: Res<Localization>,
+    #[cfg_attr(not(feature = "crash-upload"), allow(unused))] game: Res<GameMeta>,
+    #[cfg_attr(not(feature = "crash-upload"), allow(unused))] mut storage: ResMut<Storage>,
+) {
+    let Some(bundle) = unreported.0.clone() else {
+        return;
+    };
+
+    let mut handled = false;
+    egui::Window::new(localization.get("crash-report"))
+        .id(egui::Id::new("crash-report"))
+        .collapsible(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(localization.get("crash-report-description"));
+            ui.monospace(bundle.display().to_string());
+
+            ui.horizontal(|ui| {
+                if ui.button(localization.get("open-folder")).clicked() {
+                    open_folder(&bundle);
+                }
+
+                #[cfg(feature = "crash-upload")]
+                {
+                    let server = Settings::get_stored_or_default(&game, &mut storage)
+                        .crash_report_server
+                        .clone();
+                    if !server.is_empty() && ui.button(localization.get("submit")).clicked() {
+                        submit_crash_bundle(server, bundle.clone());
+                        handled = true;
+                    }
+                }
+
+                if ui.button(localization.get("dismiss")).clicked() {
+                    handled = true;
+                }
+            });
+        });
+
+    if handled {
+        mark_reported(&bundle);
+        unreported.0 = None;
+    }
+}
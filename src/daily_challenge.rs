@@ -0,0 +1,388 @@
+//! Daily challenge mode.
+//!
+//! Every day a deterministic seed is derived from the date, which is used to pick the map, the
+//! mutators, and the AI opponents for the challenge. Everybody playing on the same day gets the
+//! same challenge.
+//!
+//! Players get one scored attempt per day. The attempt ends when the local player is killed for the
+//! first time, or when they leave the match, and the result is stored locally in [`Storage`]. A
+//! player still alive when the match ends survived the whole match.
+
+use jumpy_core::match_stats::MatchStats;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{main_menu::MenuPage, prelude::*};
+
+/// Daily challenge plugin.
+pub struct JumpyDailyChallengePlugin;
+
+impl Plugin for JumpyDailyChallengePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActiveDailyChallenge>()
+            .add_event::<DailyChallengeFinished>()
+            .add_system(
+                track_daily_challenge
+                    .run_if(in_state(EngineState::InGame))
+                    .run_if(resource_exists::<Session>()),
+            )
+            .add_system(
+                record_daily_challenge_survival
+                    .in_schedule(OnEnter(InGameState::Results))
+                    .run_if(resource_exists::<Session>()),
+            )
+            .add_system(finish_daily_challenge.in_schedule(OnExit(EngineState::InGame)));
+    }
+}
+
+/// The number of milliseconds in a day.
+const MILLIS_PER_DAY: u64 = 24 * 60 * 60 * 1000;
+
+/// The chance for each mutator to be enabled in a daily challenge.
+const MUTATOR_CHANCE: f64 = 0.3;
+/// The mutators that may be enabled in a daily challenge.
+///
+/// Mutators added later aren't rolled for, so that the rolls of a day don't change.
+const CHALLENGE_MUTATORS: [Mutator; 5] = [
+    Mutator::LowGravity,
+    Mutator::Jetpacks,
+    Mutator::ExplosivesOnly,
+    Mutator::OneHitSwords,
+    Mutator::BigHeads,
+];
+
+/// The challenge for a single day.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DailyChallenge {
+    /// The number of days since the unix epoch.
+    pub day: u64,
+    /// The seed used to generate the challenge.
+    pub seed: u64,
+}
+
+impl DailyChallenge {
+    /// Get the challenge for the current day.
+    pub fn today() -> Self {
+        Self::for_day(current_day())
+    }
+
+    /// Get the challenge for the given number of days since the unix epoch.
+    pub fn for_day(day: u64) -> Self {
+        Self {
+            day,
+            seed: splitmix64(day),
+        }
+    }
+
+    /// Get the date of the challenge formatted as `YYYY-MM-DD`.
+    pub fn date(&self) -> String {
+        let (year, month, day) = date_from_day(self.day);
+        format!("{year:04}-{month:02}-{day:02}")
+    }
+
+    /// Get the [`CoreSessionInfo`] used to start the challenge.
+    ///
+    /// Returns `None` if the game doesn't have any maps or players.
+    pub fn session_info(
+        &self,
+        core: &Arc<CoreMeta>,
+        map_assets: &Assets<MapMeta>,
+    ) -> Option<CoreSessionInfo> {
+        if core.stable_maps.is_empty() || core.players.is_empty() {
+            return None;
+        }
+        let rolls = self.roll(
+            core.stable_maps.len(),
+            core.players.len(),
+            core.player_hats.len(),
+        );
+
+        let map_handle = &core.stable_maps[rolls.map];
+        let map_meta = map_assets.get(&map_handle.get_bevy_handle())?.clone();
+
+        let mut player_info = <[Option<GameSessionPlayerInfo>; MAX_PLAYERS]>::default();
+        for (i, (info, (player, hat))) in player_info.iter_mut().zip(rolls.players).enumerate() {
+            *info = Some(GameSessionPlayerInfo {
+                player: core.players[player].clone(),
+                hat: hat.map(|hat| core.player_hats[hat].clone()),
+                is_ai: i != 0,
+                ai_difficulty: default(),
+            });
+        }
+
+        Some(CoreSessionInfo {
+            meta: core.clone(),
+            map_meta,
+            player_info,
+            match_settings: MatchSettings {
+                mutators: rolls.mutators,
+                seed: Some(self.seed),
+                ..default()
+            },
+        })
+    }
+
+    /// Roll the choices of the challenge from its seed, given the number of maps, players, and hats
+    /// to choose from.
+    ///
+    /// The RNG algorithm is fixed, so that every version of the game gets the same challenge from
+    /// the same seed.
+    fn roll(&self, map_count: usize, player_count: usize, hat_count: usize) -> ChallengeRolls {
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+
+        let map = rng.gen_range(0..map_count);
+        let mutators = CHALLENGE_MUTATORS
+            .into_iter()
+            .filter(|_| rng.gen_bool(MUTATOR_CHANCE))
+            .collect();
+
+        let opponent_count = rng.gen_range(1..MAX_PLAYERS);
+        let players = (0..=opponent_count)
+            .map(|_| {
+                let hat = if hat_count == 0 || rng.gen_bool(0.5) {
+                    None
+                } else {
+                    Some(rng.gen_range(0..hat_count))
+                };
+                (rng.gen_range(0..player_count), hat)
+            })
+            .collect();
+
+        ChallengeRolls {
+            map,
+            mutators,
+            players,
+        }
+    }
+}
+
+/// The choices of a [`DailyChallenge`], as indexes into the lists of the [`CoreMeta`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ChallengeRolls {
+    /// The index of the map in the [`CoreMeta::stable_maps`].
+    map: usize,
+    mutators: Vec<Mutator>,
+    /// The indexes of the player skin and hat of every player, starting with the local player.
+    players: Vec<(usize, Option<usize>)>,
+}
+
+/// The result of a daily challenge attempt.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct DailyChallengeResult {
+    /// The number of opponents killed during the attempt.
+    pub kills: u32,
+    /// The number of seconds the player survived.
+    pub survived_secs: f32,
+}
+
+impl DailyChallengeResult {
+    /// The score for the attempt.
+    pub fn score(&self) -> u32 {
+        self.kills * 100 + self.survived_secs as u32
+    }
+}
+
+/// The results of all of the daily challenges played, by day since the unix epoch.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, Deref, DerefMut)]
+pub struct DailyChallengeResults(pub std::collections::BTreeMap<u64, DailyChallengeResult>);
+
+impl DailyChallengeResults {
+    pub const STORAGE_KEY: &str = "daily_challenge_results";
+}
+
+/// Event sent when a daily challenge attempt is finished.
+pub struct DailyChallengeFinished {
+    /// The challenge that was played.
+    pub challenge: DailyChallenge,
+    /// The final result of the attempt.
+    pub result: DailyChallengeResult,
+}
+
+/// Resource containing the daily challenge currently being played, if any.
+#[derive(Resource, Default)]
+pub struct ActiveDailyChallenge(pub Option<DailyChallengeAttempt>);
+
+/// The state of an in-progress daily challenge attempt.
+pub struct DailyChallengeAttempt {
+    /// The challenge being played.
+    pub challenge: DailyChallenge,
+    /// The current result of the attempt.
+    pub result: DailyChallengeResult,
+    /// Whether or not the local player was killed, which ends the attempt.
+    killed: bool,
+}
+
+impl DailyChallengeAttempt {
+    /// Start an attempt for the given challenge.
+    ///
+    /// This immediately records an empty result for the challenge in the storage, so that leaving
+    /// the game doesn't allow you to retry the challenge.
+    pub fn start(challenge: DailyChallenge, storage: &mut Storage) -> Self {
+        let mut results = storage
+            .get::<DailyChallengeResults>(DailyChallengeResults::STORAGE_KEY)
+            .unwrap_or_default();
+        results.insert(challenge.day, default());
+        storage.set(DailyChallengeResults::STORAGE_KEY, &results);
+        storage.save();
+
+        Self {
+            challenge,
+            result: default(),
+            killed: false,
+        }
+    }
+}
+
+/// Update the score for the in-progress daily challenge, and end it when the local player dies.
+///
+/// The kills and deaths are counted by the [`MatchStats`] of the simulation, which sees every kill
+/// event, even when the session advances several frames at once.
+fn track_daily_challenge(
+    mut commands: Commands,
+    mut active_challenge: ResMut<ActiveDailyChallenge>,
+    mut session: ResMut<Session>,
+) {
+    let Some(attempt) = &mut active_challenge.0 else {
+        return;
+    };
+    if attempt.killed {
+        return;
+    }
+
+    let world = session.world();
+    let stats = world.resource::<MatchStats>().borrow().players[0];
+    let elapsed = world
+        .resource::<bones::Time>()
+        .borrow()
+        .elapsed()
+        .as_secs_f32();
+
+    // Only the kills of the local player count, not the deaths of the opponents
+    attempt.result.kills = stats.kills;
+
+    // The attempt is over once the local player dies
+    if stats.deaths > 0 {
+        attempt.killed = true;
+        attempt.result.survived_secs = elapsed;
+        commands.insert_resource(MenuPage::DailyChallenge);
+        commands.insert_resource(NextState(Some(EngineState::MainMenu)));
+    }
+}
+
+/// Record the survival time of the local player if they are still alive when the match ends.
+fn record_daily_challenge_survival(
+    mut active_challenge: ResMut<ActiveDailyChallenge>,
+    mut session: ResMut<Session>,
+) {
+    let Some(attempt) = &mut active_challenge.0 else {
+        return;
+    };
+    if attempt.killed {
+        return;
+    }
+
+    attempt.result.survived_secs = session
+        .world()
+        .resource::<bones::Time>()
+        .borrow()
+        .elapsed()
+        .as_secs_f32();
+}
+
+/// Save the result of the daily challenge when leaving the match.
+fn finish_daily_challenge(
+    mut active_challenge: ResMut<ActiveDailyChallenge>,
+    mut storage: ResMut<Storage>,
+    mut finished_events: EventWriter<DailyChallengeFinished>,
+) {
+    let Some(attempt) = active_challenge.0.take() else {
+        return;
+    };
+
+    info!(
+        "Daily challenge for {} finished with a score of {}",
+        attempt.challenge.date(),
+        attempt.result.score()
+    );
+
+    let mut results = storage
+        .get::<DailyChallengeResults>(DailyChallengeResults::STORAGE_KEY)
+        .unwrap_or_default();
+    results.insert(attempt.challenge.day, attempt.result.clone());
+    storage.set(DailyChallengeResults::STORAGE_KEY, &results);
+    storage.save();
+
+    finished_events.send(DailyChallengeFinished {
+        challenge: attempt.challenge,
+        result: attempt.result,
+    });
+}
+
+/// Get the number of days since the unix epoch.
+fn current_day() -> u64 {
+    #[cfg(not(target_arch = "wasm32"))]
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|x| x.as_millis() as u64)
+        .unwrap_or_default();
+    #[cfg(target_arch = "wasm32")]
+    let millis = js_sys::Date::now() as u64;
+
+    millis / MILLIS_PER_DAY
+}
+
+/// Scramble the bits of the given number, to turn a day into a seed.
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+/// Convert the number of days since the unix epoch to a `(year, month, day)` civil date.
+fn date_from_day(day: u64) -> (i64, u32, u32) {
+    let z = day as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month as u32, day as u32)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn date_from_day_matches_calendar() {
+        assert_eq!(date_from_day(0), (1970, 1, 1));
+        assert_eq!(date_from_day(59), (1970, 3, 1));
+        assert_eq!(date_from_day(19723), (2024, 1, 1));
+        assert_eq!(date_from_day(19782), (2024, 2, 29));
+        assert_eq!(DailyChallenge::for_day(19723).date(), "2024-01-01");
+    }
+
+    #[test]
+    fn daily_challenge_is_stable() {
+        // Players on different versions of the game must get the same challenge on the same day.
+        let rolls = DailyChallenge::for_day(19723).roll(4, 4, 6);
+        assert_eq!(
+            rolls,
+            ChallengeRolls {
+                map: 2,
+                mutators: vec![Mutator::Jetpacks, Mutator::ExplosivesOnly],
+                players: vec![(0, None), (0, None), (1, Some(2)), (0, Some(5))],
+            }
+        );
+    }
+}
@@ -0,0 +1,251 @@
+//! Director mode, for recording trailers and highlight clips.
+//!
+//! When the game is started with `--director-script <script.yaml>`, it skips the main menu and
+//! plays back a [`Replay`] while moving the camera along a path of keyframes, and slowing down the
+//! playback on marked frames. The script looks like this:
+//!
+//! ```yaml
+//! # The replay to play back, relative to the script.
+//! replay: ./match.replay.yaml
+//! # The camera is interpolated between the keyframes, and stays at the first and last keyframes
+//! # before and after them. Without keyframes the camera follows the players as usual.
+//! camera:
+//!   - frame: 0
+//!     position: [300, 200]
+//!     height: 400
+//!   - frame: 120
+//!     position: [600, 250]
+//!     height: 250
+//! # The frames of the replay that are played back slower, or faster.
+//! slow_motion:
+//!   - start: 100
+//!     end: 160
+//!     speed: 0.25
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use jumpy_core::{input::PlayerControl, replay::Replay};
+
+use crate::{
+    config::ENGINE_CONFIG,
+    prelude::*,
+    replay::{load_replay, ReplaySessionRunner},
+};
+
+/// Director mode plugin.
+pub struct JumpyDirectorPlugin;
+
+impl Plugin for JumpyDirectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(start_director.in_schedule(OnEnter(EngineState::MainMenu)));
+    }
+}
+
+/// A director script, loaded from YAML.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct DirectorScript {
+    /// The path to the replay to play back, relative to the script.
+    pub replay: PathBuf,
+    /// The keyframes of the camera path, ordered by frame.
+    #[serde(default)]
+    pub camera: Vec<CameraKeyframe>,
+    /// The ranges of frames that are played back at a different speed.
+    #[serde(default)]
+    pub slow_motion: Vec<SlowMotion>,
+}
+
+/// A keyframe of the camera path in a [`DirectorScript`].
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct CameraKeyframe {
+    /// The replay frame the camera reaches the keyframe on.
+    pub frame: usize,
+    /// The position of the center of the camera.
+    pub position: Vec2,
+    /// The height of the area seen by the camera.
+    pub height: f32,
+}
+
+/// A range of frames played back at a different speed in a [`DirectorScript`].
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SlowMotion {
+    /// The first frame of the range.
+    pub start: usize,
+    /// The frame after the last frame of the range.
+    pub end: usize,
+    /// The playback speed, `1.0` being the normal speed.
+    pub speed: f32,
+}
+
+impl DirectorScript {
+    /// Load a script from a YAML file.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let script = std::fs::read_to_string(path)?;
+        Ok(serde_yaml::from_str(&script)?)
+    }
+
+    /// Get the position and height of the camera on the given frame, or `None` if the script has
+    /// no camera keyframes.
+    pub fn camera_at(&self, frame: usize) -> Option<(Vec2, f32)> {
+        let next = self.camera.iter().position(|x| x.frame > frame);
+        let (from, to) = match next {
+            Some(0) => (self.camera[0], self.camera[0]),
+            Some(i) => (self.camera[i - 1], self.camera[i]),
+            None => {
+                let last = *self.camera.last()?;
+                (last, last)
+            }
+        };
+
+        let t = if to.frame > from.frame {
+            (frame.saturating_sub(from.frame) as f32 / (to.frame - from.frame) as f32).min(1.0)
+        } else {
+            0.0
+        };
+        Some((
+            from.position.lerp(to.position, t),
+            from.height + (to.height - from.height) * t,
+        ))
+    }
+
+    /// Get the playback speed on the given frame.
+    pub fn speed_at(&self, frame: usize) -> f32 {
+        self.slow_motion
+            .iter()
+            .find(|x| (x.start..x.end).contains(&frame))
+            .map(|x| x.speed)
+            .unwrap_or(1.0)
+    }
+}
+
+/// Implementation of [`SessionRunner`] playing back a replay as directed by a [`DirectorScript`].
+pub struct DirectorSessionRunner {
+    pub runner: ReplaySessionRunner,
+    pub script: DirectorScript,
+}
+
+impl DirectorSessionRunner {
+    pub fn new(core_meta: Arc<CoreMeta>, replay: Replay, script: DirectorScript) -> Self {
+        Self {
+            runner: ReplaySessionRunner::new(core_meta, replay),
+            script,
+        }
+    }
+
+    /// Move the camera to where the script wants it to be on the current frame.
+    fn update_camera(&mut self) {
+        let Some((position, height)) = self.script.camera_at(self.runner.frame) else {
+            return;
+        };
+
+        self.runner
+            .core
+            .world
+            .run_initialized_system(
+                move |mut cameras: bones::CompMut<bones::Camera>,
+                      mut camera_shakes: bones::CompMut<bones::CameraShake>,
+                      mut camera_states: bones::CompMut<jumpy_core::camera::CameraState>| {
+                    let Some(camera) = cameras.iter_mut().next() else { return };
+                    let camera_shake = camera_shakes.iter_mut().next().unwrap();
+                    let camera_state = camera_states.iter_mut().next().unwrap();
+                    camera.height = height;
+                    camera_shake.center = position.extend(0.0);
+                    camera_state.disable_controller = true;
+                },
+            )
+            .ok();
+    }
+}
+
+impl SessionRunner for DirectorSessionRunner {
+    fn core_session(&mut self) -> &mut CoreSession {
+        self.runner.core_session()
+    }
+
+    fn restart(&mut self) {
+        self.runner.restart();
+    }
+
+    fn set_player_input(&mut self, player_idx: usize, control: PlayerControl) {
+        self.runner.set_player_input(player_idx, control);
+    }
+
+    fn advance(&mut self, bevy_world: &mut World) -> Result<(), SessionError> {
+        if self.runner.is_finished() {
+            return Ok(());
+        }
+
+        self.runner.advance(bevy_world)?;
+        self.update_camera();
+
+        Ok(())
+    }
+
+    fn run_criteria(&mut self, time: &Time) -> ShouldRun {
+        self.runner.speed = self.script.speed_at(self.runner.frame);
+        self.runner.run_criteria(time)
+    }
+
+    fn network_player_idx(&mut self) -> Option<usize> {
+        None
+    }
+
+    fn disconnect_player(&mut self, _player_idx: usize) {}
+
+    fn input_mapping(&mut self) -> [Option<usize>; MAX_PLAYERS] {
+        self.runner.input_mapping()
+    }
+
+    fn remap_inputs(
+        &mut self,
+        _mapping: [Option<usize>; MAX_PLAYERS],
+    ) -> Result<(), RemapInputsError> {
+        Ok(())
+    }
+}
+
+/// Start playing back the director script given on the command line, the first time the main
+/// menu is reached.
+fn start_director(mut started: Local<bool>, mut session_manager: SessionManager) {
+    if *started {
+        return;
+    }
+    *started = true;
+    let Some(script_path) = &ENGINE_CONFIG.director_script else {
+        return;
+    };
+    let script_path = Path::new(script_path);
+
+    let load = || -> anyhow::Result<(DirectorScript, Replay)> {
+        let script = DirectorScript::load(script_path)?;
+        let replay_path = script_path
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .join(&script.replay);
+        let replay = load_replay(&replay_path)?;
+        Ok((script, replay))
+    };
+    let (script, replay) = match load() {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            error!(
+                "Could not load director script {}: {e}",
+                script_path.display()
+            );
+            return;
+        }
+    };
+
+    info!("Starting director mode");
+    let core_meta = session_manager.core_meta_arc.0.clone();
+    session_manager.start(DirectorSessionRunner::new(core_meta, replay, script));
+    session_manager
+        .commands
+        .insert_resource(NextState(Some(EngineState::InGame)));
+    session_manager
+        .commands
+        .insert_resource(NextState(Some(InGameState::Playing)));
+}
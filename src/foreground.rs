@@ -0,0 +1,217 @@
+//! Fading foreground layers.
+//!
+//! The tile layers of foreground layers with a
+//! [`fade_alpha`][jumpy_core::metadata::MapForegroundMeta::fade_alpha] are hidden in the game
+//! world, because tile layers can't be made transparent. Their tiles are drawn here instead, with
+//! plain bevy sprites under one parent entity per layer, and the whole layer fades while a player
+//! is behind one of its tiles.
+//!
+//! The sprites are only respawned when the tiles of the layer change, and the fading is done
+//! outside of the simulation, so it doesn't have to be snapshotted and rolled back.
+
+use jumpy_core::{foreground::ForegroundLayer, map::SpawnedMapLayerMeta, player::PlayerIdx};
+
+use crate::prelude::*;
+
+/// Foreground plugin.
+pub struct JumpyForegroundPlugin;
+
+impl Plugin for JumpyForegroundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            sync_foreground_layers
+                .run_if(in_state(EngineState::InGame))
+                .run_if(resource_exists::<Session>()),
+        )
+        .add_system(despawn_foreground_layers.in_schedule(OnExit(EngineState::InGame)));
+    }
+}
+
+/// How quickly foreground layers fade in and out.
+const FADE_LERP_FACTOR: f32 = 0.15;
+
+/// A tile of a fading foreground layer: its atlas index, and whether it is flipped horizontally
+/// and vertically.
+type LayerTile = (usize, bool, bool);
+
+/// A fading foreground layer of the game world.
+struct FadingLayer {
+    layer_idx: usize,
+    fade_alpha: f32,
+    translation: Vec3,
+    atlas: Handle<TextureAtlas>,
+    grid_size: UVec2,
+    tile_size: Vec2,
+    tiles: Vec<Option<LayerTile>>,
+    player_behind: bool,
+}
+
+/// Component added to the parent entity of the sprites of a fading foreground layer.
+#[derive(Component)]
+struct ForegroundLayerSprites {
+    layer_idx: usize,
+    /// The current opacity of the layer.
+    alpha: f32,
+    /// The atlas and the tiles the sprites were spawned for.
+    atlas: Handle<TextureAtlas>,
+    tiles: Vec<Option<LayerTile>>,
+}
+
+/// Get the fading foreground layers of the game world.
+fn fading_layers(session: &mut Session) -> Vec<FadingLayer> {
+    session
+        .world()
+        .run_initialized_system(
+            |entities: bones::Res<bones::Entities>,
+             foreground_layers: bones::Comp<ForegroundLayer>,
+             spawned_map_layer_metas: bones::Comp<SpawnedMapLayerMeta>,
+             tile_layers: bones::Comp<bones::TileLayer>,
+             tiles: bones::Comp<bones::Tile>,
+             player_indexes: bones::Comp<PlayerIdx>,
+             transforms: bones::Comp<bones::Transform>| {
+                let player_positions = entities
+                    .iter_with((&player_indexes, &transforms))
+                    .map(|(_, (_, transform))| transform.translation.truncate())
+                    .collect::<Vec<_>>();
+
+                Ok(entities
+                    .iter_with((
+                        &foreground_layers,
+                        &spawned_map_layer_metas,
+                        &tile_layers,
+                        &transforms,
+                    ))
+                    .filter_map(|(_, (foreground, layer_meta, tile_layer, transform))| {
+                        let fade_alpha = foreground.meta.fade_alpha?;
+                        let offset = transform.translation.truncate();
+                        let grid_size = tile_layer.grid_size;
+                        let player_behind = player_positions.iter().any(|pos| {
+                            let tile_pos = ((*pos - offset) / tile_layer.tile_size).floor();
+                            tile_pos.cmpge(Vec2::ZERO).all()
+                                && tile_pos.cmplt(grid_size.as_vec2()).all()
+                                && tile_layer.get(tile_pos.as_uvec2()).is_some()
+                        });
+
+                        Some(FadingLayer {
+                            layer_idx: layer_meta.layer_idx,
+                            fade_alpha,
+                            translation: transform.translation,
+                            atlas: tile_layer.atlas.get_bevy_handle_untyped().typed(),
+                            grid_size,
+                            tile_size: tile_layer.tile_size,
+                            tiles: tile_layer
+                                .tiles
+                                .iter()
+                                .map(|ent| {
+                                    ent.and_then(|ent| tiles.get(ent))
+                                        .map(|tile| (tile.idx, tile.flip_x, tile.flip_y))
+                                })
+                                .collect(),
+                            player_behind,
+                        })
+                    })
+                    .collect())
+            },
+        )
+        .unwrap()
+}
+
+/// Draw the tiles of the fading foreground layers, and fade them when players are behind them.
+fn sync_foreground_layers(
+    mut commands: Commands,
+    mut session: ResMut<Session>,
+    mut layer_sprites: Query<(
+        Entity,
+        &mut ForegroundLayerSprites,
+        &mut Transform,
+        Option<&Children>,
+    )>,
+    mut tile_sprites: Query<&mut TextureAtlasSprite>,
+) {
+    let mut layers = fading_layers(&mut session);
+
+    for (ent, mut sprites, mut transform, children) in &mut layer_sprites {
+        let Some(idx) = layers.iter().position(|x| x.layer_idx == sprites.layer_idx) else {
+            commands.entity(ent).despawn_recursive();
+            continue;
+        };
+        let layer = layers.swap_remove(idx);
+        transform.translation = layer.translation;
+
+        let target_alpha = if layer.player_behind {
+            layer.fade_alpha
+        } else {
+            1.0
+        };
+        let alpha = sprites.alpha + (target_alpha - sprites.alpha) * FADE_LERP_FACTOR;
+        let alpha_changed = alpha != sprites.alpha;
+        sprites.alpha = alpha;
+
+        if sprites.atlas != layer.atlas || sprites.tiles != layer.tiles {
+            commands.entity(ent).despawn_descendants();
+            spawn_tile_sprites(&mut commands, ent, &layer, alpha);
+            sprites.atlas = layer.atlas;
+            sprites.tiles = layer.tiles;
+        } else if alpha_changed {
+            for child in children.into_iter().flatten() {
+                if let Ok(mut sprite) = tile_sprites.get_mut(*child) {
+                    sprite.color.set_a(alpha);
+                }
+            }
+        }
+    }
+
+    // Spawn the layers that don't have sprites yet
+    for layer in layers {
+        let ent = commands
+            .spawn((
+                Name::new(format!("Foreground Layer {}", layer.layer_idx)),
+                SpatialBundle::from_transform(Transform::from_translation(layer.translation)),
+            ))
+            .id();
+        spawn_tile_sprites(&mut commands, ent, &layer, 1.0);
+        commands.entity(ent).insert(ForegroundLayerSprites {
+            layer_idx: layer.layer_idx,
+            alpha: 1.0,
+            atlas: layer.atlas,
+            tiles: layer.tiles,
+        });
+    }
+}
+
+/// Spawn the sprites of the tiles of a fading foreground layer, as children of its entity.
+fn spawn_tile_sprites(commands: &mut Commands, parent: Entity, layer: &FadingLayer, alpha: f32) {
+    commands.entity(parent).with_children(|parent| {
+        for (i, tile) in layer.tiles.iter().enumerate() {
+            let Some((index, flip_x, flip_y)) = *tile else {
+                continue;
+            };
+            let i = i as u32;
+            let tile_pos = UVec2::new(i % layer.grid_size.x, i / layer.grid_size.x);
+            let translation = (tile_pos.as_vec2() + 0.5) * layer.tile_size;
+            let mut sprite = TextureAtlasSprite {
+                index,
+                flip_x,
+                flip_y,
+                ..default()
+            };
+            sprite.color.set_a(alpha);
+            parent.spawn(SpriteSheetBundle {
+                transform: Transform::from_translation(translation.extend(0.0)),
+                sprite,
+                texture_atlas: layer.atlas.clone(),
+                ..default()
+            });
+        }
+    });
+}
+
+/// Remove the foreground layer sprites when leaving the match.
+fn despawn_foreground_layers(
+    mut commands: Commands,
+    layer_sprites: Query<Entity, With<ForegroundLayerSprites>>,
+) {
+    for ent in &layer_sprites {
+        commands.entity(ent).despawn_recursive();
+    }
+}
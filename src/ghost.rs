@@ -0,0 +1,226 @@
+//! Ghost opponents.
+//!
+//! When the game is started with `--ghost <path>`, local matches played on the map of the given
+//! replay show a translucent "ghost" of the first human player of the replay, repeating its
+//! recorded run. This is useful for practicing movement routes and matchup timing against a
+//! previous run.
+//!
+//! The ghost is played back by a [`Ghost`] in its own [`CoreSession`], next to the match, so it
+//! can't interact with the players of the match, and it doesn't affect the simulation. Its sprites
+//! are copied to plain bevy sprites every frame. The ghost starts over when the match or round is
+//! restarted.
+
+use std::path::Path;
+
+use jumpy_core::{attachment::PlayerBodyAttachment, player::PlayerIdx, replay::Replay};
+
+use crate::{config::ENGINE_CONFIG, prelude::*, replay::load_replay};
+
+/// Ghost plugin.
+pub struct JumpyGhostPlugin;
+
+impl Plugin for JumpyGhostPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            sync_ghost_sprites
+                .run_if(in_state(EngineState::InGame))
+                .run_if(resource_exists::<Session>()),
+        )
+        .add_system(despawn_ghost_sprites.in_schedule(OnExit(EngineState::InGame)));
+    }
+}
+
+/// The opacity of the ghost sprites.
+const GHOST_ALPHA: f32 = 0.4;
+
+/// Plays back the run of a player from a replay.
+pub struct Ghost {
+    pub core: CoreSession,
+    pub replay: Replay,
+    /// The player of the replay shown as the ghost.
+    pub player_idx: usize,
+    /// The next frame of the replay to play back.
+    pub frame: usize,
+}
+
+impl Ghost {
+    /// Create a ghost of the first human player of the replay, or `None` if there isn't one.
+    pub fn new(core_meta: Arc<CoreMeta>, replay: Replay) -> Option<Self> {
+        let player_idx = replay
+            .players
+            .iter()
+            .position(|player| player.as_ref().map_or(false, |x| !x.is_ai))?;
+        let mut core = CoreSession::new(replay.session_info(core_meta));
+        core.time_step = replay.time_step;
+        Some(Self {
+            core,
+            replay,
+            player_idx,
+            frame: 0,
+        })
+    }
+
+    /// Load the ghost given on the command line for a match, if it was recorded on the map of the
+    /// match.
+    pub fn from_config(match_core: &CoreSession) -> Option<Self> {
+        let path = ENGINE_CONFIG.ghost.as_ref()?;
+        let replay = match load_replay(Path::new(path)) {
+            Ok(replay) => replay,
+            Err(e) => {
+                error!("Could not load ghost replay {path}: {e}");
+                return None;
+            }
+        };
+        if replay.map.name != match_core.info.map_meta.name {
+            info!(
+                "Not showing the ghost, because it was recorded on {}",
+                replay.map.name
+            );
+            return None;
+        }
+
+        let ghost = Self::new(match_core.info.meta.clone(), replay);
+        if ghost.is_none() {
+            warn!("The ghost replay doesn't have a human player");
+        }
+        ghost
+    }
+
+    /// Start the run of the ghost over.
+    pub fn restart(&mut self) {
+        self.core.restart();
+        self.core.time_step = self.replay.time_step;
+        self.frame = 0;
+    }
+
+    /// Simulate the next frame of the replay, if it isn't over.
+    pub fn advance(&mut self, bevy_world: &mut World) {
+        if self.frame >= self.replay.frames.len() {
+            return;
+        }
+
+        let replay = &self.replay;
+        let frame = self.frame;
+        self.core
+            .update_input(|inputs| replay.apply_frame(frame, inputs));
+        self.core.advance(bevy_world);
+        self.frame += 1;
+
+        // Only the sounds of the match are played
+        self.core
+            .world
+            .run_initialized_system(|mut audio_events: bones::ResMut<bones::AudioEvents>| {
+                audio_events.queue.clear();
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    /// Get the sprites of the ghost player and of the sprites attached to it, such as its face and
+    /// hat.
+    pub fn sprites(&mut self) -> Vec<(bones::AtlasSprite, bones::Transform)> {
+        let ghost_idx = self.player_idx;
+        self.core
+            .world
+            .run_initialized_system(
+                |entities: bones::Res<bones::Entities>,
+                 player_indexes: bones::Comp<PlayerIdx>,
+                 attachments: bones::Comp<PlayerBodyAttachment>,
+                 atlas_sprites: bones::Comp<bones::AtlasSprite>,
+                 transforms: bones::Comp<bones::Transform>| {
+                    let Some(player_ent) = entities
+                        .iter_with(&player_indexes)
+                        .find(|(_, idx)| idx.0 == ghost_idx)
+                        .map(|(ent, _)| ent)
+                    else {
+                        return Ok(Vec::new());
+                    };
+
+                    Ok(entities
+                        .iter_with((&atlas_sprites, &transforms))
+                        .filter(|(ent, _)| {
+                            *ent == player_ent
+                                || attachments
+                                    .get(*ent)
+                                    .map_or(false, |x| x.player == player_ent)
+                        })
+                        .map(|(_, (sprite, transform))| (sprite.clone(), *transform))
+                        .collect())
+                },
+            )
+            .unwrap()
+    }
+}
+
+/// Marker component for the bevy sprites showing the ghost.
+#[derive(Component)]
+struct GhostSprite;
+
+/// Copy the sprites of the ghost of the local session to the ghost bevy sprites.
+fn sync_ghost_sprites(
+    mut commands: Commands,
+    mut session: ResMut<Session>,
+    mut ghost_sprites: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut TextureAtlasSprite,
+            &mut Handle<TextureAtlas>,
+        ),
+        With<GhostSprite>,
+    >,
+) {
+    let sprites = session
+        .downcast_mut::<LocalSessionRunner>()
+        .and_then(|x| x.ghost.as_mut())
+        .map(|ghost| ghost.sprites())
+        .unwrap_or_default();
+
+    let mut ghost_sprites = ghost_sprites.iter_mut();
+    for (sprite, transform) in sprites {
+        let bevy_transform = Transform {
+            translation: transform.translation,
+            rotation: transform.rotation,
+            scale: transform.scale,
+        };
+        let mut color = sprite.color;
+        color.set_a(color.a() * GHOST_ALPHA);
+        let bevy_sprite = TextureAtlasSprite {
+            index: sprite.index,
+            flip_x: sprite.flip_x,
+            color,
+            ..default()
+        };
+        let atlas = sprite.atlas.get_bevy_handle_untyped().typed();
+
+        if let Some((_, mut ghost_transform, mut ghost_sprite, mut ghost_atlas)) =
+            ghost_sprites.next()
+        {
+            *ghost_transform = bevy_transform;
+            *ghost_sprite = bevy_sprite;
+            *ghost_atlas = atlas;
+        } else {
+            commands.spawn((
+                GhostSprite,
+                SpriteSheetBundle {
+                    transform: bevy_transform,
+                    sprite: bevy_sprite,
+                    texture_atlas: atlas,
+                    ..default()
+                },
+            ));
+        }
+    }
+
+    // Remove the sprites that aren't needed anymore
+    for (ent, ..) in ghost_sprites {
+        commands.entity(ent).despawn();
+    }
+}
+
+/// Remove the ghost sprites when leaving the match.
+fn despawn_ghost_sprites(mut commands: Commands, ghost_sprites: Query<Entity, With<GhostSprite>>) {
+    for ent in &ghost_sprites {
+        commands.entity(ent).despawn();
+    }
+}
@@ -0,0 +1,149 @@
+//! Input latency measurement.
+//!
+//! When enabled from the frame diagnostics window, the [`LatencyMeter`] measures:
+//!
+//! - The input latency: the time between local input being collected and the simulation of the
+//!   first frame that uses it. In network games this includes the GGRS input delay.
+//! - The present latency: the time between the end of a simulation update and the render app
+//!   submitting the resulting frame to the GPU.
+//!
+//! The measurements are reported as Bevy [`Diagnostics`], which makes it easier to see the effect
+//! of tuning the input delay settings.
+
+use std::{collections::VecDeque, sync::Mutex};
+
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticId, Diagnostics},
+    render::{RenderApp, RenderSet},
+    utils::Instant,
+};
+
+use crate::prelude::*;
+
+/// Latency meter plugin.
+pub struct JumpyLatencyPlugin;
+
+impl Plugin for JumpyLatencyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_diagnostics)
+            .add_system(update_diagnostics.in_base_set(CoreSet::Last));
+
+        if let Ok(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.add_system(record_frame_presented.in_set(RenderSet::Cleanup));
+        }
+    }
+}
+
+/// Diagnostic for the time between local input being collected and being simulated, in
+/// milliseconds.
+pub const INPUT_LATENCY: DiagnosticId =
+    DiagnosticId::from_u128(0x6f4b_2d91_c3a7_4e58_9b1f_07d2_e5a8_3c64);
+
+/// Diagnostic for the time between a simulation update and the frame being submitted for
+/// rendering, in milliseconds.
+pub const PRESENT_LATENCY: DiagnosticId =
+    DiagnosticId::from_u128(0x1a8e_5c37_f20b_4d96_a4e3_68b5_0c7f_d912);
+
+/// The latency meter, shared by the main app, the session runners, and the render app.
+pub static LATENCY_METER: Lazy<Mutex<LatencyMeter>> = Lazy::new(default);
+
+/// Measures the input and present latency.
+#[derive(Default)]
+pub struct LatencyMeter {
+    /// Whether or not latency is being measured.
+    pub enabled: bool,
+    /// The time the latest local input was collected, if it hasn't been handed to the simulation.
+    input_collected: Option<Instant>,
+    /// The inputs handed to a network session, with the frame they will be simulated on.
+    inputs_in_flight: VecDeque<(i32, Instant)>,
+    /// The time the latest simulation update ended, if it hasn't been presented yet.
+    simulated: Option<Instant>,
+    /// Input latency measurements, in milliseconds.
+    input_latency: Vec<f64>,
+    /// Present latency measurements, in milliseconds.
+    present_latency: Vec<f64>,
+}
+
+impl LatencyMeter {
+    /// Record that local input has been collected.
+    pub fn input_collected(&mut self) {
+        if self.enabled {
+            self.input_collected = Some(Instant::now());
+        }
+    }
+
+    /// Record that a local session has simulated a frame with the input collected last.
+    pub fn local_frame_simulated(&mut self) {
+        if let Some(collected) = self.input_collected.take() {
+            self.input_latency.push(ms(collected.elapsed()));
+        }
+    }
+
+    /// Record that the input collected last has been added to a network session, to be simulated
+    /// on the given frame.
+    pub fn input_scheduled(&mut self, frame: i32) {
+        if let Some(collected) = self.input_collected.take() {
+            self.inputs_in_flight.push_back((frame, collected));
+        }
+    }
+
+    /// Record that a network session has simulated the given frame for the first time.
+    pub fn network_frame_simulated(&mut self, frame: i32) {
+        while let Some(&(input_frame, collected)) = self.inputs_in_flight.front() {
+            if input_frame > frame {
+                break;
+            }
+            self.inputs_in_flight.pop_front();
+            self.input_latency.push(ms(collected.elapsed()));
+        }
+    }
+
+    /// Record that a simulation update has finished, and is waiting to be presented.
+    pub fn update_simulated(&mut self) {
+        if self.enabled {
+            self.simulated = Some(Instant::now());
+        }
+    }
+
+    /// Reset the meter, keeping it enabled or disabled.
+    pub fn reset(&mut self) {
+        *self = Self {
+            enabled: self.enabled,
+            ..default()
+        };
+    }
+}
+
+fn ms(duration: std::time::Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn setup_diagnostics(mut diagnostics: ResMut<Diagnostics>) {
+    diagnostics.add(Diagnostic::new(INPUT_LATENCY, "input_latency", 60).with_suffix("ms"));
+    diagnostics.add(Diagnostic::new(PRESENT_LATENCY, "present_latency", 60).with_suffix("ms"));
+}
+
+/// Report the latest measurements to the [`Diagnostics`].
+fn update_diagnostics(mut diagnostics: ResMut<Diagnostics>) {
+    let Ok(mut meter) = LATENCY_METER.lock() else {
+        return;
+    };
+
+    for latency in meter.input_latency.drain(..) {
+        diagnostics.add_measurement(INPUT_LATENCY, || latency);
+    }
+    for latency in meter.present_latency.drain(..) {
+        diagnostics.add_measurement(PRESENT_LATENCY, || latency);
+    }
+}
+
+/// Measure the present latency once the render app has submitted the frame.
+fn record_frame_presented() {
+    let Ok(mut meter) = LATENCY_METER.lock() else {
+        return;
+    };
+
+    if let Some(simulated) = meter.simulated.take() {
+        meter.present_latency.push(ms(simulated.elapsed()));
+    }
+}
@@ -0,0 +1,238 @@
+//! Online leaderboard client.
+//!
+//! This is only compiled with the `leaderboard` cargo feature, and does nothing unless the player
+//! has opted in to sharing their scores in the settings menu.
+//!
+//! Scores are submitted anonymously: the only thing sent along with a score is a random id that is
+//! generated for this installation of the game, so that the leaderboard can tell which scores came
+//! from the same player.
+//!
+//! The leaderboard server is expected to provide the following endpoints:
+//!
+//! - `POST {server}/boards/{board}/scores` with a [`LeaderboardEntry`] JSON body.
+//! - `GET {server}/boards/{board}/top` returning a JSON list of [`LeaderboardEntry`].
+
+use crate::{
+    daily_challenge::DailyChallengeFinished,
+    networking::ranked::RankedMatchFinished,
+    prelude::*,
+    utils::{bi_channel, BiChannelClient, BiChannelServer},
+};
+
+/// Leaderboard plugin.
+pub struct JumpyLeaderboardPlugin;
+
+impl Plugin for JumpyLeaderboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Leaderboard>()
+            .add_system(handle_leaderboard_responses)
+            .add_system(submit_daily_challenge_scores)
+            .add_system(submit_ranked_ratings);
+    }
+}
+
+/// The timeout for requests to the leaderboard server.
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Channel used to talk to the leaderboard server.
+///
+/// Spawns a thread that makes the actual HTTP requests.
+static LEADERBOARD_CLIENT: Lazy<BiChannelClient<LeaderboardRequest, LeaderboardResponse>> =
+    Lazy::new(|| {
+        let (client, server) = bi_channel();
+
+        std::thread::spawn(move || leaderboard_client(server));
+
+        client
+    });
+
+/// A score on the leaderboard.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LeaderboardEntry {
+    /// The anonymous id of the player that set the score.
+    pub player_id: String,
+    /// The score.
+    pub score: u32,
+}
+
+/// The id of the leaderboard for a given daily challenge.
+pub fn daily_challenge_board(challenge: &DailyChallenge) -> String {
+    format!("daily-{}", challenge.date())
+}
+
+/// The id of the leaderboard for ranked matches, where the score is the player's rating after each
+/// ranked match.
+pub const RANKED_BOARD: &str = "ranked";
+
+enum LeaderboardRequest {
+    Submit {
+        server: String,
+        board: String,
+        entry: LeaderboardEntry,
+    },
+    FetchTop {
+        server: String,
+        board: String,
+    },
+}
+
+enum LeaderboardResponse {
+    TopScores {
+        board: String,
+        entries: Vec<LeaderboardEntry>,
+    },
+    Error(String),
+}
+
+/// Resource used to submit scores to, and fetch scores from, the leaderboard server.
+#[derive(Resource, Default)]
+pub struct Leaderboard {
+    /// The top scores that have been fetched, by board.
+    pub top_scores: HashMap<String, Vec<LeaderboardEntry>>,
+    /// The last error that happened while talking to the leaderboard server.
+    pub last_error: Option<String>,
+}
+
+impl Leaderboard {
+    /// The key used to store the anonymous player id in the [`Storage`].
+    pub const PLAYER_ID_STORAGE_KEY: &str = "leaderboard_player_id";
+
+    /// Submit a score to the given board.
+    ///
+    /// Does nothing if the player hasn't opted in to the leaderboard.
+    pub fn submit(&self, settings: &Settings, storage: &mut Storage, board: &str, score: u32) {
+        if !settings.leaderboard_enabled || settings.leaderboard_server.is_empty() {
+            return;
+        }
+
+        LEADERBOARD_CLIENT
+            .try_send(LeaderboardRequest::Submit {
+                server: settings.leaderboard_server.clone(),
+                board: board.into(),
+                entry: LeaderboardEntry {
+                    player_id: Self::player_id(storage),
+                    score,
+                },
+            })
+            .ok();
+    }
+
+    /// Request the top scores for the given board.
+    ///
+    /// Does nothing if the player hasn't opted in to the leaderboard. Once the scores have been
+    /// fetched, they will be available in [`top_scores`][Self::top_scores].
+    pub fn fetch_top(&mut self, settings: &Settings, board: &str) {
+        if !settings.leaderboard_enabled || settings.leaderboard_server.is_empty() {
+            return;
+        }
+
+        self.last_error = None;
+        LEADERBOARD_CLIENT
+            .try_send(LeaderboardRequest::FetchTop {
+                server: settings.leaderboard_server.clone(),
+                board: board.into(),
+            })
+            .ok();
+    }
+
+    /// Get the anonymous player id, generating a new one if this is the first time.
+    fn player_id(storage: &mut Storage) -> String {
+        if let Some(id) = storage.get::<String>(Self::PLAYER_ID_STORAGE_KEY) {
+            id
+        } else {
+            let id = format!("{:016x}", rand::random::<u64>());
+            storage.set(Self::PLAYER_ID_STORAGE_KEY, &id);
+            storage.save();
+            id
+        }
+    }
+}
+
+/// Collect the responses from the leaderboard client thread.
+fn handle_leaderboard_responses(mut leaderboard: ResMut<Leaderboard>) {
+    while let Ok(response) = LEADERBOARD_CLIENT.try_recv() {
+        match response {
+            LeaderboardResponse::TopScores { board, entries } => {
+                leaderboard.top_scores.insert(board, entries);
+            }
+            LeaderboardResponse::Error(e) => {
+                warn!("Leaderboard request failed: {e}");
+                leaderboard.last_error = Some(e);
+            }
+        }
+    }
+}
+
+/// Submit the scores of finished daily challenges.
+fn submit_daily_challenge_scores(
+    mut events: EventReader<DailyChallengeFinished>,
+    leaderboard: Res<Leaderboard>,
+    game: Res<GameMeta>,
+    mut storage: ResMut<Storage>,
+) {
+    for event in events.iter() {
+        let settings = Settings::get_stored_or_default(&game, &mut storage).into_owned();
+        leaderboard.submit(
+            &settings,
+            &mut storage,
+            &daily_challenge_board(&event.challenge),
+            event.result.score(),
+        );
+    }
+}
+
+/// Submit the ratings of the player after their ranked matches.
+fn submit_ranked_ratings(
+    mut events: EventReader<RankedMatchFinished>,
+    leaderboard: Res<Leaderboard>,
+    game: Res<GameMeta>,
+    mut storage: ResMut<Storage>,
+) {
+    for event in events.iter() {
+        let settings = Settings::get_stored_or_default(&game, &mut storage).into_owned();
+        leaderboard.submit(
+            &settings,
+            &mut storage,
+            RANKED_BOARD,
+            event.rating.rating.round().max(0.0) as u32,
+        );
+    }
+}
+
+/// The leaderboard client thread.
+fn leaderboard_client(server: BiChannelServer<LeaderboardRequest, LeaderboardResponse>) {
+    let agent = ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build();
+
+    while let Ok(request) = server.recv_blocking() {
+        let result = match request {
+            LeaderboardRequest::Submit {
+                server,
+                board,
+                entry,
+            } => agent
+                .post(&format!("{server}/boards/{board}/scores"))
+                .send_json(&entry)
+                .map(|_| None)
+                .map_err(|e| e.to_string()),
+            LeaderboardRequest::FetchTop { server, board } => agent
+                .get(&format!("{server}/boards/{board}/top"))
+                .call()
+                .map_err(|e| e.to_string())
+                .and_then(|response| {
+                    response
+                        .into_json::<Vec<LeaderboardEntry>>()
+                        .map_err(|e| e.to_string())
+                })
+                .map(|entries| Some(LeaderboardResponse::TopScores { board, entries })),
+        };
+
+        let response = match result {
+            Ok(Some(response)) => response,
+            Ok(None) => continue,
+            Err(e) => LeaderboardResponse::Error(e),
+        };
+        if server.send_blocking(response).is_err() {
+            break;
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//! Lifetime statistics.
+//!
+//! When a match is over, the [`MatchStats`] of every local human player are added to the
+//! [`LifetimeStats`] of the fighter that they played as, which are stored locally in [`Storage`]
+//! and shown on the stats page of the main menu. The winners of the last round are the winners of
+//! the match.
+//!
+//! Only matches that are played to the end count, so matches without a number of rounds to win,
+//! and matches left early, aren't recorded.
+
+use std::collections::BTreeMap;
+
+use jumpy_core::{match_stats::MatchStats, rounds::MatchScore};
+
+use crate::prelude::*;
+
+/// Lifetime statistics plugin.
+pub struct JumpyLifetimeStatsPlugin;
+
+impl Plugin for JumpyLifetimeStatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            record_lifetime_stats
+                .in_schedule(OnEnter(InGameState::Results))
+                .run_if(resource_exists::<Session>()),
+        );
+    }
+}
+
+/// The lifetime statistics of a single fighter.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct FighterStats {
+    /// The number of matches played.
+    pub matches_played: u32,
+    /// The number of matches won.
+    pub matches_won: u32,
+    /// The number of opponents killed.
+    pub kills: u32,
+    /// The number of times the fighter was killed.
+    pub deaths: u32,
+    /// The number of times each kind of item was used, by the name of the item.
+    pub item_uses: BTreeMap<String, u32>,
+}
+
+impl FighterStats {
+    /// The share of the matches played that were won, between `0.0` and `1.0`.
+    pub fn win_rate(&self) -> f32 {
+        if self.matches_played == 0 {
+            return 0.0;
+        }
+        self.matches_won as f32 / self.matches_played as f32
+    }
+
+    /// The name of the item used the most, if any item was used.
+    ///
+    /// Ties are broken by the name of the item, so that the favorite doesn't flicker.
+    pub fn favorite_item(&self) -> Option<&str> {
+        self.item_uses
+            .iter()
+            .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Add the statistics of a match to the lifetime statistics.
+    pub fn record_match(&mut self, match_stats: &MatchStats, player_idx: usize, won: bool) {
+        let stats = &match_stats.players[player_idx];
+        self.matches_played += 1;
+        self.matches_won += won as u32;
+        self.kills += stats.kills;
+        self.deaths += stats.deaths;
+        for (item, uses) in &match_stats.item_uses[player_idx] {
+            *self.item_uses.entry(item.clone()).or_default() += uses;
+        }
+    }
+}
+
+/// The lifetime statistics of every fighter played as, by the name of the fighter.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, Deref, DerefMut)]
+pub struct LifetimeStats(pub BTreeMap<String, FighterStats>);
+
+impl LifetimeStats {
+    pub const STORAGE_KEY: &str = "lifetime_stats";
+}
+
+/// Add the statistics of the match to the lifetime statistics of the local human players.
+fn record_lifetime_stats(
+    mut session: ResMut<Session>,
+    mut storage: ResMut<Storage>,
+    player_assets: Res<Assets<PlayerMeta>>,
+) {
+    // Only the player on this computer is counted in network matches
+    let network_player_idx = session.network_player_idx();
+    let (match_stats, round_winners, player_inputs) = session
+        .world()
+        .run_initialized_system(
+            |match_stats: bones::Res<MatchStats>,
+             match_score: bones::Res<MatchScore>,
+             player_inputs: bones::Res<jumpy_core::input::PlayerInputs>| {
+                Ok((
+                    match_stats.clone(),
+                    match_score.round_winners,
+                    player_inputs.players.clone(),
+                ))
+            },
+        )
+        .unwrap();
+
+    let mut lifetime_stats = storage
+        .get::<LifetimeStats>(LifetimeStats::STORAGE_KEY)
+        .unwrap_or_default();
+    let mut recorded_any = false;
+    for (player_idx, input) in player_inputs.iter().enumerate() {
+        if !input.active || input.is_ai || network_player_idx.map_or(false, |x| x != player_idx) {
+            continue;
+        }
+        let Some(player_meta) = player_assets.get(&input.selected_player.get_bevy_handle()) else {
+            continue;
+        };
+        lifetime_stats
+            .entry(player_meta.name.clone())
+            .or_default()
+            .record_match(&match_stats, player_idx, round_winners[player_idx]);
+        recorded_any = true;
+    }
+
+    if recorded_any {
+        storage.set(LifetimeStats::STORAGE_KEY, &lifetime_stats);
+        storage.save();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn favorite_item_is_the_most_used() {
+        let mut stats = FighterStats::default();
+        assert_eq!(stats.favorite_item(), None);
+
+        let mut match_stats = MatchStats::default();
+        match_stats.record_item_use(1, "Sword");
+        match_stats.record_item_use(1, "Grenades");
+        match_stats.record_item_use(1, "Grenades");
+        match_stats.record_item_use(0, "Musket");
+        stats.record_match(&match_stats, 1, true);
+        stats.record_match(&match_stats, 1, false);
+
+        assert_eq!(stats.favorite_item(), Some("Grenades"));
+        assert_eq!(stats.item_uses.get("Grenades"), Some(&4));
+        assert_eq!(stats.item_uses.get("Musket"), None);
+        assert_eq!(stats.win_rate(), 0.5);
+    }
+}
@@ -280,6 +280,11 @@ impl<'w, 's> GameLoader<'w, 's> {
             icon.egui_texture_id = egui_ctx.add_image(icon.image.inner.clone_weak());
         }
 
+        // Add award icons to egui context
+        for icon in game.ui_theme.award_icons.as_mut_list() {
+            icon.egui_texture_id = egui_ctx.add_image(icon.image.inner.clone_weak());
+        }
+
         // Insert the game resource
         commands.insert_resource(game.clone());
         commands.insert_resource(CoreMetaArc(Arc::new(core.clone())));
@@ -169,7 +169,15 @@ impl Plugin for JumpyLogPlugin {
                 (fmt_layer, tracy_layer, puffin_layer)
             };
 
-            let subscriber = subscriber.with(fmt_layer).with(console_layer);
+            // Layer to keep the most recent logs for crash reports
+            let crash_report_layer = fmt::Layer::default()
+                .with_ansi(false)
+                .with_writer(crate::crash_report::RecentLogWriter::default);
+
+            let subscriber = subscriber
+                .with(fmt_layer)
+                .with(console_layer)
+                .with(crash_report_layer);
 
             // #[cfg(feature = "tracing-chrome")]
             // let subscriber = subscriber.with(chrome_layer);
@@ -38,13 +38,27 @@ pub mod external {
 #[allow(clippy::single_component_path_imports)]
 use bevy_dylib;
 
+pub mod announcer;
 pub mod assets;
 pub mod audio;
 pub mod bevy_states;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod bug_report;
 pub mod config;
 pub mod console;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod crash_report;
+pub mod daily_challenge;
 pub mod debug;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod director;
+pub mod foreground;
+pub mod ghost;
 pub mod input;
+pub mod latency;
+#[cfg(all(feature = "leaderboard", not(target_arch = "wasm32")))]
+pub mod leaderboard;
+pub mod lifetime_stats;
 pub mod loading;
 pub mod localization;
 pub mod logs;
@@ -52,12 +66,15 @@ pub mod metadata;
 pub mod platform;
 pub mod profiling;
 pub mod puffin_tracing;
+pub mod replay;
 pub mod session;
+#[cfg(all(feature = "steam", not(target_arch = "wasm32")))]
+pub mod steam;
+pub mod tournament;
 pub mod ui;
 pub mod utils;
 
 pub mod camera;
-#[cfg(not(target_arch = "wasm32"))]
 pub mod networking;
 pub mod prelude;
 use prelude::*;
@@ -122,14 +139,53 @@ pub fn main() {
         .add_plugin(bevy_framepace::FramepacePlugin)
         .add_plugin(JumpyPlayerInputPlugin)
         .add_plugin(JumpySessionPlugin)
+        .add_plugin(JumpyDailyChallengePlugin)
+        .add_plugin(ghost::JumpyGhostPlugin)
+        .add_plugin(foreground::JumpyForegroundPlugin)
+        .add_plugin(lifetime_stats::JumpyLifetimeStatsPlugin)
+        .add_plugin(tournament::JumpyTournamentPlugin)
         .add_plugin(JumpyUiPlugin)
         .add_plugin(JumpyAudioPlugin)
+        .add_plugin(announcer::JumpyAnnouncerPlugin)
         .add_plugin(JumpyPlatformPlugin)
         .add_plugin(JumpyLoadingPlugin)
         .add_plugin(JumpyAssetPlugin)
         .add_plugin(JumpyLocalizationPlugin)
         .add_plugin(JumpyDebugPlugin)
-        .add_plugin(JumpyConsolePlugin);
+        .add_plugin(latency::JumpyLatencyPlugin)
+        .add_plugin(JumpyConsolePlugin)
+        .add_plugin(networking::JumpyNetworkingPlugin)
+        .add_plugin(networking::conditions::JumpyNetworkConditionsPlugin)
+        .add_plugin(networking::diagnostics::JumpyNetworkDiagnosticsPlugin)
+        .add_plugin(networking::desync::JumpyDesyncPlugin)
+        .add_plugin(networking::disconnects::JumpyDisconnectVotePlugin)
+        .add_plugin(networking::resume::JumpyMatchResumePlugin)
+        .add_plugin(networking::smoothing::JumpyRollbackSmoothingPlugin)
+        .add_plugin(networking::moderation::JumpyModerationPlugin)
+        .add_plugin(networking::pause::JumpyNetworkPausePlugin)
+        .add_plugin(networking::chat::JumpyTextChatPlugin);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    app.add_plugin(crash_report::JumpyCrashReportPlugin)
+        .add_plugin(bug_report::JumpyBugReportPlugin)
+        .add_plugin(director::JumpyDirectorPlugin)
+        .add_plugin(replay::JumpyReplayPlugin)
+        .add_plugin(networking::migration::JumpyMigrationPlugin)
+        .add_plugin(networking::ranked::JumpyRankedPlugin)
+        .add_plugin(networking::quick_match::JumpyQuickMatchPlugin)
+        .add_plugin(networking::upgrade::JumpyMatchUpgradePlugin)
+        .add_plugin(networking::dedicated::JumpyDedicatedHostPlugin)
+        .add_plugin(networking::admin::JumpyAdminApiPlugin)
+        .add_plugin(networking::metrics::JumpyMetricsPlugin);
+    #[cfg(target_arch = "wasm32")]
+    app.add_plugin(networking::webrtc::JumpyWebRtcPlugin);
+
+    #[cfg(all(feature = "leaderboard", not(target_arch = "wasm32")))]
+    app.add_plugin(leaderboard::JumpyLeaderboardPlugin);
+    #[cfg(all(feature = "steam", not(target_arch = "wasm32")))]
+    app.add_plugin(steam::JumpySteamPlugin);
+    #[cfg(all(feature = "voice-chat", not(target_arch = "wasm32")))]
+    app.add_plugin(networking::voice::JumpyVoiceChatPlugin);
 
     debug!(?engine_config, "Starting game");
 
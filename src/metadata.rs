@@ -32,12 +32,44 @@ pub struct GameMeta {
 #[serde(deny_unknown_fields)]
 pub struct MusicMeta {
     pub title_screen: AssetHandle<AudioSource>,
-    pub fight: Vec<AssetHandle<AudioSource>>,
+    pub fight: Vec<FightMusicMeta>,
     pub character_screen: AssetHandle<AudioSource>,
     pub results_screen: AssetHandle<AudioSource>,
     pub credits: AssetHandle<AudioSource>,
 }
 
+/// A song played during matches.
+///
+/// It may either be a single song, or a calm and an intense stem of the same song, that are
+/// crossfaded according to the [`MusicIntensity`][crate::audio::MusicIntensity] of the match.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum FightMusicMeta {
+    Single(AssetHandle<AudioSource>),
+    Layered {
+        calm: AssetHandle<AudioSource>,
+        intense: AssetHandle<AudioSource>,
+    },
+}
+
+impl FightMusicMeta {
+    /// Get the song, or its calm stem if it is layered.
+    pub fn calm(&self) -> &AssetHandle<AudioSource> {
+        match self {
+            FightMusicMeta::Single(song) => song,
+            FightMusicMeta::Layered { calm, .. } => calm,
+        }
+    }
+
+    /// Get the intense stem of the song, if it is layered.
+    pub fn intense(&self) -> Option<&AssetHandle<AudioSource>> {
+        match self {
+            FightMusicMeta::Single(_) => None,
+            FightMusicMeta::Layered { intense, .. } => Some(intense),
+        }
+    }
+}
+
 #[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
 #[serde(deny_unknown_fields)]
 pub struct MainMenuMeta {
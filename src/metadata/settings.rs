@@ -1,6 +1,7 @@
 use std::borrow::Cow;
 
 use bevy::prelude::Gamepad;
+use jumpy_core::MAX_PLAYERS;
 use leafwing_input_manager::{axislike::VirtualDPad, prelude::InputMap, user_input::InputKind};
 use serde::{Deserialize, Serialize};
 
@@ -15,6 +16,45 @@ pub struct Settings {
     pub player_controls: PlayerControlMethods,
     /// The address of the matchmaking server to connect to for online games.
     pub matchmaking_server: String,
+    /// Whether or not the player has opted in to submitting their scores to the online leaderboard.
+    #[serde(default)]
+    pub leaderboard_enabled: bool,
+    /// The URL of the leaderboard server to submit scores to.
+    #[serde(default)]
+    pub leaderboard_server: String,
+    /// The URL of the server that crash reports are submitted to, when the player chooses to.
+    #[serde(default)]
+    pub crash_report_server: String,
+    /// The number of seconds players may go without making any input in network matches that we
+    /// host, before they are considered AFK. `0` disables AFK detection.
+    #[serde(default = "default_afk_timeout")]
+    pub afk_timeout: f32,
+    /// The maximum number of kilobytes per second sent through the relay of the matchmaking server
+    /// during an online match, or `0` for no limit.
+    #[serde(default)]
+    pub relay_bandwidth_cap: u32,
+    /// Whether or not the ping and rollback diagnostics are shown during network matches.
+    #[serde(default)]
+    pub network_hud: bool,
+    /// Whether or not menus and match events are read aloud, for low-vision players.
+    #[serde(default)]
+    pub text_to_speech: bool,
+    /// Whether or not camera shake, parallax, and background decorations are disabled.
+    #[serde(default)]
+    pub reduced_motion: bool,
+    /// Whether or not the map background is hidden so that the players and items stand out.
+    #[serde(default)]
+    pub high_contrast: bool,
+    /// Whether or not popups with the damage dealt are shown above the players that are hit.
+    #[serde(default)]
+    pub damage_numbers: bool,
+    /// Whether the grab and crouch actions are held or toggled, indexed by local input device.
+    #[serde(default)]
+    pub action_modes: [PlayerActionModes; MAX_PLAYERS],
+}
+
+fn default_afk_timeout() -> f32 {
+    60.0
 }
 
 impl Settings {
@@ -41,9 +81,20 @@ pub struct PlayerControlMethods {
     pub keyboard1: PlayerControls,
     /// Controls for keyboard player 2
     pub keyboard2: PlayerControls,
+    /// The gamepad controls of each local player who customized them, used instead of the
+    /// [`gamepad`][Self::gamepad] controls shared by the other players.
+    #[serde(default)]
+    pub gamepad_profiles: [Option<PlayerControls>; MAX_PLAYERS],
 }
 
 impl PlayerControlMethods {
+    /// Get the gamepad controls of the given player index.
+    pub fn gamepad_controls(&self, player_idx: usize) -> &PlayerControls {
+        self.gamepad_profiles[player_idx]
+            .as_ref()
+            .unwrap_or(&self.gamepad)
+    }
+
     /// Get the input map for the given player index
     pub fn get_input_map(&self, player_idx: usize) -> InputMap<PlayerAction> {
         let mut input_map = InputMap::default();
@@ -58,7 +109,7 @@ impl PlayerControlMethods {
             input_map.insert(ctrls.slide, PlayerAction::Slide);
         };
 
-        add_controls(&self.gamepad);
+        add_controls(self.gamepad_controls(player_idx));
 
         match player_idx {
             0 => add_controls(&self.keyboard1),
@@ -70,8 +121,26 @@ impl PlayerControlMethods {
     }
 }
 
+/// Whether an action is active while its input is held, or is switched on and off by pressing it.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionMode {
+    #[default]
+    Hold,
+    Toggle,
+}
+
+/// The [`ActionMode`]s chosen for a local input device.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PlayerActionModes {
+    #[serde(default)]
+    pub grab: ActionMode,
+    #[serde(default)]
+    pub crouch: ActionMode,
+}
+
 /// Binds inputs to player actions
-#[derive(Deserialize, Serialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
 pub struct PlayerControls {
     pub movement: VirtualDPad,
     pub jump: InputKind,
@@ -79,3 +148,97 @@ pub struct PlayerControls {
     pub shoot: InputKind,
     pub slide: InputKind,
 }
+
+impl PlayerControls {
+    /// The number of inputs bound by the controls.
+    pub const BINDING_COUNT: usize = 8;
+
+    /// Get the bound inputs: the movement up, down, left, and right, then jump, grab, shoot, and
+    /// slide.
+    pub fn bindings(&self) -> [InputKind; Self::BINDING_COUNT] {
+        [
+            self.movement.up,
+            self.movement.down,
+            self.movement.left,
+            self.movement.right,
+            self.jump,
+            self.grab,
+            self.shoot,
+            self.slide,
+        ]
+    }
+
+    /// Get, for each of the [`bindings()`][Self::bindings], whether its input is also bound to
+    /// another action, either in these controls or in the controls they are `shared_with` on the
+    /// same device.
+    pub fn conflicts(&self, shared_with: Option<&Self>) -> [bool; Self::BINDING_COUNT] {
+        let bindings = self.bindings();
+        let shared = shared_with.map(|x| x.bindings());
+        std::array::from_fn(|i| {
+            let input = bindings[i];
+            bindings
+                .iter()
+                .enumerate()
+                .any(|(j, other)| j != i && *other == input)
+                || shared.map_or(false, |x| x.contains(&input))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::prelude::{GamepadButtonType, KeyCode};
+
+    use super::*;
+
+    fn keyboard_controls(keys: [KeyCode; PlayerControls::BINDING_COUNT]) -> PlayerControls {
+        PlayerControls {
+            movement: VirtualDPad {
+                up: keys[0].into(),
+                down: keys[1].into(),
+                left: keys[2].into(),
+                right: keys[3].into(),
+            },
+            jump: keys[4].into(),
+            grab: keys[5].into(),
+            shoot: keys[6].into(),
+            slide: keys[7].into(),
+        }
+    }
+
+    #[test]
+    fn conflicting_bindings() {
+        use KeyCode::{Comma, Down, Left, Period, RShift, Right, Space, Up, A, C, D, S, V, W};
+        let left = keyboard_controls([W, S, A, D, Space, V, C, V]);
+        let right = keyboard_controls([Up, Down, Left, Right, Comma, RShift, Period, Space]);
+
+        let conflicts = left.conflicts(None);
+        assert_eq!(conflicts.iter().filter(|x| **x).count(), 2);
+        assert!(conflicts[5] && conflicts[7]);
+
+        // Both keyboard players share the same keyboard
+        let conflicts = left.conflicts(Some(&right));
+        assert!(conflicts[4]);
+        assert!(!conflicts[0]);
+        assert_eq!(
+            right.conflicts(Some(&left)),
+            [false, false, false, false, false, false, false, true]
+        );
+    }
+
+    #[test]
+    fn gamepad_profiles() {
+        let mut controls = PlayerControlMethods {
+            gamepad: keyboard_controls([KeyCode::Key1; PlayerControls::BINDING_COUNT]),
+            keyboard1: keyboard_controls([KeyCode::Key2; PlayerControls::BINDING_COUNT]),
+            keyboard2: keyboard_controls([KeyCode::Key3; PlayerControls::BINDING_COUNT]),
+            gamepad_profiles: Default::default(),
+        };
+        let mut custom = controls.gamepad.clone();
+        custom.jump = GamepadButtonType::North.into();
+        controls.gamepad_profiles[2] = Some(custom.clone());
+
+        assert_eq!(controls.gamepad_controls(1), &controls.gamepad);
+        assert_eq!(controls.gamepad_controls(2), &custom);
+    }
+}
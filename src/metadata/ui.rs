@@ -1,5 +1,7 @@
 use std::sync::Arc;
 
+use jumpy_core::match_stats::Award;
+
 use crate::assets::EguiFont;
 
 use super::*;
@@ -17,6 +19,7 @@ pub struct UIThemeMeta {
     pub widgets: UiThemeWidgets,
     pub debug_window_fill: ColorMeta,
     pub editor: UiThemeEditor,
+    pub award_icons: UiThemeAwardIcons,
 }
 
 #[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
@@ -45,6 +48,39 @@ impl UiThemeEditorIcons {
     }
 }
 
+/// The icons of the [`Award`]s shown on the results screen.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct UiThemeAwardIcons {
+    pub mvp: ImageMeta,
+    pub most_kills: ImageMeta,
+    pub most_deaths: ImageMeta,
+    pub pacifist: ImageMeta,
+    pub grenade_maniac: ImageMeta,
+}
+
+impl UiThemeAwardIcons {
+    pub fn get(&self, award: Award) -> &ImageMeta {
+        match award {
+            Award::Mvp => &self.mvp,
+            Award::MostKills => &self.most_kills,
+            Award::MostDeaths => &self.most_deaths,
+            Award::Pacifist => &self.pacifist,
+            Award::GrenadeManiac => &self.grenade_maniac,
+        }
+    }
+
+    pub fn as_mut_list(&mut self) -> [&mut ImageMeta; 5] {
+        [
+            &mut self.mvp,
+            &mut self.most_kills,
+            &mut self.most_deaths,
+            &mut self.pacifist,
+            &mut self.grenade_maniac,
+        ]
+    }
+}
+
 #[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
 #[serde(deny_unknown_fields)]
 pub struct UiThemeColors {
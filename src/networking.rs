@@ -1,19 +1,73 @@
 #![doc = include_str!("./networking.md")]
 
+#[cfg(not(target_arch = "wasm32"))]
+use std::net::Ipv6Addr;
+use std::{net::SocketAddr, time::Duration};
+
+#[cfg(not(target_arch = "wasm32"))]
+use bevy::tasks::IoTaskPool;
+use bevy::utils::Instant;
+use bevy_egui::EguiContexts;
+use bevy_fluent::Localization;
+#[cfg(not(target_arch = "wasm32"))]
+use futures_lite::future;
 use ggrs::{NetworkStats, P2PSession, PlayerHandle};
 use jumpy_core::input::PlayerControl;
+#[cfg(not(target_arch = "wasm32"))]
 use rand::Rng;
 
 use crate::{
-    networking::debug::{NetworkDebugMessage, NETWORK_DEBUG_CHANNEL},
+    latency::LATENCY_METER,
+    networking::{
+        debug::{NetworkDebugMessage, NETWORK_DEBUG_CHANNEL},
+        desync::{DesyncInfo, DesyncReport, StateHistory, DESYNC_CHECK_INTERVAL},
+        diagnostics::{NetworkDiagnostics, PeerDiagnostics},
+        disconnects::DisconnectVote,
+        pause::NetworkPause,
+        resume::{MatchResume, ResumeHistory},
+        smoothing::{player_positions, RollbackSmoothing},
+    },
     prelude::*,
 };
 
+#[cfg(not(target_arch = "wasm32"))]
+pub mod admin;
+pub mod bandwidth;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod certs;
+pub mod chat;
+pub mod conditions;
 pub mod debug;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod dedicated;
+pub mod desync;
+pub mod diagnostics;
+pub mod disconnects;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod lan;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod metrics;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod migration;
+pub mod moderation;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod online;
+pub mod pause;
 pub mod proto;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod quick_match;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod ranked;
+pub mod resume;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod secure;
+pub mod smoothing;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod upgrade;
+#[cfg(all(feature = "voice-chat", not(target_arch = "wasm32")))]
+pub mod voice;
+#[cfg(target_arch = "wasm32")]
+pub mod webrtc;
 
 /// The muliplier for the [`jumpy_core::FPS`] that will be used when playing an online match.
 ///
@@ -25,6 +79,17 @@ pub const NETWORK_FRAME_RATE_FACTOR: f32 = 0.9;
 /// for inputs from other players.
 pub const NETWORK_MAX_PREDICTION_WINDOW: usize = 10;
 
+/// Number of frames local input is delayed by before being simulated, giving it time to reach the
+/// other players.
+pub const NETWORK_INPUT_DELAY: usize = 1;
+
+/// How long the other players may go without sending anything before they are considered
+/// disconnected.
+///
+/// This is longer than the GGRS default so that the match survives the connections being
+/// [migrated][migration] after a network change.
+pub const NETWORK_DISCONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// The [`ggrs::Config`] implementation used by Jumpy.
 #[derive(Debug)]
 pub struct GgrsConfig;
@@ -35,6 +100,7 @@ impl ggrs::Config for GgrsConfig {
     type Address = usize;
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 /// The network endpoint used for all QUIC network communications.
 pub static NETWORK_ENDPOINT: Lazy<quinn::Endpoint> = Lazy::new(|| {
     // Generate certificate
@@ -47,9 +113,8 @@ pub static NETWORK_ENDPOINT: Lazy<quinn::Endpoint> = Lazy::new(|| {
     server_config.transport = Arc::new(transport_config);
 
     // Open Socket and create endpoint
-    let port = rand::thread_rng().gen_range(10000..=11000); // Bind a random port
-    info!(port, "Started network endpoint");
-    let socket = std::net::UdpSocket::bind(("0.0.0.0", port)).unwrap();
+    let socket = bind_endpoint_socket().unwrap();
+    info!(addr = ?socket.local_addr(), "Started network endpoint");
 
     let client_config = rustls::ClientConfig::builder()
         .with_safe_defaults()
@@ -70,6 +135,237 @@ pub static NETWORK_ENDPOINT: Lazy<quinn::Endpoint> = Lazy::new(|| {
     endpoint
 });
 
+#[cfg(not(target_arch = "wasm32"))]
+/// The delay before starting to connect to the next address of a host while the previous attempts
+/// haven't succeeded yet, as recommended by the Happy Eyeballs algorithm ([RFC 8305]).
+///
+/// [RFC 8305]: https://www.rfc-editor.org/rfc/rfc8305
+pub const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Open a UDP socket for the [`NETWORK_ENDPOINT`] on a random port.
+fn bind_endpoint_socket() -> std::io::Result<std::net::UdpSocket> {
+    let port = rand::thread_rng().gen_range(10000..=11000); // Bind a random port
+    bind_dual_stack(port).or_else(|e| {
+        warn!("Could not open an IPv6 socket, only IPv4 will be available: {e}");
+        std::net::UdpSocket::bind(("0.0.0.0", port))
+    })
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Move the [`NETWORK_ENDPOINT`] to a new UDP socket after the local network changed.
+///
+/// The connections that we opened follow the endpoint to its new address, see [`migration`].
+pub fn rebind_endpoint() -> std::io::Result<()> {
+    let socket = bind_endpoint_socket()?;
+    info!(addr = ?socket.local_addr(), "Rebinding network endpoint");
+    NETWORK_ENDPOINT.rebind(socket)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Bind a UDP socket accepting both IPv6 and IPv4 traffic on the given port.
+fn bind_dual_stack(port: u16) -> std::io::Result<std::net::UdpSocket> {
+    let socket = socket2::Socket::new(
+        socket2::Domain::IPV6,
+        socket2::Type::DGRAM,
+        Some(socket2::Protocol::UDP),
+    )?;
+    // IPv4 peers are seen as IPv4-mapped IPv6 addresses
+    socket.set_only_v6(false)?;
+    socket.bind(&SocketAddr::from((Ipv6Addr::UNSPECIFIED, port)).into())?;
+    Ok(socket.into())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Whether the [`NETWORK_ENDPOINT`] can reach IPv6 addresses.
+pub fn endpoint_supports_ipv6() -> bool {
+    NETWORK_ENDPOINT
+        .local_addr()
+        .map_or(false, |addr| addr.is_ipv6())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Turn IPv4-mapped IPv6 addresses, such as the addresses of the IPv4 peers of a dual-stack
+/// socket, back into IPv4 addresses, so that the same peer always has the same address.
+pub fn canonical_addr(addr: SocketAddr) -> SocketAddr {
+    match addr {
+        SocketAddr::V6(v6) => match v6.ip().to_ipv4_mapped() {
+            Some(v4) => SocketAddr::from((v4, v6.port())),
+            None => addr,
+        },
+        SocketAddr::V4(_) => addr,
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Order the addresses of a host in the order they should be tried in, following the Happy
+/// Eyeballs algorithm: alternating between IPv6 and IPv4, starting with IPv6.
+///
+/// IPv6 addresses are left out when they can't be reached.
+pub fn happy_eyeballs_order(addrs: &[SocketAddr], ipv6: bool) -> Vec<SocketAddr> {
+    let mut v6 = addrs.iter().filter(|x| x.is_ipv6() && ipv6).peekable();
+    let mut v4 = addrs.iter().filter(|x| x.is_ipv4()).peekable();
+    let mut ordered = Vec::with_capacity(addrs.len());
+    while v6.peek().is_some() || v4.peek().is_some() {
+        for addr in v6.next().into_iter().chain(v4.next()) {
+            if !ordered.contains(addr) {
+                ordered.push(*addr);
+            }
+        }
+    }
+    ordered
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Connect to the first of the given addresses of a host that answers.
+///
+/// The addresses are tried in [`happy_eyeballs_order`], and the next attempt is started every
+/// [`CONNECTION_ATTEMPT_DELAY`] until one of them succeeds, so that a broken IPv6 or IPv4 route
+/// doesn't hold the connection up.
+pub async fn connect_happy_eyeballs(
+    addrs: &[SocketAddr],
+    server_name: &str,
+) -> anyhow::Result<quinn::Connection> {
+    let addrs = happy_eyeballs_order(addrs, endpoint_supports_ipv6());
+    let (result_sender, result_receiver) = async_channel::unbounded();
+    let mut next = 0;
+    let mut pending = 0;
+    let mut last_error = None;
+
+    loop {
+        if let Some(&addr) = addrs.get(next) {
+            next += 1;
+            match NETWORK_ENDPOINT.connect(addr, server_name) {
+                Ok(connecting) => {
+                    pending += 1;
+                    let result_sender = result_sender.clone();
+                    IoTaskPool::get()
+                        .spawn(async move {
+                            // The connections that lost the race are closed when dropped
+                            result_sender.send((addr, connecting.await)).await.ok();
+                        })
+                        .detach();
+                }
+                Err(e) => {
+                    last_error = Some(anyhow::Error::new(e).context(format!("Can't reach {addr}")));
+                    continue;
+                }
+            }
+        }
+        if pending == 0 {
+            return Err(
+                last_error.unwrap_or_else(|| anyhow::format_err!("No address to connect to"))
+            );
+        }
+
+        // Wait for an attempt to finish, or for the time to start the next one
+        let result = if next < addrs.len() {
+            future::or(async { Some(result_receiver.recv().await) }, async {
+                sleep(CONNECTION_ATTEMPT_DELAY).await;
+                None
+            })
+            .await
+        } else {
+            Some(result_receiver.recv().await)
+        };
+        let Some(Ok((addr, result))) = result else {
+            continue;
+        };
+        pending -= 1;
+
+        match result {
+            Ok(conn) => {
+                info!(%addr, "Connected");
+                return Ok(conn);
+            }
+            Err(e) => {
+                warn!(%addr, "Connection attempt failed: {e}");
+                last_error =
+                    Some(anyhow::Error::new(e).context(format!("Couldn't connect to {addr}")));
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Wait for the given duration, using the timers of the QUIC runtime.
+async fn sleep(duration: Duration) {
+    use quinn::Runtime;
+    let mut timer = quinn_runtime_bevy::BevyIoTaskPoolExecutor.new_timer(Instant::now() + duration);
+    future::poll_fn(|cx| timer.as_mut().poll(cx)).await
+}
+
+/// Networking plugin, receiving the reliable messages sent by the other players during a match.
+pub struct JumpyNetworkingPlugin;
+
+impl Plugin for JumpyNetworkingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ReliableMessage>()
+            .add_system(
+                receive_reliable_messages
+                    .run_if(in_state(EngineState::InGame))
+                    .run_if(resource_exists::<NetworkMatchSocket>()),
+            )
+            .add_system(
+                relay_bandwidth_notice
+                    .run_if(in_state(EngineState::InGame))
+                    .run_if(resource_exists::<NetworkMatchSocket>()),
+            )
+            .configure_set(ReliableMessageHandlers.after(receive_reliable_messages));
+    }
+}
+
+/// System set containing the systems that handle the [`ReliableMessage`]s received during a
+/// match.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemSet)]
+pub struct ReliableMessageHandlers;
+
+/// Event sent for each message received from another player over the reliable channel during a
+/// match.
+///
+/// Each kind of message starts with its own tag, listed in [`proto::MessageTag`], and is
+/// handled by the module it belongs to. In the menus, the messages are handled by the player and
+/// map selection pages instead.
+pub struct ReliableMessage {
+    /// The player that sent the message.
+    pub sender: usize,
+    /// The contents of the message, starting with its tag.
+    pub data: Vec<u8>,
+}
+
+/// Receive the reliable messages sent during a match, for the [`ReliableMessageHandlers`].
+fn receive_reliable_messages(
+    socket: Res<NetworkMatchSocket>,
+    mut messages: EventWriter<ReliableMessage>,
+) {
+    messages.send_batch(
+        socket
+            .recv_reliable()
+            .into_iter()
+            .map(|(sender, data)| ReliableMessage { sender, data }),
+    );
+}
+
+/// Let the player know when the send rate is reduced because of the relay bandwidth cap.
+fn relay_bandwidth_notice(
+    mut contexts: EguiContexts,
+    socket: Res<NetworkMatchSocket>,
+    localization: Res<Localization>,
+) {
+    if !socket.is_throttled() {
+        return;
+    }
+
+    egui::Window::new(localization.get("relay-bandwidth-cap"))
+        .id(egui::Id::new("relay-bandwidth-notice"))
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::LEFT_BOTTOM, egui::Vec2::ZERO)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(localization.get("relay-bandwidth-limited"));
+        });
+}
+
 /// Resource containing the [`NetworkSocket`] implementation while there is a connection to a
 /// network game.
 ///
@@ -112,6 +408,34 @@ pub trait NetworkSocket: Sync + Send {
     fn player_is_local(&self) -> [bool; MAX_PLAYERS];
     /// Get the player count for this network match.
     fn player_count(&self) -> usize;
+    /// Get, for every player index, the GGRS address of the peer controlling the player.
+    ///
+    /// Every player is their own peer by default, but a peer may control several players, such as
+    /// after a [local match was upgraded][upgrade].
+    fn player_addrs(&self) -> [usize; MAX_PLAYERS] {
+        std::array::from_fn(|i| i)
+    }
+    /// Prevent the player with the given index from connecting to us again.
+    ///
+    /// This is a no-op for sockets where peers don't connect to us directly.
+    fn ban_player(&self, player_idx: usize);
+    /// Whether the send rate is reduced because the [bandwidth cap][bandwidth] was reached.
+    ///
+    /// Only the traffic sent through a relay is limited.
+    fn is_throttled(&self) -> bool {
+        false
+    }
+    /// Get the address of one of the peers or of the relay that the socket sends its traffic to,
+    /// used to notice when the local network changes.
+    fn remote_addr(&self) -> Option<SocketAddr>;
+    /// Connect to the other players again after the [`NETWORK_ENDPOINT`] was rebound to a new
+    /// address, for the connections that QUIC can't migrate on its own.
+    fn migrate(&self) {}
+    /// Connect to the other players again after the connections to them were lost, while the
+    /// match waits for them to [resume].
+    ///
+    /// This is a no-op for sockets whose connections can't be re-established.
+    fn reconnect(&self) {}
 }
 
 /// The destination for a reliable network message.
@@ -126,18 +450,37 @@ pub enum SocketTarget {
 ///
 /// This is where the whole `ggrs` integration is implemented.
 pub struct GgrsSessionRunner {
-    /// The last player input we detected.
-    pub last_player_input: PlayerControl,
+    /// The last input we detected for each of the local players.
+    pub local_inputs: [PlayerControl; MAX_PLAYERS],
     /// The core game session.
     pub core: CoreSession,
     /// The GGRS peer-to-peer session.
     pub session: P2PSession<GgrsConfig>,
     /// Array containing a flag indicating, for each player, whether they are a local player.
     pub player_is_local: [bool; MAX_PLAYERS],
+    /// The GGRS address of the peer controlling each player.
+    pub player_addrs: [usize; MAX_PLAYERS],
     /// The frame time delta.
     pub delta: f32,
     /// The frame time accumulator, used to produce a fixed refresh rate.
     pub accumulator: f32,
+    /// Array containing a flag indicating, for each player, whether we have removed them from the
+    /// match, so that their disconnection isn't treated as a network failure.
+    pub disconnected_players: [bool; MAX_PLAYERS],
+    /// The player that each local input device controls.
+    pub input_mapping: [Option<usize>; MAX_PLAYERS],
+    /// The state dumps of the last frames checked for desyncs.
+    pub state_history: StateHistory,
+    /// For each player, the frame from which an AI controls them if they are disconnected, as
+    /// agreed on by the [disconnect vote][disconnects].
+    pub ai_takeover_frames: [Option<i32>; MAX_PLAYERS],
+    /// The states of the last frames checked for desyncs, that the match may be [resumed][resume]
+    /// from after players lost their connection.
+    pub resume_history: ResumeHistory,
+    /// The number of players in the match.
+    pub player_count: usize,
+    /// The visual offsets hiding the rollback corrections of the remote players.
+    pub smoothing: RollbackSmoothing,
 }
 
 /// The info required to create a [`GgrsSessionRunner`].
@@ -146,6 +489,8 @@ pub struct GgrsSessionRunnerInfo {
     pub socket: BoxedNonBlockingSocket,
     /// The list of local players.
     pub player_is_local: [bool; MAX_PLAYERS],
+    /// The GGRS address of the peer controlling each player, see [`NetworkSocket::player_addrs()`].
+    pub player_addrs: [usize; MAX_PLAYERS],
     /// the player count.
     pub player_count: usize,
 }
@@ -157,32 +502,111 @@ impl GgrsSessionRunner {
         Self: Sized,
     {
         core.time_step = 1.0 / (jumpy_core::FPS * NETWORK_FRAME_RATE_FACTOR);
-        let mut builder = ggrs::SessionBuilder::new()
-            .with_num_players(info.player_count)
-            .with_max_prediction_window(NETWORK_MAX_PREDICTION_WINDOW)
-            .with_input_delay(1)
-            .with_fps((jumpy_core::FPS * NETWORK_FRAME_RATE_FACTOR) as usize)
-            .unwrap();
-
-        for i in 0..info.player_count {
-            if info.player_is_local[i] {
-                builder = builder.add_player(ggrs::PlayerType::Local, i).unwrap();
-            } else {
-                builder = builder.add_player(ggrs::PlayerType::Remote(i), i).unwrap();
-            }
-        }
+        let session = start_p2p_session(
+            info.player_count,
+            &info.player_is_local,
+            &info.player_addrs,
+            info.socket,
+        );
 
-        let session = builder.start_p2p_session(info.socket).unwrap();
+        // The first local device controls our network player by default
+        let mut input_mapping = [None; MAX_PLAYERS];
+        input_mapping[0] = info.player_is_local.iter().position(|x| *x);
 
         Self {
-            last_player_input: PlayerControl::default(),
+            local_inputs: default(),
             core,
             session,
             player_is_local: info.player_is_local,
+            player_addrs: info.player_addrs,
             accumulator: default(),
             delta: default(),
+            disconnected_players: default(),
+            input_mapping,
+            state_history: default(),
+            ai_takeover_frames: default(),
+            resume_history: default(),
+            player_count: info.player_count,
+            smoothing: default(),
         }
     }
+
+    /// Resume the match from the state of the given frame with a new GGRS session, in which only
+    /// the given `players` are connected.
+    ///
+    /// Returns `false` if the state of the frame isn't available.
+    pub fn resume(
+        &mut self,
+        frame: i32,
+        socket: BoxedNonBlockingSocket,
+        players: [bool; MAX_PLAYERS],
+    ) -> bool {
+        let Some(world) = self.resume_history.get(frame) else {
+            return false;
+        };
+        self.core.world = world.clone();
+        self.session = start_p2p_session(
+            self.player_count,
+            &self.player_is_local,
+            &self.player_addrs,
+            socket,
+        );
+
+        // The players that left the match, or that an AI took over, are still gone. The players
+        // are connected along with the peer controlling them.
+        for player_idx in 0..self.player_count {
+            let connected =
+                self.player_is_local[player_idx] || players[self.player_addrs[player_idx]];
+            self.disconnected_players[player_idx] = !connected;
+            if !connected {
+                if let Err(e) = self.session.disconnect_player(player_idx) {
+                    warn!("Could not disconnect network player {player_idx}: {e}");
+                }
+            }
+        }
+        // The frames of the new session start from the frame we resume from
+        for takeover_frame in self.ai_takeover_frames.iter_mut().flatten() {
+            *takeover_frame = (*takeover_frame - frame).max(0);
+        }
+        self.accumulator = 0.0;
+        self.state_history = default();
+        self.resume_history = default();
+        self.smoothing = default();
+        true
+    }
+}
+
+/// Start a GGRS session with the other players of a match.
+fn start_p2p_session(
+    player_count: usize,
+    player_is_local: &[bool; MAX_PLAYERS],
+    player_addrs: &[usize; MAX_PLAYERS],
+    socket: BoxedNonBlockingSocket,
+) -> P2PSession<GgrsConfig> {
+    let mut builder = ggrs::SessionBuilder::new()
+        .with_num_players(player_count)
+        .with_max_prediction_window(NETWORK_MAX_PREDICTION_WINDOW)
+        .with_input_delay(NETWORK_INPUT_DELAY)
+        .with_disconnect_timeout(NETWORK_DISCONNECT_TIMEOUT)
+        .with_desync_detection_mode(ggrs::DesyncDetection::On {
+            interval: DESYNC_CHECK_INTERVAL,
+        })
+        .with_fps((jumpy_core::FPS * NETWORK_FRAME_RATE_FACTOR) as usize)
+        .unwrap();
+
+    for i in 0..player_count {
+        if player_is_local[i] {
+            builder = builder.add_player(ggrs::PlayerType::Local, i).unwrap();
+        } else {
+            builder = builder
+                .add_player(ggrs::PlayerType::Remote(player_addrs[i]), i)
+                .unwrap();
+        }
+    }
+
+    builder
+        .start_p2p_session(conditions::SimulatedSocket::new(socket))
+        .unwrap()
 }
 
 /// Get a [`proto::DensePlayerControl`] from a normal [`PlayerControl`].
@@ -209,17 +633,21 @@ impl crate::session::SessionRunner for GgrsSessionRunner {
         if !self.player_is_local[player_idx] {
             return;
         }
-        self.last_player_input = control;
+        self.local_inputs[player_idx] = control;
     }
 
     fn advance(&mut self, bevy_world: &mut World) -> Result<(), SessionError> {
         const STEP: f32 = 1.0 / (jumpy_core::FPS * NETWORK_FRAME_RATE_FACTOR);
         let delta = self.delta;
         let local_player_idx = self.network_player_idx().unwrap();
+        #[cfg_attr(target_arch = "wasm32", allow(unused_variables))]
+        let started_at = Instant::now();
 
         self.accumulator += delta;
 
         let mut skip_frames = 0;
+        let mut rollback_frames = 0;
+        let mut interruptions = Vec::new();
 
         // Current frame before we start network update loop
         let current_frame_original = self.session.current_frame();
@@ -231,12 +659,37 @@ impl crate::session::SessionRunner for GgrsSessionRunner {
                 ggrs::GGRSEvent::Synchronized { addr } => {
                     info!(player=%addr, "Syncrhonized network client");
                 }
-                ggrs::GGRSEvent::Disconnected { .. } => return Err(SessionError::Disconnected),
+                ggrs::GGRSEvent::Disconnected { addr } if self.disconnected_players[addr] => {
+                    info!(player=%addr, "Removed network player disconnected");
+                }
+                ggrs::GGRSEvent::Disconnected { addr } => {
+                    // Every player controlled by the peer is gone with it
+                    let mut dropped = [false; MAX_PLAYERS];
+                    for i in 0..self.player_count {
+                        dropped[i] = !self.player_is_local[i] && self.player_addrs[i] == addr;
+                    }
+                    // Wait for them to reconnect, then let the other players vote on continuing
+                    // without them, when we can
+                    if let Some(mut resume) = bevy_world.get_resource_mut::<MatchResume>() {
+                        resume.players_lost(dropped, Instant::now());
+                    } else if let Some(mut vote) = bevy_world.get_resource_mut::<DisconnectVote>() {
+                        vote.players_dropped(dropped, Instant::now());
+                    } else {
+                        return Err(SessionError::Disconnected);
+                    }
+                    warn!(player=%addr, "Network player disconnected");
+                    for (disconnected, dropped) in self.disconnected_players.iter_mut().zip(dropped)
+                    {
+                        *disconnected |= dropped;
+                    }
+                }
                 ggrs::GGRSEvent::NetworkInterrupted { addr, .. } => {
                     info!(player=%addr, "Network player interrupted");
+                    interruptions.push((addr, true));
                 }
                 ggrs::GGRSEvent::NetworkResumed { addr } => {
                     info!(player=%addr, "Network player re-connected");
+                    interruptions.push((addr, false));
                 }
                 ggrs::GGRSEvent::WaitRecommendation {
                     skip_frames: skip_count,
@@ -260,14 +713,61 @@ impl crate::session::SessionRunner for GgrsSessionRunner {
                     addr,
                 } => {
                     error!(%frame, %local_checksum, %remote_checksum, player=%addr, "Network de-sync detected");
+
+                    let info = DesyncInfo {
+                        frame,
+                        player_idx: local_player_idx,
+                        remote_player_idx: addr,
+                        local_checksum,
+                        remote_checksum,
+                        state_dump: self.state_history.get(frame),
+                    };
+                    let path = match info.save() {
+                        Ok(path) => {
+                            info!(path = %path.display(), "Saved desync report");
+                            Some(path)
+                        }
+                        Err(e) => {
+                            error!("Could not save desync report: {e:?}");
+                            None
+                        }
+                    };
+                    bevy_world.insert_resource(DesyncReport { frame, path });
+                    return Err(SessionError::Desync { frame });
                 }
             }
         }
 
+        // While the match is paused no frame is advanced, but the other players are still polled
+        // so that the connections don't time out.
+        let is_paused = bevy_world
+            .get_resource::<NetworkPause>()
+            .map_or(false, |x| x.is_paused());
+        let is_voting = bevy_world
+            .get_resource::<DisconnectVote>()
+            .map_or(false, |x| x.in_progress());
+        let is_resuming = bevy_world
+            .get_resource::<MatchResume>()
+            .map_or(false, |x| x.in_progress());
+        if is_paused || is_voting || is_resuming {
+            self.accumulator = 0.0;
+            self.session.poll_remote_clients();
+        }
+
         loop {
-            self.session
-                .add_local_input(local_player_idx, get_dense_input(&self.last_player_input))
-                .unwrap();
+            for player_idx in 0..self.player_count {
+                if self.player_is_local[player_idx] {
+                    self.session
+                        .add_local_input(
+                            player_idx,
+                            get_dense_input(&self.local_inputs[player_idx]),
+                        )
+                        .unwrap();
+                }
+            }
+            if let Ok(mut meter) = LATENCY_METER.lock() {
+                meter.input_scheduled(self.session.current_frame() + NETWORK_INPUT_DELAY as i32);
+            }
             if self.accumulator >= STEP {
                 self.accumulator -= STEP;
 
@@ -288,22 +788,72 @@ impl crate::session::SessionRunner for GgrsSessionRunner {
 
                 match self.session.advance_frame() {
                     Ok(requests) => {
+                        let mut rolled_back = false;
+                        let mut advanced_frames = 0;
+                        // The frame of the state that the next advance request starts from
+                        let mut frame_to_advance = self.session.current_frame();
                         for request in requests {
                             match request {
                                 ggrs::GGRSRequest::SaveGameState { cell, frame } => {
-                                    cell.save(frame, Some(self.core.world.clone()), None)
+                                    frame_to_advance = frame;
+                                    if desync::is_checked_frame(frame) {
+                                        self.state_history.record(frame, self.core.state_dump());
+                                    }
+                                    let checksum = self.core.checksum() as u128;
+                                    if desync::is_checked_frame(frame) {
+                                        self.resume_history.record(
+                                            frame,
+                                            checksum,
+                                            self.core.world.clone(),
+                                        );
+                                    }
+                                    cell.save(frame, Some(self.core.world.clone()), Some(checksum))
                                 }
-                                ggrs::GGRSRequest::LoadGameState { cell, .. } => {
+                                ggrs::GGRSRequest::LoadGameState { cell, frame } => {
+                                    if !rolled_back {
+                                        self.smoothing.rollback_started(player_positions(
+                                            &mut self.core.world,
+                                        ));
+                                    }
+                                    frame_to_advance = frame;
                                     let world = cell.load().unwrap_or_default();
                                     self.core.world = world;
+                                    rolled_back = true;
                                 }
                                 ggrs::GGRSRequest::AdvanceFrame {
                                     inputs: network_inputs,
                                 } => {
+                                    let ai_fill_disconnected =
+                                        self.core.info.match_settings.ai_fill_disconnected;
+                                    let ai_takeover_frames = self.ai_takeover_frames;
                                     self.core.update_input(|inputs| {
-                                        for (player_idx, (input, _status)) in
+                                        for (player_idx, (input, status)) in
                                             network_inputs.into_iter().enumerate()
                                         {
+                                            // GGRS reports the disconnection on the same frame for
+                                            // every peer, so this stays deterministic.
+                                            if status == ggrs::InputStatus::Disconnected {
+                                                let input = &mut inputs.players[player_idx];
+                                                let voted_takeover = ai_takeover_frames[player_idx]
+                                                    .map_or(false, |x| frame_to_advance >= x);
+                                                if voted_takeover {
+                                                    // The AI respawns the fish if it was removed.
+                                                    if !input.is_ai {
+                                                        input.active = true;
+                                                        input.is_ai = true;
+                                                        input.control = default();
+                                                    }
+                                                } else if !ai_fill_disconnected {
+                                                    input.active = false;
+                                                    input.control = default();
+                                                } else if !input.is_ai {
+                                                    // The AI writes the controls from now on.
+                                                    input.is_ai = true;
+                                                    input.control = default();
+                                                }
+                                                continue;
+                                            }
+
                                             let control = &mut inputs.players[player_idx].control;
 
                                             let jump_pressed = input.jump_pressed();
@@ -331,9 +881,35 @@ impl crate::session::SessionRunner for GgrsSessionRunner {
                                         }
                                     });
                                     self.core.advance(bevy_world);
+                                    advanced_frames += 1;
+                                    frame_to_advance += 1;
+
+                                    // The frame shown before the rollback was simulated again
+                                    if rolled_back && frame_to_advance == current_frame {
+                                        self.smoothing.rollback_finished(
+                                            player_positions(&mut self.core.world),
+                                            &self.player_is_local,
+                                        );
+                                    }
                                 }
                             }
                         }
+
+                        // The rollbacks caused by the inputs received so far were simulated, so the
+                        // states they confirmed are final.
+                        self.resume_history.confirm(self.session.confirmed_frame());
+                        self.smoothing.frame_advanced();
+
+                        // After a rollback, every frame but the new one is simulated again
+                        if rolled_back {
+                            rollback_frames += advanced_frames.saturating_sub(1);
+                        }
+
+                        // The frame before the current one has just been simulated for the first
+                        // time, rollbacks only re-simulate frames that were simulated already.
+                        if let Ok(mut meter) = LATENCY_METER.lock() {
+                            meter.network_frame_simulated(self.session.current_frame() - 1);
+                        }
                     }
                     Err(e) => match e {
                         ggrs::GGRSError::NotSynchronized => {
@@ -363,6 +939,43 @@ impl crate::session::SessionRunner for GgrsSessionRunner {
                 network_stats.push((*handle, stats));
             }
         }
+
+        // Update the diagnostics shown in the network quality HUD
+        if let Some(mut diagnostics) = bevy_world.get_resource_mut::<NetworkDiagnostics>() {
+            let predicted_frames = self.session.current_frame() - self.session.confirmed_frame();
+            diagnostics.record_frames(
+                Instant::now(),
+                rollback_frames,
+                predicted_frames.max(0) as u32,
+            );
+            for (handle, stats) in &network_stats {
+                let peer = diagnostics.peers[*handle].get_or_insert_with(default);
+                peer.ping = stats.ping as u32;
+                peer.kbps_sent = stats.kbps_sent;
+                peer.frames_behind = stats.local_frames_behind;
+            }
+            for (handle, interrupted) in interruptions {
+                if let Some(peer) = &mut diagnostics.peers[handle] {
+                    peer.interrupted = interrupted;
+                }
+            }
+            for (peer, disconnected) in diagnostics.peers.iter_mut().zip(self.disconnected_players)
+            {
+                if disconnected {
+                    *peer = None;
+                }
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(metrics) = bevy_world.get_resource::<metrics::HostMetrics>() {
+            metrics
+                .0
+                .lock()
+                .unwrap()
+                .record_tick(started_at.elapsed(), rollback_frames);
+        }
+
         if !network_stats.is_empty() {
             NETWORK_DEBUG_CHANNEL
                 .sender
@@ -378,6 +991,10 @@ impl crate::session::SessionRunner for GgrsSessionRunner {
         ShouldRun::Yes
     }
 
+    fn is_pausable(&mut self) -> bool {
+        false
+    }
+
     fn network_player_idx(&mut self) -> Option<usize> {
         // We are the first local player
         for i in 0..MAX_PLAYERS {
@@ -387,4 +1004,65 @@ impl crate::session::SessionRunner for GgrsSessionRunner {
         }
         unreachable!();
     }
+
+    fn disconnect_player(&mut self, player_idx: usize) {
+        if self.disconnected_players[player_idx] || self.player_is_local[player_idx] {
+            return;
+        }
+        self.disconnected_players[player_idx] = true;
+
+        if let Err(e) = self.session.disconnect_player(player_idx) {
+            warn!("Could not disconnect network player {player_idx}: {e}");
+        }
+    }
+
+    fn input_mapping(&mut self) -> [Option<usize>; MAX_PLAYERS] {
+        self.input_mapping
+    }
+
+    fn remap_inputs(
+        &mut self,
+        mapping: [Option<usize>; MAX_PLAYERS],
+    ) -> Result<(), RemapInputsError> {
+        // Only the local players can be controlled by our input devices
+        validate_input_mapping(&mapping, &self.player_is_local)?;
+        self.input_mapping = mapping;
+        Ok(())
+    }
+    fn restore(&mut self, _snapshot: crate::session::SessionSnapshot) {
+        // The other players would desync
+        warn!("Ignoring snapshot restore in a network game");
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn addresses_alternate_starting_with_ipv6() {
+        let v4 = |x: u8| SocketAddr::from(([10, 0, 0, x], 1000));
+        let v6 = |x: u16| SocketAddr::from(([0x2001, 0xdb8, 0, 0, 0, 0, 0, x], 1000));
+
+        let addrs = [v4(1), v4(2), v4(3), v6(1), v6(2), v4(1)];
+        assert_eq!(
+            happy_eyeballs_order(&addrs, true),
+            [v6(1), v4(1), v6(2), v4(2), v4(3)]
+        );
+        assert_eq!(happy_eyeballs_order(&addrs, false), [v4(1), v4(2), v4(3)]);
+    }
+
+    #[test]
+    fn mapped_addresses_are_ipv4() {
+        let v4 = SocketAddr::from(([192, 168, 1, 2], 1000));
+        let mapped = SocketAddr::from((
+            std::net::Ipv4Addr::new(192, 168, 1, 2).to_ipv6_mapped(),
+            1000,
+        ));
+        assert_eq!(canonical_addr(mapped), v4);
+        assert_eq!(canonical_addr(v4), v4);
+
+        let v6 = SocketAddr::from((Ipv6Addr::LOCALHOST, 1000));
+        assert_eq!(canonical_addr(v6), v6);
+    }
 }
@@ -0,0 +1,494 @@
+//! Remote administration of [dedicated hosts][super::dedicated].
+//!
+//! When a dedicated host is started with `--admin-port <port>`, it accepts TCP connections on that
+//! port, so that community hosts can operate it from scripts. The password of the admin API must be
+//! set in the [`ADMIN_PASSWORD_ENV_VAR`] environment variable, and the API isn't started without
+//! one.
+//!
+//! The connections aren't encrypted, so the API only listens on the loopback interface by default.
+//! It may listen on another address with `--admin-address <ip>`, but should then only be reached
+//! through a tunnel such as SSH or a VPN. At most [`MAX_CONNECTIONS`] connections are served at
+//! once, and an address is refused for [`LOCKOUT_DURATION`] after [`MAX_FAILED_ATTEMPTS`] wrong
+//! passwords.
+//!
+//! Commands are sent one per line, and the first one must be `auth <password>`, sent within
+//! [`AUTH_TIMEOUT`]. The connection is closed after a wrong password. Every command is answered
+//! with the lines of its output, followed by a line with `ok`, or by a single line starting with
+//! `error:` if it failed. For example:
+//!
+//! ```text
+//! > auth hunter2
+//! < ok
+//! > players
+//! < 1: Fishy
+//! < 2: Sharky
+//! < ok
+//! > map Level 4
+//! < ok
+//! > set mutators LowGravity, Jetpacks
+//! < ok
+//! > skip
+//! < ok
+//! ```
+//!
+//! The available commands are:
+//!
+//! - `players`: list the players connected to the host, with their fighters.
+//! - `map <name>`: play the next match on the given map.
+//! - `set rounds <count>`, `set mode <mode>`, `set mutators <mutator>, ...`: change the settings
+//!   of the next match.
+//! - `skip`: end the running match and start the next one.
+//! - `kick <player>`: kick a player out of the host.
+//! - `shutdown`: leave the match and quit the game.
+//!
+//! The changes to the next match only apply to it, the following matches are played as described
+//! by the rotation.
+
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Read, Write},
+    net::{IpAddr, TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use bevy::app::AppExit;
+
+use super::{
+    dedicated::{find_map, DedicatedHost},
+    lan,
+    moderation::Moderation,
+    proto::{ModerationMessage, TaggedMessage},
+    NetworkMatchSocket, SocketTarget,
+};
+use crate::prelude::*;
+
+/// Admin API plugin.
+pub struct JumpyAdminApiPlugin;
+
+impl Plugin for JumpyAdminApiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(start_admin_api.run_if(resource_added::<DedicatedHost>()))
+            .add_system(handle_admin_requests.run_if(resource_exists::<AdminApi>()));
+    }
+}
+
+/// The longest line that may be sent to the admin API.
+const MAX_LINE_LENGTH: u64 = 1024;
+
+/// How long an admin connection may stay idle before it is closed.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// How long a new admin connection has to authenticate before it is closed.
+pub const AUTH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The most admin connections that are served at once.
+pub const MAX_CONNECTIONS: usize = 4;
+
+/// The number of wrong passwords after which the connections of an address are refused.
+pub const MAX_FAILED_ATTEMPTS: u32 = 3;
+
+/// How long the connections of an address are refused after too many wrong passwords.
+pub const LOCKOUT_DURATION: Duration = Duration::from_secs(60);
+
+/// A command sent to the admin API.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AdminCommand {
+    Players,
+    Map(String),
+    SetRounds(u32),
+    SetMode(MatchMode),
+    SetMutators(Vec<Mutator>),
+    Skip,
+    Kick(usize),
+    Shutdown,
+}
+
+impl AdminCommand {
+    /// Parse a line sent to the admin API.
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let (command, args) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+        let args = args.trim();
+        let no_args = |command| {
+            if args.is_empty() {
+                Ok(command)
+            } else {
+                Err("this command doesn't take any arguments".to_string())
+            }
+        };
+
+        match command {
+            "players" => no_args(Self::Players),
+            "skip" => no_args(Self::Skip),
+            "shutdown" => no_args(Self::Shutdown),
+            "map" if !args.is_empty() => Ok(Self::Map(args.into())),
+            "map" => Err("usage: map <name>".into()),
+            "kick" => args
+                .parse()
+                .map(Self::Kick)
+                .map_err(|_| "usage: kick <player>".into()),
+            "set" => {
+                let (setting, value) = args.split_once(' ').unwrap_or((args, ""));
+                let value = value.trim();
+                match setting {
+                    "rounds" => match value.parse() {
+                        Ok(rounds) if rounds > 0 => Ok(Self::SetRounds(rounds)),
+                        _ => Err("the number of rounds must be at least 1".into()),
+                    },
+                    "mode" => serde_yaml::from_str(value)
+                        .map(Self::SetMode)
+                        .map_err(|_| format!("unknown mode: {value}")),
+                    "mutators" => serde_yaml::from_str(&format!("[{value}]"))
+                        .map(Self::SetMutators)
+                        .map_err(|_| format!("unknown mutators: {value}")),
+                    _ => Err("usage: set rounds|mode|mutators <value>".into()),
+                }
+            }
+            _ => Err(format!("unknown command: {command}")),
+        }
+    }
+}
+
+/// A command sent to the game by an admin connection, with the channel to send its reply on.
+struct AdminRequest {
+    command: AdminCommand,
+    reply: async_channel::Sender<Result<String, String>>,
+}
+
+/// Resource receiving the commands of the admin connections, inserted once the admin API is
+/// started.
+#[derive(Resource)]
+pub struct AdminApi {
+    requests: async_channel::Receiver<AdminRequest>,
+}
+
+/// The limits on the admin connections, shared by the threads serving them.
+#[derive(Default)]
+struct ConnectionLimits {
+    /// The number of connections being served.
+    open: usize,
+    /// The number of wrong passwords sent from every address, with the time of the last one.
+    failures: HashMap<IpAddr, (u32, Instant)>,
+}
+
+impl ConnectionLimits {
+    /// Start serving a connection from the given address, unless it must be refused.
+    fn open(&mut self, address: IpAddr, now: Instant) -> Result<(), &'static str> {
+        self.failures
+            .retain(|_, (_, last)| now.duration_since(*last) < LOCKOUT_DURATION);
+        if self
+            .failures
+            .get(&address)
+            .map_or(false, |(count, _)| *count >= MAX_FAILED_ATTEMPTS)
+        {
+            return Err("too many wrong passwords, try again later");
+        }
+        if self.open >= MAX_CONNECTIONS {
+            return Err("too many admin connections");
+        }
+        self.open += 1;
+        Ok(())
+    }
+
+    /// Stop serving a connection.
+    fn close(&mut self) {
+        self.open -= 1;
+    }
+
+    /// Count a wrong password sent from the given address.
+    fn fail(&mut self, address: IpAddr, now: Instant) {
+        let (count, last) = self.failures.entry(address).or_insert((0, now));
+        *count += 1;
+        *last = now;
+    }
+}
+
+/// Start listening for admin connections when the dedicated host starts, if an admin port was
+/// given.
+fn start_admin_api(mut commands: Commands) {
+    let Some(port) = ENGINE_CONFIG.admin_port else {
+        return;
+    };
+    let Some(password) = ENGINE_CONFIG.admin_password.clone().filter(|x| !x.is_empty()) else {
+        error!("Not starting the admin API, because {ADMIN_PASSWORD_ENV_VAR} isn't set");
+        return;
+    };
+    let address = ENGINE_CONFIG.admin_address;
+    let listener = match TcpListener::bind((address, port)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Could not start the admin API on {address} port {port}: {e}");
+            return;
+        }
+    };
+
+    info!(%address, %port, "Started the admin API");
+    if !address.is_loopback() {
+        warn!("The admin API isn't encrypted, only reach it from other machines through a tunnel");
+    }
+    let (sender, receiver) = async_channel::unbounded();
+    let limits = Arc::new(Mutex::new(ConnectionLimits::default()));
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let Ok(peer) = stream.peer_addr() else {
+                continue;
+            };
+            // Refuse connections before spawning a thread for them, so that they can't pile up.
+            if let Err(e) = limits.lock().unwrap().open(peer.ip(), Instant::now()) {
+                warn!(%peer, "Refused admin connection: {e}");
+                writeln!(stream, "error: {e}").ok();
+                continue;
+            }
+
+            let password = password.clone();
+            let sender = sender.clone();
+            let limits = limits.clone();
+            std::thread::spawn(move || {
+                if let Err(e) = serve_admin_connection(stream, &password, sender, &limits) {
+                    warn!("Admin connection failed: {e}");
+                }
+                limits.lock().unwrap().close();
+            });
+        }
+    });
+    commands.insert_resource(AdminApi { requests: receiver });
+}
+
+/// Answer the commands sent on an admin connection, until it is closed.
+fn serve_admin_connection(
+    stream: TcpStream,
+    password: &str,
+    requests: async_channel::Sender<AdminRequest>,
+    limits: &Mutex<ConnectionLimits>,
+) -> std::io::Result<()> {
+    let peer = stream.peer_addr()?;
+    stream.set_read_timeout(Some(AUTH_TIMEOUT))?;
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    let mut authenticated = false;
+
+    loop {
+        let mut line = String::new();
+        if (&mut reader).take(MAX_LINE_LENGTH).read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        if !line.ends_with('\n') {
+            writeln!(writer, "error: line too long")?;
+            return Ok(());
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if !authenticated {
+            match line.strip_prefix("auth ") {
+                Some(attempt) if passwords_match(attempt, password) => {
+                    info!(%peer, "Admin connected");
+                    authenticated = true;
+                    writer.set_read_timeout(Some(IDLE_TIMEOUT))?;
+                    writeln!(writer, "ok")?;
+                    continue;
+                }
+                _ => {
+                    limits.lock().unwrap().fail(peer.ip(), Instant::now());
+                    warn!(%peer, "Refused admin connection that didn't authenticate");
+                    writeln!(writer, "error: not authenticated")?;
+                    return Ok(());
+                }
+            }
+        }
+
+        let reply = AdminCommand::parse(line).and_then(|command| {
+            info!(%peer, ?command, "Received admin command");
+            let (reply, reply_receiver) = async_channel::bounded(1);
+            requests
+                .send_blocking(AdminRequest { command, reply })
+                .map_err(|_| "the game is shutting down".to_string())?;
+            reply_receiver
+                .recv_blocking()
+                .unwrap_or_else(|_| Err("the game is shutting down".into()))
+        });
+        match reply {
+            Ok(output) => {
+                for line in output.lines() {
+                    writeln!(writer, "{line}")?;
+                }
+                writeln!(writer, "ok")?;
+            }
+            Err(e) => writeln!(writer, "error: {e}")?,
+        }
+    }
+}
+
+/// Compare a password attempt with the password, taking the same time wherever they differ.
+fn passwords_match(attempt: &str, password: &str) -> bool {
+    attempt.len() == password.len()
+        && attempt
+            .bytes()
+            .zip(password.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Run the commands sent by the admin connections.
+fn handle_admin_requests(
+    admin: Res<AdminApi>,
+    mut host: Option<ResMut<DedicatedHost>>,
+    socket: Option<Res<NetworkMatchSocket>>,
+    mut moderation: ResMut<Moderation>,
+    core_meta: Res<CoreMetaArc>,
+    map_assets: Res<Assets<MapMeta>>,
+    player_assets: Res<Assets<PlayerMeta>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    while let Ok(AdminRequest { command, reply }) = admin.requests.try_recv() {
+        let Some(host) = host.as_mut() else {
+            reply.try_send(Err("the dedicated host stopped".into())).ok();
+            continue;
+        };
+
+        let result = match command {
+            AdminCommand::Players => match (&socket, host.waiting_players()) {
+                (Some(socket), _) => Ok((1..socket.player_count().min(MAX_PLAYERS))
+                    .filter(|&i| !moderation.removed[i])
+                    .map(|i| {
+                        let fighter = host
+                            .fighter(i)
+                            .and_then(|x| player_assets.get(&x.get_bevy_handle()))
+                            .map_or("-", |x| x.name.as_str());
+                        format!("{i}: {fighter}")
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")),
+                (None, Some(joined)) => Ok(format!(
+                    "waiting for players: {joined}/{}",
+                    host.rotation.players + 1
+                )),
+                (None, None) => Ok(String::new()),
+            },
+            AdminCommand::Map(name) => {
+                if find_map(&core_meta, &map_assets, &name).is_some() {
+                    host.next_match_mut().map = name;
+                    Ok(String::new())
+                } else {
+                    Err(format!("unknown map: {name}"))
+                }
+            }
+            AdminCommand::SetRounds(rounds) => {
+                host.next_match_mut().rounds = rounds;
+                Ok(String::new())
+            }
+            AdminCommand::SetMode(mode) => {
+                host.next_match_mut().mode = mode;
+                Ok(String::new())
+            }
+            AdminCommand::SetMutators(mutators) => {
+                host.next_match_mut().mutators = mutators;
+                Ok(String::new())
+            }
+            AdminCommand::Skip => {
+                host.skip = true;
+                Ok(String::new())
+            }
+            AdminCommand::Kick(player_idx) => match &socket {
+                Some(socket)
+                    if (1..socket.player_count().min(MAX_PLAYERS)).contains(&player_idx)
+                        && !moderation.removed[player_idx] =>
+                {
+                    moderation.kick(socket, player_idx, false);
+                    Ok(String::new())
+                }
+                _ => Err(format!("no player {player_idx}")),
+            },
+            AdminCommand::Shutdown => {
+                info!("Shutting down the dedicated host");
+                // The game quits before the match could be left as usual, so let the players
+                // know right away.
+                if let Some(socket) = &socket {
+                    socket.send_reliable(SocketTarget::All, &ModerationMessage::Leave.encode());
+                    socket.close();
+                }
+                if let Some(service_info) = host.advertised_service() {
+                    lan::stop_server(service_info);
+                }
+                exit.send(AppExit);
+                Ok(String::new())
+            }
+        };
+        reply.try_send(result).ok();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_admin_commands() {
+        assert_eq!(AdminCommand::parse("players"), Ok(AdminCommand::Players));
+        assert_eq!(
+            AdminCommand::parse(" map  Level 4 "),
+            Ok(AdminCommand::Map("Level 4".into()))
+        );
+        assert_eq!(
+            AdminCommand::parse("set rounds 5"),
+            Ok(AdminCommand::SetRounds(5))
+        );
+        assert_eq!(
+            AdminCommand::parse("set mode Teams"),
+            Ok(AdminCommand::SetMode(MatchMode::Teams))
+        );
+        assert_eq!(
+            AdminCommand::parse("set mutators LowGravity, Jetpacks"),
+            Ok(AdminCommand::SetMutators(vec![
+                Mutator::LowGravity,
+                Mutator::Jetpacks
+            ]))
+        );
+        assert_eq!(
+            AdminCommand::parse("set mutators"),
+            Ok(AdminCommand::SetMutators(vec![]))
+        );
+        assert_eq!(AdminCommand::parse("kick 2"), Ok(AdminCommand::Kick(2)));
+
+        assert!(AdminCommand::parse("map").is_err());
+        assert!(AdminCommand::parse("kick me").is_err());
+        assert!(AdminCommand::parse("set rounds 0").is_err());
+        assert!(AdminCommand::parse("set mode Tag").is_err());
+        assert!(AdminCommand::parse("skip 3").is_err());
+        assert!(AdminCommand::parse("restart").is_err());
+    }
+
+    #[test]
+    fn limit_admin_connections() {
+        let mut limits = ConnectionLimits::default();
+        let now = Instant::now();
+        let address = IpAddr::from([127, 0, 0, 1]);
+
+        for _ in 0..MAX_CONNECTIONS {
+            assert!(limits.open(address, now).is_ok());
+        }
+        assert!(limits.open(address, now).is_err());
+        limits.close();
+        assert!(limits.open(address, now).is_ok());
+        limits.close();
+
+        // Addresses sending too many wrong passwords are refused for a while
+        for _ in 0..MAX_FAILED_ATTEMPTS {
+            limits.fail(address, now);
+        }
+        assert!(limits.open(address, now).is_err());
+        assert!(limits.open(IpAddr::from([127, 0, 0, 2]), now).is_ok());
+        limits.close();
+        assert!(limits.open(address, now + LOCKOUT_DURATION).is_ok());
+    }
+
+    #[test]
+    fn compare_passwords() {
+        assert!(passwords_match("hunter2", "hunter2"));
+        assert!(!passwords_match("hunter3", "hunter2"));
+        assert!(!passwords_match("hunter", "hunter2"));
+        assert!(!passwords_match("", "hunter2"));
+    }
+}
@@ -0,0 +1,125 @@
+//! Bandwidth limits for the traffic sent through the relay of the matchmaking server.
+//!
+//! Community-run matchmaking servers pay for the traffic of every online match they relay, so the
+//! [`Settings::relay_bandwidth_cap`] may limit how much is sent to the relay per second during a
+//! match. Once the cap is reached, the [`BandwidthLimiter`] throttles the socket: only every other
+//! GGRS message is sent, which works because each GGRS input message repeats the inputs that
+//! weren't acknowledged yet, and voice chat stops. The socket goes back to the full send rate once
+//! it stays well below the cap for a whole second.
+
+use std::time::Duration;
+
+use bevy::utils::Instant;
+
+use crate::prelude::*;
+
+/// The length of the windows the traffic is measured over.
+const WINDOW: Duration = Duration::from_secs(1);
+
+/// The share of the cap that the traffic of a window must stay below to stop throttling.
+const RECOVERY_RATIO: f32 = 0.5;
+
+/// Keeps the traffic sent by a socket under a bandwidth cap.
+#[derive(Debug, Clone)]
+pub struct BandwidthLimiter {
+    /// The maximum number of bytes sent per second, or `None` for no limit.
+    cap: Option<u32>,
+    /// The start of the current window.
+    window_start: Option<Instant>,
+    /// The number of bytes sent in the current window.
+    sent: u32,
+    /// Whether the send rate is reduced because the cap was reached.
+    throttled: bool,
+    /// Whether the last optional message was skipped while throttled.
+    skipped_last: bool,
+}
+
+impl BandwidthLimiter {
+    /// Create a limiter for the given number of bytes per second, or `None` for no limit.
+    pub fn new(cap: Option<u32>) -> Self {
+        Self {
+            cap,
+            window_start: None,
+            sent: 0,
+            throttled: false,
+            skipped_last: false,
+        }
+    }
+
+    /// Whether the send rate is reduced because the cap was reached.
+    pub fn is_throttled(&self) -> bool {
+        self.throttled
+    }
+
+    /// Count a message of `bytes` bytes about to be sent at `now`, returning whether it should be
+    /// sent.
+    ///
+    /// Messages that are `required` are always sent, but count towards the cap.
+    pub fn allow(&mut self, now: Instant, bytes: usize, required: bool) -> bool {
+        let Some(cap) = self.cap else { return true };
+
+        let window_start = *self.window_start.get_or_insert(now);
+        if now.duration_since(window_start) >= WINDOW {
+            if self.throttled && (self.sent as f32) < cap as f32 * RECOVERY_RATIO {
+                info!("Relay traffic is back under its bandwidth cap");
+                self.throttled = false;
+            }
+            self.window_start = Some(now);
+            self.sent = 0;
+        }
+
+        let bytes = bytes as u32;
+        if self.sent.saturating_add(bytes) > cap && !required {
+            if !self.throttled {
+                warn!(cap, "Relay bandwidth cap reached, reducing the send rate");
+            }
+            self.throttled = true;
+            return false;
+        }
+        if self.throttled && !required {
+            self.skipped_last = !self.skipped_last;
+            if self.skipped_last {
+                return false;
+            }
+        }
+
+        self.sent = self.sent.saturating_add(bytes);
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn send_rate_is_reduced_until_traffic_recovers() {
+        let start = Instant::now();
+        let mut limiter = BandwidthLimiter::new(Some(100));
+
+        assert!(limiter.allow(start, 60, false));
+        assert!(!limiter.allow(start, 60, false));
+        assert!(limiter.is_throttled());
+        // Required messages go through anyway
+        assert!(limiter.allow(start, 60, true));
+
+        // Every other message is sent while throttled
+        let second = start + WINDOW;
+        assert!(!limiter.allow(second, 10, false));
+        assert!(limiter.allow(second, 10, false));
+        assert!(!limiter.allow(second, 10, false));
+        assert!(limiter.is_throttled());
+
+        // The full rate comes back after a quiet window
+        assert!(limiter.allow(second + WINDOW, 10, false));
+        assert!(!limiter.is_throttled());
+        assert!(limiter.allow(second + WINDOW, 10, false));
+    }
+
+    #[test]
+    fn no_cap() {
+        let mut limiter = BandwidthLimiter::new(None);
+        assert!(limiter.allow(Instant::now(), usize::MAX, false));
+        assert!(!limiter.is_throttled());
+    }
+}
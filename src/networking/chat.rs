@@ -0,0 +1,349 @@
+//! Text chat for network games.
+//!
+//! Chat messages are sent to the other players over the reliable channel, prefixed with
+//! [`MessageTag::Chat`], so they never go through the GGRS input stream and can't affect the
+//! rollback simulation. In the lobby the chat box is shown on the player selection page, and during
+//! a match the chat overlay is opened with [`CHAT_KEY`], which also sends the typed message.
+//!
+//! Every player may only send [`RATE_LIMIT_MESSAGES`] messages per [`RATE_LIMIT_WINDOW`]. Messages
+//! received from a player going over the limit are dropped, so that a modified client can't flood
+//! the chat.
+
+use std::{collections::VecDeque, time::Duration};
+
+use bevy::utils::Instant;
+use bevy_egui::EguiContexts;
+use bevy_fluent::Localization;
+
+use super::{
+    proto::MessageTag, NetworkMatchSocket, NetworkSocket, ReliableMessage, ReliableMessageHandlers,
+    SocketTarget,
+};
+use crate::prelude::*;
+
+/// Text chat plugin.
+pub struct JumpyTextChatPlugin;
+
+impl Plugin for JumpyTextChatPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TextChat>()
+            .add_system(reset_text_chat.run_if(resource_added::<NetworkMatchSocket>()))
+            .add_system(
+                handle_chat_messages
+                    .in_set(ReliableMessageHandlers)
+                    .run_if(in_state(EngineState::InGame))
+                    .run_if(resource_exists::<NetworkMatchSocket>()),
+            )
+            .add_system(
+                chat_overlay
+                    .after(ReliableMessageHandlers)
+                    .run_if(in_state(EngineState::InGame))
+                    .run_if(resource_exists::<NetworkMatchSocket>()),
+            );
+    }
+}
+
+/// The key that opens the chat overlay during a match, and sends the typed message.
+pub const CHAT_KEY: KeyCode = KeyCode::Return;
+
+/// The longest message that may be sent, in characters.
+pub const MAX_MESSAGE_CHARS: usize = 200;
+
+/// The number of messages kept in the chat history.
+const HISTORY_LEN: usize = 50;
+
+/// The number of messages a player may send per [`RATE_LIMIT_WINDOW`].
+pub const RATE_LIMIT_MESSAGES: usize = 5;
+
+/// The window over which the messages sent by a player are counted for the rate limit.
+pub const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(10);
+
+/// How long new messages stay on screen while the chat overlay is closed.
+const MESSAGE_DISPLAY_TIME: Duration = Duration::from_secs(8);
+
+/// A message in the chat history.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChatEntry {
+    /// The player that sent the message.
+    pub sender: usize,
+    pub text: String,
+    /// When the message was sent or received.
+    pub time: Instant,
+}
+
+/// Counts the messages sent by a player for the rate limit.
+#[derive(Clone, Debug, Default)]
+pub struct ChatRateLimit {
+    /// When the messages of the current window were sent.
+    recent: VecDeque<Instant>,
+}
+
+impl ChatRateLimit {
+    /// Count a message sent at `now`, returning whether it is within the rate limit.
+    pub fn allow(&mut self, now: Instant) -> bool {
+        while self
+            .recent
+            .front()
+            .map_or(false, |x| now.duration_since(*x) >= RATE_LIMIT_WINDOW)
+        {
+            self.recent.pop_front();
+        }
+        if self.recent.len() >= RATE_LIMIT_MESSAGES {
+            return false;
+        }
+        self.recent.push_back(now);
+        true
+    }
+}
+
+/// Resource containing the chat of the current network game.
+#[derive(Resource, Default)]
+pub struct TextChat {
+    /// The recent messages, oldest first.
+    pub history: VecDeque<ChatEntry>,
+    /// The message being typed.
+    pub draft: String,
+    /// Whether the chat overlay is open during a match.
+    pub is_open: bool,
+    /// Whether the last message couldn't be sent because of the rate limit.
+    pub rate_limited: bool,
+    send_limit: ChatRateLimit,
+    receive_limits: [ChatRateLimit; MAX_PLAYERS],
+}
+
+impl TextChat {
+    /// Send the message being typed to the other players, if it isn't empty.
+    pub fn send_draft(&mut self, socket: &NetworkMatchSocket, now: Instant) {
+        let text = clean_message(&self.draft);
+        if text.is_empty() {
+            return;
+        }
+        self.rate_limited = !self.send_limit.allow(now);
+        if self.rate_limited {
+            return;
+        }
+
+        socket.send_reliable(SocketTarget::All, &encode_message(&text));
+        self.push(ChatEntry {
+            sender: socket.player_idx(),
+            text,
+            time: now,
+        });
+        self.draft.clear();
+    }
+
+    /// Handle a message received over the reliable channel, returning whether it was a chat
+    /// message.
+    pub fn handle_message(&mut self, sender: usize, data: &[u8], now: Instant) -> bool {
+        let Some(text) = decode_message(data) else {
+            return false;
+        };
+        let Some(limit) = self.receive_limits.get_mut(sender) else {
+            return true;
+        };
+        if !limit.allow(now) {
+            debug!(sender, "Dropped chat message over the rate limit");
+            return true;
+        }
+        if !text.is_empty() {
+            self.push(ChatEntry {
+                sender,
+                text,
+                time: now,
+            });
+        }
+        true
+    }
+
+    /// Add a message to the history, forgetting the oldest message if it is full.
+    fn push(&mut self, entry: ChatEntry) {
+        if self.history.len() >= HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back(entry);
+    }
+}
+
+/// Remove the control characters and the surrounding whitespace of a message, and shorten it to
+/// [`MAX_MESSAGE_CHARS`].
+pub fn clean_message(text: &str) -> String {
+    text.chars()
+        .filter(|x| !x.is_control())
+        .take(MAX_MESSAGE_CHARS)
+        .collect::<String>()
+        .trim()
+        .to_owned()
+}
+
+/// Encode a chat message so it can be sent over the reliable channel.
+pub fn encode_message(text: &str) -> Vec<u8> {
+    MessageTag::Chat.encode(text.as_bytes())
+}
+
+/// Decode a message received over the reliable channel, returning `None` if it isn't a chat
+/// message.
+pub fn decode_message(bytes: &[u8]) -> Option<String> {
+    let text = MessageTag::Chat.decode(bytes)?;
+    Some(clean_message(&String::from_utf8_lossy(text)))
+}
+
+/// Render the chat history and the message box, used in the lobby and in the chat overlay.
+///
+/// Returns the response of the message box.
+pub fn chat_box_ui(
+    ui: &mut egui::Ui,
+    localization: &Localization,
+    socket: &NetworkMatchSocket,
+    text_chat: &mut TextChat,
+) -> egui::Response {
+    let local_player = socket.player_idx();
+
+    egui::ScrollArea::vertical()
+        .max_height(ui.available_height().min(200.0))
+        .stick_to_bottom(true)
+        .show(ui, |ui| {
+            for entry in &text_chat.history {
+                ui.label(chat_line(localization, local_player, entry));
+            }
+        });
+
+    if text_chat.rate_limited {
+        ui.label(localization.get("chat-rate-limited"));
+    }
+
+    ui.horizontal(|ui| {
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut text_chat.draft)
+                .char_limit(MAX_MESSAGE_CHARS)
+                .hint_text(localization.get("chat-hint")),
+        );
+        let submitted = response.lost_focus() && ui.input(|x| x.key_pressed(egui::Key::Enter));
+        if ui.button(localization.get("send")).clicked() || submitted {
+            text_chat.send_draft(socket, Instant::now());
+        }
+        response
+    })
+    .inner
+}
+
+/// Get the line showing a message in the chat.
+fn chat_line(localization: &Localization, local_player: usize, entry: &ChatEntry) -> String {
+    let sender = if entry.sender == local_player {
+        localization.get("chat-you")
+    } else {
+        localization.get(&format!("chat-player?player={}", entry.sender + 1))
+    };
+    format!("{sender}: {}", entry.text)
+}
+
+/// Start every network game with an empty chat.
+fn reset_text_chat(mut text_chat: ResMut<TextChat>) {
+    *text_chat = default();
+}
+
+/// Handle the chat messages sent by the other players during a match.
+fn handle_chat_messages(
+    mut messages: EventReader<ReliableMessage>,
+    mut text_chat: ResMut<TextChat>,
+) {
+    let now = Instant::now();
+    for message in messages.iter() {
+        text_chat.handle_message(message.sender, &message.data, now);
+    }
+}
+
+/// Show the new messages during a match, and the whole chat while the overlay is open.
+fn chat_overlay(
+    mut contexts: EguiContexts,
+    keyboard: Res<Input<KeyCode>>,
+    in_game_state: Res<State<InGameState>>,
+    socket: Res<NetworkMatchSocket>,
+    localization: Res<Localization>,
+    mut text_chat: ResMut<TextChat>,
+) {
+    let now = Instant::now();
+    if in_game_state.0 != InGameState::Playing {
+        text_chat.is_open = false;
+    } else if keyboard.just_pressed(CHAT_KEY) {
+        if text_chat.is_open {
+            text_chat.send_draft(&socket, now);
+            text_chat.is_open = false;
+        } else {
+            text_chat.is_open = true;
+            text_chat.rate_limited = false;
+        }
+    }
+
+    let ctx = contexts.ctx_mut();
+    if text_chat.is_open {
+        egui::Window::new(localization.get("chat"))
+            .id(egui::Id::new("chat-overlay"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-10.0, -10.0))
+            .show(ctx, |ui| {
+                // The message box loses its focus when Enter is pressed, which is handled above
+                let text_box = chat_box_ui(ui, &localization, &socket, &mut text_chat);
+                if !text_box.has_focus() {
+                    text_box.request_focus();
+                }
+            });
+        return;
+    }
+
+    let local_player = socket.player_idx();
+    let recent = text_chat
+        .history
+        .iter()
+        .filter(|x| now.duration_since(x.time) < MESSAGE_DISPLAY_TIME)
+        .collect::<Vec<_>>();
+    if recent.is_empty() {
+        return;
+    }
+    egui::Area::new("chat-messages")
+        .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-10.0, -10.0))
+        .show(ctx, |ui| {
+            for entry in recent {
+                ui.label(chat_line(&localization, local_player, entry));
+            }
+        });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn messages_over_the_rate_limit_are_dropped() {
+        let start = Instant::now();
+        let mut limit = ChatRateLimit::default();
+        for _ in 0..RATE_LIMIT_MESSAGES {
+            assert!(limit.allow(start));
+        }
+        assert!(!limit.allow(start + Duration::from_secs(1)));
+        assert!(limit.allow(start + RATE_LIMIT_WINDOW));
+    }
+
+    #[test]
+    fn received_messages() {
+        let now = Instant::now();
+        let mut text_chat = TextChat::default();
+
+        assert!(!text_chat.handle_message(1, &[0, 1, 2], now));
+        assert!(text_chat.handle_message(1, &encode_message(" hi\n there "), now));
+        assert_eq!(text_chat.history[0].text, "hi there");
+        assert_eq!(text_chat.history[0].sender, 1);
+
+        let long = "a".repeat(MAX_MESSAGE_CHARS * 2);
+        assert!(text_chat.handle_message(2, &encode_message(&long), now));
+        assert_eq!(text_chat.history[1].text.len(), MAX_MESSAGE_CHARS);
+
+        // Spam is dropped, but still recognized as chat
+        for _ in 0..RATE_LIMIT_MESSAGES {
+            text_chat.handle_message(3, &encode_message("spam"), now);
+        }
+        assert_eq!(text_chat.history.len(), 2 + RATE_LIMIT_MESSAGES);
+        assert!(text_chat.handle_message(3, &encode_message("spam"), now));
+        assert_eq!(text_chat.history.len(), 2 + RATE_LIMIT_MESSAGES);
+    }
+}
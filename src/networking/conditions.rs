@@ -0,0 +1,300 @@
+//! Simulation of bad network conditions, to reproduce rollback issues locally.
+//!
+//! The `netsim` console command adds artificial latency, jitter, and packet loss to the GGRS
+//! messages of network matches, for example:
+//!
+//! ```text
+//! netsim --latency 80 --jitter 20 --loss 5
+//! netsim --off
+//! ```
+//!
+//! The conditions apply to the messages both sent and received by the [`SimulatedSocket`] that
+//! every GGRS session is started with, so the round trip time grows by twice the latency. They may
+//! be changed during a match. Only the GGRS messages are affected, the reliable messages are still
+//! delivered as usual.
+//!
+//! Unlike the `debug-network-slowdown` feature, this doesn't need a special build and works with
+//! both LAN and online matches.
+
+use std::{sync::Mutex, time::Duration};
+
+use bevy::utils::Instant;
+use bevy_console::{reply, AddConsoleCommand, ConsoleCommand};
+use clap::Parser;
+use rand::Rng;
+
+use super::BoxedNonBlockingSocket;
+use crate::prelude::*;
+
+/// Network conditions simulation plugin.
+pub struct JumpyNetworkConditionsPlugin;
+
+impl Plugin for JumpyNetworkConditionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_console_command::<NetSimCommand, _>(netsim_command);
+    }
+}
+
+/// The network conditions simulated by the [`SimulatedSocket`]s.
+pub static SIMULATED_NETWORK_CONDITIONS: Lazy<Mutex<NetworkConditions>> = Lazy::new(default);
+
+/// Artificial network conditions applied to the GGRS messages.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct NetworkConditions {
+    /// The latency added to every message, in each direction.
+    pub latency: Duration,
+    /// The largest random variation of the latency, in either direction.
+    pub jitter: Duration,
+    /// The fraction of the messages that are dropped, between `0.0` and `1.0`.
+    pub packet_loss: f32,
+}
+
+impl std::fmt::Display for NetworkConditions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "latency {}ms, jitter {}ms, packet loss {}%",
+            self.latency.as_millis(),
+            self.jitter.as_millis(),
+            self.packet_loss * 100.0
+        )
+    }
+}
+
+impl NetworkConditions {
+    /// Get the conditions currently simulated.
+    pub fn current() -> Self {
+        *SIMULATED_NETWORK_CONDITIONS.lock().unwrap()
+    }
+
+    /// Whether the messages are delivered without any artificial latency or loss.
+    pub fn is_ideal(&self) -> bool {
+        self.latency.is_zero() && self.jitter.is_zero() && self.packet_loss <= 0.0
+    }
+
+    /// Get how long a message is delayed, or `None` if it is lost, given two random numbers in
+    /// `0.0..1.0`.
+    fn delay(&self, loss_roll: f32, jitter_roll: f32) -> Option<Duration> {
+        if loss_roll < self.packet_loss {
+            return None;
+        }
+        let offset = f64::from(jitter_roll) * 2.0 - 1.0;
+        Some(if offset >= 0.0 {
+            self.latency + self.jitter.mul_f64(offset)
+        } else {
+            // The jitter never makes the delay negative
+            self.latency.saturating_sub(self.jitter.mul_f64(-offset))
+        })
+    }
+}
+
+/// Messages waiting for their artificial latency to elapse.
+struct DelayQueue<T> {
+    messages: Vec<(Instant, T)>,
+}
+
+impl<T> Default for DelayQueue<T> {
+    fn default() -> Self {
+        Self {
+            messages: Vec::new(),
+        }
+    }
+}
+
+impl<T> DelayQueue<T> {
+    fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// Queue a message to be delivered at the given time.
+    fn push(&mut self, deliver_at: Instant, message: T) {
+        self.messages.push((deliver_at, message));
+    }
+
+    /// Take the messages that are due at the given time, in the order they are delivered in.
+    ///
+    /// With jitter, messages may be delivered in another order than they were sent in, like they
+    /// may on a real network.
+    fn pop_due(&mut self, now: Instant) -> Vec<T> {
+        // Sorting is stable, so messages due at the same time keep their order
+        self.messages.sort_by_key(|(deliver_at, _)| *deliver_at);
+        let due = self
+            .messages
+            .partition_point(|(deliver_at, _)| *deliver_at <= now);
+        self.messages.drain(..due).map(|(_, x)| x).collect()
+    }
+}
+
+/// GGRS socket applying the [`SIMULATED_NETWORK_CONDITIONS`] to the messages of another socket.
+///
+/// When the conditions are ideal, messages are passed through untouched.
+pub struct SimulatedSocket {
+    socket: BoxedNonBlockingSocket,
+    outgoing: DelayQueue<(usize, ggrs::Message)>,
+    incoming: DelayQueue<(usize, ggrs::Message)>,
+}
+
+impl SimulatedSocket {
+    pub fn new(socket: BoxedNonBlockingSocket) -> Self {
+        Self {
+            socket,
+            outgoing: default(),
+            incoming: default(),
+        }
+    }
+
+    /// Send the outgoing messages that are due.
+    fn flush(&mut self, now: Instant) {
+        for (addr, msg) in self.outgoing.pop_due(now) {
+            self.socket.send_to(&msg, &addr);
+        }
+    }
+}
+
+/// Get when a message sent now should be delivered under the given conditions, or `None` if it is
+/// lost.
+fn deliver_at(conditions: &NetworkConditions, now: Instant) -> Option<Instant> {
+    let mut rng = rand::thread_rng();
+    conditions
+        .delay(rng.gen(), rng.gen())
+        .map(|delay| now + delay)
+}
+
+impl ggrs::NonBlockingSocket<usize> for SimulatedSocket {
+    fn send_to(&mut self, msg: &ggrs::Message, addr: &usize) {
+        let conditions = NetworkConditions::current();
+        if conditions.is_ideal() && self.outgoing.is_empty() {
+            self.socket.send_to(msg, addr);
+            return;
+        }
+
+        let now = Instant::now();
+        if let Some(deliver_at) = deliver_at(&conditions, now) {
+            self.outgoing.push(deliver_at, (*addr, msg.clone()));
+        }
+        self.flush(now);
+    }
+
+    fn receive_all_messages(&mut self) -> Vec<(usize, ggrs::Message)> {
+        let conditions = NetworkConditions::current();
+        let now = Instant::now();
+        self.flush(now);
+
+        let messages = self.socket.receive_all_messages();
+        if conditions.is_ideal() && self.incoming.is_empty() {
+            return messages;
+        }
+
+        for message in messages {
+            if let Some(deliver_at) = deliver_at(&conditions, now) {
+                self.incoming.push(deliver_at, message);
+            }
+        }
+        self.incoming.pop_due(now)
+    }
+}
+
+/// Simulate bad network conditions in network matches. Without options, print the conditions
+/// currently simulated.
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "netsim")]
+struct NetSimCommand {
+    /// The latency added to the messages sent and received, in milliseconds.
+    #[arg(long)]
+    latency: Option<u64>,
+    /// The largest random variation of the latency, in milliseconds.
+    #[arg(long)]
+    jitter: Option<u64>,
+    /// The percentage of the messages that are dropped.
+    #[arg(long)]
+    loss: Option<f32>,
+    /// Stop simulating network conditions.
+    #[arg(long)]
+    off: bool,
+}
+
+fn netsim_command(mut command: ConsoleCommand<NetSimCommand>) {
+    let Some(Ok(NetSimCommand {
+        latency,
+        jitter,
+        loss,
+        off,
+    })) = command.take()
+    else {
+        return;
+    };
+
+    if let Some(loss) = loss {
+        if !(0.0..=100.0).contains(&loss) {
+            reply!(command, "The packet loss must be between 0 and 100 percent");
+            command.failed();
+            return;
+        }
+    }
+
+    let mut conditions = SIMULATED_NETWORK_CONDITIONS.lock().unwrap();
+    if off {
+        *conditions = default();
+    }
+    if let Some(latency) = latency {
+        conditions.latency = Duration::from_millis(latency);
+    }
+    if let Some(jitter) = jitter {
+        conditions.jitter = Duration::from_millis(jitter);
+    }
+    if let Some(loss) = loss {
+        conditions.packet_loss = loss / 100.0;
+    }
+
+    if conditions.is_ideal() {
+        reply!(command, "Not simulating network conditions");
+    } else {
+        warn!(conditions = %*conditions, "Simulating network conditions");
+        reply!(command, "Simulating {}", *conditions);
+    }
+    command.ok();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn delays() {
+        let conditions = NetworkConditions {
+            latency: Duration::from_millis(100),
+            jitter: Duration::from_millis(20),
+            packet_loss: 0.25,
+        };
+        assert_eq!(conditions.delay(0.1, 0.5), None);
+        let delay = |jitter_roll| conditions.delay(0.5, jitter_roll).unwrap().as_millis();
+        assert_eq!(delay(0.5), 100);
+        assert_eq!(delay(0.0), 80);
+        assert_eq!(delay(1.0), 120);
+
+        let conditions = NetworkConditions {
+            jitter: Duration::from_millis(20),
+            ..default()
+        };
+        assert_eq!(conditions.delay(0.5, 0.0), Some(Duration::ZERO));
+        assert!(!conditions.is_ideal());
+        assert!(NetworkConditions::default().is_ideal());
+    }
+
+    #[test]
+    fn messages_are_delivered_when_due() {
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+        let mut queue = DelayQueue::default();
+        queue.push(at(30), "c");
+        queue.push(at(10), "a");
+        queue.push(at(20), "b");
+        queue.push(at(10), "a2");
+
+        assert!(queue.pop_due(start).is_empty());
+        assert_eq!(queue.pop_due(at(20)), ["a", "a2", "b"]);
+        assert!(!queue.is_empty());
+        assert_eq!(queue.pop_due(at(100)), ["c"]);
+        assert!(queue.is_empty());
+    }
+}
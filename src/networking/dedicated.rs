@@ -0,0 +1,650 @@
+//! Dedicated hosts, serving LAN matches on a rotation of maps without playing in them.
+//!
+//! When the game is started with `--dedicated-host <rotation.yaml>`, it hosts a LAN match for the
+//! number of players given by the rotation. Once they have joined and confirmed their fighters, it
+//! plays the matches of the rotation one after the other, going back to the first one after the
+//! last. The host doesn't have a fighter of its own. The rotation looks like this:
+//!
+//! ```yaml
+//! # The number of players to wait for, not counting the host.
+//! players: 2
+//! matches:
+//!   - map: Level 1
+//!     rounds: 3
+//!   - map: Level 4
+//!     mode: Teams
+//!     mutators: [LowGravity, Jetpacks]
+//!     rounds: 5
+//! ```
+//!
+//! The next match starts [`RESULTS_DURATION`] after the results of the previous one are shown. The
+//! `rotation --skip` console command ends the running match and starts the next one right away.
+//! Hosts may also be operated remotely through the [admin API][super::admin], and monitored through
+//! their [metrics][super::metrics].
+//! Once every player has left, the host waits for new players to join.
+//!
+//! Since the players can't tell that the host doesn't play, the host tells them which map,
+//! settings, and fighters to start each match with, through [`DedicatedHostMessage`]s sent over the
+//! reliable channel.
+
+use std::{
+    fmt::Display,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use bevy_console::{reply, AddConsoleCommand, ConsoleCommand};
+use clap::Parser;
+use mdns_sd::ServiceInfo;
+
+use super::{
+    chat::TextChat,
+    lan,
+    moderation::Moderation,
+    proto::{MessageTag, TaggedMessage},
+    GgrsSessionRunner, GgrsSessionRunnerInfo, NetworkMatchSocket, ReliableMessage,
+    ReliableMessageHandlers, SocketTarget,
+};
+use crate::{main_menu::player_select::PlayerSelectMessage, prelude::*};
+
+/// Dedicated host plugin.
+pub struct JumpyDedicatedHostPlugin;
+
+impl Plugin for JumpyDedicatedHostPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_console_command::<RotationCommand, _>(rotation_command)
+            .add_system(load_rotation.in_schedule(OnEnter(EngineState::MainMenu)))
+            .add_system(
+                host_lobby
+                    .run_if(in_state(EngineState::MainMenu))
+                    .run_if(resource_exists::<DedicatedHost>()),
+            )
+            .add_system(
+                host_matches
+                    .after(ReliableMessageHandlers)
+                    .run_if(in_state(EngineState::InGame))
+                    .run_if(resource_exists::<DedicatedHost>())
+                    .run_if(resource_exists::<NetworkMatchSocket>()),
+            )
+            .add_system(
+                handle_dedicated_host_messages
+                    .in_set(ReliableMessageHandlers)
+                    .run_if(in_state(EngineState::InGame))
+                    .run_if(resource_exists::<NetworkMatchSocket>()),
+            );
+    }
+}
+
+/// How long the results of a match are shown before the next match of the rotation starts.
+pub const RESULTS_DURATION: Duration = Duration::from_secs(15);
+
+/// How long the players have to confirm their fighters once they joined, after which the players
+/// that haven't are kicked out of the lobby.
+pub const LOBBY_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// The name the dedicated host is advertised with on the local network.
+const SERVICE_NAME: &str = "jumpy-dedicated-host";
+
+/// The matches played by a dedicated host, loaded from YAML.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Rotation {
+    /// The number of players to wait for before the first match, not counting the host.
+    pub players: usize,
+    /// The matches to play, in order.
+    pub matches: Vec<RotationEntry>,
+}
+
+/// A match of a [`Rotation`].
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct RotationEntry {
+    /// The name of the map to play on.
+    pub map: String,
+    /// Whether the players play for themselves or in teams.
+    #[serde(default)]
+    pub mode: MatchMode,
+    /// The mutators enabled for the match.
+    #[serde(default)]
+    pub mutators: Vec<Mutator>,
+    /// The number of rounds a player must win to win the match.
+    #[serde(default = "default_rounds")]
+    pub rounds: u32,
+}
+
+fn default_rounds() -> u32 {
+    3
+}
+
+impl Display for RotationEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({:?}, {} rounds", self.map, self.mode, self.rounds)?;
+        if !self.mutators.is_empty() {
+            write!(f, ", {:?}", self.mutators)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl Rotation {
+    /// Load a rotation from a YAML file.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let rotation = std::fs::read_to_string(path)?;
+        let rotation: Self = serde_yaml::from_str(&rotation)?;
+        rotation.validate()?;
+        Ok(rotation)
+    }
+
+    /// Check that the rotation can be played.
+    fn validate(&self) -> anyhow::Result<()> {
+        if !(1..MAX_PLAYERS).contains(&self.players) {
+            anyhow::bail!(
+                "The rotation must be for 1 to {} players, not {}",
+                MAX_PLAYERS - 1,
+                self.players
+            );
+        }
+        if self.matches.is_empty() {
+            anyhow::bail!("The rotation doesn't have any matches");
+        }
+        if let Some(i) = self.matches.iter().position(|x| x.rounds == 0) {
+            anyhow::bail!(
+                "Match {} of the rotation must have at least one round",
+                i + 1
+            );
+        }
+        Ok(())
+    }
+}
+
+impl RotationEntry {
+    /// Get the settings to play the match with, with a new random seed.
+    pub fn match_settings(&self) -> MatchSettings {
+        MatchSettings {
+            mode: self.mode,
+            mutators: self.mutators.clone(),
+            rounds_to_win: Some(self.rounds),
+            seed: Some(rand::random()),
+            ..default()
+        }
+    }
+}
+
+/// Messages sent by a dedicated host to the players over the reliable channel.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum DedicatedHostMessage {
+    /// Start a new match, replacing the running one if any.
+    StartMatch {
+        map: bones::Handle<MapMeta>,
+        match_settings: MatchSettings,
+        /// The fighters of the players, which the host doesn't have one of.
+        player_info: [Option<GameSessionPlayerInfo>; MAX_PLAYERS],
+    },
+}
+
+impl TaggedMessage for DedicatedHostMessage {
+    const TAG: MessageTag = MessageTag::DedicatedHost;
+}
+
+/// The fighter picked by a player in the lobby of the dedicated host.
+#[derive(Default, Clone)]
+struct PlayerSelection {
+    player: bones::Handle<PlayerMeta>,
+    hat: Option<bones::Handle<HatMeta>>,
+    confirmed: bool,
+}
+
+/// Resource inserted when the game runs as a dedicated host.
+#[derive(Resource)]
+pub struct DedicatedHost {
+    /// The matches to play.
+    pub rotation: Rotation,
+    /// The index of the next match of the rotation to play.
+    pub next_match: usize,
+    /// Set to end the running match and start the next one.
+    pub skip: bool,
+    /// The next match of the rotation as changed by the [admin][super::admin], played instead of
+    /// it.
+    pub next_match_changes: Option<RotationEntry>,
+    /// The number of matches started since the host started.
+    pub matches_served: u64,
+    service_info: Option<ServiceInfo>,
+    /// Whether we are waiting for players to join.
+    hosting: bool,
+    joined_players: usize,
+    /// The fighters picked by the players that joined.
+    selections: [PlayerSelection; MAX_PLAYERS],
+    /// When the players that joined reached the lobby.
+    lobby_started_at: Option<Instant>,
+    /// When the next match starts, once the results of the running match are shown.
+    next_match_at: Option<Instant>,
+}
+
+impl DedicatedHost {
+    pub fn new(rotation: Rotation) -> Self {
+        Self {
+            rotation,
+            next_match: 0,
+            skip: false,
+            next_match_changes: None,
+            matches_served: 0,
+            service_info: None,
+            hosting: false,
+            joined_players: 0,
+            selections: default(),
+            lobby_started_at: None,
+            next_match_at: None,
+        }
+    }
+
+    /// Whether we are waiting for players to join, and the number of players that joined so far.
+    pub fn waiting_players(&self) -> Option<usize> {
+        self.hosting.then_some(self.joined_players)
+    }
+
+    /// Get the service the host is advertised with on the local network, while it waits for players
+    /// to join.
+    pub fn advertised_service(&self) -> Option<&ServiceInfo> {
+        self.service_info.as_ref().filter(|_| self.hosting)
+    }
+
+    /// Get the fighter picked by the given player.
+    pub fn fighter(&self, player_idx: usize) -> Option<&bones::Handle<PlayerMeta>> {
+        self.selections
+            .get(player_idx)
+            .map(|x| &x.player)
+            .filter(|x| x.path != default())
+    }
+
+    /// Get the next match, so that it may be changed before it starts.
+    pub fn next_match_mut(&mut self) -> &mut RotationEntry {
+        let next = &self.rotation.matches[self.next_match];
+        self.next_match_changes.get_or_insert_with(|| next.clone())
+    }
+
+    /// Move on to the next match of the rotation, returning the current one.
+    fn advance(&mut self) -> RotationEntry {
+        let entry = self.rotation.matches[self.next_match].clone();
+        self.next_match = (self.next_match + 1) % self.rotation.matches.len();
+        self.next_match_changes.take().unwrap_or(entry)
+    }
+
+    /// Tell the players to start the next match of the rotation, and get the runner to play it
+    /// with.
+    ///
+    /// Matches on maps that don't exist are skipped, and `None` is returned if there are none left.
+    fn start_next_match(
+        &mut self,
+        socket: &NetworkMatchSocket,
+        core_meta: &CoreMetaArc,
+        map_assets: &Assets<MapMeta>,
+        moderation: &Moderation,
+    ) -> Option<GgrsSessionRunner> {
+        for _ in 0..self.rotation.matches.len() {
+            let entry = self.advance();
+            let Some(map) = find_map(core_meta, map_assets, &entry.map) else {
+                error!(map = %entry.map, "Skipping match of the rotation on an unknown map");
+                continue;
+            };
+            let player_info = std::array::from_fn(|i| {
+                (i != socket.player_idx() && i < socket.player_count() && !moderation.removed[i])
+                    .then(|| GameSessionPlayerInfo {
+                        player: self.fighter(i).unwrap_or(&core_meta.players[0]).clone(),
+                        hat: self.selections[i].hat.clone(),
+                        is_ai: false,
+                        ai_difficulty: default(),
+                    })
+            });
+
+            info!(%entry, "Starting the next match of the rotation");
+            let message = DedicatedHostMessage::StartMatch {
+                map,
+                match_settings: entry.match_settings(),
+                player_info,
+            };
+            socket.send_reliable(SocketTarget::All, &message.encode());
+            self.matches_served += 1;
+            return match_runner(socket.player_idx(), socket, core_meta, map_assets, message);
+        }
+        None
+    }
+}
+
+/// Find the core map with the given name.
+pub fn find_map(
+    core_meta: &CoreMetaArc,
+    map_assets: &Assets<MapMeta>,
+    name: &str,
+) -> Option<bones::Handle<MapMeta>> {
+    core_meta
+        .stable_maps
+        .iter()
+        .chain(&core_meta.experimental_maps)
+        .find(|handle| {
+            map_assets
+                .get(&handle.get_bevy_handle())
+                .map_or(false, |map| map.name == name)
+        })
+        .cloned()
+}
+
+/// Create the runner of the match started by a [`DedicatedHostMessage`] sent by the player
+/// `sender`.
+///
+/// Returns `None` if the message didn't come from the host, or if its map isn't known.
+pub fn match_runner(
+    sender: usize,
+    socket: &NetworkMatchSocket,
+    core_meta: &CoreMetaArc,
+    map_assets: &Assets<MapMeta>,
+    message: DedicatedHostMessage,
+) -> Option<GgrsSessionRunner> {
+    if sender != 0 {
+        warn!(%sender, "Ignoring dedicated host message from player that isn't the host");
+        return None;
+    }
+    let DedicatedHostMessage::StartMatch {
+        map,
+        match_settings,
+        player_info,
+    } = message;
+    let Some(map_meta) = map_assets.get(&map.get_bevy_handle()) else {
+        warn!("Ignoring match started by the dedicated host on an unknown map");
+        return None;
+    };
+
+    Some(GgrsSessionRunner::new(
+        CoreSession::new(CoreSessionInfo {
+            meta: core_meta.0.clone(),
+            map_meta: map_meta.clone(),
+            player_info,
+            match_settings,
+        }),
+        GgrsSessionRunnerInfo {
+            socket: socket.ggrs_socket(),
+            player_is_local: socket.player_is_local(),
+            player_addrs: socket.player_addrs(),
+            player_count: socket.player_count(),
+        },
+    ))
+}
+
+/// Load the rotation given on the command line and start hosting, the first time the main menu is
+/// reached.
+fn load_rotation(mut commands: Commands, mut started: Local<bool>) {
+    if *started {
+        return;
+    }
+    *started = true;
+    let Some(path) = &ENGINE_CONFIG.dedicated_host else {
+        return;
+    };
+
+    match Rotation::load(Path::new(path)) {
+        Ok(rotation) => {
+            info!(matches = rotation.matches.len(), "Starting dedicated host");
+            commands.insert_resource(DedicatedHost::new(rotation));
+        }
+        Err(e) => error!("Could not load dedicated host rotation {path}: {e}"),
+    }
+}
+
+/// Wait for the players to join, and start the first match once they have confirmed their
+/// fighters.
+fn host_lobby(
+    mut commands: Commands,
+    mut host: ResMut<DedicatedHost>,
+    socket: Option<Res<NetworkMatchSocket>>,
+    mut moderation: ResMut<Moderation>,
+    mut text_chat: ResMut<TextChat>,
+    mut session_manager: SessionManager,
+    map_assets: Res<Assets<MapMeta>>,
+) {
+    let host = &mut *host;
+    host.next_match_at = None;
+    if std::mem::take(&mut host.skip) {
+        host.advance();
+    }
+
+    let Some(socket) = socket else {
+        let player_count = host.rotation.players + 1;
+        let (_, service_info) = lan::prepare_to_host(&mut host.service_info, SERVICE_NAME);
+        if !host.hosting {
+            host.hosting = true;
+            host.lobby_started_at = None;
+            lan::start_server(service_info.clone(), player_count);
+            info!(
+                address = ?lan::local_address(),
+                "Waiting for {} players to join the dedicated host",
+                player_count - 1
+            );
+        }
+        if let Some(lan_socket) = lan::wait_players(&mut host.joined_players, service_info) {
+            commands.insert_resource(NetworkMatchSocket(Box::new(lan_socket)));
+            host.hosting = false;
+        }
+        return;
+    };
+
+    // Confirm the host's slot, so that the players may continue once they have confirmed theirs
+    let now = Instant::now();
+    let Some(lobby_started_at) = host.lobby_started_at else {
+        host.lobby_started_at = Some(now);
+        host.selections = default();
+        socket.send_reliable(
+            SocketTarget::All,
+            &postcard::to_allocvec(&PlayerSelectMessage::ConfirmSelection(true)).unwrap(),
+        );
+        return;
+    };
+
+    for (player, data) in socket.recv_reliable() {
+        if moderation.handle_message(&socket, player, &data)
+            || text_chat.handle_message(player, &data, now)
+        {
+            continue;
+        }
+        if player >= MAX_PLAYERS {
+            continue;
+        }
+
+        let selection = &mut host.selections[player];
+        match postcard::from_bytes::<PlayerSelectMessage>(&data) {
+            Ok(PlayerSelectMessage::SelectPlayer(handle)) => selection.player = handle,
+            Ok(PlayerSelectMessage::SelectHat(hat)) => selection.hat = hat,
+            Ok(PlayerSelectMessage::ConfirmSelection(confirmed)) => selection.confirmed = confirmed,
+            Err(e) => warn!("Ignoring network message that was not understood: {e}"),
+        }
+    }
+
+    let players = 1..socket.player_count().min(MAX_PLAYERS);
+    if now.duration_since(lobby_started_at) > LOBBY_TIMEOUT {
+        for i in players.clone() {
+            if !moderation.removed[i] && !host.selections[i].confirmed {
+                info!(player_idx = %i, "Kicking player that didn't confirm their fighter in time");
+                moderation.kick(&socket, i, false);
+            }
+        }
+    }
+
+    if players.clone().all(|i| moderation.removed[i]) {
+        info!("Every player left the dedicated host, waiting for new players");
+        socket.close();
+        commands.remove_resource::<NetworkMatchSocket>();
+        return;
+    }
+
+    if players
+        .clone()
+        .all(|i| moderation.removed[i] || host.selections[i].confirmed)
+    {
+        let core_meta = session_manager.core_meta_arc.clone();
+        match host.start_next_match(&socket, &core_meta, &map_assets, &moderation) {
+            Some(runner) => session_manager.start_network_runner(runner),
+            None => {
+                error!("None of the maps of the rotation exist, stopping the dedicated host");
+                socket.close();
+                commands.remove_resource::<NetworkMatchSocket>();
+                commands.remove_resource::<DedicatedHost>();
+            }
+        }
+    }
+}
+
+/// Start the next match of the rotation once the results of the running one have been shown, and
+/// go back to waiting for players once they have all left.
+fn host_matches(
+    mut host: ResMut<DedicatedHost>,
+    socket: Res<NetworkMatchSocket>,
+    moderation: Res<Moderation>,
+    in_game_state: Res<State<InGameState>>,
+    mut session_manager: SessionManager,
+    map_assets: Res<Assets<MapMeta>>,
+) {
+    if (1..socket.player_count().min(MAX_PLAYERS)).all(|i| moderation.removed[i]) {
+        info!("Every player left the dedicated host, waiting for new players");
+        host.skip = false;
+        host.next_match_at = None;
+        // Leaving the match closes the socket
+        session_manager.stop();
+        session_manager
+            .commands
+            .insert_resource(NextState(Some(EngineState::MainMenu)));
+        session_manager
+            .commands
+            .insert_resource(NextState(Some(InGameState::Playing)));
+        return;
+    }
+
+    let now = Instant::now();
+    if in_game_state.0 == InGameState::Results {
+        let next_match_at = *host.next_match_at.get_or_insert(now + RESULTS_DURATION);
+        if now >= next_match_at {
+            host.skip = true;
+        }
+    }
+    if !std::mem::take(&mut host.skip) {
+        return;
+    }
+    host.next_match_at = None;
+
+    let core_meta = session_manager.core_meta_arc.clone();
+    match host.start_next_match(&socket, &core_meta, &map_assets, &moderation) {
+        Some(runner) => {
+            session_manager.start(runner);
+            session_manager
+                .commands
+                .insert_resource(NextState(Some(InGameState::Playing)));
+        }
+        None => error!("None of the maps of the rotation exist"),
+    }
+}
+
+/// Start the next match when the dedicated host tells us to, while in a match.
+///
+/// In the menus, the messages are handled by the player and map selection pages instead.
+fn handle_dedicated_host_messages(
+    mut messages: EventReader<ReliableMessage>,
+    socket: Res<NetworkMatchSocket>,
+    mut session_manager: SessionManager,
+    map_assets: Res<Assets<MapMeta>>,
+) {
+    for message in messages.iter() {
+        let Some(start) = DedicatedHostMessage::decode(&message.data) else {
+            continue;
+        };
+        let core_meta = session_manager.core_meta_arc.clone();
+        if let Some(runner) = match_runner(message.sender, &socket, &core_meta, &map_assets, start)
+        {
+            info!("The dedicated host started the next match");
+            session_manager.start(runner);
+            session_manager
+                .commands
+                .insert_resource(NextState(Some(InGameState::Playing)));
+        }
+    }
+}
+
+/// Show the rotation of the dedicated host, or skip to its next match.
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "rotation")]
+struct RotationCommand {
+    /// End the running match and start the next match of the rotation.
+    #[arg(long)]
+    skip: bool,
+}
+
+fn rotation_command(
+    mut command: ConsoleCommand<RotationCommand>,
+    host: Option<ResMut<DedicatedHost>>,
+) {
+    let Some(Ok(RotationCommand { skip })) = command.take() else {
+        return;
+    };
+    let Some(mut host) = host else {
+        reply!(command, "The game isn't running as a dedicated host");
+        command.failed();
+        return;
+    };
+
+    if skip {
+        host.skip = true;
+        reply!(command, "Skipping to the next match of the rotation");
+    } else {
+        for (i, entry) in host.rotation.matches.iter().enumerate() {
+            let next = if i == host.next_match { ">" } else { " " };
+            reply!(command, "{next} {}. {entry}", i + 1);
+        }
+    }
+    command.ok();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_rotation() {
+        let rotation: Rotation = serde_yaml::from_str(
+            "
+            players: 2
+            matches:
+              - map: Level 1
+              - map: Level 4
+                mode: Teams
+                mutators: [LowGravity, Jetpacks]
+                rounds: 5
+            ",
+        )
+        .unwrap();
+        rotation.validate().unwrap();
+        assert_eq!(rotation.matches[0].rounds, 3);
+        assert_eq!(rotation.matches[0].mode, MatchMode::FreeForAll);
+
+        let settings = rotation.matches[1].match_settings();
+        assert_eq!(settings.mode, MatchMode::Teams);
+        assert_eq!(settings.mutators, [Mutator::LowGravity, Mutator::Jetpacks]);
+        assert_eq!(settings.rounds_to_win, Some(5));
+        assert!(settings.seed.is_some());
+    }
+
+    #[test]
+    fn invalid_rotations() {
+        fn rotation(yaml: &str) -> anyhow::Result<()> {
+            serde_yaml::from_str::<Rotation>(yaml).unwrap().validate()
+        }
+        assert!(rotation("{ players: 0, matches: [{ map: Level 1 }] }").is_err());
+        assert!(rotation(&format!(
+            "{{ players: {MAX_PLAYERS}, matches: [{{ map: Level 1 }}] }}"
+        ))
+        .is_err());
+        assert!(rotation("{ players: 1, matches: [] }").is_err());
+        assert!(rotation("{ players: 1, matches: [{ map: Level 1, rounds: 0 }] }").is_err());
+        assert!(rotation("{ players: 1, matches: [{ map: Level 1 }] }").is_ok());
+
+        assert!(serde_yaml::from_str::<Rotation>(
+            "{ players: 1, matches: [{ map: Level 1, lives: 3 }] }"
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,202 @@
+//! Desync detection for network games.
+//!
+//! The [`GgrsSessionRunner`][super::GgrsSessionRunner] saves the
+//! [checksum][jumpy_core::session::CoreSession::checksum] of the world with every frame it saves
+//! for GGRS, and every [`DESYNC_CHECK_INTERVAL`] frames, GGRS sends the checksum of the confirmed
+//! frame to the other players, which compare it with their own. When they differ, the simulations
+//! have diverged and the match can't go on: the runner ends it with [`SessionError::Desync`], and
+//! writes a desync report with a dump of the world on that frame to the `desync_reports` folder of
+//! the user's data directory.
+//!
+//! The reports written by each player can be compared with a diff tool to find the entities that
+//! are out of sync, and attached to an issue. A warning telling the player about the report is
+//! shown once back in the menu.
+
+use std::{collections::VecDeque, path::PathBuf};
+
+use bevy_egui::EguiContexts;
+use bevy_fluent::Localization;
+
+use crate::prelude::*;
+
+/// Desync detection plugin.
+pub struct JumpyDesyncPlugin;
+
+impl Plugin for JumpyDesyncPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(desync_warning.run_if(resource_exists::<DesyncReport>()));
+    }
+}
+
+/// The interval, in frames, between the frames whose checksums are compared with the other
+/// players.
+pub const DESYNC_CHECK_INTERVAL: u32 = 10;
+
+/// The number of checked frames that the state dumps are kept for.
+///
+/// The checksums of a frame are only compared once the frame is confirmed and the checksum of the
+/// other players has arrived, so this covers a few seconds.
+const STATE_HISTORY_LEN: usize = 32;
+
+/// Whether the checksum of the given frame is compared with the other players.
+pub fn is_checked_frame(frame: i32) -> bool {
+    frame % DESYNC_CHECK_INTERVAL as i32 == 0
+}
+
+/// The state dumps of the last frames checked for desyncs, used to write the desync report.
+#[derive(Default)]
+pub struct StateHistory {
+    dumps: VecDeque<(i32, String)>,
+}
+
+impl StateHistory {
+    /// Record the state dump of a frame, replacing the previous dump of the frame if it was
+    /// simulated again after a rollback.
+    pub fn record(&mut self, frame: i32, dump: String) {
+        if let Some(entry) = self.dumps.iter_mut().find(|x| x.0 == frame) {
+            entry.1 = dump;
+            return;
+        }
+        if self.dumps.len() >= STATE_HISTORY_LEN {
+            self.dumps.pop_front();
+        }
+        self.dumps.push_back((frame, dump));
+    }
+
+    /// Get the state dump of a frame, if it is still recorded.
+    pub fn get(&self, frame: i32) -> Option<&str> {
+        self.dumps
+            .iter()
+            .find(|x| x.0 == frame)
+            .map(|x| x.1.as_str())
+    }
+}
+
+/// Resource inserted when a network match ended because of a desync.
+#[derive(Resource, Debug, Clone)]
+pub struct DesyncReport {
+    /// The frame on which the simulations diverged.
+    pub frame: i32,
+    /// The path the desync report was written to, or `None` if it couldn't be written.
+    pub path: Option<PathBuf>,
+}
+
+/// The info written to a desync report.
+pub struct DesyncInfo<'a> {
+    pub frame: i32,
+    /// The player index of the local player.
+    pub player_idx: usize,
+    /// The player index of the player whose checksum was different.
+    pub remote_player_idx: usize,
+    pub local_checksum: u128,
+    pub remote_checksum: u128,
+    /// The dump of the world on the frame, if it is still recorded.
+    pub state_dump: Option<&'a str>,
+}
+
+impl DesyncInfo<'_> {
+    /// Get the contents of the desync report.
+    pub fn report(&self) -> String {
+        format!(
+            "frame: {}\nplayer: {}\nlocal checksum: {:032x}\n\
+            remote checksum: {:032x} (player {})\n\n{}",
+            self.frame,
+            self.player_idx + 1,
+            self.local_checksum,
+            self.remote_checksum,
+            self.remote_player_idx + 1,
+            self.state_dump
+                .unwrap_or("The state of the frame is no longer available.\n"),
+        )
+    }
+
+    /// Write the desync report to the user's data directory, returning the file path.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self) -> anyhow::Result<PathBuf> {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs();
+        let dir = directories::ProjectDirs::from("org", "FishFolk", "Jumpy")
+            .context("Couldn't identify the user data directory")?
+            .data_dir()
+            .join("desync_reports");
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(format!(
+            "desync-{timestamp}-player-{}.txt",
+            self.player_idx + 1
+        ));
+        std::fs::write(&path, self.report())?;
+
+        Ok(path)
+    }
+
+    /// Browser builds have no user data directory to write the desync report to.
+    #[cfg(target_arch = "wasm32")]
+    pub fn save(&self) -> anyhow::Result<PathBuf> {
+        anyhow::bail!("Desync reports can't be saved in the browser")
+    }
+}
+
+/// Tell the player that the last match ended because of a desync, and where the report is.
+fn desync_warning(
+    mut commands: Commands,
+    mut contexts: EguiContexts,
+    report: Res<DesyncReport>,
+    localization: Res<Localization>,
+) {
+    egui::Window::new(localization.get("desync"))
+        .id(egui::Id::new("desync-warning"))
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(localization.get(&format!("desync-description?frame={}", report.frame)));
+            match &report.path {
+                Some(path) => ui.label(
+                    localization.get(&format!("desync-report-saved?path={}", path.display())),
+                ),
+                None => ui.label(localization.get("desync-report-failed")),
+            };
+            if ui.button(localization.get("close")).clicked() {
+                commands.remove_resource::<DesyncReport>();
+            }
+        });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn state_history() {
+        let mut history = StateHistory::default();
+        history.record(0, "a".into());
+        history.record(10, "b".into());
+        // A rollback simulates the frame again
+        history.record(10, "c".into());
+        assert_eq!(history.get(0), Some("a"));
+        assert_eq!(history.get(10), Some("c"));
+
+        for i in 2..=STATE_HISTORY_LEN as i32 {
+            history.record(i * 10, String::new());
+        }
+        assert_eq!(history.get(0), None);
+        assert_eq!(history.get(10), Some("c"));
+    }
+
+    #[test]
+    fn report_contents() {
+        let info = DesyncInfo {
+            frame: 120,
+            player_idx: 0,
+            remote_player_idx: 1,
+            local_checksum: 0xab,
+            remote_checksum: 0xcd,
+            state_dump: None,
+        };
+        let report = info.report();
+        assert!(report.starts_with("frame: 120\nplayer: 1\n"));
+        assert!(report.contains(&format!("remote checksum: {:032x} (player 2)", 0xcd)));
+        assert!(report.ends_with("no longer available.\n"));
+    }
+}
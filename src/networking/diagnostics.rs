@@ -0,0 +1,260 @@
+//! Network quality diagnostics.
+//!
+//! The [`GgrsSessionRunner`][super::GgrsSessionRunner] keeps the [`NetworkDiagnostics`] resource up
+//! to date during network matches: the ping of every other player, how many frames are re-simulated
+//! because of rollbacks, and how far the simulation runs ahead of the inputs confirmed by everybody.
+//!
+//! When [`Settings::network_hud`] is enabled, they are shown in a small overlay in the top right
+//! corner of the screen, so that players can see why a match feels laggy.
+
+use std::time::Duration;
+
+use bevy::utils::Instant;
+use bevy_egui::EguiContexts;
+use bevy_fluent::Localization;
+
+use super::{NetworkMatchSocket, NETWORK_MAX_PREDICTION_WINDOW};
+use crate::{
+    prelude::*,
+    ui::widgets::{bordered_frame::BorderedFrame, EguiUiExt},
+};
+
+/// Network diagnostics plugin.
+pub struct JumpyNetworkDiagnosticsPlugin;
+
+impl Plugin for JumpyNetworkDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NetworkDiagnostics>()
+            .add_system(reset_network_diagnostics.run_if(resource_added::<NetworkMatchSocket>()))
+            .add_system(
+                network_quality_hud
+                    .run_if(in_state(EngineState::InGame))
+                    .run_if(resource_exists::<NetworkMatchSocket>()),
+            );
+    }
+}
+
+/// The length of the windows the re-simulated frames are counted over.
+const ROLLBACK_WINDOW: Duration = Duration::from_secs(1);
+
+/// The ping from which the connection to a player is considered fair, and poor, in milliseconds.
+const PING_THRESHOLDS: [u32; 2] = [80, 150];
+
+/// The number of frames re-simulated per second from which the connection is considered fair, and
+/// poor.
+const ROLLBACK_THRESHOLDS: [u32; 2] = [10, 30];
+
+/// The diagnostics of the connection to another player.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PeerDiagnostics {
+    /// The round trip time to the player, in milliseconds.
+    pub ping: u32,
+    /// The kilobytes per second sent to the player.
+    pub kbps_sent: usize,
+    /// The number of frames that we are behind the player.
+    pub frames_behind: i32,
+    /// Whether we haven't received anything from the player for a while.
+    pub interrupted: bool,
+}
+
+/// The overall quality of the connection to the other players.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum NetworkQuality {
+    Good,
+    Fair,
+    Poor,
+}
+
+impl NetworkQuality {
+    /// Get the quality of a measurement, given the values from which it is fair, and poor.
+    fn from_thresholds(value: u32, thresholds: [u32; 2]) -> Self {
+        if value >= thresholds[1] {
+            Self::Poor
+        } else if value >= thresholds[0] {
+            Self::Fair
+        } else {
+            Self::Good
+        }
+    }
+}
+
+/// Resource containing the network diagnostics of the current network match.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct NetworkDiagnostics {
+    /// The diagnostics of the connection to every other player still in the match.
+    pub peers: [Option<PeerDiagnostics>; MAX_PLAYERS],
+    /// The number of frames re-simulated because of rollbacks during the last second.
+    pub rollback_frames: u32,
+    /// The number of frames simulated with predicted inputs, ahead of the last frame for which the
+    /// inputs of every player were received.
+    pub predicted_frames: u32,
+    /// The start of the current window the re-simulated frames are counted over.
+    window_start: Option<Instant>,
+    /// The number of frames re-simulated during the current window.
+    window_rollback_frames: u32,
+}
+
+impl NetworkDiagnostics {
+    /// Record the frames re-simulated and the prediction depth after the session advanced at
+    /// `now`.
+    pub fn record_frames(&mut self, now: Instant, rollback_frames: u32, predicted_frames: u32) {
+        let window_start = *self.window_start.get_or_insert(now);
+        if now.duration_since(window_start) >= ROLLBACK_WINDOW {
+            self.rollback_frames = self.window_rollback_frames;
+            self.window_rollback_frames = 0;
+            self.window_start = Some(now);
+        }
+        self.window_rollback_frames += rollback_frames;
+        self.predicted_frames = predicted_frames;
+    }
+
+    /// Get the overall quality of the connection, which is the quality of its worst measurement.
+    pub fn quality(&self) -> NetworkQuality {
+        let peers = self.peers.iter().flatten();
+        let interrupted = peers.clone().any(|x| x.interrupted);
+        let max_ping = peers.map(|x| x.ping).max().unwrap_or_default();
+        let prediction_thresholds = [
+            NETWORK_MAX_PREDICTION_WINDOW as u32 / 3,
+            NETWORK_MAX_PREDICTION_WINDOW as u32 * 2 / 3,
+        ];
+
+        if interrupted {
+            return NetworkQuality::Poor;
+        }
+        NetworkQuality::from_thresholds(max_ping, PING_THRESHOLDS)
+            .max(NetworkQuality::from_thresholds(
+                self.rollback_frames,
+                ROLLBACK_THRESHOLDS,
+            ))
+            .max(NetworkQuality::from_thresholds(
+                self.predicted_frames,
+                prediction_thresholds,
+            ))
+    }
+}
+
+/// Start every network match with empty diagnostics.
+fn reset_network_diagnostics(mut diagnostics: ResMut<NetworkDiagnostics>) {
+    *diagnostics = default();
+}
+
+/// Show the network diagnostics in the top right corner of the screen, when enabled in the
+/// settings.
+fn network_quality_hud(
+    mut contexts: EguiContexts,
+    game: Res<GameMeta>,
+    mut storage: ResMut<Storage>,
+    localization: Res<Localization>,
+    diagnostics: Res<NetworkDiagnostics>,
+    mut enabled: Local<Option<bool>>,
+) {
+    if enabled.is_none() || storage.is_changed() {
+        // Reading the settings needs mutable access, but doesn't change the storage.
+        let settings = Settings::get_stored_or_default(&game, storage.bypass_change_detection());
+        *enabled = Some(settings.network_hud);
+    }
+    if *enabled != Some(true) {
+        return;
+    }
+
+    let ui_theme = &game.ui_theme;
+    let font = ui_theme
+        .font_styles
+        .normal
+        .colored(ui_theme.panel.font_color);
+    let (quality, quality_font) = match diagnostics.quality() {
+        NetworkQuality::Good => ("good", font.colored(ui_theme.colors.positive)),
+        NetworkQuality::Fair => ("fair", font.clone()),
+        NetworkQuality::Poor => ("poor", font.clone()),
+    };
+
+    egui::Area::new("network-quality-hud")
+        .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-10.0, 10.0))
+        .show(contexts.ctx_mut(), |ui| {
+            BorderedFrame::new(&ui_theme.panel.border)
+                .padding(ui_theme.panel.padding.into())
+                .show(ui, |ui| {
+                    ui.themed_label(
+                        &quality_font,
+                        &localization.get(&format!("network-quality-{quality}")),
+                    );
+                    for (player, peer) in diagnostics.peers.iter().enumerate() {
+                        let Some(peer) = peer else { continue };
+                        let label = if peer.interrupted {
+                            localization
+                                .get(&format!("network-peer-interrupted?player={}", player + 1))
+                        } else {
+                            localization.get(&format!(
+                                "network-peer-ping?player={}&ping={}",
+                                player + 1,
+                                peer.ping
+                            ))
+                        };
+                        ui.themed_label(&font, &label);
+                    }
+                    ui.themed_label(
+                        &font,
+                        &localization.get(&format!(
+                            "network-rollback-frames?frames={}",
+                            diagnostics.rollback_frames
+                        )),
+                    );
+                    ui.themed_label(
+                        &font,
+                        &localization.get(&format!(
+                            "network-predicted-frames?frames={}",
+                            diagnostics.predicted_frames
+                        )),
+                    );
+                });
+        });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rollback_frames_are_counted_per_window() {
+        let start = Instant::now();
+        let mut diagnostics = NetworkDiagnostics::default();
+
+        diagnostics.record_frames(start, 3, 2);
+        diagnostics.record_frames(start + Duration::from_millis(500), 4, 5);
+        assert_eq!(diagnostics.rollback_frames, 0);
+        assert_eq!(diagnostics.predicted_frames, 5);
+
+        diagnostics.record_frames(start + ROLLBACK_WINDOW, 1, 1);
+        assert_eq!(diagnostics.rollback_frames, 7);
+        assert_eq!(diagnostics.predicted_frames, 1);
+
+        diagnostics.record_frames(start + ROLLBACK_WINDOW * 2, 0, 0);
+        assert_eq!(diagnostics.rollback_frames, 1);
+    }
+
+    #[test]
+    fn quality_is_the_worst_measurement() {
+        let mut diagnostics = NetworkDiagnostics::default();
+        assert_eq!(diagnostics.quality(), NetworkQuality::Good);
+
+        diagnostics.peers[1] = Some(PeerDiagnostics {
+            ping: 40,
+            ..default()
+        });
+        diagnostics.peers[2] = Some(PeerDiagnostics {
+            ping: 100,
+            ..default()
+        });
+        assert_eq!(diagnostics.quality(), NetworkQuality::Fair);
+
+        diagnostics.rollback_frames = ROLLBACK_THRESHOLDS[1];
+        assert_eq!(diagnostics.quality(), NetworkQuality::Poor);
+
+        diagnostics.rollback_frames = 0;
+        diagnostics.peers[2] = None;
+        assert_eq!(diagnostics.quality(), NetworkQuality::Good);
+
+        diagnostics.peers[1].as_mut().unwrap().interrupted = true;
+        assert_eq!(diagnostics.quality(), NetworkQuality::Poor);
+    }
+}
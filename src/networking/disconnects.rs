@@ -0,0 +1,343 @@
+//! Handling players that lose their connection during network games.
+//!
+//! When GGRS reports that a player disconnected without leaving the match, the other players vote
+//! on whether to keep playing with an AI controlling the fish of the disconnected player. The match
+//! is frozen during the vote, and players that want to stop leave the match instead.
+//!
+//! Once everybody still in the match voted to continue, or after [`DISCONNECT_VOTE_TIMEOUT`], the
+//! host sends the [`takeover_frame`] from which the AI controls the vacant slot, and every peer
+//! resumes the match. The host is player 0, or the player with the lowest index when player 0 is
+//! gone. Every peer is frozen on an earlier frame, so the AI takes over on the same frame for
+//! everybody and the simulations stay in sync.
+
+use std::time::Duration;
+
+use bevy::utils::Instant;
+use bevy_egui::EguiContexts;
+use bevy_fluent::Localization;
+
+use super::{
+    moderation::Moderation,
+    proto::{MessageTag, TaggedMessage},
+    GgrsSessionRunner, NetworkMatchSocket, ReliableMessage, ReliableMessageHandlers, SocketTarget,
+    NETWORK_INPUT_DELAY, NETWORK_MAX_PREDICTION_WINDOW,
+};
+use crate::prelude::*;
+
+/// Disconnect vote plugin.
+pub struct JumpyDisconnectVotePlugin;
+
+impl Plugin for JumpyDisconnectVotePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DisconnectVote>()
+            .add_system(reset_disconnect_vote.run_if(resource_added::<NetworkMatchSocket>()))
+            .add_system(
+                handle_disconnect_messages
+                    .in_set(ReliableMessageHandlers)
+                    .run_if(in_state(EngineState::InGame))
+                    .run_if(resource_exists::<NetworkMatchSocket>())
+                    .run_if(resource_exists::<Session>()),
+            )
+            .add_systems(
+                (finish_disconnect_vote, disconnect_vote_window)
+                    .chain()
+                    .after(ReliableMessageHandlers)
+                    .distributive_run_if(in_state(EngineState::InGame))
+                    .distributive_run_if(resource_exists::<NetworkMatchSocket>())
+                    .distributive_run_if(resource_exists::<Session>()),
+            );
+    }
+}
+
+/// How long the players have to vote, after which the players that didn't vote are counted as
+/// wanting to continue.
+pub const DISCONNECT_VOTE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Control messages used to vote on continuing without disconnected players, sent over the
+/// reliable channel.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisconnectMessage {
+    /// Vote to continue the match with an AI in place of the disconnected players, which also
+    /// starts the vote for players that didn't notice the disconnection yet.
+    Continue {
+        /// The players that disconnected.
+        dropped: [bool; MAX_PLAYERS],
+    },
+    /// Sent by the host once the vote is over, to resume the match.
+    TakeOver {
+        /// The players that are now controlled by an AI.
+        players: [bool; MAX_PLAYERS],
+        /// The first frame on which the AI controls the players.
+        frame: i32,
+    },
+}
+
+impl TaggedMessage for DisconnectMessage {
+    const TAG: MessageTag = MessageTag::Disconnect;
+}
+
+/// Get the frame from which the AI takes over the disconnected players, given the current frame
+/// of the host.
+///
+/// The host is frozen during the vote, so the other players can't predict more than
+/// [`NETWORK_MAX_PREDICTION_WINDOW`] frames past the last input it sent, and they are all still on
+/// an earlier frame when they receive the takeover.
+pub fn takeover_frame(host_frame: i32) -> i32 {
+    host_frame + (NETWORK_INPUT_DELAY + NETWORK_MAX_PREDICTION_WINDOW) as i32 + 1
+}
+
+/// Resource containing the vote on continuing the current network match without the disconnected
+/// players.
+#[derive(Resource, Default, Debug)]
+pub struct DisconnectVote {
+    /// The players that disconnected, and whose slot the vote is about.
+    pub dropped: [bool; MAX_PLAYERS],
+    /// Whether each player voted to continue the match.
+    pub votes: [bool; MAX_PLAYERS],
+    /// The players that are controlled by an AI after a previous vote.
+    pub ai_players: [bool; MAX_PLAYERS],
+    /// When the vote started, if it is in progress.
+    started_at: Option<Instant>,
+}
+
+impl DisconnectVote {
+    /// Whether the vote is in progress, during which the match is frozen.
+    pub fn in_progress(&self) -> bool {
+        self.started_at.is_some()
+    }
+
+    /// Start the vote about the slots of the `dropped` players, if it didn't start yet.
+    pub fn players_dropped(&mut self, dropped: [bool; MAX_PLAYERS], now: Instant) {
+        if !dropped.contains(&true) {
+            return;
+        }
+        for (was_dropped, dropped) in self.dropped.iter_mut().zip(dropped) {
+            *was_dropped |= dropped;
+        }
+        self.started_at.get_or_insert(now);
+    }
+
+    /// Record the vote of a player to continue the match.
+    pub fn vote(&mut self, player_idx: usize) {
+        if self.in_progress() {
+            self.votes[player_idx] = true;
+        }
+    }
+
+    /// Get the number of votes to continue the match, and the number of players that may vote.
+    pub fn vote_count(&self, voters: &[bool; MAX_PLAYERS]) -> (usize, usize) {
+        let votes = (0..MAX_PLAYERS)
+            .filter(|&i| voters[i] && self.votes[i])
+            .count();
+        (votes, voters.iter().filter(|x| **x).count())
+    }
+
+    /// The time left to vote, if the vote is in progress.
+    pub fn remaining(&self, now: Instant) -> Option<Duration> {
+        self.started_at
+            .map(|x| DISCONNECT_VOTE_TIMEOUT.saturating_sub(now.duration_since(x)))
+    }
+
+    /// Whether the vote is over, because every voter voted to continue or the time is up.
+    pub fn is_over(&self, voters: &[bool; MAX_PLAYERS], now: Instant) -> bool {
+        let (votes, voter_count) = self.vote_count(voters);
+        self.in_progress() && (votes == voter_count || self.remaining(now) == Some(Duration::ZERO))
+    }
+
+    /// End the vote, returning the players that disconnected.
+    pub fn finish(&mut self) -> [bool; MAX_PLAYERS] {
+        let dropped = std::mem::take(&mut self.dropped);
+        for (is_ai, dropped) in self.ai_players.iter_mut().zip(dropped) {
+            *is_ai |= dropped;
+        }
+        self.votes = default();
+        self.started_at = None;
+        dropped
+    }
+}
+
+/// Get the players that may vote to continue the match.
+fn voters(
+    socket: &NetworkMatchSocket,
+    moderation: &Moderation,
+    vote: &DisconnectVote,
+) -> [bool; MAX_PLAYERS] {
+    std::array::from_fn(|i| {
+        i < socket.player_count()
+            && !moderation.removed[i]
+            && !vote.dropped[i]
+            && !vote.ai_players[i]
+    })
+}
+
+/// Start every network match without a vote in progress.
+fn reset_disconnect_vote(mut vote: ResMut<DisconnectVote>) {
+    *vote = default();
+}
+
+/// Let the AI take over the `players` from the given frame, and resume the match.
+fn take_over(
+    session: &mut Session,
+    vote: &mut DisconnectVote,
+    players: [bool; MAX_PLAYERS],
+    frame: i32,
+) {
+    let Some(runner) = session.downcast_mut::<GgrsSessionRunner>() else {
+        return;
+    };
+    vote.finish();
+    for (player_idx, _) in players.iter().enumerate().filter(|(_, x)| **x) {
+        info!(%player_idx, %frame, "AI taking over disconnected player");
+        runner.ai_takeover_frames[player_idx] = Some(frame);
+        vote.ai_players[player_idx] = true;
+    }
+}
+
+/// Handle the disconnect messages sent by the other players during a match.
+fn handle_disconnect_messages(
+    mut messages: EventReader<ReliableMessage>,
+    mut session: ResMut<Session>,
+    mut vote: ResMut<DisconnectVote>,
+) {
+    let now = Instant::now();
+    for message in messages.iter() {
+        match DisconnectMessage::decode(&message.data) {
+            Some(DisconnectMessage::Continue { dropped }) => {
+                vote.players_dropped(dropped, now);
+                vote.vote(message.sender);
+            }
+            Some(DisconnectMessage::TakeOver { players, frame }) => {
+                take_over(&mut session, &mut vote, players, frame);
+            }
+            None => (),
+        }
+    }
+}
+
+/// End the vote when we are the host and everybody voted, or the time is up.
+fn finish_disconnect_vote(
+    socket: Res<NetworkMatchSocket>,
+    moderation: Res<Moderation>,
+    mut session: ResMut<Session>,
+    mut vote: ResMut<DisconnectVote>,
+) {
+    let voters = voters(&socket, &moderation, &vote);
+    let host = voters.iter().position(|x| *x);
+    if host != Some(socket.player_idx()) || !vote.is_over(&voters, Instant::now()) {
+        return;
+    }
+    let Some(runner) = session.downcast_mut::<GgrsSessionRunner>() else {
+        return;
+    };
+
+    let players = vote.dropped;
+    let frame = takeover_frame(runner.session.current_frame());
+    socket.send_reliable(
+        SocketTarget::All,
+        &DisconnectMessage::TakeOver { players, frame }.encode(),
+    );
+    take_over(&mut session, &mut vote, players, frame);
+}
+
+/// Ask the player whether to continue the match without the disconnected players.
+fn disconnect_vote_window(
+    mut commands: Commands,
+    mut contexts: EguiContexts,
+    socket: Res<NetworkMatchSocket>,
+    moderation: Res<Moderation>,
+    localization: Res<Localization>,
+    mut vote: ResMut<DisconnectVote>,
+) {
+    let now = Instant::now();
+    let Some(remaining) = vote.remaining(now) else {
+        return;
+    };
+    let local_player = socket.player_idx();
+    let voters = voters(&socket, &moderation, &vote);
+    let (votes, voter_count) = vote.vote_count(&voters);
+
+    egui::Window::new(localization.get("player-disconnected"))
+        .id(egui::Id::new("disconnect-vote"))
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(contexts.ctx_mut(), |ui| {
+            for (player_idx, _) in vote.dropped.iter().enumerate().filter(|(_, x)| **x) {
+                ui.label(localization.get(&format!(
+                    "player-disconnected-description?player={}",
+                    player_idx + 1
+                )));
+            }
+            ui.label(localization.get(&format!(
+                "disconnect-vote-in-progress?votes={votes}&voters={voter_count}&seconds={}",
+                remaining.as_secs_f32().ceil() as u32
+            )));
+
+            if !vote.votes[local_player] {
+                ui.horizontal(|ui| {
+                    if ui.button(localization.get("continue")).clicked() {
+                        let message = DisconnectMessage::Continue {
+                            dropped: vote.dropped,
+                        };
+                        socket.send_reliable(SocketTarget::All, &message.encode());
+                        vote.vote(local_player);
+                    }
+                    // The other players continue without us once we leave
+                    if ui.button(localization.get("end-match")).clicked() {
+                        commands.insert_resource(NextState(Some(EngineState::MainMenu)));
+                    }
+                });
+            }
+        });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn vote_ends_when_everybody_continues() {
+        let now = Instant::now();
+        let voters = [true, false, true, false];
+        let mut vote = DisconnectVote::default();
+        assert!(!vote.in_progress());
+
+        // Votes are ignored until somebody disconnects
+        vote.vote(0);
+        assert_eq!(vote.vote_count(&voters), (0, 2));
+
+        vote.players_dropped([false, true, false, false], now);
+        vote.players_dropped([false, false, false, true], now + Duration::from_secs(1));
+        assert_eq!(vote.dropped, [false, true, false, true]);
+        assert_eq!(vote.remaining(now), Some(DISCONNECT_VOTE_TIMEOUT));
+
+        vote.vote(0);
+        assert!(!vote.is_over(&voters, now));
+        vote.vote(2);
+        assert!(vote.is_over(&voters, now));
+
+        assert_eq!(vote.finish(), [false, true, false, true]);
+        assert!(!vote.in_progress());
+        assert_eq!(vote.ai_players, [false, true, false, true]);
+        assert_eq!(vote.vote_count(&voters), (0, 2));
+    }
+
+    #[test]
+    fn vote_times_out() {
+        let now = Instant::now();
+        let voters = [true, true, false, false];
+        let mut vote = DisconnectVote::default();
+        vote.players_dropped([false, false, true, false], now);
+
+        assert!(!vote.is_over(&voters, now + DISCONNECT_VOTE_TIMEOUT / 2));
+        assert!(vote.is_over(&voters, now + DISCONNECT_VOTE_TIMEOUT));
+    }
+
+    #[test]
+    fn takeover_is_past_every_peer_frame() {
+        let host_frame = 100;
+        let furthest_peer_frame =
+            host_frame + (NETWORK_INPUT_DELAY + NETWORK_MAX_PREDICTION_WINDOW) as i32;
+        assert!(takeover_frame(host_frame) > furthest_peer_frame);
+    }
+}
@@ -9,10 +9,15 @@
 //! the [`ui::main_menu::network_game`] module, in the menu code. This probably isn't the best place
 //! for it, and it should be moved into here to be a part of the [`lan_matchmaker`] task.
 //!
+//! When MDNS doesn't work on the local network, players may also join a match directly with the
+//! address of the host, see [`local_address()`] and [`join_address()`].
+//!
 //! Communication happens directly between LAN peers over the QUIC protocol.
 
 use std::{
-    net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4},
+    collections::HashSet,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::Mutex,
     time::Duration,
 };
 
@@ -22,7 +27,10 @@ use futures_lite::{future, FutureExt};
 use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
 use smallvec::SmallVec;
 
-use super::*;
+use super::{
+    secure::{GgrsCipher, SessionKey},
+    *,
+};
 
 pub struct ServerInfo {
     pub service: ServiceInfo,
@@ -41,16 +49,33 @@ static LAN_MATCHMAKER: Lazy<LanMatchmaker> = Lazy::new(|| {
     LanMatchmaker(client)
 });
 
+/// The addresses of the players that have been banned by the host. Connections from them are
+/// refused when hosting a match.
+static BANNED_PEERS: Lazy<Mutex<HashSet<IpAddr>>> = Lazy::new(default);
+
 static MDNS: Lazy<ServiceDaemon> =
     Lazy::new(|| ServiceDaemon::new().expect("Couldn't start MDNS service discovery thread."));
 
 const MDNS_SERVICE_TYPE: &str = "_jumpy._udp.local.";
 
+/// The multicast group that MDNS uses on IPv4 networks.
+const MDNS_IPV4_GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+
+/// The multicast group that MDNS uses on IPv6 networks.
+const MDNS_IPV6_GROUP: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 0xfb);
+
+/// The port that MDNS uses.
+const MDNS_PORT: u16 = 5353;
+
+/// The largest size of the message telling the players of a LAN match the addresses of their peers,
+/// which may be IPv6 addresses.
+const MATCH_READY_MAX_SIZE: usize = 192;
+
 #[derive(DerefMut, Deref)]
 struct Pinger(BiChannelClient<PingerRequest, PingerResponse>);
 
-type PingerRequest = SmallVec<[Ipv4Addr; 10]>;
-type PingerResponse = SmallVec<[(Ipv4Addr, Option<u16>); 10]>;
+type PingerRequest = SmallVec<[IpAddr; 10]>;
+type PingerResponse = SmallVec<[(IpAddr, Option<u16>); 10]>;
 
 static PINGER: Lazy<Pinger> = Lazy::new(|| {
     let (client, server) = bi_channel();
@@ -86,7 +111,7 @@ pub fn stop_server(service_info: &ServiceInfo) {
 pub fn wait_players(joined_players: &mut usize, service_info: &ServiceInfo) -> Option<LanSocket> {
     while let Ok(response) = LAN_MATCHMAKER.try_recv() {
         match response {
-            LanMatchmakerResponse::ServerStarted => {}
+            LanMatchmakerResponse::ServerStarted | LanMatchmakerResponse::Error(_) => {}
             LanMatchmakerResponse::PlayerCount(count) => {
                 *joined_players = count;
             }
@@ -110,16 +135,62 @@ pub fn wait_players(joined_players: &mut usize, service_info: &ServiceInfo) -> O
     None
 }
 
+/// Get the address of a server discovered on the local network.
+fn server_address(server: &ServerInfo) -> IpAddr {
+    IpAddr::V4(*server.service.get_addresses().iter().next().unwrap())
+}
+
 /// Join a server hosted by someone else.
 pub fn join_server(server: &ServerInfo) {
+    join_address(SocketAddr::new(
+        server_address(server),
+        server.service.get_port(),
+    ));
+}
+
+/// Join a server hosted by someone else at the given address, without discovering it first.
+pub fn join_address(addr: SocketAddr) {
     LAN_MATCHMAKER
-        .try_send(networking::lan::LanMatchmakerRequest::JoinServer {
-            ip: *server.service.get_addresses().iter().next().unwrap(),
-            port: server.service.get_port(),
-        })
+        .try_send(networking::lan::LanMatchmakerRequest::JoinServer { addr })
         .unwrap();
 }
 
+/// Parse the address of a server typed in by the player, like `192.168.1.20:10500` or
+/// `[fd00::20]:10500`.
+pub fn parse_server_address(addr: &str) -> Option<SocketAddr> {
+    let addr: SocketAddr = addr.trim().parse().ok()?;
+    if addr.ip().is_unspecified() || addr.ip().is_multicast() || addr.port() == 0 {
+        return None;
+    }
+    Some(addr)
+}
+
+/// Get the address other players on the local network may join our server with directly.
+///
+/// The IPv4 address is preferred, since it is shorter to type in, and the IPv6 address is only
+/// used on networks without IPv4.
+pub fn local_address() -> Option<SocketAddr> {
+    let ip = local_ip((MDNS_IPV4_GROUP, MDNS_PORT).into()).or_else(|| {
+        local_ip((MDNS_IPV6_GROUP, MDNS_PORT).into()).filter(|_| endpoint_supports_ipv6())
+    })?;
+    let port = NETWORK_ENDPOINT.local_addr().ok()?.port();
+    Some(SocketAddr::new(ip, port))
+}
+
+/// Get the local address that reaches the given MDNS multicast group, which is the one used on the
+/// local network.
+fn local_ip(group: SocketAddr) -> Option<IpAddr> {
+    let unspecified = match group {
+        SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    };
+    // Connecting a UDP socket doesn't send anything, but picks the local address.
+    let socket = std::net::UdpSocket::bind((unspecified, 0)).ok()?;
+    socket.connect(group).ok()?;
+    let ip = socket.local_addr().ok()?.ip();
+    (!ip.is_unspecified()).then_some(ip)
+}
+
 /// Leave a joined server.
 pub fn leave_server() {
     LAN_MATCHMAKER
@@ -127,22 +198,23 @@ pub fn leave_server() {
         .unwrap();
 }
 
-/// Wait for a joined game to start.
-pub fn wait_game_start() -> Option<LanSocket> {
+/// Wait for a joined game to start, returning an error if we could not join it.
+pub fn wait_game_start() -> Result<Option<LanSocket>, String> {
     while let Ok(message) = LAN_MATCHMAKER.try_recv() {
         match message {
             LanMatchmakerResponse::ServerStarted | LanMatchmakerResponse::PlayerCount(_) => {}
+            LanMatchmakerResponse::Error(e) => return Err(e),
             LanMatchmakerResponse::GameStarting {
                 lan_socket,
                 player_idx,
                 player_count: _,
             } => {
                 info!(?player_idx, "Starting network game");
-                return Some(lan_socket);
+                return Ok(Some(lan_socket));
             }
         }
     }
-    None
+    Ok(None)
 }
 
 /// Update server pings and turn on service discovery.
@@ -154,18 +226,13 @@ pub fn prepare_to_join(
     // Update server pings
     if ping_update_timer.finished() {
         PINGER
-            .try_send(
-                servers
-                    .iter()
-                    .map(|x| *x.service.get_addresses().iter().next().unwrap())
-                    .collect(),
-            )
+            .try_send(servers.iter().map(server_address).collect())
             .ok();
     }
     if let Ok(pings) = PINGER.try_recv() {
         for (server, ping) in pings {
             for info in servers.iter_mut() {
-                if info.service.get_addresses().contains(&server) {
+                if server_address(info) == server {
                     info.ping = ping;
                 }
             }
@@ -234,10 +301,14 @@ async fn lan_matchmaker(
     enum MatchmakerNetMsg {
         MatchReady {
             /// The peers they have for the match, with the index in the array being the player index of the peer.
-            peers: [Option<SocketAddrV4>; MAX_PLAYERS],
+            peers: [Option<SocketAddr>; MAX_PLAYERS],
             /// The player index of the player getting the message.
             player_idx: usize,
             player_count: usize,
+            /// The token the players use to rejoin the match after a network change.
+            session_token: u64,
+            /// The key the GGRS messages of the match are sealed with.
+            session_key: [u8; 32],
         },
     }
 
@@ -282,6 +353,12 @@ async fn lan_matchmaker(
                         // Handle new connections
                         either::Either::Right(Some(new_connection)) => {
                             let Some(conn) = new_connection.await.ok() else { continue };
+                            let ip = canonical_addr(conn.remote_address()).ip();
+                            if BANNED_PEERS.lock().unwrap().contains(&ip) {
+                                info!(%ip, "Refused connection from banned player");
+                                conn.close(0u8.into(), b"banned");
+                                continue;
+                            }
                             connections.push(conn);
                             let current_players = connections.len() + 1;
                             info!(%current_players, "New player connection");
@@ -307,6 +384,9 @@ async fn lan_matchmaker(
                     if connections.len() == player_count - 1 {
                         info!("All players joined.");
 
+                        let session_token = rand::random();
+                        let session_key = SessionKey::random();
+
                         // Tell all clients we're ready
                         for (i, conn) in connections.iter().enumerate() {
                             let mut peers = [None; MAX_PLAYERS];
@@ -315,20 +395,20 @@ async fn lan_matchmaker(
                                 .enumerate()
                                 .filter(|x| x.0 != i)
                                 .for_each(|(i, conn)| {
-                                    if let SocketAddr::V4(addr) = conn.remote_address() {
-                                        peers[i + 1] = Some(addr);
-                                    } else {
-                                        unreachable!("IPV6 not supported in LAN matchmaking");
-                                    };
+                                    peers[i + 1] = Some(canonical_addr(conn.remote_address()));
                                 });
 
                             let mut uni = conn.open_uni().await.unwrap();
                             uni.write_all(
-                                &postcard::to_vec::<_, 20>(&MatchmakerNetMsg::MatchReady {
-                                    player_idx: i + 1,
-                                    peers,
-                                    player_count,
-                                })
+                                &postcard::to_vec::<_, MATCH_READY_MAX_SIZE>(
+                                    &MatchmakerNetMsg::MatchReady {
+                                        player_idx: i + 1,
+                                        peers,
+                                        player_count,
+                                        session_token,
+                                        session_key: session_key.0,
+                                    },
+                                )
                                 .unwrap(),
                             )
                             .await
@@ -348,7 +428,12 @@ async fn lan_matchmaker(
                         // Send the connections to the game so that it can start the network match.
                         matchmaker_channel
                             .try_send(LanMatchmakerResponse::GameStarting {
-                                lan_socket: LanSocket::new(0, connections),
+                                lan_socket: LanSocket::new(
+                                    0,
+                                    connections,
+                                    session_token,
+                                    session_key,
+                                ),
                                 player_idx: 0,
                                 player_count,
                             })
@@ -374,16 +459,26 @@ async fn lan_matchmaker(
             LanMatchmakerRequest::StopJoin => (),
 
             // Join a hosted match
-            LanMatchmakerRequest::JoinServer { ip, port } => {
-                let conn = NETWORK_ENDPOINT
-                    .connect((ip, port).into(), "jumpy-host")
-                    .unwrap()
-                    .await
-                    .expect("Could not connect to server");
+            LanMatchmakerRequest::JoinServer { addr } => {
+                // The address may have been typed in by the player, so it may not be reachable
+                let conn = match NETWORK_ENDPOINT.connect(addr, "jumpy-host") {
+                    Ok(connecting) => connecting.await.map_err(|e| e.to_string()),
+                    Err(e) => Err(e.to_string()),
+                };
+                let conn = match conn {
+                    Ok(conn) => conn,
+                    Err(e) => {
+                        warn!(%addr, "Could not connect to server: {e}");
+                        matchmaker_channel
+                            .try_send(LanMatchmakerResponse::Error(e))
+                            .ok();
+                        continue;
+                    }
+                };
 
                 // Wait for match to start
                 let mut uni = conn.accept_uni().await.unwrap();
-                let bytes = uni.read_to_end(20).await.unwrap();
+                let bytes = uni.read_to_end(MATCH_READY_MAX_SIZE).await.unwrap();
                 let message: MatchmakerNetMsg = postcard::from_bytes(&bytes).unwrap();
 
                 match message {
@@ -391,6 +486,8 @@ async fn lan_matchmaker(
                         peers: peer_addrs,
                         player_idx,
                         player_count,
+                        session_token,
+                        session_key,
                     } => {
                         info!(%player_count, %player_idx, ?peer_addrs, "Matchmaking finished");
                         let mut peer_connections = std::array::from_fn(|_| None);
@@ -430,7 +527,7 @@ async fn lan_matchmaker(
                         for i in range {
                             let addr = peer_addrs[i].unwrap();
                             let conn = NETWORK_ENDPOINT
-                                .connect(addr.into(), "jumpy-peer")
+                                .connect(addr, "jumpy-peer")
                                 .unwrap()
                                 .await
                                 .expect("Could not connect to peer");
@@ -443,7 +540,12 @@ async fn lan_matchmaker(
                             peer_connections[i] = Some(conn);
                         }
 
-                        let lan_socket = LanSocket::new(player_idx, peer_connections);
+                        let lan_socket = LanSocket::new(
+                            player_idx,
+                            peer_connections,
+                            session_token,
+                            SessionKey(session_key),
+                        );
                         info!("Connections established.");
 
                         matchmaker_channel
@@ -468,7 +570,7 @@ pub struct LanMatchmaker(BiChannelClient<LanMatchmakerRequest, LanMatchmakerResp
 #[derive(Debug)]
 pub enum LanMatchmakerRequest {
     StartServer { player_count: usize },
-    JoinServer { ip: Ipv4Addr, port: u16 },
+    JoinServer { addr: SocketAddr },
     StopServer,
     StopJoin,
 }
@@ -476,6 +578,8 @@ pub enum LanMatchmakerRequest {
 /// A response that may come from the [`LAN_MATCHMAKER`].
 pub enum LanMatchmakerResponse {
     ServerStarted,
+    /// Joining a server failed.
+    Error(String),
     PlayerCount(usize),
     GameStarting {
         lan_socket: LanSocket,
@@ -484,147 +588,326 @@ pub enum LanMatchmakerResponse {
     },
 }
 
+/// The largest size of the message sent by a player rejoining a LAN match.
+const REJOIN_MAX_SIZE: usize = 32;
+
+/// The message sent by a player connecting to a peer again after their network changed.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+struct RejoinMessage {
+    /// The token shared by the players of the match.
+    session_token: u64,
+    /// The player index of the rejoining player.
+    player_idx: usize,
+}
+
+impl RejoinMessage {
+    /// Whether the player with the given index may let the peer rejoin with this message.
+    ///
+    /// Peers only rejoin the players that they connected to during matchmaking, which are the
+    /// players with a higher index than theirs.
+    fn is_valid(&self, session_token: u64, player_idx: usize) -> bool {
+        self.session_token == session_token && self.player_idx < player_idx
+    }
+}
+
+/// The connections of a [`LanSocket`] to its peers, and the channels their messages are received
+/// into.
+#[derive(Debug, Clone)]
+struct LanPeers {
+    /// The connection to each peer, replaced when the peer rejoins after a network change.
+    connections: Arc<Mutex<[Option<quinn::Connection>; MAX_PLAYERS]>>,
+    /// Seals and opens the GGRS messages exchanged with the peers.
+    cipher: GgrsCipher,
+    ggrs_sender: async_channel::Sender<(usize, ggrs::Message)>,
+    reliable_sender: async_channel::Sender<(usize, Vec<u8>)>,
+}
+
+impl LanPeers {
+    /// Get the connection to the peer with the given index.
+    fn get(&self, i: usize) -> Option<quinn::Connection> {
+        self.connections.lock().unwrap()[i].clone()
+    }
+
+    /// Replace the connection to a peer with the new connection they rejoined with.
+    fn replace(&self, i: usize, conn: quinn::Connection) {
+        self.spawn_receivers(i, conn.clone());
+        if let Some(old) = self.connections.lock().unwrap()[i].replace(conn) {
+            old.close(0u8.into(), b"rejoined");
+        }
+    }
+
+    /// Spawn the tasks receiving the network messages of a peer, until the connection is closed.
+    fn spawn_receivers(&self, i: usize, conn: quinn::Connection) {
+        let pool = IoTaskPool::get();
+        let cipher = self.cipher.clone();
+        let ggrs_sender = self.ggrs_sender.clone();
+        let reliable_sender = self.reliable_sender.clone();
+
+        // Unreliable message receiver
+        let conn_ = conn.clone();
+        pool.spawn(async move {
+            let conn = conn_;
+
+            #[cfg(feature = "debug-network-slowdown")]
+            use turborand::prelude::*;
+            #[cfg(feature = "debug-network-slowdown")]
+            let rng = AtomicRng::new();
+
+            loop {
+                let event = future::or(async { either::Left(conn.closed().await) }, async {
+                    either::Right(conn.read_datagram().await)
+                })
+                .await;
+
+                match event {
+                    either::Either::Left(closed) => {
+                        warn!("Connection error: {closed}");
+                        break;
+                    }
+                    either::Either::Right(datagram_result) => match datagram_result {
+                        Ok(data) => {
+                            let Some(data) = cipher.open(i, &data) else {
+                                debug!(player = %i, "Dropped unauthenticated network message");
+                                continue;
+                            };
+                            let message: ggrs::Message = postcard::from_bytes(&data)
+                                .expect("Could not deserialize net message");
+
+                            // Debugging code to introduce artificial latency
+                            #[cfg(feature = "debug-network-slowdown")]
+                            {
+                                use async_timer::Oneshot;
+                                async_timer::oneshot::Timer::new(std::time::Duration::from_millis(
+                                    (rng.f32_normalized() * 100.0) as u64 + 1,
+                                ))
+                                .await;
+                            }
+                            if ggrs_sender.send((i, message)).await.is_err() {
+                                break;
+                            }
+                        }
+                        Err(e) => {
+                            warn!("Connection error: {e}");
+                        }
+                    },
+                }
+            }
+        })
+        .detach();
+
+        // Reliable message receiver
+        pool.spawn(async move {
+            #[cfg(feature = "debug-network-slowdown")]
+            use turborand::prelude::*;
+            #[cfg(feature = "debug-network-slowdown")]
+            let rng = AtomicRng::new();
+
+            loop {
+                let event = future::or(async { either::Left(conn.closed().await) }, async {
+                    either::Right(conn.accept_uni().await)
+                })
+                .await;
+
+                match event {
+                    either::Either::Left(closed) => {
+                        warn!("Connection error: {closed}");
+                        break;
+                    }
+                    either::Either::Right(result) => match result {
+                        Ok(mut stream) => {
+                            let data = stream.read_to_end(4096).await.expect("Network read error");
+
+                            // Debugging code to introduce artificial latency
+                            #[cfg(feature = "debug-network-slowdown")]
+                            {
+                                use async_timer::Oneshot;
+                                async_timer::oneshot::Timer::new(std::time::Duration::from_millis(
+                                    (rng.f32_normalized() * 100.0) as u64 + 1,
+                                ))
+                                .await;
+                            }
+                            if reliable_sender.send((i, data)).await.is_err() {
+                                break;
+                            }
+                        }
+                        Err(e) => {
+                            warn!("Connection error: {e}");
+                        }
+                    },
+                }
+            }
+        })
+        .detach();
+    }
+}
+
 /// The LAN [`NetworkSocket`] implementation.
+///
+/// During matchmaking the peers with a higher player index than ours connect to us, and we connect
+/// to the others. When our network changes, QUIC migrates the connections we opened to our new
+/// address, but the peers that connected to us can't follow us, so we connect to them again with
+/// the [`session_token`][Self::session_token] of the match. See [`migration`][super::migration].
 #[derive(Debug, Clone)]
 pub struct LanSocket {
-    pub connections: [Option<quinn::Connection>; MAX_PLAYERS],
+    peers: LanPeers,
     pub ggrs_receiver: async_channel::Receiver<(usize, ggrs::Message)>,
     pub reliable_receiver: async_channel::Receiver<(usize, Vec<u8>)>,
     pub player_idx: usize,
     pub player_count: usize,
+    /// The token shared by the players of the match, used to rejoin it after a network change.
+    pub session_token: u64,
+    /// Closed to stop accepting rejoining peers once the socket is closed.
+    rejoin_stop: async_channel::Sender<()>,
 }
 
 impl LanSocket {
-    pub fn new(player_idx: usize, connections: [Option<quinn::Connection>; MAX_PLAYERS]) -> Self {
+    pub fn new(
+        player_idx: usize,
+        connections: [Option<quinn::Connection>; MAX_PLAYERS],
+        session_token: u64,
+        session_key: SessionKey,
+    ) -> Self {
         let (ggrs_sender, ggrs_receiver) = async_channel::unbounded();
         let (reliable_sender, reliable_receiver) = async_channel::unbounded();
+        let (rejoin_stop, rejoin_stop_receiver) = async_channel::bounded(1);
 
-        let pool = bevy::tasks::IoTaskPool::get();
+        let player_count = connections.iter().flatten().count() + 1;
+        let peers = LanPeers {
+            connections: Arc::new(Mutex::new(connections.clone())),
+            cipher: GgrsCipher::new(session_key, player_idx),
+            ggrs_sender,
+            reliable_sender,
+        };
 
         // Spawn tasks to receive network messages from each peer
-        #[allow(clippy::needless_range_loop)]
-        for i in 0..MAX_PLAYERS {
-            if let Some(conn) = connections[i].clone() {
-                let ggrs_sender = ggrs_sender.clone();
-
-                // Unreliable message receiver
-                let conn_ = conn.clone();
-                pool.spawn(async move {
-                    let conn = conn_;
-
-                    #[cfg(feature = "debug-network-slowdown")]
-                    use turborand::prelude::*;
-                    #[cfg(feature = "debug-network-slowdown")]
-                    let rng = AtomicRng::new();
-
-                    loop {
-                        let event =
-                            future::or(async { either::Left(conn.closed().await) }, async {
-                                either::Right(conn.read_datagram().await)
-                            })
-                            .await;
+        for (i, conn) in connections.into_iter().enumerate() {
+            if let Some(conn) = conn {
+                peers.spawn_receivers(i, conn);
+            }
+        }
 
-                        match event {
-                            either::Either::Left(closed) => {
-                                warn!("Connection error: {closed}");
-                                break;
-                            }
-                            either::Either::Right(datagram_result) => match datagram_result {
-                                Ok(data) => {
-                                    let message: ggrs::Message = postcard::from_bytes(&data)
-                                        .expect("Could not deserialize net message");
-
-                                    // Debugging code to introduce artificial latency
-                                    #[cfg(feature = "debug-network-slowdown")]
-                                    {
-                                        use async_timer::Oneshot;
-                                        async_timer::oneshot::Timer::new(
-                                            std::time::Duration::from_millis(
-                                                (rng.f32_normalized() * 100.0) as u64 + 1,
-                                            ),
-                                        )
-                                        .await;
-                                    }
-                                    if ggrs_sender.send((i, message)).await.is_err() {
-                                        break;
-                                    }
-                                }
-                                Err(e) => {
-                                    warn!("Connection error: {e}");
-                                }
-                            },
-                        }
-                    }
-                })
-                .detach();
+        IoTaskPool::get()
+            .spawn(accept_rejoining_peers(
+                peers.clone(),
+                player_idx,
+                session_token,
+                rejoin_stop_receiver,
+            ))
+            .detach();
 
-                // Reliable message receiver
-                let reliable_sender = reliable_sender.clone();
-                pool.spawn(async move {
-                    #[cfg(feature = "debug-network-slowdown")]
-                    use turborand::prelude::*;
-                    #[cfg(feature = "debug-network-slowdown")]
-                    let rng = AtomicRng::new();
-
-                    loop {
-                        let event =
-                            future::or(async { either::Left(conn.closed().await) }, async {
-                                either::Right(conn.accept_uni().await)
-                            })
-                            .await;
+        Self {
+            peers,
+            ggrs_receiver,
+            reliable_receiver,
+            player_idx,
+            player_count,
+            session_token,
+            rejoin_stop,
+        }
+    }
 
-                        match event {
-                            either::Either::Left(closed) => {
-                                warn!("Connection error: {closed}");
-                                break;
-                            }
-                            either::Either::Right(result) => match result {
-                                Ok(mut stream) => {
-                                    let data =
-                                        stream.read_to_end(4096).await.expect("Network read error");
-
-                                    // Debugging code to introduce artificial latency
-                                    #[cfg(feature = "debug-network-slowdown")]
-                                    {
-                                        use async_timer::Oneshot;
-                                        async_timer::oneshot::Timer::new(
-                                            std::time::Duration::from_millis(
-                                                (rng.f32_normalized() * 100.0) as u64 + 1,
-                                            ),
-                                        )
-                                        .await;
-                                    }
-                                    if reliable_sender.send((i, data)).await.is_err() {
-                                        break;
-                                    }
-                                }
-                                Err(e) => {
-                                    warn!("Connection error: {e}");
-                                }
-                            },
-                        }
+    /// Connect again to the peers that we connected to during matchmaking, when the connection to
+    /// them passes the `filter`.
+    fn rejoin_peers(&self, filter: impl Fn(&quinn::Connection) -> bool) {
+        let message = RejoinMessage {
+            session_token: self.session_token,
+            player_idx: self.player_idx,
+        };
+        for i in (self.player_idx + 1)..self.player_count {
+            if !self.peers.get(i).map_or(false, |x| filter(&x)) {
+                continue;
+            }
+            let peers = self.peers.clone();
+            IoTaskPool::get()
+                .spawn(async move {
+                    if let Err(e) = rejoin_peer(peers, i, message).await {
+                        warn!(player = i, "Could not rejoin network player: {e}");
                     }
                 })
                 .detach();
-            }
         }
+    }
+}
 
-        Self {
-            player_idx,
-            player_count: connections.iter().flatten().count() + 1,
-            connections,
-            ggrs_receiver,
-            reliable_receiver,
-        }
+/// Accept the peers rejoining the match after their network changed, until the socket is closed.
+async fn accept_rejoining_peers(
+    peers: LanPeers,
+    player_idx: usize,
+    session_token: u64,
+    stop: async_channel::Receiver<()>,
+) {
+    loop {
+        let next_conn = async { NETWORK_ENDPOINT.accept().await };
+        let stopped = async {
+            stop.recv().await.ok();
+            None
+        };
+        let Some(connecting) = next_conn.or(stopped).await else { break };
+
+        let peers = peers.clone();
+        IoTaskPool::get()
+            .spawn(async move {
+                let Ok(conn) = connecting.await else { return };
+                let ip = canonical_addr(conn.remote_address()).ip();
+                if BANNED_PEERS.lock().unwrap().contains(&ip) {
+                    info!(%ip, "Refused connection from banned player");
+                    conn.close(0u8.into(), b"banned");
+                    return;
+                }
+
+                let message = async {
+                    let mut uni = conn.accept_uni().await.ok()?;
+                    let bytes = uni.read_to_end(REJOIN_MAX_SIZE).await.ok()?;
+                    postcard::from_bytes::<RejoinMessage>(&bytes).ok()
+                }
+                .await;
+                match message {
+                    Some(message) if message.is_valid(session_token, player_idx) => {
+                        info!(player = message.player_idx, "Network player rejoined");
+                        peers.replace(message.player_idx, conn);
+                    }
+                    _ => {
+                        info!(%ip, "Refused connection to the running match");
+                        conn.close(0u8.into(), b"refused");
+                    }
+                }
+            })
+            .detach();
     }
 }
 
+/// Connect again to a peer that connected to us during matchmaking, after our network changed.
+async fn rejoin_peer(
+    peers: LanPeers,
+    peer_idx: usize,
+    message: RejoinMessage,
+) -> anyhow::Result<()> {
+    let Some(old) = peers.get(peer_idx) else {
+        return Ok(());
+    };
+    let addr = canonical_addr(old.remote_address());
+    let conn = NETWORK_ENDPOINT.connect(addr, "jumpy-peer")?.await?;
+
+    let mut uni = conn.open_uni().await?;
+    uni.write_all(
+        &postcard::to_vec::<_, REJOIN_MAX_SIZE>(&message)
+            .map_err(|e| anyhow::format_err!("Could not encode rejoin message: {e}"))?,
+    )
+    .await?;
+    uni.finish().await?;
+
+    info!(player = peer_idx, "Rejoined network player");
+    peers.replace(peer_idx, conn);
+    Ok(())
+}
+
 impl ggrs::NonBlockingSocket<usize> for LanSocket {
     fn send_to(&mut self, msg: &ggrs::Message, addr: &usize) {
-        let conn = self.connections[*addr].as_ref().unwrap();
+        let conn = self.peers.get(*addr).unwrap();
 
         // TODO: determine a reasonable size for this buffer.
         let msg_bytes = postcard::to_allocvec(msg).unwrap();
+        let msg_bytes = self.peers.cipher.seal(*addr, &msg_bytes);
         conn.send_datagram(Bytes::copy_from_slice(&msg_bytes[..]))
             .ok();
     }
@@ -645,25 +928,26 @@ impl NetworkSocket for LanSocket {
 
         match target {
             SocketTarget::Player(i) => {
-                let conn = self.connections[i].as_ref().unwrap().clone();
+                let conn = self.peers.get(i).unwrap();
 
                 task_pool
                     .spawn(async move {
-                        let mut stream = conn.open_uni().await.unwrap();
-                        stream.write_chunk(message).await.unwrap();
-                        stream.finish().await.unwrap();
+                        // The player may have left or been kicked out of the game.
+                        let Ok(mut stream) = conn.open_uni().await else { return };
+                        stream.write_chunk(message).await.ok();
+                        stream.finish().await.ok();
                     })
                     .detach();
             }
             SocketTarget::All => {
-                for conn in &self.connections {
+                for conn in self.peers.connections.lock().unwrap().iter() {
                     if let Some(conn) = conn.clone() {
                         let message = message.clone();
                         task_pool
                             .spawn(async move {
-                                let mut stream = conn.open_uni().await.unwrap();
-                                stream.write_chunk(message).await.unwrap();
-                                stream.finish().await.unwrap();
+                                let Ok(mut stream) = conn.open_uni().await else { return };
+                                stream.write_chunk(message).await.ok();
+                                stream.finish().await.ok();
                             })
                             .detach();
                     }
@@ -685,7 +969,8 @@ impl NetworkSocket for LanSocket {
     }
 
     fn close(&self) {
-        for conn in self.connections.iter().flatten() {
+        self.rejoin_stop.close();
+        for conn in self.peers.connections.lock().unwrap().iter().flatten() {
             conn.close(0u8.into(), &[]);
         }
     }
@@ -699,7 +984,31 @@ impl NetworkSocket for LanSocket {
     }
 
     fn player_is_local(&self) -> [bool; MAX_PLAYERS] {
-        std::array::from_fn(|i| self.connections[i].is_none() && i < self.player_count)
+        let connections = self.peers.connections.lock().unwrap();
+        std::array::from_fn(|i| connections[i].is_none() && i < self.player_count)
+    }
+
+    fn ban_player(&self, player_idx: usize) {
+        if let Some(conn) = self.peers.get(player_idx) {
+            BANNED_PEERS
+                .lock()
+                .unwrap()
+                .insert(canonical_addr(conn.remote_address()).ip());
+        }
+    }
+
+    fn remote_addr(&self) -> Option<SocketAddr> {
+        let connections = self.peers.connections.lock().unwrap();
+        let conn = connections.iter().flatten().next()?;
+        Some(canonical_addr(conn.remote_address()))
+    }
+
+    fn migrate(&self) {
+        self.rejoin_peers(|_| true);
+    }
+
+    fn reconnect(&self) {
+        self.rejoin_peers(|conn| conn.close_reason().is_some());
     }
 }
 
@@ -708,12 +1017,8 @@ fn pinger(server: BiChannelServer<PingerRequest, PingerResponse>) {
         let mut pings = SmallVec::new();
         for server in servers {
             let start = Instant::now();
-            let ping_result = ping_rs::send_ping(
-                &IpAddr::V4(server),
-                Duration::from_secs(2),
-                &[1, 2, 3, 4],
-                None,
-            );
+            let ping_result =
+                ping_rs::send_ping(&server, Duration::from_secs(2), &[1, 2, 3, 4], None);
 
             let ping = if let Err(e) = ping_result {
                 warn!("Error pinging {server}: {e:?}");
@@ -729,3 +1034,49 @@ fn pinger(server: BiChannelServer<PingerRequest, PingerResponse>) {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rejoin_message() {
+        let message = RejoinMessage {
+            session_token: u64::MAX,
+            player_idx: 1,
+        };
+        let bytes = postcard::to_vec::<_, REJOIN_MAX_SIZE>(&message).unwrap();
+        assert_eq!(
+            postcard::from_bytes::<RejoinMessage>(&bytes).unwrap(),
+            message
+        );
+
+        // Peers may only rejoin the players they connected to, with the token of the match
+        assert!(message.is_valid(u64::MAX, 2));
+        assert!(!message.is_valid(u64::MAX, 1));
+        assert!(!message.is_valid(u64::MAX, 0));
+        assert!(!message.is_valid(0, 2));
+    }
+
+    #[test]
+    fn server_addresses() {
+        assert_eq!(
+            parse_server_address(" 192.168.1.20:10500 "),
+            Some(SocketAddr::from(([192, 168, 1, 20], 10500)))
+        );
+        assert_eq!(
+            parse_server_address("[fd00::20]:10500"),
+            Some(SocketAddr::from((
+                Ipv6Addr::new(0xfd00, 0, 0, 0, 0, 0, 0, 0x20),
+                10500
+            )))
+        );
+        assert_eq!(parse_server_address("192.168.1.20"), None);
+        assert_eq!(parse_server_address("192.168.1.20:0"), None);
+        assert_eq!(parse_server_address("0.0.0.0:10500"), None);
+        assert_eq!(parse_server_address("224.0.0.251:10500"), None);
+        assert_eq!(parse_server_address("[::]:10500"), None);
+        assert_eq!(parse_server_address("[ff02::fb]:10500"), None);
+        assert_eq!(parse_server_address("jumpy:10500"), None);
+    }
+}
@@ -0,0 +1,230 @@
+//! Metrics of [dedicated hosts][super::dedicated], for monitoring their health.
+//!
+//! When a dedicated host is started with `--metrics-port <port>`, it serves its metrics over HTTP
+//! at `/metrics` on that port, in the Prometheus text format:
+//!
+//! - `jumpy_matches_served_total`: the number of matches started since the host started.
+//! - `jumpy_active_peers`: the number of players connected to the host.
+//! - `jumpy_rollback_frames_total`, and `jumpy_rollback_frames_average`: the number of frames
+//!   re-simulated because of rollbacks, in total, and on average every time the network session
+//!   advances.
+//! - `jumpy_tick_duration_seconds`: a histogram of how long the network session takes to advance.
+
+use std::{
+    fmt::Write as _,
+    io::{Read, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    sync::Mutex,
+    time::Duration,
+};
+
+use super::{dedicated::DedicatedHost, moderation::Moderation, NetworkMatchSocket};
+use crate::prelude::*;
+
+/// Metrics exporter plugin.
+pub struct JumpyMetricsPlugin;
+
+impl Plugin for JumpyMetricsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(start_metrics_exporter.run_if(resource_added::<DedicatedHost>()))
+            .add_system(update_host_metrics.run_if(resource_exists::<HostMetrics>()));
+    }
+}
+
+/// The upper bounds of the buckets of the tick duration histogram, in seconds.
+const TICK_DURATION_BUCKETS: [f64; 8] = [0.001, 0.002, 0.004, 0.008, 0.016, 0.033, 0.066, 0.1];
+
+/// How long the exporter waits for the request of a connection.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The number of bytes of the requests read by the exporter, which only looks at the request line.
+const MAX_REQUEST_SIZE: usize = 512;
+
+/// The metrics of the dedicated host.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Metrics {
+    pub matches_served: u64,
+    pub active_peers: usize,
+    pub rollback_frames: u64,
+    /// The number of observations in each bucket of the tick duration histogram, the last one
+    /// being for the durations longer than every bucket.
+    pub tick_duration_buckets: [u64; TICK_DURATION_BUCKETS.len() + 1],
+    /// The sum of the observed tick durations, in seconds.
+    pub tick_duration_sum: f64,
+    pub ticks: u64,
+}
+
+impl Metrics {
+    /// Record the duration of a tick of the network session, and the number of frames it
+    /// re-simulated.
+    pub fn record_tick(&mut self, duration: Duration, rollback_frames: u32) {
+        let seconds = duration.as_secs_f64();
+        let bucket = TICK_DURATION_BUCKETS
+            .iter()
+            .position(|x| seconds <= *x)
+            .unwrap_or(TICK_DURATION_BUCKETS.len());
+        self.tick_duration_buckets[bucket] += 1;
+        self.tick_duration_sum += seconds;
+        self.ticks += 1;
+        self.rollback_frames += u64::from(rollback_frames);
+    }
+
+    /// Render the metrics in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: &dyn std::fmt::Display| {
+            writeln!(text, "# HELP {name} {help}").unwrap();
+            writeln!(text, "# TYPE {name} {kind}").unwrap();
+            writeln!(text, "{name} {value}").unwrap();
+        };
+        metric(
+            "jumpy_matches_served_total",
+            "counter",
+            "The number of matches started by the dedicated host.",
+            &self.matches_served,
+        );
+        metric(
+            "jumpy_active_peers",
+            "gauge",
+            "The number of players connected to the dedicated host.",
+            &self.active_peers,
+        );
+        metric(
+            "jumpy_rollback_frames_total",
+            "counter",
+            "The number of frames re-simulated because of rollbacks.",
+            &self.rollback_frames,
+        );
+        let average = if self.ticks > 0 {
+            self.rollback_frames as f64 / self.ticks as f64
+        } else {
+            0.0
+        };
+        metric(
+            "jumpy_rollback_frames_average",
+            "gauge",
+            "The average number of frames re-simulated every time the network session advances.",
+            &average,
+        );
+
+        let name = "jumpy_tick_duration_seconds";
+        writeln!(
+            text,
+            "# HELP {name} How long the network session takes to advance."
+        )
+        .unwrap();
+        writeln!(text, "# TYPE {name} histogram").unwrap();
+        let mut count = 0;
+        for (i, observations) in self.tick_duration_buckets.iter().enumerate() {
+            count += observations;
+            let le = TICK_DURATION_BUCKETS
+                .get(i)
+                .map_or("+Inf".to_string(), |x| x.to_string());
+            writeln!(text, "{name}_bucket{{le=\"{le}\"}} {count}").unwrap();
+        }
+        writeln!(text, "{name}_sum {}", self.tick_duration_sum).unwrap();
+        writeln!(text, "{name}_count {}", self.ticks).unwrap();
+        text
+    }
+}
+
+/// Resource containing the metrics of the dedicated host, shared with the thread serving them.
+///
+/// It is only inserted while the metrics are exported.
+#[derive(Resource, Clone, Default)]
+pub struct HostMetrics(pub Arc<Mutex<Metrics>>);
+
+/// Start serving the metrics when the dedicated host starts, if a metrics port was given.
+fn start_metrics_exporter(mut commands: Commands) {
+    let Some(port) = ENGINE_CONFIG.metrics_port else {
+        return;
+    };
+    let listener = match TcpListener::bind((Ipv4Addr::UNSPECIFIED, port)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Could not start the metrics exporter on port {port}: {e}");
+            return;
+        }
+    };
+
+    info!(%port, "Started the metrics exporter");
+    let metrics = HostMetrics::default();
+    let shared = metrics.0.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if let Err(e) = serve_metrics(stream, &shared) {
+                debug!("Metrics request failed: {e}");
+            }
+        }
+    });
+    commands.insert_resource(metrics);
+}
+
+/// Answer a request for the metrics.
+fn serve_metrics(mut stream: TcpStream, metrics: &Mutex<Metrics>) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut request = [0; MAX_REQUEST_SIZE];
+    let len = stream.read(&mut request)?;
+    let request = String::from_utf8_lossy(&request[..len]);
+
+    let response = if request.starts_with("GET /metrics ") {
+        let body = metrics.lock().unwrap().render();
+        format!(
+            "HTTP/1.1 200 OK\r\n\
+            Content-Type: text/plain; version=0.0.4\r\n\
+            Content-Length: {}\r\n\
+            Connection: close\r\n\r\n{body}",
+            body.len()
+        )
+    } else {
+        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+    };
+    stream.write_all(response.as_bytes())
+}
+
+/// Keep the metrics that aren't recorded by the network session up to date.
+fn update_host_metrics(
+    metrics: Res<HostMetrics>,
+    host: Option<Res<DedicatedHost>>,
+    socket: Option<Res<NetworkMatchSocket>>,
+    moderation: Res<Moderation>,
+) {
+    let mut metrics = metrics.0.lock().unwrap();
+    metrics.matches_served = host.map_or(0, |x| x.matches_served);
+    metrics.active_peers = socket.map_or(0, |socket| {
+        let player_is_local = socket.player_is_local();
+        (0..socket.player_count().min(MAX_PLAYERS))
+            .filter(|&i| !player_is_local[i] && !moderation.removed[i])
+            .count()
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn render_metrics() {
+        let mut metrics = Metrics {
+            matches_served: 3,
+            active_peers: 2,
+            ..default()
+        };
+        metrics.record_tick(Duration::from_micros(500), 0);
+        metrics.record_tick(Duration::from_millis(5), 2);
+        metrics.record_tick(Duration::from_millis(200), 4);
+
+        let text = metrics.render();
+        let lines = text.lines().collect::<Vec<_>>();
+        assert!(lines.contains(&"jumpy_matches_served_total 3"));
+        assert!(lines.contains(&"jumpy_active_peers 2"));
+        assert!(lines.contains(&"jumpy_rollback_frames_total 6"));
+        assert!(lines.contains(&"jumpy_rollback_frames_average 2"));
+        assert!(lines.contains(&"jumpy_tick_duration_seconds_bucket{le=\"0.001\"} 1"));
+        assert!(lines.contains(&"jumpy_tick_duration_seconds_bucket{le=\"0.004\"} 1"));
+        assert!(lines.contains(&"jumpy_tick_duration_seconds_bucket{le=\"0.008\"} 2"));
+        assert!(lines.contains(&"jumpy_tick_duration_seconds_bucket{le=\"0.1\"} 2"));
+        assert!(lines.contains(&"jumpy_tick_duration_seconds_bucket{le=\"+Inf\"} 3"));
+        assert!(lines.contains(&"jumpy_tick_duration_seconds_count 3"));
+    }
+}
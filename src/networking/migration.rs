@@ -0,0 +1,147 @@
+//! Connection migration when the local network changes during a network game.
+//!
+//! When the player switches networks in the middle of a match, for example from Wi-Fi to ethernet
+//! or between mobile networks, the local address of the [`NETWORK_ENDPOINT`] stops working. The
+//! [`NetworkPathMonitor`] notices the change by checking which local address the traffic to the
+//! other players goes out from, and the endpoint is then [rebound][super::rebind_endpoint] to a new
+//! UDP socket.
+//!
+//! QUIC migrates the connections that we opened to the new address on its own, which covers the
+//! connection to the matchmaker relay of online games. The peers of a LAN game that connected to us
+//! can't follow us though, so the [`NetworkSocket`][super::NetworkSocket] connects to them again
+//! with the session token of the match, and they replace the old connection with the new one.
+//!
+//! Meanwhile GGRS keeps predicting, and only reports the other players as disconnected after the
+//! [`NETWORK_DISCONNECT_TIMEOUT`][super::NETWORK_DISCONNECT_TIMEOUT], which leaves time for the
+//! connections to come back.
+
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::{Duration, Instant},
+};
+
+use super::{canonical_addr, NetworkMatchSocket, NETWORK_ENDPOINT};
+use crate::prelude::*;
+
+/// Connection migration plugin.
+pub struct JumpyMigrationPlugin;
+
+impl Plugin for JumpyMigrationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NetworkPathMonitor>()
+            .add_system(reset_path_monitor.run_if(resource_added::<NetworkMatchSocket>()))
+            .add_system(
+                monitor_network_path
+                    .after(reset_path_monitor)
+                    .run_if(resource_exists::<NetworkMatchSocket>()),
+            );
+    }
+}
+
+/// How often the local address used to reach the other players is checked.
+const PATH_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Resource keeping track of the local address used to reach the other players of the current
+/// network game.
+#[derive(Resource, Default, Debug)]
+pub struct NetworkPathMonitor {
+    /// The local address the traffic to the other players goes out from.
+    local_ip: Option<IpAddr>,
+    /// When the local address was last checked.
+    last_check: Option<Instant>,
+}
+
+impl NetworkPathMonitor {
+    /// Record the local address the traffic currently goes out from, returning whether it changed.
+    ///
+    /// `None` means that there is no route to the other players at the moment, such as while
+    /// switching networks, which isn't a change by itself.
+    pub fn update(&mut self, local_ip: Option<IpAddr>) -> bool {
+        let Some(local_ip) = local_ip else {
+            return false;
+        };
+        let changed = self.local_ip.map_or(false, |x| x != local_ip);
+        self.local_ip = Some(local_ip);
+        changed
+    }
+}
+
+/// Get the local address that the traffic to `remote` goes out from.
+///
+/// This doesn't send anything: connecting a UDP socket only picks the route to the address.
+pub fn route_local_ip(remote: SocketAddr) -> Option<IpAddr> {
+    let remote = canonical_addr(remote);
+    let unspecified = match remote {
+        SocketAddr::V4(_) => IpAddr::from(Ipv4Addr::UNSPECIFIED),
+        SocketAddr::V6(_) => IpAddr::from(Ipv6Addr::UNSPECIFIED),
+    };
+    let socket = std::net::UdpSocket::bind((unspecified, 0)).ok()?;
+    socket.connect(remote).ok()?;
+    socket.local_addr().ok().map(|x| x.ip())
+}
+
+/// Start tracking the local address again for every network game.
+fn reset_path_monitor(mut monitor: ResMut<NetworkPathMonitor>) {
+    *monitor = default();
+}
+
+/// Migrate the network connections when the local address used to reach the other players changes.
+fn monitor_network_path(socket: Res<NetworkMatchSocket>, mut monitor: ResMut<NetworkPathMonitor>) {
+    let now = Instant::now();
+    if monitor
+        .last_check
+        .map_or(false, |x| now.duration_since(x) < PATH_CHECK_INTERVAL)
+    {
+        return;
+    }
+    monitor.last_check = Some(now);
+
+    let Some(remote) = socket.remote_addr() else {
+        return;
+    };
+    let previous_ip = monitor.local_ip;
+    if !monitor.update(route_local_ip(remote)) {
+        return;
+    }
+
+    info!(
+        previous = ?previous_ip,
+        current = ?monitor.local_ip,
+        "Local network changed, migrating network connections"
+    );
+    if let Err(e) = super::rebind_endpoint() {
+        warn!(
+            addr = ?NETWORK_ENDPOINT.local_addr(),
+            "Could not rebind network endpoint: {e}"
+        );
+        // Try again on the next check
+        monitor.local_ip = previous_ip;
+        return;
+    }
+    socket.migrate();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn only_address_changes_are_reported() {
+        let wifi = IpAddr::from([192, 168, 1, 10]);
+        let ethernet = IpAddr::from([10, 0, 0, 5]);
+        let mut monitor = NetworkPathMonitor::default();
+
+        assert!(!monitor.update(Some(wifi)));
+        assert!(!monitor.update(Some(wifi)));
+        // Losing the route while switching networks isn't a change yet
+        assert!(!monitor.update(None));
+        assert!(monitor.update(Some(ethernet)));
+        assert!(!monitor.update(Some(ethernet)));
+    }
+
+    #[test]
+    fn loopback_route() {
+        let local_ip = route_local_ip(SocketAddr::from(([127, 0, 0, 1], 10000)));
+        assert_eq!(local_ip, Some(IpAddr::from([127, 0, 0, 1])));
+    }
+}
@@ -0,0 +1,393 @@
+//! Lobby moderation and votekicks for network games.
+//!
+//! Before the match starts, the host (player 0) may kick players out of the lobby from the player
+//! selection menu, optionally banning them so they can't join the host again. During a match, any
+//! player may start a vote to kick another player by pressing [`VOTEKICK_KEY`], and the player is
+//! kicked once a majority of the other players voted for it.
+//!
+//! Kicks are enforced by every peer: kicked players are disconnected from the GGRS session, which
+//! deactivates their input on the same frame for everybody, and the kicked player leaves the match.
+//! When [`MatchSettings::ai_fill_disconnected`] is set, an AI takes over their fish instead.
+//!
+//! Players quitting the match notify the others the same way, who may then choose to keep playing
+//! without them or to end the match too.
+
+use bevy_egui::EguiContexts;
+use bevy_fluent::Localization;
+
+use super::{
+    proto::{ModerationMessage, TaggedMessage},
+    NetworkMatchSocket, ReliableMessage, ReliableMessageHandlers, SocketTarget,
+};
+use crate::{main_menu::MenuPage, prelude::*};
+
+/// Moderation plugin.
+pub struct JumpyModerationPlugin;
+
+impl Plugin for JumpyModerationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Moderation>()
+            .add_system(reset_moderation.run_if(resource_added::<NetworkMatchSocket>()))
+            .add_system(leave_when_kicked.run_if(resource_exists::<NetworkMatchSocket>()))
+            .add_system(kicked_notice.run_if(in_state(EngineState::MainMenu)))
+            .add_system(
+                notify_leaving
+                    .in_schedule(OnExit(EngineState::InGame))
+                    .run_if(resource_exists::<NetworkMatchSocket>()),
+            )
+            .add_system(
+                handle_moderation_messages
+                    .in_set(ReliableMessageHandlers)
+                    .run_if(in_state(EngineState::InGame))
+                    .run_if(resource_exists::<NetworkMatchSocket>()),
+            )
+            .add_systems(
+                (apply_kicks, votekick_window, left_players_notice)
+                    .chain()
+                    .after(ReliableMessageHandlers)
+                    .before(leave_when_kicked)
+                    .distributive_run_if(in_state(EngineState::InGame))
+                    .distributive_run_if(resource_exists::<NetworkMatchSocket>())
+                    .distributive_run_if(resource_exists::<Session>()),
+            );
+    }
+}
+
+/// The key used to open the votekick window during a network match.
+pub const VOTEKICK_KEY: KeyCode = KeyCode::F6;
+
+/// Resource containing the moderation state of the current network game.
+#[derive(Resource, Default)]
+pub struct Moderation {
+    /// Whether each player has been kicked out of the game.
+    pub removed: [bool; MAX_PLAYERS],
+    /// For each player, the votes of the other players to kick them, if they voted.
+    pub votes: [[Option<bool>; MAX_PLAYERS]; MAX_PLAYERS],
+    /// Whether we have been kicked out of the game.
+    pub kicked: bool,
+    /// Whether the votekick window is open.
+    pub window_open: bool,
+    /// The players that have left the match, and that we haven't acknowledged yet.
+    pub left_players: Vec<usize>,
+}
+
+impl Moderation {
+    /// Kick a player out of the lobby. Only the host may do this.
+    pub fn kick(&mut self, socket: &NetworkMatchSocket, player_idx: usize, ban: bool) {
+        if socket.player_idx() != 0 {
+            return;
+        }
+
+        info!(%player_idx, %ban, "Kicking player out of the lobby");
+        socket.send_reliable(
+            SocketTarget::All,
+            &ModerationMessage::Kick { player_idx, ban }.encode(),
+        );
+        if ban {
+            socket.ban_player(player_idx);
+        }
+        self.removed[player_idx] = true;
+    }
+
+    /// Vote for or against kicking a player out of the match.
+    pub fn vote(&mut self, socket: &NetworkMatchSocket, target: usize, kick: bool) {
+        socket.send_reliable(
+            SocketTarget::All,
+            &ModerationMessage::VoteKick { target, kick }.encode(),
+        );
+        self.votes[target][socket.player_idx()] = Some(kick);
+        self.count_votes(socket, target);
+    }
+
+    /// Handle a reliable message received from the player `sender`.
+    ///
+    /// Returns `false` if the message isn't a moderation message, so that it can be handled by
+    /// something else.
+    pub fn handle_message(
+        &mut self,
+        socket: &NetworkMatchSocket,
+        sender: usize,
+        data: &[u8],
+    ) -> bool {
+        let Some(message) = ModerationMessage::decode(data) else {
+            return false;
+        };
+
+        // The player indexes come from the network, so a malformed message could name any player
+        let player_count = socket.player_count().min(MAX_PLAYERS);
+        let valid = sender < player_count
+            && match message {
+                ModerationMessage::Kick { player_idx, .. } => player_idx < player_count,
+                ModerationMessage::VoteKick { target, .. } => {
+                    target < player_count && target != sender
+                }
+                ModerationMessage::Leave => true,
+            };
+        if !valid {
+            warn!(%sender, "Ignoring moderation message with an invalid player index");
+            return true;
+        }
+
+        match message {
+            ModerationMessage::Kick { player_idx, .. } => {
+                if sender != 0 {
+                    warn!(%sender, "Ignoring kick from player that isn't the host");
+                    return true;
+                }
+                self.removed[player_idx] = true;
+                if player_idx == socket.player_idx() {
+                    self.kicked = true;
+                }
+            }
+            ModerationMessage::VoteKick { target, kick } => {
+                self.votes[target][sender] = Some(kick);
+                self.count_votes(socket, target);
+            }
+            ModerationMessage::Leave => {
+                info!(player_idx=%sender, "Player left the match");
+                self.removed[sender] = true;
+                self.left_players.push(sender);
+            }
+        }
+
+        true
+    }
+
+    /// Get the target of the vote in progress, if any.
+    pub fn current_vote(&self) -> Option<usize> {
+        (0..MAX_PLAYERS).find(|&i| !self.removed[i] && self.votes[i].iter().any(|x| x.is_some()))
+    }
+
+    /// Get the number of votes to kick the `target`, and the number of players that may vote.
+    pub fn vote_count(&self, socket: &NetworkMatchSocket, target: usize) -> (usize, usize) {
+        let voters = (0..socket.player_count()).filter(|&i| i != target && !self.removed[i]);
+        let votes = voters
+            .clone()
+            .filter(|&i| self.votes[target][i] == Some(true))
+            .count();
+
+        (votes, voters.count())
+    }
+
+    /// Kick the `target` if a majority of the players voted for it, or end the vote once everybody
+    /// has voted.
+    fn count_votes(&mut self, socket: &NetworkMatchSocket, target: usize) {
+        let (votes, voters) = self.vote_count(socket, target);
+        if self.removed[target] {
+            return;
+        }
+        if votes * 2 <= voters {
+            let voted = (0..socket.player_count())
+                .filter(|&i| i != target && !self.removed[i] && self.votes[target][i].is_some())
+                .count();
+            if voted == voters {
+                info!(player_idx=%target, "Vote to kick player failed");
+                self.votes[target] = default();
+            }
+            return;
+        }
+
+        info!(player_idx=%target, "Player was voted out of the match");
+        self.removed[target] = true;
+        if target == socket.player_idx() {
+            self.kicked = true;
+        }
+    }
+}
+
+/// Start with a clean slate whenever we connect to a new network game.
+fn reset_moderation(mut moderation: ResMut<Moderation>) {
+    *moderation = default();
+}
+
+/// Handle the moderation messages received during a match.
+///
+/// In the menus, the messages are handled by the player and map selection pages instead, because
+/// they receive all of the reliable messages.
+fn handle_moderation_messages(
+    mut messages: EventReader<ReliableMessage>,
+    socket: Res<NetworkMatchSocket>,
+    mut moderation: ResMut<Moderation>,
+) {
+    for message in messages.iter() {
+        moderation.handle_message(&socket, message.sender, &message.data);
+    }
+}
+
+/// Disconnect the kicked players from the match.
+fn apply_kicks(mut session: ResMut<Session>, moderation: Res<Moderation>) {
+    for (player_idx, _) in moderation.removed.iter().enumerate().filter(|(_, x)| **x) {
+        session.disconnect_player(player_idx);
+    }
+}
+
+/// Leave the game and go back to the main menu when we have been kicked.
+fn leave_when_kicked(
+    mut commands: Commands,
+    engine_state: Res<State<EngineState>>,
+    socket: Res<NetworkMatchSocket>,
+    moderation: Res<Moderation>,
+    mut session_manager: SessionManager,
+) {
+    if !moderation.kicked {
+        return;
+    }
+
+    socket.close();
+    commands.remove_resource::<NetworkMatchSocket>();
+    commands.insert_resource(MenuPage::Home);
+    if engine_state.0 == EngineState::InGame {
+        session_manager.stop();
+        commands.insert_resource(NextState(Some(EngineState::MainMenu)));
+    }
+}
+
+/// Let the other players know that we are leaving the match when we go back to the menu.
+fn notify_leaving(
+    mut commands: Commands,
+    socket: Res<NetworkMatchSocket>,
+    moderation: Res<Moderation>,
+) {
+    if moderation.kicked {
+        return;
+    }
+
+    info!("Leaving network match");
+    socket.send_reliable(SocketTarget::All, &ModerationMessage::Leave.encode());
+    socket.close();
+    commands.remove_resource::<NetworkMatchSocket>();
+}
+
+/// Let the player know that they have been kicked.
+fn kicked_notice(
+    mut contexts: EguiContexts,
+    mut moderation: ResMut<Moderation>,
+    localization: Res<Localization>,
+) {
+    if !moderation.kicked {
+        return;
+    }
+
+    egui::Window::new(localization.get("kicked"))
+        .id(egui::Id::new("kicked-notice"))
+        .collapsible(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(localization.get("kicked-description"));
+            if ui.button(localization.get("close")).clicked() {
+                moderation.kicked = false;
+            }
+        });
+}
+
+/// Show the votekick window, and the vote in progress.
+fn votekick_window(
+    mut contexts: EguiContexts,
+    keyboard: Res<Input<KeyCode>>,
+    socket: Res<NetworkMatchSocket>,
+    mut moderation: ResMut<Moderation>,
+    localization: Res<Localization>,
+) {
+    if keyboard.just_pressed(VOTEKICK_KEY) {
+        moderation.window_open = !moderation.window_open;
+    }
+
+    let local_player = socket.player_idx();
+
+    // Show the vote in progress to everybody but the player that may be kicked.
+    if let Some(target) = moderation.current_vote() {
+        if target != local_player {
+            let (votes, voters) = moderation.vote_count(&socket, target);
+            let voted = moderation.votes[target][local_player].is_some();
+
+            egui::Window::new(localization.get("votekick"))
+                .id(egui::Id::new("votekick-vote"))
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::RIGHT_TOP, egui::Vec2::ZERO)
+                .show(contexts.ctx_mut(), |ui| {
+                    ui.label(localization.get(&format!(
+                        "votekick-in-progress?player={}&votes={votes}&voters={voters}",
+                        target + 1
+                    )));
+
+                    if !voted {
+                        ui.horizontal(|ui| {
+                            if ui.button(localization.get("vote-yes")).clicked() {
+                                moderation.vote(&socket, target, true);
+                            }
+                            if ui.button(localization.get("vote-no")).clicked() {
+                                moderation.vote(&socket, target, false);
+                            }
+                        });
+                    }
+                });
+        }
+    }
+
+    if !moderation.window_open {
+        return;
+    }
+
+    let mut open = true;
+    egui::Window::new(localization.get("votekick"))
+        .id(egui::Id::new("votekick"))
+        .collapsible(false)
+        .resizable(false)
+        .open(&mut open)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(localization.get("votekick-description"));
+
+            let vote_in_progress = moderation.current_vote().is_some();
+            for player_idx in 0..socket.player_count() {
+                if player_idx == local_player || moderation.removed[player_idx] {
+                    continue;
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label(format!("{} {}", localization.get("player"), player_idx + 1));
+                    ui.add_enabled_ui(!vote_in_progress, |ui| {
+                        if ui.button(localization.get("kick")).clicked() {
+                            moderation.vote(&socket, player_idx, true);
+                        }
+                    });
+                });
+            }
+        });
+    moderation.window_open = open;
+}
+
+/// Let the player know that other players left the match, and let them choose whether to keep
+/// playing or to end the match.
+fn left_players_notice(
+    mut commands: Commands,
+    mut contexts: EguiContexts,
+    mut moderation: ResMut<Moderation>,
+    localization: Res<Localization>,
+) {
+    let Some(&player_idx) = moderation.left_players.first() else {
+        return;
+    };
+
+    egui::Window::new(localization.get("player-left"))
+        .id(egui::Id::new("player-left"))
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_TOP, egui::Vec2::ZERO)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(localization.get(&format!(
+                "player-left-description?player={}",
+                player_idx + 1
+            )));
+            ui.horizontal(|ui| {
+                if ui.button(localization.get("continue")).clicked() {
+                    moderation.left_players.remove(0);
+                }
+                if ui.button(localization.get("end-match")).clicked() {
+                    moderation.left_players.clear();
+                    commands.insert_resource(NextState(Some(EngineState::MainMenu)));
+                }
+            });
+        });
+}
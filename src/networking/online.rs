@@ -7,10 +7,16 @@ use bones_matchmaker_proto::{MatchInfo, MatchmakerRequest, MatchmakerResponse};
 use bytes::Bytes;
 use futures_lite::future;
 use quinn::Connection;
+use rand::Rng;
 
 use crate::prelude::*;
 
-use super::{NetworkSocket, NETWORK_ENDPOINT};
+use super::{
+    bandwidth::BandwidthLimiter,
+    connect_happy_eyeballs,
+    secure::{GgrsCipher, SessionKey},
+    NetworkSocket,
+};
 
 pub static ONLINE_MATCHMAKER: Lazy<OnlineMatchmaker> = Lazy::new(|| {
     let (client, server) = bi_channel();
@@ -20,12 +26,47 @@ pub static ONLINE_MATCHMAKER: Lazy<OnlineMatchmaker> = Lazy::new(|| {
     OnlineMatchmaker(client)
 });
 
+/// Resource used to share lobby codes with friends through a platform integration, such as Steam.
+///
+/// The matchmaker only matches players searching with the same lobby code, so sending somebody
+/// the lobby code is all that is needed to invite them to a match.
+#[derive(Resource, Default)]
+pub struct LobbyInvites {
+    /// Whether or not there is a platform integration available that can send invites.
+    pub available: bool,
+    /// The lobby code that the player is currently searching with, if any.
+    pub current_lobby: Option<String>,
+    /// The lobby code of an invite that the player accepted, waiting to be joined.
+    pub pending_join: Option<String>,
+    /// Set to request that the platform's invite dialog be opened.
+    pub open_invite_dialog: bool,
+}
+
+/// Generate a random, human friendly, lobby code.
+pub fn generate_lobby_code() -> String {
+    // Leave out characters that are easy to confuse with each other.
+    const CHARS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+    let mut rng = rand::thread_rng();
+    (0..6)
+        .map(|_| CHARS[rng.gen_range(0..CHARS.len())] as char)
+        .collect()
+}
+
 #[derive(DerefMut, Deref)]
 pub struct OnlineMatchmaker(BiChannelClient<OnlineMatchmakerRequest, OnlineMatchmakerResponse>);
 
 #[derive(Debug)]
 pub enum OnlineMatchmakerRequest {
-    SearchForGame { addr: String, player_count: usize },
+    SearchForGame {
+        addr: String,
+        player_count: usize,
+        /// Only players searching with the same match data will be matched together, see
+        /// [`lobby_match_data`].
+        match_data: Vec<u8>,
+        /// The maximum number of bytes per second sent through the relay during the match, if
+        /// any.
+        bandwidth_cap: Option<u32>,
+    },
     StopSearch,
 }
 
@@ -37,7 +78,21 @@ pub enum OnlineMatchmakerResponse {
         online_socket: OnlineSocket,
         player_idx: usize,
         player_count: usize,
+        /// The random seed shared by all the players of the match.
+        random_seed: u64,
     },
+    /// The search failed, because the connection to the matchmaker failed, or because the
+    /// matchmaker sent an unexpected response.
+    Error(String),
+}
+
+/// Get the match data used to search for a game with the given lobby code, or with anybody if
+/// there is no lobby code.
+pub fn lobby_match_data(lobby_code: Option<&str>) -> Vec<u8> {
+    match lobby_code {
+        Some(code) => format!("jumpy_lobby_{code}").into_bytes(),
+        None => b"jumpy_default_game".to_vec(),
+    }
 }
 
 async fn online_matchmaker(
@@ -45,135 +100,168 @@ async fn online_matchmaker(
 ) {
     while let Ok(message) = matchmaker_channel.recv().await {
         match message {
-            OnlineMatchmakerRequest::SearchForGame { addr, player_count } => {
-                info!("Connecting to online matchmaker");
-                let addr = resolve_addr_blocking(&addr).unwrap();
-                let conn = NETWORK_ENDPOINT
-                    .connect(addr, "matchmaker")
-                    .unwrap()
-                    .await
-                    .unwrap();
-                info!("Connected to online matchmaker");
-
-                matchmaker_channel
-                    .try_send(OnlineMatchmakerResponse::Searching)
-                    .unwrap();
-
-                // Send a match request to the server
-                let (mut send, mut recv) = conn.open_bi().await.unwrap();
-
-                let message = MatchmakerRequest::RequestMatch(MatchInfo {
+            OnlineMatchmakerRequest::SearchForGame {
+                addr,
+                player_count,
+                match_data,
+                bandwidth_cap,
+            } => {
+                let search = MatchInfo {
                     client_count: player_count.try_into().unwrap(),
-                    match_data: b"jumpy_default_game".to_vec(),
-                });
-                info!(request=?message, "Sending match request");
-                let message = postcard::to_allocvec(&message).unwrap();
-                send.write_all(&message).await.unwrap();
-                send.finish().await.unwrap();
-
-                let response = recv.read_to_end(256).await.unwrap();
-                let message: MatchmakerResponse = postcard::from_bytes(&response).unwrap();
-
-                if let MatchmakerResponse::Accepted = message {
-                    info!("Waiting for match...");
-                } else {
-                    panic!("Invalid response from matchmaker");
+                    match_data,
+                };
+                if let Err(e) =
+                    search_for_game(&matchmaker_channel, &addr, search, bandwidth_cap).await
+                {
+                    warn!("Online search failed: {e:?}");
+                    matchmaker_channel
+                        .try_send(OnlineMatchmakerResponse::Error(e.to_string()))
+                        .unwrap();
                 }
+            }
+            OnlineMatchmakerRequest::StopSearch => (), // Not searching, don't do anything
+        }
+    }
+}
 
-                loop {
-                    let recv_ui_message = matchmaker_channel.recv();
-                    let recv_online_matchmaker = conn.accept_uni();
+/// Search for a game on the matchmaker until one is found, or the search is stopped.
+async fn search_for_game(
+    matchmaker_channel: &BiChannelServer<OnlineMatchmakerRequest, OnlineMatchmakerResponse>,
+    addr: &str,
+    match_info: MatchInfo,
+    bandwidth_cap: Option<u32>,
+) -> anyhow::Result<()> {
+    info!("Connecting to online matchmaker");
+    let addrs = resolve_addrs_blocking(addr)?;
+    let conn = connect_happy_eyeballs(&addrs, "matchmaker")
+        .await
+        .context("Couldn't connect to the matchmaker")?;
+    info!("Connected to online matchmaker");
+
+    matchmaker_channel
+        .try_send(OnlineMatchmakerResponse::Searching)
+        .unwrap();
+
+    // Send a match request to the server
+    let (mut send, mut recv) = conn.open_bi().await?;
+
+    // Only the players searching with the same match data are matched together.
+    let match_data = match_info.match_data.clone();
+    let message = MatchmakerRequest::RequestMatch(match_info);
+    info!(request=?message, "Sending match request");
+    let message = postcard::to_allocvec(&message).unwrap();
+    send.write_all(&message).await?;
+    send.finish().await?;
+
+    let response = recv.read_to_end(256).await?;
+    let message: MatchmakerResponse = postcard::from_bytes(&response)
+        .map_err(|e| anyhow::format_err!("Invalid response from matchmaker: {e}"))?;
+
+    if let MatchmakerResponse::Accepted = message {
+        info!("Waiting for match...");
+    } else {
+        anyhow::bail!("Invalid response from matchmaker");
+    }
 
-                    let next_message = futures_lite::future::or(
-                        async move { either::Left(recv_ui_message.await) },
-                        async move { either::Right(recv_online_matchmaker.await) },
-                    )
-                    .await;
+    loop {
+        let recv_ui_message = matchmaker_channel.recv();
+        let recv_online_matchmaker = conn.accept_uni();
 
-                    match next_message {
-                        // UI message
-                        either::Either::Left(message) => {
-                            let message = message.unwrap();
+        let next_message = futures_lite::future::or(
+            async move { either::Left(recv_ui_message.await) },
+            async move { either::Right(recv_online_matchmaker.await) },
+        )
+        .await;
 
-                            match message {
-                                OnlineMatchmakerRequest::SearchForGame { .. } => {
-                                    panic!("Unexpected message from UI");
-                                }
-                                OnlineMatchmakerRequest::StopSearch => {
-                                    info!("Canceling online search");
-                                    break;
-                                }
-                            }
-                        }
+        match next_message {
+            // UI message
+            either::Either::Left(message) => {
+                let message = message.unwrap();
 
-                        // Matchmaker message
-                        either::Either::Right(recv) => {
-                            let mut recv = recv.unwrap();
-                            let message = recv.read_to_end(256).await.unwrap();
-                            let message: MatchmakerResponse =
-                                postcard::from_bytes(&message).unwrap();
-
-                            match message {
-                                MatchmakerResponse::ClientCount(count) => {
-                                    info!("Online match player count: {count}");
-                                    matchmaker_channel
-                                        .try_send(OnlineMatchmakerResponse::PlayerCount(count as _))
-                                        .unwrap();
-                                }
-                                MatchmakerResponse::Success {
-                                    random_seed,
-                                    player_idx,
-                                    client_count,
-                                } => {
-                                    info!(%random_seed, %player_idx, player_count=%client_count, "Online match complete");
-                                    let online_socket = OnlineSocket::new(
-                                        player_idx as usize,
-                                        client_count as usize,
-                                        conn,
-                                    );
-
-                                    matchmaker_channel
-                                        .try_send(OnlineMatchmakerResponse::GameStarting {
-                                            online_socket,
-                                            player_idx: player_idx as _,
-                                            player_count: client_count as _,
-                                        })
-                                        .unwrap();
-                                    break;
-                                }
-                                _ => panic!("Unexpected message from matchmaker"),
-                            }
-                        }
+                match message {
+                    OnlineMatchmakerRequest::SearchForGame { .. } => {
+                        panic!("Unexpected message from UI");
+                    }
+                    OnlineMatchmakerRequest::StopSearch => {
+                        info!("Canceling online search");
+                        return Ok(());
                     }
                 }
             }
-            OnlineMatchmakerRequest::StopSearch => (), // Not searching, don't do anything
+
+            // Matchmaker message
+            either::Either::Right(recv) => {
+                let mut recv = recv.context("Lost the connection to the matchmaker")?;
+                let message = recv.read_to_end(256).await?;
+                let message: MatchmakerResponse = postcard::from_bytes(&message)
+                    .map_err(|e| anyhow::format_err!("Invalid message from matchmaker: {e}"))?;
+
+                match message {
+                    MatchmakerResponse::ClientCount(count) => {
+                        info!("Online match player count: {count}");
+                        matchmaker_channel
+                            .try_send(OnlineMatchmakerResponse::PlayerCount(count as _))
+                            .unwrap();
+                    }
+                    MatchmakerResponse::Success {
+                        random_seed,
+                        player_idx,
+                        client_count,
+                    } => {
+                        info!(%random_seed, %player_idx, player_count=%client_count, "Online match complete");
+                        let session_key =
+                            SessionKey::derive(&random_seed.to_le_bytes(), &match_data);
+                        let online_socket = OnlineSocket::new(
+                            player_idx as usize,
+                            client_count as usize,
+                            conn,
+                            bandwidth_cap,
+                            session_key,
+                        );
+
+                        matchmaker_channel
+                            .try_send(OnlineMatchmakerResponse::GameStarting {
+                                online_socket,
+                                player_idx: player_idx as _,
+                                player_count: client_count as _,
+                                random_seed,
+                            })
+                            .unwrap();
+                        return Ok(());
+                    }
+                    _ => anyhow::bail!("Unexpected message from matchmaker"),
+                }
+            }
         }
     }
 }
 
-/// Resolve a server address.
+/// Resolve all of the addresses of a server, given as `host:port`, where the host may be a domain
+/// name, an IPv4 address, or an IPv6 address in square brackets.
 ///
 /// Note: This may block the thread
-fn resolve_addr_blocking(addr: &str) -> anyhow::Result<SocketAddr> {
+fn resolve_addrs_blocking(addr: &str) -> anyhow::Result<Vec<SocketAddr>> {
     let formatting_err =
         || anyhow::format_err!("Matchmaking server must be in the format `host:port`");
 
-    let mut iter = addr.split(':');
-    let host = iter.next().ok_or_else(formatting_err)?;
-    let port = iter.next().ok_or_else(formatting_err)?;
+    let (host, port) = addr.rsplit_once(':').ok_or_else(formatting_err)?;
     let port: u16 = port.parse().context("Couldn't parse port number")?;
-    if iter.next().is_some() {
-        return Err(formatting_err());
-    }
-
-    let addr = (host, port)
+    let host = match host.strip_prefix('[') {
+        Some(host) => host.strip_suffix(']').ok_or_else(formatting_err)?,
+        // Without brackets, a colon can only be part of an IPv6 address that is missing its port
+        None if host.contains(':') => return Err(formatting_err()),
+        None => host,
+    };
+
+    let addrs = (host, port)
         .to_socket_addrs()
         .context("Couldn't resolve matchmaker address")?
-        .find(|x| x.is_ipv4()) // For now, only support IpV4. I don't think IpV6 works right.
-        .ok_or_else(|| anyhow::format_err!("Couldn't resolve matchmaker address"))?;
+        .collect::<Vec<_>>();
+    if addrs.is_empty() {
+        anyhow::bail!("Couldn't resolve matchmaker address");
+    }
 
-    Ok(addr)
+    Ok(addrs)
 }
 
 #[derive(Debug, Clone)]
@@ -183,19 +271,32 @@ pub struct OnlineSocket {
     pub reliable_receiver: async_channel::Receiver<(usize, Vec<u8>)>,
     pub player_idx: usize,
     pub player_count: usize,
+    /// Keeps the traffic sent through the relay under its bandwidth cap.
+    pub bandwidth: Arc<std::sync::Mutex<BandwidthLimiter>>,
+    /// Seals and opens the GGRS messages exchanged with the other players.
+    cipher: GgrsCipher,
 }
 
 impl OnlineSocket {
-    pub fn new(player_idx: usize, player_count: usize, conn: Connection) -> Self {
+    pub fn new(
+        player_idx: usize,
+        player_count: usize,
+        conn: Connection,
+        bandwidth_cap: Option<u32>,
+        session_key: SessionKey,
+    ) -> Self {
         let (ggrs_sender, ggrs_receiver) = async_channel::unbounded();
         let (reliable_sender, reliable_receiver) = async_channel::unbounded();
+        let cipher = GgrsCipher::new(session_key, player_idx);
 
         let task_pool = IoTaskPool::get();
 
         let conn_ = conn.clone();
+        let cipher_ = cipher.clone();
         task_pool
             .spawn(async move {
                 let conn = conn_;
+                let cipher = cipher_;
                 loop {
                     let event = future::or(async { either::Left(conn.closed().await) }, async {
                         either::Right(conn.read_datagram().await)
@@ -212,10 +313,14 @@ impl OnlineSocket {
                                 let message: bones_matchmaker_proto::RecvProxyMessage =
                                     postcard::from_bytes(&data)
                                         .expect("Could not deserialize net message");
-                                let player = message.from_client;
-                                let message = postcard::from_bytes(&message.message).unwrap();
-
-                                if ggrs_sender.send((player as _, message)).await.is_err() {
+                                let player = message.from_client as usize;
+                                let Some(message) = cipher.open(player, &message.message) else {
+                                    debug!(%player, "Dropped unauthenticated network message");
+                                    continue;
+                                };
+                                let message = postcard::from_bytes(&message).unwrap();
+
+                                if ggrs_sender.send((player, message)).await.is_err() {
                                     break;
                                 }
                             }
@@ -273,8 +378,18 @@ impl OnlineSocket {
             reliable_receiver,
             player_idx,
             player_count,
+            bandwidth: Arc::new(std::sync::Mutex::new(BandwidthLimiter::new(bandwidth_cap))),
+            cipher,
         }
     }
+
+    /// Count a message about to be sent towards the bandwidth cap, returning whether it should be
+    /// sent.
+    fn allow_send(&self, bytes: usize, required: bool) -> bool {
+        self.bandwidth.lock().map_or(true, |mut x| {
+            x.allow(std::time::Instant::now(), bytes, required)
+        })
+    }
 }
 
 impl NetworkSocket for OnlineSocket {
@@ -294,15 +409,16 @@ impl NetworkSocket for OnlineSocket {
             target_client,
             message: message.into(),
         };
+        let message = postcard::to_allocvec(&message).unwrap();
+        // Reliable messages can't be dropped
+        self.allow_send(message.len(), true);
 
         let conn = self.conn.clone();
         task_pool
             .spawn(async move {
                 let mut send = conn.open_uni().await.unwrap();
 
-                send.write_all(&postcard::to_allocvec(&message).unwrap())
-                    .await
-                    .unwrap();
+                send.write_all(&message).await.unwrap();
                 send.finish().await.unwrap();
             })
             .detach();
@@ -331,15 +447,32 @@ impl NetworkSocket for OnlineSocket {
     fn player_count(&self) -> usize {
         self.player_count
     }
+
+    fn ban_player(&self, _player_idx: usize) {
+        // All of the connections go through the matchmaker, so there is no peer address to ban.
+    }
+
+    fn is_throttled(&self) -> bool {
+        self.bandwidth.lock().map_or(false, |x| x.is_throttled())
+    }
+
+    fn remote_addr(&self) -> Option<SocketAddr> {
+        // We are the client of the relay connection, so QUIC migrates it on its own.
+        Some(self.conn.remote_address())
+    }
 }
 
 impl ggrs::NonBlockingSocket<usize> for OnlineSocket {
     fn send_to(&mut self, msg: &ggrs::Message, addr: &usize) {
+        let msg_bytes = postcard::to_allocvec(msg).unwrap();
         let message = bones_matchmaker_proto::SendProxyMessage {
             target_client: bones_matchmaker_proto::TargetClient::One(*addr as u8),
-            message: postcard::to_allocvec(msg).unwrap(),
+            message: self.cipher.seal(*addr, &msg_bytes),
         };
         let msg_bytes = postcard::to_allocvec(&message).unwrap();
+        if !self.allow_send(msg_bytes.len(), false) {
+            return;
+        }
         self.conn
             .send_datagram(Bytes::copy_from_slice(&msg_bytes[..]))
             .ok();
@@ -353,3 +486,23 @@ impl ggrs::NonBlockingSocket<usize> for OnlineSocket {
         messages
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn server_addresses() {
+        assert_eq!(
+            resolve_addrs_blocking("127.0.0.1:8943").unwrap(),
+            [SocketAddr::from(([127, 0, 0, 1], 8943))]
+        );
+        assert_eq!(
+            resolve_addrs_blocking("[::1]:8943").unwrap(),
+            [SocketAddr::from((std::net::Ipv6Addr::LOCALHOST, 8943))]
+        );
+        assert!(resolve_addrs_blocking("::1").is_err());
+        assert!(resolve_addrs_blocking("127.0.0.1").is_err());
+        assert!(resolve_addrs_blocking("[::1:8943").is_err());
+    }
+}
@@ -0,0 +1,326 @@
+//! Pausing network games.
+//!
+//! Network games keep running while the pause menu is open, since the other players can't wait for
+//! us. Instead, any player may ask to pause the match for everybody by pressing [`PAUSE_KEY`], and
+//! the match is paused once every player still in the match voted for it. The host (player 0)
+//! doesn't need a vote and pauses the match right away.
+//!
+//! The votes are sent over the reliable channel, prefixed with [`MessageTag::Pause`]. While the
+//! match is paused, the [`GgrsSessionRunner`][super::GgrsSessionRunner] stops advancing frames but
+//! keeps polling the other players, so that nobody times out. Any player may resume the match, and
+//! it resumes on its own after [`MAX_PAUSE_DURATION`].
+
+use std::time::Duration;
+
+use bevy::utils::Instant;
+use bevy_egui::EguiContexts;
+use bevy_fluent::Localization;
+
+use super::{
+    moderation::Moderation,
+    proto::{MessageTag, TaggedMessage},
+    NetworkMatchSocket, ReliableMessage, ReliableMessageHandlers, SocketTarget,
+};
+use crate::{
+    prelude::*,
+    ui::widgets::{bordered_frame::BorderedFrame, EguiUiExt},
+};
+
+/// Network pause plugin.
+pub struct JumpyNetworkPausePlugin;
+
+impl Plugin for JumpyNetworkPausePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NetworkPause>()
+            .add_system(reset_network_pause.run_if(resource_added::<NetworkMatchSocket>()))
+            .add_system(
+                handle_pause_messages
+                    .in_set(ReliableMessageHandlers)
+                    .run_if(in_state(EngineState::InGame))
+                    .run_if(resource_exists::<NetworkMatchSocket>()),
+            )
+            .add_system(
+                pause_banner
+                    .after(ReliableMessageHandlers)
+                    .run_if(in_state(EngineState::InGame))
+                    .run_if(resource_exists::<NetworkMatchSocket>()),
+            );
+    }
+}
+
+/// The key used to ask the other players to pause the match, or to resume it.
+pub const PAUSE_KEY: KeyCode = KeyCode::F5;
+
+/// The longest time the match may stay paused, after which it resumes on its own.
+pub const MAX_PAUSE_DURATION: Duration = Duration::from_secs(60);
+
+/// Control messages used to pause network games, sent over the reliable channel.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseMessage {
+    /// Vote for or against pausing the match. The first vote starts the vote.
+    Vote {
+        /// Whether the vote is in favor of pausing the match.
+        pause: bool,
+    },
+    /// Resume the paused match.
+    Resume,
+}
+
+impl TaggedMessage for PauseMessage {
+    const TAG: MessageTag = MessageTag::Pause;
+}
+
+/// Resource containing the pause state of the current network match.
+#[derive(Resource, Default, Debug)]
+pub struct NetworkPause {
+    /// The votes of every player in the vote in progress, if they voted.
+    pub votes: [Option<bool>; MAX_PLAYERS],
+    /// When the match was paused, if it is paused.
+    paused_at: Option<Instant>,
+}
+
+impl NetworkPause {
+    /// Whether the match is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    /// Whether a vote to pause the match is in progress.
+    pub fn vote_in_progress(&self) -> bool {
+        self.votes.iter().any(|x| x.is_some())
+    }
+
+    /// The time left before the match resumes on its own, if it is paused.
+    pub fn remaining(&self, now: Instant) -> Option<Duration> {
+        self.paused_at
+            .map(|x| MAX_PAUSE_DURATION.saturating_sub(now.duration_since(x)))
+    }
+
+    /// Get the number of votes to pause the match, and the number of players that may vote.
+    pub fn vote_count(&self, voters: &[bool; MAX_PLAYERS]) -> (usize, usize) {
+        let votes = (0..MAX_PLAYERS)
+            .filter(|&i| voters[i] && self.votes[i] == Some(true))
+            .count();
+        (votes, voters.iter().filter(|x| **x).count())
+    }
+
+    /// Record the vote of a player, and pause the match if the host or every voter voted for it.
+    ///
+    /// A single vote against the pause ends the vote.
+    pub fn vote(
+        &mut self,
+        player_idx: usize,
+        pause: bool,
+        voters: &[bool; MAX_PLAYERS],
+        now: Instant,
+    ) {
+        if self.is_paused() || !voters[player_idx] {
+            return;
+        }
+        if !pause {
+            info!(%player_idx, "Vote to pause the match failed");
+            self.votes = default();
+            return;
+        }
+
+        self.votes[player_idx] = Some(true);
+        let (votes, voter_count) = self.vote_count(voters);
+        if player_idx == 0 || votes == voter_count {
+            info!(%player_idx, "Pausing the match");
+            self.votes = default();
+            self.paused_at = Some(now);
+        }
+    }
+
+    /// Resume the match.
+    pub fn resume(&mut self) {
+        if self.is_paused() {
+            info!("Resuming the match");
+        }
+        self.paused_at = None;
+    }
+
+    /// Resume the match if it has been paused for longer than [`MAX_PAUSE_DURATION`].
+    pub fn update(&mut self, now: Instant) {
+        if self.remaining(now) == Some(Duration::ZERO) {
+            self.resume();
+        }
+    }
+}
+
+/// Get the players that may vote to pause the match.
+fn voters(socket: &NetworkMatchSocket, moderation: &Moderation) -> [bool; MAX_PLAYERS] {
+    std::array::from_fn(|i| i < socket.player_count() && !moderation.removed[i])
+}
+
+/// Start every network match unpaused.
+fn reset_network_pause(mut pause: ResMut<NetworkPause>) {
+    *pause = default();
+}
+
+/// Handle the pause messages sent by the other players during a match.
+fn handle_pause_messages(
+    mut messages: EventReader<ReliableMessage>,
+    socket: Res<NetworkMatchSocket>,
+    moderation: Res<Moderation>,
+    mut pause: ResMut<NetworkPause>,
+) {
+    let now = Instant::now();
+    let voters = voters(&socket, &moderation);
+    for message in messages.iter() {
+        match PauseMessage::decode(&message.data) {
+            Some(PauseMessage::Vote { pause: vote }) => {
+                pause.vote(message.sender, vote, &voters, now)
+            }
+            Some(PauseMessage::Resume) => pause.resume(),
+            None => (),
+        }
+    }
+}
+
+/// Start or answer a pause vote, and show the countdown while the match is paused.
+fn pause_banner(
+    mut contexts: EguiContexts,
+    keyboard: Res<Input<KeyCode>>,
+    game: Res<GameMeta>,
+    socket: Res<NetworkMatchSocket>,
+    moderation: Res<Moderation>,
+    localization: Res<Localization>,
+    mut pause: ResMut<NetworkPause>,
+) {
+    let now = Instant::now();
+    let local_player = socket.player_idx();
+    let voters = voters(&socket, &moderation);
+    pause.update(now);
+
+    if keyboard.just_pressed(PAUSE_KEY) {
+        if pause.is_paused() {
+            socket.send_reliable(SocketTarget::All, &PauseMessage::Resume.encode());
+            pause.resume();
+        } else if pause.votes[local_player].is_none() {
+            let vote = PauseMessage::Vote { pause: true };
+            socket.send_reliable(SocketTarget::All, &vote.encode());
+            pause.vote(local_player, true, &voters, now);
+        }
+    }
+
+    let ctx = contexts.ctx_mut();
+    if let Some(remaining) = pause.remaining(now) {
+        let ui_theme = &game.ui_theme;
+        let heading_font = ui_theme
+            .font_styles
+            .heading
+            .colored(ui_theme.panel.font_color);
+        let font = ui_theme
+            .font_styles
+            .normal
+            .colored(ui_theme.panel.font_color);
+
+        egui::Area::new("network-pause-banner")
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 10.0))
+            .show(ctx, |ui| {
+                BorderedFrame::new(&ui_theme.panel.border)
+                    .padding(ui_theme.panel.padding.into())
+                    .show(ui, |ui| {
+                        ui.vertical_centered(|ui| {
+                            ui.themed_label(&heading_font, &localization.get("match-paused"));
+                            ui.themed_label(
+                                &font,
+                                &localization.get(&format!(
+                                    "match-paused-countdown?seconds={}&key={PAUSE_KEY:?}",
+                                    remaining.as_secs_f32().ceil() as u32
+                                )),
+                            );
+                        });
+                    });
+            });
+        return;
+    }
+
+    if !pause.vote_in_progress() {
+        return;
+    }
+    let (votes, voter_count) = pause.vote_count(&voters);
+    egui::Window::new(localization.get("pause-vote"))
+        .id(egui::Id::new("pause-vote"))
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 10.0))
+        .show(ctx, |ui| {
+            ui.label(localization.get(&format!(
+                "pause-vote-in-progress?votes={votes}&voters={voter_count}"
+            )));
+
+            if pause.votes[local_player].is_none() {
+                ui.horizontal(|ui| {
+                    for (label, vote) in [("vote-yes", true), ("vote-no", false)] {
+                        if ui.button(localization.get(label)).clicked() {
+                            let message = PauseMessage::Vote { pause: vote };
+                            socket.send_reliable(SocketTarget::All, &message.encode());
+                            pause.vote(local_player, vote, &voters, now);
+                        }
+                    }
+                });
+            }
+        });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const THREE_PLAYERS: [bool; MAX_PLAYERS] = [true, true, true, false];
+
+    #[test]
+    fn unanimous_vote_pauses() {
+        let now = Instant::now();
+        let mut pause = NetworkPause::default();
+
+        pause.vote(1, true, &THREE_PLAYERS, now);
+        assert!(pause.vote_in_progress());
+        assert_eq!(pause.vote_count(&THREE_PLAYERS), (1, 3));
+        // Players that aren't in the match can't vote
+        pause.vote(3, true, &THREE_PLAYERS, now);
+        assert_eq!(pause.vote_count(&THREE_PLAYERS), (1, 3));
+
+        pause.vote(2, true, &THREE_PLAYERS, now);
+        assert!(!pause.is_paused());
+        pause.vote(0, true, &THREE_PLAYERS, now);
+        assert!(pause.is_paused());
+        assert!(!pause.vote_in_progress());
+    }
+
+    #[test]
+    fn vote_against_ends_the_vote() {
+        let now = Instant::now();
+        let mut pause = NetworkPause::default();
+
+        pause.vote(1, true, &THREE_PLAYERS, now);
+        pause.vote(2, false, &THREE_PLAYERS, now);
+        assert!(!pause.vote_in_progress());
+        assert!(!pause.is_paused());
+    }
+
+    #[test]
+    fn host_pauses_without_vote() {
+        let now = Instant::now();
+        let mut pause = NetworkPause::default();
+
+        pause.vote(0, true, &THREE_PLAYERS, now);
+        assert!(pause.is_paused());
+        assert_eq!(pause.remaining(now), Some(MAX_PAUSE_DURATION));
+    }
+
+    #[test]
+    fn pause_resumes_on_its_own() {
+        let now = Instant::now();
+        let mut pause = NetworkPause::default();
+        pause.vote(0, true, &THREE_PLAYERS, now);
+
+        pause.update(now + MAX_PAUSE_DURATION / 2);
+        assert!(pause.is_paused());
+        pause.update(now + MAX_PAUSE_DURATION);
+        assert!(!pause.is_paused());
+        assert_eq!(pause.remaining(now), None);
+    }
+}
@@ -1,6 +1,7 @@
 //! Serializable data types for network messages used by the game.
 
 use numquant::{IntRange, Quantized};
+use serde::de::DeserializeOwned;
 
 use crate::prelude::*;
 
@@ -67,3 +68,139 @@ impl From<DenseMoveDirection> for u16 {
         x_bits | (y_bits << 6)
     }
 }
+
+/// The bytes that prefix the messages sent over the reliable channel next to the player and map
+/// selection messages, so that every kind of message can be told apart from the others.
+///
+/// Postcard encodes enum variants as varints, so the selection messages never start with bytes this
+/// high. Every kind of tagged message must have its own tag here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum MessageTag {
+    /// [`ModerationMessage`]s.
+    Moderation = 0xFE,
+    /// The packets of the voice chat.
+    Voice = 0xFD,
+    /// The ratings of the players of ranked matches.
+    Rating = 0xFC,
+    /// The messages of the text [`chat`][super::chat].
+    Chat = 0xFB,
+    /// [`PauseMessage`][super::pause::PauseMessage]s.
+    Pause = 0xFA,
+    /// [`DisconnectMessage`][super::disconnects::DisconnectMessage]s.
+    Disconnect = 0xF9,
+    /// [`ResumeMessage`][super::resume::ResumeMessage]s.
+    Resume = 0xF8,
+    /// The snapshot chunks sent to upgrade local matches into network matches.
+    Upgrade = 0xF7,
+    /// The messages of dedicated hosts.
+    DedicatedHost = 0xF6,
+}
+
+impl MessageTag {
+    /// Every tag.
+    pub const ALL: [MessageTag; 9] = [
+        MessageTag::Moderation,
+        MessageTag::Voice,
+        MessageTag::Rating,
+        MessageTag::Chat,
+        MessageTag::Pause,
+        MessageTag::Disconnect,
+        MessageTag::Resume,
+        MessageTag::Upgrade,
+        MessageTag::DedicatedHost,
+    ];
+
+    /// Prefix the payload of a message with the tag, so it can be sent over the reliable channel.
+    pub fn encode(self, payload: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(payload.len() + 1);
+        bytes.push(self as u8);
+        bytes.extend_from_slice(payload);
+        bytes
+    }
+
+    /// Get the payload of a message received over the reliable channel, returning `None` if it
+    /// doesn't start with the tag.
+    pub fn decode(self, bytes: &[u8]) -> Option<&[u8]> {
+        bytes
+            .split_first()
+            .filter(|(tag, _)| **tag == self as u8)
+            .map(|(_, payload)| payload)
+    }
+}
+
+/// A message sent over the reliable channel, encoded with postcard after its [`MessageTag`].
+pub trait TaggedMessage: Serialize + DeserializeOwned {
+    /// The tag that prefixes the message.
+    const TAG: MessageTag;
+
+    /// Encode the message so it can be sent over the reliable channel.
+    fn encode(&self) -> Vec<u8> {
+        Self::TAG.encode(&postcard::to_allocvec(self).unwrap())
+    }
+
+    /// Decode a message received over the reliable channel, returning `None` if it isn't a message
+    /// of this kind.
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        postcard::from_bytes(Self::TAG.decode(bytes)?).ok()
+    }
+}
+
+/// Control messages used to moderate network games, sent over the reliable channel.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum ModerationMessage {
+    /// Sent by the host to remove a player from the lobby before the match starts.
+    Kick {
+        /// The index of the player to remove.
+        player_idx: usize,
+        /// Whether or not the player should also be prevented from joining the host again.
+        ban: bool,
+    },
+    /// Vote for or against kicking a player out of the running match. The first vote for a player
+    /// starts the vote.
+    VoteKick {
+        /// The index of the player to kick.
+        target: usize,
+        /// Whether the vote is in favor of kicking the player.
+        kick: bool,
+    },
+    /// Sent by a player that is quitting the match, so that the other players don't have to wait
+    /// for them to time out.
+    Leave,
+}
+
+impl TaggedMessage for ModerationMessage {
+    const TAG: MessageTag = MessageTag::Moderation;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn message_tags_are_unique() {
+        let mut tags = MessageTag::ALL.map(|x| x as u8).to_vec();
+        tags.sort_unstable();
+        tags.dedup();
+        assert_eq!(tags.len(), MessageTag::ALL.len());
+
+        // The selection messages start with the varint of their variant, which is below 0x80.
+        assert!(tags.iter().all(|&x| x >= 0x80));
+    }
+
+    #[test]
+    fn tagged_message_encoding() {
+        let message = ModerationMessage::VoteKick {
+            target: 2,
+            kick: true,
+        };
+        let bytes = message.encode();
+        assert_eq!(bytes[0], MessageTag::Moderation as u8);
+        assert_eq!(ModerationMessage::decode(&bytes), Some(message));
+        assert_eq!(
+            ModerationMessage::decode(&[MessageTag::Pause as u8, 0]),
+            None
+        );
+        assert_eq!(ModerationMessage::decode(&[]), None);
+    }
+}
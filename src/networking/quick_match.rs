@@ -0,0 +1,271 @@
+//! Quick match queue for online games.
+//!
+//! Instead of searching with a lobby code, players may join the quick match queue with the region
+//! they want to play in and the number of players they want in the match. They are only matched
+//! with players searching in the same region, for the same number of players, and with a similar
+//! [`OnlineRating`][super::ranked::OnlineRating]. Like in the ranked queue, the longer the player
+//! waits, the further from their rating the brackets they search in get, with match data that only
+//! depends on the region and the bracket searched.
+//!
+//! Once enough compatible players are found, the match is joined automatically and the players go
+//! to the player selection like in any other online match.
+
+use std::time::Duration;
+
+use super::{
+    online::{OnlineMatchmakerRequest, OnlineMatchmakerResponse, ONLINE_MATCHMAKER},
+    ranked::{bracket_match_data, MAX_WIDENING, WIDENING_DELAY},
+    NetworkMatchSocket,
+};
+use crate::{main_menu::MenuPage, prelude::*};
+
+/// Quick match plugin.
+pub struct JumpyQuickMatchPlugin;
+
+impl Plugin for JumpyQuickMatchPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<QuickMatchQueue>().add_system(
+            update_quick_match_queue
+                .run_if(in_state(EngineState::MainMenu))
+                .run_if(|queue: Res<QuickMatchQueue>| queue.is_active()),
+        );
+    }
+}
+
+/// The regions that players may search for a quick match in.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MatchRegion {
+    #[default]
+    NorthAmerica,
+    SouthAmerica,
+    Europe,
+    Africa,
+    Asia,
+    Oceania,
+}
+
+impl MatchRegion {
+    /// The key the last region searched in is stored under in the [`Storage`].
+    pub const STORAGE_KEY: &str = "quick_match_region";
+
+    /// All of the regions, in the order they are shown in.
+    pub const ALL: [Self; 6] = [
+        Self::NorthAmerica,
+        Self::SouthAmerica,
+        Self::Europe,
+        Self::Africa,
+        Self::Asia,
+        Self::Oceania,
+    ];
+
+    /// The short code of the region, used in the match data and the localization keys.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NorthAmerica => "na",
+            Self::SouthAmerica => "sa",
+            Self::Europe => "eu",
+            Self::Africa => "af",
+            Self::Asia => "as",
+            Self::Oceania => "oc",
+        }
+    }
+
+    /// Get the region shown `offset` places after this one, wrapping around.
+    pub fn cycle(&self, offset: isize) -> Self {
+        let idx = Self::ALL.iter().position(|x| x == self).unwrap() as isize;
+        Self::ALL[(idx + offset).rem_euclid(Self::ALL.len() as isize) as usize]
+    }
+}
+
+/// Get the match data used to search the quick match queue in the given region with the given
+/// rating, after the search was widened the given number of times.
+///
+/// The player count doesn't need to be part of it, since the matchmaker only matches players
+/// searching for the same number of players.
+pub fn quick_match_data(region: MatchRegion, rating: f32, widening: u32) -> Vec<u8> {
+    bracket_match_data(&format!("jumpy_quick_{}", region.code()), rating, widening)
+}
+
+/// The status of the [`QuickMatchQueue`].
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuickMatchStatus {
+    /// Not in the queue.
+    #[default]
+    Idle,
+    /// Connecting to the matchmaker.
+    Connecting,
+    /// Waiting in the queue, with the given number of compatible players found.
+    Searching(usize),
+    /// The connection to the matchmaker failed.
+    Failed,
+}
+
+/// Resource containing the state of the quick match queue.
+#[derive(Resource, Default)]
+pub struct QuickMatchQueue {
+    pub status: QuickMatchStatus,
+    /// The region searched in.
+    pub region: MatchRegion,
+    /// The number of players of the match.
+    pub player_count: usize,
+    /// How long we've been in the queue.
+    pub elapsed: Duration,
+    /// The address of the matchmaking server.
+    addr: String,
+    /// The maximum number of bytes per second sent through the relay during the match, if any.
+    bandwidth_cap: Option<u32>,
+    /// The rating the queue was joined with.
+    rating: f32,
+    /// The number of times the search moved on to another rating bracket, since it last started
+    /// over from the bracket of the rating.
+    widening: u32,
+    /// How long we've been searching in the current rating bracket.
+    waited: Duration,
+}
+
+impl QuickMatchQueue {
+    /// Join the queue on the given matchmaking server.
+    pub fn join(
+        &mut self,
+        addr: String,
+        region: MatchRegion,
+        player_count: usize,
+        rating: f32,
+        bandwidth_cap: Option<u32>,
+    ) {
+        info!(?region, %player_count, %rating, "Joining the quick match queue");
+        *self = Self {
+            region,
+            player_count,
+            addr,
+            bandwidth_cap,
+            rating,
+            ..default()
+        };
+        self.search();
+    }
+
+    /// Leave the queue.
+    pub fn leave(&mut self) {
+        if self.is_active() {
+            info!("Leaving the quick match queue");
+            ONLINE_MATCHMAKER
+                .try_send(OnlineMatchmakerRequest::StopSearch)
+                .unwrap();
+        }
+        self.status = QuickMatchStatus::Idle;
+    }
+
+    /// Whether we are in the queue.
+    pub fn is_active(&self) -> bool {
+        !matches!(
+            self.status,
+            QuickMatchStatus::Idle | QuickMatchStatus::Failed
+        )
+    }
+
+    /// Send a search for the current rating bracket to the matchmaker.
+    fn search(&mut self) {
+        // Don't pick up the responses to a previous search
+        while ONLINE_MATCHMAKER.try_recv().is_ok() {}
+
+        self.status = QuickMatchStatus::Connecting;
+        self.waited = Duration::ZERO;
+        ONLINE_MATCHMAKER
+            .try_send(OnlineMatchmakerRequest::SearchForGame {
+                addr: self.addr.clone(),
+                player_count: self.player_count,
+                match_data: quick_match_data(self.region, self.rating, self.widening),
+                bandwidth_cap: self.bandwidth_cap,
+            })
+            .unwrap();
+    }
+}
+
+/// Get the dots animated after the searching message, given how long we've been searching.
+pub fn searching_dots(elapsed: Duration) -> &'static str {
+    match elapsed.as_millis() / 500 % 3 {
+        0 => ".",
+        1 => "..",
+        _ => "...",
+    }
+}
+
+/// Wait in the queue, widening the search when needed, and join the match once it is found.
+fn update_quick_match_queue(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut queue: ResMut<QuickMatchQueue>,
+) {
+    queue.elapsed += time.delta();
+    if let QuickMatchStatus::Searching(_) = queue.status {
+        queue.waited += time.delta();
+        if queue.waited >= WIDENING_DELAY {
+            queue.widening = (queue.widening + 1) % (MAX_WIDENING + 1);
+            info!(widening = queue.widening, "Widening the quick match search");
+            ONLINE_MATCHMAKER
+                .try_send(OnlineMatchmakerRequest::StopSearch)
+                .unwrap();
+            queue.search();
+        }
+    }
+
+    while let Ok(message) = ONLINE_MATCHMAKER.try_recv() {
+        match message {
+            OnlineMatchmakerResponse::Searching => {
+                queue.status = QuickMatchStatus::Searching(0);
+            }
+            OnlineMatchmakerResponse::PlayerCount(count) => {
+                queue.status = QuickMatchStatus::Searching(count);
+            }
+            OnlineMatchmakerResponse::Error(e) => {
+                warn!("Could not search for a quick match: {e}");
+                queue.status = QuickMatchStatus::Failed;
+            }
+            OnlineMatchmakerResponse::GameStarting {
+                online_socket,
+                player_idx,
+                ..
+            } => {
+                info!(?player_idx, "Joining quick match");
+                commands.insert_resource(NetworkMatchSocket(Box::new(online_socket)));
+                commands.insert_resource(MenuPage::PlayerSelect);
+                queue.status = QuickMatchStatus::Idle;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn only_compatible_players_are_matched() {
+        let eu = quick_match_data(MatchRegion::Europe, 1000.0, 0);
+        assert_eq!(eu, quick_match_data(MatchRegion::Europe, 1050.0, 0));
+        assert_ne!(eu, quick_match_data(MatchRegion::Asia, 1000.0, 0));
+        assert_ne!(eu, quick_match_data(MatchRegion::Europe, 1500.0, 0));
+
+        // Players who have waited meet the players who just joined the bracket they moved on to,
+        // in the same region only
+        let widened = quick_match_data(MatchRegion::Europe, 1050.0, 1);
+        assert_eq!(widened, quick_match_data(MatchRegion::Europe, 900.0, 0));
+        assert_ne!(widened, quick_match_data(MatchRegion::Oceania, 900.0, 0));
+    }
+
+    #[test]
+    fn regions_cycle() {
+        assert_eq!(MatchRegion::NorthAmerica.cycle(-1), MatchRegion::Oceania);
+        assert_eq!(MatchRegion::Oceania.cycle(1), MatchRegion::NorthAmerica);
+        assert_eq!(MatchRegion::Europe.cycle(1), MatchRegion::Africa);
+    }
+
+    #[test]
+    fn searching_dots_are_animated() {
+        assert_eq!(searching_dots(Duration::ZERO), ".");
+        assert_eq!(searching_dots(Duration::from_millis(700)), "..");
+        assert_eq!(searching_dots(Duration::from_millis(1200)), "...");
+        assert_eq!(searching_dots(Duration::from_millis(1500)), ".");
+    }
+}
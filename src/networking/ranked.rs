@@ -0,0 +1,566 @@
+//! Skill-based online matchmaking.
+//!
+//! Besides searching for a match with a lobby code, players may join the ranked queue, where they
+//! are matched with players of a similar [`OnlineRating`]. The rating is tracked locally in
+//! [`Storage`], and updated with the Elo rating system at the end of every ranked match, against
+//! the ratings that the players send each other when the match starts. Matches left before the end
+//! aren't rated.
+//!
+//! The matchmaker only matches players searching with the exact same match data, so the queue is
+//! split into rating brackets, see [`bracket_match_data`]. The longer the player waits, the further
+//! from their rating the brackets they search in get. The match data only depends on the bracket,
+//! so the players who have waited for a while meet the players who just joined that bracket. If
+//! the connection to the matchmaker fails before a match is found, the queue is joined again after
+//! [`RECONNECT_DELAY`], up to [`MAX_RECONNECT_ATTEMPTS`] times.
+//!
+//! Ranked matches skip player and map selection: every player picks the map and the fighters of
+//! the match from the random seed that the matchmaker sends to all of them, and the match starts as
+//! soon as it is found.
+
+use std::time::Duration;
+
+use jumpy_core::rounds::MatchScore;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use super::{
+    online::{OnlineMatchmakerRequest, OnlineMatchmakerResponse, ONLINE_MATCHMAKER},
+    proto::MessageTag,
+    GgrsSessionRunnerInfo, NetworkMatchSocket, NetworkSocket, ReliableMessage,
+    ReliableMessageHandlers, SocketTarget,
+};
+use crate::prelude::*;
+
+/// Ranked matchmaking plugin.
+pub struct JumpyRankedPlugin;
+
+impl Plugin for JumpyRankedPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RankedQueue>()
+            .add_event::<RankedMatchFinished>()
+            .add_system(
+                update_ranked_queue
+                    .run_if(in_state(EngineState::MainMenu))
+                    .run_if(|queue: Res<RankedQueue>| queue.is_active()),
+            )
+            .add_system(
+                send_rating
+                    .run_if(in_state(EngineState::InGame))
+                    .run_if(resource_exists::<NetworkMatchSocket>())
+                    .run_if(resource_exists::<RankedMatch>()),
+            )
+            .add_system(
+                handle_rating_messages
+                    .in_set(ReliableMessageHandlers)
+                    .run_if(in_state(EngineState::InGame))
+                    .run_if(resource_exists::<RankedMatch>()),
+            )
+            .add_system(
+                record_ranked_match
+                    .in_schedule(OnEnter(InGameState::Results))
+                    .run_if(resource_exists::<Session>())
+                    .run_if(resource_exists::<RankedMatch>()),
+            )
+            .add_system(end_ranked_match.in_schedule(OnExit(EngineState::InGame)));
+    }
+}
+
+/// The number of players in a ranked match.
+pub const RANKED_PLAYER_COUNT: usize = 2;
+
+/// The number of rounds a player must win to win a ranked match.
+pub const RANKED_ROUNDS_TO_WIN: u32 = 3;
+
+/// The rating of players that haven't played a ranked match yet.
+pub const DEFAULT_RATING: f32 = 1000.0;
+
+/// The largest change of rating after a single match.
+const RATING_K_FACTOR: f32 = 32.0;
+
+/// The width of the rating brackets the queue is split into.
+const BRACKET_WIDTH: f32 = 200.0;
+
+/// The number of times the search moves on to another rating bracket before starting over from the
+/// bracket of the player's rating.
+pub const MAX_WIDENING: u32 = 3;
+
+/// How long to search in a rating bracket before moving on to the next one.
+pub const WIDENING_DELAY: Duration = Duration::from_secs(30);
+
+/// The number of times the queue is joined again after the connection to the matchmaker fails.
+pub const MAX_RECONNECT_ATTEMPTS: u32 = 3;
+
+/// How long to wait before joining the queue again after the connection to the matchmaker fails.
+pub const RECONNECT_DELAY: Duration = Duration::from_secs(3);
+
+/// Event sent when a ranked match is over and has been rated.
+pub struct RankedMatchFinished {
+    /// The rating of the player after the match.
+    pub rating: OnlineRating,
+    /// Whether the player won the match.
+    pub won: bool,
+}
+
+/// The locally tracked rating of the player in ranked matches.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct OnlineRating {
+    /// The Elo rating of the player.
+    pub rating: f32,
+    /// The number of ranked matches that were rated.
+    pub matches_played: u32,
+}
+
+impl Default for OnlineRating {
+    fn default() -> Self {
+        Self {
+            rating: DEFAULT_RATING,
+            matches_played: 0,
+        }
+    }
+}
+
+impl OnlineRating {
+    /// The key the rating is stored under in the [`Storage`].
+    pub const STORAGE_KEY: &str = "online_rating";
+
+    /// The chance of winning against an opponent with the given rating, between `0.0` and `1.0`.
+    pub fn expected_score(&self, opponent_rating: f32) -> f32 {
+        1.0 / (1.0 + 10f32.powf((opponent_rating - self.rating) / 400.0))
+    }
+
+    /// Update the rating after a match against opponents with the given average rating.
+    pub fn record_match(&mut self, opponent_rating: f32, won: bool) {
+        let score = if won { 1.0 } else { 0.0 };
+        self.rating += RATING_K_FACTOR * (score - self.expected_score(opponent_rating));
+        self.matches_played += 1;
+    }
+}
+
+/// Get the match data used to search the ranked queue with the given rating, after the search was
+/// widened the given number of times.
+pub fn queue_match_data(rating: f32, widening: u32) -> Vec<u8> {
+    bracket_match_data("jumpy_ranked", rating, widening)
+}
+
+/// Get the match data used to search the given queue with the given rating, after the search was
+/// widened the given number of times.
+///
+/// It only depends on the [bracket searched][searched_bracket], and not on how long the player has
+/// waited, since the matchmaker only matches players searching with the exact same match data.
+pub fn bracket_match_data(queue: &str, rating: f32, widening: u32) -> Vec<u8> {
+    format!("{queue}_b{}", searched_bracket(rating, widening)).into_bytes()
+}
+
+/// Get the range of ratings searched with the given rating, after the search was widened the given
+/// number of times.
+pub fn rating_range(rating: f32, widening: u32) -> (f32, f32) {
+    let min = searched_bracket(rating, widening) as f32 * BRACKET_WIDTH;
+    (min, min + BRACKET_WIDTH)
+}
+
+/// Get the index of the rating bracket searched with the given rating, after the search was
+/// widened the given number of times.
+///
+/// The search starts in the bracket of the rating, and then alternates between the brackets on
+/// either side of it, starting with the side closest to the rating, and getting one bracket further
+/// every other time.
+pub fn searched_bracket(rating: f32, widening: u32) -> u32 {
+    let position = rating.max(0.0) / BRACKET_WIDTH;
+    let own = position as u32;
+    let distance = (widening + 1) / 2;
+    let closest_is_above = position.fract() >= 0.5;
+    let above = (widening % 2 == 1) == closest_is_above;
+    if above || distance > own {
+        own + distance
+    } else {
+        own - distance
+    }
+}
+
+/// The status of the [`RankedQueue`].
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RankedQueueStatus {
+    /// Not in the queue.
+    #[default]
+    Idle,
+    /// Connecting to the matchmaker.
+    Connecting,
+    /// Waiting in the queue, with the given number of players.
+    Searching(usize),
+    /// The connection to the matchmaker failed, and the queue will be joined again.
+    Reconnecting {
+        /// The number of the reconnection attempt, starting from `1`.
+        attempt: u32,
+        /// The time left before joining the queue again.
+        retry_in: Duration,
+    },
+    /// The connection to the matchmaker failed [`MAX_RECONNECT_ATTEMPTS`] times.
+    Failed,
+}
+
+/// Resource containing the state of the ranked queue.
+#[derive(Resource, Default)]
+pub struct RankedQueue {
+    pub status: RankedQueueStatus,
+    /// The address of the matchmaking server.
+    addr: String,
+    /// The maximum number of bytes per second sent through the relay during the match, if any.
+    bandwidth_cap: Option<u32>,
+    /// The rating the queue was joined with.
+    rating: f32,
+    /// The number of times the search moved on to another rating bracket, since it last started
+    /// over from the bracket of the rating.
+    widening: u32,
+    /// How long we've been searching in the current rating bracket.
+    waited: Duration,
+    /// The number of times the queue was joined again after the connection failed.
+    reconnect_attempts: u32,
+}
+
+impl RankedQueue {
+    /// Join the queue on the given matchmaking server with the given rating.
+    pub fn join(&mut self, addr: String, rating: f32, bandwidth_cap: Option<u32>) {
+        info!(%rating, "Joining the ranked queue");
+        *self = Self {
+            addr,
+            bandwidth_cap,
+            rating,
+            ..default()
+        };
+        self.search();
+    }
+
+    /// Leave the queue.
+    pub fn leave(&mut self) {
+        if self.is_active() {
+            info!("Leaving the ranked queue");
+            ONLINE_MATCHMAKER
+                .try_send(OnlineMatchmakerRequest::StopSearch)
+                .unwrap();
+        }
+        self.status = RankedQueueStatus::Idle;
+    }
+
+    /// Whether we are in the queue.
+    pub fn is_active(&self) -> bool {
+        !matches!(
+            self.status,
+            RankedQueueStatus::Idle | RankedQueueStatus::Failed
+        )
+    }
+
+    /// Get the range of ratings currently searched.
+    pub fn rating_range(&self) -> (f32, f32) {
+        rating_range(self.rating, self.widening)
+    }
+
+    /// Send a search for the current rating bracket to the matchmaker.
+    fn search(&mut self) {
+        // Don't pick up the responses to a previous search
+        while ONLINE_MATCHMAKER.try_recv().is_ok() {}
+
+        self.status = RankedQueueStatus::Connecting;
+        self.waited = Duration::ZERO;
+        ONLINE_MATCHMAKER
+            .try_send(OnlineMatchmakerRequest::SearchForGame {
+                addr: self.addr.clone(),
+                player_count: RANKED_PLAYER_COUNT,
+                match_data: queue_match_data(self.rating, self.widening),
+                bandwidth_cap: self.bandwidth_cap,
+            })
+            .unwrap();
+    }
+}
+
+/// Get the [`CoreSessionInfo`] of a ranked match from the random seed of the match, which is the
+/// same for all of its players.
+///
+/// Returns `None` if the game doesn't have any maps or players.
+pub fn ranked_session_info(
+    core: &Arc<CoreMeta>,
+    map_assets: &Assets<MapMeta>,
+    random_seed: u64,
+    player_count: usize,
+) -> Option<CoreSessionInfo> {
+    if core.stable_maps.is_empty() || core.players.is_empty() {
+        return None;
+    }
+    let mut rng = ChaCha8Rng::seed_from_u64(random_seed);
+
+    let map_handle = &core.stable_maps[rng.gen_range(0..core.stable_maps.len())];
+    let map_meta = map_assets.get(&map_handle.get_bevy_handle())?.clone();
+
+    let mut player_info = <[Option<GameSessionPlayerInfo>; MAX_PLAYERS]>::default();
+    for info in player_info.iter_mut().take(player_count) {
+        *info = Some(GameSessionPlayerInfo {
+            player: core.players[rng.gen_range(0..core.players.len())].clone(),
+            hat: None,
+            is_ai: false,
+            ai_difficulty: default(),
+        });
+    }
+
+    Some(CoreSessionInfo {
+        meta: core.clone(),
+        map_meta,
+        player_info,
+        match_settings: MatchSettings {
+            rounds_to_win: Some(RANKED_ROUNDS_TO_WIN),
+            seed: Some(random_seed),
+            ..default()
+        },
+    })
+}
+
+/// Resource present during a ranked match.
+#[derive(Resource)]
+pub struct RankedMatch {
+    /// The rating of each player of the match, once it was received.
+    pub ratings: [Option<f32>; MAX_PLAYERS],
+    /// The index of the local player.
+    pub player_idx: usize,
+    /// Whether our rating was sent to the other players.
+    rating_sent: bool,
+}
+
+impl RankedMatch {
+    fn new(player_idx: usize, rating: f32) -> Self {
+        let mut ratings = [None; MAX_PLAYERS];
+        ratings[player_idx] = Some(rating);
+        Self {
+            ratings,
+            player_idx,
+            rating_sent: false,
+        }
+    }
+
+    /// Get the average rating of the opponents, or `None` if none of their ratings were received.
+    pub fn opponent_rating(&self) -> Option<f32> {
+        let opponents = self
+            .ratings
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != self.player_idx)
+            .filter_map(|(_, rating)| *rating)
+            .collect::<Vec<_>>();
+        (!opponents.is_empty()).then(|| opponents.iter().sum::<f32>() / opponents.len() as f32)
+    }
+}
+
+/// Wait in the queue, widening the search and reconnecting when needed, and start the match once it
+/// is found.
+fn update_ranked_queue(
+    mut commands: Commands,
+    time: Res<Time>,
+    core: Res<CoreMetaArc>,
+    map_assets: Res<Assets<MapMeta>>,
+    mut queue: ResMut<RankedQueue>,
+    mut session_manager: SessionManager,
+) {
+    let status = queue.status;
+    match status {
+        RankedQueueStatus::Reconnecting { attempt, retry_in } => {
+            let retry_in = retry_in.saturating_sub(time.delta());
+            if retry_in.is_zero() {
+                info!(attempt, "Joining the ranked queue again");
+                queue.search();
+            } else {
+                queue.status = RankedQueueStatus::Reconnecting { attempt, retry_in };
+            }
+            return;
+        }
+        RankedQueueStatus::Searching(_) => {
+            queue.waited += time.delta();
+            if queue.waited >= WIDENING_DELAY {
+                queue.widening = (queue.widening + 1) % (MAX_WIDENING + 1);
+                info!(widening = queue.widening, "Widening the ranked search");
+                ONLINE_MATCHMAKER
+                    .try_send(OnlineMatchmakerRequest::StopSearch)
+                    .unwrap();
+                queue.search();
+            }
+        }
+        _ => (),
+    }
+
+    while let Ok(message) = ONLINE_MATCHMAKER.try_recv() {
+        match message {
+            OnlineMatchmakerResponse::Searching => {
+                queue.status = RankedQueueStatus::Searching(0);
+            }
+            OnlineMatchmakerResponse::PlayerCount(count) => {
+                queue.status = RankedQueueStatus::Searching(count);
+            }
+            OnlineMatchmakerResponse::Error(e) => {
+                warn!("Lost the connection to the ranked queue: {e}");
+                if queue.reconnect_attempts < MAX_RECONNECT_ATTEMPTS {
+                    queue.reconnect_attempts += 1;
+                    queue.status = RankedQueueStatus::Reconnecting {
+                        attempt: queue.reconnect_attempts,
+                        retry_in: RECONNECT_DELAY,
+                    };
+                } else {
+                    queue.status = RankedQueueStatus::Failed;
+                }
+            }
+            OnlineMatchmakerResponse::GameStarting {
+                online_socket,
+                player_idx,
+                player_count,
+                random_seed,
+            } => {
+                let Some(core_info) =
+                    ranked_session_info(&core.0, &map_assets, random_seed, player_count)
+                else {
+                    error!("Could not pick the map and fighters of the ranked match");
+                    online_socket.close();
+                    queue.status = RankedQueueStatus::Failed;
+                    continue;
+                };
+
+                info!(?player_idx, "Starting ranked match");
+                session_manager.start_network(
+                    core_info,
+                    GgrsSessionRunnerInfo {
+                        socket: online_socket.ggrs_socket(),
+                        player_is_local: online_socket.player_is_local(),
+                        player_addrs: online_socket.player_addrs(),
+                        player_count: online_socket.player_count(),
+                    },
+                );
+                commands.insert_resource(RankedMatch::new(player_idx, queue.rating));
+                commands.insert_resource(NetworkMatchSocket(Box::new(online_socket)));
+                queue.status = RankedQueueStatus::Idle;
+            }
+        }
+    }
+}
+
+/// Send our rating to the other players at the start of a ranked match.
+fn send_rating(socket: Res<NetworkMatchSocket>, mut ranked_match: ResMut<RankedMatch>) {
+    if ranked_match.rating_sent {
+        return;
+    }
+    let Some(rating) = ranked_match.ratings[ranked_match.player_idx] else {
+        return;
+    };
+
+    let message = MessageTag::Rating.encode(&rating.to_le_bytes());
+    socket.send_reliable(SocketTarget::All, &message);
+    ranked_match.rating_sent = true;
+}
+
+/// Handle the ratings sent by the other players.
+fn handle_rating_messages(
+    mut messages: EventReader<ReliableMessage>,
+    mut ranked_match: ResMut<RankedMatch>,
+) {
+    for message in messages.iter() {
+        let Some(rating) = MessageTag::Rating.decode(&message.data) else {
+            continue;
+        };
+        let Ok(rating) = <[u8; 4]>::try_from(rating).map(f32::from_le_bytes) else {
+            continue;
+        };
+        if rating.is_finite() && message.sender < MAX_PLAYERS {
+            ranked_match.ratings[message.sender] = Some(rating);
+        }
+    }
+}
+
+/// Update the stored rating when a ranked match is over.
+fn record_ranked_match(
+    mut commands: Commands,
+    mut session: ResMut<Session>,
+    mut storage: ResMut<Storage>,
+    ranked_match: Res<RankedMatch>,
+    mut finished_events: EventWriter<RankedMatchFinished>,
+) {
+    commands.remove_resource::<RankedMatch>();
+
+    let round_winners = session
+        .world()
+        .resource::<MatchScore>()
+        .borrow()
+        .round_winners;
+    let won = round_winners[ranked_match.player_idx];
+
+    let mut rating = storage
+        .get::<OnlineRating>(OnlineRating::STORAGE_KEY)
+        .unwrap_or_default();
+    // Without the ratings of the opponents, the match is rated as an even one
+    let opponent_rating = ranked_match.opponent_rating().unwrap_or(rating.rating);
+    rating.record_match(opponent_rating, won);
+    info!(rating = rating.rating, won, "Ranked match rated");
+
+    storage.set(OnlineRating::STORAGE_KEY, &rating);
+    storage.save();
+
+    finished_events.send(RankedMatchFinished { rating, won });
+}
+
+/// Forget about the ranked match when leaving it.
+fn end_ranked_match(mut commands: Commands) {
+    commands.remove_resource::<RankedMatch>();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rating_moves_by_surprise() {
+        let mut rating = OnlineRating::default();
+        assert_eq!(rating.expected_score(DEFAULT_RATING), 0.5);
+
+        rating.record_match(DEFAULT_RATING, true);
+        assert_eq!(rating.rating, DEFAULT_RATING + RATING_K_FACTOR / 2.0);
+        assert_eq!(rating.matches_played, 1);
+
+        // Beating a much weaker opponent is barely worth anything
+        let mut strong = OnlineRating {
+            rating: 2000.0,
+            matches_played: 0,
+        };
+        strong.record_match(1000.0, true);
+        assert!(strong.rating - 2000.0 < 1.0);
+        strong.record_match(1000.0, false);
+        assert!(strong.rating < 1970.0);
+    }
+
+    #[test]
+    fn search_moves_away_from_the_rating() {
+        // The closest bracket is searched first
+        assert_eq!(rating_range(1050.0, 0), (1000.0, 1200.0));
+        assert_eq!(rating_range(1050.0, 1), (800.0, 1000.0));
+        assert_eq!(rating_range(1050.0, 2), (1200.0, 1400.0));
+        assert_eq!(rating_range(1050.0, 3), (600.0, 800.0));
+        assert_eq!(rating_range(1150.0, 1), (1200.0, 1400.0));
+        assert_eq!(rating_range(1150.0, 2), (800.0, 1000.0));
+
+        // There are no brackets below zero
+        assert_eq!(rating_range(-20.0, 0), (0.0, 200.0));
+        assert_eq!(rating_range(50.0, 1), (200.0, 400.0));
+
+        assert_eq!(queue_match_data(1050.0, 0), b"jumpy_ranked_b5");
+        assert_eq!(queue_match_data(1150.0, 0), queue_match_data(1050.0, 0));
+        assert_ne!(queue_match_data(1250.0, 0), queue_match_data(1050.0, 0));
+    }
+
+    #[test]
+    fn waiting_players_meet_new_players() {
+        // A player who has waited searches with the same match data as the players who just
+        // joined the bracket they moved on to
+        assert_eq!(queue_match_data(1050.0, 1), queue_match_data(900.0, 0));
+        assert_eq!(queue_match_data(1050.0, 2), queue_match_data(1300.0, 0));
+    }
+
+    #[test]
+    fn opponent_rating_is_the_average() {
+        let mut ranked_match = RankedMatch::new(1, 1500.0);
+        assert_eq!(ranked_match.opponent_rating(), None);
+        ranked_match.ratings[0] = Some(1000.0);
+        ranked_match.ratings[2] = Some(1200.0);
+        assert_eq!(ranked_match.opponent_rating(), Some(1100.0));
+    }
+}
@@ -0,0 +1,457 @@
+//! Resuming network games after a transient network loss.
+//!
+//! GGRS reports a player as disconnected after
+//! [`NETWORK_DISCONNECT_TIMEOUT`][super::NETWORK_DISCONNECT_TIMEOUT] without hearing from them, and
+//! can't let them back into the session afterwards. Instead of moving on without them right away,
+//! the players freeze the match for [`RECONNECT_GRACE_PERIOD`] and try to
+//! [reconnect][super::NetworkSocket::reconnect] to each other. Until then, the QUIC connections
+//! usually survive on their own, so the relay of online games keeps the players in the match.
+//!
+//! While waiting, every player regularly sends the checksums of the last states of the match that
+//! it knows to be final, which are the states saved on the frames checked for desyncs. Once the
+//! leader, the player with the lowest index in the match, has heard from everybody, it picks the
+//! latest of these states that every player has with the same checksum, and every player restores
+//! it and starts a new GGRS session from there, so that the players that reconnected are back in
+//! the match and in sync.
+//!
+//! When the grace period is over, the players that are still missing are left to the
+//! [disconnect vote][super::disconnects].
+
+use std::{collections::VecDeque, time::Duration};
+
+use bevy::utils::Instant;
+use bevy_egui::EguiContexts;
+use bevy_fluent::Localization;
+
+use super::{
+    disconnects::DisconnectVote,
+    moderation::Moderation,
+    proto::{MessageTag, TaggedMessage},
+    GgrsSessionRunner, NetworkMatchSocket, ReliableMessage, ReliableMessageHandlers, SocketTarget,
+};
+use crate::{
+    prelude::*,
+    ui::widgets::{bordered_frame::BorderedFrame, EguiUiExt},
+};
+
+/// Match resume plugin.
+pub struct JumpyMatchResumePlugin;
+
+impl Plugin for JumpyMatchResumePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MatchResume>()
+            .add_system(reset_match_resume.run_if(resource_added::<NetworkMatchSocket>()))
+            .add_system(
+                handle_resume_messages
+                    .in_set(ReliableMessageHandlers)
+                    .run_if(in_state(EngineState::InGame))
+                    .run_if(resource_exists::<NetworkMatchSocket>())
+                    .run_if(resource_exists::<Session>()),
+            )
+            .add_systems(
+                (update_match_resume, reconnecting_banner)
+                    .chain()
+                    .after(ReliableMessageHandlers)
+                    .distributive_run_if(in_state(EngineState::InGame))
+                    .distributive_run_if(resource_exists::<NetworkMatchSocket>())
+                    .distributive_run_if(resource_exists::<Session>()),
+            );
+    }
+}
+
+/// How long the match waits for the missing players to reconnect, after which the other players
+/// vote on continuing without them.
+pub const RECONNECT_GRACE_PERIOD: Duration = Duration::from_secs(20);
+
+/// How often the connections are re-established and the players send that they are ready while
+/// waiting.
+pub const RECONNECT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The number of checked frames that the states are kept for to resume the match.
+///
+/// The players are frozen within the prediction window of each other when the connection is lost,
+/// so they only need a few states in common.
+const RESUME_HISTORY_LEN: usize = 8;
+
+/// Control messages used to resume network games, sent over the reliable channel.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum ResumeMessage {
+    /// Sent while waiting for the missing players, with the frame and checksum of the states that
+    /// the sender may resume the match from.
+    Ready { checksums: Vec<(i32, u128)> },
+    /// Sent by the leader once everybody is ready, to resume the match from the state of the frame.
+    Resume { frame: i32 },
+}
+
+impl TaggedMessage for ResumeMessage {
+    const TAG: MessageTag = MessageTag::Resume;
+}
+
+/// The states of the last frames checked for desyncs, that the match may be resumed from.
+#[derive(Default)]
+pub struct ResumeHistory {
+    states: VecDeque<(i32, u128, bones::World)>,
+    /// The last frame whose inputs were all received.
+    confirmed_frame: i32,
+}
+
+impl ResumeHistory {
+    /// Record the state of a frame, replacing the previous state of the frame if it was simulated
+    /// again after a rollback.
+    pub fn record(&mut self, frame: i32, checksum: u128, world: bones::World) {
+        if let Some(entry) = self.states.iter_mut().find(|x| x.0 == frame) {
+            *entry = (frame, checksum, world);
+            return;
+        }
+        if self.states.len() >= RESUME_HISTORY_LEN {
+            self.states.pop_front();
+        }
+        self.states.push_back((frame, checksum, world));
+    }
+
+    /// Record the last frame whose inputs were all received, after the rollbacks they caused were
+    /// simulated.
+    pub fn confirm(&mut self, confirmed_frame: i32) {
+        self.confirmed_frame = confirmed_frame;
+    }
+
+    /// Whether the state of a frame only depends on inputs that were all received, so that it
+    /// won't change anymore.
+    fn is_final(&self, frame: i32) -> bool {
+        frame <= self.confirmed_frame + 1
+    }
+
+    /// Get the frame and checksum of the final states.
+    pub fn checksums(&self) -> Vec<(i32, u128)> {
+        self.states
+            .iter()
+            .filter(|x| self.is_final(x.0))
+            .map(|x| (x.0, x.1))
+            .collect()
+    }
+
+    /// Get the state of a frame, if it is final and still recorded.
+    pub fn get(&self, frame: i32) -> Option<&bones::World> {
+        self.states
+            .iter()
+            .find(|x| x.0 == frame && self.is_final(frame))
+            .map(|x| &x.2)
+    }
+}
+
+/// Resource containing the state of the current network match while it waits for players to
+/// reconnect.
+#[derive(Resource, Default, Debug)]
+pub struct MatchResume {
+    /// The players whose connection was lost, and that we are waiting for.
+    pub missing: [bool; MAX_PLAYERS],
+    /// The checksums sent by each player that is ready to resume the match.
+    pub ready: [Option<Vec<(i32, u128)>>; MAX_PLAYERS],
+    /// When we started waiting, if we are waiting.
+    started_at: Option<Instant>,
+    /// When we last tried to reconnect.
+    last_attempt: Option<Instant>,
+}
+
+impl MatchResume {
+    /// Whether we are waiting for players to reconnect, during which the match is frozen.
+    pub fn in_progress(&self) -> bool {
+        self.started_at.is_some()
+    }
+
+    /// Start waiting for the `lost` players to reconnect, if we didn't start yet.
+    pub fn players_lost(&mut self, lost: [bool; MAX_PLAYERS], now: Instant) {
+        if !lost.contains(&true) {
+            return;
+        }
+        for (missing, lost) in self.missing.iter_mut().zip(lost) {
+            *missing |= lost;
+        }
+        self.started_at.get_or_insert(now);
+    }
+
+    /// The time left for the missing players to reconnect, if we are waiting.
+    pub fn remaining(&self, now: Instant) -> Option<Duration> {
+        self.started_at
+            .map(|x| RECONNECT_GRACE_PERIOD.saturating_sub(now.duration_since(x)))
+    }
+
+    /// Whether it is time to try to reconnect again, recording the attempt if it is.
+    pub fn should_attempt(&mut self, now: Instant) -> bool {
+        let due = self
+            .last_attempt
+            .map_or(true, |x| now.duration_since(x) >= RECONNECT_INTERVAL);
+        if self.in_progress() && due {
+            self.last_attempt = Some(now);
+            return true;
+        }
+        false
+    }
+
+    /// Get the frame that every one of the `players` may resume the match from, if they are all
+    /// ready and have a state in common.
+    pub fn resume_frame(&self, players: &[bool; MAX_PLAYERS]) -> Option<i32> {
+        let mut ready = (0..MAX_PLAYERS)
+            .filter(|&i| players[i])
+            .map(|i| self.ready[i].as_ref());
+        let first = ready.next()??;
+        let others = ready.collect::<Option<Vec<_>>>()?;
+
+        first
+            .iter()
+            .filter(|state| others.iter().all(|x| x.contains(state)))
+            .map(|(frame, _)| *frame)
+            .max()
+    }
+
+    /// Stop waiting, returning the players that were missing.
+    pub fn finish(&mut self) -> [bool; MAX_PLAYERS] {
+        let missing = self.missing;
+        *self = default();
+        missing
+    }
+}
+
+/// Get the players that take part in resuming the match.
+fn resuming_players(
+    socket: &NetworkMatchSocket,
+    moderation: &Moderation,
+    vote: &DisconnectVote,
+) -> [bool; MAX_PLAYERS] {
+    std::array::from_fn(|i| {
+        i < socket.player_count()
+            && !moderation.removed[i]
+            && !vote.dropped[i]
+            && !vote.ai_players[i]
+    })
+}
+
+/// Start every network match without waiting for anybody.
+fn reset_match_resume(mut resume: ResMut<MatchResume>) {
+    *resume = default();
+}
+
+/// Resume the match from the state of the given frame.
+fn resume_match(
+    session: &mut Session,
+    socket: &NetworkMatchSocket,
+    resume: &mut MatchResume,
+    players: [bool; MAX_PLAYERS],
+    frame: i32,
+) {
+    let Some(runner) = session.downcast_mut::<GgrsSessionRunner>() else {
+        return;
+    };
+    if runner.resume(frame, socket.ggrs_socket(), players) {
+        info!(%frame, "Resumed network match");
+        resume.finish();
+    } else {
+        warn!(%frame, "Could not resume network match, the state is not available");
+    }
+}
+
+/// Handle the resume messages sent by the other players during a match.
+fn handle_resume_messages(
+    mut messages: EventReader<ReliableMessage>,
+    socket: Res<NetworkMatchSocket>,
+    moderation: Res<Moderation>,
+    vote: Res<DisconnectVote>,
+    mut session: ResMut<Session>,
+    mut resume: ResMut<MatchResume>,
+) {
+    let players = resuming_players(&socket, &moderation, &vote);
+    for message in messages.iter() {
+        // The players keep sending that they are ready until the match resumes, so the messages
+        // received before we noticed the lost connection aren't needed.
+        if !resume.in_progress() {
+            continue;
+        }
+        match ResumeMessage::decode(&message.data) {
+            Some(ResumeMessage::Ready { checksums }) => {
+                resume.ready[message.sender] = Some(checksums);
+            }
+            Some(ResumeMessage::Resume { frame }) => {
+                resume_match(&mut session, &socket, &mut resume, players, frame);
+            }
+            None => (),
+        }
+    }
+}
+
+/// Try to reconnect while waiting, resume the match when we are the leader and everybody is
+/// ready, or leave the missing players to the disconnect vote when the time is up.
+fn update_match_resume(
+    socket: Res<NetworkMatchSocket>,
+    moderation: Res<Moderation>,
+    mut session: ResMut<Session>,
+    mut vote: ResMut<DisconnectVote>,
+    mut resume: ResMut<MatchResume>,
+) {
+    let now = Instant::now();
+    if resume.remaining(now) == Some(Duration::ZERO) {
+        warn!("Network players did not reconnect in time");
+        let missing = resume.finish();
+        vote.players_dropped(missing, now);
+        return;
+    }
+    if !resume.should_attempt(now) {
+        return;
+    }
+    let Some(runner) = session.downcast_mut::<GgrsSessionRunner>() else {
+        return;
+    };
+
+    socket.reconnect();
+    let checksums = runner.resume_history.checksums();
+    socket.send_reliable(
+        SocketTarget::All,
+        &ResumeMessage::Ready {
+            checksums: checksums.clone(),
+        }
+        .encode(),
+    );
+    let local_player = socket.player_idx();
+    resume.ready[local_player] = Some(checksums);
+
+    let players = resuming_players(&socket, &moderation, &vote);
+    if players.iter().position(|x| *x) != Some(local_player) {
+        return;
+    }
+    match resume.resume_frame(&players) {
+        Some(frame) => {
+            socket.send_reliable(SocketTarget::All, &ResumeMessage::Resume { frame }.encode());
+            resume_match(&mut session, &socket, &mut resume, players, frame);
+        }
+        None => debug!("Waiting for network players to be ready to resume"),
+    }
+}
+
+/// Show the players we are waiting for, and the time left for them to reconnect.
+fn reconnecting_banner(
+    mut contexts: EguiContexts,
+    game: Res<GameMeta>,
+    socket: Res<NetworkMatchSocket>,
+    moderation: Res<Moderation>,
+    vote: Res<DisconnectVote>,
+    localization: Res<Localization>,
+    resume: Res<MatchResume>,
+) {
+    let Some(remaining) = resume.remaining(Instant::now()) else {
+        return;
+    };
+    let seconds = remaining.as_secs_f32().ceil() as u32;
+    let local_player = socket.player_idx();
+    let players = resuming_players(&socket, &moderation, &vote);
+    // When we can't reach anybody, it is most likely our connection that was lost
+    let connection_lost = (0..MAX_PLAYERS)
+        .filter(|&i| players[i] && i != local_player)
+        .all(|i| resume.missing[i]);
+
+    let ui_theme = &game.ui_theme;
+    let heading_font = ui_theme
+        .font_styles
+        .heading
+        .colored(ui_theme.panel.font_color);
+    let font = ui_theme
+        .font_styles
+        .normal
+        .colored(ui_theme.panel.font_color);
+
+    egui::Area::new("network-reconnecting-banner")
+        .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 10.0))
+        .show(contexts.ctx_mut(), |ui| {
+            BorderedFrame::new(&ui_theme.panel.border)
+                .padding(ui_theme.panel.padding.into())
+                .show(ui, |ui| {
+                    ui.vertical_centered(|ui| {
+                        if connection_lost {
+                            ui.themed_label(&heading_font, &localization.get("connection-lost"));
+                            ui.themed_label(
+                                &font,
+                                &localization.get(&format!(
+                                    "connection-lost-reconnecting?seconds={seconds}"
+                                )),
+                            );
+                            return;
+                        }
+
+                        ui.themed_label(&heading_font, &localization.get("player-reconnecting"));
+                        for (player_idx, _) in resume.missing.iter().enumerate().filter(|x| *x.1) {
+                            ui.themed_label(
+                                &font,
+                                &localization.get(&format!(
+                                    "player-reconnecting-description?player={}&seconds={seconds}",
+                                    player_idx + 1
+                                )),
+                            );
+                        }
+                    });
+                });
+        });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn only_final_states_are_offered() {
+        let mut history = ResumeHistory::default();
+        history.record(10, 1, default());
+        history.record(20, 2, default());
+        history.confirm(15);
+        assert_eq!(history.checksums(), [(10, 1)]);
+        assert!(history.get(20).is_none());
+
+        // Simulated again after a rollback
+        history.record(20, 3, default());
+        history.confirm(19);
+        assert_eq!(history.checksums(), [(10, 1), (20, 3)]);
+        assert!(history.get(20).is_some());
+
+        for i in 3..=RESUME_HISTORY_LEN as i32 {
+            history.record(i * 10, i as u128, default());
+        }
+        assert!(history.get(10).is_none());
+    }
+
+    #[test]
+    fn latest_common_state_is_resumed() {
+        let players = [true, true, true, false];
+        let mut resume = MatchResume::default();
+        resume.ready[0] = Some(vec![(10, 1), (20, 2), (30, 3)]);
+        resume.ready[1] = Some(vec![(10, 1), (20, 2), (30, 4)]);
+        // Player 2 isn't ready yet
+        assert_eq!(resume.resume_frame(&players), None);
+
+        resume.ready[2] = Some(vec![(20, 2), (30, 3)]);
+        // The states of frame 30 differ, since player 1 saw player 2 disconnect before it
+        assert_eq!(resume.resume_frame(&players), Some(20));
+
+        resume.ready[2] = Some(vec![(30, 3)]);
+        assert_eq!(resume.resume_frame(&players), None);
+    }
+
+    #[test]
+    fn grace_period() {
+        let now = Instant::now();
+        let mut resume = MatchResume::default();
+        assert!(!resume.should_attempt(now));
+
+        resume.players_lost([false, true, false, false], now);
+        resume.players_lost([false, false, true, false], now + Duration::from_secs(1));
+        assert_eq!(resume.missing, [false, true, true, false]);
+        assert_eq!(resume.remaining(now), Some(RECONNECT_GRACE_PERIOD));
+
+        assert!(resume.should_attempt(now));
+        assert!(!resume.should_attempt(now + RECONNECT_INTERVAL / 2));
+        assert!(resume.should_attempt(now + RECONNECT_INTERVAL));
+
+        assert_eq!(
+            resume.remaining(now + RECONNECT_GRACE_PERIOD),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(resume.finish(), [false, true, true, false]);
+        assert!(!resume.in_progress());
+    }
+}
@@ -0,0 +1,266 @@
+//! Authenticated encryption of the GGRS messages of network games.
+//!
+//! The connections to the other players, or to the matchmaker relay, are QUIC connections, but the
+//! certificates of the peers are self-signed and never verified, see
+//! [`SkipServerVerification`][super::certs::SkipServerVerification]. To make sure that the inputs
+//! we simulate really come from the players of the match, every GGRS message is also sealed with
+//! ChaCha20-Poly1305 using a [`SessionKey`] that only the players of the match know:
+//!
+//! - In LAN games, the host generates a random key and sends it to the players with the rest of the
+//!   match info.
+//! - In online games, the key is derived from the random seed that the matchmaker sends to the
+//!   players of the match once it is found, and from the match data they searched with, such as
+//!   the lobby code.
+//!
+//! The nonce of every message is made of the index of the player sending it and of a counter, and
+//! the indices of the sender and the receiver are authenticated with it, so that messages can't be
+//! forged, passed off as coming from another player, sent to another player, or replayed. Messages
+//! that don't pass these checks are dropped before they reach GGRS.
+
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Mutex,
+};
+
+use ring::{aead, hkdf};
+
+use crate::prelude::*;
+
+/// The number of bytes added to every sealed message: the counter and the authentication tag.
+pub const SEAL_OVERHEAD: usize = 8 + 16;
+
+/// The number of counters before the highest one received that are still accepted, since
+/// datagrams may arrive out of order.
+const REPLAY_WINDOW_LEN: u64 = 64;
+
+/// The key the GGRS messages of a match are sealed with.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SessionKey(pub [u8; 32]);
+
+impl std::fmt::Debug for SessionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Don't leak the key into the logs
+        f.write_str("SessionKey(..)")
+    }
+}
+
+impl SessionKey {
+    /// Generate a new random key.
+    pub fn random() -> Self {
+        Self(rand::random())
+    }
+
+    /// Derive a key from a secret shared by the players, and the context it is used in.
+    pub fn derive(secret: &[u8], context: &[u8]) -> Self {
+        let mut key = [0; 32];
+        hkdf::Salt::new(hkdf::HKDF_SHA256, b"jumpy-ggrs-session-key")
+            .extract(secret)
+            .expand(&[context], hkdf::HKDF_SHA256)
+            .and_then(|x| x.fill(&mut key))
+            .expect("HKDF output is the size of a SHA-256 hash");
+        Self(key)
+    }
+}
+
+/// Keeps track of the counters of the messages received from a player, to drop the messages that
+/// are replayed.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ReplayWindow {
+    /// The highest counter received, `0` if none was received yet.
+    highest: u64,
+    /// Bit `n` is set when the counter `highest - n` was received.
+    received: u64,
+}
+
+impl ReplayWindow {
+    /// Record a counter, returning whether it wasn't received before and isn't too old.
+    pub fn accept(&mut self, counter: u64) -> bool {
+        if counter == 0 {
+            return false;
+        }
+        if counter > self.highest {
+            let shift = counter - self.highest;
+            self.received = if shift >= REPLAY_WINDOW_LEN {
+                1
+            } else {
+                (self.received << shift) | 1
+            };
+            self.highest = counter;
+            return true;
+        }
+
+        let age = self.highest - counter;
+        if age >= REPLAY_WINDOW_LEN || self.received & (1 << age) != 0 {
+            return false;
+        }
+        self.received |= 1 << age;
+        true
+    }
+}
+
+/// Seals the GGRS messages we send, and opens the ones we receive.
+///
+/// Clones share the same counters, so the socket can be cloned for GGRS.
+#[derive(Clone)]
+pub struct GgrsCipher {
+    key: SessionKey,
+    /// The index of the local player.
+    player_idx: usize,
+    /// The counter of the last message sent.
+    send_counter: Arc<AtomicU64>,
+    replay_windows: Arc<Mutex<[ReplayWindow; MAX_PLAYERS]>>,
+}
+
+impl std::fmt::Debug for GgrsCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GgrsCipher")
+            .field("player_idx", &self.player_idx)
+            .finish_non_exhaustive()
+    }
+}
+
+impl GgrsCipher {
+    pub fn new(key: SessionKey, player_idx: usize) -> Self {
+        Self {
+            key,
+            player_idx,
+            send_counter: default(),
+            replay_windows: default(),
+        }
+    }
+
+    /// Seal a message sent to the player `receiver`.
+    pub fn seal(&self, receiver: usize, message: &[u8]) -> Vec<u8> {
+        let counter = self.send_counter.fetch_add(1, Ordering::Relaxed) + 1;
+        let mut in_out = message.to_vec();
+        self.aead_key()
+            .seal_in_place_append_tag(
+                nonce(self.player_idx, counter),
+                aad(self.player_idx, receiver),
+                &mut in_out,
+            )
+            .expect("Message is too large to be sealed");
+
+        let mut packet = Vec::with_capacity(in_out.len() + 8);
+        packet.extend_from_slice(&counter.to_le_bytes());
+        packet.extend_from_slice(&in_out);
+        packet
+    }
+
+    /// Open a message received from the player `sender`, returning `None` if it wasn't sealed by
+    /// them with the key of the match for us, or if it was already received.
+    pub fn open(&self, sender: usize, packet: &[u8]) -> Option<Vec<u8>> {
+        if sender >= MAX_PLAYERS || packet.len() < SEAL_OVERHEAD {
+            return None;
+        }
+        let (counter, sealed) = packet.split_at(8);
+        let counter = u64::from_le_bytes(counter.try_into().unwrap());
+
+        let mut in_out = sealed.to_vec();
+        let len = self
+            .aead_key()
+            .open_in_place(
+                nonce(sender, counter),
+                aad(sender, self.player_idx),
+                &mut in_out,
+            )
+            .ok()?
+            .len();
+        // Only count the messages that were authenticated, so forged ones can't fill the window
+        if !self.replay_windows.lock().unwrap()[sender].accept(counter) {
+            return None;
+        }
+        in_out.truncate(len);
+        Some(in_out)
+    }
+
+    fn aead_key(&self) -> aead::LessSafeKey {
+        let key = aead::UnboundKey::new(&aead::CHACHA20_POLY1305, &self.key.0).unwrap();
+        aead::LessSafeKey::new(key)
+    }
+}
+
+/// Get the nonce of the message with the given counter sent by the player `sender`.
+///
+/// Every player has their own counter, so the sender is part of the nonce to keep it unique.
+fn nonce(sender: usize, counter: u64) -> aead::Nonce {
+    let mut nonce = [0; aead::NONCE_LEN];
+    nonce[0] = sender as u8;
+    nonce[4..].copy_from_slice(&counter.to_le_bytes());
+    aead::Nonce::assume_unique_for_key(nonce)
+}
+
+/// Get the additional data authenticated with a message from `sender` to `receiver`.
+fn aad(sender: usize, receiver: usize) -> aead::Aad<[u8; 2]> {
+    aead::Aad::from([sender as u8, receiver as u8])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn messages_are_authenticated() {
+        let key = SessionKey::random();
+        let player_0 = GgrsCipher::new(key, 0);
+        let player_1 = GgrsCipher::new(key, 1);
+        let player_2 = GgrsCipher::new(key, 2);
+
+        let packet = player_0.seal(1, b"inputs");
+        assert_eq!(packet.len(), 6 + SEAL_OVERHEAD);
+        assert_eq!(player_1.open(0, &packet).as_deref(), Some(&b"inputs"[..]));
+
+        // Replayed
+        assert_eq!(player_1.open(0, &packet), None);
+
+        let packet = player_0.seal(1, b"inputs");
+        // Passed off as coming from another player, or sent to another player
+        assert_eq!(player_1.open(2, &packet), None);
+        assert_eq!(player_2.open(0, &packet), None);
+
+        // Tampered with
+        let mut tampered = packet.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert_eq!(player_1.open(0, &tampered), None);
+
+        // Sealed with another key
+        let outsider = GgrsCipher::new(SessionKey::random(), 0);
+        assert_eq!(player_1.open(0, &outsider.seal(1, b"inputs")), None);
+
+        assert!(player_1.open(0, &packet).is_some());
+    }
+
+    #[test]
+    fn replay_window() {
+        let mut window = ReplayWindow::default();
+        assert!(!window.accept(0));
+        assert!(window.accept(1));
+        assert!(window.accept(3));
+        // Out of order
+        assert!(window.accept(2));
+        assert!(!window.accept(2));
+
+        assert!(window.accept(3 + REPLAY_WINDOW_LEN));
+        // Too old
+        assert!(!window.accept(3));
+        // The oldest counter still in the window
+        assert!(window.accept(4));
+    }
+
+    #[test]
+    fn derived_keys() {
+        let key = SessionKey::derive(&42u64.to_le_bytes(), b"jumpy_lobby_ABCDEF");
+        assert_eq!(
+            key,
+            SessionKey::derive(&42u64.to_le_bytes(), b"jumpy_lobby_ABCDEF")
+        );
+        assert_ne!(
+            key,
+            SessionKey::derive(&43u64.to_le_bytes(), b"jumpy_lobby_ABCDEF")
+        );
+        assert_ne!(
+            key,
+            SessionKey::derive(&42u64.to_le_bytes(), b"jumpy_default_game")
+        );
+    }
+}
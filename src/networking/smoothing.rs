@@ -0,0 +1,254 @@
+//! Visual smoothing of the rollback corrections of remote players.
+//!
+//! When the inputs of a remote player arrive and differ from the inputs that GGRS predicted, the
+//! [`GgrsSessionRunner`] rolls the match back and simulates it again, and the remote player snaps to
+//! where they really are. To hide the snap, the difference between the position that was shown
+//! and the corrected position is kept as a visual offset, which shrinks by [`SMOOTHING_DECAY`] every
+//! frame. Corrections larger than [`MAX_SMOOTHED_DISTANCE`], such as respawns, aren't smoothed.
+//!
+//! The offsets are only added to the transforms of the players, and of the entities following
+//! them, while the world is rendered. The simulated transforms are restored at the start of the
+//! next frame, before the match is simulated, so the simulation and its checksums never see them.
+
+use jumpy_core::{attachment::PlayerBodyAttachment, item::Inventory, player::PlayerIdx};
+
+use super::GgrsSessionRunner;
+use crate::prelude::*;
+
+/// Rollback smoothing plugin.
+pub struct JumpyRollbackSmoothingPlugin;
+
+impl Plugin for JumpyRollbackSmoothingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            apply_rollback_smoothing
+                .in_base_set(CoreSet::PostUpdate)
+                .run_if(in_state(EngineState::InGame))
+                .run_if(resource_exists::<Session>()),
+        )
+        .add_system(
+            restore_simulated_transforms
+                .in_base_set(CoreSet::First)
+                .run_if(resource_exists::<Session>()),
+        );
+    }
+}
+
+/// The fraction of the visual offset of a player that is left after each frame.
+pub const SMOOTHING_DECAY: f32 = 0.6;
+
+/// The largest correction that is smoothed, in pixels. Players snap to their position after
+/// larger corrections.
+pub const MAX_SMOOTHED_DISTANCE: f32 = 48.0;
+
+/// The offsets shorter than this, in pixels, are dropped.
+const MIN_OFFSET: f32 = 0.1;
+
+/// Keeps track of the visual offsets of the remote players after rollbacks.
+#[derive(Default, Debug)]
+pub struct RollbackSmoothing {
+    /// The offset of each player from their simulated position, where they are shown.
+    pub offsets: [Vec2; MAX_PLAYERS],
+    /// The positions of the players before the rollback in progress, if any.
+    predicted: Option<[Option<Vec2>; MAX_PLAYERS]>,
+    /// The entities moved by the offsets while the world is rendered, with their simulated
+    /// translation.
+    moved: Vec<(bones::Entity, Vec3)>,
+}
+
+impl RollbackSmoothing {
+    /// Record the positions of the players, as they are shown, before the match is rolled back.
+    pub fn rollback_started(&mut self, positions: [Option<Vec2>; MAX_PLAYERS]) {
+        self.predicted = Some(positions);
+    }
+
+    /// Record the positions of the players once the frame that was shown before the rollback was
+    /// simulated again, so that the remote players are still shown where they were.
+    pub fn rollback_finished(
+        &mut self,
+        positions: [Option<Vec2>; MAX_PLAYERS],
+        player_is_local: &[bool; MAX_PLAYERS],
+    ) {
+        let Some(predicted) = self.predicted.take() else {
+            return;
+        };
+        for player_idx in 0..MAX_PLAYERS {
+            let (Some(predicted), Some(corrected)) = (predicted[player_idx], positions[player_idx])
+            else {
+                continue;
+            };
+            if player_is_local[player_idx] {
+                continue;
+            }
+            let offset = self.offsets[player_idx] + predicted - corrected;
+            self.offsets[player_idx] = if offset.length() > MAX_SMOOTHED_DISTANCE {
+                Vec2::ZERO
+            } else {
+                offset
+            };
+        }
+    }
+
+    /// Shrink the offsets after a frame was simulated.
+    pub fn frame_advanced(&mut self) {
+        self.predicted = None;
+        for offset in &mut self.offsets {
+            *offset *= SMOOTHING_DECAY;
+            if offset.length() < MIN_OFFSET {
+                *offset = Vec2::ZERO;
+            }
+        }
+    }
+
+    /// Move the players, and the entities following them, by their offsets.
+    fn apply(&mut self, world: &mut bones::World) {
+        let offsets = self.offsets;
+        if offsets.iter().all(|x| *x == Vec2::ZERO) {
+            return;
+        }
+        self.moved = world
+            .run_initialized_system(
+                move |entities: bones::Res<bones::Entities>,
+                      player_indexes: bones::Comp<PlayerIdx>,
+                      body_attachments: bones::Comp<PlayerBodyAttachment>,
+                      inventories: bones::Comp<Inventory>,
+                      mut transforms: bones::CompMut<bones::Transform>| {
+                    let mut moved = Vec::new();
+                    for (player_ent, player_idx) in entities.iter_with(&player_indexes) {
+                        let offset = offsets[player_idx.0];
+                        if offset == Vec2::ZERO {
+                            continue;
+                        }
+
+                        let mut followers = vec![player_ent];
+                        followers.extend(
+                            entities
+                                .iter_with(&body_attachments)
+                                .filter(|(_, x)| x.player == player_ent)
+                                .map(|(ent, _)| ent),
+                        );
+                        followers.extend(inventories.get(player_ent).and_then(|x| x.0));
+                        for ent in followers {
+                            if let Some(transform) = transforms.get_mut(ent) {
+                                moved.push((ent, transform.translation));
+                                transform.translation += offset.extend(0.0);
+                            }
+                        }
+                    }
+                    Ok(moved)
+                },
+            )
+            .unwrap();
+    }
+
+    /// Put the entities moved by [`apply()`][Self::apply] back where they are simulated.
+    fn restore(&mut self, world: &mut bones::World) {
+        if self.moved.is_empty() {
+            return;
+        }
+        let moved = std::mem::take(&mut self.moved);
+        world
+            .run_initialized_system(move |mut transforms: bones::CompMut<bones::Transform>| {
+                // Restore the exact simulated values, so the simulation stays deterministic
+                for (ent, translation) in &moved {
+                    if let Some(transform) = transforms.get_mut(*ent) {
+                        transform.translation = *translation;
+                    }
+                }
+                Ok(())
+            })
+            .unwrap();
+    }
+}
+
+/// Get the simulated position of every player in the world.
+pub fn player_positions(world: &mut bones::World) -> [Option<Vec2>; MAX_PLAYERS] {
+    world
+        .run_initialized_system(
+            |entities: bones::Res<bones::Entities>,
+             player_indexes: bones::Comp<PlayerIdx>,
+             transforms: bones::Comp<bones::Transform>| {
+                let mut positions = [None; MAX_PLAYERS];
+                for (_, (player_idx, transform)) in
+                    entities.iter_with((&player_indexes, &transforms))
+                {
+                    positions[player_idx.0] = Some(transform.translation.truncate());
+                }
+                Ok(positions)
+            },
+        )
+        .unwrap()
+}
+
+/// Show the remote players of a network match where they were before the last rollbacks.
+fn apply_rollback_smoothing(mut session: ResMut<Session>) {
+    if let Some(runner) = session.downcast_mut::<GgrsSessionRunner>() {
+        runner.smoothing.apply(&mut runner.core.world);
+    }
+}
+
+/// Restore the simulated transforms of the players before the match is simulated.
+fn restore_simulated_transforms(mut session: ResMut<Session>) {
+    if let Some(runner) = session.downcast_mut::<GgrsSessionRunner>() {
+        runner.smoothing.restore(&mut runner.core.world);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const REMOTE_PLAYER_1: [bool; MAX_PLAYERS] = [true, false, false, false];
+
+    #[test]
+    fn corrections_are_smoothed() {
+        let mut smoothing = RollbackSmoothing::default();
+        smoothing.rollback_started([Some(Vec2::ZERO), Some(Vec2::new(10.0, 0.0)), None, None]);
+        smoothing.rollback_finished(
+            [Some(Vec2::ONE), Some(Vec2::new(20.0, 4.0)), None, None],
+            &REMOTE_PLAYER_1,
+        );
+        // The local player isn't smoothed
+        assert_eq!(smoothing.offsets[0], Vec2::ZERO);
+        assert_eq!(smoothing.offsets[1], Vec2::new(-10.0, -4.0));
+
+        smoothing.frame_advanced();
+        assert_eq!(
+            smoothing.offsets[1],
+            Vec2::new(-10.0, -4.0) * SMOOTHING_DECAY
+        );
+        for _ in 0..20 {
+            smoothing.frame_advanced();
+        }
+        assert_eq!(smoothing.offsets[1], Vec2::ZERO);
+    }
+
+    #[test]
+    fn large_corrections_snap() {
+        let mut smoothing = RollbackSmoothing::default();
+        smoothing.rollback_started([None, Some(Vec2::ZERO), None, None]);
+        smoothing.rollback_finished(
+            [
+                None,
+                Some(Vec2::new(MAX_SMOOTHED_DISTANCE * 2.0, 0.0)),
+                None,
+                None,
+            ],
+            &REMOTE_PLAYER_1,
+        );
+        assert_eq!(smoothing.offsets[1], Vec2::ZERO);
+    }
+
+    #[test]
+    fn corrections_without_rollback_are_ignored() {
+        let mut smoothing = RollbackSmoothing::default();
+        smoothing.rollback_finished([None, Some(Vec2::ONE), None, None], &REMOTE_PLAYER_1);
+        assert_eq!(smoothing.offsets[1], Vec2::ZERO);
+
+        // The rollback is over once the frame is simulated
+        smoothing.rollback_started([None, Some(Vec2::ZERO), None, None]);
+        smoothing.frame_advanced();
+        smoothing.rollback_finished([None, Some(Vec2::ONE), None, None], &REMOTE_PLAYER_1);
+        assert_eq!(smoothing.offsets[1], Vec2::ZERO);
+    }
+}
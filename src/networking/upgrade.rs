@@ -0,0 +1,855 @@
+//! Upgrading a local match into a network match when an online player joins it.
+//!
+//! Players in a local match may invite an online player from the pause menu, as long as there is a
+//! free player slot. The host searches the matchmaker with a new lobby code, and the online player,
+//! the guest, joins with it from the online tab of the main menu.
+//!
+//! Once they are connected, the host snapshots the match as the [replay][Replay] recorded by the
+//! [`LocalSessionRunner`] so far, and sends it to the guest in [`SnapshotChunk`]s over the
+//! reliable channel. The replay is much smaller than the world, and it is deterministic, so the
+//! guest simulates it again and checks that it reaches the same state as the host. Both of them
+//! then add the guest to the match in the same way, and continue it with a
+//! [`GgrsSessionRunner`] from that state, in which the host controls all of the other players.
+//!
+//! The matchmaker only knows about the two peers of the [`OnlineSocket`], so the
+//! [`UpgradeSocket`] maps the players of the match to them with a [`SlotMap`].
+
+use bevy::ecs::system::SystemState;
+use bevy_egui::EguiContexts;
+use bevy_fluent::Localization;
+use jumpy_core::{
+    input::{PlayerControl, PlayerInputs},
+    replay::Replay,
+};
+
+use super::{
+    online::{
+        generate_lobby_code, OnlineMatchmakerRequest, OnlineMatchmakerResponse, OnlineSocket,
+        ONLINE_MATCHMAKER,
+    },
+    proto::{MessageTag, TaggedMessage},
+    BoxedNonBlockingSocket, GgrsSessionRunner, GgrsSessionRunnerInfo, NetworkMatchSocket,
+    NetworkSocket, SocketTarget,
+};
+use crate::{
+    prelude::*,
+    ui::widgets::{bordered_frame::BorderedFrame, EguiUiExt},
+};
+
+/// Match upgrade plugin.
+pub struct JumpyMatchUpgradePlugin;
+
+impl Plugin for JumpyMatchUpgradePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MatchUpgrade>()
+            .add_system(
+                host_match_upgrade
+                    .run_if(in_state(EngineState::InGame))
+                    .run_if(|upgrade: Res<MatchUpgrade>| upgrade.is_inviting()),
+            )
+            .add_system(
+                invite_banner
+                    .run_if(in_state(EngineState::InGame))
+                    .run_if(|upgrade: Res<MatchUpgrade>| upgrade.is_inviting()),
+            )
+            .add_system(
+                join_match_upgrade
+                    .run_if(in_state(EngineState::MainMenu))
+                    .run_if(|upgrade: Res<MatchUpgrade>| upgrade.is_joining()),
+            )
+            .add_system(cancel_match_upgrade.in_schedule(OnExit(EngineState::InGame)));
+    }
+}
+
+/// The largest number of bytes of the snapshot sent in a single [`SnapshotChunk`], which keeps the
+/// reliable messages under the size they are read with.
+pub const SNAPSHOT_CHUNK_SIZE: usize = 3 * 1024;
+
+/// The largest snapshot that the guest accepts, in bytes.
+pub const MAX_SNAPSHOT_SIZE: usize = 32 * 1024 * 1024;
+
+/// The largest number of [`SnapshotChunk`]s that a snapshot may be split into.
+pub const MAX_SNAPSHOT_CHUNKS: usize =
+    (MAX_SNAPSHOT_SIZE + SNAPSHOT_CHUNK_SIZE - 1) / SNAPSHOT_CHUNK_SIZE;
+
+/// The largest number of frames in the replay of a snapshot, which is an hour of play.
+///
+/// Longer matches can't be upgraded, and keep the encoded snapshot under [`MAX_SNAPSHOT_SIZE`].
+pub const MAX_SNAPSHOT_FRAMES: usize = 60 * 60 * 60;
+
+/// Get the match data used to search for the other player of an upgrade with the given lobby code.
+pub fn upgrade_match_data(lobby_code: &str) -> Vec<u8> {
+    format!("jumpy_upgrade_{lobby_code}").into_bytes()
+}
+
+/// The status of the [`MatchUpgrade`].
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchUpgradeStatus {
+    /// Not upgrading a match.
+    #[default]
+    Idle,
+    /// Waiting for an online player to join the local match we are playing.
+    Inviting,
+    /// Searching for the host of the match we are joining.
+    Joining,
+    /// Receiving the snapshot of the match we are joining from its host.
+    Receiving,
+    /// The last upgrade failed.
+    Failed,
+}
+
+/// Resource containing the state of the upgrade of a local match into a network match, on the side
+/// of either the host or the guest.
+#[derive(Resource, Default)]
+pub struct MatchUpgrade {
+    pub status: MatchUpgradeStatus,
+    /// The lobby code the guest joins the match with.
+    pub lobby_code: String,
+    /// The connection to the host, while the guest receives the snapshot.
+    socket: Option<OnlineSocket>,
+    /// The chunks of the snapshot received so far.
+    chunks: SnapshotAssembler,
+}
+
+impl MatchUpgrade {
+    /// Invite an online player into the local match being played, with a new lobby code.
+    pub fn invite(&mut self, addr: String, bandwidth_cap: Option<u32>) {
+        self.cancel();
+        self.lobby_code = generate_lobby_code();
+        info!(lobby_code = %self.lobby_code, "Inviting an online player into the match");
+        self.status = MatchUpgradeStatus::Inviting;
+        self.search(addr, bandwidth_cap);
+    }
+
+    /// Join the match of a host who invited us with the given lobby code.
+    pub fn join(&mut self, addr: String, lobby_code: String, bandwidth_cap: Option<u32>) {
+        self.cancel();
+        info!(%lobby_code, "Joining a match in progress");
+        self.lobby_code = lobby_code;
+        self.status = MatchUpgradeStatus::Joining;
+        self.search(addr, bandwidth_cap);
+    }
+
+    /// Stop inviting or joining.
+    pub fn cancel(&mut self) {
+        if matches!(
+            self.status,
+            MatchUpgradeStatus::Inviting | MatchUpgradeStatus::Joining
+        ) {
+            ONLINE_MATCHMAKER
+                .try_send(OnlineMatchmakerRequest::StopSearch)
+                .unwrap();
+        }
+        if let Some(socket) = self.socket.take() {
+            socket.close();
+        }
+        self.chunks = default();
+        self.status = MatchUpgradeStatus::Idle;
+    }
+
+    /// Whether we are waiting for an online player to join our match.
+    pub fn is_inviting(&self) -> bool {
+        self.status == MatchUpgradeStatus::Inviting
+    }
+
+    /// Whether we are joining the match of a host.
+    pub fn is_joining(&self) -> bool {
+        matches!(
+            self.status,
+            MatchUpgradeStatus::Joining | MatchUpgradeStatus::Receiving
+        )
+    }
+
+    fn search(&mut self, addr: String, bandwidth_cap: Option<u32>) {
+        // Don't pick up the responses to a previous search
+        while ONLINE_MATCHMAKER.try_recv().is_ok() {}
+
+        ONLINE_MATCHMAKER
+            .try_send(OnlineMatchmakerRequest::SearchForGame {
+                addr,
+                player_count: 2,
+                match_data: upgrade_match_data(&self.lobby_code),
+                bandwidth_cap,
+            })
+            .unwrap();
+    }
+}
+
+/// Whether the match of the session may be upgraded into a network match: it must be a local
+/// match with a free player slot, whose replay was recorded from its start.
+pub fn can_upgrade(session: &mut Session) -> bool {
+    let Some(runner) = session.downcast_mut::<LocalSessionRunner>() else {
+        return false;
+    };
+    free_slot(&runner.core).is_some()
+        && runner
+            .recorder
+            .as_ref()
+            .map_or(false, |recorder| !recorder.interrupted)
+}
+
+/// Get, for every player slot, whether somebody plays in it.
+fn occupied_slots(core: &CoreSession) -> [bool; MAX_PLAYERS] {
+    let inputs = core.world.resource::<PlayerInputs>();
+    let inputs = inputs.borrow();
+    std::array::from_fn(|i| core.info.player_info[i].is_some() || inputs.players[i].active)
+}
+
+/// Get the first player slot that nobody plays in, if any.
+fn free_slot(core: &CoreSession) -> Option<usize> {
+    occupied_slots(core).iter().position(|x| !x)
+}
+
+/// Add the guest to the match in the given slot.
+///
+/// The host and the guest both do this to the same state, before the first frame of the network
+/// match, so they stay in sync.
+fn add_guest(core: &mut CoreSession, slot: usize, player: &GameSessionPlayerInfo) {
+    core.info.player_info[slot] = Some(player.clone());
+    core.update_input(|inputs| {
+        let input = &mut inputs.players[slot];
+        input.active = true;
+        input.is_ai = false;
+        input.selected_player = player.player.clone();
+        input.selected_hat = player.hat.clone();
+        input.control = default();
+    });
+}
+
+/// Maps the players of an upgraded match to the two peers of the [`OnlineSocket`].
+///
+/// The guest controls the player in the guest slot, and the host controls all of the others. Each
+/// peer is addressed by the index of the first player it controls, its primary slot, in the
+/// reliable messages and GGRS.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SlotMap {
+    /// The number of players of the match.
+    pub player_count: usize,
+    /// The player that the guest controls.
+    pub guest_slot: usize,
+    /// The index of the host in the online socket.
+    pub host_peer: usize,
+    /// Our index in the online socket.
+    pub local_peer: usize,
+}
+
+impl SlotMap {
+    /// Get the index in the online socket of the peer controlling the given player.
+    pub fn peer(&self, slot: usize) -> Option<usize> {
+        if slot >= self.player_count {
+            None
+        } else if slot == self.guest_slot {
+            Some(1 - self.host_peer)
+        } else {
+            Some(self.host_peer)
+        }
+    }
+
+    /// Get the first player controlled by the given peer.
+    pub fn primary_slot(&self, peer: usize) -> Option<usize> {
+        (0..self.player_count).find(|&slot| self.peer(slot) == Some(peer))
+    }
+
+    /// Return, for every player, whether we control them.
+    pub fn player_is_local(&self) -> [bool; MAX_PLAYERS] {
+        std::array::from_fn(|slot| self.peer(slot) == Some(self.local_peer))
+    }
+
+    /// Get, for every player, the primary slot of the peer controlling them.
+    pub fn player_addrs(&self) -> [usize; MAX_PLAYERS] {
+        std::array::from_fn(|slot| {
+            self.peer(slot)
+                .and_then(|peer| self.primary_slot(peer))
+                .unwrap_or(slot)
+        })
+    }
+}
+
+/// [`NetworkSocket`] of an upgraded match, translating between the players of the match and the
+/// peers of the [`OnlineSocket`] with a [`SlotMap`].
+pub struct UpgradeSocket {
+    socket: OnlineSocket,
+    slots: SlotMap,
+}
+
+impl UpgradeSocket {
+    pub fn new(socket: OnlineSocket, slots: SlotMap) -> Self {
+        Self { socket, slots }
+    }
+}
+
+impl NetworkSocket for UpgradeSocket {
+    fn ggrs_socket(&self) -> BoxedNonBlockingSocket {
+        BoxedNonBlockingSocket(Box::new(UpgradeGgrsSocket {
+            socket: self.socket.ggrs_socket(),
+            slots: self.slots,
+        }))
+    }
+
+    fn send_reliable(&self, target: SocketTarget, message: &[u8]) {
+        match target {
+            SocketTarget::Player(slot) => {
+                if let Some(peer) = self.slots.peer(slot) {
+                    self.socket
+                        .send_reliable(SocketTarget::Player(peer), message);
+                }
+            }
+            SocketTarget::All => self.socket.send_reliable(SocketTarget::All, message),
+        }
+    }
+
+    fn recv_reliable(&self) -> Vec<(usize, Vec<u8>)> {
+        self.socket
+            .recv_reliable()
+            .into_iter()
+            .filter_map(|(peer, message)| Some((self.slots.primary_slot(peer)?, message)))
+            .collect()
+    }
+
+    fn close(&self) {
+        self.socket.close();
+    }
+
+    fn player_idx(&self) -> usize {
+        self.slots.primary_slot(self.slots.local_peer).unwrap()
+    }
+
+    fn player_is_local(&self) -> [bool; MAX_PLAYERS] {
+        self.slots.player_is_local()
+    }
+
+    fn player_count(&self) -> usize {
+        self.slots.player_count
+    }
+
+    fn player_addrs(&self) -> [usize; MAX_PLAYERS] {
+        self.slots.player_addrs()
+    }
+
+    fn ban_player(&self, player_idx: usize) {
+        if let Some(peer) = self.slots.peer(player_idx) {
+            self.socket.ban_player(peer);
+        }
+    }
+
+    fn is_throttled(&self) -> bool {
+        self.socket.is_throttled()
+    }
+
+    fn remote_addr(&self) -> Option<std::net::SocketAddr> {
+        self.socket.remote_addr()
+    }
+
+    fn migrate(&self) {
+        self.socket.migrate();
+    }
+
+    fn reconnect(&self) {
+        self.socket.reconnect();
+    }
+}
+
+/// GGRS socket of an upgraded match, addressing the peers by their primary slot.
+struct UpgradeGgrsSocket {
+    socket: BoxedNonBlockingSocket,
+    slots: SlotMap,
+}
+
+impl ggrs::NonBlockingSocket<usize> for UpgradeGgrsSocket {
+    fn send_to(&mut self, msg: &ggrs::Message, addr: &usize) {
+        if let Some(peer) = self.slots.peer(*addr) {
+            self.socket.send_to(msg, &peer);
+        }
+    }
+
+    fn receive_all_messages(&mut self) -> Vec<(usize, ggrs::Message)> {
+        self.socket
+            .receive_all_messages()
+            .into_iter()
+            .filter_map(|(peer, msg)| Some((self.slots.primary_slot(peer)?, msg)))
+            .collect()
+    }
+}
+
+/// The inputs of a frame of a [`Replay`].
+type ReplayFrame = [Option<PlayerControl>; MAX_PLAYERS];
+
+/// The snapshot of the match that the host sends to the guest.
+#[derive(Serialize, Deserialize)]
+struct MatchSnapshot {
+    /// The [`SnapshotHeader`] as YAML, since the metadata in the replay is only meant to be
+    /// serialized to self-describing formats.
+    header: String,
+    /// The frames of the replay, with the number of times each of them is repeated in a row.
+    frames: Vec<(u32, ReplayFrame)>,
+}
+
+/// Everything in the [`MatchSnapshot`] but the frames of the replay.
+#[derive(Serialize, Deserialize)]
+struct SnapshotHeader {
+    /// The replay of the match so far, without its frames.
+    replay: Replay,
+    slots: SlotMap,
+    /// The player the guest plays as.
+    player: GameSessionPlayerInfo,
+}
+
+/// Run-length encode the frames of a replay, since the inputs rarely change from a frame to the
+/// next.
+fn encode_frames(frames: &[ReplayFrame]) -> Vec<(u32, ReplayFrame)> {
+    let mut encoded: Vec<(u32, ReplayFrame)> = Vec::new();
+    for frame in frames {
+        match encoded.last_mut() {
+            Some((count, last)) if last == frame => *count += 1,
+            _ => encoded.push((1, frame.clone())),
+        }
+    }
+    encoded
+}
+
+/// Decode frames encoded with [`encode_frames()`].
+///
+/// Returns `None` if there are more than [`MAX_SNAPSHOT_FRAMES`] frames.
+fn decode_frames(encoded: Vec<(u32, ReplayFrame)>) -> Option<Vec<ReplayFrame>> {
+    let frame_count = encoded
+        .iter()
+        .try_fold(0usize, |total, (count, _)| {
+            total.checked_add(*count as usize)
+        })
+        .filter(|x| *x <= MAX_SNAPSHOT_FRAMES)?;
+
+    let mut frames = Vec::with_capacity(frame_count);
+    for (count, frame) in encoded {
+        frames.extend(std::iter::repeat(frame).take(count as usize));
+    }
+    Some(frames)
+}
+
+/// A part of the snapshot sent by the host, since reliable messages are limited in size.
+///
+/// The chunks may be received in any order.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotChunk {
+    /// The index of the chunk.
+    pub index: u32,
+    /// The number of chunks of the snapshot.
+    pub count: u32,
+    pub data: Vec<u8>,
+}
+
+impl SnapshotChunk {
+    /// Split an encoded snapshot into chunks.
+    pub fn split(snapshot: &[u8]) -> Vec<Self> {
+        let count = snapshot.chunks(SNAPSHOT_CHUNK_SIZE).len() as u32;
+        snapshot
+            .chunks(SNAPSHOT_CHUNK_SIZE)
+            .enumerate()
+            .map(|(index, data)| Self {
+                index: index as u32,
+                count,
+                data: data.to_vec(),
+            })
+            .collect()
+    }
+}
+
+impl TaggedMessage for SnapshotChunk {
+    const TAG: MessageTag = MessageTag::Upgrade;
+}
+
+/// Puts the [`SnapshotChunk`]s received back together.
+#[derive(Default)]
+struct SnapshotAssembler {
+    chunks: Vec<Option<Vec<u8>>>,
+}
+
+impl SnapshotAssembler {
+    /// Add a chunk, returning the whole snapshot once every chunk was received.
+    ///
+    /// Chunks that can't be part of a valid snapshot are ignored: the chunks come from the network,
+    /// so their count and size are limited before anything is allocated for them.
+    fn add(&mut self, chunk: SnapshotChunk) -> Option<Vec<u8>> {
+        let count = chunk.count as usize;
+        if chunk.index >= chunk.count
+            || count > MAX_SNAPSHOT_CHUNKS
+            || chunk.data.len() > SNAPSHOT_CHUNK_SIZE
+        {
+            warn!(
+                index = chunk.index,
+                count, "Ignoring invalid snapshot chunk"
+            );
+            return None;
+        }
+        if self.chunks.is_empty() {
+            self.chunks = vec![None; count];
+        } else if self.chunks.len() != count {
+            warn!(
+                count,
+                expected = self.chunks.len(),
+                "Ignoring snapshot chunk with a different chunk count"
+            );
+            return None;
+        }
+        self.chunks[chunk.index as usize] = Some(chunk.data);
+        if self.chunks.iter().any(|x| x.is_none()) {
+            return None;
+        }
+        Some(
+            std::mem::take(&mut self.chunks)
+                .into_iter()
+                .flatten()
+                .flatten()
+                .collect(),
+        )
+    }
+}
+
+/// Upgrade our local match once the guest is connected.
+fn host_match_upgrade(world: &mut World) {
+    while let Ok(message) = ONLINE_MATCHMAKER.try_recv() {
+        match message {
+            OnlineMatchmakerResponse::Searching | OnlineMatchmakerResponse::PlayerCount(_) => (),
+            OnlineMatchmakerResponse::Error(e) => {
+                warn!("Could not search for an online player: {e}");
+                world.resource_mut::<MatchUpgrade>().status = MatchUpgradeStatus::Failed;
+            }
+            OnlineMatchmakerResponse::GameStarting { online_socket, .. } => {
+                let status = match upgrade_local_match(world, online_socket) {
+                    Ok(()) => MatchUpgradeStatus::Idle,
+                    Err(e) => {
+                        warn!("Could not upgrade the match: {e}");
+                        MatchUpgradeStatus::Failed
+                    }
+                };
+                world.resource_mut::<MatchUpgrade>().status = status;
+                return;
+            }
+        }
+    }
+}
+
+/// Replace the local session runner with a network one, and send the snapshot of the match to the
+/// guest.
+fn upgrade_local_match(world: &mut World, online_socket: OnlineSocket) -> anyhow::Result<()> {
+    let prepared = world.get_resource_mut::<Session>().and_then(|mut session| {
+        let runner = session.downcast_mut::<LocalSessionRunner>()?;
+        let slot = free_slot(&runner.core)?;
+        let replay = runner
+            .recorded_replay()
+            .filter(|x| x.frames.len() <= MAX_SNAPSHOT_FRAMES)?;
+        let players = &runner.core.info.meta.players;
+        let player = players.get(slot % players.len().max(1))?.clone();
+        Some((slot, replay, player))
+    });
+    let Some((guest_slot, mut replay, player)) = prepared else {
+        online_socket.close();
+        anyhow::bail!("The match can't take another player anymore");
+    };
+    let session = world.remove_resource::<Session>().unwrap();
+    let Ok(runner) = session.0.downcast::<LocalSessionRunner>() else {
+        unreachable!("The session runner was checked above");
+    };
+    let LocalSessionRunner {
+        mut core,
+        input_mapping,
+        ..
+    } = *runner;
+
+    let player = GameSessionPlayerInfo {
+        player,
+        hat: None,
+        is_ai: false,
+        ai_difficulty: default(),
+    };
+    add_guest(&mut core, guest_slot, &player);
+
+    let player_count = occupied_slots(&core)
+        .iter()
+        .rposition(|x| *x)
+        .map_or(0, |i| i + 1);
+    let local_peer = online_socket.player_idx();
+    let slots = SlotMap {
+        player_count,
+        guest_slot,
+        host_peer: local_peer,
+        local_peer,
+    };
+    let socket = UpgradeSocket::new(online_socket, slots);
+
+    let frames = std::mem::take(&mut replay.frames);
+    let header = SnapshotHeader {
+        replay,
+        slots,
+        player,
+    };
+    let snapshot = MatchSnapshot {
+        header: serde_yaml::to_string(&header)?,
+        frames: encode_frames(&frames),
+    };
+    let chunks = SnapshotChunk::split(&postcard::to_allocvec(&snapshot)?);
+    info!(
+        %guest_slot,
+        frames = frames.len(),
+        chunks = chunks.len(),
+        "Upgrading the match into a network match"
+    );
+    for chunk in &chunks {
+        socket.send_reliable(SocketTarget::Player(guest_slot), &chunk.encode());
+    }
+
+    let player_is_local = socket.player_is_local();
+    let mut runner = GgrsSessionRunner::new(
+        core,
+        GgrsSessionRunnerInfo {
+            socket: socket.ggrs_socket(),
+            player_is_local,
+            player_addrs: socket.player_addrs(),
+            player_count,
+        },
+    );
+    // The local input devices keep controlling the same players
+    runner.input_mapping = input_mapping.map(|x| x.filter(|&i| player_is_local[i]));
+    world.insert_resource(Session(Box::new(runner)));
+    world.insert_resource(NetworkMatchSocket(Box::new(socket)));
+    Ok(())
+}
+
+/// Connect to the host and receive the snapshot of their match, then join it.
+fn join_match_upgrade(world: &mut World) {
+    let mut upgrade = world.resource_mut::<MatchUpgrade>();
+    if upgrade.status == MatchUpgradeStatus::Joining {
+        while let Ok(message) = ONLINE_MATCHMAKER.try_recv() {
+            match message {
+                OnlineMatchmakerResponse::Searching | OnlineMatchmakerResponse::PlayerCount(_) => {}
+                OnlineMatchmakerResponse::Error(e) => {
+                    warn!("Could not search for the match: {e}");
+                    upgrade.status = MatchUpgradeStatus::Failed;
+                }
+                OnlineMatchmakerResponse::GameStarting { online_socket, .. } => {
+                    info!("Connected to the host, receiving the match");
+                    upgrade.socket = Some(online_socket);
+                    upgrade.status = MatchUpgradeStatus::Receiving;
+                }
+            }
+        }
+        return;
+    }
+
+    let Some(messages) = upgrade.socket.as_ref().map(|x| x.recv_reliable()) else {
+        return;
+    };
+    let mut snapshot = None;
+    for (_, message) in messages {
+        if let Some(chunk) = SnapshotChunk::decode(&message) {
+            snapshot = upgrade.chunks.add(chunk).or(snapshot);
+        }
+    }
+    let Some(snapshot) = snapshot else {
+        return;
+    };
+
+    let socket = upgrade.socket.take().unwrap();
+    upgrade.status = MatchUpgradeStatus::Idle;
+    if let Err(e) = join_upgraded_match(world, socket.clone(), &snapshot) {
+        warn!("Could not join the match: {e}");
+        socket.close();
+        world.resource_mut::<MatchUpgrade>().status = MatchUpgradeStatus::Failed;
+    }
+}
+
+/// Simulate the match of the snapshot again, and start playing it over the network.
+fn join_upgraded_match(
+    world: &mut World,
+    online_socket: OnlineSocket,
+    snapshot: &[u8],
+) -> anyhow::Result<()> {
+    let snapshot: MatchSnapshot = postcard::from_bytes(snapshot)?;
+    let SnapshotHeader {
+        mut replay,
+        slots,
+        player,
+    } = serde_yaml::from_str(&snapshot.header)?;
+    if slots.player_count > MAX_PLAYERS || slots.guest_slot >= slots.player_count {
+        anyhow::bail!("Invalid player slots: {slots:?}");
+    }
+    replay.frames = decode_frames(snapshot.frames)
+        .ok_or_else(|| anyhow::format_err!("The match is too long"))?;
+
+    info!(frames = replay.frames.len(), "Simulating the match again");
+    let core_meta = world.resource::<CoreMetaArc>().0.clone();
+    let mut core = CoreSession::new(replay.session_info(core_meta));
+    let checksum = replay.play(&mut core, world);
+    if checksum != replay.checksum {
+        anyhow::bail!("The match is in a different state than for the host");
+    }
+    // The sounds of the simulated frames shouldn't be played
+    core.world
+        .run_initialized_system(|mut audio_events: bones::ResMut<bones::AudioEvents>| {
+            audio_events.queue.clear();
+            Ok(())
+        })
+        .unwrap();
+    add_guest(&mut core, slots.guest_slot, &player);
+
+    let slots = SlotMap {
+        local_peer: online_socket.player_idx(),
+        ..slots
+    };
+    let socket = UpgradeSocket::new(online_socket, slots);
+    let runner = GgrsSessionRunner::new(
+        core,
+        GgrsSessionRunnerInfo {
+            socket: socket.ggrs_socket(),
+            player_is_local: socket.player_is_local(),
+            player_addrs: socket.player_addrs(),
+            player_count: slots.player_count,
+        },
+    );
+
+    info!(guest_slot = %slots.guest_slot, "Joining the match");
+    let mut session_manager = SystemState::<SessionManager>::new(world);
+    session_manager.get_mut(world).start_network_runner(runner);
+    session_manager.apply(world);
+    world.insert_resource(NetworkMatchSocket(Box::new(socket)));
+    Ok(())
+}
+
+/// Stop inviting an online player when the match is left.
+fn cancel_match_upgrade(mut upgrade: ResMut<MatchUpgrade>) {
+    if upgrade.is_inviting() {
+        upgrade.cancel();
+    }
+}
+
+/// Show the lobby code that the online player joins with while inviting them.
+fn invite_banner(
+    mut contexts: EguiContexts,
+    game: Res<GameMeta>,
+    localization: Res<Localization>,
+    upgrade: Res<MatchUpgrade>,
+) {
+    let ui_theme = &game.ui_theme;
+    let font = ui_theme
+        .font_styles
+        .normal
+        .colored(ui_theme.panel.font_color);
+
+    egui::Area::new("match-upgrade-banner")
+        .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 10.0))
+        .show(contexts.ctx_mut(), |ui| {
+            BorderedFrame::new(&ui_theme.panel.border)
+                .padding(ui_theme.panel.padding.into())
+                .show(ui, |ui| {
+                    ui.themed_label(
+                        &font,
+                        &localization.get(&format!(
+                            "match-upgrade-inviting?code={}",
+                            upgrade.lobby_code
+                        )),
+                    );
+                });
+        });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn players_are_mapped_to_peers() {
+        // The host plays in slots 0 and 2, and the guest joins in slot 1
+        let host = SlotMap {
+            player_count: 3,
+            guest_slot: 1,
+            host_peer: 1,
+            local_peer: 1,
+        };
+        let guest = SlotMap {
+            local_peer: 0,
+            ..host
+        };
+
+        assert_eq!(host.peer(0), Some(1));
+        assert_eq!(host.peer(1), Some(0));
+        assert_eq!(host.peer(3), None);
+        assert_eq!(host.primary_slot(1), Some(0));
+        assert_eq!(host.primary_slot(0), Some(1));
+
+        assert_eq!(host.player_is_local(), [true, false, true, false]);
+        assert_eq!(guest.player_is_local(), [false, true, false, false]);
+        assert_eq!(host.player_addrs(), [0, 1, 0, 3]);
+        assert_eq!(guest.player_addrs(), host.player_addrs());
+    }
+
+    #[test]
+    fn snapshot_chunks_are_reassembled() {
+        let snapshot = (0..SNAPSHOT_CHUNK_SIZE * 2 + 10)
+            .map(|x| x as u8)
+            .collect::<Vec<_>>();
+        let chunks = SnapshotChunk::split(&snapshot);
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks
+            .iter()
+            .all(|x| x.encode().len() <= SNAPSHOT_CHUNK_SIZE + 16));
+
+        let mut assembler = SnapshotAssembler::default();
+        let mut decoded = chunks
+            .iter()
+            .map(|x| SnapshotChunk::decode(&x.encode()).unwrap());
+        let (first, second, third) = (
+            decoded.next().unwrap(),
+            decoded.next().unwrap(),
+            decoded.next().unwrap(),
+        );
+        // Out of order
+        assert_eq!(assembler.add(third), None);
+        assert_eq!(assembler.add(first.clone()), None);
+        assert_eq!(assembler.add(first), None);
+        assert_eq!(assembler.add(second), Some(snapshot));
+
+        assert_eq!(SnapshotChunk::decode(&[0xF8, 0]), None);
+
+        // Chunks with a count that can't be right are ignored without allocating for them
+        let mut assembler = SnapshotAssembler::default();
+        let huge = SnapshotChunk {
+            index: 0,
+            count: u32::MAX,
+            data: vec![0],
+        };
+        assert_eq!(assembler.add(huge), None);
+        assert!(assembler.chunks.is_empty());
+
+        let mut chunks = SnapshotChunk::split(&snapshot).into_iter();
+        assert_eq!(assembler.add(chunks.next().unwrap()), None);
+        let other_count = SnapshotChunk {
+            index: 1,
+            count: 2,
+            data: vec![0],
+        };
+        assert_eq!(assembler.add(other_count), None);
+        assert_eq!(assembler.chunks.len(), 3);
+    }
+
+    #[test]
+    fn frames_are_run_length_encoded() {
+        let still = [Some(PlayerControl::default()), None, None, None];
+        let moving = [
+            Some(PlayerControl {
+                moving: true,
+                ..default()
+            }),
+            None,
+            None,
+            None,
+        ];
+        let frames = vec![still.clone(), still.clone(), moving.clone(), still.clone()];
+
+        let encoded = encode_frames(&frames);
+        assert_eq!(encoded.len(), 3);
+        assert_eq!(encoded[0].0, 2);
+        assert_eq!(decode_frames(encoded), Some(frames));
+
+        let too_long = vec![(u32::MAX, still.clone()), (u32::MAX, still)];
+        assert_eq!(decode_frames(too_long), None);
+    }
+}
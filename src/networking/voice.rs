@@ -0,0 +1,347 @@
+//! Push-to-talk voice chat for network games.
+//!
+//! This is only built with the `voice-chat` feature, because it needs the system's audio capture
+//! libraries.
+//!
+//! While [`PUSH_TO_TALK_KEY`] is held during a match, the microphone is recorded and encoded with
+//! Opus in packets of [`FRAME_SAMPLES`], which are sent to the other players over the reliable
+//! channel, prefixed with [`MessageTag::Voice`]. Received packets are decoded and mixed into the
+//! audio output, with the mute and volume settings chosen for each player in the pause menu.
+//!
+//! The audio streams live in a thread of their own, because they can't be sent between threads on
+//! every platform.
+
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use async_channel::{Receiver, Sender};
+use audiopus::{
+    coder::{Decoder, Encoder},
+    Application, Channels, SampleRate,
+};
+use bevy_fluent::Localization;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+use super::{
+    proto::MessageTag, NetworkMatchSocket, NetworkSocket, ReliableMessage, ReliableMessageHandlers,
+    SocketTarget,
+};
+use crate::prelude::*;
+
+/// Voice chat plugin.
+pub struct JumpyVoiceChatPlugin;
+
+impl Plugin for JumpyVoiceChatPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(start_and_stop_voice_chat)
+            .add_system(
+                send_voice
+                    .run_if(in_state(EngineState::InGame))
+                    .run_if(resource_exists::<NetworkMatchSocket>())
+                    .run_if(resource_exists::<VoiceChat>()),
+            )
+            .add_system(
+                handle_voice_messages
+                    .in_set(ReliableMessageHandlers)
+                    .run_if(in_state(EngineState::InGame))
+                    .run_if(resource_exists::<VoiceChat>()),
+            );
+    }
+}
+
+/// The key held to talk to the other players.
+pub const PUSH_TO_TALK_KEY: KeyCode = KeyCode::T;
+
+/// The sample rate voice is recorded, sent, and played at.
+const SAMPLE_RATE: u32 = 48_000;
+
+/// The number of samples in an encoded packet: 20 ms of audio.
+pub const FRAME_SAMPLES: usize = SAMPLE_RATE as usize / 50;
+
+/// The largest encoded packet size.
+const MAX_PACKET_SIZE: usize = 4000;
+
+/// The largest number of samples buffered for each player before they are played, so that voice
+/// doesn't lag further and further behind when packets arrive in bursts.
+const MAX_BUFFERED_SAMPLES: usize = SAMPLE_RATE as usize / 2;
+
+/// The voice chat settings for another player.
+#[derive(Clone, Copy, Debug)]
+pub struct VoicePlayerSettings {
+    /// Whether the player is muted.
+    pub muted: bool,
+    /// The volume of the player's voice.
+    pub volume: f32,
+}
+
+impl Default for VoicePlayerSettings {
+    fn default() -> Self {
+        Self {
+            muted: false,
+            volume: 1.0,
+        }
+    }
+}
+
+/// The samples waiting to be played for each player.
+type OutputQueues = Arc<Mutex<[VecDeque<f32>; MAX_PLAYERS]>>;
+
+/// The Opus encoder and decoders.
+struct Codec {
+    encoder: Encoder,
+    decoders: [Decoder; MAX_PLAYERS],
+}
+
+/// Resource containing the voice chat state, present while connected to a network game.
+#[derive(Resource)]
+pub struct VoiceChat {
+    /// The settings for each player.
+    pub players: [VoicePlayerSettings; MAX_PLAYERS],
+    /// Whether or not we are talking.
+    pub talking: bool,
+    codec: Mutex<Codec>,
+    /// Whether or not the microphone is being recorded.
+    recording: Arc<AtomicBool>,
+    /// The recorded samples.
+    recorded: Receiver<Vec<f32>>,
+    /// The recorded samples that haven't been encoded yet.
+    record_buffer: Vec<f32>,
+    output: OutputQueues,
+    /// Stops the audio thread when dropped.
+    _audio_thread: Sender<()>,
+}
+
+impl VoiceChat {
+    /// Start voice chat, opening the default audio devices.
+    pub fn new() -> anyhow::Result<Self> {
+        let codec = Codec {
+            encoder: Encoder::new(SampleRate::Hz48000, Channels::Mono, Application::Voip)?,
+            decoders: [
+                Decoder::new(SampleRate::Hz48000, Channels::Mono)?,
+                Decoder::new(SampleRate::Hz48000, Channels::Mono)?,
+                Decoder::new(SampleRate::Hz48000, Channels::Mono)?,
+                Decoder::new(SampleRate::Hz48000, Channels::Mono)?,
+            ],
+        };
+
+        let recording = Arc::new(AtomicBool::new(false));
+        let (recorded_sender, recorded) = async_channel::unbounded();
+        let output = OutputQueues::default();
+        let (audio_thread, stop) = async_channel::bounded(1);
+        {
+            let recording = recording.clone();
+            let output = output.clone();
+            std::thread::Builder::new()
+                .name("voice-chat".into())
+                .spawn(move || run_audio_streams(recording, recorded_sender, output, stop))?;
+        }
+
+        Ok(Self {
+            players: default(),
+            talking: false,
+            codec: Mutex::new(codec),
+            recording,
+            recorded,
+            record_buffer: default(),
+            output,
+            _audio_thread: audio_thread,
+        })
+    }
+
+    /// Handle a message received over the reliable channel, returning `false` if it isn't a voice
+    /// packet.
+    pub fn handle_message(&mut self, sender: usize, data: &[u8]) -> bool {
+        let Some(packet) = MessageTag::Voice.decode(data) else {
+            return false;
+        };
+        let settings = self.players[sender];
+        if settings.muted {
+            return true;
+        }
+
+        let mut samples = [0.0; FRAME_SAMPLES];
+        let codec = self.codec.get_mut().unwrap();
+        match codec.decoders[sender].decode_float(Some(packet), &mut samples, false) {
+            Ok(len) => {
+                let mut output = self.output.lock().unwrap();
+                let queue = &mut output[sender];
+                queue.extend(samples[..len].iter().map(|x| x * settings.volume));
+                let excess = queue.len().saturating_sub(MAX_BUFFERED_SAMPLES);
+                queue.drain(..excess);
+            }
+            Err(e) => warn!("Could not decode voice packet: {e}"),
+        }
+
+        true
+    }
+}
+
+/// Open the audio devices and keep the streams running until `stop` is closed.
+fn run_audio_streams(
+    recording: Arc<AtomicBool>,
+    recorded: Sender<Vec<f32>>,
+    output: OutputQueues,
+    stop: Receiver<()>,
+) {
+    let host = cpal::default_host();
+    let mono = cpal::StreamConfig {
+        channels: 1,
+        sample_rate: cpal::SampleRate(SAMPLE_RATE),
+        buffer_size: cpal::BufferSize::Default,
+    };
+
+    let input = host.default_input_device().and_then(|device| {
+        device
+            .build_input_stream(
+                &mono,
+                move |data: &[f32], _: &cpal::InputCallbackInfo| {
+                    if recording.load(Ordering::Relaxed) {
+                        recorded.try_send(data.to_vec()).ok();
+                    }
+                },
+                |e| warn!("Voice chat recording error: {e}"),
+                None,
+            )
+            .map_err(|e| warn!("Could not open microphone for voice chat: {e}"))
+            .ok()
+    });
+
+    let output = host.default_output_device().and_then(|device| {
+        let channels = device
+            .default_output_config()
+            .map(|config| config.channels())
+            .unwrap_or(2);
+        let config = cpal::StreamConfig { channels, ..mono };
+        device
+            .build_output_stream(
+                &config,
+                move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+                    let mut queues = output.lock().unwrap();
+                    for frame in data.chunks_mut(channels as usize) {
+                        let sample = queues
+                            .iter_mut()
+                            .filter_map(|queue| queue.pop_front())
+                            .sum::<f32>()
+                            .clamp(-1.0, 1.0);
+                        frame.fill(sample);
+                    }
+                },
+                |e| warn!("Voice chat playback error: {e}"),
+                None,
+            )
+            .map_err(|e| warn!("Could not open audio output for voice chat: {e}"))
+            .ok()
+    });
+
+    for stream in input.iter().chain(&output) {
+        if let Err(e) = stream.play() {
+            warn!("Could not start voice chat audio stream: {e}");
+        }
+    }
+
+    // Keep the streams alive until voice chat is stopped.
+    stop.recv_blocking().ok();
+}
+
+/// Start voice chat when connecting to a network game, and stop it when disconnecting.
+fn start_and_stop_voice_chat(
+    mut commands: Commands,
+    mut failed: Local<bool>,
+    socket: Option<Res<NetworkMatchSocket>>,
+    voice_chat: Option<Res<VoiceChat>>,
+) {
+    match (socket, voice_chat) {
+        (Some(_), None) if !*failed => match VoiceChat::new() {
+            Ok(voice_chat) => commands.insert_resource(voice_chat),
+            Err(e) => {
+                error!("Could not start voice chat: {e}");
+                // Don't try again until the next connection
+                *failed = true;
+            }
+        },
+        (None, Some(_)) => commands.remove_resource::<VoiceChat>(),
+        (None, None) => *failed = false,
+        _ => (),
+    }
+}
+
+/// Play the voice packets received during a match.
+fn handle_voice_messages(
+    mut messages: EventReader<ReliableMessage>,
+    mut voice_chat: ResMut<VoiceChat>,
+) {
+    for message in messages.iter() {
+        voice_chat.handle_message(message.sender, &message.data);
+    }
+}
+
+/// Record, encode, and send our voice while the push-to-talk key is held.
+fn send_voice(
+    keyboard: Res<Input<KeyCode>>,
+    socket: Res<NetworkMatchSocket>,
+    mut voice_chat: ResMut<VoiceChat>,
+) {
+    let voice_chat = &mut *voice_chat;
+    voice_chat.talking = keyboard.pressed(PUSH_TO_TALK_KEY);
+    voice_chat
+        .recording
+        .store(voice_chat.talking, Ordering::Relaxed);
+
+    while let Ok(samples) = voice_chat.recorded.try_recv() {
+        voice_chat.record_buffer.extend(samples);
+    }
+    // Voice is the first thing to go when the bandwidth cap is reached
+    if !voice_chat.talking || socket.is_throttled() {
+        voice_chat.record_buffer.clear();
+        return;
+    }
+
+    let codec = voice_chat.codec.get_mut().unwrap();
+    while voice_chat.record_buffer.len() >= FRAME_SAMPLES {
+        let frame = voice_chat
+            .record_buffer
+            .drain(..FRAME_SAMPLES)
+            .collect::<Vec<_>>();
+        let mut packet = [0; MAX_PACKET_SIZE];
+        match codec.encoder.encode_float(&frame, &mut packet) {
+            Ok(len) => {
+                let message = MessageTag::Voice.encode(&packet[..len]);
+                socket.send_reliable(SocketTarget::All, &message);
+            }
+            Err(e) => warn!("Could not encode voice: {e}"),
+        }
+    }
+}
+
+/// Render the voice chat settings for the other players in the pause menu.
+pub fn voice_chat_settings_ui(
+    ui: &mut egui::Ui,
+    localization: &Localization,
+    socket: &NetworkMatchSocket,
+    voice_chat: &mut VoiceChat,
+) {
+    ui.label(localization.get("voice-chat"));
+    ui.label(localization.get(&format!("push-to-talk?key={PUSH_TO_TALK_KEY:?}")));
+    let local_player = socket.player_idx();
+    for (player, settings) in voice_chat.players[..socket.player_count()]
+        .iter_mut()
+        .enumerate()
+    {
+        if player == local_player {
+            continue;
+        }
+        ui.horizontal(|ui| {
+            ui.label(localization.get(&format!("voice-chat-player?player={}", player + 1)));
+            ui.checkbox(&mut settings.muted, localization.get("mute"));
+            ui.add_enabled(
+                !settings.muted,
+                egui::Slider::new(&mut settings.volume, 0.0..=2.0).text(localization.get("volume")),
+            );
+        });
+    }
+}
@@ -0,0 +1,677 @@
+//! Browser matchmaker, connecting the players of an online lobby with `WebRTC` data channels.
+//!
+//! Browsers can't open the UDP sockets used by the QUIC matchmakers, so browser builds join online
+//! lobbies through a signaling server instead. The lobby is given in the query string, with the
+//! address of the signaling server and, optionally, the number of players of the lobby and the STUN
+//! server the players find their public address with:
+//!
+//! ```text
+//! ?lobby_code=ABC123&signaling_server=wss://example.org/jumpy&player_count=3&stun_server=stun:stun.example.org:3478
+//! ```
+//!
+//! Once the lobby is full, the signaling server gives every player their player index, and relays
+//! the [`Signal`]s that the players send each other to open their peer connections: the player
+//! with the lower index sends an offer, the other one answers it, and both of them send their ICE
+//! candidates.
+//!
+//! # Signaling server
+//!
+//! The game doesn't ship a signaling server, but any `WebSocket` server following this protocol
+//! can be used:
+//!
+//! - Every binary `WebSocket` message holds exactly one message serialized with `postcard`: a
+//!   [`SignalingRequest`] from the player, or a [`SignalingResponse`] from the server.
+//! - The first message of a player is a [`SignalingRequest::Join`]. The first player joining a
+//!   lobby code sets its player count, and a player asking for another count, or joining a lobby
+//!   that is already full, gets a [`SignalingResponse::Error`].
+//! - A player disconnecting before the lobby is full leaves it, freeing their place.
+//! - Once the lobby has as many players as its player count, the server sends
+//!   [`SignalingResponse::Matched`] to each of them, numbering the players from `0` in the order
+//!   they joined, and forgets the lobby code, so that it may be used by another lobby.
+//! - From then on, a [`SignalingRequest::Signal`] is relayed unchanged to the player it is
+//!   addressed to, as a [`SignalingResponse::Signal`] whose `peer` is the index of the sender.
+//!   Signals addressed to a disconnected player or to an unknown index are dropped.
+//! - The players close their connection once their peer connections are open, and the server
+//!   should not close it before: if it does, or if it sends an error, the players give up.
+//!
+//! Every pair of players opens two data channels: an unordered and unreliable one for the GGRS
+//! messages, and an ordered and reliable one for the reliable messages. Once all of them are open,
+//! the [`WebRtcSocket`] is handed to the menu, and the match is set up like for the other
+//! matchmakers.
+//!
+//! The handshake is driven by the browser event loop with [`wasm_bindgen_futures`], the callbacks
+//! of the browser sending the events it waits for through a channel.
+
+use std::net::SocketAddr;
+
+use async_channel::{Receiver, Sender};
+use bevy_egui::EguiContexts;
+use bevy_fluent::Localization;
+use wasm_bindgen::{prelude::*, JsCast};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    BinaryType, MessageEvent, RtcConfiguration, RtcDataChannel, RtcDataChannelInit,
+    RtcDataChannelType, RtcIceCandidateInit, RtcIceServer, RtcPeerConnection,
+    RtcPeerConnectionIceEvent, RtcSdpType, RtcSessionDescriptionInit, WebSocket,
+};
+
+use super::{BoxedNonBlockingSocket, NetworkMatchSocket, NetworkSocket, SocketTarget};
+use crate::{main_menu::MenuPage, prelude::*};
+
+/// Browser matchmaker plugin.
+pub struct JumpyWebRtcPlugin;
+
+impl Plugin for JumpyWebRtcPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(join_lobby.in_schedule(OnEnter(EngineState::MainMenu)))
+            .add_system(
+                wait_for_lobby
+                    .run_if(in_state(EngineState::MainMenu))
+                    .run_if(resource_exists::<WebRtcMatchmaker>()),
+            );
+    }
+}
+
+/// The STUN server used by the players to find out their public address, when the query string
+/// doesn't give one.
+const DEFAULT_STUN_SERVER: &str = "stun:stun.l.google.com:19302";
+
+/// The number of players of the lobby when the query string doesn't give it.
+const DEFAULT_PLAYER_COUNT: usize = 2;
+
+/// The id of the data channel carrying the GGRS messages, negotiated by both players.
+const GGRS_CHANNEL_ID: u16 = 0;
+
+/// The id of the data channel carrying the reliable messages, negotiated by both players.
+const RELIABLE_CHANNEL_ID: u16 = 1;
+
+/// A message sent to the signaling server.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum SignalingRequest {
+    /// Join the lobby with the given code, which is full once it has `player_count` players.
+    Join { lobby: String, player_count: usize },
+    /// Relay a signal to the player with the given index.
+    Signal { peer: usize, signal: Signal },
+}
+
+/// A message sent by the signaling server.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum SignalingResponse {
+    /// The lobby is full, and the local player has the given player index.
+    Matched {
+        player_idx: usize,
+        player_count: usize,
+    },
+    /// A signal relayed from the player with the given index.
+    Signal { peer: usize, signal: Signal },
+    /// The lobby couldn't be joined.
+    Error(String),
+}
+
+/// A message exchanged by two players through the signaling server to open their peer connection.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum Signal {
+    /// The session description offered by the player with the lower index.
+    Offer(String),
+    /// The session description answering the offer.
+    Answer(String),
+    /// A way the sender may be reached.
+    IceCandidate {
+        candidate: String,
+        sdp_mid: Option<String>,
+        sdp_m_line_index: Option<u16>,
+    },
+}
+
+/// Resource inserted while joining the lobby given in the query string.
+#[derive(Resource)]
+pub struct WebRtcMatchmaker {
+    /// The code of the lobby.
+    pub lobby: String,
+    /// Whether the lobby couldn't be joined.
+    pub failed: bool,
+    receiver: Receiver<anyhow::Result<WebRtcSocket>>,
+}
+
+/// Start joining the lobby given in the query string, the first time the main menu is shown.
+fn join_lobby(mut commands: Commands, mut started: Local<bool>) {
+    if std::mem::replace(&mut *started, true) {
+        return;
+    }
+    let Some(lobby) = ENGINE_CONFIG.lobby_code.clone() else {
+        return;
+    };
+    let Some(server) = ENGINE_CONFIG.signaling_server.clone() else {
+        warn!(%lobby, "Can't join the lobby without a signaling server");
+        return;
+    };
+    let player_count = ENGINE_CONFIG
+        .lobby_player_count
+        .unwrap_or(DEFAULT_PLAYER_COUNT)
+        .clamp(2, MAX_PLAYERS);
+
+    info!(%lobby, %server, %player_count, "Joining online lobby");
+    let (sender, receiver) = async_channel::bounded(1);
+    let code = lobby.clone();
+    wasm_bindgen_futures::spawn_local(async move {
+        sender
+            .send(join(&server, &code, player_count).await)
+            .await
+            .ok();
+    });
+    commands.insert_resource(WebRtcMatchmaker {
+        lobby,
+        failed: false,
+        receiver,
+    });
+}
+
+/// Show the progress of joining the lobby, and go to the player selection once the other players
+/// are connected.
+fn wait_for_lobby(
+    mut commands: Commands,
+    mut contexts: EguiContexts,
+    mut matchmaker: ResMut<WebRtcMatchmaker>,
+    mut menu_page: ResMut<MenuPage>,
+    localization: Res<Localization>,
+) {
+    match matchmaker.receiver.try_recv() {
+        Ok(Ok(socket)) => {
+            info!("Connected to the other players of the lobby");
+            commands.insert_resource(NetworkMatchSocket(Box::new(socket)));
+            commands.remove_resource::<WebRtcMatchmaker>();
+            *menu_page = MenuPage::PlayerSelect;
+            return;
+        }
+        Ok(Err(e)) => {
+            error!("Could not join lobby {}: {e:?}", matchmaker.lobby);
+            matchmaker.failed = true;
+        }
+        Err(_) => (),
+    }
+
+    egui::Window::new(localization.get("online-game"))
+        .id(egui::Id::new("webrtc-lobby"))
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(contexts.ctx_mut(), |ui| {
+            if matchmaker.failed {
+                ui.label(localization.get("join-lobby-failed"));
+                if ui.button(localization.get("close")).clicked() {
+                    commands.remove_resource::<WebRtcMatchmaker>();
+                }
+            } else {
+                ui.label(localization.get(&format!("joining-lobby?code={}", matchmaker.lobby)));
+            }
+        });
+}
+
+/// The events that the handshake waits for, sent by the callbacks of the browser.
+enum HandshakeEvent {
+    SignalingOpen,
+    SignalingClosed,
+    /// A message from the signaling server.
+    Response(Vec<u8>),
+    /// One of the data channels with the other players opened.
+    ChannelOpen,
+}
+
+/// Join a lobby through the given signaling server, returning a socket connected to every other
+/// player of the lobby.
+async fn join(server: &str, lobby: &str, player_count: usize) -> anyhow::Result<WebRtcSocket> {
+    let (event_sender, events) = async_channel::unbounded();
+    let signaling = WebSocket::new(server).map_err(js_error)?;
+    signaling.set_binary_type(BinaryType::Arraybuffer);
+    let on_open = on_event(&event_sender, |_| Some(HandshakeEvent::SignalingOpen));
+    let on_message = on_event(&event_sender, |event| {
+        message_data(&event).map(HandshakeEvent::Response)
+    });
+    let on_close = on_event(&event_sender, |_| Some(HandshakeEvent::SignalingClosed));
+    signaling.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+    signaling.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+    signaling.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+
+    let result = connect_peers(&signaling, &event_sender, &events, lobby, player_count).await;
+
+    // The players talk to each other directly from now on
+    signaling.set_onopen(None);
+    signaling.set_onmessage(None);
+    signaling.set_onclose(None);
+    signaling.close().ok();
+    result
+}
+
+/// Join the lobby once connected to the signaling server, and open the peer connections to the
+/// other players.
+async fn connect_peers(
+    signaling: &WebSocket,
+    event_sender: &Sender<HandshakeEvent>,
+    events: &Receiver<HandshakeEvent>,
+    lobby: &str,
+    player_count: usize,
+) -> anyhow::Result<WebRtcSocket> {
+    let HandshakeEvent::SignalingOpen = events.recv().await? else {
+        anyhow::bail!("Couldn't connect to the signaling server");
+    };
+    send_request(
+        signaling,
+        &SignalingRequest::Join {
+            lobby: lobby.into(),
+            player_count,
+        },
+    )?;
+
+    // Wait for the lobby to be full
+    let (player_idx, player_count) = loop {
+        match events.recv().await? {
+            HandshakeEvent::Response(data) => {
+                match postcard::from_bytes::<SignalingResponse>(&data)? {
+                    SignalingResponse::Matched {
+                        player_idx,
+                        player_count,
+                    } => break (player_idx, player_count),
+                    SignalingResponse::Error(e) => {
+                        anyhow::bail!("The lobby couldn't be joined: {e}")
+                    }
+                    response => warn!(?response, "Ignoring unexpected signaling response"),
+                }
+            }
+            HandshakeEvent::SignalingClosed => {
+                anyhow::bail!("Lost the connection to the signaling server")
+            }
+            HandshakeEvent::SignalingOpen | HandshakeEvent::ChannelOpen => (),
+        }
+    };
+    if player_count > MAX_PLAYERS || player_idx >= player_count {
+        anyhow::bail!("Invalid player index {player_idx} in a lobby of {player_count} players");
+    }
+    info!(%player_idx, %player_count, "Lobby is full, connecting to the other players");
+
+    let (ggrs_sender, ggrs_receiver) = async_channel::unbounded();
+    let (reliable_sender, reliable_receiver) = async_channel::unbounded();
+    let mut peers = Vec::with_capacity(player_count);
+    for peer_idx in 0..player_count {
+        if peer_idx == player_idx {
+            peers.push(None);
+            continue;
+        }
+        let peer = WebRtcPeer::new(
+            peer_idx,
+            signaling,
+            event_sender,
+            &ggrs_sender,
+            &reliable_sender,
+        )?;
+        if player_idx < peer_idx {
+            let offer = JsFuture::from(peer.connection.create_offer())
+                .await
+                .map_err(js_error)?;
+            let sdp = session_sdp(&offer)?;
+            let description = session_description(RtcSdpType::Offer, &sdp);
+            JsFuture::from(peer.connection.set_local_description(&description))
+                .await
+                .map_err(js_error)?;
+            send_request(
+                signaling,
+                &SignalingRequest::Signal {
+                    peer: peer_idx,
+                    signal: Signal::Offer(sdp),
+                },
+            )?;
+        }
+        peers.push(Some(peer));
+    }
+
+    // Wait for both data channels with every other player to open
+    let mut open_channels = 0;
+    while open_channels < 2 * (player_count - 1) {
+        match events.recv().await? {
+            HandshakeEvent::ChannelOpen => open_channels += 1,
+            HandshakeEvent::Response(data) => {
+                match postcard::from_bytes::<SignalingResponse>(&data)? {
+                    SignalingResponse::Signal { peer, signal } => {
+                        let Some(Some(webrtc_peer)) = peers.get(peer) else {
+                        warn!(%peer, "Ignoring signal from an unknown player");
+                        continue;
+                    };
+                        webrtc_peer.handle_signal(peer, signal, signaling).await?;
+                    }
+                    SignalingResponse::Error(e) => anyhow::bail!("Signaling failed: {e}"),
+                    response => warn!(?response, "Ignoring unexpected signaling response"),
+                }
+            }
+            HandshakeEvent::SignalingClosed => {
+                anyhow::bail!("Lost the connection to the signaling server")
+            }
+            HandshakeEvent::SignalingOpen => (),
+        }
+    }
+
+    Ok(WebRtcSocket {
+        player_idx,
+        player_count,
+        peers: Arc::new(peers),
+        ggrs_receiver,
+        reliable_receiver,
+    })
+}
+
+/// Create a browser callback sending the event returned by `f`, if any, to the handshake.
+fn on_event(
+    sender: &Sender<HandshakeEvent>,
+    f: impl Fn(JsValue) -> Option<HandshakeEvent> + 'static,
+) -> Closure<dyn FnMut(JsValue)> {
+    let sender = sender.clone();
+    Closure::new(move |event| {
+        if let Some(event) = f(event) {
+            // The handshake may be over already
+            sender.try_send(event).ok();
+        }
+    })
+}
+
+/// Get the binary data of a `MessageEvent`.
+fn message_data(event: &JsValue) -> Option<Vec<u8>> {
+    let buffer = event
+        .unchecked_ref::<MessageEvent>()
+        .data()
+        .dyn_into::<js_sys::ArrayBuffer>()
+        .ok()?;
+    Some(js_sys::Uint8Array::new(&buffer).to_vec())
+}
+
+/// Send a message to the signaling server.
+fn send_request(signaling: &WebSocket, request: &SignalingRequest) -> anyhow::Result<()> {
+    signaling
+        .send_with_u8_array(&postcard::to_allocvec(request)?)
+        .map_err(js_error)
+}
+
+/// Get the SDP of the session description returned by `createOffer()` or `createAnswer()`.
+fn session_sdp(description: &JsValue) -> anyhow::Result<String> {
+    js_sys::Reflect::get(description, &JsValue::from_str("sdp"))
+        .map_err(js_error)?
+        .as_string()
+        .context("The session description has no SDP")
+}
+
+/// Create a session description with the given type and SDP.
+fn session_description(kind: RtcSdpType, sdp: &str) -> RtcSessionDescriptionInit {
+    let mut description = RtcSessionDescriptionInit::new(kind);
+    description.sdp(sdp);
+    description
+}
+
+/// Turn a JavaScript exception into an error.
+fn js_error(error: JsValue) -> anyhow::Error {
+    anyhow::format_err!("{error:?}")
+}
+
+/// The peer connection to another player, and its data channels.
+struct WebRtcPeer {
+    connection: RtcPeerConnection,
+    ggrs_channel: RtcDataChannel,
+    reliable_channel: RtcDataChannel,
+    /// The callbacks registered with the connection and its channels, which must live as long as
+    /// them.
+    _callbacks: Vec<Closure<dyn FnMut(JsValue)>>,
+}
+
+// SAFETY: JavaScript objects can't be sent to other threads, but browser builds of the game are
+// single-threaded, so they are never accessed from another thread.
+unsafe impl Send for WebRtcPeer {}
+unsafe impl Sync for WebRtcPeer {}
+
+impl WebRtcPeer {
+    /// Create the peer connection to the player with the given index, forwarding its ICE
+    /// candidates to the signaling server, and the messages received from it to the given
+    /// channels.
+    fn new(
+        peer_idx: usize,
+        signaling: &WebSocket,
+        event_sender: &Sender<HandshakeEvent>,
+        ggrs_sender: &Sender<(usize, ggrs::Message)>,
+        reliable_sender: &Sender<(usize, Vec<u8>)>,
+    ) -> anyhow::Result<Self> {
+        let mut ice_server = RtcIceServer::new();
+        let stun_server = ENGINE_CONFIG
+            .stun_server
+            .as_deref()
+            .unwrap_or(DEFAULT_STUN_SERVER);
+        ice_server.urls(&JsValue::from_str(stun_server));
+        let mut config = RtcConfiguration::new();
+        config.ice_servers(&js_sys::Array::of1(&ice_server));
+        let connection = RtcPeerConnection::new_with_configuration(&config).map_err(js_error)?;
+
+        // The channels are negotiated, so that both players create them with the same ids
+        let mut ggrs_init = RtcDataChannelInit::new();
+        ggrs_init
+            .negotiated(true)
+            .id(GGRS_CHANNEL_ID)
+            .ordered(false)
+            .max_retransmits(0);
+        let ggrs_channel =
+            connection.create_data_channel_with_data_channel_dict("ggrs", &ggrs_init);
+        let mut reliable_init = RtcDataChannelInit::new();
+        reliable_init.negotiated(true).id(RELIABLE_CHANNEL_ID);
+        let reliable_channel =
+            connection.create_data_channel_with_data_channel_dict("reliable", &reliable_init);
+
+        let signaling = signaling.clone();
+        let on_ice_candidate = Closure::<dyn FnMut(JsValue)>::new(move |event: JsValue| {
+            // The last event has no candidate, once all of them were gathered
+            let Some(candidate) = event.unchecked_ref::<RtcPeerConnectionIceEvent>().candidate()
+            else {
+                return;
+            };
+            let signal = Signal::IceCandidate {
+                candidate: candidate.candidate(),
+                sdp_mid: candidate.sdp_mid(),
+                sdp_m_line_index: candidate.sdp_m_line_index(),
+            };
+            let request = SignalingRequest::Signal {
+                peer: peer_idx,
+                signal,
+            };
+            if let Err(e) = send_request(&signaling, &request) {
+                warn!("Could not send ICE candidate: {e}");
+            }
+        });
+        connection.set_onicecandidate(Some(on_ice_candidate.as_ref().unchecked_ref()));
+
+        let ggrs_sender = ggrs_sender.clone();
+        let on_ggrs_message = Closure::<dyn FnMut(JsValue)>::new(move |event: JsValue| {
+            let Some(data) = message_data(&event) else {
+                return;
+            };
+            match postcard::from_bytes(&data) {
+                Ok(message) => {
+                    ggrs_sender.try_send((peer_idx, message)).ok();
+                }
+                Err(e) => warn!("Ignoring GGRS message that was not understood: {e}"),
+            }
+        });
+        let reliable_sender = reliable_sender.clone();
+        let on_reliable_message = Closure::<dyn FnMut(JsValue)>::new(move |event: JsValue| {
+            if let Some(data) = message_data(&event) {
+                reliable_sender.try_send((peer_idx, data)).ok();
+            }
+        });
+        let on_open = on_event(event_sender, |_| Some(HandshakeEvent::ChannelOpen));
+        for (channel, on_message) in [
+            (&ggrs_channel, &on_ggrs_message),
+            (&reliable_channel, &on_reliable_message),
+        ] {
+            channel.set_binary_type(RtcDataChannelType::Arraybuffer);
+            channel.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+            channel.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        }
+
+        Ok(Self {
+            connection,
+            ggrs_channel,
+            reliable_channel,
+            _callbacks: vec![
+                on_ice_candidate,
+                on_ggrs_message,
+                on_reliable_message,
+                on_open,
+            ],
+        })
+    }
+
+    /// Handle a signal sent by the player through the signaling server.
+    async fn handle_signal(
+        &self,
+        peer_idx: usize,
+        signal: Signal,
+        signaling: &WebSocket,
+    ) -> anyhow::Result<()> {
+        match signal {
+            Signal::Offer(sdp) => {
+                let offer = session_description(RtcSdpType::Offer, &sdp);
+                JsFuture::from(self.connection.set_remote_description(&offer))
+                    .await
+                    .map_err(js_error)?;
+                let answer = JsFuture::from(self.connection.create_answer())
+                    .await
+                    .map_err(js_error)?;
+                let sdp = session_sdp(&answer)?;
+                let answer = session_description(RtcSdpType::Answer, &sdp);
+                JsFuture::from(self.connection.set_local_description(&answer))
+                    .await
+                    .map_err(js_error)?;
+                send_request(
+                    signaling,
+                    &SignalingRequest::Signal {
+                        peer: peer_idx,
+                        signal: Signal::Answer(sdp),
+                    },
+                )?;
+            }
+            Signal::Answer(sdp) => {
+                let answer = session_description(RtcSdpType::Answer, &sdp);
+                JsFuture::from(self.connection.set_remote_description(&answer))
+                    .await
+                    .map_err(js_error)?;
+            }
+            Signal::IceCandidate {
+                candidate,
+                sdp_mid,
+                sdp_m_line_index,
+            } => {
+                let mut init = RtcIceCandidateInit::new(&candidate);
+                init.sdp_mid(sdp_mid.as_deref())
+                    .sdp_m_line_index(sdp_m_line_index);
+                let promise = self
+                    .connection
+                    .add_ice_candidate_with_opt_rtc_ice_candidate_init(Some(&init));
+                JsFuture::from(promise).await.map_err(js_error)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Close the data channels and the peer connection.
+    fn close(&self) {
+        self.ggrs_channel.close();
+        self.reliable_channel.close();
+        self.connection.close();
+    }
+}
+
+impl Drop for WebRtcPeer {
+    fn drop(&mut self) {
+        // The browser must not call the callbacks once they are dropped
+        self.connection.set_onicecandidate(None);
+        for channel in [&self.ggrs_channel, &self.reliable_channel] {
+            channel.set_onopen(None);
+            channel.set_onmessage(None);
+        }
+        self.close();
+    }
+}
+
+/// [`NetworkSocket`] connecting the players of a lobby with `WebRTC` data channels.
+#[derive(Clone)]
+pub struct WebRtcSocket {
+    player_idx: usize,
+    player_count: usize,
+    /// The connection to every other player, by player index.
+    peers: Arc<Vec<Option<WebRtcPeer>>>,
+    ggrs_receiver: Receiver<(usize, ggrs::Message)>,
+    reliable_receiver: Receiver<(usize, Vec<u8>)>,
+}
+
+impl ggrs::NonBlockingSocket<usize> for WebRtcSocket {
+    fn send_to(&mut self, msg: &ggrs::Message, addr: &usize) {
+        let Some(Some(peer)) = self.peers.get(*addr) else {
+            return;
+        };
+        // The message is lost if the channel closed, like any other unreliable message
+        peer.ggrs_channel
+            .send_with_u8_array(&postcard::to_allocvec(msg).unwrap())
+            .ok();
+    }
+
+    fn receive_all_messages(&mut self) -> Vec<(usize, ggrs::Message)> {
+        let mut messages = Vec::new();
+        while let Ok(message) = self.ggrs_receiver.try_recv() {
+            messages.push(message);
+        }
+        messages
+    }
+}
+
+impl NetworkSocket for WebRtcSocket {
+    fn ggrs_socket(&self) -> BoxedNonBlockingSocket {
+        BoxedNonBlockingSocket(Box::new(self.clone()))
+    }
+
+    fn send_reliable(&self, target: SocketTarget, message: &[u8]) {
+        let peers: Vec<&WebRtcPeer> = match target {
+            SocketTarget::Player(i) => self.peers.get(i).into_iter().flatten().collect(),
+            SocketTarget::All => self.peers.iter().flatten().collect(),
+        };
+        for peer in peers {
+            // The player may have left or been kicked out of the game.
+            peer.reliable_channel.send_with_u8_array(message).ok();
+        }
+    }
+
+    fn recv_reliable(&self) -> Vec<(usize, Vec<u8>)> {
+        let mut messages = Vec::new();
+        while let Ok(message) = self.reliable_receiver.try_recv() {
+            messages.push(message);
+        }
+        messages
+    }
+
+    fn close(&self) {
+        for peer in self.peers.iter().flatten() {
+            peer.close();
+        }
+    }
+
+    fn player_idx(&self) -> usize {
+        self.player_idx
+    }
+
+    fn player_is_local(&self) -> [bool; MAX_PLAYERS] {
+        std::array::from_fn(|i| i == self.player_idx)
+    }
+
+    fn player_count(&self) -> usize {
+        self.player_count
+    }
+
+    fn ban_player(&self, _player_idx: usize) {
+        // The players can only reach us through the signaling server while the lobby fills up
+    }
+
+    fn remote_addr(&self) -> Option<SocketAddr> {
+        // Browsers don't expose the addresses of the peers
+        None
+    }
+}
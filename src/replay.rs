@@ -0,0 +1,313 @@
+//! Recording and playing back match replays.
+//!
+//! Local matches are recorded by a [`ReplayRecorder`] in the [`LocalSessionRunner`]. When the game
+//! is started with `--record-replays`, the replays are saved to the `replays` folder of the user
+//! data directory when the match is restarted or left. Changes made in the editor during a match
+//! aren't recorded.
+//!
+//! A saved replay is played back by the [`ReplaySessionRunner`] when the game is started with
+//! `--replay <path>`. Once the last frame has been simulated, the state of the world is compared to
+//! the recorded one, to detect changes to the simulation or non-determinism.
+//!
+//! See [`jumpy_core::replay`] for what replays contain.
+
+use std::path::{Path, PathBuf};
+
+use bevy::utils::Instant;
+use jumpy_core::{
+    input::{PlayerControl, PlayerInputs},
+    replay::{Replay, REPLAY_VERSION},
+};
+
+use crate::{config::ENGINE_CONFIG, prelude::*};
+
+/// Replay plugin.
+pub struct JumpyReplayPlugin;
+
+impl Plugin for JumpyReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(save_replays.in_schedule(OnExit(EngineState::InGame)))
+            .add_system(start_replay.in_schedule(OnEnter(EngineState::MainMenu)));
+    }
+}
+
+/// Records the replays of the matches played in a session.
+pub struct ReplayRecorder {
+    /// The replay of the current match.
+    pub replay: Replay,
+    /// The replays of the matches that have been finished, but not saved yet.
+    pub finished: Vec<Replay>,
+    /// Whether recording is interrupted until the next match.
+    pub interrupted: bool,
+}
+
+impl ReplayRecorder {
+    /// Start recording the given session.
+    ///
+    /// This must be called before the first frame has been simulated.
+    pub fn new(core: &CoreSession) -> Self {
+        Self {
+            replay: Replay::new(core),
+            finished: default(),
+            interrupted: false,
+        }
+    }
+
+    /// Record the player inputs of the frame that is about to be simulated.
+    pub fn record_frame(&mut self, core: &CoreSession) {
+        if self.interrupted {
+            return;
+        }
+        let inputs = core.world.resource::<PlayerInputs>();
+        self.replay.record_frame(&inputs.borrow());
+    }
+
+    /// Finish the replay of the current match, and start recording the next one.
+    ///
+    /// This must be called before the session is restarted.
+    pub fn finish(&mut self, core: &CoreSession) {
+        let mut replay = std::mem::replace(&mut self.replay, Replay::new(core));
+        if !replay.frames.is_empty() {
+            replay.finish(core);
+            self.finished.push(replay);
+        }
+        self.interrupted = false;
+    }
+
+    /// Finish the replay of the current match, and stop recording until the next one.
+    ///
+    /// This is used when the match doesn't continue from the last recorded frame.
+    pub fn interrupt(&mut self, core: &CoreSession) {
+        self.finish(core);
+        self.interrupted = true;
+    }
+
+    /// Get the replay of the current match up to the last simulated frame, or `None` if recording
+    /// is interrupted.
+    pub fn current(&self, core: &CoreSession) -> Option<Replay> {
+        if self.interrupted {
+            return None;
+        }
+        let mut replay = self.replay.clone();
+        replay.finish(core);
+        Some(replay)
+    }
+
+    /// Continue recording the current match from a replay of it up to the last simulated frame,
+    /// such as after restoring a [`SessionSnapshot`].
+    ///
+    /// The frames recorded since the replay was taken are discarded.
+    pub fn resume(&mut self, replay: Replay) {
+        self.replay = replay;
+        self.interrupted = false;
+    }
+}
+
+/// Get the directory replays are saved to.
+pub fn replay_dir() -> Option<PathBuf> {
+    directories::ProjectDirs::from("org", "FishFolk", "Jumpy")
+        .map(|dirs| dirs.data_dir().join("replays"))
+}
+
+/// Save a replay to the [`replay_dir()`] with the given file name, without the extension,
+/// returning the path of the file.
+pub fn save_replay(replay: &Replay, name: &str) -> anyhow::Result<PathBuf> {
+    let dir = replay_dir().context("Couldn't identify the user data directory")?;
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{name}.replay.yaml"));
+    std::fs::write(&path, serde_yaml::to_string(replay)?)?;
+    Ok(path)
+}
+
+/// Load a replay from a YAML file, checking that it was recorded with this version of the game.
+pub fn load_replay(path: &Path) -> anyhow::Result<Replay> {
+    let replay: Replay = serde_yaml::from_str(&std::fs::read_to_string(path)?)?;
+    anyhow::ensure!(
+        replay.version == REPLAY_VERSION,
+        "The replay was recorded with version {} of the game, but this is version {}",
+        replay.version,
+        REPLAY_VERSION
+    );
+    Ok(replay)
+}
+
+/// Implementation of [`SessionRunner`] playing back a [`Replay`].
+pub struct ReplaySessionRunner {
+    pub core: CoreSession,
+    pub replay: Replay,
+    /// The next frame of the replay to play back.
+    pub frame: usize,
+    /// The playback speed, `1.0` being the normal speed.
+    pub speed: f32,
+    pub accumulator: f64,
+    pub loop_start: Option<Instant>,
+}
+
+impl ReplaySessionRunner {
+    pub fn new(core_meta: Arc<CoreMeta>, replay: Replay) -> Self {
+        let mut core = CoreSession::new(replay.session_info(core_meta));
+        core.time_step = replay.time_step;
+        Self {
+            core,
+            replay,
+            frame: 0,
+            speed: 1.0,
+            accumulator: default(),
+            loop_start: default(),
+        }
+    }
+
+    /// Whether or not all of the frames of the replay have been played back.
+    pub fn is_finished(&self) -> bool {
+        self.frame >= self.replay.frames.len()
+    }
+}
+
+impl SessionRunner for ReplaySessionRunner {
+    fn core_session(&mut self) -> &mut CoreSession {
+        &mut self.core
+    }
+
+    fn restart(&mut self) {
+        self.core.restart();
+        self.core.time_step = self.replay.time_step;
+        self.frame = 0;
+    }
+
+    fn set_player_input(&mut self, _player_idx: usize, _control: PlayerControl) {
+        // The inputs come from the replay.
+    }
+
+    fn advance(&mut self, bevy_world: &mut World) -> Result<(), SessionError> {
+        if self.is_finished() {
+            return Ok(());
+        }
+
+        let replay = &self.replay;
+        let frame = self.frame;
+        self.core
+            .update_input(|inputs| replay.apply_frame(frame, inputs));
+        self.core.advance(bevy_world);
+        self.frame += 1;
+
+        if self.is_finished() {
+            let checksum = self.core.checksum();
+            if checksum == self.replay.checksum {
+                info!("Replay finished");
+            } else {
+                warn!(
+                    recorded = self.replay.checksum,
+                    checksum, "Replay finished in a different state than it was recorded in"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn run_criteria(&mut self, time: &Time) -> ShouldRun {
+        if self.is_finished() {
+            return ShouldRun::No;
+        }
+
+        let step = self.replay.time_step as f64 / self.speed as f64;
+        if self.loop_start.is_none() {
+            self.accumulator += time.delta_seconds_f64();
+        }
+
+        if self.accumulator >= step {
+            let start = self.loop_start.get_or_insert_with(Instant::now);
+
+            let loop_too_long = (Instant::now() - *start).as_secs_f64() > step;
+
+            if loop_too_long {
+                warn!("Frame took too long: couldn't keep up with fixed update.");
+                self.accumulator = 0.0;
+                self.loop_start = None;
+                ShouldRun::No
+            } else {
+                self.accumulator -= step;
+                ShouldRun::YesAndCheckAgain
+            }
+        } else {
+            self.loop_start = None;
+            ShouldRun::No
+        }
+    }
+
+    fn network_player_idx(&mut self) -> Option<usize> {
+        None
+    }
+
+    fn disconnect_player(&mut self, _player_idx: usize) {}
+
+    fn input_mapping(&mut self) -> [Option<usize>; MAX_PLAYERS] {
+        // Local devices don't control anything while playing back a replay.
+        [None; MAX_PLAYERS]
+    }
+
+    fn remap_inputs(
+        &mut self,
+        _mapping: [Option<usize>; MAX_PLAYERS],
+    ) -> Result<(), RemapInputsError> {
+        Ok(())
+    }
+}
+
+/// Save the replays recorded in the session when leaving the match.
+fn save_replays(session: Option<ResMut<Session>>) {
+    let Some(mut session) = session else { return };
+    let Some(local_session) = session.downcast_mut::<LocalSessionRunner>() else {
+        return;
+    };
+    let Some(recorder) = &mut local_session.recorder else {
+        return;
+    };
+    recorder.finish(&local_session.core);
+    // Matches are always recorded, but only saved when replay recording is enabled
+    if !ENGINE_CONFIG.record_replays {
+        recorder.finished.clear();
+        return;
+    }
+
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or_default();
+    for (i, replay) in recorder.finished.drain(..).enumerate() {
+        match save_replay(&replay, &format!("replay-{timestamp}-{i}")) {
+            Ok(path) => info!("Saved replay to {}", path.display()),
+            Err(e) => error!("Could not save replay: {e}"),
+        }
+    }
+}
+
+/// Start playing back the replay given on the command line, the first time the main menu is
+/// reached.
+fn start_replay(mut started: Local<bool>, mut session_manager: SessionManager) {
+    if *started {
+        return;
+    }
+    *started = true;
+    let Some(replay_path) = &ENGINE_CONFIG.replay else {
+        return;
+    };
+
+    let replay = match load_replay(Path::new(replay_path)) {
+        Ok(replay) => replay,
+        Err(e) => {
+            error!("Could not load replay {replay_path}: {e}");
+            return;
+        }
+    };
+
+    info!("Playing back replay");
+    let core_meta = session_manager.core_meta_arc.0.clone();
+    session_manager.start(ReplaySessionRunner::new(core_meta, replay));
+    session_manager
+        .commands
+        .insert_resource(NextState(Some(EngineState::InGame)));
+    session_manager
+        .commands
+        .insert_resource(NextState(Some(InGameState::Playing)));
+}
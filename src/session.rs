@@ -5,16 +5,25 @@
 //!
 //! Right now there are two kinds of sessions: local sessions and network sessions. These are
 //! implemented by the [`LocalSessionRunner`] and
-//! [`GgrsSessionRunner`][crate::networking::GgrsSessionRunner] types respectively.
+//! [`GgrsSessionRunner`][crate::networking::GgrsSessionRunner] types respectively. Replays are
+//! also played back by the [`ReplaySessionRunner`][crate::replay::ReplaySessionRunner], and by the
+//! [`DirectorSessionRunner`][crate::director::DirectorSessionRunner] in director mode.
 //!
 //! Both of them implmenent [`SessionRunner`] which is a trait used by the [`SessionManager`] to
 //! advance the game simulation properly.
 
 use bevy::utils::Instant;
+use bevy_egui::EguiContexts;
 use downcast_rs::{impl_downcast, Downcast};
-use jumpy_core::input::{PlayerControl, PlayerInputs};
+use jumpy_core::{
+    input::{PlayerControl, PlayerInputs},
+    presentation::PresentationSettings,
+    replay::Replay,
+};
 
-use crate::{main_menu::MenuPage, prelude::*};
+use crate::{
+    ghost::Ghost, latency::LATENCY_METER, main_menu::MenuPage, prelude::*, replay::ReplayRecorder,
+};
 
 /// Session plugin.
 pub struct JumpySessionPlugin;
@@ -32,6 +41,7 @@ impl Plugin for JumpySessionPlugin {
         let mut session_schedule = Schedule::new();
         session_schedule.add_systems((
             ensure_2_players,
+            update_presentation_settings,
             collect_local_input.pipe(update_game),
             play_sounds,
         ));
@@ -39,6 +49,8 @@ impl Plugin for JumpySessionPlugin {
         app.add_plugin(bones_bevy_renderer::BonesRendererPlugin::<Session>::with_sync_time(false))
             .add_plugin(jumpy_core::metadata::JumpyCoreAssetsPlugin)
             .init_resource::<CurrentEditorInput>()
+            .init_resource::<LocalActionToggles>()
+            .add_system(reset_action_toggles.in_schedule(OnEnter(EngineState::InGame)))
             .configure_set(
                 SessionStage::Update
                     .before(CoreSet::Update)
@@ -47,12 +59,15 @@ impl Plugin for JumpySessionPlugin {
                     .run_if(resource_exists::<Session>()),
             )
             .add_system(move |world: &mut World| {
-                let in_correct_state = {
-                    world.resource::<State<EngineState>>().0 == EngineState::InGame
-                        && world.resource::<State<InGameState>>().0 == InGameState::Playing
-                };
+                if world.resource::<State<EngineState>>().0 != EngineState::InGame
+                    || !world.contains_resource::<Session>()
+                {
+                    return;
+                }
 
-                if !in_correct_state || !world.contains_resource::<Session>() {
+                // Local games are frozen while paused, but network games keep running
+                let is_paused = world.resource::<State<InGameState>>().0 == InGameState::Paused;
+                if is_paused && world.resource_mut::<Session>().is_pausable() {
                     return;
                 }
 
@@ -81,6 +96,16 @@ impl Plugin for JumpySessionPlugin {
 #[derive(Resource, Deref, DerefMut)]
 pub struct Session(pub Box<dyn SessionRunner>);
 
+impl Session {
+    /// Take a snapshot of the match.
+    pub fn snapshot(&mut self) -> SessionSnapshot {
+        SessionSnapshot {
+            world: Some(self.world().clone()),
+            replay: self.recorded_replay(),
+        }
+    }
+}
+
 /// Trait implemented by types that know how to advance the core game simulation.
 ///
 /// Things like fixed frame updates are expected to be handled by the session runner.
@@ -96,6 +121,10 @@ pub trait SessionRunner: Sync + Send + Downcast {
     }
     /// Restart the session.
     fn restart(&mut self);
+    /// Restart the current round of the match, keeping the score of the previous rounds.
+    fn restart_round(&mut self) {
+        self.core_session().restart_round();
+    }
     /// Get the control input for the player with the given `player_idx`.
     fn get_player_input(&mut self, player_idx: usize) -> PlayerControl {
         self.core_session()
@@ -109,19 +138,128 @@ pub trait SessionRunner: Sync + Send + Downcast {
     ///
     /// This is used to created fixed refresh rates.
     fn run_criteria(&mut self, time: &Time) -> ShouldRun;
+    /// Whether the simulation is paused while the pause menu is open.
+    ///
+    /// Network games keep running, since the other players can't wait for us.
+    fn is_pausable(&mut self) -> bool {
+        true
+    }
     /// Returns the player index of the player if we are in a network game.
     ///
     /// In a network game, we currently only allow for one local player, so this allows the session
     /// to find out which player we are playing as so it can map the local player 1's input to the
     /// appropriate network player.
     fn network_player_idx(&mut self) -> Option<usize>;
+    /// Remove the player with the given `player_idx` from the running match.
+    fn disconnect_player(&mut self, player_idx: usize);
+    /// Get the player that each local input device controls, indexed by the local device index.
+    fn input_mapping(&mut self) -> [Option<usize>; MAX_PLAYERS];
+    /// Change the player that each local input device controls, indexed by the local device
+    /// index.
+    ///
+    /// This may be used between rounds to let local players swap who is playing which player,
+    /// without having to create a new session. Devices mapped to `None` don't control any player.
+    ///
+    /// The mapping is left unchanged if it is invalid for this session.
+    fn remap_inputs(
+        &mut self,
+        mapping: [Option<usize>; MAX_PLAYERS],
+    ) -> Result<(), RemapInputsError>;
+    /// Get a replay of the running match up to the last simulated frame, if the session records
+    /// it.
+    fn recorded_replay(&mut self) -> Option<Replay> {
+        None
+    }
+    /// Restore the state of the match from a [`SessionSnapshot`].
+    fn restore(&mut self, snapshot: SessionSnapshot) {
+        match snapshot.world {
+            Some(world) => *self.world() = world,
+            None => warn!("This session can't simulate a snapshot again"),
+        }
+    }
 }
 impl_downcast!(SessionRunner);
 
+/// A snapshot of the state of a match, taken with [`SessionManager::snapshot()`].
+///
+/// The [`bones::World`] can't be serialized, because its components are stored without their types,
+/// so snapshots keep a copy of it in memory to restore it instantly. Local matches also keep a
+/// [`Replay`] of the match up to the snapshot when they are recorded: it is the
+/// [serializable form][Self::replay] of the snapshot, which is restored by simulating the match
+/// again with the recorded inputs.
+///
+/// # Limitations
+///
+/// Quick-resume after the game is suspended is only partly supported: the world itself isn't
+/// saved, so resuming re-simulates every frame of the match up to the snapshot, and matches that
+/// aren't recorded, such as network games, can't be resumed at all. Saving the world needs every
+/// component and resource to be registered with its serializer, which bones doesn't support yet.
+#[derive(Clone)]
+pub struct SessionSnapshot {
+    // TODO: Serialize the world once bones can serialize its components, so that long matches can
+    // be resumed without simulating them again.
+    world: Option<bones::World>,
+    replay: Option<Replay>,
+}
+
+impl SessionSnapshot {
+    /// Create a snapshot from its serializable form, such as a replay saved before the game was
+    /// suspended.
+    pub fn from_replay(replay: Replay) -> Self {
+        Self {
+            world: None,
+            replay: Some(replay),
+        }
+    }
+
+    /// Get the serializable form of the snapshot: the match info and the inputs of every frame up
+    /// to the snapshot, with the checksum of the world at that point.
+    ///
+    /// This is `None` if the session doesn't record its matches, such as in network games, or if
+    /// recording was interrupted.
+    pub fn replay(&self) -> Option<&Replay> {
+        self.replay.as_ref()
+    }
+}
+
 /// Possible errors returned by [`SessionRunner::advance`].
 pub enum SessionError {
     /// The session was disconnected.
     Disconnected,
+    /// The simulation of another player diverged from ours on the given frame.
+    Desync { frame: i32 },
+}
+
+/// Possible errors returned by [`SessionRunner::remap_inputs`].
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemapInputsError {
+    #[error("Player {0} does not exist")]
+    PlayerOutOfRange(usize),
+    #[error("Player {0} is controlled by more than one input device")]
+    DuplicatePlayer(usize),
+    #[error("Player {0} is not controlled on this computer")]
+    NotLocal(usize),
+}
+
+/// Check that an input mapping only maps devices to existing players that are controlled on this
+/// computer, with at most one device per player.
+pub fn validate_input_mapping(
+    mapping: &[Option<usize>; MAX_PLAYERS],
+    player_is_local: &[bool],
+) -> Result<(), RemapInputsError> {
+    let mut mapped = [false; MAX_PLAYERS];
+    for &player_idx in mapping.iter().flatten() {
+        if player_idx >= MAX_PLAYERS {
+            return Err(RemapInputsError::PlayerOutOfRange(player_idx));
+        }
+        if !player_is_local.get(player_idx).copied().unwrap_or(false) {
+            return Err(RemapInputsError::NotLocal(player_idx));
+        }
+        if std::mem::replace(&mut mapped[player_idx], true) {
+            return Err(RemapInputsError::DuplicatePlayer(player_idx));
+        }
+    }
+    Ok(())
 }
 
 /// Implementation of [`SessionRunner`] for local games.
@@ -132,6 +270,15 @@ pub struct LocalSessionRunner {
     pub core: CoreSession,
     pub accumulator: f64,
     pub loop_start: Option<Instant>,
+    /// The player that each local input device controls.
+    pub input_mapping: [Option<usize>; MAX_PLAYERS],
+    /// Records the matches, to save them when replay recording is enabled, to restore snapshots,
+    /// and to [upgrade][crate::networking::upgrade] the match to a network match.
+    pub recorder: Option<ReplayRecorder>,
+    /// Plays back the ghost shown in the match, if there is one.
+    pub ghost: Option<Ghost>,
+    /// A snapshot restored from its replay, simulated again before the next frame.
+    pending_restore: Option<Replay>,
 }
 
 impl LocalSessionRunner {
@@ -140,9 +287,42 @@ impl LocalSessionRunner {
         Self: Sized,
     {
         LocalSessionRunner {
+            recorder: Some(ReplayRecorder::new(&core)),
+            ghost: Ghost::from_config(&core),
             core,
             accumulator: default(),
             loop_start: default(),
+            input_mapping: std::array::from_fn(Some),
+            pending_restore: None,
+        }
+    }
+
+    /// Simulate the match again from the start with the inputs of a replay, to restore a snapshot
+    /// that was taken without its world.
+    fn apply_restore(&mut self, replay: Replay, bevy_world: &mut World) {
+        info!(frames = replay.frames.len(), "Simulating snapshot again");
+        let time_step = self.core.time_step;
+        self.core = CoreSession::new(replay.session_info(self.core.info.meta.clone()));
+        let checksum = replay.play(&mut self.core, bevy_world);
+        if checksum != replay.checksum {
+            warn!(
+                recorded = replay.checksum,
+                checksum, "The restored match is in a different state than in the snapshot"
+            );
+        }
+        self.core.time_step = time_step;
+
+        // The sounds of the simulated frames have been played already
+        self.core
+            .world
+            .run_initialized_system(|mut audio_events: bones::ResMut<bones::AudioEvents>| {
+                audio_events.queue.clear();
+                Ok(())
+            })
+            .unwrap();
+
+        if let Some(recorder) = &mut self.recorder {
+            recorder.resume(replay);
         }
     }
 }
@@ -167,11 +347,43 @@ impl SessionRunner for LocalSessionRunner {
     }
 
     fn restart(&mut self) {
+        self.pending_restore = None;
+        if let Some(recorder) = &mut self.recorder {
+            recorder.finish(&self.core);
+        }
+        if let Some(ghost) = &mut self.ghost {
+            ghost.restart();
+        }
         self.core.restart();
     }
 
+    fn restart_round(&mut self) {
+        self.pending_restore = None;
+        if let Some(recorder) = &mut self.recorder {
+            // The replay can't jump to the restarted round
+            recorder.interrupt(&self.core);
+        }
+        if let Some(ghost) = &mut self.ghost {
+            ghost.restart();
+        }
+        self.core.restart_round();
+    }
+
     fn advance(&mut self, bevy_world: &mut World) -> Result<(), SessionError> {
+        if let Some(replay) = self.pending_restore.take() {
+            self.apply_restore(replay, bevy_world);
+        }
+
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record_frame(&self.core);
+        }
         self.core.advance(bevy_world);
+        if let Some(ghost) = &mut self.ghost {
+            ghost.advance(bevy_world);
+        }
+        if let Ok(mut meter) = LATENCY_METER.lock() {
+            meter.local_frame_simulated();
+        }
 
         Ok(())
     }
@@ -204,6 +416,43 @@ impl SessionRunner for LocalSessionRunner {
     fn network_player_idx(&mut self) -> Option<usize> {
         None
     }
+    fn disconnect_player(&mut self, player_idx: usize) {
+        self.core.update_input(|inputs| {
+            inputs.players[player_idx].active = false;
+        });
+    }
+    fn input_mapping(&mut self) -> [Option<usize>; MAX_PLAYERS] {
+        self.input_mapping
+    }
+    fn remap_inputs(
+        &mut self,
+        mapping: [Option<usize>; MAX_PLAYERS],
+    ) -> Result<(), RemapInputsError> {
+        validate_input_mapping(&mapping, &[true; MAX_PLAYERS])?;
+        self.input_mapping = mapping;
+        Ok(())
+    }
+    fn recorded_replay(&mut self) -> Option<Replay> {
+        self.recorder.as_ref()?.current(&self.core)
+    }
+    fn restore(&mut self, snapshot: SessionSnapshot) {
+        match (snapshot.world, snapshot.replay) {
+            (Some(world), replay) => {
+                self.pending_restore = None;
+                self.core.world = world;
+                if let Some(recorder) = &mut self.recorder {
+                    match replay {
+                        Some(replay) => recorder.resume(replay),
+                        // The replay can't jump to the snapshot
+                        None => recorder.interrupt(&self.core),
+                    }
+                }
+            }
+            // The world is simulated again in the next `advance()`, which has the bevy world
+            (None, Some(replay)) => self.pending_restore = Some(replay),
+            (None, None) => {}
+        }
+    }
 }
 
 // Give bones_bevy_render plugin access to the bones world in our game session.
@@ -230,25 +479,60 @@ impl<'w, 's> SessionManager<'w, 's> {
         self.menu_camera.for_each_mut(|mut x| x.is_active = false);
     }
 
+    /// Start a game session with a custom [`SessionRunner`].
+    pub fn start(&mut self, runner: impl SessionRunner) {
+        self.commands.insert_resource(Session(Box::new(runner)));
+        self.menu_camera.for_each_mut(|mut x| x.is_active = false);
+    }
+
     /// Start a network game session.
-    #[cfg(not(target_arch = "wasm32"))]
     pub fn start_network(
         &mut self,
         core_info: CoreSessionInfo,
         ggrs_info: crate::networking::GgrsSessionRunnerInfo,
     ) {
-        let session = Session(Box::new(crate::networking::GgrsSessionRunner::new(
+        self.start_network_runner(crate::networking::GgrsSessionRunner::new(
             CoreSession::new(core_info),
             ggrs_info,
-        )));
-        self.commands.insert_resource(session);
-        self.menu_camera.for_each_mut(|mut x| x.is_active = false);
+        ));
+    }
+
+    /// Start a network game session with a runner that was already created, such as one joining
+    /// a match in progress.
+    pub fn start_network_runner(&mut self, runner: crate::networking::GgrsSessionRunner) {
+        self.start(runner);
         self.commands
             .insert_resource(NextState(Some(InGameState::Playing)));
         self.commands
             .insert_resource(NextState(Some(EngineState::InGame)));
     }
 
+    /// Take a snapshot of the running match, or `None` if there is no session.
+    pub fn snapshot(&mut self) -> Option<SessionSnapshot> {
+        Some(self.session.as_mut()?.snapshot())
+    }
+
+    /// Restore the running match to the state of a snapshot taken with [`Self::snapshot()`].
+    ///
+    /// The snapshot should have been taken in the same session, unless it was created
+    /// [from its replay][SessionSnapshot::from_replay].
+    pub fn restore(&mut self, snapshot: SessionSnapshot) {
+        if let Some(session) = self.session.as_mut() {
+            session.restore(snapshot);
+        }
+    }
+
+    /// Start a local game session from the [serializable form][SessionSnapshot::replay] of a
+    /// snapshot, to resume a match after the game was suspended.
+    ///
+    /// The match is simulated again up to the snapshot before its first frame is shown.
+    pub fn resume_local(&mut self, replay: Replay) {
+        let core_meta = self.core_meta_arc.0.clone();
+        let mut runner = LocalSessionRunner::new(CoreSession::new(replay.session_info(core_meta)));
+        runner.restore(SessionSnapshot::from_replay(replay));
+        self.start(runner);
+    }
+
     /// Restart a game session without changing the settings
     pub fn restart(&mut self) {
         if let Some(session) = self.session.as_mut() {
@@ -256,6 +540,13 @@ impl<'w, 's> SessionManager<'w, 's> {
         }
     }
 
+    /// Restart the current round of the game session, keeping the score of the previous rounds.
+    pub fn restart_round(&mut self) {
+        if let Some(session) = self.session.as_mut() {
+            session.restart_round();
+        }
+    }
+
     /// Stop a game session
     pub fn stop(&mut self) {
         self.commands.remove_resource::<Session>();
@@ -282,13 +573,84 @@ fn ensure_2_players(mut session: ResMut<Session>, core_meta: Res<CoreMetaArc>) {
     }
 }
 
+/// Don't carry the toggled actions over to a new match.
+fn reset_action_toggles(mut action_toggles: ResMut<LocalActionToggles>) {
+    *action_toggles = default();
+}
+
+/// Pass the presentation settings chosen by the player to the game session.
+fn update_presentation_settings(
+    mut session: ResMut<Session>,
+    game: Res<GameMeta>,
+    mut storage: ResMut<Storage>,
+    mut presentation: Local<Option<PresentationSettings>>,
+) {
+    if presentation.is_none() || storage.is_changed() {
+        // Reading the settings needs mutable access, but doesn't change the storage.
+        let settings = Settings::get_stored_or_default(&game, storage.bypass_change_detection());
+        *presentation = Some(PresentationSettings {
+            reduced_motion: settings.reduced_motion,
+            high_contrast: settings.high_contrast,
+            hit_popups: settings.damage_numbers,
+            ..default()
+        });
+    }
+    let mut presentation = presentation.unwrap();
+
+    // The players controlled by the local input devices see themselves while invisible
+    for player_idx in session.input_mapping().into_iter().flatten() {
+        presentation.local_players[player_idx] = true;
+    }
+
+    let resource = session.world().resource::<PresentationSettings>();
+    *resource.borrow_mut() = presentation;
+}
+
+/// The stick deflection past which moving down crouches.
+const CROUCH_THRESHOLD: f32 = 0.5;
+
+/// Resource containing the state of the toggled actions of each local input device.
+#[derive(Resource, Default, Deref, DerefMut)]
+struct LocalActionToggles([ActionToggles; MAX_PLAYERS]);
+
+/// The state of the toggled actions of a local input device.
+#[derive(Default, Clone, Copy)]
+struct ActionToggles {
+    grab: bool,
+    crouch: bool,
+    /// Whether the grab input was held during the last update.
+    grab_held: bool,
+    /// Whether the down input was held during the last update.
+    down_held: bool,
+}
+
 /// Update the input to the game session.
+///
+/// Actions set to [`ActionMode::Toggle`] are translated to held inputs here, so that the
+/// [`PlayerControl`] is the same whichever mode the player prefers.
 fn collect_local_input(
     mut session: ResMut<Session>,
     player_input_collectors: Query<(&PlayerInputCollector, &ActionState<PlayerAction>)>,
     mut current_editor_input: ResMut<CurrentEditorInput>,
+    game: Res<GameMeta>,
+    mut storage: ResMut<Storage>,
+    mut action_modes: Local<Option<[PlayerActionModes; MAX_PLAYERS]>>,
+    mut action_toggles: ResMut<LocalActionToggles>,
+    in_game_state: Res<State<InGameState>>,
+    mut egui_contexts: EguiContexts,
 ) {
-    let network_player_idx = session.network_player_idx();
+    if action_modes.is_none() || storage.is_changed() {
+        // Reading the settings needs mutable access, but doesn't change the storage.
+        let settings = Settings::get_stored_or_default(&game, storage.bypass_change_detection());
+        *action_modes = Some(settings.action_modes);
+    }
+    let action_modes = action_modes.unwrap();
+
+    let input_mapping = session.input_mapping();
+    let is_typing = egui_contexts.ctx_mut().wants_keyboard_input();
+    if let Ok(mut meter) = LATENCY_METER.lock() {
+        meter.input_collected();
+    }
 
     if let Some(local_session) = session.downcast_mut::<LocalSessionRunner>() {
         // TODO: Handle editor input for non-local sessions.
@@ -298,24 +660,47 @@ fn collect_local_input(
         });
     }
 
-    for (player_idx, action_state) in &player_input_collectors {
+    for (device_idx, action_state) in &player_input_collectors {
+        let Some(player_idx) = input_mapping[device_idx.0] else {
+            continue;
+        };
         let is_ai = {
             let world = &session.core_session().world;
             let inputs = world.resource::<PlayerInputs>();
             let inputs = inputs.borrow();
-            inputs.players[player_idx.0].is_ai
+            inputs.players[player_idx].is_ai
         };
-        if (player_idx.0 != 0 && network_player_idx.is_some()) || is_ai {
+        if is_ai {
+            continue;
+        }
+
+        // Let go of everything while the pause menu or the results screen is open, or while typing
+        // in a text box, such as the chat
+        if in_game_state.0 != InGameState::Playing || is_typing {
+            session.set_player_input(player_idx, default());
             continue;
         }
 
-        let mut control = session.0.get_player_input(player_idx.0);
+        let mut control = session.0.get_player_input(player_idx);
 
         let jump_pressed = action_state.pressed(PlayerAction::Jump);
         control.jump_just_pressed = jump_pressed && !control.jump_pressed;
         control.jump_pressed = jump_pressed;
 
-        let grab_pressed = action_state.pressed(PlayerAction::Grab);
+        let modes = action_modes[device_idx.0];
+        let toggles = &mut action_toggles[device_idx.0];
+
+        let grab_held = action_state.pressed(PlayerAction::Grab);
+        let grab_pressed = match modes.grab {
+            ActionMode::Hold => grab_held,
+            ActionMode::Toggle => {
+                if grab_held && !toggles.grab_held {
+                    toggles.grab = !toggles.grab;
+                }
+                toggles.grab
+            }
+        };
+        toggles.grab_held = grab_held;
         control.grab_just_pressed = grab_pressed && !control.grab_pressed;
         control.grab_pressed = grab_pressed;
 
@@ -325,10 +710,22 @@ fn collect_local_input(
 
         let was_moving = control.move_direction.length_squared() > f32::MIN_POSITIVE;
         control.move_direction = action_state.axis_pair(PlayerAction::Move).unwrap().xy();
+        let down_held = control.move_direction.y < -CROUCH_THRESHOLD;
+        if modes.crouch == ActionMode::Toggle {
+            if down_held && !toggles.down_held {
+                toggles.crouch = !toggles.crouch;
+            } else if control.move_direction.y > CROUCH_THRESHOLD {
+                toggles.crouch = false;
+            }
+            if toggles.crouch {
+                control.move_direction.y = -1.0;
+            }
+        }
+        toggles.down_held = down_held;
         let is_moving = control.move_direction.length_squared() > f32::MIN_POSITIVE;
         control.just_moved = !was_moving && is_moving;
 
-        session.set_player_input(network_player_idx.unwrap_or(player_idx.0), control);
+        session.set_player_input(player_idx, control);
     }
 }
 
@@ -339,21 +736,24 @@ fn update_game(world: &mut World) {
     // Advance the game session
     if let Err(e) = session.advance(world) {
         match e {
-            SessionError::Disconnected => {
-                error!("Network session disconnected");
-                // Don't return the session to the world
-
-                // Go back to the menu
-                let mut cameras = world.query_filtered::<&mut Camera, With<MenuCamera>>();
-                cameras.for_each_mut(world, |mut camera| camera.is_active = true);
-                world.insert_resource(MenuPage::Home);
-                world.insert_resource(NextState(Some(EngineState::MainMenu)));
-                world.insert_resource(NextState(Some(InGameState::Playing)));
-            }
+            SessionError::Disconnected => error!("Network session disconnected"),
+            SessionError::Desync { frame } => error!(%frame, "Network session de-synced"),
         }
+        // Don't return the session to the world
+
+        // Go back to the menu
+        let mut cameras = world.query_filtered::<&mut Camera, With<MenuCamera>>();
+        cameras.for_each_mut(world, |mut camera| camera.is_active = true);
+        world.insert_resource(MenuPage::Home);
+        world.insert_resource(NextState(Some(EngineState::MainMenu)));
+        world.insert_resource(NextState(Some(InGameState::Playing)));
 
     // If the session is OK
     } else {
+        if let Ok(mut meter) = LATENCY_METER.lock() {
+            meter.update_simulated();
+        }
+
         // Return the session to the world
         world.insert_resource(session);
     }
@@ -383,3 +783,38 @@ pub fn play_sounds(audio: Res<AudioChannel<EffectsChannel>>, mut session: ResMut
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_input_mappings_are_rejected() {
+        let mut player_is_local = [false; MAX_PLAYERS];
+        player_is_local[0] = true;
+        player_is_local[1] = true;
+
+        let mut mapping = [None; MAX_PLAYERS];
+        mapping[0] = Some(1);
+        mapping[1] = Some(0);
+        assert_eq!(validate_input_mapping(&mapping, &player_is_local), Ok(()));
+
+        mapping[1] = Some(MAX_PLAYERS);
+        assert_eq!(
+            validate_input_mapping(&mapping, &player_is_local),
+            Err(RemapInputsError::PlayerOutOfRange(MAX_PLAYERS))
+        );
+
+        mapping[1] = Some(2);
+        assert_eq!(
+            validate_input_mapping(&mapping, &player_is_local),
+            Err(RemapInputsError::NotLocal(2))
+        );
+
+        mapping[1] = Some(1);
+        assert_eq!(
+            validate_input_mapping(&mapping, &player_is_local),
+            Err(RemapInputsError::DuplicatePlayer(1))
+        );
+    }
+}
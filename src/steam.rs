@@ -0,0 +1,244 @@
+//! Steam integration.
+//!
+//! This is only compiled with the `steam` cargo feature. If the game is started with the feature
+//! enabled, but Steam isn't running, the integration is simply disabled.
+//!
+//! The integration provides:
+//!
+//! - **Steam Input:** Controllers connected through Steam Input are mapped to the
+//!   [`PlayerAction`]s of the player with the same index, using the `ingame` action set with the
+//!   `move` analog action and the `jump`, `shoot`, `grab`, and `slide` digital actions.
+//! - **Rich presence:** Friends can see whether you are in the menu, in a match, or searching for
+//!   an online match.
+//! - **Invites:** While searching for an online match with a lobby code, friends can be invited
+//!   through the Steam overlay. Accepting an invite opens the network game menu with the lobby code
+//!   filled in, as soon as the player is back in the main menu.
+
+use std::ffi::{c_char, c_void, CStr, CString};
+
+use leafwing_input_manager::{axislike::DualAxisData, plugin::InputManagerSystem};
+use steamworks::{
+    sys::{
+        self, ISteamInput, InputActionSetHandle_t, InputAnalogActionHandle_t,
+        InputDigitalActionHandle_t, InputHandle_t,
+    },
+    Callback, Client, ClientManager, SingleClient,
+};
+
+use crate::{loading::PlayerInputCollector, networking::online::LobbyInvites, prelude::*};
+
+/// Steam plugin.
+pub struct JumpySteamPlugin;
+
+impl Plugin for JumpySteamPlugin {
+    fn build(&self, app: &mut App) {
+        let (client, single) = match Client::init() {
+            Ok(client) => client,
+            Err(e) => {
+                warn!("Steam integration disabled, could not connect to Steam: {e}");
+                return;
+            }
+        };
+
+        // Forward invites accepted in the Steam overlay to the game.
+        let (join_sender, join_receiver) = async_channel::unbounded();
+        let join_callback = client.register_callback(move |request: RichPresenceJoinRequested| {
+            join_sender.try_send(request.connect).ok();
+        });
+
+        let input = SteamInput::new();
+
+        app.insert_resource(SteamClient {
+            client,
+            join_receiver,
+            _join_callback: join_callback,
+        })
+        .insert_resource(input)
+        .insert_non_send_resource(single)
+        .add_system(run_steam_callbacks.in_base_set(CoreSet::First))
+        .add_system(
+            collect_steam_input
+                .in_base_set(CoreSet::PreUpdate)
+                .after(InputManagerSystem::Update),
+        )
+        .add_systems((handle_invites, update_rich_presence));
+    }
+}
+
+/// The prefix of the rich presence connect string, which is passed to the game on the command line
+/// when a friend accepts an invite while the game isn't running.
+const CONNECT_PREFIX: &str = "--lobby-code ";
+
+/// Resource containing the Steam client.
+#[derive(Resource)]
+pub struct SteamClient {
+    pub client: Client<ClientManager>,
+    join_receiver: async_channel::Receiver<String>,
+    _join_callback: steamworks::CallbackHandle<ClientManager>,
+}
+
+/// The `GameRichPresenceJoinRequested_t` callback, sent when the player accepts an invite while
+/// the game is running.
+///
+/// The `steamworks` crate doesn't wrap it, so it is read from the raw bindings.
+struct RichPresenceJoinRequested {
+    connect: String,
+}
+
+unsafe impl Callback for RichPresenceJoinRequested {
+    const ID: i32 = sys::GameRichPresenceJoinRequested_t_k_iCallback as i32;
+    const SIZE: i32 = std::mem::size_of::<sys::GameRichPresenceJoinRequested_t>() as i32;
+
+    unsafe fn from_raw(raw: *mut c_void) -> Self {
+        let val = &*(raw as *const sys::GameRichPresenceJoinRequested_t);
+        Self {
+            connect: CStr::from_ptr(val.m_rgchConnect.as_ptr())
+                .to_string_lossy()
+                .into_owned(),
+        }
+    }
+}
+
+/// Get the Steam Input interface.
+///
+/// The `steamworks` crate doesn't wrap Steam Input, so it is used through the raw bindings. This
+/// must only be called once the [`Client`] has been initialized.
+fn input_interface() -> *mut ISteamInput {
+    // SAFETY: The Steam API has been initialized by `Client::init()`.
+    unsafe { sys::SteamAPI_SteamInput_v006() }
+}
+
+/// The Steam Input action handles.
+#[derive(Resource)]
+struct SteamInput {
+    action_set: InputActionSetHandle_t,
+    move_action: InputAnalogActionHandle_t,
+    digital_actions: [(PlayerAction, InputDigitalActionHandle_t); 4],
+}
+
+impl SteamInput {
+    fn new() -> Self {
+        let input = input_interface();
+        let handle =
+            |name: &str, get: unsafe extern "C" fn(*mut ISteamInput, *const c_char) -> u64| {
+                let name = CString::new(name).unwrap();
+                // SAFETY: The interface is valid, and the name is a nul-terminated string that
+                // outlives the call.
+                unsafe { get(input, name.as_ptr()) }
+            };
+        let digital_action = |name| handle(name, sys::SteamAPI_ISteamInput_GetDigitalActionHandle);
+
+        // SAFETY: The interface is valid. The frames are run by `SingleClient::run_callbacks()`.
+        unsafe { sys::SteamAPI_ISteamInput_Init(input, false) };
+
+        Self {
+            action_set: handle("ingame", sys::SteamAPI_ISteamInput_GetActionSetHandle),
+            move_action: handle("move", sys::SteamAPI_ISteamInput_GetAnalogActionHandle),
+            digital_actions: [
+                (PlayerAction::Jump, digital_action("jump")),
+                (PlayerAction::Shoot, digital_action("shoot")),
+                (PlayerAction::Grab, digital_action("grab")),
+                (PlayerAction::Slide, digital_action("slide")),
+            ],
+        }
+    }
+}
+
+fn run_steam_callbacks(single: NonSend<SingleClient>) {
+    single.run_callbacks();
+}
+
+/// Apply the Steam Input controller state to the player action states.
+fn collect_steam_input(
+    steam_input: Res<SteamInput>,
+    mut player_input_collectors: Query<(&PlayerInputCollector, &mut ActionState<PlayerAction>)>,
+) {
+    let input = input_interface();
+    let mut controllers: [InputHandle_t; sys::STEAM_INPUT_MAX_COUNT as usize] = default();
+    // SAFETY: The interface is valid, and the buffer holds `STEAM_INPUT_MAX_COUNT` handles, as
+    // required.
+    let count = unsafe {
+        sys::SteamAPI_ISteamInput_GetConnectedControllers(input, controllers.as_mut_ptr())
+    };
+    let controllers = &controllers[..count.max(0) as usize];
+
+    for (player_idx, mut action_state) in &mut player_input_collectors {
+        let Some(&controller) = controllers.get(player_idx.0) else {
+            continue;
+        };
+
+        // SAFETY: The interface is valid, and the handles have been returned by Steam Input.
+        let (digital_data, move_data) = unsafe {
+            sys::SteamAPI_ISteamInput_ActivateActionSet(input, controller, steam_input.action_set);
+            (
+                steam_input.digital_actions.map(|(action, handle)| {
+                    let data =
+                        sys::SteamAPI_ISteamInput_GetDigitalActionData(input, controller, handle);
+                    (action, data)
+                }),
+                sys::SteamAPI_ISteamInput_GetAnalogActionData(
+                    input,
+                    controller,
+                    steam_input.move_action,
+                ),
+            )
+        };
+
+        for (action, data) in digital_data {
+            if data.bActive && data.bState {
+                action_state.press(action);
+            }
+        }
+
+        if move_data.bActive && (move_data.x != 0.0 || move_data.y != 0.0) {
+            action_state.press(PlayerAction::Move);
+            action_state.action_data_mut(PlayerAction::Move).axis_pair =
+                Some(DualAxisData::new(move_data.x, move_data.y));
+        }
+    }
+}
+
+/// Open the invite dialog when requested, and pass accepted invites to the network game menu.
+fn handle_invites(steam: Res<SteamClient>, mut lobby_invites: ResMut<LobbyInvites>) {
+    lobby_invites.available = true;
+
+    if lobby_invites.open_invite_dialog {
+        lobby_invites.open_invite_dialog = false;
+        steam.client.friends().activate_game_overlay("friends");
+    }
+
+    while let Ok(connect) = steam.join_receiver.try_recv() {
+        if let Some(lobby_code) = connect.strip_prefix(CONNECT_PREFIX) {
+            info!("Accepted Steam invite to lobby {lobby_code}");
+            lobby_invites.pending_join = Some(lobby_code.trim().to_string());
+        }
+    }
+}
+
+/// Keep the Steam rich presence up-to-date with what the player is doing.
+fn update_rich_presence(
+    steam: Res<SteamClient>,
+    engine_state: Res<State<EngineState>>,
+    lobby_invites: Res<LobbyInvites>,
+    mut last_presence: Local<Option<(String, Option<String>)>>,
+) {
+    let status = match engine_state.0 {
+        EngineState::InGame => "In a match",
+        _ if lobby_invites.current_lobby.is_some() => "Looking for a match",
+        _ => "In the menu",
+    };
+    let connect = lobby_invites
+        .current_lobby
+        .as_ref()
+        .map(|code| format!("{CONNECT_PREFIX}{code}"));
+
+    let presence = (status.to_string(), connect);
+    if last_presence.as_ref() == Some(&presence) {
+        return;
+    }
+
+    let friends = steam.client.friends();
+    friends.set_rich_presence("status", Some(&presence.0));
+    friends.set_rich_presence("connect", presence.1.as_deref());
+    *last_presence = Some(presence);
+}
@@ -0,0 +1,321 @@
+//! Local tournaments.
+//!
+//! Up to [`MAX_PARTICIPANTS`] local participants enter a single-elimination tournament, and are
+//! placed in a [`Bracket`] in the order they entered. Every match of the bracket is a local match
+//! between two participants, played on the map picked for the tournament, and the winner of the
+//! match moves on to the next round of the bracket. The bracket is shown on the tournament page of
+//! the main menu between the matches.
+//!
+//! When the number of participants isn't a power of two, the first participants get a bye in the
+//! first round.
+//!
+//! The tournament is kept in [`Storage`] whenever it changes, and resumed when the game is started
+//! again, so quitting the game between two matches doesn't lose it.
+
+use jumpy_core::rounds::MatchScore;
+
+use crate::{main_menu::MenuPage, prelude::*};
+
+/// Tournament plugin.
+pub struct JumpyTournamentPlugin;
+
+impl Plugin for JumpyTournamentPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActiveTournament>()
+            .add_system(
+                record_tournament_match
+                    .in_schedule(OnEnter(InGameState::Results))
+                    .run_if(resource_exists::<Session>()),
+            )
+            .add_system(load_tournament.in_schedule(OnEnter(EngineState::LoadingGameData)))
+            .add_system(show_tournament_bracket.in_schedule(OnEnter(EngineState::MainMenu)));
+    }
+}
+
+/// The maximum number of participants in a tournament.
+pub const MAX_PARTICIPANTS: usize = 8;
+
+/// The number of rounds a participant must win to win a match of the tournament.
+pub const ROUNDS_TO_WIN: u32 = 2;
+
+/// A match of a [`Bracket`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BracketMatch {
+    /// The indexes of the two participants of the match, or `None` while they are unknown, or if
+    /// the other participant has a bye.
+    pub participants: [Option<usize>; 2],
+    /// The index of the participant that won the match, once it is played.
+    pub winner: Option<usize>,
+}
+
+/// A single-elimination tournament bracket.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bracket {
+    /// The matches of every round, starting from the first round.
+    ///
+    /// The winners of the matches `2 * i` and `2 * i + 1` of a round meet in the match `i` of the
+    /// next round, and the last round is the final.
+    pub rounds: Vec<Vec<BracketMatch>>,
+}
+
+impl Bracket {
+    /// Create the bracket for the given number of participants, seeded in order.
+    pub fn new(participant_count: usize) -> Self {
+        let size = participant_count.next_power_of_two().max(2);
+        let round_count = size.trailing_zeros() as usize;
+
+        let seeds = seed_order(size);
+        let mut rounds = vec![seeds
+            .chunks(2)
+            .map(|pair| BracketMatch {
+                participants: [pair[0], pair[1]].map(|x| (x < participant_count).then_some(x)),
+                winner: None,
+            })
+            .collect::<Vec<_>>()];
+        for round in 1..round_count {
+            rounds.push(vec![default(); size >> (round + 1)]);
+        }
+
+        let mut bracket = Self { rounds };
+
+        // Participants with a bye move on right away
+        for i in 0..bracket.rounds[0].len() {
+            if let [Some(participant), None] | [None, Some(participant)] =
+                bracket.rounds[0][i].participants
+            {
+                bracket.set_winner(0, i, participant);
+            }
+        }
+
+        bracket
+    }
+
+    /// Get the next match to play, as its round and index in the round, or `None` if the
+    /// tournament is over.
+    pub fn next_match(&self) -> Option<(usize, usize)> {
+        self.rounds.iter().enumerate().find_map(|(round, matches)| {
+            matches
+                .iter()
+                .position(|x| x.winner.is_none() && x.participants.iter().all(|x| x.is_some()))
+                .map(|i| (round, i))
+        })
+    }
+
+    /// Set the winner of a match, moving them on to the next round.
+    pub fn set_winner(&mut self, round: usize, match_idx: usize, winner: usize) {
+        self.rounds[round][match_idx].winner = Some(winner);
+        if let Some(next_round) = self.rounds.get_mut(round + 1) {
+            next_round[match_idx / 2].participants[match_idx % 2] = Some(winner);
+        }
+    }
+
+    /// Get the winner of the tournament, once the final has been played.
+    pub fn champion(&self) -> Option<usize> {
+        self.rounds.last()?.first()?.winner
+    }
+}
+
+/// Get the order in which the seeds are placed in a bracket of the given size, so that the best
+/// seeds only meet in the last rounds.
+fn seed_order(size: usize) -> Vec<usize> {
+    let mut order = vec![0];
+    while order.len() < size {
+        let len = order.len() * 2;
+        order = order.iter().flat_map(|&x| [x, len - 1 - x]).collect();
+    }
+    order
+}
+
+/// A participant of a tournament.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TournamentParticipant {
+    /// The name entered for the participant.
+    pub name: String,
+    /// The fighter the participant plays as.
+    pub player: bones::Handle<PlayerMeta>,
+}
+
+/// A local tournament.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Tournament {
+    pub participants: Vec<TournamentParticipant>,
+    pub bracket: Bracket,
+    /// The map every match is played on.
+    pub map_meta: MapMeta,
+    /// The match being played, as its round and index in the bracket.
+    ///
+    /// This isn't stored, since a match interrupted by quitting the game has to be played again.
+    #[serde(skip)]
+    pub current_match: Option<(usize, usize)>,
+}
+
+impl Tournament {
+    /// Start a tournament between the given participants, played on the given map.
+    pub fn new(participants: Vec<TournamentParticipant>, map_meta: MapMeta) -> Self {
+        Self {
+            bracket: Bracket::new(participants.len()),
+            participants,
+            map_meta,
+            current_match: None,
+        }
+    }
+
+    /// Get the names of the participants of a match, or `None` for the participants that are
+    /// unknown.
+    pub fn match_names(&self, bracket_match: &BracketMatch) -> [Option<&str>; 2] {
+        bracket_match
+            .participants
+            .map(|x| x.map(|x| self.participants[x].name.as_str()))
+    }
+
+    /// Get the [`CoreSessionInfo`] used to start the next match, with its first participant as
+    /// the first player, and mark it as the current match.
+    ///
+    /// Returns `None` if the tournament is over.
+    pub fn start_next_match(&mut self, core: &Arc<CoreMeta>) -> Option<CoreSessionInfo> {
+        let (round, match_idx) = self.bracket.next_match()?;
+        self.current_match = Some((round, match_idx));
+
+        let mut player_info = <[Option<GameSessionPlayerInfo>; MAX_PLAYERS]>::default();
+        for (info, participant) in player_info
+            .iter_mut()
+            .zip(self.bracket.rounds[round][match_idx].participants)
+        {
+            *info = participant.map(|x| GameSessionPlayerInfo {
+                player: self.participants[x].player.clone(),
+                hat: None,
+                is_ai: false,
+                ai_difficulty: default(),
+            });
+        }
+
+        Some(CoreSessionInfo {
+            meta: core.clone(),
+            map_meta: self.map_meta.clone(),
+            player_info,
+            match_settings: MatchSettings {
+                rounds_to_win: Some(ROUNDS_TO_WIN),
+                ..default()
+            },
+        })
+    }
+}
+
+/// Resource containing the tournament currently being played, if any.
+#[derive(Resource, Default)]
+pub struct ActiveTournament(pub Option<Tournament>);
+
+impl ActiveTournament {
+    /// The key used to store the tournament in [`Storage`].
+    pub const STORAGE_KEY: &str = "tournament";
+
+    /// Save the tournament to storage, or clear it from storage if there is no tournament anymore.
+    pub fn save(&self, storage: &mut Storage) {
+        storage.set(Self::STORAGE_KEY, &self.0);
+        storage.save();
+    }
+}
+
+/// Resume the tournament that was being played when the game was closed, if any.
+fn load_tournament(mut active_tournament: ResMut<ActiveTournament>, mut storage: ResMut<Storage>) {
+    active_tournament.0 = storage
+        .get::<Option<Tournament>>(ActiveTournament::STORAGE_KEY)
+        .flatten();
+    if let Some(tournament) = &active_tournament.0 {
+        info!(
+            participants = tournament.participants.len(),
+            "Resuming tournament"
+        );
+    }
+}
+
+/// Move the winner of the tournament match that just ended on in the bracket.
+fn record_tournament_match(
+    mut active_tournament: ResMut<ActiveTournament>,
+    mut session: ResMut<Session>,
+    mut storage: ResMut<Storage>,
+) {
+    let Some(tournament) = &mut active_tournament.0 else {
+        return;
+    };
+    // A rematch from the results screen doesn't count
+    let Some((round, match_idx)) = tournament.current_match.take() else {
+        return;
+    };
+
+    let round_winners = session
+        .world()
+        .resource::<MatchScore>()
+        .borrow()
+        .round_winners;
+    let participants = tournament.bracket.rounds[round][match_idx].participants;
+    let Some(winner) = (0..2)
+        .find(|&i| round_winners[i])
+        .and_then(|i| participants[i])
+    else {
+        return;
+    };
+
+    info!(
+        "{} won match {match_idx} of round {round} of the tournament",
+        tournament.participants[winner].name
+    );
+    tournament.bracket.set_winner(round, match_idx, winner);
+    active_tournament.save(&mut storage);
+}
+
+/// Go back to the bracket when leaving a match of a tournament.
+fn show_tournament_bracket(
+    active_tournament: Res<ActiveTournament>,
+    mut menu_page: ResMut<MenuPage>,
+) {
+    if active_tournament.0.is_some() {
+        *menu_page = MenuPage::Tournament;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn top_seeds_meet_in_the_final() {
+        assert_eq!(seed_order(2), [0, 1]);
+        assert_eq!(seed_order(4), [0, 3, 1, 2]);
+        assert_eq!(seed_order(8), [0, 7, 3, 4, 1, 6, 2, 5]);
+    }
+
+    #[test]
+    fn bracket_is_stored() {
+        let mut bracket = Bracket::new(3);
+        bracket.set_winner(0, 1, 2);
+
+        let stored = serde_yaml::to_string(&bracket).unwrap();
+        assert_eq!(serde_yaml::from_str::<Bracket>(&stored).unwrap(), bracket);
+    }
+
+    #[test]
+    fn bracket_with_byes() {
+        let mut bracket = Bracket::new(5);
+        assert_eq!(bracket.rounds.len(), 3);
+
+        // Only the last two seeds play in the first round, and the others have a bye
+        assert_eq!(bracket.next_match(), Some((0, 1)));
+        assert_eq!(bracket.rounds[0][1].participants, [Some(3), Some(4)]);
+        assert_eq!(bracket.rounds[1][0].participants, [Some(0), None]);
+        assert_eq!(bracket.rounds[1][1].participants, [Some(1), Some(2)]);
+        bracket.set_winner(0, 1, 4);
+
+        assert_eq!(bracket.next_match(), Some((1, 0)));
+        assert_eq!(bracket.rounds[1][0].participants, [Some(0), Some(4)]);
+        bracket.set_winner(1, 0, 4);
+        assert_eq!(bracket.next_match(), Some((1, 1)));
+        bracket.set_winner(1, 1, 2);
+
+        assert_eq!(bracket.next_match(), Some((2, 0)));
+        assert_eq!(bracket.champion(), None);
+        bracket.set_winner(2, 0, 2);
+        assert_eq!(bracket.next_match(), None);
+        assert_eq!(bracket.champion(), Some(2));
+    }
+}
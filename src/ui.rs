@@ -13,10 +13,17 @@ pub use self::ui_input::MenuAction;
 pub mod ui_input;
 pub mod widgets;
 
+pub mod afk_warning;
+pub mod coin_counter;
+pub mod ctf_score;
 pub mod debug_tools;
 pub mod editor;
+pub mod kill_feed;
 pub mod main_menu;
+pub mod match_timer;
 pub mod pause_menu;
+pub mod results;
+pub mod scoreboard;
 
 pub struct JumpyUiPlugin;
 
@@ -28,6 +35,13 @@ impl Plugin for JumpyUiPlugin {
             .add_plugin(editor::EditorPlugin)
             .add_plugin(debug_tools::DebugToolsPlugin)
             .add_plugin(pause_menu::PausePlugin)
+            .add_plugin(afk_warning::AfkWarningPlugin)
+            .add_plugin(scoreboard::ScoreboardPlugin)
+            .add_plugin(coin_counter::CoinCounterPlugin)
+            .add_plugin(ctf_score::CtfScorePlugin)
+            .add_plugin(match_timer::MatchTimerPlugin)
+            .add_plugin(kill_feed::KillFeedPlugin)
+            .add_plugin(results::ResultsPlugin)
             .init_resource::<WidgetAdjacencies>()
             .init_resource::<DisableMenuInput>()
             .add_system(
@@ -0,0 +1,82 @@
+//! Overlay warning the local player that they are about to be considered AFK.
+
+use bevy_egui::EguiContexts;
+use bevy_fluent::Localization;
+use jumpy_core::afk::{AfkAction, AfkTimers};
+
+use crate::{announcer::Announcer, prelude::*};
+
+use super::widgets::EguiUiExt;
+
+pub struct AfkWarningPlugin;
+
+impl Plugin for AfkWarningPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            afk_warning
+                .run_if(in_state(EngineState::InGame))
+                .run_if(resource_exists::<Session>()),
+        );
+    }
+}
+
+/// The number of seconds before the player is considered AFK that are counted down aloud by the
+/// [`Announcer`].
+const AFK_COUNTDOWN_SECONDS: u32 = 5;
+
+/// Show the AFK warning when the local player hasn't made any input for a while.
+fn afk_warning(
+    mut contexts: EguiContexts,
+    mut session: ResMut<Session>,
+    game: Res<GameMeta>,
+    localization: Res<Localization>,
+    mut announcer: ResMut<Announcer>,
+    mut announced_seconds: Local<Option<u32>>,
+) {
+    // AFK detection is only relevant for network games.
+    let Some(player_idx) = session.network_player_idx() else {
+        return;
+    };
+
+    let warning = session
+        .world()
+        .run_initialized_system(
+            move |afk_timers: bones::Res<AfkTimers>, match_settings: bones::Res<MatchSettings>| {
+                Ok(afk_timers
+                    .warning(player_idx, &match_settings)
+                    .map(|remaining| (remaining, match_settings.afk_action)))
+            },
+        )
+        .unwrap();
+    let Some((remaining, action)) = warning else {
+        *announced_seconds = None;
+        return;
+    };
+
+    let key = match action {
+        AfkAction::ConvertToAi => "afk-warning-ai",
+        AfkAction::Drop => "afk-warning-drop",
+    };
+    let seconds = remaining.ceil() as u32;
+    let message = localization.get(&format!("{key}?seconds={seconds}"));
+
+    // Read the whole warning once, then count down the last seconds
+    match *announced_seconds {
+        None => announcer.announce(&message),
+        Some(announced) if announced != seconds && seconds <= AFK_COUNTDOWN_SECONDS => {
+            announcer.announce(seconds.to_string())
+        }
+        _ => (),
+    }
+    *announced_seconds = Some(seconds);
+
+    let ui_theme = &game.ui_theme;
+    egui::Area::new("afk-warning")
+        .anchor(
+            egui::Align2::CENTER_TOP,
+            egui::vec2(0.0, ui_theme.font_styles.heading.size),
+        )
+        .show(contexts.ctx_mut(), |ui| {
+            ui.themed_label(&ui_theme.font_styles.bigger, &message);
+        });
+}
@@ -0,0 +1,76 @@
+//! Counter showing the coins collected by each player in arcade mode.
+
+use bevy_egui::EguiContexts;
+use bevy_fluent::Localization;
+use jumpy_core::arcade::ArcadeScore;
+
+use crate::prelude::*;
+
+use super::widgets::{bordered_frame::BorderedFrame, EguiUiExt};
+
+pub struct CoinCounterPlugin;
+
+impl Plugin for CoinCounterPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            coin_counter
+                .run_if(in_state(EngineState::InGame))
+                .run_if(resource_exists::<Session>()),
+        );
+    }
+}
+
+/// Show the coins of the active players in the corner of the screen during an arcade match.
+fn coin_counter(
+    mut contexts: EguiContexts,
+    mut session: ResMut<Session>,
+    game: Res<GameMeta>,
+    localization: Res<Localization>,
+) {
+    let coins = session
+        .world()
+        .run_initialized_system(
+            |arcade_score: bones::Res<ArcadeScore>,
+             match_settings: bones::Res<MatchSettings>,
+             player_inputs: bones::Res<jumpy_core::input::PlayerInputs>| {
+                if !match_settings.arcade {
+                    return Ok(None);
+                }
+                let coins = (0..MAX_PLAYERS)
+                    .filter(|&i| player_inputs.players[i].active)
+                    .map(|i| (i, arcade_score.coins[i]))
+                    .collect::<Vec<_>>();
+                Ok(Some(coins))
+            },
+        )
+        .unwrap();
+    let Some(coins) = coins else {
+        return;
+    };
+
+    let ui_theme = &game.ui_theme;
+    let font = ui_theme
+        .font_styles
+        .normal
+        .colored(ui_theme.panel.font_color);
+
+    egui::Area::new("coin-counter")
+        .anchor(egui::Align2::LEFT_TOP, egui::vec2(10.0, 10.0))
+        .show(contexts.ctx_mut(), |ui| {
+            BorderedFrame::new(&ui_theme.panel.border)
+                .padding(ui_theme.panel.padding.into())
+                .show(ui, |ui| {
+                    ui.themed_label(&font, &localization.get("coins"));
+                    for (player, coins) in coins {
+                        ui.themed_label(
+                            &font,
+                            &localization.get(&format!(
+                                "player-coins?player={}&coins={}",
+                                player + 1,
+                                coins
+                            )),
+                        );
+                    }
+                });
+        });
+}
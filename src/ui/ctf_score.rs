@@ -0,0 +1,70 @@
+//! Counter showing the flags captured by each team in a capture the flag match.
+
+use bevy_egui::EguiContexts;
+use bevy_fluent::Localization;
+use jumpy_core::gamemode::ctf::CtfScore;
+
+use crate::prelude::*;
+
+use super::widgets::{bordered_frame::BorderedFrame, EguiUiExt};
+
+pub struct CtfScorePlugin;
+
+impl Plugin for CtfScorePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            ctf_score
+                .run_if(in_state(EngineState::InGame))
+                .run_if(resource_exists::<Session>()),
+        );
+    }
+}
+
+/// Show the captures of the teams at the top of the screen during a capture the flag match.
+fn ctf_score(
+    mut contexts: EguiContexts,
+    mut session: ResMut<Session>,
+    game: Res<GameMeta>,
+    localization: Res<Localization>,
+) {
+    let captures = session
+        .world()
+        .run_initialized_system(
+            |ctf_score: bones::Res<CtfScore>, match_settings: bones::Res<MatchSettings>| {
+                if match_settings.mode != MatchMode::CaptureTheFlag {
+                    return Ok(None);
+                }
+                Ok(Some(ctf_score.captures))
+            },
+        )
+        .unwrap();
+    let Some(captures) = captures else {
+        return;
+    };
+
+    let ui_theme = &game.ui_theme;
+    let font = ui_theme
+        .font_styles
+        .normal
+        .colored(ui_theme.panel.font_color);
+
+    egui::Area::new("ctf-score")
+        .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 10.0))
+        .show(contexts.ctx_mut(), |ui| {
+            BorderedFrame::new(&ui_theme.panel.border)
+                .padding(ui_theme.panel.padding.into())
+                .show(ui, |ui| {
+                    ui.themed_label(&font, &localization.get("flag-captures"));
+                    for (team, captures) in captures.iter().enumerate() {
+                        ui.themed_label(
+                            &font,
+                            &localization.get(&format!(
+                                "team-captures?team={}&captures={}",
+                                team + 1,
+                                captures
+                            )),
+                        );
+                    }
+                });
+        });
+}
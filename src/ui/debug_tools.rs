@@ -2,12 +2,14 @@ use bevy::{
     diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin},
     prelude::*,
 };
-use bevy_egui::{egui::Checkbox, *};
+use bevy_egui::*;
 use bevy_fluent::Localization;
 
-use crate::prelude::*;
+use crate::{
+    latency::{INPUT_LATENCY, LATENCY_METER, PRESENT_LATENCY},
+    prelude::*,
+};
 
-#[cfg(not(target_arch = "wasm32"))]
 use crate::networking::debug::{network_debug_window, NetworkDebug};
 
 pub struct DebugToolsPlugin;
@@ -24,7 +26,6 @@ impl Plugin for DebugToolsPlugin {
             .add_system(frame_diagnostic_window)
             .add_system(profiler_window);
 
-        #[cfg(not(target_arch = "wasm32"))]
         app.init_resource::<NetworkDebug>().add_system(
             network_debug_window
                 .in_base_set(CoreSet::Last)
@@ -146,16 +147,11 @@ fn debug_tools_window(
                 format!("{} ( F7 )", localization.get("profiler")),
             );
 
-            let networking_enabled = cfg!(not(target_arch = "wasm32"));
             // Show network diagnostics
-            ui.add_enabled(
-                networking_enabled,
-                Checkbox::new(
-                    &mut show_debug_windows.network_debug,
-                    format!("{} ( F6 )", localization.get("network-diagnostics")),
-                ),
-            )
-            .on_disabled_hover_text(localization.get("networking-disabled-message"));
+            ui.checkbox(
+                &mut show_debug_windows.network_debug,
+                format!("{} ( F6 )", localization.get("network-diagnostics")),
+            );
 
             // Snapshot/Restore buttons
             ui.add_space(2.0);
@@ -191,6 +187,10 @@ struct FrameDiagState {
     max_fps: f64,
     min_frame_time: f64,
     max_frame_time: f64,
+    min_input_latency: f64,
+    max_input_latency: f64,
+    min_present_latency: f64,
+    max_present_latency: f64,
 }
 
 impl Default for FrameDiagState {
@@ -200,6 +200,10 @@ impl Default for FrameDiagState {
             max_fps: 0.0,
             min_frame_time: f64::MAX,
             max_frame_time: 0.0,
+            min_input_latency: f64::MAX,
+            max_input_latency: 0.0,
+            min_present_latency: f64::MAX,
+            max_present_latency: 0.0,
         }
     }
 }
@@ -261,6 +265,49 @@ fn frame_diagnostic_window(
                     avg = frame_time.average().unwrap() * 1000.0,
                     max = state.max_frame_time * 1000.0,
                 ));
+
+                ui.add_space(2.0);
+                let Ok(mut meter) = LATENCY_METER.lock() else {
+                    return;
+                };
+                let was_enabled = meter.enabled;
+                ui.checkbox(&mut meter.enabled, localization.get("measure-latency"));
+                if meter.enabled != was_enabled {
+                    meter.reset();
+                }
+                if !meter.enabled {
+                    return;
+                }
+                drop(meter);
+
+                let state = &mut *state;
+                for (label, id, min, max) in [
+                    (
+                        "input-latency",
+                        INPUT_LATENCY,
+                        &mut state.min_input_latency,
+                        &mut state.max_input_latency,
+                    ),
+                    (
+                        "present-latency",
+                        PRESENT_LATENCY,
+                        &mut state.min_present_latency,
+                        &mut state.max_present_latency,
+                    ),
+                ] {
+                    let latency = diagnostics.get(id).unwrap();
+                    let (Some(value), Some(avg)) = (latency.value(), latency.average()) else {
+                        continue;
+                    };
+                    *min = min.min(value);
+                    *max = max.max(value);
+
+                    ui.monospace(&format!(
+                        "{label:20}: {value:4.1}{suffix:3} ( {min:4.1}{suffix:3}, {avg:4.1}{suffix:3}, {max:4.1}{suffix:3} )",
+                        label = localization.get(label),
+                        suffix = latency.suffix,
+                    ));
+                }
             });
     }
 }
@@ -7,6 +7,7 @@ use bones_bevy_renderer::BevyBonesEntity;
 use jumpy_core::{
     input::{ElementLayer, TileLayer},
     physics::TileCollisionKind,
+    teams::TEAM_COUNT,
 };
 use std::marker::PhantomData;
 
@@ -20,7 +21,9 @@ impl Plugin for EditorPlugin {
                     .run_if(in_state(EngineState::InGame))
                     .run_if(in_state(GameEditorState::Visible)),
             )
-            .add_system(cleanup_editor.in_schedule(OnExit(GameEditorState::Visible)));
+            .add_system(cleanup_editor.in_schedule(OnExit(GameEditorState::Visible)))
+            .add_system(stop_element_preview.in_schedule(OnExit(GameEditorState::Visible)))
+            .add_system(stop_element_preview.in_schedule(OnExit(EngineState::InGame)));
     }
 }
 
@@ -39,9 +42,23 @@ struct EditorState {
     pub current_collision: TileCollisionKind,
     pub current_tool: EditorTool,
     pub camera: EditorCameraPos,
+    /// The rectangle of tiles selected with the prefab tool, with an exclusive maximum.
+    pub prefab_selection: Option<(UVec2, UVec2)>,
+    /// The prefab that will be stamped when clicking on the map with the prefab tool.
+    pub current_prefab: Option<MapPrefabMeta>,
+    /// The element being previewed, if any.
+    pub preview: Option<ElementPreview>,
     // pub hidden_layers: HashSet<usize>,
 }
 
+/// An element being previewed on its own, with the rest of the map and the players removed.
+struct ElementPreview {
+    /// The name of the previewed element.
+    element_name: String,
+    /// The match before the preview started, restored when it stops.
+    snapshot: SessionSnapshot,
+}
+
 impl Default for EditorState {
     fn default() -> Self {
         Self {
@@ -51,6 +68,9 @@ impl Default for EditorState {
             current_collision: TileCollisionKind::Solid,
             current_tool: Default::default(),
             camera: Default::default(),
+            prefab_selection: None,
+            current_prefab: None,
+            preview: None,
         }
     }
 }
@@ -62,10 +82,19 @@ impl UserMapStorage {
     pub const STORAGE_KEY: &str = "user_maps";
 }
 
+/// The prefabs created in the editor, by name.
+#[derive(Serialize, Deserialize, Clone, Default, Deref, DerefMut)]
+pub struct UserPrefabStorage(pub HashMap<String, MapPrefabMeta>);
+
+impl UserPrefabStorage {
+    pub const STORAGE_KEY: &str = "user_prefabs";
+}
+
 fn tile_collision_color(collision: TileCollisionKind) -> egui::Color32 {
     match collision {
         TileCollisionKind::Solid => egui::Color32::LIGHT_GRAY.linear_multiply(0.68),
         TileCollisionKind::JumpThrough => egui::Color32::GOLD,
+        TileCollisionKind::Rail => egui::Color32::DARK_RED,
         _ => egui::Color32::BLACK,
     }
 }
@@ -95,6 +124,7 @@ enum EditorTool {
     Element,
     Tile,
     Collision,
+    Prefab,
 }
 
 impl EditorTool {
@@ -103,6 +133,7 @@ impl EditorTool {
             EditorTool::Element => egui::CursorIcon::Default,
             EditorTool::Tile => egui::CursorIcon::Crosshair,
             EditorTool::Collision => egui::CursorIcon::Default,
+            EditorTool::Prefab => egui::CursorIcon::Crosshair,
         }
     }
 }
@@ -137,6 +168,48 @@ pub fn cleanup_editor(session: Option<ResMut<Session>>) {
     }
 }
 
+/// Start previewing an element, removing the rest of the map and the players until the preview is
+/// stopped.
+fn start_element_preview(
+    session: &mut Session,
+    element: bones::Entity,
+    element_name: String,
+) -> ElementPreview {
+    let snapshot = session.snapshot();
+
+    session
+        .world()
+        .run_initialized_system(move |mut map_manager: jumpy_core::editor::MapManager| {
+            map_manager.isolate_element(element);
+        })
+        .ok();
+    session
+        .world()
+        .run_initialized_system(
+            |mut commands: bones::Commands,
+             entities: bones::Res<bones::Entities>,
+             player_indexes: bones::Comp<jumpy_core::player::PlayerIdx>| {
+                for (player, _) in entities.iter_with(&player_indexes) {
+                    commands.add(jumpy_core::player::PlayerCommand::despawn(player));
+                }
+            },
+        )
+        .ok();
+
+    ElementPreview {
+        element_name,
+        snapshot,
+    }
+}
+
+/// Stop the element preview, restoring the match to how it was before.
+fn stop_element_preview(session: Option<ResMut<Session>>, mut state: ResMut<EditorState>) {
+    let Some(preview) = state.preview.take() else { return };
+    if let Some(mut session) = session {
+        session.restore(preview.snapshot);
+    }
+}
+
 pub fn editor_ui_system(world: &mut World) {
     // Force set the camera position
     {
@@ -184,13 +257,14 @@ pub fn editor_ui_system(world: &mut World) {
             })
     };
 
-    // Get the up-to-date map meta export from the world
-    let map_meta = {
-        world
+    // Get the up-to-date map meta export from the world, unless most of it has been removed to
+    // preview an element
+    if world.resource::<EditorState>().preview.is_none() {
+        let map_meta = world
             .get_resource_mut::<Session>()
-            .map(|mut sess| sess.core_session().export_map())
-    };
-    world.insert_resource(EditorMapExport(map_meta));
+            .map(|mut sess| sess.core_session().export_map());
+        world.insert_resource(EditorMapExport(map_meta));
+    }
 
     let mut state = world.resource_mut::<EditorState>();
     state.cursor.current_pos = cursor_pos;
@@ -251,6 +325,7 @@ struct EditorTopBar<'w, 's> {
     clipboard: ResMut<'w, bevy_egui::EguiClipboard>,
     map_export: Res<'w, EditorMapExport>,
     storage: ResMut<'w, Storage>,
+    editor_input: ResMut<'w, CurrentEditorInput>,
 }
 
 impl<'w, 's> WidgetSystem for EditorTopBar<'w, 's> {
@@ -338,6 +413,7 @@ impl<'w, 's> WidgetSystem for EditorTopBar<'w, 's> {
                             meta: params.core_meta.0.clone(),
                             map_meta: params.map_export.0.as_ref().unwrap().clone(),
                             player_info: default(),
+                            match_settings: default(),
                         });
                         params
                             .commands
@@ -348,6 +424,11 @@ impl<'w, 's> WidgetSystem for EditorTopBar<'w, 's> {
                             && ui.input(|i| i.modifiers.command))
                     {
                         if let Some(map) = params.map_export.0.as_ref() {
+                            // Bump the version so that outdated copies of the map can be told apart
+                            let map = MapMeta {
+                                version: map.version + 1,
+                                ..map.clone()
+                            };
                             let mut user_maps: UserMapStorage = params
                                 .storage
                                 .get(UserMapStorage::STORAGE_KEY)
@@ -355,6 +436,12 @@ impl<'w, 's> WidgetSystem for EditorTopBar<'w, 's> {
                             user_maps.insert(map.name.clone(), map.clone());
                             params.storage.set(UserMapStorage::STORAGE_KEY, &user_maps);
                             params.storage.save();
+                            **params.editor_input = Some(EditorInput::SetMapInfo {
+                                author: map.author,
+                                description: map.description,
+                                version: map.version,
+                                recommended_players: map.recommended_players,
+                            });
                         }
                     }
                 });
@@ -457,11 +544,17 @@ impl<'w, 's> WidgetSystem for EditorLeftToolbar<'w, 's> {
         let mut params: EditorLeftToolbar = state.get_mut(world);
         let icons = &params.game.ui_theme.editor.icons;
         let width = ui.available_width();
-        for tool in [EditorTool::Element, EditorTool::Tile, EditorTool::Collision] {
+        for tool in [
+            EditorTool::Element,
+            EditorTool::Tile,
+            EditorTool::Collision,
+            EditorTool::Prefab,
+        ] {
             let (image, hover_text) = match tool {
                 EditorTool::Element => (&icons.elements, params.localization.get("elements")),
                 EditorTool::Tile => (&icons.tiles, params.localization.get("tiles")),
                 EditorTool::Collision => (&icons.collisions, params.localization.get("collisions")),
+                EditorTool::Prefab => (&icons.select, params.localization.get("prefabs")),
             };
             ui.add_space(ui.spacing().window_margin.top);
 
@@ -490,12 +583,14 @@ struct LayerCreateInfo {
 struct EditorRightToolbar<'w, 's> {
     show_layer_create: Local<'s, bool>,
     layer_create_info: Local<'s, LayerCreateInfo>,
+    prefab_name: Local<'s, String>,
     game: Res<'w, GameMeta>,
     localization: Res<'w, Localization>,
     state: ResMut<'w, EditorState>,
     editor_input: ResMut<'w, CurrentEditorInput>,
     map_export: Res<'w, EditorMapExport>,
     tilesets: Res<'w, MapTilesetEguiTextures>,
+    storage: ResMut<'w, Storage>,
 }
 
 impl<'w, 's> WidgetSystem for EditorRightToolbar<'w, 's> {
@@ -546,6 +641,74 @@ impl<'w, 's> WidgetSystem for EditorRightToolbar<'w, 's> {
                             }
                         });
                     });
+
+                    let mut author = map.author.clone();
+                    let mut description = map.description.clone();
+                    let mut recommended_players = map.recommended_players;
+                    body.row(row_height, |mut row| {
+                        row.col(|ui| {
+                            ui.label(&params.localization.get("author"));
+                        });
+                        row.col(|ui| {
+                            egui::TextEdit::singleline(&mut author)
+                                .desired_width(ui.available_width() * 0.9)
+                                .show(ui);
+                        });
+                    });
+                    body.row(row_height, |mut row| {
+                        row.col(|ui| {
+                            ui.label(&params.localization.get("description"));
+                        });
+                        row.col(|ui| {
+                            egui::TextEdit::singleline(&mut description)
+                                .desired_width(ui.available_width() * 0.9)
+                                .show(ui);
+                        });
+                    });
+                    body.row(row_height, |mut row| {
+                        row.col(|ui| {
+                            ui.label(&params.localization.get("recommended-players"));
+                        });
+                        row.col(|ui| {
+                            let mut recommended = recommended_players.is_some();
+                            if ui.checkbox(&mut recommended, "").changed() {
+                                recommended_players = recommended.then(default);
+                            }
+                            if let Some(players) = &mut recommended_players {
+                                let max_players = MAX_PLAYERS as u32;
+                                ui.add(
+                                    egui::DragValue::new(&mut players.min)
+                                        .clamp_range(1..=max_players),
+                                );
+                                ui.label("-");
+                                ui.add(
+                                    egui::DragValue::new(&mut players.max)
+                                        .clamp_range(players.min..=max_players),
+                                );
+                                players.max = players.max.max(players.min);
+                            }
+                        });
+                    });
+                    body.row(row_height, |mut row| {
+                        row.col(|ui| {
+                            ui.label(&params.localization.get("version"));
+                        });
+                        row.col(|ui| {
+                            ui.label(map.version.to_string());
+                        });
+                    });
+
+                    if author != map.author
+                        || description != map.description
+                        || recommended_players != map.recommended_players
+                    {
+                        **params.editor_input = Some(EditorInput::SetMapInfo {
+                            author,
+                            description,
+                            version: map.version,
+                            recommended_players,
+                        });
+                    }
                 }
 
                 body.row(row_height, |mut row| {
@@ -658,6 +821,14 @@ impl<'w, 's> WidgetSystem for EditorRightToolbar<'w, 's> {
 
                         response = response.context_menu(|ui| {
                             params.state.current_layer_idx = i;
+                            layer_foreground_settings(
+                                ui,
+                                &params.localization,
+                                &mut params.editor_input,
+                                i,
+                                layer,
+                            );
+                            ui.separator();
                             if ui
                                 .button(&format!("🗑 {}", params.localization.get("delete-layer")))
                                 .clicked()
@@ -824,6 +995,7 @@ impl<'w, 's> WidgetSystem for EditorRightToolbar<'w, 's> {
                     TileCollisionKind::JumpThrough,
                     params.localization.get("jump-through"),
                 ),
+                (TileCollisionKind::Rail, params.localization.get("rail")),
                 (TileCollisionKind::Empty, params.localization.get("empty")),
             ] {
                 let color = tile_collision_color(collision);
@@ -839,9 +1011,14 @@ impl<'w, 's> WidgetSystem for EditorRightToolbar<'w, 's> {
             }
         }
 
+        // Prefab section
+        if params.state.current_tool == EditorTool::Prefab {
+            prefab_palette(ui, &mut params);
+        }
+
         // Tilemap section
         if params.state.current_tool == EditorTool::Tile {
-            if let Some(map_meta) = map_meta {
+            if let Some(map_meta) = params.map_export.0.as_ref() {
                 if map_meta.layers.is_empty() {
                     return;
                 }
@@ -961,6 +1138,142 @@ impl<'w, 's> WidgetSystem for EditorRightToolbar<'w, 's> {
     }
 }
 
+/// Render the list of prefabs, and the form to create a prefab from the selected tiles.
+fn prefab_palette(ui: &mut egui::Ui, params: &mut EditorRightToolbar) {
+    let Some(map_meta) = params.map_export.0.as_ref() else { return };
+
+    ui.separator();
+    ui.horizontal(|ui| {
+        ui.label(&params.localization.get("prefabs"));
+    });
+    ui.separator();
+
+    ui.label(egui::RichText::new(params.localization.get("prefab-hint")).small());
+    ui.horizontal(|ui| {
+        ui.add(
+            egui::TextEdit::singleline(&mut *params.prefab_name)
+                .hint_text(params.localization.get("name"))
+                .desired_width(ui.available_width() * 0.6),
+        );
+
+        let selection = params.state.prefab_selection;
+        let layer = map_meta.layers.get(params.state.current_layer_idx);
+        let can_create = selection.is_some() && layer.is_some() && !params.prefab_name.is_empty();
+        if ui
+            .add_enabled(
+                can_create,
+                egui::Button::new(params.localization.get("create")),
+            )
+            .clicked()
+        {
+            let (min, max) = selection.unwrap();
+            let prefab = MapPrefabMeta::from_layer(
+                params.prefab_name.clone(),
+                layer.unwrap(),
+                map_meta.tile_size,
+                min,
+                max,
+            );
+
+            let mut prefabs: UserPrefabStorage = params
+                .storage
+                .get(UserPrefabStorage::STORAGE_KEY)
+                .unwrap_or_default();
+            prefabs.insert(prefab.name.clone(), prefab.clone());
+            params.storage.set(UserPrefabStorage::STORAGE_KEY, &prefabs);
+            params.storage.save();
+
+            params.state.current_prefab = Some(prefab);
+            params.state.prefab_selection = None;
+            params.prefab_name.clear();
+        }
+    });
+    ui.add_space(ui.spacing().item_spacing.y);
+
+    let mut prefabs: UserPrefabStorage = params
+        .storage
+        .get(UserPrefabStorage::STORAGE_KEY)
+        .unwrap_or_default();
+    let mut names = prefabs.keys().cloned().collect::<Vec<_>>();
+    names.sort();
+
+    let mut deleted = None;
+    for name in names {
+        ui.horizontal(|ui| {
+            let selected = params.state.current_prefab.as_ref().map(|x| &x.name) == Some(&name);
+            if ui.selectable_label(selected, &name).clicked() {
+                params.state.current_prefab = prefabs.get(&name).cloned();
+            }
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui
+                    .button("🗑")
+                    .on_hover_text(params.localization.get("delete"))
+                    .clicked()
+                {
+                    deleted = Some(name.clone());
+                }
+            });
+        });
+    }
+
+    if let Some(name) = deleted {
+        prefabs.remove(&name);
+        params.storage.set(UserPrefabStorage::STORAGE_KEY, &prefabs);
+        params.storage.save();
+
+        if params.state.current_prefab.as_ref().map(|x| &x.name) == Some(&name) {
+            params.state.current_prefab = None;
+        }
+    }
+}
+
+/// Render the settings for rendering a layer in front of the players.
+fn layer_foreground_settings(
+    ui: &mut egui::Ui,
+    localization: &Localization,
+    editor_input: &mut CurrentEditorInput,
+    layer_idx: usize,
+    layer: &MapLayerMeta,
+) {
+    let mut foreground = layer.foreground;
+
+    let mut is_foreground = foreground.is_some();
+    if ui
+        .checkbox(&mut is_foreground, localization.get("foreground-layer"))
+        .changed()
+    {
+        foreground = is_foreground.then(default);
+    }
+
+    if let Some(meta) = &mut foreground {
+        ui.add(
+            egui::Slider::new(&mut meta.parallax, 0.0..=0.5)
+                .text(localization.get("foreground-parallax")),
+        );
+
+        let mut fade = meta.fade_alpha.is_some();
+        if ui
+            .checkbox(&mut fade, localization.get("foreground-fade"))
+            .changed()
+        {
+            meta.fade_alpha = fade.then_some(0.4);
+        }
+        if let Some(alpha) = &mut meta.fade_alpha {
+            ui.add(
+                egui::Slider::new(alpha, 0.0..=1.0).text(localization.get("foreground-fade-alpha")),
+            );
+        }
+    }
+
+    if foreground != layer.foreground {
+        **editor_input = Some(EditorInput::SetLayerForeground {
+            layer: layer_idx as u8,
+            foreground,
+        });
+    }
+}
+
 fn layer_create_dialog(ui: &mut egui::Ui, params: &mut EditorRightToolbar) {
     let space = ui.spacing().icon_width;
 
@@ -1081,6 +1394,26 @@ impl<'w, 's> WidgetSystem for EditorCentralPanel<'w, 's> {
             ui.set_enabled(false);
         }
 
+        // The map can't be edited during a preview
+        if let Some(preview) = &params.state.preview {
+            let mut stop = false;
+            ui.vertical_centered(|ui| {
+                ui.label(format!(
+                    "{}: {}",
+                    params.localization.get("previewing-element"),
+                    preview.element_name
+                ));
+                stop = ui
+                    .button(&format!("⏹ {}", params.localization.get("stop-preview")))
+                    .clicked();
+            });
+            if stop {
+                let preview = params.state.preview.take().unwrap();
+                params.session_manager.restore(preview.snapshot);
+            }
+            return;
+        }
+
         if let Some(mut session) = params.session_manager.session {
             let Ok((camera, camera_transform, _)) = params.camera.get_single() else { return };
             let Some(map) = params.map.0.as_ref() else { return; };
@@ -1148,6 +1481,12 @@ impl<'w, 's> WidgetSystem for EditorCentralPanel<'w, 's> {
                         |entities: bones::Res<bones::Entities>,
                          transforms: bones::Comp<bones::Transform>,
                          element_handles: bones::Comp<jumpy_core::elements::ElementHandle>,
+                         element_orientations: bones::Comp<
+                            jumpy_core::elements::ElementOrientation,
+                        >,
+                         spawn_points: bones::Comp<SpawnPointMeta>,
+                         wire_anchors: bones::Comp<WireAnchorMeta>,
+                         links: bones::Comp<LinkMeta>,
                          spawned_map_layer_metas: bones::Comp<
                             jumpy_core::map::SpawnedMapLayerMeta,
                         >| {
@@ -1163,6 +1502,10 @@ impl<'w, 's> WidgetSystem for EditorCentralPanel<'w, 's> {
                                         handle.get_bevy_handle(),
                                         transform.translation,
                                         layer.layer_idx,
+                                        element_orientations.get(ent).copied().unwrap_or_default(),
+                                        spawn_points.get(ent).copied(),
+                                        wire_anchors.get(ent).copied(),
+                                        links.get(ent).copied(),
                                     )
                                 })
                                 .collect::<Vec<_>>())
@@ -1238,7 +1581,17 @@ impl<'w, 's> WidgetSystem for EditorCentralPanel<'w, 's> {
                 });
 
                 // Selectable element rendering and handling
-                for (entity, handle, translation, layer_idx) in elements {
+                for (
+                    entity,
+                    handle,
+                    translation,
+                    layer_idx,
+                    orientation,
+                    spawn_point,
+                    wire_anchor,
+                    link,
+                ) in elements
+                {
                     if layer_idx != params.state.current_layer_idx {
                         continue;
                     }
@@ -1265,6 +1618,112 @@ impl<'w, 's> WidgetSystem for EditorCentralPanel<'w, 's> {
                         .allocate_rect(rect, egui::Sense::click_and_drag())
                         .context_menu(|ui| {
                             color_override = Some(egui::Color32::RED);
+                            let mut new_orientation = None;
+                            if ui
+                                .button(&format!("↔ {}", params.localization.get("flip-element-x")))
+                                .clicked()
+                            {
+                                new_orientation = Some(jumpy_core::elements::ElementOrientation {
+                                    flip_x: !orientation.flip_x,
+                                    ..orientation
+                                });
+                            }
+                            if ui
+                                .button(&format!("↕ {}", params.localization.get("flip-element-y")))
+                                .clicked()
+                            {
+                                new_orientation = Some(jumpy_core::elements::ElementOrientation {
+                                    flip_y: !orientation.flip_y,
+                                    ..orientation
+                                });
+                            }
+                            if ui
+                                .button(&format!(
+                                    "🔄 {}",
+                                    params.localization.get("rotate-element")
+                                ))
+                                .clicked()
+                            {
+                                new_orientation = Some(jumpy_core::elements::ElementOrientation {
+                                    rotation: (orientation.rotation + 1) % 4,
+                                    ..orientation
+                                });
+                            }
+                            if let Some(new_orientation) = new_orientation {
+                                ui.close_menu();
+                                **params.editor_input = Some(EditorInput::OrientEntity {
+                                    entity,
+                                    flip_x: new_orientation.flip_x,
+                                    flip_y: new_orientation.flip_y,
+                                    rotation: new_orientation.rotation,
+                                });
+                            }
+                            if let BuiltinElementKind::PlayerSpawner = element_meta.builtin {
+                                ui.separator();
+                                let mut new_spawn_point = spawn_point.unwrap_or_default();
+                                spawn_point_ui(ui, &params.localization, &mut new_spawn_point);
+                                if Some(new_spawn_point) != spawn_point {
+                                    **params.editor_input = Some(EditorInput::SetSpawnPoint {
+                                        entity,
+                                        spawn_point: (new_spawn_point != default())
+                                            .then_some(new_spawn_point),
+                                    });
+                                }
+                                ui.separator();
+                            }
+                            if let BuiltinElementKind::TripWire { default_anchor, .. } =
+                                &element_meta.builtin
+                            {
+                                ui.separator();
+                                let mut new_wire_anchor = wire_anchor.unwrap_or(WireAnchorMeta {
+                                    offset: *default_anchor,
+                                });
+                                wire_anchor_ui(ui, &params.localization, &mut new_wire_anchor);
+                                if new_wire_anchor.offset
+                                    != wire_anchor.map_or(*default_anchor, |x| x.offset)
+                                {
+                                    **params.editor_input = Some(EditorInput::SetWireAnchor {
+                                        entity,
+                                        wire_anchor: (new_wire_anchor.offset != *default_anchor)
+                                            .then_some(new_wire_anchor),
+                                    });
+                                }
+                                ui.separator();
+                            }
+                            if let BuiltinElementKind::Elevator { .. }
+                            | BuiltinElementKind::ElevatorCallButton { .. } =
+                                &element_meta.builtin
+                            {
+                                ui.separator();
+                                let mut new_link = link.unwrap_or_default();
+                                link_ui(ui, &params.localization, &mut new_link);
+                                if new_link != link.unwrap_or_default() {
+                                    **params.editor_input = Some(EditorInput::SetLink {
+                                        entity,
+                                        link: (new_link != default()).then_some(new_link),
+                                    });
+                                }
+                                ui.separator();
+                            }
+                            // Snapshots can't be restored in network games
+                            let can_preview = session.network_player_idx().is_none();
+                            if ui
+                                .add_enabled(
+                                    can_preview,
+                                    egui::Button::new(format!(
+                                        "▶ {}",
+                                        params.localization.get("preview-element")
+                                    )),
+                                )
+                                .clicked()
+                            {
+                                ui.close_menu();
+                                params.state.preview = Some(start_element_preview(
+                                    &mut session,
+                                    entity,
+                                    element_meta.name.clone(),
+                                ));
+                            }
                             if ui
                                 .button(&format!("🗑 {}", params.localization.get("delete-element")))
                                 .clicked()
@@ -1465,6 +1924,74 @@ impl<'w, 's> WidgetSystem for EditorCentralPanel<'w, 's> {
                         }
                     }
                 }
+
+            // Prefab tool
+            } else if params.state.current_tool == EditorTool::Prefab {
+                let world_to_screen = |pos: Vec2| {
+                    camera
+                        .world_to_ndc(&(*camera_transform).into(), pos.extend(0.0))
+                        .map(|ndc| {
+                            let ndc = (ndc + 1.0) / 2.0;
+                            egui::pos2(window_size.x * ndc.x, window_size.y - window_size.y * ndc.y)
+                        })
+                };
+                let tile_rect = |min: UVec2, max: UVec2| {
+                    let bottom_left = world_to_screen(min.as_vec2() * map.tile_size)?;
+                    let top_right = world_to_screen(max.as_vec2() * map.tile_size)?;
+                    Some(egui::Rect::from_two_pos(bottom_left, top_right))
+                };
+
+                let cursor_tile = params.state.cursor.current_pos.and_then(|pos| {
+                    let tile = (pos / map.tile_size).floor();
+                    let in_map =
+                        tile.cmpge(Vec2::ZERO).all() && tile.cmplt(map.grid_size.as_vec2()).all();
+                    in_map.then(|| tile.as_uvec2())
+                });
+
+                // Select the tiles to create a prefab from by dragging, and stamp the current
+                // prefab by clicking.
+                #[derive(Clone)]
+                struct PrefabSelectionStart(UVec2);
+                let drag_id = egui::Id::from("prefab_selection");
+                let ctrl_modifier = ui.input(|i| i.modifiers.command);
+                if let Some(cursor_tile) = cursor_tile.filter(|_| !ctrl_modifier) {
+                    if map_response.drag_started() {
+                        ui.data_mut(|d| d.insert_temp(drag_id, PrefabSelectionStart(cursor_tile)));
+                    }
+                    if map_response.dragged_by(egui::PointerButton::Primary) {
+                        if let Some(PrefabSelectionStart(start)) =
+                            ui.data_mut(|d| d.get_temp(drag_id))
+                        {
+                            params.state.prefab_selection =
+                                Some((start.min(cursor_tile), start.max(cursor_tile) + UVec2::ONE));
+                        }
+                    }
+                    if map_response.clicked() {
+                        if let Some(prefab) = &params.state.current_prefab {
+                            **params.editor_input = Some(EditorInput::StampPrefab {
+                                prefab: prefab.clone(),
+                                layer: params.state.current_layer_idx as u8,
+                                pos: cursor_tile,
+                            });
+                        }
+                    }
+                }
+                if map_response.drag_released() {
+                    ui.data_mut(|d| d.remove::<PrefabSelectionStart>(drag_id));
+                }
+
+                let mut painter = ui.painter_at(map_response_rect);
+                painter.set_clip_rect(map_response_rect);
+                if let Some(rect) = params
+                    .state
+                    .prefab_selection
+                    .and_then(|(min, max)| tile_rect(min, max))
+                {
+                    painter.rect_stroke(rect, 1.0, (1.5, egui::Color32::GREEN));
+                }
+                if let Some(rect) = cursor_tile.and_then(|x| tile_rect(x, x + UVec2::ONE)) {
+                    painter.rect_stroke(rect, 1.0, ui.visuals().widgets.active.fg_stroke);
+                }
             };
 
         // If there is no current map
@@ -1536,6 +2063,7 @@ fn map_open_dialog(ui: &mut egui::Ui, params: &mut EditorCentralPanel) {
                                 meta: params.core_meta.0.clone(),
                                 map_meta: (*map_meta).clone(),
                                 player_info: default(),
+                                match_settings: default(),
                             });
                             *params.show_map_open = false;
                         }
@@ -1574,6 +2102,7 @@ fn map_open_dialog(ui: &mut egui::Ui, params: &mut EditorCentralPanel) {
                                         meta: params.core_meta.0.clone(),
                                         map_meta: map_meta.clone(),
                                         player_info: default(),
+                                        match_settings: default(),
                                     });
                                     *params.show_map_open = false;
                                 };
@@ -1729,3 +2258,57 @@ fn overlay_window<R, F: FnOnce(&mut egui::Ui) -> R>(
         response: i.response,
     }
 }
+
+/// Render the attributes of a player spawner in its context menu.
+fn spawn_point_ui(
+    ui: &mut egui::Ui,
+    localization: &Localization,
+    spawn_point: &mut SpawnPointMeta,
+) {
+    let team_label = |team: Option<u8>| match team {
+        Some(team) => localization.get(&format!("spawn-point-team?team={}", team + 1)),
+        None => localization.get("spawn-point-no-team"),
+    };
+    ui.menu_button(team_label(spawn_point.team), |ui| {
+        for team in std::iter::once(None).chain((0..TEAM_COUNT as u8).map(Some)) {
+            if ui
+                .selectable_label(spawn_point.team == team, team_label(team))
+                .clicked()
+            {
+                spawn_point.team = team;
+                ui.close_menu();
+            }
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label(localization.get("spawn-point-priority"));
+        ui.add(egui::DragValue::new(&mut spawn_point.priority).clamp_range(-10..=10));
+    });
+    ui.checkbox(
+        &mut spawn_point.face_left,
+        localization.get("spawn-point-face-left"),
+    );
+}
+
+/// Render the far anchor point of a trip wire in its context menu.
+fn wire_anchor_ui(
+    ui: &mut egui::Ui,
+    localization: &Localization,
+    wire_anchor: &mut WireAnchorMeta,
+) {
+    ui.label(localization.get("wire-anchor"));
+    ui.horizontal(|ui| {
+        ui.label("X");
+        ui.add(egui::DragValue::new(&mut wire_anchor.offset.x).speed(1.0));
+        ui.label("Y");
+        ui.add(egui::DragValue::new(&mut wire_anchor.offset.y).speed(1.0));
+    });
+}
+
+/// Render the link channel of an element in its context menu.
+fn link_ui(ui: &mut egui::Ui, localization: &Localization, link: &mut LinkMeta) {
+    ui.horizontal(|ui| {
+        ui.label(localization.get("link-channel"));
+        ui.add(egui::DragValue::new(&mut link.channel).clamp_range(0..=99));
+    });
+}
@@ -0,0 +1,71 @@
+//! Feed listing the recent kills in the corner of the screen.
+
+use bevy_egui::EguiContexts;
+use bevy_fluent::Localization;
+use jumpy_core::kill_feed::KillFeed;
+
+use crate::prelude::*;
+
+use super::widgets::{bordered_frame::BorderedFrame, EguiUiExt};
+
+pub struct KillFeedPlugin;
+
+impl Plugin for KillFeedPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            kill_feed
+                .run_if(in_state(EngineState::InGame))
+                .run_if(resource_exists::<Session>()),
+        );
+    }
+}
+
+/// Show the recent kills, with the players credited with them.
+fn kill_feed(
+    mut contexts: EguiContexts,
+    mut session: ResMut<Session>,
+    game: Res<GameMeta>,
+    localization: Res<Localization>,
+) {
+    let kills = session
+        .world()
+        .run_initialized_system(|kill_feed: bones::Res<KillFeed>| {
+            Ok(kill_feed
+                .entries
+                .iter()
+                .map(|(event, _)| *event)
+                .collect::<Vec<_>>())
+        })
+        .unwrap();
+    if kills.is_empty() {
+        return;
+    }
+
+    let ui_theme = &game.ui_theme;
+    let font = ui_theme
+        .font_styles
+        .normal
+        .colored(ui_theme.panel.font_color);
+
+    egui::Area::new("kill-feed")
+        .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-10.0, 10.0))
+        .show(contexts.ctx_mut(), |ui| {
+            BorderedFrame::new(&ui_theme.panel.border)
+                .padding(ui_theme.panel.padding.into())
+                .show(ui, |ui| {
+                    for kill in kills {
+                        let victim = kill.victim + 1;
+                        let message = match kill.killer {
+                            Some(killer) if killer == kill.victim => {
+                                format!("kill-feed-self-kill?victim={victim}")
+                            }
+                            Some(killer) => {
+                                format!("kill-feed-kill?killer={}&victim={victim}", killer + 1)
+                            }
+                            None => format!("kill-feed-death?victim={victim}"),
+                        };
+                        ui.themed_label(&font, &localization.get(&message));
+                    }
+                });
+        });
+}
@@ -28,11 +28,16 @@ use super::{
 };
 
 pub mod credits;
+pub mod daily_challenge;
+#[cfg(all(feature = "leaderboard", not(target_arch = "wasm32")))]
+pub mod leaderboard;
 pub mod map_select;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod network_game;
 pub mod player_select;
 pub mod settings;
+pub mod stats;
+pub mod tournament;
 
 pub struct MainMenuPlugin;
 
@@ -43,11 +48,31 @@ impl Plugin for MainMenuPlugin {
             .init_resource::<settings::SettingsTab>()
             .init_resource::<settings::ModifiedSettings>()
             .init_resource::<player_select::PlayerSelectState>()
+            .init_resource::<map_select::MatchSettingsState>()
+            .init_resource::<tournament::TournamentSetupState>()
             .add_systems((
                 main_menu_system.run_if(in_state(EngineState::MainMenu)),
                 setup_main_menu.in_schedule(OnEnter(EngineState::MainMenu)),
                 clean_up_main_menu.in_schedule(OnExit(EngineState::MainMenu)),
             ));
+
+        #[cfg(not(target_arch = "wasm32"))]
+        app.insert_resource(crate::networking::online::LobbyInvites {
+            pending_join: ENGINE_CONFIG.lobby_code.clone(),
+            ..default()
+        })
+        .add_system(join_pending_lobby.run_if(in_state(EngineState::MainMenu)));
+    }
+}
+
+/// Go to the network game page when there is a lobby invite waiting to be joined.
+#[cfg(not(target_arch = "wasm32"))]
+fn join_pending_lobby(
+    lobby_invites: Res<crate::networking::online::LobbyInvites>,
+    mut menu_page: ResMut<MenuPage>,
+) {
+    if lobby_invites.pending_join.is_some() && matches!(*menu_page, MenuPage::Home) {
+        *menu_page = MenuPage::NetworkGame;
     }
 }
 
@@ -142,6 +167,10 @@ pub enum MenuPage {
     },
     Credits,
     NetworkGame,
+    DailyChallenge,
+    Leaderboard,
+    Stats,
+    Tournament,
 }
 
 impl Default for MenuPage {
@@ -206,6 +235,21 @@ impl<'w, 's> WidgetSystem for MainMenu<'w, 's> {
                 widget::<settings::SettingsMenu>(world, ui, id.with("settings"), ())
             }
             MenuPage::Credits => widget::<credits::CreditsMenu>(world, ui, id.with("credits"), ()),
+            MenuPage::DailyChallenge => widget::<daily_challenge::DailyChallengeMenu>(
+                world,
+                ui,
+                id.with("daily-challenge"),
+                (),
+            ),
+            MenuPage::Leaderboard =>
+            {
+                #[cfg(all(feature = "leaderboard", not(target_arch = "wasm32")))]
+                widget::<leaderboard::LeaderboardMenu>(world, ui, id.with("leaderboard"), ())
+            }
+            MenuPage::Stats => widget::<stats::StatsMenu>(world, ui, id.with("stats"), ()),
+            MenuPage::Tournament => {
+                widget::<tournament::TournamentMenu>(world, ui, id.with("tournament"), ())
+            }
         }
     }
 }
@@ -281,6 +325,30 @@ impl<'w, 's> WidgetSystem for HomeMenu<'w, 's> {
                         *params.menu_page = MenuPage::PlayerSelect;
                     }
 
+                    // Daily Challenge
+                    if BorderedButton::themed(
+                        &ui_theme.button_styles.normal,
+                        &params.localization.get("daily-challenge"),
+                    )
+                    .min_size(min_button_size)
+                    .show(ui)
+                    .clicked()
+                    {
+                        *params.menu_page = MenuPage::DailyChallenge;
+                    }
+
+                    // Tournament
+                    if BorderedButton::themed(
+                        &ui_theme.button_styles.normal,
+                        &params.localization.get("tournament"),
+                    )
+                    .min_size(min_button_size)
+                    .show(ui)
+                    .clicked()
+                    {
+                        *params.menu_page = MenuPage::Tournament;
+                    }
+
                     // Network Game
                     #[cfg(not(target_arch = "wasm32"))]
                     {
@@ -320,6 +388,18 @@ impl<'w, 's> WidgetSystem for HomeMenu<'w, 's> {
                         }
                     });
 
+                    // Stats button
+                    if BorderedButton::themed(
+                        &ui_theme.button_styles.normal,
+                        &params.localization.get("stats"),
+                    )
+                    .min_size(min_button_size)
+                    .show(ui)
+                    .clicked()
+                    {
+                        *params.menu_page = MenuPage::Stats;
+                    }
+
                     // Settings button
                     if BorderedButton::themed(
                         &ui_theme.button_styles.normal,
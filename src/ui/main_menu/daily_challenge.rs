@@ -0,0 +1,180 @@
+use crate::daily_challenge::{
+    ActiveDailyChallenge, DailyChallenge, DailyChallengeAttempt, DailyChallengeResults,
+};
+
+use super::*;
+
+/// The number of previous daily challenge results to show.
+const RESULT_HISTORY_LEN: usize = 7;
+
+#[derive(SystemParam)]
+pub struct DailyChallengeMenu<'w, 's> {
+    commands: Commands<'w, 's>,
+    game: Res<'w, GameMeta>,
+    core: Res<'w, CoreMetaArc>,
+    menu_page: ResMut<'w, MenuPage>,
+    localization: Res<'w, Localization>,
+    menu_input: Query<'w, 's, &'static mut ActionState<MenuAction>>,
+    map_assets: Res<'w, Assets<MapMeta>>,
+    storage: ResMut<'w, Storage>,
+    session_manager: SessionManager<'w, 's>,
+    active_challenge: ResMut<'w, ActiveDailyChallenge>,
+}
+
+impl<'w, 's> WidgetSystem for DailyChallengeMenu<'w, 's> {
+    type Args = ();
+
+    fn system(
+        world: &mut World,
+        state: &mut SystemState<Self>,
+        ui: &mut egui::Ui,
+        _id: WidgetId,
+        _args: Self::Args,
+    ) {
+        let mut params: DailyChallengeMenu = state.get_mut(world);
+
+        let challenge = DailyChallenge::today();
+        let session_info = challenge.session_info(&params.core.0, &params.map_assets);
+        let results: DailyChallengeResults = params
+            .storage
+            .get(DailyChallengeResults::STORAGE_KEY)
+            .unwrap_or_default();
+        let todays_result = results.get(&challenge.day);
+
+        let ui_theme = &params.game.ui_theme;
+        let heading_font = ui_theme
+            .font_styles
+            .heading
+            .colored(ui_theme.panel.font_color);
+        let bigger_font = ui_theme
+            .font_styles
+            .bigger
+            .colored(ui_theme.panel.font_color);
+        let normal_font = ui_theme
+            .font_styles
+            .normal
+            .colored(ui_theme.panel.font_color);
+
+        let available_size = ui.available_size();
+        let menu_width = params.game.main_menu.menu_width;
+        let x_margin = (available_size.x - menu_width) / 2.0;
+        let outer_margin = egui::style::Margin::symmetric(x_margin, bigger_font.size);
+
+        BorderedFrame::new(&ui_theme.panel.border)
+            .margin(outer_margin)
+            .padding(ui_theme.panel.padding.into())
+            .show(ui, |ui| {
+                ui.set_min_width(ui.available_width());
+
+                ui.vertical_centered(|ui| {
+                    ui.themed_label(&heading_font, &params.localization.get("daily-challenge"));
+                    ui.themed_label(&bigger_font, &challenge.date());
+                });
+                ui.add_space(normal_font.size);
+
+                // Describe the challenge
+                if let Some(info) = &session_info {
+                    let mutators = info
+                        .match_settings
+                        .mutators
+                        .iter()
+                        .map(|x| params.localization.get(x.name_key()))
+                        .collect::<Vec<_>>();
+                    let opponents = info
+                        .player_info
+                        .iter()
+                        .flatten()
+                        .filter(|x| x.is_ai)
+                        .count();
+
+                    ui.themed_label(&normal_font, &info.map_meta.name);
+                    ui.themed_label(
+                        &normal_font,
+                        &format!("{}: {opponents}", params.localization.get("opponents")),
+                    );
+                    if !mutators.is_empty() {
+                        ui.themed_label(&normal_font, &mutators.join(", "));
+                    }
+                }
+                ui.add_space(normal_font.size);
+
+                // Show today's result, or let the player start the challenge
+                if let Some(result) = todays_result {
+                    ui.themed_label(
+                        &bigger_font,
+                        &format!("{}: {}", params.localization.get("score"), result.score()),
+                    );
+                } else {
+                    ui.scope(|ui| {
+                        ui.set_enabled(session_info.is_some());
+                        let start_button = BorderedButton::themed(
+                            &ui_theme.button_styles.normal,
+                            &params.localization.get("start"),
+                        )
+                        .min_size(egui::vec2(ui.available_width(), 0.0))
+                        .show(ui)
+                        .focus_by_default(ui);
+
+                        if start_button.clicked() {
+                            if let Some(info) = session_info.clone() {
+                                params.active_challenge.0 = Some(DailyChallengeAttempt::start(
+                                    challenge,
+                                    &mut params.storage,
+                                ));
+                                params.session_manager.start_local(info);
+                                *params.menu_page = MenuPage::Home;
+                                params
+                                    .commands
+                                    .insert_resource(NextState(Some(EngineState::InGame)));
+                                params
+                                    .commands
+                                    .insert_resource(NextState(Some(InGameState::Playing)));
+                            }
+                        }
+                    });
+                }
+
+                // Show the results of previous challenges
+                if !results.is_empty() {
+                    ui.add_space(normal_font.size);
+                    ui.themed_label(&bigger_font, &params.localization.get("previous-results"));
+                    for (day, result) in results.iter().rev().take(RESULT_HISTORY_LEN) {
+                        ui.themed_label(
+                            &normal_font,
+                            &format!(
+                                "{}: {}",
+                                DailyChallenge::for_day(*day).date(),
+                                result.score()
+                            ),
+                        );
+                    }
+                }
+
+                ui.add_space(normal_font.size);
+                ui.horizontal(|ui| {
+                    let back_button = BorderedButton::themed(
+                        &ui_theme.button_styles.small,
+                        &params.localization.get("back"),
+                    )
+                    .show(ui);
+
+                    if back_button.clicked()
+                        || params.menu_input.single().just_pressed(MenuAction::Back)
+                    {
+                        *params.menu_page = MenuPage::Home;
+                    }
+
+                    #[cfg(all(feature = "leaderboard", not(target_arch = "wasm32")))]
+                    if BorderedButton::themed(
+                        &ui_theme.button_styles.small,
+                        &params.localization.get("leaderboard"),
+                    )
+                    .show(ui)
+                    .clicked()
+                    {
+                        *params.menu_page = MenuPage::Leaderboard;
+                    }
+                });
+            });
+    }
+}
@@ -0,0 +1,156 @@
+use crate::{
+    daily_challenge::DailyChallenge,
+    leaderboard::{daily_challenge_board, Leaderboard, RANKED_BOARD},
+};
+
+use super::*;
+
+/// The number of top scores to show.
+const TOP_SCORES_LEN: usize = 10;
+
+#[derive(SystemParam)]
+pub struct LeaderboardMenu<'w, 's> {
+    game: Res<'w, GameMeta>,
+    menu_page: ResMut<'w, MenuPage>,
+    localization: Res<'w, Localization>,
+    menu_input: Query<'w, 's, &'static mut ActionState<MenuAction>>,
+    storage: ResMut<'w, Storage>,
+    leaderboard: ResMut<'w, Leaderboard>,
+    /// The board that we last requested the top scores for.
+    fetched_board: Local<'s, Option<String>>,
+    /// Whether the ranked board is shown instead of the board of today's daily challenge.
+    show_ranked: Local<'s, bool>,
+}
+
+impl<'w, 's> WidgetSystem for LeaderboardMenu<'w, 's> {
+    type Args = ();
+
+    fn system(
+        world: &mut World,
+        state: &mut SystemState<Self>,
+        ui: &mut egui::Ui,
+        _id: WidgetId,
+        _args: Self::Args,
+    ) {
+        let mut params: LeaderboardMenu = state.get_mut(world);
+
+        let settings =
+            Settings::get_stored_or_default(&params.game, &mut params.storage).into_owned();
+        let challenge = DailyChallenge::today();
+        let board = if *params.show_ranked {
+            RANKED_BOARD.to_string()
+        } else {
+            daily_challenge_board(&challenge)
+        };
+
+        // Fetch the scores the first time we show the board
+        if params.fetched_board.as_ref() != Some(&board) {
+            params.leaderboard.fetch_top(&settings, &board);
+            *params.fetched_board = Some(board.clone());
+        }
+
+        let ui_theme = &params.game.ui_theme;
+        let heading_font = ui_theme
+            .font_styles
+            .heading
+            .colored(ui_theme.panel.font_color);
+        let bigger_font = ui_theme
+            .font_styles
+            .bigger
+            .colored(ui_theme.panel.font_color);
+        let normal_font = ui_theme
+            .font_styles
+            .normal
+            .colored(ui_theme.panel.font_color);
+
+        let available_size = ui.available_size();
+        let menu_width = params.game.main_menu.menu_width;
+        let x_margin = (available_size.x - menu_width) / 2.0;
+        let outer_margin = egui::style::Margin::symmetric(x_margin, bigger_font.size);
+
+        BorderedFrame::new(&ui_theme.panel.border)
+            .margin(outer_margin)
+            .padding(ui_theme.panel.padding.into())
+            .show(ui, |ui| {
+                ui.set_min_width(ui.available_width());
+
+                ui.vertical_centered(|ui| {
+                    ui.themed_label(&heading_font, &params.localization.get("leaderboard"));
+                    let board_name = if *params.show_ranked {
+                        params.localization.get("ranked")
+                    } else {
+                        format!(
+                            "{} {}",
+                            params.localization.get("daily-challenge"),
+                            challenge.date()
+                        )
+                    };
+                    ui.themed_label(&bigger_font, &board_name);
+                });
+                ui.add_space(normal_font.size);
+
+                if !settings.leaderboard_enabled || settings.leaderboard_server.is_empty() {
+                    ui.themed_label(
+                        &normal_font,
+                        &params.localization.get("leaderboard-disabled"),
+                    );
+                } else if let Some(error) = &params.leaderboard.last_error {
+                    ui.themed_label(
+                        &normal_font,
+                        &format!("{}: {error}", params.localization.get("leaderboard-error")),
+                    );
+                } else if let Some(entries) = params.leaderboard.top_scores.get(&board) {
+                    for (i, entry) in entries.iter().take(TOP_SCORES_LEN).enumerate() {
+                        ui.themed_label(
+                            &normal_font,
+                            &format!("{}. {}  {}", i + 1, entry.player_id, entry.score),
+                        );
+                    }
+                } else {
+                    ui.themed_label(&normal_font, &params.localization.get("loading"));
+                }
+
+                ui.add_space(normal_font.size);
+                ui.horizontal(|ui| {
+                    let back_button = BorderedButton::themed(
+                        &ui_theme.button_styles.small,
+                        &params.localization.get("back"),
+                    )
+                    .show(ui)
+                    .focus_by_default(ui);
+
+                    if back_button.clicked()
+                        || params.menu_input.single().just_pressed(MenuAction::Back)
+                    {
+                        *params.menu_page = MenuPage::DailyChallenge;
+                    }
+
+                    if BorderedButton::themed(
+                        &ui_theme.button_styles.small,
+                        &params.localization.get("refresh"),
+                    )
+                    .show(ui)
+                    .clicked()
+                    {
+                        params.leaderboard.fetch_top(&settings, &board);
+                    }
+
+                    // Switch between the daily challenge and the ranked boards
+                    let other_board = if *params.show_ranked {
+                        "daily-challenge"
+                    } else {
+                        "ranked"
+                    };
+                    if BorderedButton::themed(
+                        &ui_theme.button_styles.small,
+                        &params.localization.get(other_board),
+                    )
+                    .show(ui)
+                    .clicked()
+                    {
+                        *params.show_ranked = !*params.show_ranked;
+                    }
+                });
+            });
+    }
+}
@@ -1,14 +1,65 @@
 use crate::{editor::UserMapStorage, ui::pause_menu::PauseMenuPage};
+use jumpy_core::{afk::AfkAction, feature_flags};
 
+use crate::networking::{
+    chat::TextChat, moderation::Moderation, GgrsSessionRunnerInfo, NetworkMatchSocket, SocketTarget,
+};
 #[cfg(not(target_arch = "wasm32"))]
-use crate::networking::{GgrsSessionRunnerInfo, NetworkMatchSocket, SocketTarget};
+use crate::networking::{
+    dedicated::{self, DedicatedHostMessage},
+    proto::TaggedMessage,
+};
 
 use super::*;
 
+/// The choices for [`MatchSettings::rounds_to_win`] in the item settings, cycled through in order.
+const ROUNDS_TO_WIN_OPTIONS: [Option<u32>; 4] = [None, Some(3), Some(5), Some(10)];
+/// The choices for [`MatchSettings::time_limit`] in the item settings, in seconds, cycled through
+/// in order.
+const TIME_LIMIT_OPTIONS: [Option<f32>; 4] = [None, Some(60.0), Some(120.0), Some(180.0)];
+
 /// Network message that may be sent when selecting a map.
 #[derive(Serialize, Deserialize)]
 pub enum MapSelectMessage {
-    SelectMap(bones::Handle<MapMeta>),
+    SelectMap(bones::Handle<MapMeta>, MatchSettings),
+}
+
+/// The match settings that will be used when starting a game from the map select menu.
+///
+/// The settings are kept in [`Storage`], so that they are the same after restarting the game.
+#[derive(Resource, Default)]
+pub struct MatchSettingsState {
+    /// The settings to start the match with.
+    pub settings: MatchSettings,
+    /// Whether or not the item settings are being shown instead of the map list.
+    pub show_item_settings: bool,
+    /// The settings last loaded from or saved to storage, or `None` if they haven't been loaded
+    /// yet.
+    stored: Option<MatchSettings>,
+}
+
+impl MatchSettingsState {
+    /// The key used to store the match settings in [`Storage`].
+    pub const STORAGE_KEY: &str = "match_settings";
+
+    /// Load the settings from storage, if they haven't been loaded yet.
+    fn load(&mut self, storage: &mut Storage) {
+        if self.stored.is_none() {
+            if let Some(settings) = storage.get::<MatchSettings>(Self::STORAGE_KEY) {
+                self.settings = settings;
+            }
+            self.stored = Some(self.settings.clone());
+        }
+    }
+
+    /// Save the settings to storage, if they have changed since they were last stored.
+    fn save(&mut self, storage: &mut Storage) {
+        if self.stored.as_ref() != Some(&self.settings) {
+            storage.set(Self::STORAGE_KEY, &self.settings);
+            storage.save();
+            self.stored = Some(self.settings.clone());
+        }
+    }
 }
 
 #[derive(SystemParam)]
@@ -24,9 +75,12 @@ pub struct MapSelectMenu<'w, 's> {
     commands: Commands<'w, 's>,
     localization: Res<'w, Localization>,
     map_assets: Res<'w, Assets<MapMeta>>,
+    element_assets: Res<'w, Assets<ElementMeta>>,
+    match_settings: ResMut<'w, MatchSettingsState>,
     storage: ResMut<'w, Storage>,
-    #[cfg(not(target_arch = "wasm32"))]
     network_socket: Option<Res<'w, NetworkMatchSocket>>,
+    moderation: ResMut<'w, Moderation>,
+    text_chat: ResMut<'w, TextChat>,
 }
 
 impl<'w, 's> WidgetSystem for MapSelectMenu<'w, 's> {
@@ -40,15 +94,19 @@ impl<'w, 's> WidgetSystem for MapSelectMenu<'w, 's> {
         is_waiting: Self::Args,
     ) {
         let mut params: MapSelectMenu = state.get_mut(world);
+        params.match_settings.load(&mut params.storage);
 
-        #[cfg(not(target_arch = "wasm32"))]
         handle_match_setup_messages(&mut params);
 
         let in_game = params.game_state.0 == EngineState::InGame;
 
         if params.menu_input.single().just_pressed(MenuAction::Back) {
+            // If we are editing the item settings, go back to the map list
+            if params.match_settings.show_item_settings {
+                params.match_settings.show_item_settings = false;
+
             // If we are on the main menu
-            if params.game_state.0 == EngineState::MainMenu {
+            } else if params.game_state.0 == EngineState::MainMenu {
                 *params.menu_page = MenuPage::PlayerSelect;
 
             // If we're on a map selection in game, we must be in the pause menu
@@ -89,6 +147,34 @@ impl<'w, 's> WidgetSystem for MapSelectMenu<'w, 's> {
                     .show(ui, |ui| {
                         ui.set_width(ui.available_width());
 
+                        let item_settings_button = BorderedButton::themed(
+                            small_button_style,
+                            &params
+                                .localization
+                                .get(if params.match_settings.show_item_settings {
+                                    "map-list"
+                                } else {
+                                    "item-settings"
+                                }),
+                        )
+                        .show(ui);
+                        if item_settings_button.clicked() {
+                            params.match_settings.show_item_settings =
+                                !params.match_settings.show_item_settings;
+                        }
+
+                        if params.match_settings.show_item_settings {
+                            item_settings_ui(
+                                ui,
+                                &params.game,
+                                &params.core,
+                                &params.localization,
+                                &params.element_assets,
+                                &mut params.match_settings.settings,
+                            );
+                            return;
+                        }
+
                         let mut first_button = true;
 
                         egui::ScrollArea::vertical().show(ui, |ui| {
@@ -119,7 +205,10 @@ impl<'w, 's> WidgetSystem for MapSelectMenu<'w, 's> {
 
                                     let mut button =
                                         BorderedButton::themed(small_button_style, &map_meta.name)
-                                            .show(ui);
+                                            .show(ui)
+                                            .on_hover_ui(|ui| {
+                                                map_info_ui(ui, &params.localization, map_meta)
+                                            });
 
                                     if first_button {
                                         first_button = false;
@@ -143,15 +232,32 @@ impl<'w, 's> WidgetSystem for MapSelectMenu<'w, 's> {
                                                     player: slot.selected_player.clone(),
                                                     hat: slot.selected_hat.clone(),
                                                     is_ai: slot.is_ai,
+                                                    ai_difficulty: slot.ai_difficulty,
                                                 });
                                             }
                                         });
+                                        let mut match_settings =
+                                            params.match_settings.settings.clone();
+                                        // Give every match its own random drops, which are
+                                        // shared with the network players through the settings.
+                                        match_settings.seed.get_or_insert_with(rand::random);
+                                        // Kick AFK players from network games so they don't
+                                        // hold the other players hostage.
+                                        if params.network_socket.is_some() {
+                                            let afk_timeout = Settings::get_stored_or_default(
+                                                &params.game,
+                                                &mut params.storage,
+                                            )
+                                            .afk_timeout;
+                                            match_settings.afk_timeout =
+                                                (afk_timeout > 0.0).then_some(afk_timeout);
+                                        }
                                         let core_info = CoreSessionInfo {
                                             meta: params.core.0.clone(),
                                             map_meta: map_meta.clone(),
                                             player_info,
+                                            match_settings: match_settings.clone(),
                                         };
-                                        #[cfg(not(target_arch = "wasm32"))]
                                         if let Some(socket) = &params.network_socket {
                                             info!("Selected map, starting network game");
                                             params.session_manager.start_network(
@@ -159,6 +265,7 @@ impl<'w, 's> WidgetSystem for MapSelectMenu<'w, 's> {
                                                 GgrsSessionRunnerInfo {
                                                     socket: socket.ggrs_socket(),
                                                     player_is_local: socket.player_is_local(),
+                                                    player_addrs: socket.player_addrs(),
                                                     player_count: socket.player_count(),
                                                 },
                                             );
@@ -166,11 +273,6 @@ impl<'w, 's> WidgetSystem for MapSelectMenu<'w, 's> {
                                             info!("Selected map, starting game");
                                             params.session_manager.start_local(core_info);
                                         }
-                                        #[cfg(target_arch = "wasm32")]
-                                        {
-                                            info!("Selected map, starting game");
-                                            params.session_manager.start_local(core_info);
-                                        }
 
                                         params
                                             .commands
@@ -179,12 +281,14 @@ impl<'w, 's> WidgetSystem for MapSelectMenu<'w, 's> {
                                             .commands
                                             .insert_resource(NextState(Some(InGameState::Playing)));
 
-                                        #[cfg(not(target_arch = "wasm32"))]
                                         if let Some(socket) = &params.network_socket {
                                             socket.send_reliable(
                                                 SocketTarget::All,
                                                 &postcard::to_allocvec(
-                                                    &MapSelectMessage::SelectMap(map_handle),
+                                                    &MapSelectMessage::SelectMap(
+                                                        map_handle,
+                                                        match_settings,
+                                                    ),
                                                 )
                                                 .unwrap(),
                                             );
@@ -195,10 +299,7 @@ impl<'w, 's> WidgetSystem for MapSelectMenu<'w, 's> {
                                 let user_maps: Option<UserMapStorage> =
                                     params.storage.get(UserMapStorage::STORAGE_KEY);
                                 if let Some(user_maps) = user_maps {
-                                    #[cfg(not(target_arch = "wasm32"))]
                                     let is_network = params.network_socket.is_some();
-                                    #[cfg(target_arch = "wasm32")]
-                                    let is_network = false;
 
                                     // For now, network games can only play core maps.
                                     ui.set_enabled(!is_network);
@@ -216,7 +317,10 @@ impl<'w, 's> WidgetSystem for MapSelectMenu<'w, 's> {
                                         ui.add_space(ui.spacing().item_spacing.y);
                                         let button =
                                             BorderedButton::themed(small_button_style, &name)
-                                                .show(ui);
+                                                .show(ui)
+                                                .on_hover_ui(|ui| {
+                                                    map_info_ui(ui, &params.localization, &map_meta)
+                                                });
                                         if button.clicked() {
                                             *params.pause_page = PauseMenuPage::Default;
                                             *params.menu_page = MenuPage::Home;
@@ -231,6 +335,7 @@ impl<'w, 's> WidgetSystem for MapSelectMenu<'w, 's> {
                                                         player: slot.selected_player.clone(),
                                                         hat: slot.selected_hat.clone(),
                                                         is_ai: slot.is_ai,
+                                                        ai_difficulty: slot.ai_difficulty,
                                                     });
                                                 }
                                             });
@@ -238,6 +343,10 @@ impl<'w, 's> WidgetSystem for MapSelectMenu<'w, 's> {
                                                 meta: params.core.0.clone(),
                                                 map_meta,
                                                 player_info,
+                                                match_settings: params
+                                                    .match_settings
+                                                    .settings
+                                                    .clone(),
                                             });
                                             params.commands.insert_resource(NextState(Some(
                                                 EngineState::InGame,
@@ -253,18 +362,51 @@ impl<'w, 's> WidgetSystem for MapSelectMenu<'w, 's> {
                     });
             }
         });
+
+        params.match_settings.save(&mut params.storage);
     }
 }
 
-#[cfg(not(target_arch = "wasm32"))]
 fn handle_match_setup_messages(params: &mut MapSelectMenu) {
     if let Some(socket) = &params.network_socket {
         let datas: Vec<(usize, Vec<u8>)> = socket.recv_reliable();
 
         for (player, data) in datas {
+            if params.moderation.handle_message(socket, player, &data)
+                || params
+                    .text_chat
+                    .handle_message(player, &data, bevy::utils::Instant::now())
+            {
+                continue;
+            }
+
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Some(message) = DedicatedHostMessage::decode(&data) {
+                if let Some(runner) = dedicated::match_runner(
+                    player,
+                    socket,
+                    &params.core,
+                    &params.map_assets,
+                    message,
+                ) {
+                    info!("Dedicated host started the match");
+                    *params.pause_page = PauseMenuPage::Default;
+                    *params.menu_page = MenuPage::Home;
+                    if params.game_state.0 == EngineState::InGame {
+                        params.session_manager.start(runner);
+                        params
+                            .commands
+                            .insert_resource(NextState(Some(InGameState::Playing)));
+                    } else {
+                        params.session_manager.start_network_runner(runner);
+                    }
+                }
+                continue;
+            }
+
             match postcard::from_bytes::<MapSelectMessage>(&data) {
                 Ok(message) => match message {
-                    MapSelectMessage::SelectMap(map_handle) => {
+                    MapSelectMessage::SelectMap(map_handle, match_settings) => {
                         assert_eq!(player, 0, "Only player 0 may select the map.");
                         info!("Other player selected map, starting game");
                         *params.pause_page = PauseMenuPage::Default;
@@ -280,11 +422,12 @@ fn handle_match_setup_messages(params: &mut MapSelectMenu) {
                             <[Option<GameSessionPlayerInfo>; MAX_PLAYERS]>::default();
                         (0..MAX_PLAYERS).for_each(|i| {
                             let slot = &params.player_select_state.slots[i];
-                            if slot.active {
+                            if slot.active && !params.moderation.removed[i] {
                                 player_info[i] = Some(GameSessionPlayerInfo {
                                     player: slot.selected_player.clone(),
                                     hat: slot.selected_hat.clone(),
                                     is_ai: slot.is_ai,
+                                    ai_difficulty: slot.ai_difficulty,
                                 });
                             }
                         });
@@ -293,10 +436,12 @@ fn handle_match_setup_messages(params: &mut MapSelectMenu) {
                                 meta: params.core.0.clone(),
                                 map_meta,
                                 player_info,
+                                match_settings,
                             },
                             GgrsSessionRunnerInfo {
                                 socket: socket.ggrs_socket(),
                                 player_is_local: socket.player_is_local(),
+                                player_addrs: socket.player_addrs(),
                                 player_count: socket.player_count(),
                             },
                         );
@@ -318,3 +463,325 @@ fn handle_match_setup_messages(params: &mut MapSelectMenu) {
         }
     }
 }
+
+/// Render the information about a map, shown when hovering it in the map list.
+fn map_info_ui(ui: &mut egui::Ui, localization: &Localization, map_meta: &MapMeta) {
+    ui.label(egui::RichText::new(&map_meta.name).strong());
+    if !map_meta.author.is_empty() {
+        ui.label(format!(
+            "{}: {}",
+            localization.get("author"),
+            map_meta.author
+        ));
+    }
+    if !map_meta.description.is_empty() {
+        ui.label(&map_meta.description);
+    }
+    if let Some(players) = map_meta.recommended_players {
+        ui.label(format!(
+            "{}: {}-{}",
+            localization.get("recommended-players"),
+            players.min,
+            players.max
+        ));
+    }
+    ui.label(format!(
+        "{}: {}",
+        localization.get("version"),
+        map_meta.version
+    ));
+}
+
+/// Render the list of mutators and items that may be enabled, disabled, or have their spawn rate
+/// changed for the match.
+fn item_settings_ui(
+    ui: &mut egui::Ui,
+    game: &GameMeta,
+    core: &CoreMeta,
+    localization: &Localization,
+    element_assets: &Assets<ElementMeta>,
+    settings: &mut MatchSettings,
+) {
+    let bigger_text_style = &game.ui_theme.font_styles.bigger;
+    let normal_text_style = &game.ui_theme.font_styles.normal;
+    let small_button_style = &game.ui_theme.button_styles.small;
+
+    ui.add_space(bigger_text_style.size / 2.0);
+    ui.themed_label(bigger_text_style, &localization.get("item-settings"));
+
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        ui.add_space(ui.spacing().item_spacing.y);
+        ui.horizontal(|ui| {
+            ui.themed_label(normal_text_style, &localization.get("match-mode"));
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                let (label, next_mode) = match settings.mode {
+                    MatchMode::FreeForAll => ("match-mode-free-for-all", MatchMode::Teams),
+                    MatchMode::Teams => ("match-mode-teams", MatchMode::CaptureTheFlag),
+                    MatchMode::CaptureTheFlag => {
+                        ("match-mode-capture-the-flag", MatchMode::FreeForAll)
+                    }
+                };
+                if BorderedButton::themed(small_button_style, &localization.get(label))
+                    .show(ui)
+                    .clicked()
+                {
+                    settings.mode = next_mode;
+                }
+            });
+        });
+
+        if settings.mode != MatchMode::FreeForAll {
+            ui.add_space(ui.spacing().item_spacing.y);
+            ui.horizontal(|ui| {
+                ui.themed_label(normal_text_style, &localization.get("friendly-fire"));
+
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    let enabled = settings.friendly_fire;
+                    let toggle_label = if enabled {
+                        localization.get("enabled")
+                    } else {
+                        localization.get("disabled")
+                    };
+                    if BorderedButton::themed(small_button_style, &toggle_label)
+                        .show(ui)
+                        .clicked()
+                    {
+                        settings.friendly_fire = !enabled;
+                    }
+                });
+            });
+        }
+
+        ui.add_space(ui.spacing().item_spacing.y);
+        ui.horizontal(|ui| {
+            ui.themed_label(normal_text_style, &localization.get("rounds-to-win"));
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                let label = match settings.rounds_to_win {
+                    Some(rounds) => {
+                        localization.get(&format!("rounds-to-win-count?rounds={rounds}"))
+                    }
+                    None => localization.get("rounds-to-win-endless"),
+                };
+                if BorderedButton::themed(small_button_style, &label)
+                    .show(ui)
+                    .clicked()
+                {
+                    let current = ROUNDS_TO_WIN_OPTIONS
+                        .iter()
+                        .position(|x| *x == settings.rounds_to_win)
+                        .unwrap_or_default();
+                    settings.rounds_to_win =
+                        ROUNDS_TO_WIN_OPTIONS[(current + 1) % ROUNDS_TO_WIN_OPTIONS.len()];
+                }
+            });
+        });
+
+        // Sudden death can only end rounds
+        if settings.rounds_to_win.is_some() {
+            ui.add_space(ui.spacing().item_spacing.y);
+            ui.horizontal(|ui| {
+                ui.themed_label(normal_text_style, &localization.get("time-limit"));
+
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    let label = match settings.time_limit {
+                        Some(secs) => {
+                            localization.get(&format!("time-limit-minutes?minutes={}", secs / 60.0))
+                        }
+                        None => localization.get("time-limit-none"),
+                    };
+                    if BorderedButton::themed(small_button_style, &label)
+                        .show(ui)
+                        .clicked()
+                    {
+                        let current = TIME_LIMIT_OPTIONS
+                            .iter()
+                            .position(|x| *x == settings.time_limit)
+                            .unwrap_or_default();
+                        settings.time_limit =
+                            TIME_LIMIT_OPTIONS[(current + 1) % TIME_LIMIT_OPTIONS.len()];
+                    }
+                });
+            });
+        }
+
+        ui.add_space(ui.spacing().item_spacing.y);
+        ui.horizontal(|ui| {
+            ui.themed_label(normal_text_style, &localization.get("arcade-mode"));
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                let enabled = settings.arcade;
+                let toggle_label = if enabled {
+                    localization.get("enabled")
+                } else {
+                    localization.get("disabled")
+                };
+                if BorderedButton::themed(small_button_style, &toggle_label)
+                    .show(ui)
+                    .clicked()
+                {
+                    settings.arcade = !enabled;
+                }
+            });
+        });
+
+        for mutator in Mutator::ALL {
+            ui.add_space(ui.spacing().item_spacing.y);
+            ui.horizontal(|ui| {
+                ui.themed_label(normal_text_style, &localization.get(mutator.name_key()));
+
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    let enabled = settings.has_mutator(mutator);
+                    let toggle_label = if enabled {
+                        localization.get("enabled")
+                    } else {
+                        localization.get("disabled")
+                    };
+                    if BorderedButton::themed(small_button_style, &toggle_label)
+                        .show(ui)
+                        .clicked()
+                    {
+                        settings.set_mutator_enabled(mutator, !enabled);
+                    }
+                });
+            });
+        }
+
+        for flag in feature_flags::ALL {
+            ui.add_space(ui.spacing().item_spacing.y);
+            ui.horizontal(|ui| {
+                ui.themed_label(
+                    normal_text_style,
+                    &localization.get(&feature_flags::name_key(flag)),
+                );
+
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    let enabled = settings.has_feature_flag(flag);
+                    let toggle_label = if enabled {
+                        localization.get("enabled")
+                    } else {
+                        localization.get("disabled")
+                    };
+                    if BorderedButton::themed(small_button_style, &toggle_label)
+                        .show(ui)
+                        .clicked()
+                    {
+                        settings.set_feature_flag_enabled(flag, !enabled);
+                    }
+                });
+            });
+        }
+
+        ui.add_space(ui.spacing().item_spacing.y);
+        ui.horizontal(|ui| {
+            ui.themed_label(normal_text_style, &localization.get("pickup-assist"));
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                let enabled = settings.pickup_assist_radius.is_some();
+                let toggle_label = if enabled {
+                    localization.get("enabled")
+                } else {
+                    localization.get("disabled")
+                };
+                if BorderedButton::themed(small_button_style, &toggle_label)
+                    .show(ui)
+                    .clicked()
+                {
+                    settings.pickup_assist_radius =
+                        (!enabled).then_some(MatchSettings::DEFAULT_PICKUP_ASSIST_RADIUS);
+                }
+            });
+        });
+
+        // Only has an effect in network games, where players may disconnect.
+        ui.add_space(ui.spacing().item_spacing.y);
+        ui.horizontal(|ui| {
+            ui.themed_label(normal_text_style, &localization.get("ai-fill-disconnected"));
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                let enabled = settings.ai_fill_disconnected;
+                let toggle_label = if enabled {
+                    localization.get("enabled")
+                } else {
+                    localization.get("disabled")
+                };
+                if BorderedButton::themed(small_button_style, &toggle_label)
+                    .show(ui)
+                    .clicked()
+                {
+                    settings.ai_fill_disconnected = !enabled;
+                }
+            });
+        });
+
+        // What happens to players that stay idle past the AFK timeout from the settings, which is
+        // only applied to network games.
+        ui.add_space(ui.spacing().item_spacing.y);
+        ui.horizontal(|ui| {
+            ui.themed_label(normal_text_style, &localization.get("afk-action"));
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                let (label, next_action) = match settings.afk_action {
+                    AfkAction::ConvertToAi => ("afk-action-ai", AfkAction::Drop),
+                    AfkAction::Drop => ("afk-action-drop", AfkAction::ConvertToAi),
+                };
+                if BorderedButton::themed(small_button_style, &localization.get(label))
+                    .show(ui)
+                    .clicked()
+                {
+                    settings.afk_action = next_action;
+                }
+            });
+        });
+
+        ui.add_space(bigger_text_style.size / 2.0);
+
+        for element_handle in &core.map_elements {
+            let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+                continue;
+            };
+            // Only items may be configured, other elements are part of the map itself.
+            if !element_meta.builtin.is_item() {
+                continue;
+            }
+            let name = &element_meta.name;
+
+            ui.add_space(ui.spacing().item_spacing.y);
+            ui.horizontal(|ui| {
+                ui.themed_label(normal_text_style, name);
+
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    let enabled = settings.is_element_enabled(name);
+                    let multiplier = settings.spawn_multiplier(name);
+
+                    if BorderedButton::themed(small_button_style, "+")
+                        .show(ui)
+                        .clicked()
+                    {
+                        settings.set_spawn_multiplier(name, multiplier + 0.5);
+                    }
+                    ui.themed_label(normal_text_style, &format!("{multiplier:.1}x"));
+                    if BorderedButton::themed(small_button_style, "-")
+                        .show(ui)
+                        .clicked()
+                    {
+                        settings.set_spawn_multiplier(name, multiplier - 0.5);
+                    }
+
+                    let toggle_label = if enabled {
+                        localization.get("enabled")
+                    } else {
+                        localization.get("disabled")
+                    };
+                    if BorderedButton::themed(small_button_style, &toggle_label)
+                        .show(ui)
+                        .clicked()
+                    {
+                        settings.set_element_enabled(name, !enabled);
+                    }
+                });
+            });
+        }
+    });
+}
@@ -2,7 +2,13 @@ use std::time::Duration;
 
 use crate::networking::{
     lan,
-    online::{OnlineMatchmakerRequest, OnlineMatchmakerResponse, ONLINE_MATCHMAKER},
+    online::{
+        generate_lobby_code, lobby_match_data, LobbyInvites, OnlineMatchmakerRequest,
+        OnlineMatchmakerResponse, ONLINE_MATCHMAKER,
+    },
+    quick_match::{searching_dots, MatchRegion, QuickMatchQueue, QuickMatchStatus},
+    ranked::{OnlineRating, RankedQueue, RankedQueueStatus, MAX_RECONNECT_ATTEMPTS},
+    upgrade::{MatchUpgrade, MatchUpgradeStatus},
     NetworkMatchSocket,
 };
 
@@ -18,6 +24,10 @@ pub struct MatchmakingMenu<'w, 's> {
     menu_input: Query<'w, 's, &'static mut ActionState<MenuAction>>,
     commands: Commands<'w, 's>,
     storage: ResMut<'w, Storage>,
+    lobby_invites: ResMut<'w, LobbyInvites>,
+    ranked_queue: ResMut<'w, RankedQueue>,
+    quick_match_queue: ResMut<'w, QuickMatchQueue>,
+    match_upgrade: ResMut<'w, MatchUpgrade>,
 }
 
 pub struct State {
@@ -28,6 +38,12 @@ pub struct State {
     joined_players: usize,
     lan_servers: Vec<lan::ServerInfo>,
     ping_update_timer: Timer,
+    /// The address of the LAN server to join directly, as typed in by the player.
+    direct_address: String,
+    /// The address players may join our LAN server with directly, while hosting.
+    local_address: Option<std::net::SocketAddr>,
+    /// Whether joining the last LAN server failed.
+    join_failed: bool,
 }
 
 #[derive(Default, PartialEq, Eq)]
@@ -49,6 +65,9 @@ impl Default for State {
             lan_servers: default(),
             joined_players: default(),
             ping_update_timer: Timer::new(Duration::from_secs(1), TimerMode::Repeating),
+            direct_address: default(),
+            local_address: default(),
+            join_failed: default(),
         }
     }
 }
@@ -57,6 +76,8 @@ impl Default for State {
 pub enum MatchKind {
     Lan(LanMode),
     Online(OnlineState),
+    Ranked,
+    QuickMatch(QuickMatchState),
 }
 
 impl Default for MatchKind {
@@ -79,6 +100,8 @@ pub enum LanMode {
 pub struct OnlineState {
     player_count: usize,
     matchmaking_server: String,
+    /// The lobby code to search with. If empty, we will be matched with anybody.
+    lobby_code: String,
     search_state: SearchState,
 }
 
@@ -87,11 +110,28 @@ impl Default for OnlineState {
         Self {
             player_count: 2,
             matchmaking_server: String::new(),
+            lobby_code: String::new(),
             search_state: default(),
         }
     }
 }
 
+#[derive(Eq, PartialEq, Clone)]
+pub struct QuickMatchState {
+    player_count: usize,
+    /// The region to search in, loaded from the storage when the tab is opened.
+    region: Option<MatchRegion>,
+}
+
+impl Default for QuickMatchState {
+    fn default() -> Self {
+        Self {
+            player_count: 2,
+            region: None,
+        }
+    }
+}
+
 #[derive(Default, PartialEq, Eq, Clone, Copy)]
 pub enum SearchState {
     #[default]
@@ -113,6 +153,16 @@ impl<'w, 's> WidgetSystem for MatchmakingMenu<'w, 's> {
         let menu_input = params.menu_input.single();
         params.state.ping_update_timer.tick(params.time.delta());
 
+        // Switch to the lobby of an accepted invite
+        if params.state.status == Status::Idle {
+            if let Some(lobby_code) = params.lobby_invites.pending_join.take() {
+                params.state.match_kind = MatchKind::Online(OnlineState {
+                    lobby_code,
+                    ..default()
+                });
+            }
+        }
+
         let bigger_text_style = &params.game.ui_theme.font_styles.bigger;
         let normal_text_style = &params.game.ui_theme.font_styles.normal;
         let smaller_text_style = &params.game.ui_theme.font_styles.smaller;
@@ -165,6 +215,31 @@ impl<'w, 's> WidgetSystem for MatchmakingMenu<'w, 's> {
                         params.state.match_kind = MatchKind::Online(default());
                     }
 
+                    // Ranked tab
+                    let mut ranked = egui::RichText::new(params.localization.get("ranked"));
+                    if matches!(params.state.match_kind, MatchKind::Ranked) {
+                        ranked = ranked.underline();
+                    }
+                    if BorderedButton::themed(normal_button_style, ranked)
+                        .show(ui)
+                        .clicked()
+                    {
+                        params.state.match_kind = MatchKind::Ranked;
+                    }
+
+                    // Quick match tab
+                    let mut quick_match =
+                        egui::RichText::new(params.localization.get("quick-match"));
+                    if matches!(params.state.match_kind, MatchKind::QuickMatch(..)) {
+                        quick_match = quick_match.underline();
+                    }
+                    if BorderedButton::themed(normal_button_style, quick_match)
+                        .show(ui)
+                        .clicked()
+                    {
+                        params.state.match_kind = MatchKind::QuickMatch(default());
+                    }
+
                     match &mut params.state.match_kind {
                         MatchKind::Lan(mode) => {
                             ui.with_layout(
@@ -220,9 +295,48 @@ impl<'w, 's> WidgetSystem for MatchmakingMenu<'w, 's> {
                                 },
                             );
                         }
+                        MatchKind::Ranked => {
+                            ui.with_layout(
+                                egui::Layout::right_to_left(egui::Align::Center),
+                                |ui| {
+                                    ui.themed_label(
+                                        normal_text_style,
+                                        &params.localization.get("search-for-ranked-match"),
+                                    );
+                                },
+                            );
+                        }
+                        MatchKind::QuickMatch(_) => {
+                            ui.with_layout(
+                                egui::Layout::right_to_left(egui::Align::Center),
+                                |ui| {
+                                    ui.themed_label(
+                                        normal_text_style,
+                                        &params.localization.get("search-for-quick-match"),
+                                    );
+                                },
+                            );
+                        }
                     }
                 });
 
+                // Leave the ranked queue when switching to another tab
+                if !matches!(params.state.match_kind, MatchKind::Ranked)
+                    && params.ranked_queue.is_active()
+                {
+                    params.ranked_queue.leave();
+                }
+                if !matches!(params.state.match_kind, MatchKind::QuickMatch(..))
+                    && params.quick_match_queue.is_active()
+                {
+                    params.quick_match_queue.leave();
+                }
+                if !matches!(params.state.match_kind, MatchKind::Online(..))
+                    && params.match_upgrade.is_joining()
+                {
+                    params.match_upgrade.cancel();
+                }
+
                 let State {
                     match_kind,
                     lan_service_discovery_recv,
@@ -231,6 +345,9 @@ impl<'w, 's> WidgetSystem for MatchmakingMenu<'w, 's> {
                     status,
                     ping_update_timer,
                     joined_players,
+                    direct_address,
+                    local_address,
+                    join_failed,
                 } = &mut *params.state;
 
                 ui.separator();
@@ -267,6 +384,7 @@ impl<'w, 's> WidgetSystem for MatchmakingMenu<'w, 's> {
                                             {
                                                 lan::join_server(server);
                                                 *status = Status::Joining;
+                                                *join_failed = false;
                                             }
 
                                             let label_text = egui::RichText::new(format!(
@@ -289,6 +407,44 @@ impl<'w, 's> WidgetSystem for MatchmakingMenu<'w, 's> {
                                     }
                                 });
 
+                                // Join a server that wasn't discovered with its address
+                                ui.add_space(normal_text_style.size / 2.0);
+                                ui.horizontal(|ui| {
+                                    ui.themed_label(
+                                        normal_text_style,
+                                        &params.localization.get("direct-connect"),
+                                    );
+                                    ui.add(
+                                        egui::TextEdit::singleline(direct_address)
+                                            .font(normal_text_style.font_id())
+                                            .hint_text("192.168.1.20:10500")
+                                            .desired_width(normal_text_style.size * 10.0),
+                                    );
+
+                                    let addr = lan::parse_server_address(direct_address);
+                                    ui.set_enabled(addr.is_some());
+                                    if BorderedButton::themed(
+                                        small_button_style,
+                                        &params.localization.get("join"),
+                                    )
+                                    .show(ui)
+                                    .clicked()
+                                    {
+                                        if let Some(addr) = addr {
+                                            lan::join_address(addr);
+                                            *status = Status::Joining;
+                                            *join_failed = false;
+                                        }
+                                    }
+                                });
+
+                                if *join_failed {
+                                    ui.themed_label(
+                                        normal_text_style,
+                                        &params.localization.get("join-server-failed"),
+                                    );
+                                }
+
                             // If we are trying to join a match.
                             } else {
                                 ui.themed_label(
@@ -296,13 +452,21 @@ impl<'w, 's> WidgetSystem for MatchmakingMenu<'w, 's> {
                                     &params.localization.get("joining"),
                                 );
 
-                                if let Some(lan_socket) = lan::wait_game_start() {
-                                    params.commands.insert_resource(NetworkMatchSocket(
-                                        Box::new(lan_socket),
-                                    ));
+                                match lan::wait_game_start() {
+                                    Ok(Some(lan_socket)) => {
+                                        params.commands.insert_resource(NetworkMatchSocket(
+                                            Box::new(lan_socket),
+                                        ));
 
-                                    *status = default();
-                                    *params.menu_page = MenuPage::PlayerSelect;
+                                        *status = default();
+                                        *params.menu_page = MenuPage::PlayerSelect;
+                                    }
+                                    Ok(None) => (),
+                                    Err(e) => {
+                                        warn!("Could not join LAN server: {e}");
+                                        *status = Status::Idle;
+                                        *join_failed = true;
+                                    }
                                 }
                             }
 
@@ -378,6 +542,7 @@ impl<'w, 's> WidgetSystem for MatchmakingMenu<'w, 's> {
                                 .clicked()
                                 {
                                     *status = Status::Hosting;
+                                    *local_address = lan::local_address();
                                     lan::start_server(service_info.clone(), *player_count);
                                 }
 
@@ -414,6 +579,17 @@ impl<'w, 's> WidgetSystem for MatchmakingMenu<'w, 's> {
                                         ),
                                     );
                                 });
+
+                                // Let players that don't see the server join it directly
+                                if let Some(addr) = local_address {
+                                    ui.add_space(normal_text_style.size / 2.0);
+                                    ui.themed_label(
+                                        normal_text_style,
+                                        &params
+                                            .localization
+                                            .get(&format!("server-address?address={addr}")),
+                                    );
+                                }
                             }
                         }
                     },
@@ -422,6 +598,7 @@ impl<'w, 's> WidgetSystem for MatchmakingMenu<'w, 's> {
                     MatchKind::Online(OnlineState {
                         player_count,
                         matchmaking_server,
+                        lobby_code,
                         mut search_state,
                     }) => {
                         // Get the matchmaking server from the settings.
@@ -465,9 +642,88 @@ impl<'w, 's> WidgetSystem for MatchmakingMenu<'w, 's> {
                             });
                         });
 
+                        ui.add_space(normal_text_style.size / 2.0);
+                        ui.horizontal(|ui| {
+                            ui.set_enabled(*status == Status::Idle);
+                            ui.themed_label(
+                                normal_text_style,
+                                &params.localization.get("lobby-code"),
+                            );
+                            ui.add(
+                                egui::TextEdit::singleline(lobby_code)
+                                    .font(normal_text_style.font_id())
+                                    .desired_width(normal_text_style.size * 6.0),
+                            );
+                            *lobby_code = lobby_code.trim().to_uppercase();
+
+                            if BorderedButton::themed(
+                                small_button_style,
+                                &params.localization.get("new-lobby"),
+                            )
+                            .show(ui)
+                            .clicked()
+                            {
+                                *lobby_code = generate_lobby_code();
+                            }
+                        });
+
                         ui.add_space(normal_text_style.size);
 
-                        if *status == Status::Idle {
+                        if params.match_upgrade.is_joining() {
+                            ui.horizontal(|ui| {
+                                if BorderedButton::themed(
+                                    small_button_style,
+                                    &params.localization.get("cancel"),
+                                )
+                                .show(ui)
+                                .clicked()
+                                {
+                                    params.match_upgrade.cancel();
+                                }
+
+                                ui.themed_label(
+                                    smaller_text_style,
+                                    &params.localization.get(
+                                        if params.match_upgrade.status
+                                            == MatchUpgradeStatus::Receiving
+                                        {
+                                            "match-upgrade-receiving"
+                                        } else {
+                                            "match-upgrade-joining"
+                                        },
+                                    ),
+                                );
+                            });
+                        } else if *status == Status::Idle {
+                            ui.horizontal(|ui| {
+                                ui.set_enabled(!lobby_code.is_empty());
+                                if BorderedButton::themed(
+                                    small_button_style,
+                                    &params.localization.get("join-match-in-progress"),
+                                )
+                                .show(ui)
+                                .clicked()
+                                {
+                                    let bandwidth_cap = Settings::get_stored_or_default(
+                                        &params.game,
+                                        &mut params.storage,
+                                    )
+                                    .relay_bandwidth_cap;
+                                    params.match_upgrade.join(
+                                        matchmaking_server.clone(),
+                                        lobby_code.clone(),
+                                        (bandwidth_cap > 0).then_some(bandwidth_cap * 1024),
+                                    );
+                                }
+                            });
+                            if params.match_upgrade.status == MatchUpgradeStatus::Failed {
+                                ui.themed_label(
+                                    smaller_text_style,
+                                    &params.localization.get("join-match-failed"),
+                                );
+                            }
+                            ui.add_space(normal_text_style.size / 2.0);
+
                             if BorderedButton::themed(
                                 small_button_style,
                                 &params.localization.get("search"),
@@ -476,10 +732,21 @@ impl<'w, 's> WidgetSystem for MatchmakingMenu<'w, 's> {
                             .clicked()
                             {
                                 *status = Status::Searching;
+                                let lobby_code =
+                                    (!lobby_code.is_empty()).then(|| lobby_code.clone());
+                                params.lobby_invites.current_lobby = lobby_code.clone();
+                                let bandwidth_cap = Settings::get_stored_or_default(
+                                    &params.game,
+                                    &mut params.storage,
+                                )
+                                .relay_bandwidth_cap;
                                 ONLINE_MATCHMAKER
                                     .try_send(OnlineMatchmakerRequest::SearchForGame {
                                         addr: matchmaking_server.clone(),
                                         player_count: *player_count,
+                                        match_data: lobby_match_data(lobby_code.as_deref()),
+                                        bandwidth_cap: (bandwidth_cap > 0)
+                                            .then_some(bandwidth_cap * 1024),
                                     })
                                     .unwrap();
                             }
@@ -496,6 +763,7 @@ impl<'w, 's> WidgetSystem for MatchmakingMenu<'w, 's> {
                                         online_socket,
                                         player_idx,
                                         player_count: _,
+                                        random_seed: _,
                                     } => {
                                         info!(?player_idx, "Starting network game");
                                         params.commands.insert_resource(NetworkMatchSocket(
@@ -504,8 +772,15 @@ impl<'w, 's> WidgetSystem for MatchmakingMenu<'w, 's> {
 
                                         *status = default();
                                         search_state = default();
+                                        params.lobby_invites.current_lobby = None;
                                         *params.menu_page = MenuPage::PlayerSelect;
                                     }
+                                    OnlineMatchmakerResponse::Error(e) => {
+                                        warn!("Could not search for a match: {e}");
+                                        *status = Status::Idle;
+                                        search_state = default();
+                                        params.lobby_invites.current_lobby = None;
+                                    }
                                 }
                             }
 
@@ -521,9 +796,22 @@ impl<'w, 's> WidgetSystem for MatchmakingMenu<'w, 's> {
                                         .try_send(OnlineMatchmakerRequest::StopSearch)
                                         .unwrap();
                                     search_state = default();
+                                    params.lobby_invites.current_lobby = None;
                                     *status = Status::Idle;
                                 }
 
+                                if params.lobby_invites.available
+                                    && params.lobby_invites.current_lobby.is_some()
+                                    && BorderedButton::themed(
+                                        small_button_style,
+                                        &params.localization.get("invite-friends"),
+                                    )
+                                    .show(ui)
+                                    .clicked()
+                                {
+                                    params.lobby_invites.open_invite_dialog = true;
+                                }
+
                                 ui.themed_label(
                                     smaller_text_style,
                                     &match search_state {
@@ -543,6 +831,236 @@ impl<'w, 's> WidgetSystem for MatchmakingMenu<'w, 's> {
                             });
                         }
                     }
+
+                    // Ranked game
+                    MatchKind::Ranked => {
+                        let rating = params
+                            .storage
+                            .get::<OnlineRating>(OnlineRating::STORAGE_KEY)
+                            .unwrap_or_default();
+                        ui.themed_label(
+                            normal_text_style,
+                            &params.localization.get(&format!(
+                                "ranked-rating?rating={}&matches={}",
+                                rating.rating.round(),
+                                rating.matches_played
+                            )),
+                        );
+                        ui.add_space(normal_text_style.size);
+
+                        if params.ranked_queue.is_active() {
+                            ui.horizontal(|ui| {
+                                if BorderedButton::themed(
+                                    small_button_style,
+                                    &params.localization.get("leave-queue"),
+                                )
+                                .show(ui)
+                                .clicked()
+                                {
+                                    params.ranked_queue.leave();
+                                }
+
+                                let status = match params.ranked_queue.status {
+                                    RankedQueueStatus::Reconnecting { attempt, .. } => {
+                                        params.localization.get(&format!(
+                                            "ranked-reconnecting?attempt={attempt}&max={MAX_RECONNECT_ATTEMPTS}"
+                                        ))
+                                    }
+                                    RankedQueueStatus::Searching(_) => {
+                                        let (min, max) = params.ranked_queue.rating_range();
+                                        params.localization.get(&format!(
+                                            "ranked-rating-range?min={min}&max={max}"
+                                        ))
+                                    }
+                                    _ => params.localization.get("connecting"),
+                                };
+                                ui.themed_label(smaller_text_style, &status);
+                            });
+                        } else {
+                            if params.ranked_queue.status == RankedQueueStatus::Failed {
+                                ui.themed_label(
+                                    smaller_text_style,
+                                    &params.localization.get("ranked-connection-failed"),
+                                );
+                                ui.add_space(normal_text_style.size / 2.0);
+                            }
+
+                            if BorderedButton::themed(
+                                small_button_style,
+                                &params.localization.get("join-queue"),
+                            )
+                            .show(ui)
+                            .clicked()
+                            {
+                                let settings = Settings::get_stored_or_default(
+                                    &params.game,
+                                    &mut params.storage,
+                                );
+                                let bandwidth_cap = settings.relay_bandwidth_cap;
+                                params.ranked_queue.join(
+                                    settings.matchmaking_server.clone(),
+                                    rating.rating,
+                                    (bandwidth_cap > 0).then_some(bandwidth_cap * 1024),
+                                );
+                            }
+                        }
+                    }
+
+                    // Quick match
+                    MatchKind::QuickMatch(QuickMatchState {
+                        player_count,
+                        region,
+                    }) => {
+                        let region = region.get_or_insert_with(|| {
+                            params
+                                .storage
+                                .get::<MatchRegion>(MatchRegion::STORAGE_KEY)
+                                .unwrap_or_default()
+                        });
+                        let queue = &mut params.quick_match_queue;
+
+                        if queue.is_active() {
+                            ui.horizontal(|ui| {
+                                ui.add(egui::Spinner::new().size(normal_text_style.size));
+                                let status = match queue.status {
+                                    QuickMatchStatus::Searching(current) => {
+                                        params.localization.get(&format!(
+                                            "quick-match-searching?region={}&current={current}&total={}",
+                                            params.localization.get(&format!(
+                                                "region-{}",
+                                                queue.region.code()
+                                            )),
+                                            queue.player_count
+                                        ))
+                                    }
+                                    _ => params.localization.get("connecting"),
+                                };
+                                ui.themed_label(
+                                    normal_text_style,
+                                    &format!("{status}{}", searching_dots(queue.elapsed)),
+                                );
+                            });
+                            let elapsed = queue.elapsed.as_secs();
+                            ui.themed_label(
+                                smaller_text_style,
+                                &params.localization.get(&format!(
+                                    "quick-match-elapsed?time={}:{:02}",
+                                    elapsed / 60,
+                                    elapsed % 60
+                                )),
+                            );
+                            ui.themed_label(
+                                smaller_text_style,
+                                &params.localization.get("quick-match-similar-rating"),
+                            );
+                            ui.add_space(normal_text_style.size / 2.0);
+
+                            if BorderedButton::themed(
+                                small_button_style,
+                                &params.localization.get("leave-queue"),
+                            )
+                            .show(ui)
+                            .clicked()
+                            {
+                                queue.leave();
+                            }
+                        } else {
+                            if queue.status == QuickMatchStatus::Failed {
+                                ui.themed_label(
+                                    smaller_text_style,
+                                    &params.localization.get("ranked-connection-failed"),
+                                );
+                                ui.add_space(normal_text_style.size / 2.0);
+                            }
+
+                            ui.horizontal(|ui| {
+                                ui.themed_label(
+                                    normal_text_style,
+                                    &params.localization.get("region"),
+                                );
+                                if BorderedButton::themed(small_button_style, "<")
+                                    .min_size(egui::vec2(normal_text_style.size * 2.0, 0.0))
+                                    .show(ui)
+                                    .clicked()
+                                {
+                                    *region = region.cycle(-1);
+                                }
+                                ui.themed_label(
+                                    normal_text_style,
+                                    &params
+                                        .localization
+                                        .get(&format!("region-{}", region.code())),
+                                );
+                                if BorderedButton::themed(small_button_style, ">")
+                                    .min_size(egui::vec2(normal_text_style.size * 2.0, 0.0))
+                                    .show(ui)
+                                    .clicked()
+                                {
+                                    *region = region.cycle(1);
+                                }
+                            });
+
+                            ui.add_space(normal_text_style.size / 2.0);
+                            ui.horizontal(|ui| {
+                                ui.themed_label(
+                                    normal_text_style,
+                                    &params.localization.get("player-count"),
+                                );
+
+                                ui.scope(|ui| {
+                                    ui.set_enabled(*player_count > 2);
+                                    if BorderedButton::themed(small_button_style, "-")
+                                        .min_size(egui::vec2(normal_text_style.size * 2.0, 0.0))
+                                        .show(ui)
+                                        .clicked()
+                                    {
+                                        *player_count =
+                                            player_count.saturating_sub(1).clamp(2, MAX_PLAYERS);
+                                    }
+                                });
+                                ui.themed_label(normal_text_style, &player_count.to_string());
+                                ui.scope(|ui| {
+                                    ui.set_enabled(*player_count < MAX_PLAYERS);
+                                    if BorderedButton::themed(small_button_style, "+")
+                                        .min_size(egui::vec2(normal_text_style.size * 2.0, 0.0))
+                                        .show(ui)
+                                        .clicked()
+                                    {
+                                        *player_count =
+                                            player_count.saturating_add(1).clamp(2, MAX_PLAYERS);
+                                    }
+                                });
+                            });
+
+                            ui.add_space(normal_text_style.size);
+                            if BorderedButton::themed(
+                                small_button_style,
+                                &params.localization.get("join-queue"),
+                            )
+                            .show(ui)
+                            .clicked()
+                            {
+                                params.storage.set(MatchRegion::STORAGE_KEY, &*region);
+                                params.storage.save();
+                                let rating = params
+                                    .storage
+                                    .get::<OnlineRating>(OnlineRating::STORAGE_KEY)
+                                    .unwrap_or_default();
+                                let settings = Settings::get_stored_or_default(
+                                    &params.game,
+                                    &mut params.storage,
+                                );
+                                let bandwidth_cap = settings.relay_bandwidth_cap;
+                                queue.join(
+                                    settings.matchmaking_server.clone(),
+                                    *region,
+                                    *player_count,
+                                    rating.rating,
+                                    (bandwidth_cap > 0).then_some(bandwidth_cap * 1024),
+                                );
+                            }
+                        }
+                    }
                 }
 
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Min), |ui| {
@@ -551,12 +1069,16 @@ impl<'w, 's> WidgetSystem for MatchmakingMenu<'w, 's> {
                         .clicked()
                         || menu_input.pressed(MenuAction::Back)
                     {
+                        params.ranked_queue.leave();
+                        params.quick_match_queue.leave();
+                        params.match_upgrade.cancel();
                         match status {
                             Status::Idle => (),
                             Status::Searching => {
                                 if let Err(err) = ONLINE_MATCHMAKER.try_send(OnlineMatchmakerRequest::StopSearch){
                                     error!("Error stopping search: {:?}", err);
                                 }
+                                params.lobby_invites.current_lobby = None;
 
                                 *status = Status::Idle;
                             }
@@ -1,6 +1,14 @@
 use crate::loading::PlayerInputCollector;
+use crate::networking::{
+    chat::{chat_box_ui, TextChat},
+    moderation::Moderation,
+    NetworkMatchSocket, SocketTarget,
+};
 #[cfg(not(target_arch = "wasm32"))]
-use crate::networking::{NetworkMatchSocket, SocketTarget};
+use crate::networking::{
+    dedicated::{self, DedicatedHostMessage},
+    proto::TaggedMessage,
+};
 
 use bones_lib::prelude::{key, Key, KeyError};
 use rand::Rng;
@@ -22,6 +30,8 @@ pub struct PlayerSlot {
     pub selected_player: bones::Handle<PlayerMeta>,
     pub selected_hat: Option<bones::Handle<HatMeta>>,
     pub is_ai: bool,
+    /// How well the player plays, if it is an AI player.
+    pub ai_difficulty: AiDifficulty,
 }
 
 /// Network message that may be sent during player selection.
@@ -40,8 +50,13 @@ pub struct PlayerSelectMenu<'w, 's> {
     keyboard_input: Res<'w, Input<KeyCode>>,
     player_select_state: ResMut<'w, PlayerSelectState>,
     menu_input: Query<'w, 's, &'static mut ActionState<MenuAction>>,
-    #[cfg(not(target_arch = "wasm32"))]
     network_socket: Option<Res<'w, NetworkMatchSocket>>,
+    moderation: ResMut<'w, Moderation>,
+    text_chat: ResMut<'w, TextChat>,
+    #[cfg(not(target_arch = "wasm32"))]
+    session_manager: SessionManager<'w, 's>,
+    #[cfg(not(target_arch = "wasm32"))]
+    map_assets: Res<'w, Assets<MapMeta>>,
 }
 
 impl<'w, 's> WidgetSystem for PlayerSelectMenu<'w, 's> {
@@ -53,11 +68,9 @@ impl<'w, 's> WidgetSystem for PlayerSelectMenu<'w, 's> {
         id: WidgetId,
         _: (),
     ) {
-        #[cfg_attr(target_arch = "wasm32", allow(unused_mut))]
         let mut params: PlayerSelectMenu = state.get_mut(world);
         let is_online = false;
 
-        #[cfg(not(target_arch = "wasm32"))]
         handle_match_setup_messages(&mut params);
 
         // Whether or not the continue button should be enabled
@@ -73,7 +86,6 @@ impl<'w, 's> WidgetSystem for PlayerSelectMenu<'w, 's> {
         }
         let may_continue = ready_players >= 1 && unconfirmed_players == 0;
 
-        #[cfg(not(target_arch = "wasm32"))]
         if let Some(socket) = &params.network_socket {
             if may_continue {
                 // The first player picks the map
@@ -141,8 +153,7 @@ impl<'w, 's> WidgetSystem for PlayerSelectMenu<'w, 's> {
                         } else {
                             *params.menu_page = MenuPage::Home;
 
-                            #[cfg(not(target_arch = "wasm32"))]
-                            if let Some(socket) = params.network_socket {
+                            if let Some(socket) = &params.network_socket {
                                 socket.close();
                             }
 
@@ -168,13 +179,23 @@ impl<'w, 's> WidgetSystem for PlayerSelectMenu<'w, 's> {
 
                     if continue_button.clicked()
                         || ((params.menu_input.single().just_pressed(MenuAction::Start)
-                            || params.keyboard_input.just_pressed(KeyCode::Return))
+                            || (params.keyboard_input.just_pressed(KeyCode::Return)
+                                && !ui.ctx().wants_keyboard_input()))
                             && may_continue)
                     {
                         *params.menu_page = MenuPage::MapSelect { is_waiting: false };
                     }
                 });
 
+                // Chat with the other players of the lobby
+                if let Some(socket) = &params.network_socket {
+                    ui.add_space(normal_button_style.font.size);
+                    ui.vertical(|ui| {
+                        ui.set_max_width(ui.available_width() / 2.0);
+                        chat_box_ui(ui, &params.localization, socket, &mut params.text_chat);
+                    });
+                }
+
                 ui.add_space(normal_button_style.font.size);
 
                 ui.vertical_centered(|ui| {
@@ -199,12 +220,32 @@ impl<'w, 's> WidgetSystem for PlayerSelectMenu<'w, 's> {
     }
 }
 
-#[cfg(not(target_arch = "wasm32"))]
 fn handle_match_setup_messages(params: &mut PlayerSelectMenu) {
     if let Some(socket) = &params.network_socket {
         let datas: Vec<(usize, Vec<u8>)> = socket.recv_reliable();
 
         for (player, data) in datas {
+            if params.moderation.handle_message(socket, player, &data)
+                || params
+                    .text_chat
+                    .handle_message(player, &data, bevy::utils::Instant::now())
+            {
+                continue;
+            }
+
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Some(message) = DedicatedHostMessage::decode(&data) {
+                let core_meta = params.session_manager.core_meta_arc.clone();
+                if let Some(runner) =
+                    dedicated::match_runner(player, socket, &core_meta, &params.map_assets, message)
+                {
+                    info!("Dedicated host started the match");
+                    *params.menu_page = MenuPage::Home;
+                    params.session_manager.start_network_runner(runner);
+                }
+                continue;
+            }
+
             match postcard::from_bytes::<PlayerSelectMessage>(&data) {
                 Ok(message) => match message {
                     PlayerSelectMessage::SelectPlayer(player_handle) => {
@@ -297,8 +338,8 @@ struct PlayerSelectPanel<'w, 's> {
             &'static InputMap<PlayerAction>,
         ),
     >,
-    #[cfg(not(target_arch = "wasm32"))]
     network_socket: Option<Res<'w, NetworkMatchSocket>>,
+    moderation: ResMut<'w, Moderation>,
 }
 
 impl<'w, 's> WidgetSystem for PlayerSelectPanel<'w, 's> {
@@ -312,9 +353,6 @@ impl<'w, 's> WidgetSystem for PlayerSelectPanel<'w, 's> {
     ) {
         let mut params: PlayerSelectPanel = state.get_mut(world);
 
-        #[cfg(target_arch = "wasm32")]
-        let is_network = false;
-        #[cfg(not(target_arch = "wasm32"))]
         let is_network = params.network_socket.is_some();
 
         let player_id = args;
@@ -326,10 +364,8 @@ impl<'w, 's> WidgetSystem for PlayerSelectPanel<'w, 's> {
             .unwrap()
             .2;
 
-        #[cfg(not(target_arch = "wasm32"))]
         let dummy_actions = default();
         let (player_actions, player_action_map) = if is_network {
-            #[cfg(not(target_arch = "wasm32"))]
             if let Some(socket) = &params.network_socket {
                 let actions = if player_id == socket.player_idx() {
                     params
@@ -346,9 +382,6 @@ impl<'w, 's> WidgetSystem for PlayerSelectPanel<'w, 's> {
             } else {
                 unreachable!();
             }
-
-            #[cfg(target_arch = "wasm32")]
-            unreachable!()
         } else {
             let actions = params
                 .players
@@ -361,11 +394,14 @@ impl<'w, 's> WidgetSystem for PlayerSelectPanel<'w, 's> {
         };
 
         let slot = &mut params.player_select_state.slots[player_id];
-        #[cfg(not(target_arch = "wasm32"))]
         if let Some(socket) = &params.network_socket {
-            // Don't show panels for non-connected players.
+            // Don't show panels for non-connected or kicked players.
             if player_id + 1 > socket.player_count() {
                 return;
+            } else if params.moderation.removed[player_id] {
+                slot.active = false;
+                slot.confirmed = false;
+                return;
             } else {
                 slot.active = true;
             }
@@ -391,7 +427,6 @@ impl<'w, 's> WidgetSystem for PlayerSelectPanel<'w, 's> {
                 slot.confirmed = true;
             }
 
-            #[cfg(not(target_arch = "wasm32"))]
             if let Some(socket) = &params.network_socket {
                 socket.send_reliable(
                     SocketTarget::All,
@@ -410,7 +445,6 @@ impl<'w, 's> WidgetSystem for PlayerSelectPanel<'w, 's> {
                 slot.confirmed = false;
             }
 
-            #[cfg(not(target_arch = "wasm32"))]
             if let Some(socket) = &params.network_socket {
                 socket.send_reliable(
                     SocketTarget::All,
@@ -453,7 +487,6 @@ impl<'w, 's> WidgetSystem for PlayerSelectPanel<'w, 's> {
                 };
                 *player_hat = next_idx.map(|idx| params.core.player_hats.get(idx).unwrap().clone());
 
-                #[cfg(not(target_arch = "wasm32"))]
                 if let Some(socket) = &params.network_socket {
                     socket.send_reliable(
                         SocketTarget::All,
@@ -493,7 +526,6 @@ impl<'w, 's> WidgetSystem for PlayerSelectPanel<'w, 's> {
                     }
                 }
 
-                #[cfg(not(target_arch = "wasm32"))]
                 if let Some(socket) = &params.network_socket {
                     socket.send_reliable(
                         SocketTarget::All,
@@ -517,20 +549,32 @@ impl<'w, 's> WidgetSystem for PlayerSelectPanel<'w, 's> {
                 let heading_font = &params.game.ui_theme.font_styles.heading;
 
                 // Marker for current player in online matches
-                #[cfg(not(target_arch = "wasm32"))]
                 if let Some(socket) = &params.network_socket {
                     if socket.player_idx() == player_id {
                         ui.vertical_centered(|ui| {
                             ui.themed_label(normal_font, &params.localization.get("you-marker"));
                         });
+                    // The host may kick the other players out of the lobby
+                    } else if socket.player_idx() == 0 {
+                        ui.horizontal(|ui| {
+                            for (label, ban) in [("kick", false), ("ban", true)] {
+                                if BorderedButton::themed(
+                                    &params.game.ui_theme.button_styles.small,
+                                    &params.localization.get(label),
+                                )
+                                .show(ui)
+                                .clicked()
+                                {
+                                    params.moderation.kick(socket, player_id, ban);
+                                }
+                            }
+                        });
                     } else {
                         ui.add_space(normal_font.size);
                     }
                 } else {
                     ui.add_space(normal_font.size);
                 }
-                #[cfg(target_arch = "wasm32")]
-                ui.add_space(normal_font.size);
 
                 if slot.active {
                     ui.vertical_centered(|ui| {
@@ -590,6 +634,25 @@ impl<'w, 's> WidgetSystem for PlayerSelectPanel<'w, 's> {
                                     &heading_font.colored(params.game.ui_theme.colors.positive),
                                     &params.localization.get("ai-player"),
                                 );
+                                let difficulty_label = match slot.ai_difficulty {
+                                    AiDifficulty::Easy => "ai-difficulty-easy",
+                                    AiDifficulty::Normal => "ai-difficulty-normal",
+                                    AiDifficulty::Hard => "ai-difficulty-hard",
+                                };
+                                if BorderedButton::themed(
+                                    &params.game.ui_theme.button_styles.small,
+                                    &params.localization.get(difficulty_label),
+                                )
+                                .show(ui)
+                                .clicked()
+                                {
+                                    let current = AiDifficulty::ALL
+                                        .iter()
+                                        .position(|x| *x == slot.ai_difficulty)
+                                        .unwrap_or_default();
+                                    slot.ai_difficulty =
+                                        AiDifficulty::ALL[(current + 1) % AiDifficulty::ALL.len()];
+                                }
                                 if BorderedButton::themed(
                                     &params.game.ui_theme.button_styles.normal,
                                     &params.localization.get("remove-ai-player"),
@@ -600,6 +663,7 @@ impl<'w, 's> WidgetSystem for PlayerSelectPanel<'w, 's> {
                                     slot.confirmed = false;
                                     slot.active = false;
                                     slot.is_ai = false;
+                                    slot.ai_difficulty = default();
                                 }
                             }
                         });
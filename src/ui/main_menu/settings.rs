@@ -3,6 +3,7 @@ use leafwing_input_manager::{axislike::SingleAxis, user_input::InputKind, Action
 
 use super::*;
 
+mod accessibility;
 mod controls;
 mod networking;
 mod sound;
@@ -14,13 +15,16 @@ pub enum SettingsTab {
     #[allow(unused)] // TODO: Just for now until we get sound settings setup
     Sound,
     Networking,
+    Accessibility,
 }
 
 impl SettingsTab {
     const TABS: &'static [(Self, &'static str)] = &[
         (Self::Controls, "controls"),
-        (Self::Networking, "networking"), // For now, hide the sound tab because we don't have it working yet.
-                                          // (Self::Sound, "sound")
+        (Self::Networking, "networking"),
+        (Self::Accessibility, "accessibility"),
+        // For now, hide the sound tab because we don't have it working yet.
+        // (Self::Sound, "sound")
     ];
 }
 
@@ -40,11 +44,22 @@ pub struct SettingsMenu<'w, 's> {
     menu_page: ResMut<'w, MenuPage>,
     modified_settings: ResMut<'w, ModifiedSettings>,
     currently_binding_input_idx: Local<'s, Option<usize>>,
+    /// The player whose gamepad controls are shown, or `None` for the controls shared by all
+    /// players.
+    gamepad_profile: Local<'s, Option<usize>>,
     localization: Res<'w, Localization>,
     adjacencies: ResMut<'w, WidgetAdjacencies>,
     storage: ResMut<'w, Storage>,
     control_inputs: controls::ControlInputBindingEvents<'w, 's>,
     menu_input: Query<'w, 's, &'static mut ActionState<MenuAction>>,
+    input_collectors: Query<
+        'w,
+        's,
+        (
+            &'static PlayerInputCollector,
+            &'static mut InputMap<PlayerAction>,
+        ),
+    >,
     #[system_param(ignore)]
     _phantom: PhantomData<(&'w (), &'s ())>,
 }
@@ -178,6 +193,17 @@ impl<'w, 's> WidgetSystem for SettingsMenu<'w, 's> {
                                     // Persist to storage
                                     params.storage.save();
 
+                                    // Apply the new controls without restarting the game
+                                    let controls = &params
+                                        .modified_settings
+                                        .0
+                                        .as_ref()
+                                        .unwrap()
+                                        .player_controls;
+                                    for (collector, mut input_map) in &mut params.input_collectors {
+                                        *input_map = controls.get_input_map(collector.0);
+                                    }
+
                                     // Go to main menu
                                     *params.menu_page = MenuPage::Home;
                                     ui.ctx().clear_focus();
@@ -214,6 +240,13 @@ impl<'w, 's> WidgetSystem for SettingsMenu<'w, 's> {
                                     &tabs,
                                     &bottom_buttons,
                                 ),
+                                SettingsTab::Accessibility => {
+                                    accessibility::accessibility_settings_ui(
+                                        &mut params,
+                                        ui,
+                                        bottom_buttons[1].clicked(),
+                                    )
+                                }
                                 SettingsTab::Sound => sound::sound_settings_ui(ui, &params.game),
                             }
                         });
@@ -0,0 +1,78 @@
+use super::*;
+
+/// Render the accessibility settings UI
+pub fn accessibility_settings_ui(params: &mut SettingsMenu, ui: &mut egui::Ui, should_reset: bool) {
+    let settings = params.modified_settings.0.as_mut().unwrap();
+
+    if should_reset {
+        let defaults = &params.game.default_settings;
+        settings.text_to_speech = defaults.text_to_speech;
+        settings.reduced_motion = defaults.reduced_motion;
+        settings.high_contrast = defaults.high_contrast;
+        settings.damage_numbers = defaults.damage_numbers;
+        settings.action_modes = defaults.action_modes;
+    }
+
+    let bigger_font = &params.game.ui_theme.font_styles.bigger;
+
+    ui.add_space(bigger_font.size);
+
+    for (label, enabled) in [
+        ("text-to-speech", &mut settings.text_to_speech),
+        ("reduced-motion", &mut settings.reduced_motion),
+        ("high-contrast", &mut settings.high_contrast),
+        ("damage-numbers", &mut settings.damage_numbers),
+    ] {
+        ui.horizontal(|ui| {
+            ui.add_space(bigger_font.size * 2.0);
+            ui.themed_label(bigger_font, &format!("{}:", params.localization.get(label)));
+
+            let toggle_label = if *enabled {
+                params.localization.get("enabled")
+            } else {
+                params.localization.get("disabled")
+            };
+            if BorderedButton::themed(&params.game.ui_theme.button_styles.normal, &toggle_label)
+                .show(ui)
+                .clicked()
+            {
+                *enabled = !*enabled;
+            }
+        });
+    }
+
+    ui.add_space(bigger_font.size);
+
+    // Whether the grab and crouch actions are held or toggled, for each player
+    let mode_label = |mode: ActionMode| match mode {
+        ActionMode::Hold => params.localization.get("hold"),
+        ActionMode::Toggle => params.localization.get("toggle"),
+    };
+    for (i, modes) in settings.action_modes.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            ui.add_space(bigger_font.size * 2.0);
+            ui.themed_label(
+                bigger_font,
+                &params
+                    .localization
+                    .get(&format!("action-modes?player={}", i + 1)),
+            );
+
+            for (label, mode) in [("grab", &mut modes.grab), ("crouch", &mut modes.crouch)] {
+                ui.themed_label(bigger_font, &format!("{}:", params.localization.get(label)));
+                if BorderedButton::themed(
+                    &params.game.ui_theme.button_styles.normal,
+                    &mode_label(*mode),
+                )
+                .show(ui)
+                .clicked()
+                {
+                    *mode = match mode {
+                        ActionMode::Hold => ActionMode::Toggle,
+                        ActionMode::Toggle => ActionMode::Hold,
+                    };
+                }
+            }
+        });
+    }
+}
@@ -18,6 +18,7 @@ pub fn controls_settings_ui(
         params.modified_settings.0.as_mut().unwrap().player_controls =
             params.game.default_settings.player_controls.clone();
     }
+    let small_button_style = &ui_theme.button_styles.small;
 
     // Get the font meta for the table headings and labels
     let bigger_font = &ui_theme
@@ -31,8 +32,54 @@ pub fn controls_settings_ui(
 
     ui.add_space(bigger_font.size * 0.1);
 
+    // Choose which player's gamepad controls are shown: the ones shared by all players, or the
+    // custom profile of a single player.
+    let profile_buttons = ui
+        .horizontal(|ui| {
+            ui.themed_label(
+                label_font,
+                &format!("{}:", params.localization.get("gamepad-profile")),
+            );
+
+            let profile = *params.gamepad_profile;
+            let profile_label = match profile {
+                None => params.localization.get("all-players"),
+                Some(player) => params
+                    .localization
+                    .get(&format!("player-profile?player={}", player + 1)),
+            };
+            let profile_button =
+                BorderedButton::themed(small_button_style, &profile_label).show(ui);
+            if profile_button.clicked() {
+                *params.gamepad_profile = match profile {
+                    None => Some(0),
+                    Some(player) if player + 1 < MAX_PLAYERS => Some(player + 1),
+                    Some(_) => None,
+                };
+            }
+
+            let mut buttons = vec![profile_button];
+            if let Some(player) = profile {
+                let controls = &mut params.modified_settings.0.as_mut().unwrap().player_controls;
+                if controls.gamepad_profiles[player].is_some() {
+                    let shared_button = BorderedButton::themed(
+                        small_button_style,
+                        &params.localization.get("use-shared-controls"),
+                    )
+                    .show(ui);
+                    if shared_button.clicked() {
+                        controls.gamepad_profiles[player] = None;
+                    }
+                    buttons.push(shared_button);
+                } else {
+                    ui.themed_label(label_font, &params.localization.get("shared-controls"));
+                }
+            }
+            buttons
+        })
+        .inner;
+
     // Calculate the row height so that it can fit the input buttons
-    let small_button_style = &ui_theme.button_styles.small;
     let row_height = small_button_style.font.size
         + small_button_style.padding.top
         + small_button_style.padding.bottom;
@@ -40,6 +87,21 @@ pub fn controls_settings_ui(
     // Mutably borrow the player controlls settings
     let controls = &mut params.modified_settings.0.as_mut().unwrap().player_controls;
 
+    // Edit a copy of the gamepad controls, which becomes the player's profile once it is changed
+    let profile = *params.gamepad_profile;
+    let shown_gamepad = match profile {
+        Some(player) => controls.gamepad_controls(player).clone(),
+        None => controls.gamepad.clone(),
+    };
+    let mut gamepad = shown_gamepad.clone();
+
+    // Find the inputs bound to several actions. Both keyboard players share the same keyboard.
+    let conflicts = [
+        controls.keyboard1.conflicts(Some(&controls.keyboard2)),
+        controls.keyboard2.conflicts(Some(&controls.keyboard1)),
+        gamepad.conflicts(None),
+    ];
+
     // Build the table rows of control bindings as an array of mutable InputKind's
     let mut input_rows = [
         (
@@ -47,7 +109,7 @@ pub fn controls_settings_ui(
             [
                 &mut controls.keyboard1.movement.up,
                 &mut controls.keyboard2.movement.up,
-                &mut controls.gamepad.movement.up,
+                &mut gamepad.movement.up,
             ],
         ),
         (
@@ -55,7 +117,7 @@ pub fn controls_settings_ui(
             [
                 &mut controls.keyboard1.movement.down,
                 &mut controls.keyboard2.movement.down,
-                &mut controls.gamepad.movement.down,
+                &mut gamepad.movement.down,
             ],
         ),
         (
@@ -63,7 +125,7 @@ pub fn controls_settings_ui(
             [
                 &mut controls.keyboard1.movement.left,
                 &mut controls.keyboard2.movement.left,
-                &mut controls.gamepad.movement.left,
+                &mut gamepad.movement.left,
             ],
         ),
         (
@@ -71,7 +133,7 @@ pub fn controls_settings_ui(
             [
                 &mut controls.keyboard1.movement.right,
                 &mut controls.keyboard2.movement.right,
-                &mut controls.gamepad.movement.right,
+                &mut gamepad.movement.right,
             ],
         ),
         (
@@ -79,7 +141,7 @@ pub fn controls_settings_ui(
             [
                 &mut controls.keyboard1.jump,
                 &mut controls.keyboard2.jump,
-                &mut controls.gamepad.jump,
+                &mut gamepad.jump,
             ],
         ),
         (
@@ -87,7 +149,7 @@ pub fn controls_settings_ui(
             [
                 &mut controls.keyboard1.grab,
                 &mut controls.keyboard2.grab,
-                &mut controls.gamepad.grab,
+                &mut gamepad.grab,
             ],
         ),
         (
@@ -95,7 +157,7 @@ pub fn controls_settings_ui(
             [
                 &mut controls.keyboard1.shoot,
                 &mut controls.keyboard2.shoot,
-                &mut controls.gamepad.shoot,
+                &mut gamepad.shoot,
             ],
         ),
         (
@@ -103,7 +165,7 @@ pub fn controls_settings_ui(
             [
                 &mut controls.keyboard1.slide,
                 &mut controls.keyboard2.slide,
-                &mut controls.gamepad.slide,
+                &mut gamepad.slide,
             ],
         ),
     ];
@@ -143,7 +205,7 @@ pub fn controls_settings_ui(
             let mut input_idx = 0;
 
             // Loop through the input rows
-            for (title, inputs) in &mut input_rows {
+            for (row_idx, (title, inputs)) in input_rows.iter_mut().enumerate() {
                 body.row(row_height, |mut row| {
                     // Add row label
                     row.col(|ui| {
@@ -161,11 +223,14 @@ pub fn controls_settings_ui(
 
                         // Render the button
                         row.col(|ui| {
-                            let button = BorderedButton::themed(
-                                &ui_theme.button_styles.small,
-                                format_input(input),
-                            )
-                            .show(ui);
+                            // Mark the inputs bound to several actions
+                            let mut label = format_input(input);
+                            if conflicts[button_idx][row_idx] {
+                                label = format!("! {label}");
+                            }
+                            let button =
+                                BorderedButton::themed(&ui_theme.button_styles.small, label)
+                                    .show(ui);
 
                             // Start an input binding if the button is clicked
                             if button.clicked() {
@@ -253,30 +318,60 @@ pub fn controls_settings_ui(
             }
         });
 
+    let row_count = input_rows.len();
+
+    // Save the gamepad controls where they are shown from
+    if gamepad != shown_gamepad {
+        let controls = &mut params.modified_settings.0.as_mut().unwrap().player_controls;
+        match profile {
+            Some(player) => controls.gamepad_profiles[player] = Some(gamepad),
+            None => controls.gamepad = gamepad,
+        }
+    }
+
+    if conflicts.iter().flatten().any(|x| *x) {
+        ui.themed_label(label_font, &params.localization.get("conflicting-inputs"));
+    }
+
+    // The gamepad profile buttons are below the settings tabs
+    for tab in settings_tabs {
+        params.adjacencies.widget(tab).above(&profile_buttons[0]);
+    }
+    params
+        .adjacencies
+        .widget(&profile_buttons[0])
+        .to_right_of(&settings_tabs[settings_tabs.len() - 1]);
+    if let [profile_button, shared_button] = &profile_buttons[..] {
+        params
+            .adjacencies
+            .widget(profile_button)
+            .to_left_of(shared_button);
+    }
+
     // Set adjacency for all of the gamepad input buttons
-    for row_idx in 0..input_rows.len() {
+    for row_idx in 0..row_count {
         if row_idx == 0 {
             // Reverse button order here so that the first input button gets priority when
-            // navigating down from the tabs.
+            // navigating down from the profile buttons.
             for i in (0..3).rev() {
                 let button = &input_buttons[row_idx * 3 + i];
 
-                // The top row of buttons is below the settings tabs
-                for tab in settings_tabs {
-                    params.adjacencies.widget(tab).above(button);
+                // The top row of buttons is below the gamepad profile buttons
+                for profile_button in &profile_buttons {
+                    params.adjacencies.widget(profile_button).above(button);
                 }
 
-                // The last tab is considered to the left of the first button
+                // The last profile button is considered to the left of the first button
                 if i == 0 {
                     params
                         .adjacencies
                         .widget(button)
-                        .to_right_of(&settings_tabs[settings_tabs.len() - 1]);
+                        .to_right_of(&profile_buttons[profile_buttons.len() - 1]);
                 }
             }
 
         // If this is the last row, the input buttons are above the bottom buttons
-        } else if row_idx == input_rows.len() - 1 {
+        } else if row_idx == row_count - 1 {
             for i in 0..3 {
                 let button_above = &input_buttons[(row_idx - 1) * 3 + i];
                 let button = &input_buttons[row_idx * 3 + i];
@@ -1,5 +1,8 @@
 use super::*;
 
+/// The relay bandwidth caps to choose from, in kilobytes per second, `0` meaning no limit.
+const RELAY_BANDWIDTH_CAP_OPTIONS: [u32; 5] = [0, 16, 32, 64, 128];
+
 pub fn networking_settings_ui(
     params: &mut SettingsMenu,
     ui: &mut egui::Ui,
@@ -56,4 +59,110 @@ pub fn networking_settings_ui(
             .widget(last_bottom_button)
             .to_left_of(first_top_tab);
     });
+
+    if should_reset {
+        settings.relay_bandwidth_cap = params.game.default_settings.relay_bandwidth_cap;
+    }
+
+    ui.add_space(bigger_font.size);
+
+    // Limit the traffic sent through the matchmaking server's relay
+    ui.horizontal(|ui| {
+        ui.add_space(bigger_font.size * 2.0);
+        ui.themed_label(
+            bigger_font,
+            &format!("{}:", params.localization.get("relay-bandwidth-cap")),
+        );
+
+        let cap_label = match settings.relay_bandwidth_cap {
+            0 => params.localization.get("relay-bandwidth-unlimited"),
+            cap => params
+                .localization
+                .get(&format!("relay-bandwidth-kbps?kbps={cap}")),
+        };
+        if BorderedButton::themed(&params.game.ui_theme.button_styles.normal, &cap_label)
+            .show(ui)
+            .clicked()
+        {
+            let current = RELAY_BANDWIDTH_CAP_OPTIONS
+                .iter()
+                .position(|&x| x == settings.relay_bandwidth_cap);
+            settings.relay_bandwidth_cap = RELAY_BANDWIDTH_CAP_OPTIONS
+                [current.map_or(0, |i| (i + 1) % RELAY_BANDWIDTH_CAP_OPTIONS.len())];
+        }
+    });
+
+    if should_reset {
+        settings.network_hud = params.game.default_settings.network_hud;
+    }
+
+    ui.add_space(bigger_font.size);
+
+    // Show the ping and rollback diagnostics during network matches
+    ui.horizontal(|ui| {
+        ui.add_space(bigger_font.size * 2.0);
+        ui.themed_label(
+            bigger_font,
+            &format!("{}:", params.localization.get("network-hud")),
+        );
+
+        let toggle_label = if settings.network_hud {
+            params.localization.get("enabled")
+        } else {
+            params.localization.get("disabled")
+        };
+        if BorderedButton::themed(&params.game.ui_theme.button_styles.normal, &toggle_label)
+            .show(ui)
+            .clicked()
+        {
+            settings.network_hud = !settings.network_hud;
+        }
+    });
+
+    #[cfg(all(feature = "leaderboard", not(target_arch = "wasm32")))]
+    {
+        if should_reset {
+            settings.leaderboard_enabled = params.game.default_settings.leaderboard_enabled;
+            settings.leaderboard_server = params.game.default_settings.leaderboard_server.clone();
+        }
+
+        ui.add_space(bigger_font.size);
+
+        // Leaderboard opt-in. Scores are never submitted unless this is enabled.
+        ui.horizontal(|ui| {
+            ui.add_space(bigger_font.size * 2.0);
+            ui.themed_label(
+                bigger_font,
+                &format!("{}:", params.localization.get("leaderboard-opt-in")),
+            );
+
+            let toggle_label = if settings.leaderboard_enabled {
+                params.localization.get("enabled")
+            } else {
+                params.localization.get("disabled")
+            };
+            if BorderedButton::themed(&params.game.ui_theme.button_styles.normal, &toggle_label)
+                .show(ui)
+                .clicked()
+            {
+                settings.leaderboard_enabled = !settings.leaderboard_enabled;
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.add_space(bigger_font.size * 2.0);
+            ui.themed_label(
+                bigger_font,
+                &format!("{}:", params.localization.get("leaderboard-server")),
+            );
+
+            let text_box = ui.add_enabled(
+                settings.leaderboard_enabled,
+                egui::TextEdit::singleline(&mut settings.leaderboard_server)
+                    .font(normal_font.clone())
+                    .desired_width(ui.available_width() - bigger_font.size * 2.0),
+            );
+            params.adjacencies.text_boxes.insert(text_box.id);
+        });
+    }
 }
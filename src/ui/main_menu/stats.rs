@@ -0,0 +1,102 @@
+use crate::lifetime_stats::LifetimeStats;
+
+use super::*;
+
+#[derive(SystemParam)]
+pub struct StatsMenu<'w, 's> {
+    game: Res<'w, GameMeta>,
+    menu_page: ResMut<'w, MenuPage>,
+    localization: Res<'w, Localization>,
+    menu_input: Query<'w, 's, &'static mut ActionState<MenuAction>>,
+    storage: ResMut<'w, Storage>,
+}
+
+impl<'w, 's> WidgetSystem for StatsMenu<'w, 's> {
+    type Args = ();
+
+    fn system(
+        world: &mut World,
+        state: &mut SystemState<Self>,
+        ui: &mut egui::Ui,
+        _id: WidgetId,
+        _args: Self::Args,
+    ) {
+        let mut params: StatsMenu = state.get_mut(world);
+
+        let lifetime_stats: LifetimeStats = params
+            .storage
+            .get(LifetimeStats::STORAGE_KEY)
+            .unwrap_or_default();
+
+        let ui_theme = &params.game.ui_theme;
+        let heading_font = ui_theme
+            .font_styles
+            .heading
+            .colored(ui_theme.panel.font_color);
+        let bigger_font = ui_theme
+            .font_styles
+            .bigger
+            .colored(ui_theme.panel.font_color);
+        let normal_font = ui_theme
+            .font_styles
+            .normal
+            .colored(ui_theme.panel.font_color);
+
+        let available_size = ui.available_size();
+        let menu_width = params.game.main_menu.menu_width;
+        let x_margin = (available_size.x - menu_width) / 2.0;
+        let outer_margin = egui::style::Margin::symmetric(x_margin, bigger_font.size);
+
+        BorderedFrame::new(&ui_theme.panel.border)
+            .margin(outer_margin)
+            .padding(ui_theme.panel.padding.into())
+            .show(ui, |ui| {
+                ui.set_min_width(ui.available_width());
+
+                ui.vertical_centered(|ui| {
+                    ui.themed_label(&heading_font, &params.localization.get("stats"));
+                });
+                ui.add_space(normal_font.size);
+
+                if lifetime_stats.is_empty() {
+                    ui.themed_label(&normal_font, &params.localization.get("no-stats"));
+                } else {
+                    egui::Grid::new("stats-grid")
+                        .spacing(egui::vec2(20.0, 4.0))
+                        .show(ui, |ui| {
+                            ui.label("");
+                            for column in ["matches-played", "win-rate", "kills", "favorite-item"] {
+                                ui.themed_label(&normal_font, &params.localization.get(column));
+                            }
+                            ui.end_row();
+
+                            for (fighter, stats) in lifetime_stats.iter() {
+                                ui.themed_label(&bigger_font, fighter);
+                                ui.themed_label(&normal_font, &stats.matches_played.to_string());
+                                ui.themed_label(
+                                    &normal_font,
+                                    &format!("{:.0}%", stats.win_rate() * 100.0),
+                                );
+                                ui.themed_label(&normal_font, &stats.kills.to_string());
+                                ui.themed_label(&normal_font, stats.favorite_item().unwrap_or("-"));
+                                ui.end_row();
+                            }
+                        });
+                }
+
+                ui.add_space(normal_font.size);
+                let back_button = BorderedButton::themed(
+                    &ui_theme.button_styles.small,
+                    &params.localization.get("back"),
+                )
+                .show(ui)
+                .focus_by_default(ui);
+
+                if back_button.clicked()
+                    || params.menu_input.single().just_pressed(MenuAction::Back)
+                {
+                    *params.menu_page = MenuPage::Home;
+                }
+            });
+    }
+}
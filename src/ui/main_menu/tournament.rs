@@ -0,0 +1,316 @@
+use crate::tournament::{ActiveTournament, Tournament, TournamentParticipant, MAX_PARTICIPANTS};
+
+use super::*;
+
+/// The participants and map entered for the next tournament.
+#[derive(Resource, Default)]
+pub struct TournamentSetupState {
+    /// The name of each participant, and the index of their fighter in the [`CoreMeta::players`].
+    pub participants: Vec<(String, usize)>,
+    /// The index of the map in the [`CoreMeta::stable_maps`].
+    pub map: usize,
+}
+
+#[derive(SystemParam)]
+pub struct TournamentMenu<'w, 's> {
+    commands: Commands<'w, 's>,
+    game: Res<'w, GameMeta>,
+    core: Res<'w, CoreMetaArc>,
+    menu_page: ResMut<'w, MenuPage>,
+    localization: Res<'w, Localization>,
+    menu_input: Query<'w, 's, &'static mut ActionState<MenuAction>>,
+    map_assets: Res<'w, Assets<MapMeta>>,
+    player_assets: Res<'w, Assets<PlayerMeta>>,
+    session_manager: SessionManager<'w, 's>,
+    setup: ResMut<'w, TournamentSetupState>,
+    active_tournament: ResMut<'w, ActiveTournament>,
+    storage: ResMut<'w, Storage>,
+}
+
+impl<'w, 's> WidgetSystem for TournamentMenu<'w, 's> {
+    type Args = ();
+
+    fn system(
+        world: &mut World,
+        state: &mut SystemState<Self>,
+        ui: &mut egui::Ui,
+        _id: WidgetId,
+        _args: Self::Args,
+    ) {
+        let mut params: TournamentMenu = state.get_mut(world);
+
+        let ui_theme = &params.game.ui_theme;
+        let heading_font = ui_theme
+            .font_styles
+            .heading
+            .colored(ui_theme.panel.font_color);
+        let bigger_font = ui_theme
+            .font_styles
+            .bigger
+            .colored(ui_theme.panel.font_color);
+
+        let available_size = ui.available_size();
+        let menu_width = params.game.main_menu.menu_width;
+        let x_margin = (available_size.x - menu_width) / 2.0;
+        let outer_margin = egui::style::Margin::symmetric(x_margin, bigger_font.size);
+
+        BorderedFrame::new(&ui_theme.panel.border)
+            .margin(outer_margin)
+            .padding(ui_theme.panel.padding.into())
+            .show(ui, |ui| {
+                ui.set_min_width(ui.available_width());
+
+                ui.vertical_centered(|ui| {
+                    ui.themed_label(&heading_font, &params.localization.get("tournament"));
+                });
+                ui.add_space(bigger_font.size);
+
+                if params.active_tournament.0.is_some() {
+                    bracket_ui(ui, &mut params);
+                } else {
+                    setup_ui(ui, &mut params);
+                }
+            });
+    }
+}
+
+/// Show the bracket of the active tournament, with a button to play the next match.
+fn bracket_ui(ui: &mut egui::Ui, params: &mut TournamentMenu) {
+    let ui_theme = &params.game.ui_theme;
+    let bigger_font = ui_theme
+        .font_styles
+        .bigger
+        .colored(ui_theme.panel.font_color);
+    let normal_font = ui_theme
+        .font_styles
+        .normal
+        .colored(ui_theme.panel.font_color);
+    let winner_font = normal_font.colored(ui_theme.colors.positive);
+    let Some(tournament) = &mut params.active_tournament.0 else {
+        return;
+    };
+
+    // Show every round as a column, from the first round to the final
+    ui.columns(tournament.bracket.rounds.len(), |columns| {
+        for (column, matches) in columns.iter_mut().zip(&tournament.bracket.rounds) {
+            for bracket_match in matches {
+                let names = tournament.match_names(bracket_match);
+                for (participant, name) in bracket_match.participants.iter().zip(names) {
+                    let font = if participant.is_some() && bracket_match.winner == *participant {
+                        &winner_font
+                    } else {
+                        &normal_font
+                    };
+                    column.themed_label(font, name.unwrap_or("-"));
+                }
+                column.add_space(normal_font.size);
+            }
+        }
+    });
+    ui.add_space(normal_font.size);
+
+    let mut is_finished = false;
+    if let Some(champion) = tournament.bracket.champion() {
+        ui.themed_label(
+            &bigger_font,
+            &params.localization.get(&format!(
+                "tournament-champion?name={}",
+                tournament.participants[champion].name
+            )),
+        );
+        ui.add_space(normal_font.size);
+
+        let finish_button = BorderedButton::themed(
+            &ui_theme.button_styles.normal,
+            &params.localization.get("finish-tournament"),
+        )
+        .min_size(egui::vec2(ui.available_width(), 0.0))
+        .show(ui)
+        .focus_by_default(ui);
+        is_finished = finish_button.clicked();
+    } else if let Some((round, match_idx)) = tournament.bracket.next_match() {
+        let [Some(first), Some(second)] =
+            tournament.match_names(&tournament.bracket.rounds[round][match_idx])
+        else {
+            return;
+        };
+        ui.themed_label(
+            &bigger_font,
+            &params.localization.get(&format!(
+                "tournament-next-match?first={first}&second={second}"
+            )),
+        );
+        ui.add_space(normal_font.size);
+
+        let play_button = BorderedButton::themed(
+            &ui_theme.button_styles.normal,
+            &params.localization.get("play"),
+        )
+        .min_size(egui::vec2(ui.available_width(), 0.0))
+        .show(ui)
+        .focus_by_default(ui);
+
+        if play_button.clicked() {
+            if let Some(info) = tournament.start_next_match(&params.core.0) {
+                params.session_manager.start_local(info);
+                *params.menu_page = MenuPage::Home;
+                params
+                    .commands
+                    .insert_resource(NextState(Some(EngineState::InGame)));
+                params
+                    .commands
+                    .insert_resource(NextState(Some(InGameState::Playing)));
+            }
+        }
+    }
+
+    ui.add_space(normal_font.size);
+    ui.horizontal(|ui| {
+        let back_button = BorderedButton::themed(
+            &ui_theme.button_styles.small,
+            &params.localization.get("back"),
+        )
+        .show(ui);
+
+        if back_button.clicked() || params.menu_input.single().just_pressed(MenuAction::Back) {
+            *params.menu_page = MenuPage::Home;
+        }
+
+        if BorderedButton::themed(
+            &ui_theme.button_styles.small,
+            &params.localization.get("quit-tournament"),
+        )
+        .show(ui)
+        .clicked()
+        {
+            is_finished = true;
+        }
+    });
+
+    if is_finished {
+        params.active_tournament.0 = None;
+        params.active_tournament.save(&mut params.storage);
+    }
+}
+
+/// Show the participants and the map of the next tournament, with a button to start it.
+fn setup_ui(ui: &mut egui::Ui, params: &mut TournamentMenu) {
+    let ui_theme = &params.game.ui_theme;
+    let normal_font = ui_theme
+        .font_styles
+        .normal
+        .colored(ui_theme.panel.font_color);
+    let small_button_style = &ui_theme.button_styles.small;
+    let core = &params.core.0;
+    let setup = &mut *params.setup;
+
+    let player_name = |player: usize| {
+        params
+            .player_assets
+            .get(&core.players[player].get_bevy_handle())
+            .map(|x| x.name.clone())
+            .unwrap_or_default()
+    };
+
+    // Enter the participants
+    let mut removed = None;
+    for (i, (name, player)) in setup.participants.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(name).desired_width(ui.available_width() / 2.0));
+
+            if BorderedButton::themed(
+                small_button_style,
+                &format!("<  {}  >", player_name(*player)),
+            )
+            .show(ui)
+            .clicked()
+            {
+                *player = (*player + 1) % core.players.len();
+            }
+
+            if BorderedButton::themed(
+                small_button_style,
+                &params.localization.get("remove-participant"),
+            )
+            .show(ui)
+            .clicked()
+            {
+                removed = Some(i);
+            }
+        });
+    }
+    if let Some(i) = removed {
+        setup.participants.remove(i);
+    }
+
+    if setup.participants.len() < MAX_PARTICIPANTS
+        && !core.players.is_empty()
+        && BorderedButton::themed(
+            small_button_style,
+            &params.localization.get("add-participant"),
+        )
+        .show(ui)
+        .clicked()
+    {
+        let number = setup.participants.len() + 1;
+        setup.participants.push((
+            params
+                .localization
+                .get(&format!("player-name?player={number}")),
+            setup.participants.len() % core.players.len(),
+        ));
+    }
+    ui.add_space(normal_font.size);
+
+    // Pick the map
+    let map_meta = core
+        .stable_maps
+        .get(setup.map)
+        .and_then(|x| params.map_assets.get(&x.get_bevy_handle()));
+    ui.horizontal(|ui| {
+        ui.themed_label(&normal_font, &params.localization.get("tournament-map"));
+        let map_name = map_meta.map(|x| x.name.as_str()).unwrap_or("-");
+        if BorderedButton::themed(small_button_style, &format!("<  {map_name}  >"))
+            .show(ui)
+            .clicked()
+            && !core.stable_maps.is_empty()
+        {
+            setup.map = (setup.map + 1) % core.stable_maps.len();
+        }
+    });
+    ui.add_space(normal_font.size);
+
+    ui.horizontal(|ui| {
+        let back_button =
+            BorderedButton::themed(small_button_style, &params.localization.get("back")).show(ui);
+
+        if back_button.clicked() || params.menu_input.single().just_pressed(MenuAction::Back) {
+            *params.menu_page = MenuPage::Home;
+        }
+
+        ui.scope(|ui| {
+            let can_start = setup.participants.len() >= 2
+                && setup.participants.iter().all(|(name, _)| !name.is_empty());
+            ui.set_enabled(can_start && map_meta.is_some());
+
+            if BorderedButton::themed(small_button_style, &params.localization.get("start"))
+                .show(ui)
+                .clicked()
+            {
+                if let Some(map_meta) = map_meta {
+                    let participants = setup
+                        .participants
+                        .iter()
+                        .map(|(name, player)| TournamentParticipant {
+                            name: name.clone(),
+                            player: core.players[*player].clone(),
+                        })
+                        .collect();
+                    params.active_tournament.0 =
+                        Some(Tournament::new(participants, map_meta.clone()));
+                    params.active_tournament.save(&mut params.storage);
+                }
+            }
+        });
+    });
+}
@@ -0,0 +1,58 @@
+//! Countdown to sudden death, shown during rounds with a time limit.
+
+use bevy_egui::EguiContexts;
+use bevy_fluent::Localization;
+use jumpy_core::sudden_death::SuddenDeath;
+
+use crate::prelude::*;
+
+use super::widgets::{bordered_frame::BorderedFrame, EguiUiExt};
+
+pub struct MatchTimerPlugin;
+
+impl Plugin for MatchTimerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            match_timer
+                .run_if(in_state(EngineState::InGame))
+                .run_if(resource_exists::<Session>()),
+        );
+    }
+}
+
+/// Show the time left until sudden death at the bottom of the screen, and a warning once it has
+/// started.
+fn match_timer(
+    mut contexts: EguiContexts,
+    mut session: ResMut<Session>,
+    game: Res<GameMeta>,
+    localization: Res<Localization>,
+) {
+    let remaining = session.world().resource::<SuddenDeath>().borrow().remaining;
+    let Some(remaining) = remaining else {
+        return;
+    };
+
+    let ui_theme = &game.ui_theme;
+    let font = ui_theme
+        .font_styles
+        .bigger
+        .colored(ui_theme.panel.font_color);
+    let label = if remaining.is_zero() {
+        localization.get("sudden-death")
+    } else {
+        // Round up, so that sudden death starts as the countdown reaches zero
+        let secs = remaining.as_secs_f32().ceil() as u32;
+        format!("{}:{:02}", secs / 60, secs % 60)
+    };
+
+    egui::Area::new("match-timer")
+        .anchor(egui::Align2::CENTER_BOTTOM, egui::vec2(0.0, -10.0))
+        .show(contexts.ctx_mut(), |ui| {
+            BorderedFrame::new(&ui_theme.panel.border)
+                .padding(ui_theme.panel.padding.into())
+                .show(ui, |ui| {
+                    ui.themed_label(&font, &label);
+                });
+        });
+}
@@ -1,11 +1,16 @@
 use bevy::window::PrimaryWindow;
 use bevy_egui::*;
 use bevy_fluent::Localization;
+use jumpy_core::rounds::{MatchScore, RoundState};
 
-use crate::{prelude::*, widgets::EguiResponseExt};
+use crate::{metadata::Settings, platform::Storage, prelude::*, widgets::EguiResponseExt};
 
 use super::{
-    main_menu::map_select::MapSelectMenu,
+    main_menu::{
+        map_select::MapSelectMenu,
+        settings::{ModifiedSettings, SettingsMenu},
+        MenuPage,
+    },
     widget,
     widgets::{
         bordered_button::BorderedButton, bordered_frame::BorderedFrame, EguiContextExt, EguiUiExt,
@@ -40,6 +45,10 @@ impl Plugin for PausePlugin {
                         .run_if(in_state(EngineState::InGame))
                         .run_if(in_state(InGameState::Paused))
                         .run_if(resource_equals(PauseMenuPage::MapSelect)),
+                    pause_menu_settings
+                        .run_if(in_state(EngineState::InGame))
+                        .run_if(in_state(InGameState::Paused))
+                        .run_if(resource_equals(PauseMenuPage::Settings)),
                 )
                     .in_base_set(CoreSet::Update),
             );
@@ -72,6 +81,9 @@ pub enum PauseMenuPage {
     #[default]
     Default,
     MapSelect,
+    Settings,
+    #[cfg(not(target_arch = "wasm32"))]
+    BugReport,
 }
 
 pub fn pause_menu_default(
@@ -81,10 +93,35 @@ pub fn pause_menu_default(
     map_handle: Query<&AssetHandle<MapMeta>>,
     map_assets: Res<Assets<MapMeta>>,
     mut pause_page: ResMut<PauseMenuPage>,
+    mut menu_page: ResMut<MenuPage>,
+    mut modified_settings: ResMut<ModifiedSettings>,
+    mut storage: ResMut<Storage>,
     mut session_manager: SessionManager,
     mut contexts: EguiContexts,
+    #[cfg(all(feature = "voice-chat", not(target_arch = "wasm32")))] mut voice_chat: Option<
+        ResMut<crate::networking::voice::VoiceChat>,
+    >,
+    #[cfg(all(feature = "voice-chat", not(target_arch = "wasm32")))] socket: Option<
+        Res<crate::networking::NetworkMatchSocket>,
+    >,
+    #[cfg(not(target_arch = "wasm32"))] mut match_upgrade: ResMut<
+        crate::networking::upgrade::MatchUpgrade,
+    >,
 ) {
-    let is_online = false;
+    let Some(session) = session_manager.session.as_mut() else {
+        return;
+    };
+    let is_online = session.network_player_idx().is_some();
+    #[cfg(not(target_arch = "wasm32"))]
+    let can_upgrade = crate::networking::upgrade::can_upgrade(session);
+    let has_rounds = session
+        .world()
+        .resource::<MatchSettings>()
+        .borrow()
+        .rounds_to_win
+        .is_some();
+    let is_round_playing =
+        session.world().resource::<MatchScore>().borrow().state == RoundState::Playing;
     let ui_theme = &game.ui_theme;
 
     egui::CentralPanel::default()
@@ -120,6 +157,9 @@ pub fn pause_menu_default(
                             ui.themed_label(&bigger_font, &map_meta.name);
                         }
                         ui.themed_label(&heading_font, &localization.get("paused"));
+                        if is_online {
+                            ui.themed_label(&bigger_font, &localization.get("network-not-paused"));
+                        }
 
                         ui.add_space(10.0);
 
@@ -163,8 +203,88 @@ pub fn pause_menu_default(
                                 session_manager.restart();
                                 commands.insert_resource(NextState(Some(InGameState::Playing)));
                             }
+
+                            if has_rounds {
+                                ui.scope(|ui| {
+                                    ui.set_enabled(is_round_playing);
+
+                                    if BorderedButton::themed(
+                                        &ui_theme.button_styles.normal,
+                                        &localization.get("restart-round"),
+                                    )
+                                    .min_size(egui::vec2(width, 0.0))
+                                    .show(ui)
+                                    .clicked()
+                                    {
+                                        session_manager.restart_round();
+                                        commands
+                                            .insert_resource(NextState(Some(InGameState::Playing)));
+                                    }
+                                });
+                            }
                         });
 
+                        #[cfg(not(target_arch = "wasm32"))]
+                        if match_upgrade.is_inviting() {
+                            if BorderedButton::themed(
+                                &ui_theme.button_styles.normal,
+                                &localization.get("cancel-invite"),
+                            )
+                            .min_size(egui::vec2(width, 0.0))
+                            .show(ui)
+                            .clicked()
+                            {
+                                match_upgrade.cancel();
+                            }
+                        } else {
+                            ui.scope(|ui| {
+                                ui.set_enabled(can_upgrade);
+
+                                if BorderedButton::themed(
+                                    &ui_theme.button_styles.normal,
+                                    &localization.get("invite-online-player"),
+                                )
+                                .min_size(egui::vec2(width, 0.0))
+                                .show(ui)
+                                .clicked()
+                                {
+                                    let settings =
+                                        Settings::get_stored_or_default(&game, &mut storage);
+                                    let bandwidth_cap = settings.relay_bandwidth_cap;
+                                    match_upgrade.invite(
+                                        settings.matchmaking_server.clone(),
+                                        (bandwidth_cap > 0).then_some(bandwidth_cap * 1024),
+                                    );
+                                    commands.insert_resource(NextState(Some(InGameState::Playing)));
+                                }
+                            });
+                            if match_upgrade.status
+                                == crate::networking::upgrade::MatchUpgradeStatus::Failed
+                            {
+                                ui.themed_label(
+                                    &bigger_font,
+                                    &localization.get("match-upgrade-failed"),
+                                );
+                            }
+                        }
+
+                        if BorderedButton::themed(
+                            &ui_theme.button_styles.normal,
+                            &localization.get("settings"),
+                        )
+                        .min_size(egui::vec2(width, 0.0))
+                        .show(ui)
+                        .clicked()
+                        {
+                            *pause_page = PauseMenuPage::Settings;
+                            *menu_page = MenuPage::Settings;
+                            **modified_settings = Some(
+                                storage
+                                    .get(Settings::STORAGE_KEY)
+                                    .unwrap_or_else(|| game.default_settings.clone()),
+                            );
+                        }
+
                         ui.scope(|ui| {
                             if BorderedButton::themed(
                                 &ui_theme.button_styles.normal,
@@ -188,9 +308,23 @@ pub fn pause_menu_default(
                         .clicked()
                         {
                             // Show the main menu
+                            session_manager.stop();
+                            *pause_page = default();
                             commands.insert_resource(NextState(Some(EngineState::MainMenu)));
+                            commands.insert_resource(NextState(Some(InGameState::Playing)));
                             ui.ctx().clear_focus();
                         }
+
+                        #[cfg(all(feature = "voice-chat", not(target_arch = "wasm32")))]
+                        if let (Some(voice_chat), Some(socket)) = (&mut voice_chat, &socket) {
+                            ui.add_space(10.0);
+                            crate::networking::voice::voice_chat_settings_ui(
+                                ui,
+                                &localization,
+                                socket,
+                                voice_chat,
+                            );
+                        }
                     });
                 });
         });
@@ -208,3 +342,22 @@ fn pause_menu_map_select(world: &mut World) {
             widget::<MapSelectMenu>(world, ui, WidgetId::new("map-select"), false);
         });
 }
+
+/// Show the settings menu, until its changes are saved or cancelled.
+fn pause_menu_settings(world: &mut World) {
+    let mut egui_context = world
+        .query_filtered::<&mut EguiContext, With<PrimaryWindow>>()
+        .single(world)
+        .clone();
+
+    egui::CentralPanel::default()
+        .frame(egui::Frame::none())
+        .show(egui_context.get_mut(), |ui| {
+            widget::<SettingsMenu>(world, ui, WidgetId::new("settings"), ());
+        });
+
+    // The settings menu goes back to the main menu home page when it is closed
+    if !matches!(*world.resource::<MenuPage>(), MenuPage::Settings) {
+        *world.resource_mut::<PauseMenuPage>() = PauseMenuPage::Default;
+    }
+}
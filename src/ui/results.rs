@@ -0,0 +1,192 @@
+//! Results screen shown once a player has won the match.
+
+use bevy_egui::EguiContexts;
+use bevy_fluent::Localization;
+use jumpy_core::{
+    match_stats::{Award, MatchStats, PlayerStats},
+    rounds::{MatchScore, RoundState},
+};
+
+use crate::{prelude::*, widgets::EguiResponseExt};
+
+use super::widgets::{
+    bordered_button::BorderedButton, bordered_frame::BorderedFrame, EguiContextExt, EguiUiExt,
+};
+
+pub struct ResultsPlugin;
+
+impl Plugin for ResultsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems((
+            show_results
+                .run_if(in_state(EngineState::InGame))
+                .run_if(in_state(InGameState::Playing))
+                .run_if(resource_exists::<Session>()),
+            results_screen
+                .run_if(in_state(EngineState::InGame))
+                .run_if(in_state(InGameState::Results))
+                .run_if(resource_exists::<Session>()),
+        ));
+    }
+}
+
+/// Switch to the results screen once the match is over.
+fn show_results(mut commands: Commands, mut session: ResMut<Session>) {
+    let match_over =
+        session.world().resource::<MatchScore>().borrow().state == RoundState::MatchOver;
+    if match_over {
+        commands.insert_resource(NextState(Some(InGameState::Results)));
+    }
+}
+
+/// Show the statistics of every player over the match, with buttons to play it again or go back to
+/// the main menu.
+fn results_screen(
+    mut commands: Commands,
+    game: Res<GameMeta>,
+    localization: Res<Localization>,
+    mut session_manager: SessionManager,
+    mut contexts: EguiContexts,
+) {
+    let Some(session) = session_manager.session.as_mut() else {
+        return;
+    };
+    let is_online = session.network_player_idx().is_some();
+    let (stats, awards) = session
+        .world()
+        .run_initialized_system(
+            |match_stats: bones::Res<MatchStats>,
+             player_inputs: bones::Res<jumpy_core::input::PlayerInputs>| {
+                let players = (0..MAX_PLAYERS)
+                    .filter(|&i| player_inputs.players[i].active)
+                    .collect::<Vec<_>>();
+                let stats = players
+                    .iter()
+                    .map(|&i| (i, match_stats.players[i]))
+                    .collect::<Vec<_>>();
+                Ok((stats, match_stats.awards(&players)))
+            },
+        )
+        .unwrap();
+
+    let ui_theme = &game.ui_theme;
+    let heading_font = ui_theme
+        .font_styles
+        .heading
+        .colored(ui_theme.panel.font_color);
+    let bigger_font = ui_theme
+        .font_styles
+        .bigger
+        .colored(ui_theme.panel.font_color);
+    let font = ui_theme
+        .font_styles
+        .normal
+        .colored(ui_theme.panel.font_color);
+
+    let columns: [(&str, fn(&PlayerStats) -> u32); 5] = [
+        ("round-wins", |x| x.round_wins),
+        ("kills", |x| x.kills),
+        ("deaths", |x| x.deaths),
+        ("damage-dealt", |x| x.damage_dealt),
+        ("items-used", |x| x.items_used),
+    ];
+
+    egui::Area::new("results")
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(contexts.ctx_mut(), |ui| {
+            BorderedFrame::new(&ui_theme.panel.border)
+                .padding(ui_theme.panel.padding.into())
+                .show(ui, |ui| {
+                    ui.vertical_centered(|ui| {
+                        ui.themed_label(&heading_font, &localization.get("match-results"));
+                        ui.add_space(10.0);
+
+                        egui::Grid::new("results-grid")
+                            .spacing(egui::vec2(20.0, 4.0))
+                            .show(ui, |ui| {
+                                ui.label("");
+                                for (column, _) in &columns {
+                                    ui.themed_label(&font, &localization.get(column));
+                                }
+                                ui.end_row();
+
+                                for (player, stats) in &stats {
+                                    ui.themed_label(
+                                        &bigger_font,
+                                        &localization
+                                            .get(&format!("player-name?player={}", player + 1)),
+                                    );
+                                    for (_, value) in &columns {
+                                        ui.themed_label(&bigger_font, &value(stats).to_string());
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+
+                        if !awards.is_empty() {
+                            ui.add_space(10.0);
+                            ui.themed_label(&bigger_font, &localization.get("awards"));
+                            egui::Grid::new("awards-grid")
+                                .spacing(egui::vec2(10.0, 4.0))
+                                .show(ui, |ui| {
+                                    for (award, player) in &awards {
+                                        let icon = ui_theme.award_icons.get(*award);
+                                        ui.image(icon.egui_texture_id, icon.image_size.to_array());
+                                        ui.themed_label(
+                                            &font,
+                                            &localization.get(award_name(*award)),
+                                        );
+                                        ui.themed_label(
+                                            &font,
+                                            &localization
+                                                .get(&format!("player-name?player={}", player + 1)),
+                                        );
+                                        ui.end_row();
+                                    }
+                                });
+                        }
+
+                        ui.add_space(10.0);
+
+                        ui.scope(|ui| {
+                            ui.set_enabled(!is_online);
+
+                            let rematch_button = BorderedButton::themed(
+                                &ui_theme.button_styles.normal,
+                                &localization.get("rematch"),
+                            )
+                            .show(ui)
+                            .focus_by_default(ui);
+                            if rematch_button.clicked() {
+                                session_manager.restart();
+                                commands.insert_resource(NextState(Some(InGameState::Playing)));
+                            }
+                        });
+
+                        if BorderedButton::themed(
+                            &ui_theme.button_styles.normal,
+                            &localization.get("main-menu"),
+                        )
+                        .show(ui)
+                        .clicked()
+                        {
+                            session_manager.stop();
+                            commands.insert_resource(NextState(Some(EngineState::MainMenu)));
+                            commands.insert_resource(NextState(Some(InGameState::Playing)));
+                            ui.ctx().clear_focus();
+                        }
+                    });
+                });
+        });
+}
+
+/// Get the localization key of the name of an award.
+fn award_name(award: Award) -> &'static str {
+    match award {
+        Award::Mvp => "award-mvp",
+        Award::MostKills => "award-most-kills",
+        Award::MostDeaths => "award-most-deaths",
+        Award::Pacifist => "award-pacifist",
+        Award::GrenadeManiac => "award-grenade-maniac",
+    }
+}
@@ -0,0 +1,124 @@
+//! Scoreboard shown between the rounds of a match.
+
+use bevy_egui::EguiContexts;
+use bevy_fluent::Localization;
+use jumpy_core::{
+    match_stats::MatchStats,
+    rounds::{MatchScore, RoundState},
+};
+
+use crate::{announcer::Announcer, prelude::*};
+
+use super::widgets::{bordered_frame::BorderedFrame, EguiUiExt};
+
+pub struct ScoreboardPlugin;
+
+impl Plugin for ScoreboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            scoreboard
+                .run_if(in_state(EngineState::InGame))
+                .run_if(resource_exists::<Session>()),
+        );
+    }
+}
+
+/// Show the score of the match while the next round is waiting to start.
+fn scoreboard(
+    mut contexts: EguiContexts,
+    mut session: ResMut<Session>,
+    game: Res<GameMeta>,
+    localization: Res<Localization>,
+    mut announcer: ResMut<Announcer>,
+    mut announced_round: Local<Option<u32>>,
+) {
+    let (match_score, match_settings, match_stats, active_players) = session
+        .world()
+        .run_initialized_system(
+            |match_score: bones::Res<MatchScore>,
+             match_settings: bones::Res<MatchSettings>,
+             match_stats: bones::Res<MatchStats>,
+             player_inputs: bones::Res<jumpy_core::input::PlayerInputs>| {
+                let active_players = (0..MAX_PLAYERS)
+                    .filter(|&i| player_inputs.players[i].active)
+                    .collect::<Vec<_>>();
+                Ok((
+                    match_score.clone(),
+                    match_settings.clone(),
+                    match_stats.clone(),
+                    active_players,
+                ))
+            },
+        )
+        .unwrap();
+    if match_score.state == RoundState::Playing {
+        *announced_round = None;
+        return;
+    }
+
+    let winner = match_score.round_winners.iter().position(|&won| won);
+    let result = match (winner, match_settings.team_of(winner.unwrap_or_default())) {
+        (None, _) => localization.get("round-draw"),
+        (Some(_), Some(team)) => localization.get(&format!("team-wins?team={}", team + 1)),
+        (Some(player), None) => localization.get(&format!("player-wins?player={}", player + 1)),
+    };
+    let heading = match match_score.state {
+        RoundState::MatchOver => localization.get("match-over"),
+        _ => localization.get(&format!("round-over?round={}", match_score.round + 1)),
+    };
+
+    if *announced_round != Some(match_score.round) {
+        announcer.announce(format!("{heading} {result}"));
+        *announced_round = Some(match_score.round);
+    }
+    // The results screen is shown once the match is over
+    if match_score.state == RoundState::MatchOver {
+        return;
+    }
+
+    let ui_theme = &game.ui_theme;
+    let heading_font = ui_theme
+        .font_styles
+        .heading
+        .colored(ui_theme.panel.font_color);
+    let bigger_font = ui_theme
+        .font_styles
+        .bigger
+        .colored(ui_theme.panel.font_color);
+    let font = ui_theme
+        .font_styles
+        .normal
+        .colored(ui_theme.panel.font_color);
+
+    egui::Area::new("scoreboard")
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(contexts.ctx_mut(), |ui| {
+            BorderedFrame::new(&ui_theme.panel.border)
+                .padding(ui_theme.panel.padding.into())
+                .show(ui, |ui| {
+                    ui.vertical_centered(|ui| {
+                        ui.themed_label(&heading_font, &heading);
+                        ui.themed_label(&bigger_font, &result);
+                        ui.add_space(10.0);
+                        for player in active_players {
+                            ui.themed_label(
+                                &bigger_font,
+                                &localization.get(&format!(
+                                    "player-round-wins?player={}&wins={}",
+                                    player + 1,
+                                    match_score.wins[player]
+                                )),
+                            );
+                            ui.themed_label(
+                                &font,
+                                &localization.get(&format!(
+                                    "player-kills-deaths?kills={}&deaths={}",
+                                    match_stats.players[player].kills,
+                                    match_stats.players[player].deaths
+                                )),
+                            );
+                        }
+                    });
+                });
+        });
+}